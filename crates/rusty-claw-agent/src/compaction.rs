@@ -13,7 +13,7 @@ use rusty_claw_core::types::ContentBlock;
 use rusty_claw_plugins::{HookContext, HookEvent, HookRegistry};
 use rusty_claw_providers::{CompletionRequest, Credentials, LlmProvider};
 
-use crate::transcript::estimate_transcript_tokens;
+use crate::transcript::{estimate_transcript_tokens, orphaned_tool_ids, tool_result_ids, tool_use_ids};
 
 /// Compact the transcript if it exceeds the configured token limit.
 ///
@@ -28,7 +28,7 @@ pub async fn compact_transcript(
     let max_tokens = config.max_context_tokens();
     let keep_recent = config.compact_keep_recent();
 
-    let current_tokens = estimate_transcript_tokens(&session.transcript);
+    let current_tokens = count_transcript_tokens(session, config, provider, credentials).await;
     debug!(current_tokens, max_tokens, "Checking if compaction needed");
 
     if current_tokens <= max_tokens {
@@ -59,7 +59,7 @@ pub async fn compact_transcript(
 
     // Split: old entries to summarize vs recent entries to keep
     let total = session.transcript.len();
-    let split_at = total.saturating_sub(keep_recent);
+    let split_at = pair_safe_split(&session.transcript, total.saturating_sub(keep_recent));
 
     if split_at == 0 {
         debug!("Not enough entries to compact, keeping all");
@@ -93,6 +93,15 @@ pub async fn compact_transcript(
         tools: None,
         system: Some("You are a transcript summarizer. Produce a concise summary.".into()),
         thinking_budget_tokens: None,
+        enable_prompt_cache: false,
+        stop_sequences: None,
+        tool_choice: None,
+        response_format: None,
+        top_p: None,
+        top_k: None,
+        frequency_penalty: None,
+        presence_penalty: None,
+        seed: None,
     };
 
     let stream = provider.stream(&request, credentials).await?;
@@ -129,11 +138,23 @@ pub async fn compact_transcript(
         timestamp: Utc::now(),
     };
 
-    session.transcript = Vec::with_capacity(1 + recent_entries.len());
-    session.transcript.push(compaction_entry);
-    session.transcript.extend(recent_entries);
+    let mut compacted = Vec::with_capacity(1 + recent_entries.len());
+    compacted.push(compaction_entry);
+    compacted.extend(recent_entries);
+
+    // Never introduce orphaned tool ids; the provider would reject the next turn
+    let before = orphaned_tool_ids(&session.transcript);
+    let introduced: Vec<String> = orphaned_tool_ids(&compacted)
+        .into_iter()
+        .filter(|id| !before.contains(id))
+        .collect();
+    if !introduced.is_empty() {
+        warn!(?introduced, "Compaction would orphan tool calls, keeping transcript as-is");
+        return Ok(false);
+    }
+    session.transcript = compacted;
 
-    let new_tokens = estimate_transcript_tokens(&session.transcript);
+    let new_tokens = count_transcript_tokens(session, config, provider, credentials).await;
     info!(
         old_tokens = current_tokens,
         new_tokens, "Compaction complete"
@@ -155,6 +176,54 @@ pub async fn compact_transcript(
     Ok(true)
 }
 
+/// Tokens in the session's transcript as the provider counts them, falling
+/// back to the character heuristic if counting fails.
+pub async fn count_transcript_tokens(
+    session: &Session,
+    config: &Config,
+    provider: &dyn LlmProvider,
+    credentials: &Credentials,
+) -> usize {
+    let request = CompletionRequest {
+        model: session
+            .meta
+            .model
+            .clone()
+            .unwrap_or_else(|| config.default_model()),
+        messages: provider.format_messages(&session.transcript),
+        ..Default::default()
+    };
+    match provider.count_tokens(&request, credentials).await {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            debug!(%e, "Token counting failed, using estimate");
+            estimate_transcript_tokens(&session.transcript)
+        }
+    }
+}
+
+/// Move the compaction boundary back so that no tool result kept in the
+/// recent part answers a tool use that would be summarized away. A tool use
+/// and its result are always kept or summarized together.
+pub fn pair_safe_split(entries: &[TranscriptEntry], split_at: usize) -> usize {
+    let mut split = split_at.min(entries.len());
+    loop {
+        let earliest_use = entries[split..]
+            .iter()
+            .flat_map(tool_result_ids)
+            .filter_map(|id| {
+                entries[..split]
+                    .iter()
+                    .rposition(|e| tool_use_ids(e).contains(&id))
+            })
+            .min();
+        match earliest_use {
+            Some(i) => split = i,
+            None => return split,
+        }
+    }
+}
+
 /// Format transcript entries into readable text for the summarizer.
 pub fn format_entries_for_summary(entries: &[TranscriptEntry]) -> String {
     let mut parts = Vec::new();
@@ -230,6 +299,9 @@ mod tests {
                     text: "Rust is a systems programming language.".into(),
                 }],
                 usage: None,
+                thinking: None,
+                thinking_signature: None,
+                thinking_blocks: Vec::new(),
                 timestamp: Utc::now(),
             },
         ];
@@ -280,4 +352,102 @@ mod tests {
         let tokens = estimate_transcript_tokens(&entries);
         assert!(tokens < 100_000);
     }
+
+    fn assistant_tools(ids: &[&str]) -> TranscriptEntry {
+        TranscriptEntry::Assistant {
+            content: ids
+                .iter()
+                .map(|id| ContentBlock::ToolUse {
+                    id: (*id).into(),
+                    name: "read_file".into(),
+                    input: json!({}),
+                })
+                .collect(),
+            usage: None,
+            thinking: None,
+            thinking_signature: None,
+            thinking_blocks: Vec::new(),
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn tool_call() -> TranscriptEntry {
+        TranscriptEntry::ToolCall {
+            tool: "read_file".into(),
+            params: json!({}),
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn tool_result(id: &str) -> TranscriptEntry {
+        TranscriptEntry::ToolResult {
+            tool_use_id: id.into(),
+            tool: "read_file".into(),
+            content: "ok".into(),
+            is_error: false,
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn text(user: bool, text: &str) -> TranscriptEntry {
+        let content = vec![ContentBlock::Text { text: text.into() }];
+        if user {
+            TranscriptEntry::User {
+                content,
+                timestamp: Utc::now(),
+            }
+        } else {
+            TranscriptEntry::Assistant {
+                content,
+                usage: None,
+                thinking: None,
+                thinking_signature: None,
+                thinking_blocks: Vec::new(),
+                timestamp: Utc::now(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_split_keeps_tool_pairs_together() {
+        let entries = vec![
+            text(true, "inspect both files"), // 0
+            assistant_tools(&["a", "b"]),     // 1
+            tool_call(),                      // 2
+            tool_result("a"),                 // 3
+            tool_call(),                      // 4
+            tool_result("b"),                 // 5
+            text(false, "both read"),         // 6
+            text(true, "and the third"),      // 7
+            assistant_tools(&["c"]),          // 8
+            tool_call(),                      // 9
+            tool_result("c"),                 // 10
+            text(false, "done"),              // 11
+        ];
+
+        // Every boundary inside a tool sequence moves back to the assistant
+        // turn that issued the calls
+        for naive in 2..=5 {
+            assert_eq!(pair_safe_split(&entries, naive), 1, "split at {naive}");
+        }
+        for naive in 9..=10 {
+            assert_eq!(pair_safe_split(&entries, naive), 8, "split at {naive}");
+        }
+        // Boundaries between complete exchanges are left alone
+        assert_eq!(pair_safe_split(&entries, 6), 6);
+        assert_eq!(pair_safe_split(&entries, 8), 8);
+
+        for naive in 0..entries.len() {
+            let split = pair_safe_split(&entries, naive);
+            assert!(orphaned_tool_ids(&entries[split..]).is_empty(), "split at {naive}");
+        }
+    }
+
+    #[test]
+    fn test_orphaned_tool_ids() {
+        let entries = vec![assistant_tools(&["a", "b"]), tool_result("a"), tool_result("z")];
+        let mut orphans = orphaned_tool_ids(&entries);
+        orphans.sort();
+        assert_eq!(orphans, ["b", "z"]);
+    }
 }
@@ -9,9 +9,52 @@ use serde::{Deserialize, Serialize};
 pub mod compaction;
 pub mod prompt;
 pub mod runtime;
+pub mod structured;
 pub mod transcript;
 
-pub use runtime::run_agent;
+pub use runtime::{run_agent, run_agent_with_options};
+pub use structured::{StructuredOutput, run_agent_structured};
+
+use std::sync::Arc;
+
+use rusty_claw_core::session::SessionStore;
+use rusty_claw_core::skills::SkillDefinition;
+use rusty_claw_providers::{ResponseFormat, ToolChoice};
+use tokio_util::sync::CancellationToken;
+
+/// Per-run overrides for [`run_agent_with_options`].
+#[derive(Clone, Default)]
+pub struct AgentRunOptions {
+    /// Tool choice for the first LLM call of the run. Later iterations use
+    /// the provider default so the model can answer after the forced call.
+    pub tool_choice: Option<ToolChoice>,
+    /// Return the run's reasoning text in [`AgentPayload::thinking`].
+    pub include_thinking: bool,
+    /// Structured output constraint applied to every LLM call of the run.
+    pub response_format: Option<ResponseFormat>,
+    /// Cancels the run. Checked between iterations and while streaming, so
+    /// an abort drops the in-flight provider response immediately.
+    pub cancel: Option<CancellationToken>,
+    /// Store the session is saved to after each transcript append, so a
+    /// crash mid-run loses at most the tool call in flight.
+    pub checkpoint: Option<Arc<dyn SessionStore>>,
+    /// Skills active for this run; their prompts are added to the system
+    /// prompt.
+    pub active_skills: Vec<SkillDefinition>,
+}
+
+impl std::fmt::Debug for AgentRunOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgentRunOptions")
+            .field("tool_choice", &self.tool_choice)
+            .field("include_thinking", &self.include_thinking)
+            .field("response_format", &self.response_format)
+            .field("cancel", &self.cancel)
+            .field("checkpoint", &self.checkpoint.is_some())
+            .field("active_skills", &self.active_skills)
+            .finish()
+    }
+}
 
 /// Events emitted by the agent runtime during a run.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +68,10 @@ pub enum AgentEvent {
     #[serde(rename = "reasoning")]
     ReasoningStream { text: String },
 
+    /// Skills active for this run, sent once at its start.
+    #[serde(rename = "skills_activated")]
+    SkillsActivated { skills: Vec<String> },
+
     /// A tool call is being made.
     #[serde(rename = "tool_call")]
     ToolCall {
@@ -32,12 +79,15 @@ pub enum AgentEvent {
         params: serde_json::Value,
     },
 
-    /// A tool call has completed.
+    /// A tool call has completed. With `partial` set, `content` is an
+    /// incremental chunk of output from a tool that is still running.
     #[serde(rename = "tool_result")]
     ToolResult {
         tool: String,
         content: String,
         is_error: bool,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        partial: bool,
     },
 
     /// Streaming text delta for live typing indicators.
@@ -51,6 +101,15 @@ pub enum AgentEvent {
         output_tokens: u64,
     },
 
+    /// Latency of an LLM call, sent when its first chunk with content arrives.
+    #[serde(rename = "timing")]
+    Timing {
+        /// Zero-based tool-loop iteration the call belongs to.
+        iteration: u32,
+        /// Time from sending the request to the first content chunk.
+        time_to_first_token_ms: u64,
+    },
+
     /// Audio data for voice pipeline (base64-encoded).
     #[serde(rename = "audio_delta")]
     AudioDelta {
@@ -76,6 +135,10 @@ pub struct AgentPayload {
     pub text: Option<String>,
     pub media_urls: Vec<String>,
     pub is_error: bool,
+    /// Reasoning accumulated over the run, when requested via
+    /// [`AgentRunOptions::include_thinking`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -84,11 +147,50 @@ pub struct AgentRunMeta {
     pub input_tokens: u64,
     pub output_tokens: u64,
     pub tool_calls: u32,
+    /// Prompt-cache tokens read across all LLM calls in the run.
+    #[serde(default)]
+    pub cache_read_tokens: u64,
+    /// Prompt-cache tokens written across all LLM calls in the run.
+    #[serde(default)]
+    pub cache_write_tokens: u64,
+    /// Time from sending the first LLM request to its first streamed content.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_to_first_token_ms: Option<u64>,
+    /// Time spent streaming LLM responses, summed over all calls.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_stream_ms: Option<u64>,
+    /// Estimated cost in USD, if the model has a `pricing` entry.
+    #[serde(default)]
+    pub cost_usd: Option<f64>,
+    /// Backend fingerprint reported by the provider (OpenAI), if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_fingerprint: Option<String>,
     pub aborted: bool,
     pub stop_reason: Option<String>,
     pub error: Option<AgentRunError>,
 }
 
+impl AgentRunResult {
+    /// Final reply text of the run, if it produced one without error.
+    pub fn final_text(&self) -> Option<&str> {
+        self.payloads
+            .iter()
+            .filter(|p| !p.is_error)
+            .find_map(|p| p.text.as_deref())
+    }
+}
+
+impl AgentRunMeta {
+    /// Fraction of prompt tokens served from cache, if any caching happened.
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        let total = self.input_tokens + self.cache_read_tokens + self.cache_write_tokens;
+        if self.cache_read_tokens + self.cache_write_tokens == 0 || total == 0 {
+            return None;
+        }
+        Some(self.cache_read_tokens as f64 / total as f64)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentRunError {
     pub kind: AgentErrorKind,
@@ -105,4 +207,6 @@ pub enum AgentErrorKind {
     ToolError,
     Timeout,
     Aborted,
+    /// The tool loop hit `max_tool_iterations` before the model finished.
+    MaxIterations,
 }
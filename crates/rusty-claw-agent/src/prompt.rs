@@ -1,9 +1,17 @@
 //! System prompt builder for the agent.
+//!
+//! Runs use [`cached_system_prompt`], which keeps the prompt on the session and
+//! rebuilds it only when its inputs change: the tool set, active skills,
+//! custom prompt, workspace instruction files, or the config. The current
+//! time is filled in on every call.
 
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::path::Path;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use rusty_claw_core::config::Config;
+use rusty_claw_core::session::{CachedSystemPrompt, Session};
 use rusty_claw_core::skills::SkillDefinition;
 use rusty_claw_tools::ToolRegistry;
 
@@ -17,6 +25,9 @@ pub fn build_system_prompt(
     build_system_prompt_with_persona(_config, tools, workspace, active_skills, None)
 }
 
+/// Workspace files whose contents are added to the prompt.
+const WORKSPACE_PROMPT_FILES: [&str; 3] = ["SOUL.md", "AGENTS.md", "TOOLS.md"];
+
 /// Build the system prompt with an optional custom persona override.
 pub fn build_system_prompt_with_persona(
     _config: &Arc<Config>,
@@ -25,21 +36,99 @@ pub fn build_system_prompt_with_persona(
     active_skills: &[&SkillDefinition],
     custom_system_prompt: Option<&str>,
 ) -> String {
-    let mut parts = Vec::new();
+    let (identity, rest) =
+        prompt_sections(&tools.list(), workspace, active_skills, custom_system_prompt);
+    with_current_time(&identity, &rest)
+}
 
-    let identity = custom_system_prompt
-        .unwrap_or("You are a helpful personal AI assistant powered by Rusty Claw.");
-    parts.push(identity.to_string());
+/// [`build_system_prompt_with_persona`] for `session` and the tools in
+/// `tool_names`, reusing the prompt cached on the session when none of its
+/// inputs changed, including the config (by its [`Config::version`]).
+pub fn cached_system_prompt(
+    session: &mut Session,
+    config_version: u64,
+    tool_names: &[&str],
+    workspace: &Path,
+    active_skills: &[&SkillDefinition],
+) -> String {
+    let custom_system_prompt = session.meta.custom_system_prompt.as_deref();
+    let inputs_hash = prompt_inputs_hash(
+        config_version,
+        tool_names,
+        workspace,
+        active_skills,
+        custom_system_prompt,
+    );
+    if let Some(cached) = &session.meta.system_prompt_cache
+        && cached.inputs_hash == inputs_hash
+    {
+        return with_current_time(&cached.identity, &cached.rest);
+    }
+
+    let (identity, rest) =
+        prompt_sections(tool_names, workspace, active_skills, custom_system_prompt);
+    let prompt = with_current_time(&identity, &rest);
+    session.meta.system_prompt_cache = Some(CachedSystemPrompt {
+        inputs_hash,
+        identity,
+        rest,
+    });
+    prompt
+}
 
-    // Add current time
+/// Hash of everything the prompt is built from, except the time.
+fn prompt_inputs_hash(
+    config_version: u64,
+    tool_names: &[&str],
+    workspace: &Path,
+    active_skills: &[&SkillDefinition],
+    custom_system_prompt: Option<&str>,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    config_version.hash(&mut hasher);
+    tool_names.hash(&mut hasher);
+    workspace.hash(&mut hasher);
+    for file in WORKSPACE_PROMPT_FILES {
+        let modified = std::fs::metadata(workspace.join(file))
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok());
+        modified.hash(&mut hasher);
+    }
+    for skill in active_skills {
+        skill.name.hash(&mut hasher);
+        skill.system_prompt.hash(&mut hasher);
+    }
+    custom_system_prompt.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Join the prompt sections around the current time.
+fn with_current_time(identity: &str, rest: &str) -> String {
     let now = chrono::Utc::now();
-    parts.push(format!("Current time: {}", now.format("%Y-%m-%d %H:%M:%S UTC")));
+    format!(
+        "{identity}\n\nCurrent time: {}\n\n{rest}",
+        now.format("%Y-%m-%d %H:%M:%S UTC")
+    )
+}
+
+/// The prompt before and after the time line.
+fn prompt_sections(
+    tool_names: &[&str],
+    workspace: &Path,
+    active_skills: &[&SkillDefinition],
+    custom_system_prompt: Option<&str>,
+) -> (String, String) {
+    let identity = custom_system_prompt
+        .unwrap_or("You are a helpful personal AI assistant powered by Rusty Claw.")
+        .to_string();
+
+    let mut parts = Vec::new();
 
     // Workspace info
     parts.push(format!("Workspace directory: {}", workspace.display()));
 
     // Available tools
-    let tool_names = tools.list();
     if !tool_names.is_empty() {
         parts.push(format!(
             "Available tools: {}",
@@ -79,13 +168,70 @@ pub fn build_system_prompt_with_persona(
         }
     }
 
-    parts.join("\n\n")
+    (identity, parts.join("\n\n"))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_cached_prompt_reused_until_inputs_change() {
+        use rusty_claw_core::session::{SessionKey, SessionScope};
+        use rusty_claw_core::types::ChatType;
+
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path();
+        let tools_md = workspace.join("TOOLS.md");
+        std::fs::write(&tools_md, "Version one.").unwrap();
+        let modified = std::fs::metadata(&tools_md).unwrap().modified().unwrap();
+        let rewrite_tools_md = |text: &str| {
+            std::fs::write(&tools_md, text).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&tools_md)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+        let tools = ToolRegistry::new();
+        let mut session = Session::new(SessionKey {
+            channel: "test".into(),
+            account_id: "default".into(),
+            chat_type: ChatType::Dm,
+            peer_id: "user1".into(),
+            scope: SessionScope::PerSender,
+        });
+        let version = Config::default().version();
+
+        let first = cached_system_prompt(&mut session, version, &tools.list(), workspace, &[]);
+        assert!(first.contains("Version one."));
+        assert!(first.contains("Current time: "));
+        assert!(session.meta.system_prompt_cache.is_some());
+
+        // Same inputs (the file's mtime is unchanged): the cached prompt is used
+        rewrite_tools_md("Version two.");
+        let cached = cached_system_prompt(&mut session, version, &tools.list(), workspace, &[]);
+        assert!(cached.contains("Version one."));
+
+        // A changed persona rebuilds it
+        session.meta.custom_system_prompt = Some("You are Ada.".into());
+        let persona = cached_system_prompt(&mut session, version, &tools.list(), workspace, &[]);
+        assert!(persona.starts_with("You are Ada."));
+        assert!(persona.contains("Version two."));
+
+        // So does a changed config
+        rewrite_tools_md("Version three.");
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "agents": {"defaults": {"max_tool_iterations": 3}}
+        }))
+        .unwrap();
+        assert_ne!(config.version(), version);
+        let rebuilt =
+            cached_system_prompt(&mut session, config.version(), &tools.list(), workspace, &[]);
+        assert!(rebuilt.contains("Version three."));
+    }
+
     #[test]
     fn test_tools_md_loaded_in_prompt() {
         let dir = tempfile::tempdir().unwrap();
@@ -1,6 +1,6 @@
 //! Agent runtime loop — orchestrates LLM streaming + tool calling.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Instant;
 
@@ -8,17 +8,25 @@ use chrono::Utc;
 use serde_json::json;
 use tokio::sync::mpsc;
 use tokio_stream::StreamExt;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
 
-use rusty_claw_core::config::Config;
-use rusty_claw_core::session::{Session, TranscriptEntry, Usage};
+use rusty_claw_core::config::{Config, SkillToolMode};
+use rusty_claw_core::session::{Session, ThinkingBlock, TranscriptEntry, Usage};
 use rusty_claw_core::types::{ContentBlock, ImageSource, InboundMessage, ThinkingLevel};
 use rusty_claw_plugins::{HookContext, HookEvent, HookRegistry};
-use rusty_claw_providers::{CompletionRequest, Credentials, LlmProvider, ToolDefinition};
-use rusty_claw_tools::{ToolContext, ToolRegistry};
+use rusty_claw_providers::pricing::estimate_cost;
+use rusty_claw_providers::{
+    ChunkUsage, CompletionRequest, Credentials, LlmProvider, ProviderTimeoutError, ToolChoice,
+    ToolDefinition,
+};
+use rusty_claw_tools::{ToolContext, ToolProgress, ToolRegistry};
 
-use crate::prompt::build_system_prompt_with_persona;
-use crate::{AgentEvent, AgentErrorKind, AgentPayload, AgentRunError, AgentRunMeta, AgentRunResult};
+use crate::prompt::cached_system_prompt;
+use crate::{
+    AgentEvent, AgentErrorKind, AgentPayload, AgentRunError, AgentRunMeta, AgentRunOptions,
+    AgentRunResult,
+};
 
 /// Build a [`HookContext`] for the current session.
 fn hook_ctx(session: &Session) -> HookContext {
@@ -29,6 +37,47 @@ fn hook_ctx(session: &Session) -> HookContext {
     }
 }
 
+/// Tools the model may use given the active skills, or `None` for all of
+/// them. In restrict mode this is the union of the skills' `tools` lists plus
+/// the configured core tools; skills that list no tools restrict nothing.
+fn skill_tool_allowlist(
+    config: &Config,
+    active_skills: &[&rusty_claw_core::skills::SkillDefinition],
+) -> Option<HashSet<String>> {
+    let skills_config = config.skills.clone().unwrap_or_default();
+    if skills_config.tool_mode == SkillToolMode::Augment {
+        return None;
+    }
+    let skill_tools: HashSet<String> = active_skills
+        .iter()
+        .flat_map(|s| s.tools.iter().cloned())
+        .collect();
+    if skill_tools.is_empty() {
+        return None;
+    }
+    Some(skill_tools.into_iter().chain(skills_config.core_tools()).collect())
+}
+
+/// Save the session mid-run when checkpointing is enabled.
+async fn checkpoint(session: &Session, options: &AgentRunOptions) {
+    if let Some(store) = &options.checkpoint
+        && let Err(e) = store.save(session).await
+    {
+        warn!(%e, "Failed to checkpoint session");
+    }
+}
+
+/// Milliseconds in `d`, or `None` if nothing was measured.
+fn nonzero_ms(d: std::time::Duration) -> Option<u64> {
+    (!d.is_zero()).then_some(d.as_millis() as u64)
+}
+
+/// Instruction for the final call made when the tool loop runs out of
+/// iterations.
+const ITERATION_LIMIT_PROMPT: &str = "You have reached the maximum number of tool steps for this \
+turn and cannot call any more tools. Reply to the user now: summarize what you have done so far, \
+what you found, and what remains unfinished.";
+
 /// Run the agent loop: stream LLM, execute tools, emit events.
 #[allow(clippy::too_many_arguments)]
 pub async fn run_agent(
@@ -40,19 +89,59 @@ pub async fn run_agent(
     credentials: &Credentials,
     event_tx: mpsc::UnboundedSender<AgentEvent>,
     hooks: &Arc<HookRegistry>,
+) -> anyhow::Result<AgentRunResult> {
+    run_agent_with_options(
+        session,
+        message,
+        config,
+        tools,
+        provider,
+        credentials,
+        event_tx,
+        hooks,
+        AgentRunOptions::default(),
+    )
+    .await
+}
+
+/// Like [`run_agent`], with per-run overrides such as a forced tool choice.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_agent_with_options(
+    session: &mut Session,
+    message: InboundMessage,
+    config: &Arc<Config>,
+    tools: &ToolRegistry,
+    provider: &dyn LlmProvider,
+    credentials: &Credentials,
+    event_tx: mpsc::UnboundedSender<AgentEvent>,
+    hooks: &Arc<HookRegistry>,
+    options: AgentRunOptions,
 ) -> anyhow::Result<AgentRunResult> {
     let start = Instant::now();
     let max_iterations = config.max_tool_iterations();
     let workspace = config.workspace_dir();
 
-    // 1. Build system prompt (no active skills in this code path — gateway can set per-session)
-    let active_skills: Vec<&rusty_claw_core::skills::SkillDefinition> = Vec::new();
-    let system_prompt = build_system_prompt_with_persona(
-        config,
-        tools,
+    // 1. Build system prompt with the skills the caller activated
+    let active_skills: Vec<&rusty_claw_core::skills::SkillDefinition> =
+        options.active_skills.iter().collect();
+    if !active_skills.is_empty() {
+        let _ = event_tx.send(AgentEvent::SkillsActivated {
+            skills: active_skills.iter().map(|s| s.name.clone()).collect(),
+        });
+    }
+    // Active skills may narrow the tools offered to the model
+    let allowed_tools = skill_tool_allowlist(config, &active_skills);
+    let tool_names: Vec<&str> = tools
+        .list()
+        .into_iter()
+        .filter(|name| allowed_tools.as_ref().is_none_or(|a| a.contains(*name)))
+        .collect();
+    let system_prompt = cached_system_prompt(
+        session,
+        config.version(),
+        &tool_names,
         &workspace,
         &active_skills,
-        session.meta.custom_system_prompt.as_deref(),
     );
 
     // 2. Append user message to transcript
@@ -92,6 +181,7 @@ pub async fn run_agent(
         content: user_content,
         timestamp: Utc::now(),
     });
+    checkpoint(session, &options).await;
 
     // --- Hook: BeforeAgentStart ---
     let _ = hooks
@@ -107,8 +197,21 @@ pub async fn run_agent(
 
     let mut total_input_tokens: u64 = 0;
     let mut total_output_tokens: u64 = 0;
+    let mut total_cache_read: u64 = 0;
+    let mut total_cache_write: u64 = 0;
+    let mut first_token_ms: Option<u64> = None;
+    let mut cost_usd: Option<f64> = None;
+    let mut system_fingerprint: Option<String> = None;
+    let mut run_thinking = String::new();
+    let mut total_stream = std::time::Duration::ZERO;
     let mut tool_call_count: u32 = 0;
     let mut final_text = String::new();
+    let mut aborted = false;
+    let mut timed_out: Option<String> = None;
+    // Set when the model ends the turn itself rather than the loop running out
+    let mut finished = false;
+    let mut last_request: Option<CompletionRequest> = None;
+    let cancel = options.cancel.clone().unwrap_or_default();
 
     // Auto-compact if enabled and transcript exceeds limit
     if config
@@ -132,15 +235,20 @@ pub async fn run_agent(
     // 3. Tool loop
     for iteration in 0..max_iterations {
         debug!(iteration, "Agent loop iteration");
+        if cancel.is_cancelled() {
+            aborted = true;
+            break;
+        }
 
         // Build completion request from transcript
         let messages = provider.format_messages(&session.transcript);
-        let tool_defs = if tools.list().is_empty() {
+        let tool_defs = if tool_names.is_empty() {
             None
         } else {
             let definitions: Vec<ToolDefinition> = tools
                 .tools()
                 .iter()
+                .filter(|t| tool_names.contains(&t.name()))
                 .map(|t| ToolDefinition {
                     name: t.name().to_string(),
                     description: t.description().to_string(),
@@ -177,6 +285,19 @@ pub async fn run_agent(
             tools: tool_defs,
             system: Some(system_prompt.clone()),
             thinking_budget_tokens: thinking_budget,
+            enable_prompt_cache: config.prompt_cache_enabled(),
+            stop_sequences: config.stop_sequences(),
+            tool_choice: if iteration == 0 {
+                options.tool_choice.clone()
+            } else {
+                None
+            },
+            response_format: options.response_format.clone(),
+            top_p: config.top_p(),
+            top_k: config.top_k(),
+            frequency_penalty: config.frequency_penalty(),
+            presence_penalty: config.presence_penalty(),
+            seed: config.seed(),
         };
 
         // --- Hook: LlmInput ---
@@ -192,12 +313,18 @@ pub async fn run_agent(
             .await;
 
         // Stream LLM response
+        let call_start = Instant::now();
         let stream = match provider.stream(&request, credentials).await {
             Ok(s) => s,
             Err(e) => {
                 error!(%e, "Provider stream error");
+                let (kind, error_kind) = if e.downcast_ref::<ProviderTimeoutError>().is_some() {
+                    ("timeout", AgentErrorKind::Timeout)
+                } else {
+                    ("provider_error", AgentErrorKind::ProviderError)
+                };
                 let _ = event_tx.send(AgentEvent::Error {
-                    kind: "provider_error".into(),
+                    kind: kind.into(),
                     message: e.to_string(),
                 });
                 return Ok(AgentRunResult {
@@ -205,16 +332,23 @@ pub async fn run_agent(
                         text: Some(format!("Provider error: {e}")),
                         media_urls: vec![],
                         is_error: true,
+                        thinking: None,
                     }],
                     meta: AgentRunMeta {
                         duration_ms: start.elapsed().as_millis() as u64,
                         input_tokens: total_input_tokens,
                         output_tokens: total_output_tokens,
                         tool_calls: tool_call_count,
+                        cache_read_tokens: total_cache_read,
+                        cache_write_tokens: total_cache_write,
+                        time_to_first_token_ms: first_token_ms,
+                        total_stream_ms: nonzero_ms(total_stream),
+                        cost_usd,
+                        system_fingerprint,
                         aborted: false,
                         stop_reason: None,
                         error: Some(AgentRunError {
-                            kind: AgentErrorKind::ProviderError,
+                            kind: error_kind,
                             message: e.to_string(),
                         }),
                     },
@@ -222,14 +356,44 @@ pub async fn run_agent(
             }
         };
 
-        let mut stream = std::pin::pin!(stream);
+        let mut stream = stream;
         let mut response_text = String::new();
         let mut tool_uses: Vec<(String, String, serde_json::Value)> = Vec::new(); // (id, name, input)
         let mut stop_reason = None;
+        let mut call_usage = ChunkUsage::default();
+        let mut thinking_text = String::new();
+        // Each signature closes the thinking block streamed before it
+        let mut thinking_blocks: Vec<ThinkingBlock> = Vec::new();
+        let mut block_text = String::new();
+        let mut saw_content = false;
 
-        while let Some(chunk_result) = stream.next().await {
+        loop {
+            let chunk_result = tokio::select! {
+                biased;
+                _ = cancel.cancelled() => {
+                    aborted = true;
+                    break;
+                }
+                next = stream.next() => match next {
+                    Some(chunk_result) => chunk_result,
+                    None => break,
+                },
+            };
             match chunk_result {
                 Ok(chunk) => {
+                    let has_content = chunk.delta.as_deref().is_some_and(|d| !d.is_empty())
+                        || chunk.thinking.as_deref().is_some_and(|t| !t.is_empty())
+                        || chunk.tool_use.is_some();
+                    if has_content && !saw_content {
+                        saw_content = true;
+                        let ttft_ms = call_start.elapsed().as_millis() as u64;
+                        first_token_ms.get_or_insert(ttft_ms);
+                        let _ = event_tx.send(AgentEvent::Timing {
+                            iteration,
+                            time_to_first_token_ms: ttft_ms,
+                        });
+                    }
+
                     // Text delta
                     if let Some(ref delta) = chunk.delta {
                         response_text.push_str(delta);
@@ -240,10 +404,21 @@ pub async fn run_agent(
 
                     // Thinking delta
                     if let Some(ref thinking) = chunk.thinking {
+                        thinking_text.push_str(thinking);
+                        block_text.push_str(thinking);
                         let _ = event_tx.send(AgentEvent::ReasoningStream {
                             text: thinking.clone(),
                         });
                     }
+                    if let Some(ref signature) = chunk.thinking_signature {
+                        thinking_blocks.push(ThinkingBlock {
+                            thinking: std::mem::take(&mut block_text),
+                            signature: Some(signature.clone()),
+                        });
+                    }
+                    if chunk.system_fingerprint.is_some() {
+                        system_fingerprint = chunk.system_fingerprint.clone();
+                    }
 
                     // Tool use
                     if let Some(ref tool_use) = chunk.tool_use {
@@ -260,9 +435,18 @@ pub async fn run_agent(
                     if let Some(ref usage) = chunk.usage {
                         if let Some(inp) = usage.input_tokens {
                             total_input_tokens = inp;
+                            call_usage.input_tokens = Some(inp);
                         }
                         if let Some(out) = usage.output_tokens {
                             total_output_tokens = out;
+                            call_usage.output_tokens = Some(out);
+                        }
+                        if usage.cache_read_input_tokens.is_some() {
+                            call_usage.cache_read_input_tokens = usage.cache_read_input_tokens;
+                        }
+                        if usage.cache_creation_input_tokens.is_some() {
+                            call_usage.cache_creation_input_tokens =
+                                usage.cache_creation_input_tokens;
                         }
                     }
 
@@ -273,15 +457,46 @@ pub async fn run_agent(
                 }
                 Err(e) => {
                     error!(%e, "Stream chunk error");
+                    let timeout = e.downcast_ref::<ProviderTimeoutError>().is_some();
                     let _ = event_tx.send(AgentEvent::Error {
-                        kind: "provider_error".into(),
+                        kind: if timeout { "timeout" } else { "provider_error" }.into(),
                         message: e.to_string(),
                     });
+                    if timeout {
+                        timed_out = Some(e.to_string());
+                    }
                     break;
                 }
             }
         }
 
+        if aborted || timed_out.is_some() {
+            // Dropping the stream closes the HTTP connection
+            drop(stream);
+            info!(iteration, aborted, "Agent run interrupted mid-stream");
+            // Partial tool calls never ran, so only the text survives
+            tool_uses.clear();
+        }
+
+        total_stream += call_start.elapsed();
+        let cache_read = call_usage.cache_read_input_tokens;
+        let cache_write = call_usage.cache_creation_input_tokens;
+        total_cache_read += cache_read.unwrap_or(0);
+        total_cache_write += cache_write.unwrap_or(0);
+        if let Some(pricing) = &config.pricing {
+            if let Some(cost) = estimate_cost(pricing, &request.model, &call_usage) {
+                *cost_usd.get_or_insert(0.0) += cost;
+            }
+        }
+        last_request = Some(request);
+
+        if !block_text.is_empty() {
+            thinking_blocks.push(ThinkingBlock {
+                thinking: block_text,
+                signature: None,
+            });
+        }
+
         // Build assistant content blocks
         let mut assistant_content: Vec<ContentBlock> = Vec::new();
         if !response_text.is_empty() {
@@ -303,11 +518,26 @@ pub async fn run_agent(
             usage: Some(Usage {
                 input_tokens: total_input_tokens,
                 output_tokens: total_output_tokens,
-                cache_read_tokens: None,
-                cache_write_tokens: None,
+                cache_read_tokens: cache_read,
+                cache_write_tokens: cache_write,
             }),
+            thinking: (!thinking_text.is_empty()).then(|| thinking_text.clone()),
+            thinking_signature: None,
+            thinking_blocks,
             timestamp: Utc::now(),
         });
+        checkpoint(session, &options).await;
+        if !thinking_text.is_empty() {
+            if !run_thinking.is_empty() {
+                run_thinking.push_str("\n\n");
+            }
+            run_thinking.push_str(&thinking_text);
+        }
+
+        if aborted || timed_out.is_some() {
+            final_text = response_text;
+            break;
+        }
 
         // --- Hook: LlmOutput ---
         let _ = hooks
@@ -329,13 +559,14 @@ pub async fn run_agent(
             output_tokens: total_output_tokens,
         });
 
-        // Check stop reason
+        // Check stop reason (a stop-sequence finish ends the turn normally)
         let is_tool_use = stop_reason
             .as_deref()
             .is_some_and(|r| provider.is_tool_use_stop(r));
 
         if !is_tool_use || tool_uses.is_empty() {
             // No tools to call — we're done
+            finished = true;
             final_text = response_text;
             let _ = event_tx.send(AgentEvent::BlockReply {
                 text: final_text.clone(),
@@ -370,10 +601,12 @@ pub async fn run_agent(
                         is_error: true,
                         timestamp: Utc::now(),
                     });
+                    checkpoint(session, &options).await;
                     let _ = event_tx.send(AgentEvent::ToolResult {
                         tool: name.clone(),
                         content: format!("Tool call cancelled: {reason}"),
                         is_error: true,
+                        partial: false,
                     });
                     true
                 }
@@ -406,6 +639,7 @@ pub async fn run_agent(
                 .map(|s| s.restrict_to_workspace)
                 .unwrap_or(true);
 
+            let (progress_tx, progress_rx) = mpsc::unbounded_channel();
             let tool_context = ToolContext {
                 session_key: session.meta.key.hash_key(),
                 workspace: workspace.clone(),
@@ -413,10 +647,24 @@ pub async fn run_agent(
                 restrict_to_workspace,
                 sandbox_mode,
                 browser_pool: None, // Set by gateway when browser is available
+                progress: Some(progress_tx),
             };
 
-            let tool_output = match tools.get(name) {
-                Some(tool) => match tool.execute(input.clone(), &tool_context).await {
+            let mut tool_output = match tools.get(name) {
+                Some(_) if !tool_names.contains(&name.as_str()) => rusty_claw_tools::ToolOutput {
+                    content: format!("Tool '{name}' is not available with the active skills"),
+                    is_error: true,
+                    media: None,
+                },
+                Some(tool) => match execute_with_progress(
+                    tool,
+                    input.clone(),
+                    &tool_context,
+                    progress_rx,
+                    &event_tx,
+                )
+                .await
+                {
                     Ok(output) => output,
                     Err(e) => {
                         warn!(%e, tool = %name, "Tool execution error");
@@ -447,10 +695,37 @@ pub async fn run_agent(
                 )
                 .await;
 
+            // --- Hook: ToolResultPersist (can rewrite what is stored and
+            // sent on, e.g. to redact secrets) ---
+            let hook_data = json!({
+                "tool": name,
+                "tool_use_id": id,
+                "content": &tool_output.content,
+                "is_error": tool_output.is_error,
+            });
+            match hooks
+                .fire_or_cancel(HookEvent::ToolResultPersist, hook_ctx(session), hook_data)
+                .await
+            {
+                Ok(data) => {
+                    if let Some(content) = data.get("content").and_then(|c| c.as_str())
+                        && content != tool_output.content
+                    {
+                        debug!(tool = %name, "Tool result rewritten by hook");
+                        tool_output.content = content.to_string();
+                    }
+                }
+                Err(reason) => {
+                    warn!(tool = %name, reason = %reason, "Tool result withheld by hook");
+                    tool_output.content = format!("Tool result withheld: {reason}");
+                }
+            }
+
             let _ = event_tx.send(AgentEvent::ToolResult {
                 tool: name.clone(),
                 content: tool_output.content.clone(),
                 is_error: tool_output.is_error,
+                partial: false,
             });
 
             // Record tool result in transcript
@@ -461,11 +736,80 @@ pub async fn run_agent(
                 is_error: tool_output.is_error,
                 timestamp: Utc::now(),
             });
+            checkpoint(session, &options).await;
         }
 
         // Continue the loop — LLM will see the tool results
     }
 
+    // Out of iterations: ask the model for a summary with tools disabled, so
+    // the user gets a coherent reply instead of silence
+    let hit_iteration_limit = !finished && !aborted && timed_out.is_none();
+    if hit_iteration_limit {
+        warn!(max_iterations, "Tool iteration limit reached, requesting summary");
+        if let Some(mut request) = last_request.take() {
+            let mut transcript = session.transcript.clone();
+            transcript.push(TranscriptEntry::User {
+                content: vec![ContentBlock::Text {
+                    text: ITERATION_LIMIT_PROMPT.into(),
+                }],
+                timestamp: Utc::now(),
+            });
+            request.messages = provider.format_messages(&transcript);
+            request.tool_choice = Some(ToolChoice::None);
+            request.response_format = None;
+            request.thinking_budget_tokens = None;
+            match summarize_progress(provider, &request, credentials, &event_tx, &cancel).await {
+                Ok((text, usage)) => {
+                    if let Some(inp) = usage.input_tokens {
+                        total_input_tokens = inp;
+                    }
+                    if let Some(out) = usage.output_tokens {
+                        total_output_tokens = out;
+                    }
+                    if let Some(pricing) = &config.pricing
+                        && let Some(cost) = estimate_cost(pricing, &request.model, &usage)
+                    {
+                        *cost_usd.get_or_insert(0.0) += cost;
+                    }
+                    if !text.is_empty() {
+                        session.append(TranscriptEntry::Assistant {
+                            content: vec![ContentBlock::Text { text: text.clone() }],
+                            usage: Some(Usage {
+                                input_tokens: total_input_tokens,
+                                output_tokens: total_output_tokens,
+                                cache_read_tokens: usage.cache_read_input_tokens,
+                                cache_write_tokens: usage.cache_creation_input_tokens,
+                            }),
+                            thinking: None,
+                            thinking_signature: None,
+                            thinking_blocks: Vec::new(),
+                            timestamp: Utc::now(),
+                        });
+                    }
+                    final_text = text;
+                }
+                Err(e) => warn!(%e, "Iteration-limit summary failed"),
+            }
+        }
+        if final_text.is_empty() {
+            final_text = format!(
+                "I ran out of steps ({max_iterations} tool iterations) before finishing this task."
+            );
+        }
+        let _ = event_tx.send(AgentEvent::BlockReply {
+            text: final_text.clone(),
+            is_final: true,
+        });
+    }
+
+    if aborted {
+        let _ = event_tx.send(AgentEvent::Error {
+            kind: "aborted".into(),
+            message: "Run aborted".into(),
+        });
+    }
+
     // --- Hook: AgentEnd ---
     let _ = hooks
         .fire(
@@ -480,6 +824,28 @@ pub async fn run_agent(
         )
         .await;
 
+    let (stop_reason, error) = if aborted {
+        let error = AgentRunError {
+            kind: AgentErrorKind::Aborted,
+            message: "Run aborted".into(),
+        };
+        ("aborted", Some(error))
+    } else if let Some(message) = timed_out {
+        let error = AgentRunError {
+            kind: AgentErrorKind::Timeout,
+            message,
+        };
+        ("timeout", Some(error))
+    } else if hit_iteration_limit {
+        let error = AgentRunError {
+            kind: AgentErrorKind::MaxIterations,
+            message: format!("Reached the limit of {max_iterations} tool iterations"),
+        };
+        ("max_iterations", Some(error))
+    } else {
+        ("end_turn", None)
+    };
+
     Ok(AgentRunResult {
         payloads: vec![AgentPayload {
             text: if final_text.is_empty() {
@@ -489,15 +855,525 @@ pub async fn run_agent(
             },
             media_urls: vec![],
             is_error: false,
+            thinking: (options.include_thinking && !run_thinking.is_empty()).then_some(run_thinking),
         }],
         meta: AgentRunMeta {
             duration_ms: start.elapsed().as_millis() as u64,
             input_tokens: total_input_tokens,
             output_tokens: total_output_tokens,
             tool_calls: tool_call_count,
-            aborted: false,
-            stop_reason: Some("end_turn".into()),
-            error: None,
+            cache_read_tokens: total_cache_read,
+            cache_write_tokens: total_cache_write,
+            time_to_first_token_ms: first_token_ms,
+            total_stream_ms: nonzero_ms(total_stream),
+            cost_usd,
+            system_fingerprint,
+            aborted,
+            stop_reason: Some(stop_reason.into()),
+            error,
         },
     })
 }
+
+/// Make the final tool-free call after the iteration limit, streaming its
+/// text as partial replies. Returns the text and the call's usage.
+async fn summarize_progress(
+    provider: &dyn LlmProvider,
+    request: &CompletionRequest,
+    credentials: &Credentials,
+    event_tx: &mpsc::UnboundedSender<AgentEvent>,
+    cancel: &CancellationToken,
+) -> anyhow::Result<(String, ChunkUsage)> {
+    let mut stream = provider.stream(request, credentials).await?;
+    let mut text = String::new();
+    let mut usage = ChunkUsage::default();
+    loop {
+        let chunk = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => break,
+            next = stream.next() => match next {
+                Some(chunk) => chunk?,
+                None => break,
+            },
+        };
+        // Tool calls are disabled; any that slip through are ignored
+        if let Some(delta) = chunk.delta {
+            text.push_str(&delta);
+            let _ = event_tx.send(AgentEvent::PartialReply { delta });
+        }
+        if let Some(u) = chunk.usage {
+            usage.input_tokens = u.input_tokens.or(usage.input_tokens);
+            usage.output_tokens = u.output_tokens.or(usage.output_tokens);
+            usage.cache_read_input_tokens = u.cache_read_input_tokens.or(usage.cache_read_input_tokens);
+            usage.cache_creation_input_tokens =
+                u.cache_creation_input_tokens.or(usage.cache_creation_input_tokens);
+        }
+    }
+    Ok((text, usage))
+}
+
+/// Run a tool, forwarding its incremental output as partial
+/// [`AgentEvent::ToolResult`] events (and audio as
+/// [`AgentEvent::AudioDelta`]) until it completes.
+async fn execute_with_progress(
+    tool: &dyn rusty_claw_tools::Tool,
+    input: serde_json::Value,
+    context: &ToolContext,
+    mut progress_rx: mpsc::UnboundedReceiver<ToolProgress>,
+    event_tx: &mpsc::UnboundedSender<AgentEvent>,
+) -> anyhow::Result<rusty_claw_tools::ToolOutput> {
+    let forward = |progress: ToolProgress| {
+        let event = match progress {
+            ToolProgress::Output(content) => AgentEvent::ToolResult {
+                tool: tool.name().to_string(),
+                content,
+                is_error: false,
+                partial: true,
+            },
+            ToolProgress::Audio { media, is_final } => AgentEvent::AudioDelta {
+                data: media.data,
+                format: media.mime_type,
+                is_final,
+            },
+        };
+        let _ = event_tx.send(event);
+    };
+
+    let execution = tool.execute(input, context);
+    tokio::pin!(execution);
+    let result = loop {
+        tokio::select! {
+            result = &mut execution => break result,
+            Some(chunk) = progress_rx.recv() => forward(chunk),
+        }
+    };
+    while let Ok(chunk) = progress_rx.try_recv() {
+        forward(chunk);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::pin::Pin;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use futures::Stream;
+    use rusty_claw_core::session::{SessionKey, SessionScope};
+    use rusty_claw_core::types::ChatType;
+    use rusty_claw_providers::{CompletionChunk, ModelApi, ModelInfo, ToolUseChunk};
+
+    /// Answers each `stream` call with the next queued response and records
+    /// the requests it was sent.
+    struct ScriptedProvider {
+        responses: Mutex<VecDeque<Vec<CompletionChunk>>>,
+        requests: Mutex<Vec<CompletionRequest>>,
+        /// Wait before the first chunk of each response.
+        delay: Option<Duration>,
+        /// Keep each stream open after its chunks, as a stalled response would.
+        hang: bool,
+    }
+
+    impl ScriptedProvider {
+        fn new(responses: impl IntoIterator<Item = Vec<CompletionChunk>>) -> Self {
+            Self {
+                responses: Mutex::new(responses.into_iter().collect()),
+                requests: Mutex::new(Vec::new()),
+                delay: None,
+                hang: false,
+            }
+        }
+
+        fn with_delay(mut self, delay: Duration) -> Self {
+            self.delay = Some(delay);
+            self
+        }
+
+        fn hanging(mut self) -> Self {
+            self.hang = true;
+            self
+        }
+
+        fn requests(&self) -> Vec<CompletionRequest> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl LlmProvider for ScriptedProvider {
+        fn id(&self) -> &str {
+            "scripted"
+        }
+        fn api(&self) -> ModelApi {
+            ModelApi::AnthropicMessages
+        }
+        fn format_tools(&self, _tools: &[ToolDefinition]) -> Vec<serde_json::Value> {
+            vec![]
+        }
+        fn format_messages(&self, _transcript: &[TranscriptEntry]) -> Vec<serde_json::Value> {
+            vec![]
+        }
+        fn is_tool_use_stop(&self, stop_reason: &str) -> bool {
+            stop_reason == "tool_use"
+        }
+        async fn stream(
+            &self,
+            request: &CompletionRequest,
+            _credentials: &Credentials,
+        ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<CompletionChunk>> + Send>>>
+        {
+            self.requests.lock().unwrap().push(request.clone());
+            let Some(chunks) = self.responses.lock().unwrap().pop_front() else {
+                anyhow::bail!("scripted provider has no response left");
+            };
+            let stream = futures::stream::unfold(
+                (chunks.into_iter(), self.delay),
+                |(mut chunks, delay)| async move {
+                    if let Some(delay) = delay {
+                        tokio::time::sleep(delay).await;
+                    }
+                    chunks.next().map(|chunk| (Ok(chunk), (chunks, None)))
+                },
+            );
+            if self.hang {
+                Ok(Box::pin(stream.chain(futures::stream::pending())))
+            } else {
+                Ok(Box::pin(stream))
+            }
+        }
+        async fn list_models(&self, _credentials: &Credentials) -> anyhow::Result<Vec<ModelInfo>> {
+            Ok(vec![])
+        }
+    }
+
+    fn answer(text: &str) -> Vec<CompletionChunk> {
+        vec![CompletionChunk {
+            delta: Some(text.into()),
+            stop_reason: Some("end_turn".into()),
+            ..Default::default()
+        }]
+    }
+
+    fn tool_call() -> Vec<CompletionChunk> {
+        vec![CompletionChunk {
+            tool_use: Some(ToolUseChunk {
+                id: "call".into(),
+                name: "search".into(),
+                input_json: "{}".into(),
+            }),
+            stop_reason: Some("tool_use".into()),
+            ..Default::default()
+        }]
+    }
+
+    fn test_session() -> Session {
+        Session::new(SessionKey {
+            channel: "test".into(),
+            account_id: "a".into(),
+            chat_type: ChatType::Dm,
+            peer_id: "p".into(),
+            scope: SessionScope::PerSender,
+        })
+    }
+
+    fn test_credentials() -> Credentials {
+        Credentials::ApiKey {
+            api_key: "k".into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_abort_mid_stream_returns_promptly() {
+        let mut session = test_session();
+        let provider = ScriptedProvider::new([vec![CompletionChunk {
+            delta: Some("Partial".into()),
+            ..Default::default()
+        }]])
+        .hanging();
+        let config = Arc::new(Config::default());
+        let tools = ToolRegistry::new();
+        let hooks = Arc::new(HookRegistry::new());
+        let (event_tx, _event_rx) = mpsc::unbounded_channel();
+        let cancel = CancellationToken::new();
+        let canceller = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            canceller.cancel();
+        });
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            run_agent_with_options(
+                &mut session,
+                InboundMessage::from_cli_text("hello"),
+                &config,
+                &tools,
+                &provider,
+                &test_credentials(),
+                event_tx,
+                &hooks,
+                AgentRunOptions {
+                    cancel: Some(cancel),
+                    ..Default::default()
+                },
+            ),
+        )
+        .await
+        .expect("aborted run should return promptly")
+        .unwrap();
+
+        assert!(result.meta.aborted);
+        assert!(matches!(
+            result.meta.error.as_ref().map(|e| &e.kind),
+            Some(AgentErrorKind::Aborted)
+        ));
+        assert_eq!(result.payloads[0].text.as_deref(), Some("Partial"));
+        match session.transcript.last() {
+            Some(TranscriptEntry::Assistant { content, .. }) => {
+                assert!(matches!(&content[..], [ContentBlock::Text { text }] if text == "Partial"));
+            }
+            other => panic!("expected partial assistant entry, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timing_measures_time_to_first_token() {
+        let mut session = test_session();
+        let provider =
+            ScriptedProvider::new([tool_call(), answer("Found it.")]).with_delay(Duration::from_millis(100));
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+        let result = run_agent(
+            &mut session,
+            InboundMessage::from_cli_text("find it"),
+            &Arc::new(Config::default()),
+            &ToolRegistry::new(),
+            &provider,
+            &test_credentials(),
+            event_tx,
+            &Arc::new(HookRegistry::new()),
+        )
+        .await
+        .unwrap();
+
+        let ttft = result.meta.time_to_first_token_ms.unwrap();
+        assert!(ttft >= 100, "ttft {ttft}ms includes the delayed first chunk");
+        // Both calls waited for their first chunk
+        let total_stream = result.meta.total_stream_ms.unwrap();
+        assert!(total_stream >= 200, "total_stream {total_stream}ms covers both calls");
+        assert!(result.meta.duration_ms >= total_stream);
+
+        let mut timings = Vec::new();
+        while let Ok(event) = event_rx.try_recv() {
+            if let AgentEvent::Timing { iteration, time_to_first_token_ms } = event {
+                timings.push((iteration, time_to_first_token_ms));
+            }
+        }
+        assert_eq!(timings.len(), 2);
+        assert_eq!(timings[0], (0, ttft));
+        assert_eq!(timings[1].0, 1);
+        assert!(timings[1].1 >= 100);
+    }
+
+    #[tokio::test]
+    async fn test_thinking_blocks_keep_their_signatures() {
+        let mut session = test_session();
+        let thinking = |text: &str| CompletionChunk {
+            thinking: Some(text.into()),
+            ..Default::default()
+        };
+        let signature = |sig: &str| CompletionChunk {
+            thinking_signature: Some(sig.into()),
+            ..Default::default()
+        };
+        let mut response = vec![thinking("First"), signature("sig1"), thinking("Second"), signature("sig2")];
+        response.extend(answer("Answer"));
+        let provider = ScriptedProvider::new([response]);
+        let (event_tx, _event_rx) = mpsc::unbounded_channel();
+        run_agent(
+            &mut session,
+            InboundMessage::from_cli_text("think"),
+            &Arc::new(Config::default()),
+            &ToolRegistry::new(),
+            &provider,
+            &test_credentials(),
+            event_tx,
+            &Arc::new(HookRegistry::new()),
+        )
+        .await
+        .unwrap();
+
+        let entry = session.transcript.last().unwrap();
+        let block = |thinking: &str, signature: &str| ThinkingBlock {
+            thinking: thinking.into(),
+            signature: Some(signature.into()),
+        };
+        assert_eq!(entry.thinking_blocks(), vec![block("First", "sig1"), block("Second", "sig2")]);
+        assert!(matches!(
+            entry,
+            TranscriptEntry::Assistant { thinking: Some(t), thinking_signature: None, .. }
+                if t == "FirstSecond"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_configured_stop_sequences_are_sent() {
+        let mut session = test_session();
+        let config: Config = serde_json::from_value(
+            json!({"agents": {"defaults": {"stop_sequences": ["</answer>", "END"]}}}),
+        )
+        .unwrap();
+        let provider = ScriptedProvider::new([vec![CompletionChunk {
+            delta: Some("42".into()),
+            stop_reason: Some("stop_sequence".into()),
+            ..Default::default()
+        }]]);
+        let (event_tx, _event_rx) = mpsc::unbounded_channel();
+        let result = run_agent(
+            &mut session,
+            InboundMessage::from_cli_text("extract"),
+            &Arc::new(config),
+            &ToolRegistry::new(),
+            &provider,
+            &test_credentials(),
+            event_tx,
+            &Arc::new(HookRegistry::new()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            provider.requests()[0].stop_sequences,
+            Some(vec!["</answer>".to_string(), "END".to_string()])
+        );
+        assert_eq!(result.payloads[0].text.as_deref(), Some("42"));
+        assert!(result.meta.error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_iteration_limit_produces_summary() {
+        let mut session = test_session();
+        let config: Config =
+            serde_json::from_value(json!({"agents": {"defaults": {"max_tool_iterations": 2}}})).unwrap();
+        let config = Arc::new(config);
+        let tools = ToolRegistry::new();
+        let hooks = Arc::new(HookRegistry::new());
+        let provider = ScriptedProvider::new([
+            tool_call(),
+            tool_call(),
+            answer("I searched twice but ran out of steps."),
+        ]);
+        let (event_tx, _event_rx) = mpsc::unbounded_channel();
+
+        let result = run_agent(
+            &mut session,
+            InboundMessage::from_cli_text("find it"),
+            &config,
+            &tools,
+            &provider,
+            &test_credentials(),
+            event_tx,
+            &hooks,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.meta.stop_reason.as_deref(), Some("max_iterations"));
+        assert!(matches!(
+            result.meta.error.as_ref().map(|e| &e.kind),
+            Some(AgentErrorKind::MaxIterations)
+        ));
+        assert_eq!(
+            result.payloads[0].text.as_deref(),
+            Some("I searched twice but ran out of steps.")
+        );
+        assert_eq!(result.meta.tool_calls, 2);
+
+        // Only the summary call has tools disabled
+        let requests = provider.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[..2].iter().all(|r| r.tool_choice != Some(ToolChoice::None)));
+        assert_eq!(requests[2].tool_choice, Some(ToolChoice::None));
+    }
+
+    #[tokio::test]
+    async fn test_tool_result_persist_hook_rewrites_content() {
+        let mut session = test_session();
+        let config: Config =
+            serde_json::from_value(json!({"agents": {"defaults": {"max_tool_iterations": 1}}})).unwrap();
+        let config = Arc::new(config);
+        let tools = ToolRegistry::new();
+        let hooks = Arc::new(HookRegistry::new());
+        hooks
+            .register(
+                HookEvent::ToolResultPersist,
+                Box::new(|_ctx, mut data| {
+                    data["content"] = json!("[redacted]");
+                    Box::pin(async move { Ok(rusty_claw_plugins::HookResult::Modified(data)) })
+                }),
+            )
+            .await;
+        let provider = ScriptedProvider::new([tool_call(), answer("Done.")]);
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+
+        run_agent(
+            &mut session,
+            InboundMessage::from_cli_text("find it"),
+            &config,
+            &tools,
+            &provider,
+            &test_credentials(),
+            event_tx,
+            &hooks,
+        )
+        .await
+        .unwrap();
+
+        let stored = session.transcript.iter().find_map(|e| match e {
+            TranscriptEntry::ToolResult { content, .. } => Some(content.as_str()),
+            _ => None,
+        });
+        assert_eq!(stored, Some("[redacted]"));
+        while let Ok(event) = event_rx.try_recv() {
+            if let AgentEvent::ToolResult { content, .. } = event {
+                assert_eq!(content, "[redacted]");
+            }
+        }
+    }
+
+    #[test]
+    fn test_skill_tool_allowlist() {
+        let research = rusty_claw_core::skills::SkillDefinition {
+            name: "research".into(),
+            description: "Research a topic on the web".into(),
+            system_prompt: String::new(),
+            tools: vec!["web_search".into(), "web_fetch".into()],
+            tags: vec![],
+            examples: vec![],
+            file_path: Default::default(),
+        };
+        let mut config = Config::default();
+
+        let allowed = skill_tool_allowlist(&config, &[&research]).unwrap();
+        assert!(allowed.contains("web_search"));
+        assert!(allowed.contains("memory_search"));
+        assert!(!allowed.contains("exec"));
+
+        // No skills, or skills without tool lists, restrict nothing
+        assert!(skill_tool_allowlist(&config, &[]).is_none());
+        let open = rusty_claw_core::skills::SkillDefinition {
+            tools: vec![],
+            ..research.clone()
+        };
+        assert!(skill_tool_allowlist(&config, &[&open]).is_none());
+
+        config.skills = Some(rusty_claw_core::config::SkillsConfig {
+            tool_mode: SkillToolMode::Augment,
+            ..Default::default()
+        });
+        assert!(skill_tool_allowlist(&config, &[&research]).is_none());
+    }
+}
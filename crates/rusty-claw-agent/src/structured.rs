@@ -0,0 +1,254 @@
+//! Structured output — run a turn that must answer with schema-valid JSON.
+//!
+//! The provider is asked for JSON via [`ResponseFormat::JsonSchema`]; the
+//! reply is then checked here as well, since not every backend enforces the
+//! schema strictly. An invalid reply is retried once with the validation
+//! error sent back to the model.
+
+use std::sync::Arc;
+
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use rusty_claw_core::config::Config;
+use rusty_claw_core::session::Session;
+use rusty_claw_core::types::InboundMessage;
+use rusty_claw_plugins::HookRegistry;
+use rusty_claw_providers::{Credentials, LlmProvider, ResponseFormat};
+use rusty_claw_tools::ToolRegistry;
+
+use crate::runtime::run_agent_with_options;
+use crate::{AgentEvent, AgentRunOptions, AgentRunResult};
+
+/// A validated structured reply and the run that produced it.
+#[derive(Debug, Clone)]
+pub struct StructuredOutput {
+    pub value: Value,
+    pub result: AgentRunResult,
+}
+
+/// Run one agent turn constrained to `schema`, validating the reply and
+/// retrying once with the validation error if it does not conform.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_agent_structured(
+    session: &mut Session,
+    message: InboundMessage,
+    config: &Arc<Config>,
+    tools: &ToolRegistry,
+    provider: &dyn LlmProvider,
+    credentials: &Credentials,
+    event_tx: mpsc::UnboundedSender<AgentEvent>,
+    hooks: &Arc<HookRegistry>,
+    schema: &Value,
+) -> anyhow::Result<StructuredOutput> {
+    let options = AgentRunOptions {
+        response_format: Some(ResponseFormat::JsonSchema {
+            schema: schema.clone(),
+        }),
+        ..Default::default()
+    };
+
+    let mut message = message;
+    let mut last_error = String::new();
+    for attempt in 0..2 {
+        let result = run_agent_with_options(
+            session,
+            message.clone(),
+            config,
+            tools,
+            provider,
+            credentials,
+            event_tx.clone(),
+            hooks,
+            options.clone(),
+        )
+        .await?;
+
+        if let Some(err) = &result.meta.error {
+            anyhow::bail!("Structured run failed: {}", err.message);
+        }
+
+        let text = result
+            .payloads
+            .first()
+            .and_then(|p| p.text.as_deref())
+            .unwrap_or("");
+        match parse_and_validate(text, schema) {
+            Ok(value) => return Ok(StructuredOutput { value, result }),
+            Err(e) => {
+                warn!(attempt, error = %e, "Structured output failed validation");
+                message.text = Some(format!(
+                    "Your previous reply was not valid: {e}. \
+                     Reply again with only a JSON value that matches the schema."
+                ));
+                last_error = e;
+            }
+        }
+    }
+
+    anyhow::bail!("Structured output failed validation: {last_error}")
+}
+
+/// Parse `text` as JSON (tolerating a Markdown code fence) and validate it.
+pub fn parse_and_validate(text: &str, schema: &Value) -> Result<Value, String> {
+    let trimmed = text.trim();
+    let body = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .unwrap_or(trimmed)
+        .trim();
+    let value: Value = serde_json::from_str(body).map_err(|e| format!("invalid JSON: {e}"))?;
+    validate(&value, schema, "$")?;
+    Ok(value)
+}
+
+/// Check `value` against the common subset of JSON Schema: `type`, `enum`,
+/// `const`, `properties`, `required`, `additionalProperties`, `items` and
+/// `anyOf`. Unknown keywords are ignored.
+pub fn validate(value: &Value, schema: &Value, path: &str) -> Result<(), String> {
+    let Some(schema) = schema.as_object() else {
+        // `true`/`{}` accept anything; `false` accepts nothing
+        return match schema {
+            Value::Bool(false) => Err(format!("{path}: not allowed")),
+            _ => Ok(()),
+        };
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+            _ => vec![],
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| type_matches(value, t)) {
+            return Err(format!(
+                "{path}: expected {}, got {}",
+                allowed.join(" or "),
+                type_name(value)
+            ));
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(|v| v.as_array()) {
+        if !options.contains(value) {
+            return Err(format!("{path}: {value} is not one of {}", Value::from(options.clone())));
+        }
+    }
+    if let Some(constant) = schema.get("const") {
+        if constant != value {
+            return Err(format!("{path}: expected {constant}"));
+        }
+    }
+
+    if let Some(variants) = schema.get("anyOf").and_then(|v| v.as_array()) {
+        if !variants.iter().any(|s| validate(value, s, path).is_ok()) {
+            return Err(format!("{path}: does not match any allowed schema"));
+        }
+    }
+
+    if let Some(object) = value.as_object() {
+        if let Some(required) = schema.get("required").and_then(|v| v.as_array()) {
+            for key in required.iter().filter_map(|k| k.as_str()) {
+                if !object.contains_key(key) {
+                    return Err(format!("{path}: missing required property \"{key}\""));
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(|v| v.as_object());
+        for (key, child) in object {
+            let child_path = format!("{path}.{key}");
+            match properties.and_then(|p| p.get(key)) {
+                Some(child_schema) => validate(child, child_schema, &child_path)?,
+                None => {
+                    if let Some(extra) = schema.get("additionalProperties") {
+                        validate(child, extra, &child_path)?;
+                    }
+                }
+            }
+        }
+    }
+
+    if let (Some(items), Some(array)) = (schema.get("items"), value.as_array()) {
+        for (i, item) in array.iter().enumerate() {
+            validate(item, items, &format!("{path}[{i}]"))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn type_matches(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn person_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "age": { "type": "integer" },
+                "tags": { "type": "array", "items": { "type": "string" } },
+                "role": { "enum": ["admin", "user"] }
+            },
+            "required": ["name"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn test_valid_object_passes() {
+        let value = json!({"name": "Ada", "age": 36, "tags": ["math"], "role": "admin"});
+        assert!(validate(&value, &person_schema(), "$").is_ok());
+    }
+
+    #[test]
+    fn test_validation_errors_name_the_path() {
+        let schema = person_schema();
+        let err = validate(&json!({"age": 3}), &schema, "$").unwrap_err();
+        assert!(err.contains("missing required property \"name\""), "{err}");
+
+        let err = validate(&json!({"name": "x", "tags": ["a", 1]}), &schema, "$").unwrap_err();
+        assert_eq!(err, "$.tags[1]: expected string, got number");
+
+        let err = validate(&json!({"name": "x", "extra": 1}), &schema, "$").unwrap_err();
+        assert!(err.starts_with("$.extra"), "{err}");
+
+        let err = validate(&json!({"name": "x", "role": "root"}), &schema, "$").unwrap_err();
+        assert!(err.contains("is not one of"), "{err}");
+    }
+
+    #[test]
+    fn test_parse_and_validate_strips_code_fence() {
+        let schema = json!({"type": "object", "required": ["ok"]});
+        let value = parse_and_validate("```json\n{\"ok\": true}\n```", &schema).unwrap();
+        assert_eq!(value["ok"], true);
+        assert!(parse_and_validate("not json", &schema).unwrap_err().starts_with("invalid JSON"));
+    }
+}
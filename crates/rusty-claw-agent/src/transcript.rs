@@ -91,6 +91,59 @@ fn content_block_to_json(block: &ContentBlock) -> serde_json::Value {
     }
 }
 
+// ============================================================
+// Tool pairing
+// ============================================================
+
+/// IDs of tool uses issued by an entry.
+pub fn tool_use_ids(entry: &TranscriptEntry) -> Vec<&str> {
+    match entry {
+        TranscriptEntry::Assistant { content, .. } => content
+            .iter()
+            .filter_map(|b| match b {
+                ContentBlock::ToolUse { id, .. } => Some(id.as_str()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// IDs of tool uses answered by an entry.
+pub fn tool_result_ids(entry: &TranscriptEntry) -> Vec<&str> {
+    match entry {
+        TranscriptEntry::ToolResult { tool_use_id, .. } => vec![tool_use_id.as_str()],
+        TranscriptEntry::User { content, .. } => content
+            .iter()
+            .filter_map(|b| match b {
+                ContentBlock::ToolResult { tool_use_id, .. } => Some(tool_use_id.as_str()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Tool IDs that break pairing: uses never answered by a later result, and
+/// results with no earlier use. Providers reject transcripts with either.
+pub fn orphaned_tool_ids(transcript: &[TranscriptEntry]) -> Vec<String> {
+    let mut pending: Vec<&str> = Vec::new();
+    let mut orphans = Vec::new();
+    for entry in transcript {
+        pending.extend(tool_use_ids(entry));
+        for id in tool_result_ids(entry) {
+            match pending.iter().position(|p| *p == id) {
+                Some(i) => {
+                    pending.remove(i);
+                }
+                None => orphans.push(id.to_string()),
+            }
+        }
+    }
+    orphans.extend(pending.into_iter().map(String::from));
+    orphans
+}
+
 // ============================================================
 // Token estimation
 // ============================================================
@@ -170,6 +223,9 @@ mod tests {
             TranscriptEntry::Assistant {
                 content: vec![ContentBlock::Text { text: "Hi! How can I help you?".into() }],
                 usage: None,
+                thinking: None,
+                thinking_signature: None,
+                thinking_blocks: Vec::new(),
                 timestamp: Utc::now(),
             },
         ];
@@ -283,6 +339,9 @@ mod tests {
                     input: json!({"command": "ls"}),
                 }],
                 usage: None,
+                thinking: None,
+                thinking_signature: None,
+                thinking_blocks: Vec::new(),
                 timestamp: Utc::now(),
             },
             TranscriptEntry::ToolCall {
@@ -6,4 +6,4 @@
 pub mod config;
 pub mod pool;
 
-pub use pool::BrowserPool;
+pub use pool::{BrowserPool, Screenshot, ScreenshotOptions};
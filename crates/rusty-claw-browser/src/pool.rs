@@ -1,7 +1,12 @@
 //! Browser pool — manages Chrome/Chromium instances for automation.
+//!
+//! Every session gets its own profile so cookies and localStorage never leak
+//! between users: an isolated browser context in a shared Chrome, or, with
+//! `profiles_dir` set, a dedicated Chrome on a persistent user-data-dir.
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use tokio::sync::RwLock;
 #[cfg(feature = "browser")]
@@ -18,6 +23,74 @@ pub struct PageInfo {
     pub url: String,
 }
 
+/// What a screenshot captures.
+#[derive(Debug, Clone, Default)]
+pub struct ScreenshotOptions {
+    /// Capture the whole scrollable page rather than the viewport.
+    pub full_page: bool,
+    /// Capture only the element matching this CSS selector.
+    pub selector: Option<String>,
+}
+
+/// An encoded screenshot.
+#[derive(Debug, Clone)]
+pub struct Screenshot {
+    pub data: Vec<u8>,
+    pub mime_type: &'static str,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Scale that fits a `width` x `height` capture within `max_dimension`
+/// pixels on its longest side, never scaling up.
+pub fn clip_scale(width: f64, height: f64, max_dimension: u32) -> f64 {
+    let longest = width.max(height);
+    if longest <= max_dimension as f64 {
+        1.0
+    } else {
+        max_dimension as f64 / longest
+    }
+}
+
+/// Directory name of a session's persistent profile. Bytes other than ASCII
+/// alphanumerics and `-` are hex-escaped, so distinct keys never collide.
+pub fn profile_dir_name(session_id: &str) -> String {
+    let mut name = String::with_capacity(session_id.len());
+    for byte in session_id.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' {
+            name.push(byte as char);
+        } else {
+            name.push_str(&format!("_{byte:02x}"));
+        }
+    }
+    name
+}
+
+/// When each session last used its page, so idle sessions can be closed.
+#[derive(Default)]
+struct IdleTracker(std::sync::Mutex<HashMap<String, Instant>>);
+
+impl IdleTracker {
+    fn touch(&self, session_id: &str) {
+        self.0.lock().unwrap().insert(session_id.to_string(), Instant::now());
+    }
+
+    fn forget(&self, session_id: &str) {
+        self.0.lock().unwrap().remove(session_id);
+    }
+
+    /// Sessions unused for longer than `timeout`, other than `current`.
+    fn idle(&self, timeout: Duration, current: &str) -> Vec<String> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(id, used)| id.as_str() != current && used.elapsed() > timeout)
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+}
+
 // ============================================================
 // Real chromiumoxide implementation
 // ============================================================
@@ -25,16 +98,39 @@ pub struct PageInfo {
 mod real {
     use super::*;
     use chromiumoxide::browser::{Browser, BrowserConfig as CdpConfig};
+    use chromiumoxide::cdp::browser_protocol::browser::BrowserContextId;
+    use chromiumoxide::cdp::browser_protocol::page::{CaptureScreenshotFormat, Viewport};
+    use chromiumoxide::cdp::browser_protocol::target::{
+        CreateBrowserContextParams, CreateTargetParams,
+    };
+    use chromiumoxide::element::Element;
     use chromiumoxide::page::ScreenshotParams;
     use chromiumoxide::Page;
     use futures::StreamExt;
-    use std::time::Duration;
+    use std::path::Path;
+
+    /// Reports a filled field's state; password values are not echoed.
+    const FIELD_STATE_JS: &str = "function() { return JSON.stringify({ \
+        tag: this.tagName.toLowerCase(), type: this.type || null, \
+        value: this.type === 'password' ? null : this.value, \
+        length: (this.value || '').length }); }";
+
+    /// Where a session's pages live.
+    enum Profile {
+        /// Isolated context in the shared browser; disposed with the page.
+        Context(BrowserContextId),
+        /// Dedicated browser on the session's persistent user-data-dir.
+        Persistent(Box<Browser>),
+    }
 
     /// A pool of browser pages backed by real Chrome DevTools Protocol.
     pub struct BrowserPool {
         config: BrowserConfig,
+        /// Shared browser for in-memory profiles.
         browser: RwLock<Option<Browser>>,
+        profiles: RwLock<HashMap<String, Profile>>,
         pages: Arc<RwLock<HashMap<String, Page>>>,
+        last_used: IdleTracker,
     }
 
     impl BrowserPool {
@@ -42,7 +138,9 @@ mod real {
             Self {
                 config,
                 browser: RwLock::new(None),
+                profiles: RwLock::new(HashMap::new()),
                 pages: Arc::new(RwLock::new(HashMap::new())),
+                last_used: IdleTracker::default(),
             }
         }
 
@@ -50,14 +148,19 @@ mod real {
             self.config.max_pages
         }
 
-        /// Lazily launch Chrome if not already running.
+        /// Lazily launch the shared Chrome if not already running.
         async fn ensure_browser(&self) -> anyhow::Result<()> {
             let mut browser_guard = self.browser.write().await;
             if browser_guard.is_some() {
                 return Ok(());
             }
+            *browser_guard = Some(self.launch(None).await?);
+            Ok(())
+        }
 
-            info!("Launching headless Chrome via CDP");
+        /// Launch a Chrome instance, on `user_data_dir` if given.
+        async fn launch(&self, user_data_dir: Option<&Path>) -> anyhow::Result<Browser> {
+            info!(?user_data_dir, "Launching headless Chrome via CDP");
 
             let mut builder = CdpConfig::builder();
             if self.config.headless {
@@ -71,6 +174,9 @@ mod real {
             if let Some(ref path) = self.config.chrome_path {
                 builder = builder.chrome_executable(path);
             }
+            if let Some(dir) = user_data_dir {
+                builder = builder.user_data_dir(dir);
+            }
 
             let cdp_config = builder
                 .build()
@@ -91,16 +197,118 @@ mod real {
                 debug!("CDP handler finished");
             });
 
-            *browser_guard = Some(browser);
+            Ok(browser)
+        }
+
+        /// Open a page in the session's profile, creating the profile on
+        /// first use.
+        async fn open_page(&self, session_id: &str, url: &str) -> anyhow::Result<Page> {
+            if !self.profiles.read().await.contains_key(session_id) {
+                // Launch without the lock held so other sessions aren't blocked
+                let profile = self.create_profile(session_id).await?;
+                let mut profiles = self.profiles.write().await;
+                if profiles.contains_key(session_id) {
+                    drop(profiles);
+                    self.release(Some(profile)).await?;
+                } else {
+                    profiles.insert(session_id.to_string(), profile);
+                }
+            }
+
+            let profiles = self.profiles.read().await;
+            let page = match profiles.get(session_id) {
+                Some(Profile::Persistent(browser)) => browser.new_page(url).await,
+                Some(Profile::Context(id)) => {
+                    let params = CreateTargetParams::builder()
+                        .url(url)
+                        .browser_context_id(id.clone())
+                        .build()
+                        .map_err(|e| anyhow::anyhow!(e))?;
+                    let browser_guard = self.browser.read().await;
+                    let browser = browser_guard
+                        .as_ref()
+                        .ok_or_else(|| anyhow::anyhow!("Browser not launched"))?;
+                    browser.new_page(params).await
+                }
+                None => anyhow::bail!("Profile for session {session_id} was closed"),
+            };
+            page.map_err(|e| anyhow::anyhow!("Failed to create page: {e}"))
+        }
+
+        async fn create_profile(&self, session_id: &str) -> anyhow::Result<Profile> {
+            match self.config.profiles_path() {
+                Some(dir) => {
+                    let dir = dir.join(profile_dir_name(session_id));
+                    std::fs::create_dir_all(&dir)?;
+                    Ok(Profile::Persistent(Box::new(self.launch(Some(&dir)).await?)))
+                }
+                None => {
+                    let browser_guard = self.browser.read().await;
+                    let browser = browser_guard
+                        .as_ref()
+                        .ok_or_else(|| anyhow::anyhow!("Browser not launched"))?;
+                    let id = browser
+                        .create_browser_context(CreateBrowserContextParams::default())
+                        .await
+                        .map_err(|e| anyhow::anyhow!("Failed to create profile: {e}"))?;
+                    Ok(Profile::Context(id))
+                }
+            }
+        }
+
+        /// Dispose an in-memory profile, or shut down a persistent profile's
+        /// Chrome. Its files on disk are kept.
+        async fn release(&self, profile: Option<Profile>) -> anyhow::Result<()> {
+            match profile {
+                Some(Profile::Context(id)) => {
+                    let browser_guard = self.browser.read().await;
+                    if let Some(browser) = browser_guard.as_ref() {
+                        browser
+                            .dispose_browser_context(id)
+                            .await
+                            .map_err(|e| anyhow::anyhow!("Failed to clear profile: {e}"))?;
+                    }
+                }
+                Some(Profile::Persistent(mut browser)) => {
+                    let _ = browser.close().await;
+                    let _ = browser.wait().await;
+                }
+                None => {}
+            }
             Ok(())
         }
 
+        /// The session's open page, marking the session as in use.
+        fn page<'a>(
+            &self,
+            pages: &'a HashMap<String, Page>,
+            session_id: &str,
+        ) -> anyhow::Result<&'a Page> {
+            let page = pages
+                .get(session_id)
+                .ok_or_else(|| anyhow::anyhow!("No page open for session {session_id}"))?;
+            self.last_used.touch(session_id);
+            Ok(page)
+        }
+
+        /// Close sessions idle for longer than `idle_timeout_secs`.
+        async fn close_idle(&self, current: &str) {
+            let timeout = Duration::from_secs(self.config.idle_timeout_secs);
+            for session_id in self.last_used.idle(timeout, current) {
+                debug!(session_id, "Closing idle browser session");
+                self.close_page(&session_id).await;
+            }
+        }
+
         fn timeout_duration(&self) -> Duration {
             Duration::from_millis(self.config.timeout_ms)
         }
 
         pub async fn navigate(&self, session_id: &str, url: &str) -> anyhow::Result<PageInfo> {
-            self.ensure_browser().await?;
+            self.close_idle(session_id).await;
+            if self.config.profiles_dir.is_none() {
+                self.ensure_browser().await?;
+            }
 
             let mut pages = self.pages.write().await;
 
@@ -112,11 +320,7 @@ mod real {
             }
 
             info!(session_id, url, "Browser navigate");
-
-            let browser_guard = self.browser.read().await;
-            let browser = browser_guard
-                .as_ref()
-                .ok_or_else(|| anyhow::anyhow!("Browser not launched"))?;
+            self.last_used.touch(session_id);
 
             if let Some(existing) = pages.get(session_id) {
                 existing
@@ -138,10 +342,10 @@ mod real {
                     url: current_url,
                 })
             } else {
-                let new_page = tokio::time::timeout(self.timeout_duration(), browser.new_page(url))
-                    .await
-                    .map_err(|_| anyhow::anyhow!("Page creation timed out"))?
-                    .map_err(|e| anyhow::anyhow!("Failed to create page: {e}"))?;
+                let new_page =
+                    tokio::time::timeout(self.timeout_duration(), self.open_page(session_id, url))
+                        .await
+                        .map_err(|_| anyhow::anyhow!("Page creation timed out"))??;
 
                 let title = new_page.get_title().await.unwrap_or_default().unwrap_or_default();
                 let current_url = new_page
@@ -161,28 +365,112 @@ mod real {
             }
         }
 
-        pub async fn screenshot(&self, session_id: &str) -> anyhow::Result<Vec<u8>> {
+        /// Capture the viewport, the whole page, or one element, scaled to
+        /// fit `screenshot_max_dimension` and within `screenshot_max_bytes`.
+        pub async fn screenshot(
+            &self,
+            session_id: &str,
+            options: &ScreenshotOptions,
+        ) -> anyhow::Result<Screenshot> {
             let pages = self.pages.read().await;
-            let page = pages
-                .get(session_id)
-                .ok_or_else(|| anyhow::anyhow!("No page open for session {session_id}"))?;
+            let page = self.page(&pages, session_id)?;
 
-            let bytes = tokio::time::timeout(
-                self.timeout_duration(),
-                page.screenshot(ScreenshotParams::builder().build()),
-            )
-            .await
-            .map_err(|_| anyhow::anyhow!("Screenshot timed out"))?
-            .map_err(|e| anyhow::anyhow!("Screenshot failed: {e}"))?;
+            tokio::time::timeout(self.timeout_duration(), self.capture(page, options))
+                .await
+                .map_err(|_| anyhow::anyhow!("Screenshot timed out"))?
+        }
 
-            Ok(bytes)
+        async fn capture(
+            &self,
+            page: &Page,
+            options: &ScreenshotOptions,
+        ) -> anyhow::Result<Screenshot> {
+            // Region to capture, in page coordinates
+            let rect_js = match &options.selector {
+                Some(selector) => format!(
+                    "(() => {{ const el = document.querySelector({}); if (!el) return null; \
+                     const r = el.getBoundingClientRect(); return JSON.stringify({{ \
+                     x: r.left + window.scrollX, y: r.top + window.scrollY, \
+                     width: r.width, height: r.height }}); }})()",
+                    serde_json::to_string(selector)?
+                ),
+                None if options.full_page => "JSON.stringify({ x: 0, y: 0, \
+                     width: document.documentElement.scrollWidth, \
+                     height: document.documentElement.scrollHeight })"
+                    .to_string(),
+                None => "JSON.stringify({ x: window.scrollX, y: window.scrollY, \
+                     width: window.innerWidth, height: window.innerHeight })"
+                    .to_string(),
+            };
+            let rect: Option<String> = page
+                .evaluate(rect_js)
+                .await
+                .map_err(|e| anyhow::anyhow!("Screenshot failed: {e}"))?
+                .into_value()
+                .map_err(|e| anyhow::anyhow!("JS result conversion failed: {e:?}"))?;
+            let Some(rect) = rect else {
+                anyhow::bail!(
+                    "Element not found '{}'",
+                    options.selector.as_deref().unwrap_or_default()
+                );
+            };
+            let rect: serde_json::Value = serde_json::from_str(&rect)?;
+            let get = |key: &str| rect.get(key).and_then(|v| v.as_f64()).unwrap_or(0.0);
+            let (width, height) = (get("width"), get("height"));
+            if width < 1.0 || height < 1.0 {
+                anyhow::bail!("Nothing visible to capture");
+            }
+
+            let scale = clip_scale(width, height, self.config.screenshot_max_dimension);
+            let clip = Viewport {
+                x: get("x"),
+                y: get("y"),
+                width,
+                height,
+                scale,
+            };
+            let shot = |format: CaptureScreenshotFormat| {
+                let mut params = ScreenshotParams::builder()
+                    .format(format.clone())
+                    .clip(clip.clone())
+                    .capture_beyond_viewport(true);
+                if format == CaptureScreenshotFormat::Jpeg {
+                    params = params.quality(75);
+                }
+                page.screenshot(params.build())
+            };
+
+            let max_bytes = self.config.screenshot_max_bytes;
+            let mut data = shot(CaptureScreenshotFormat::Png)
+                .await
+                .map_err(|e| anyhow::anyhow!("Screenshot failed: {e}"))?;
+            let mut mime_type = "image/png";
+            if data.len() > max_bytes {
+                debug!(bytes = data.len(), "Screenshot too large as PNG, trying JPEG");
+                data = shot(CaptureScreenshotFormat::Jpeg)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Screenshot failed: {e}"))?;
+                mime_type = "image/jpeg";
+            }
+            if data.len() > max_bytes {
+                anyhow::bail!(
+                    "Screenshot is {} bytes, over the {max_bytes} byte limit; \
+                     capture an element with a selector instead",
+                    data.len()
+                );
+            }
+
+            Ok(Screenshot {
+                data,
+                mime_type,
+                width: (width * scale).round() as u32,
+                height: (height * scale).round() as u32,
+            })
         }
 
         pub async fn click(&self, session_id: &str, selector: &str) -> anyhow::Result<()> {
             let pages = self.pages.read().await;
-            let page = pages
-                .get(session_id)
-                .ok_or_else(|| anyhow::anyhow!("No page open for session {session_id}"))?;
+            let page = self.page(&pages, session_id)?;
 
             let element = tokio::time::timeout(
                 self.timeout_duration(),
@@ -200,15 +488,162 @@ mod real {
             Ok(())
         }
 
+        /// Poll until `selector` matches an element that is enabled and not
+        /// read-only, up to the configured timeout.
+        async fn wait_until_editable(
+            &self,
+            page: &Page,
+            selector: &str,
+        ) -> anyhow::Result<Element> {
+            let selector_js = serde_json::to_string(selector)?;
+            let check = format!(
+                "(() => {{ const el = document.querySelector({selector_js}); \
+                 return !!el && !el.disabled && !el.readOnly; }})()"
+            );
+            let deadline = tokio::time::Instant::now() + self.timeout_duration();
+            loop {
+                let ready = page
+                    .evaluate(check.as_str())
+                    .await
+                    .ok()
+                    .and_then(|r| r.into_value::<bool>().ok())
+                    .unwrap_or(false);
+                if ready {
+                    return page
+                        .find_element(selector)
+                        .await
+                        .map_err(|e| anyhow::anyhow!("Element not found '{selector}': {e}"));
+                }
+                if tokio::time::Instant::now() >= deadline {
+                    anyhow::bail!("Timed out waiting for editable element '{selector}'");
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+
+        /// Run a JS function on `element` returning `JSON.stringify(...)` of
+        /// its result, and parse it.
+        async fn element_json(
+            element: &Element,
+            function: &str,
+        ) -> anyhow::Result<serde_json::Value> {
+            let result = element
+                .call_js_fn(function, false)
+                .await
+                .map_err(|e| anyhow::anyhow!("JS evaluation failed: {e}"))?;
+            let json = result
+                .result
+                .value
+                .and_then(|v| v.as_str().map(String::from))
+                .ok_or_else(|| anyhow::anyhow!("JS returned no result"))?;
+            Ok(serde_json::from_str(&json)?)
+        }
+
+        /// Replace the value of an input or textarea by typing `value`, then
+        /// optionally submit its form. Returns the element's resulting state.
+        pub async fn fill(
+            &self,
+            session_id: &str,
+            selector: &str,
+            value: &str,
+            submit: bool,
+        ) -> anyhow::Result<serde_json::Value> {
+            let pages = self.pages.read().await;
+            let page = self.page(&pages, session_id)?;
+
+            let element = self.wait_until_editable(page, selector).await?;
+            let fill = async {
+                // Clear, then type so sites see real key events
+                element
+                    .call_js_fn(
+                        "function() { this.focus(); this.value = ''; \
+                         this.dispatchEvent(new Event('input', { bubbles: true })); }",
+                        false,
+                    )
+                    .await?;
+                element.type_str(value).await?;
+                let state = Self::element_json(&element, FIELD_STATE_JS).await?;
+                if submit {
+                    let submitted = element
+                        .call_js_fn(
+                            "function() { if (this.form) { this.form.requestSubmit(); \
+                             return true; } return false; }",
+                            false,
+                        )
+                        .await?
+                        .result
+                        .value
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false);
+                    if !submitted {
+                        element.press_key("Enter").await?;
+                    }
+                }
+                anyhow::Ok(state)
+            };
+            tokio::time::timeout(self.timeout_duration(), fill)
+                .await
+                .map_err(|_| anyhow::anyhow!("Fill timed out"))?
+                .map_err(|e| anyhow::anyhow!("Fill failed: {e}"))
+        }
+
+        /// Select the option of a `<select>` whose value or label matches
+        /// `option`. Returns the selected value and label.
+        pub async fn select(
+            &self,
+            session_id: &str,
+            selector: &str,
+            option: &str,
+        ) -> anyhow::Result<serde_json::Value> {
+            let pages = self.pages.read().await;
+            let page = self.page(&pages, session_id)?;
+
+            let element = self.wait_until_editable(page, selector).await?;
+            let option_js = serde_json::to_string(option)?;
+            let function = format!(
+                "function() {{
+                    if (this.tagName !== 'SELECT') {{
+                        return JSON.stringify({{ error: 'element is not a <select>' }});
+                    }}
+                    const wanted = {option_js};
+                    const options = Array.from(this.options);
+                    const match = options.find(o => o.value === wanted)
+                        || options.find(o => o.label.trim() === wanted.trim());
+                    if (!match) {{
+                        return JSON.stringify({{
+                            error: 'no matching option',
+                            options: options.map(o => ({{ value: o.value, label: o.label }})),
+                        }});
+                    }}
+                    this.value = match.value;
+                    this.dispatchEvent(new Event('input', {{ bubbles: true }}));
+                    this.dispatchEvent(new Event('change', {{ bubbles: true }}));
+                    return JSON.stringify({{ value: this.value, label: match.label }});
+                }}"
+            );
+            let state = tokio::time::timeout(
+                self.timeout_duration(),
+                Self::element_json(&element, &function),
+            )
+            .await
+            .map_err(|_| anyhow::anyhow!("Select timed out"))??;
+
+            if let Some(error) = state.get("error").and_then(|e| e.as_str()) {
+                match state.get("options") {
+                    Some(options) => anyhow::bail!("{error} for '{option}', available: {options}"),
+                    None => anyhow::bail!("{error}"),
+                }
+            }
+            Ok(state)
+        }
+
         pub async fn extract_text(
             &self,
             session_id: &str,
             selector: Option<&str>,
         ) -> anyhow::Result<String> {
             let pages = self.pages.read().await;
-            let page = pages
-                .get(session_id)
-                .ok_or_else(|| anyhow::anyhow!("No page open for session {session_id}"))?;
+            let page = self.page(&pages, session_id)?;
 
             let text = if let Some(sel) = selector {
                 let element = tokio::time::timeout(
@@ -243,9 +678,7 @@ mod real {
             expression: &str,
         ) -> anyhow::Result<serde_json::Value> {
             let pages = self.pages.read().await;
-            let page = pages
-                .get(session_id)
-                .ok_or_else(|| anyhow::anyhow!("No page open for session {session_id}"))?;
+            let page = self.page(&pages, session_id)?;
 
             let result = tokio::time::timeout(
                 self.timeout_duration(),
@@ -269,9 +702,7 @@ mod real {
             timeout_ms: Option<u64>,
         ) -> anyhow::Result<bool> {
             let pages = self.pages.read().await;
-            let page = pages
-                .get(session_id)
-                .ok_or_else(|| anyhow::anyhow!("No page open for session {session_id}"))?;
+            let page = self.page(&pages, session_id)?;
 
             let timeout =
                 std::time::Duration::from_millis(timeout_ms.unwrap_or(self.config.timeout_ms));
@@ -282,12 +713,42 @@ mod real {
             }
         }
 
+        /// Close the session's page and release its profile. An in-memory
+        /// profile is discarded; a persistent one stays on disk, so its
+        /// cookies are still there when the session navigates again.
         pub async fn close_page(&self, session_id: &str) {
             let mut pages = self.pages.write().await;
             if let Some(page) = pages.remove(session_id) {
                 let _ = page.close().await;
                 debug!(session_id, "Browser page closed");
             }
+            self.last_used.forget(session_id);
+            let profile = self.profiles.write().await.remove(session_id);
+            if let Err(e) = self.release(profile).await {
+                warn!(session_id, %e, "Failed to release browser profile");
+            }
+        }
+
+        /// Discard the session's cookies and storage by closing its page and
+        /// deleting its profile. The next navigation starts fresh.
+        pub async fn clear_cookies(&self, session_id: &str) -> anyhow::Result<()> {
+            let mut pages = self.pages.write().await;
+            if let Some(page) = pages.remove(session_id) {
+                let _ = page.close().await;
+            }
+            self.last_used.forget(session_id);
+
+            let profile = self.profiles.write().await.remove(session_id);
+            self.release(profile).await?;
+
+            if let Some(dir) = self.config.profiles_path() {
+                let dir = dir.join(profile_dir_name(session_id));
+                if dir.exists() {
+                    std::fs::remove_dir_all(&dir)?;
+                }
+            }
+            info!(session_id, "Browser profile cleared");
+            Ok(())
         }
 
         pub async fn active_pages(&self) -> usize {
@@ -307,6 +768,7 @@ mod stub {
     pub struct BrowserPool {
         config: BrowserConfig,
         pages: Arc<RwLock<HashMap<String, ()>>>,
+        last_used: IdleTracker,
     }
 
     impl BrowserPool {
@@ -314,6 +776,7 @@ mod stub {
             Self {
                 config,
                 pages: Arc::new(RwLock::new(HashMap::new())),
+                last_used: IdleTracker::default(),
             }
         }
 
@@ -322,6 +785,10 @@ mod stub {
         }
 
         pub async fn navigate(&self, session_id: &str, url: &str) -> anyhow::Result<PageInfo> {
+            let timeout = Duration::from_secs(self.config.idle_timeout_secs);
+            for idle in self.last_used.idle(timeout, session_id) {
+                self.close_page(&idle).await;
+            }
             let mut pages = self.pages.write().await;
 
             if pages.len() >= self.config.max_pages && !pages.contains_key(session_id) {
@@ -333,6 +800,7 @@ mod stub {
 
             info!(session_id, url, "Browser navigate (stub)");
             pages.entry(session_id.to_string()).or_insert(());
+            self.last_used.touch(session_id);
 
             Ok(PageInfo {
                 title: format!("Page: {url}"),
@@ -340,7 +808,11 @@ mod stub {
             })
         }
 
-        pub async fn screenshot(&self, _session_id: &str) -> anyhow::Result<Vec<u8>> {
+        pub async fn screenshot(
+            &self,
+            _session_id: &str,
+            _options: &ScreenshotOptions,
+        ) -> anyhow::Result<Screenshot> {
             anyhow::bail!("Browser feature not enabled — rebuild with --features browser")
         }
 
@@ -348,6 +820,25 @@ mod stub {
             anyhow::bail!("Browser feature not enabled — rebuild with --features browser")
         }
 
+        pub async fn fill(
+            &self,
+            _session_id: &str,
+            _selector: &str,
+            _value: &str,
+            _submit: bool,
+        ) -> anyhow::Result<serde_json::Value> {
+            anyhow::bail!("Browser feature not enabled — rebuild with --features browser")
+        }
+
+        pub async fn select(
+            &self,
+            _session_id: &str,
+            _selector: &str,
+            _option: &str,
+        ) -> anyhow::Result<serde_json::Value> {
+            anyhow::bail!("Browser feature not enabled — rebuild with --features browser")
+        }
+
         pub async fn extract_text(
             &self,
             _session_id: &str,
@@ -378,6 +869,12 @@ mod stub {
             if pages.remove(session_id).is_some() {
                 debug!(session_id, "Browser page closed (stub)");
             }
+            self.last_used.forget(session_id);
+        }
+
+        pub async fn clear_cookies(&self, session_id: &str) -> anyhow::Result<()> {
+            self.close_page(session_id).await;
+            Ok(())
         }
 
         pub async fn active_pages(&self) -> usize {
@@ -402,9 +899,38 @@ mod tests {
             headless: true,
             max_pages: 5,
             timeout_ms: 30_000,
+            profiles_dir: None,
+            screenshot_max_dimension: 8000,
+            screenshot_max_bytes: 5 * 1024 * 1024,
+            idle_timeout_secs: 600,
         }
     }
 
+    #[test]
+    fn test_clip_scale_limits_longest_side() {
+        assert_eq!(clip_scale(1280.0, 720.0, 8000), 1.0);
+        // A very long page is scaled down to fit
+        let scale = clip_scale(1280.0, 40_000.0, 8000);
+        assert_eq!(scale, 0.2);
+        assert_eq!((1280.0 * scale) as u32, 256);
+    }
+
+    #[test]
+    fn test_profile_dir_name_is_unambiguous() {
+        assert_eq!(profile_dir_name("agent-main"), "agent-main");
+        assert_eq!(profile_dir_name("a:b"), "a_3ab");
+        assert_ne!(profile_dir_name("a:b"), profile_dir_name("a_b"));
+        assert_ne!(profile_dir_name("../x"), "../x");
+    }
+
+    #[tokio::test]
+    async fn test_pool_clear_cookies_closes_page() {
+        let pool = BrowserPool::new(default_config());
+        pool.navigate("s1", "https://a.com").await.unwrap();
+        pool.clear_cookies("s1").await.unwrap();
+        assert_eq!(pool.active_pages().await, 0);
+    }
+
     #[tokio::test]
     async fn test_pool_navigate() {
         let pool = BrowserPool::new(default_config());
@@ -424,6 +950,20 @@ mod tests {
         assert!(pool.navigate("s3", "https://c.com").await.is_err());
     }
 
+    #[tokio::test]
+    async fn test_pool_closes_idle_sessions() {
+        let mut config = default_config();
+        config.idle_timeout_secs = 0;
+        let pool = BrowserPool::new(config);
+        pool.navigate("s1", "https://a.com").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        // Navigating in another session closes the idle one
+        pool.navigate("s2", "https://b.com").await.unwrap();
+        assert_eq!(pool.active_pages().await, 1);
+        pool.navigate("s2", "https://c.com").await.unwrap();
+        assert_eq!(pool.active_pages().await, 1);
+    }
+
     #[tokio::test]
     async fn test_pool_close_page() {
         let pool = BrowserPool::new(default_config());
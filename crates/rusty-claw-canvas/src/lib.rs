@@ -6,5 +6,5 @@
 pub mod protocol;
 pub mod session;
 
-pub use protocol::{CanvasEvent, CanvasOperation};
+pub use protocol::{CanvasEvent, CanvasHandler, CanvasOperation, CanvasPatch, user_action_message};
 pub use session::CanvasSession;
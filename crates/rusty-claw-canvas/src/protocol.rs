@@ -2,12 +2,22 @@
 
 use serde::{Deserialize, Serialize};
 
+use rusty_claw_core::types::{ChatType, InboundMessage, Sender};
+
 /// Operations the agent can perform on a canvas session.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "action", rename_all = "snake_case")]
 pub enum CanvasOperation {
     /// Push HTML content to the canvas.
     Push { html: String },
+    /// Change an existing element in place, keeping the rest of the page
+    /// (scroll position, input focus) intact.
+    Patch { selector: String, patch: CanvasPatch },
+    /// Append an HTML fragment as the last child of the matched element.
+    AppendChild { selector: String, html: String },
+    /// Declare the element ids whose clicks and submissions the agent wants
+    /// to receive. Replaces any previous declaration.
+    DeclareHandlers { handlers: Vec<CanvasHandler> },
     /// Clear all canvas content.
     Reset,
     /// Evaluate JavaScript in the canvas context.
@@ -16,16 +26,145 @@ pub enum CanvasOperation {
     Snapshot,
 }
 
-/// Events sent to connected canvas clients.
+/// A targeted change to the element matched by a CSS selector.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CanvasPatch {
+    /// Replace the element's inner HTML.
+    Html { html: String },
+    /// Set an attribute, or remove it when `value` is absent.
+    Attribute {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        value: Option<String>,
+    },
+}
+
+/// A named action the agent can receive from the canvas.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CanvasHandler {
+    /// Element id the client reports actions for.
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Events exchanged with canvas clients. All variants are sent to clients
+/// except `UserAction`, which clients send back.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum CanvasEvent {
     /// A new HTML component was added.
     ComponentAdded { index: usize, html: String },
+    /// Apply `patch` to the first element matching `selector`.
+    Patched { selector: String, patch: CanvasPatch },
+    /// Append `html` to the first element matching `selector`.
+    ChildAppended { selector: String, html: String },
     /// Canvas was reset.
     Reset,
     /// JavaScript evaluation request.
     Eval { js: String },
     /// Full snapshot of all components.
     Snapshot { components: Vec<String> },
+    /// The element ids clients should report actions for.
+    HandlersDeclared { handlers: Vec<CanvasHandler> },
+    /// A click or form submission on a declared element (client → gateway).
+    /// `session_key` is set by the gateway from the connection, never
+    /// trusted from the client.
+    UserAction {
+        #[serde(default)]
+        session_key: String,
+        element_id: String,
+        #[serde(default)]
+        value: serde_json::Value,
+    },
+    /// A `UserAction` was not delivered to the agent.
+    ActionRejected { element_id: String, reason: String },
+}
+
+/// Build the inbound message that carries a user action into the agent
+/// session. The text is for the model; `raw` keeps the structured action.
+pub fn user_action_message(
+    session_key: &str,
+    element_id: &str,
+    value: &serde_json::Value,
+) -> InboundMessage {
+    let mut text = format!("[canvas action] {element_id}");
+    match value {
+        serde_json::Value::Null => {}
+        serde_json::Value::String(s) => text.push_str(&format!(": {s}")),
+        other => text.push_str(&format!(": {other}")),
+    }
+    let mut message = InboundMessage::from_cli_text(&text);
+    message.channel = "canvas".into();
+    message.account_id = session_key.to_string();
+    message.sender = Sender {
+        id: format!("canvas:{session_key}"),
+        display_name: Some("Canvas".into()),
+        username: None,
+    };
+    message.chat_type = ChatType::Dm;
+    message.raw = Some(serde_json::json!({
+        "type": "user_action",
+        "session_key": session_key,
+        "element_id": element_id,
+        "value": value,
+    }));
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patch_operation_wire_format() {
+        let op: CanvasOperation = serde_json::from_str(
+            r##"{"action":"patch","selector":"#status","patch":{"kind":"attribute","name":"hidden"}}"##,
+        )
+        .unwrap();
+        match op {
+            CanvasOperation::Patch { selector, patch } => {
+                assert_eq!(selector, "#status");
+                assert_eq!(
+                    patch,
+                    CanvasPatch::Attribute {
+                        name: "hidden".into(),
+                        value: None
+                    }
+                );
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+
+        let event = CanvasEvent::ChildAppended {
+            selector: "#log".into(),
+            html: "<li>step</li>".into(),
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({"type": "child_appended", "selector": "#log", "html": "<li>step</li>"})
+        );
+    }
+
+    #[test]
+    fn test_user_action_message() {
+        let action: CanvasEvent = serde_json::from_str(
+            r#"{"type":"user_action","element_id":"signup","value":{"email":"a@b.c"}}"#,
+        )
+        .unwrap();
+        let CanvasEvent::UserAction { element_id, value, .. } = action else {
+            panic!("expected user_action");
+        };
+
+        let message = user_action_message("abc123", &element_id, &value);
+        assert_eq!(message.channel, "canvas");
+        assert_eq!(message.text.as_deref(), Some(r#"[canvas action] signup: {"email":"a@b.c"}"#));
+        let raw = message.raw.unwrap();
+        assert_eq!(raw["session_key"], "abc123");
+        assert_eq!(raw["value"]["email"], "a@b.c");
+
+        let click = user_action_message("abc123", "refresh", &serde_json::Value::Null);
+        assert_eq!(click.text.as_deref(), Some("[canvas action] refresh"));
+    }
 }
@@ -2,6 +2,8 @@
 
 use chrono::{DateTime, Utc};
 
+use crate::protocol::{CanvasEvent, CanvasHandler, CanvasOperation};
+
 /// A canvas session tracks components and connected clients.
 #[derive(Debug, Clone)]
 pub struct CanvasSession {
@@ -9,6 +11,11 @@ pub struct CanvasSession {
     pub components: Vec<String>,
     pub created_at: DateTime<Utc>,
     pub last_updated: DateTime<Utc>,
+    /// Content-changing events since the last reset, in order, so a client
+    /// connecting later can rebuild the same DOM.
+    history: Vec<CanvasEvent>,
+    /// Actions the agent declared it can receive. Kept across resets.
+    handlers: Vec<CanvasHandler>,
 }
 
 impl CanvasSession {
@@ -19,23 +26,88 @@ impl CanvasSession {
             components: Vec::new(),
             created_at: now,
             last_updated: now,
+            history: Vec::new(),
+            handlers: Vec::new(),
         }
     }
 
     pub fn push(&mut self, html: String) {
-        self.components.push(html);
-        self.last_updated = Utc::now();
+        self.apply(CanvasOperation::Push { html });
     }
 
     pub fn reset(&mut self) {
-        self.components.clear();
+        self.apply(CanvasOperation::Reset);
+    }
+
+    /// Apply an operation and return the event to broadcast to clients.
+    pub fn apply(&mut self, op: CanvasOperation) -> CanvasEvent {
+        let event = match op {
+            CanvasOperation::Push { html } => {
+                self.components.push(html.clone());
+                CanvasEvent::ComponentAdded {
+                    index: self.components.len() - 1,
+                    html,
+                }
+            }
+            CanvasOperation::Patch { selector, patch } => CanvasEvent::Patched { selector, patch },
+            CanvasOperation::AppendChild { selector, html } => {
+                CanvasEvent::ChildAppended { selector, html }
+            }
+            CanvasOperation::Reset => {
+                self.components.clear();
+                self.history.clear();
+                CanvasEvent::Reset
+            }
+            CanvasOperation::DeclareHandlers { handlers } => {
+                self.handlers = handlers.clone();
+                self.last_updated = Utc::now();
+                return CanvasEvent::HandlersDeclared { handlers };
+            }
+            CanvasOperation::Eval { js } => return CanvasEvent::Eval { js },
+            CanvasOperation::Snapshot => {
+                return CanvasEvent::Snapshot {
+                    components: self.components.clone(),
+                };
+            }
+        };
+        if !matches!(event, CanvasEvent::Reset) {
+            self.history.push(event.clone());
+        }
         self.last_updated = Utc::now();
+        event
+    }
+
+    /// Events that bring a freshly connected client up to date: a snapshot
+    /// of the components pushed before the first incremental change, then
+    /// every later event in order.
+    pub fn replay(&self) -> Vec<CanvasEvent> {
+        let split = self
+            .history
+            .iter()
+            .position(|e| !matches!(e, CanvasEvent::ComponentAdded { .. }))
+            .unwrap_or(self.history.len());
+        let mut events = vec![CanvasEvent::Snapshot {
+            components: self.components[..split].to_vec(),
+        }];
+        events.extend(self.history[split..].iter().cloned());
+        if !self.handlers.is_empty() {
+            events.push(CanvasEvent::HandlersDeclared {
+                handlers: self.handlers.clone(),
+            });
+        }
+        events
+    }
+
+    /// Whether the agent declared a handler for `element_id`.
+    pub fn accepts_action(&self, element_id: &str) -> bool {
+        self.handlers.iter().any(|h| h.id == element_id)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::protocol::CanvasPatch;
 
     #[test]
     fn test_canvas_session() {
@@ -51,4 +123,52 @@ mod tests {
         session.reset();
         assert!(session.components.is_empty());
     }
+
+    #[test]
+    fn test_replay_keeps_patch_order() {
+        let mut session = CanvasSession::new("test-2".to_string());
+        session.push("<ul id=\"log\"></ul>".to_string());
+        session.apply(CanvasOperation::AppendChild {
+            selector: "#log".into(),
+            html: "<li>one</li>".into(),
+        });
+        session.push("<p id=\"status\">working</p>".to_string());
+        session.apply(CanvasOperation::Patch {
+            selector: "#status".into(),
+            patch: CanvasPatch::Html { html: "done".into() },
+        });
+
+        let replay = session.replay();
+        assert_eq!(replay.len(), 4);
+        match &replay[0] {
+            CanvasEvent::Snapshot { components } => assert_eq!(components.len(), 1),
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(matches!(replay[1], CanvasEvent::ChildAppended { .. }));
+        assert!(matches!(replay[2], CanvasEvent::ComponentAdded { index: 1, .. }));
+        assert!(matches!(replay[3], CanvasEvent::Patched { .. }));
+
+        session.reset();
+        assert_eq!(session.replay().len(), 1);
+    }
+
+    #[test]
+    fn test_declared_handlers() {
+        let mut session = CanvasSession::new("test-3".to_string());
+        assert!(!session.accepts_action("submit"));
+
+        session.apply(CanvasOperation::DeclareHandlers {
+            handlers: vec![CanvasHandler {
+                id: "submit".into(),
+                description: Some("Signup form".into()),
+            }],
+        });
+        session.reset();
+        assert!(session.accepts_action("submit"));
+        assert!(!session.accepts_action("delete-all"));
+        assert!(matches!(
+            session.replay().last(),
+            Some(CanvasEvent::HandlersDeclared { .. })
+        ));
+    }
 }
@@ -153,9 +153,11 @@ impl Channel for BlueBubblesChannel {
                                                 text: Some(text),
                                                 media: vec![],
                                                 reply_to: None,
+                                                message_id: None,
                                                 thread_id: None,
                                                 timestamp: chrono::Utc::now(),
                                                 raw: None,
+                                                reaction: None,
                                             };
                                             let _ = inbound_tx.send(msg);
                                         }
@@ -193,6 +195,7 @@ impl Channel for BlueBubblesChannel {
         if text.is_empty() {
             return Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: true,
                 error: None,
             });
@@ -214,6 +217,7 @@ impl Channel for BlueBubblesChannel {
         match resp {
             Ok(r) if r.status().is_success() => Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: true,
                 error: None,
             }),
@@ -222,12 +226,14 @@ impl Channel for BlueBubblesChannel {
                 error!(%status, "BlueBubbles send failed");
                 Ok(SendResult {
                     message_id: None,
+                    message_ids: vec![],
                     success: false,
                     error: Some(format!("BlueBubbles API error {status}")),
                 })
             }
             Err(e) => Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: false,
                 error: Some(e.to_string()),
             }),
@@ -2,19 +2,72 @@
 //!
 //! Supports DMs, guilds, and threads via the Discord HTTP API.
 //! The bot token comes from config.
+//!
+//! Slash commands are registered through the application commands API on
+//! startup; their interactions arrive over the Gateway WebSocket. Each one is
+//! deferred immediately and the agent's reply is delivered as the follow-up.
+//!
+//! Plain messages (DMs, and guild messages that mention the bot) arrive over
+//! the same connection. Replies reference the triggering message, or with
+//! `reply_in_threads` open a thread on it and continue the conversation there.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
 use tokio::sync::mpsc;
-use tracing::{error, info};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, error, info, warn};
 
+use rusty_claw_core::config::{ChannelRateLimitConfig, DiscordCommandAction, DiscordCommandConfig};
 use rusty_claw_core::types::{
-    ChatType, InboundMessage, OutboundMessage, SendResult, SendTarget,
+    ChatType, InboundMessage, OutboundMessage, Reaction, SendResult, SendTarget, Sender,
 };
 
+use crate::rate_limit::RateLimiter;
 use crate::{
     Channel, ChannelCapabilities, ChannelHandle, ChannelMeta, ChannelStatus, InboundReceiver,
+    InboundSender,
 };
 
+const API_BASE: &str = "https://discord.com/api/v10";
+const GATEWAY_URL: &str = "wss://gateway.discord.gg/?v=10&encoding=json";
+
+/// Interaction tokens can be used for follow-ups for 15 minutes.
+const INTERACTION_TOKEN_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Longest wait between Gateway reconnect attempts.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Gateway opcodes we handle.
+const OP_DISPATCH: u8 = 0;
+const OP_HEARTBEAT: u8 = 1;
+const OP_IDENTIFY: u8 = 2;
+const OP_RECONNECT: u8 = 7;
+const OP_INVALID_SESSION: u8 = 9;
+const OP_HELLO: u8 = 10;
+
+/// Interaction type for slash commands.
+const APPLICATION_COMMAND: u8 = 2;
+
+/// Gateway intents: GUILD_MESSAGES | GUILD_MESSAGE_REACTIONS | DIRECT_MESSAGES
+/// | DIRECT_MESSAGE_REACTIONS. Without the privileged MESSAGE_CONTENT intent,
+/// content is still delivered for DMs and mentions.
+const INTENTS: u64 = (1 << 9) | (1 << 10) | (1 << 12) | (1 << 13);
+/// MESSAGE_CONTENT, needed to read unmentioned messages in reply threads.
+const INTENT_MESSAGE_CONTENT: u64 = 1 << 15;
+
+/// Discord's limit on thread names.
+const MAX_THREAD_NAME: usize = 100;
+
+/// Default pacing: Discord allows 5 messages per 5 seconds per channel.
+const DEFAULT_MESSAGES_PER_SECOND: f64 = 1.0;
+const DEFAULT_BURST: u32 = 5;
+
 /// Discord channel configuration (typed).
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct DiscordConfig {
@@ -26,6 +79,10 @@ pub struct DiscordConfig {
     pub allowed_guilds: Vec<String>,
     #[serde(default)]
     pub allowed_users: Vec<String>,
+    #[serde(default)]
+    pub commands: Option<Vec<DiscordCommandConfig>>,
+    #[serde(default)]
+    pub reply_in_threads: bool,
 }
 
 impl DiscordConfig {
@@ -48,8 +105,16 @@ impl DiscordConfig {
 
 pub struct DiscordChannel {
     bot_token: String,
-    _allowed_guilds: Vec<String>,
-    _allowed_users: Vec<String>,
+    allowed_guilds: Vec<String>,
+    allowed_users: Vec<String>,
+    commands: Vec<DiscordCommandConfig>,
+    pending: PendingInteractions,
+    reply_in_threads: bool,
+    /// Threads the bot opened for conversations.
+    threads: Arc<Mutex<HashSet<String>>>,
+    /// Where recently sent messages can be edited, by message id.
+    edit_routes: Mutex<HashMap<String, EditRoute>>,
+    limiter: RateLimiter,
 }
 
 impl DiscordChannel {
@@ -60,10 +125,668 @@ impl DiscordChannel {
     ) -> Self {
         Self {
             bot_token,
-            _allowed_guilds: allowed_guilds,
-            _allowed_users: allowed_users,
+            allowed_guilds,
+            allowed_users,
+            commands: Vec::new(),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            reply_in_threads: false,
+            threads: Arc::new(Mutex::new(HashSet::new())),
+            edit_routes: Mutex::new(HashMap::new()),
+            limiter: RateLimiter::new(DEFAULT_MESSAGES_PER_SECOND, DEFAULT_BURST),
+        }
+    }
+
+    /// Override the default outbound pacing.
+    pub fn with_rate_limit(mut self, config: Option<&ChannelRateLimitConfig>) -> Self {
+        self.limiter = RateLimiter::from_config(config, DEFAULT_MESSAGES_PER_SECOND, DEFAULT_BURST);
+        self
+    }
+
+    /// Answer guild messages in a new thread per conversation.
+    pub fn with_reply_in_threads(mut self, enabled: bool) -> Self {
+        self.reply_in_threads = enabled;
+        self
+    }
+
+    /// Open a thread on `message_id`, returning the thread's channel id.
+    async fn start_thread(
+        &self,
+        client: &reqwest::Client,
+        channel_id: &str,
+        message_id: &str,
+        name: &str,
+    ) -> Result<String, String> {
+        let request = client
+            .post(format!(
+                "{API_BASE}/channels/{channel_id}/messages/{message_id}/threads"
+            ))
+            .header("Authorization", format!("Bot {}", self.bot_token))
+            .json(&json!({ "name": name, "auto_archive_duration": 1440 }));
+        let thread_id = send_discord_message(&self.limiter, request)
+            .await?
+            .ok_or_else(|| "Discord returned no thread id".to_string())?;
+        self.threads.lock().unwrap().insert(thread_id.clone());
+        Ok(thread_id)
+    }
+
+    /// Set the slash commands registered on startup.
+    pub fn with_commands(mut self, commands: Vec<DiscordCommandConfig>) -> Self {
+        self.commands = commands;
+        self
+    }
+
+    /// Take the deferred interaction awaiting a reply in this chat, if its
+    /// token is still valid.
+    fn take_pending(&self, target: &SendTarget) -> Option<PendingInteraction> {
+        let key = (target.account_id.clone(), target.chat_id.clone());
+        let pending = self.pending.lock().unwrap().remove(&key)?;
+        (pending.created.elapsed() < INTERACTION_TOKEN_TTL).then_some(pending)
+    }
+}
+
+/// A deferred slash command waiting for the agent's reply.
+#[derive(Debug, Clone)]
+struct PendingInteraction {
+    application_id: String,
+    token: String,
+    created: Instant,
+}
+
+/// How a sent message is edited: through its channel, or through the
+/// interaction webhook when it answered a slash command.
+#[derive(Debug, Clone, PartialEq)]
+enum EditRoute {
+    Channel(String),
+    Webhook { application_id: String, token: String },
+}
+
+/// Sent messages remembered for editing; the map is cleared when full.
+const MAX_EDIT_ROUTES: usize = 256;
+
+/// Deferred interactions keyed by (channel id, user id), which is how the
+/// router addresses the reply (`account_id`, `chat_id`).
+type PendingInteractions = Arc<Mutex<HashMap<(String, String), PendingInteraction>>>;
+
+/// Build the bulk-overwrite body for the application commands API.
+fn command_payload(commands: &[DiscordCommandConfig]) -> serde_json::Value {
+    let commands: Vec<serde_json::Value> = commands
+        .iter()
+        .map(|command| {
+            let options = match command.action {
+                DiscordCommandAction::Prompt => json!([{
+                    "type": 3, // STRING
+                    "name": "prompt",
+                    "description": "What to ask",
+                    "required": true,
+                }]),
+                DiscordCommandAction::Reset => json!([]),
+            };
+            json!({
+                "type": 1, // CHAT_INPUT
+                "name": command.name,
+                "description": command.description,
+                "options": options,
+            })
+        })
+        .collect();
+    serde_json::Value::Array(commands)
+}
+
+/// Register `commands` for the bot's application, replacing any existing set.
+async fn register_commands(
+    client: &reqwest::Client,
+    bot_token: &str,
+    commands: &[DiscordCommandConfig],
+) -> anyhow::Result<()> {
+    let auth = format!("Bot {bot_token}");
+    let app: serde_json::Value = client
+        .get(format!("{API_BASE}/applications/@me"))
+        .header("Authorization", &auth)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let app_id = app["id"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("applications/@me returned no id"))?;
+
+    client
+        .put(format!("{API_BASE}/applications/{app_id}/commands"))
+        .header("Authorization", &auth)
+        .json(&command_payload(commands))
+        .send()
+        .await?
+        .error_for_status()?;
+    info!(count = commands.len(), "Discord slash commands registered");
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct GatewayPayload {
+    op: u8,
+    #[serde(default)]
+    d: serde_json::Value,
+    #[serde(default)]
+    s: Option<u64>,
+    #[serde(default)]
+    t: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Interaction {
+    id: String,
+    application_id: String,
+    #[serde(rename = "type")]
+    kind: u8,
+    token: String,
+    #[serde(default)]
+    guild_id: Option<String>,
+    #[serde(default)]
+    channel_id: Option<String>,
+    /// Present for guild invocations.
+    #[serde(default)]
+    member: Option<InteractionMember>,
+    /// Present for DM invocations.
+    #[serde(default)]
+    user: Option<InteractionUser>,
+    #[serde(default)]
+    data: Option<CommandData>,
+}
+
+impl Interaction {
+    fn user(&self) -> Option<&InteractionUser> {
+        self.member.as_ref().map(|m| &m.user).or(self.user.as_ref())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct InteractionMember {
+    user: InteractionUser,
+}
+
+#[derive(Debug, Deserialize)]
+struct InteractionUser {
+    id: String,
+    username: String,
+    #[serde(default)]
+    global_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommandData {
+    name: String,
+    #[serde(default)]
+    options: Vec<CommandOption>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommandOption {
+    name: String,
+    #[serde(default)]
+    value: serde_json::Value,
+}
+
+/// Convert a slash command invocation into an inbound message. `/reset`-style
+/// commands become the text "/reset", which the router handles itself.
+fn interaction_to_inbound(
+    interaction: &Interaction,
+    commands: &[DiscordCommandConfig],
+) -> Option<InboundMessage> {
+    let data = interaction.data.as_ref()?;
+    let command = commands.iter().find(|c| c.name == data.name)?;
+    let text = match command.action {
+        DiscordCommandAction::Prompt => data
+            .options
+            .iter()
+            .find(|o| o.name == "prompt")?
+            .value
+            .as_str()?
+            .to_string(),
+        DiscordCommandAction::Reset => "/reset".to_string(),
+    };
+    let user = interaction.user()?;
+
+    Some(InboundMessage {
+        channel: "discord".into(),
+        account_id: interaction.channel_id.clone()?,
+        chat_type: if interaction.guild_id.is_some() {
+            ChatType::Group
+        } else {
+            ChatType::Dm
+        },
+        sender: Sender {
+            id: user.id.clone(),
+            display_name: user.global_name.clone(),
+            username: Some(user.username.clone()),
+        },
+        text: Some(text),
+        media: vec![],
+        reply_to: None,
+        message_id: None,
+        thread_id: None,
+        timestamp: chrono::Utc::now(),
+        raw: Some(json!({
+            "interaction": { "id": interaction.id, "command": data.name }
+        })),
+        reaction: None,
+    })
+}
+
+/// A MESSAGE_CREATE payload.
+#[derive(Debug, Deserialize)]
+struct DiscordMessage {
+    id: String,
+    channel_id: String,
+    #[serde(default)]
+    guild_id: Option<String>,
+    author: DiscordAuthor,
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    mentions: Vec<DiscordAuthor>,
+    #[serde(default)]
+    message_reference: Option<MessageReference>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscordAuthor {
+    id: String,
+    #[serde(default)]
+    username: String,
+    #[serde(default)]
+    global_name: Option<String>,
+    #[serde(default)]
+    bot: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageReference {
+    #[serde(default)]
+    message_id: Option<String>,
+}
+
+/// Convert a message into an inbound message. In guild channels only
+/// messages that mention the bot are taken, except inside the bot's own
+/// conversation threads; the mention itself is stripped from the text.
+fn message_to_inbound(
+    message: &DiscordMessage,
+    bot_user_id: Option<&str>,
+    threads: &HashSet<String>,
+) -> Option<InboundMessage> {
+    if message.author.bot {
+        return None;
+    }
+    let in_thread = threads.contains(&message.channel_id);
+    let mut text = message.content.clone();
+    if let Some(bot_id) = bot_user_id {
+        let mentioned = message.mentions.iter().any(|m| m.id == bot_id);
+        if message.guild_id.is_some() && !in_thread && !mentioned {
+            return None;
+        }
+        text = text
+            .replace(&format!("<@{bot_id}>"), "")
+            .replace(&format!("<@!{bot_id}>"), "");
+    }
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+
+    let chat_type = if message.guild_id.is_none() {
+        ChatType::Dm
+    } else if in_thread {
+        ChatType::Thread
+    } else {
+        ChatType::Group
+    };
+    Some(InboundMessage {
+        channel: "discord".into(),
+        account_id: message.channel_id.clone(),
+        chat_type,
+        sender: Sender {
+            id: message.author.id.clone(),
+            display_name: message.author.global_name.clone(),
+            username: Some(message.author.username.clone()),
+        },
+        text: Some(text.to_string()),
+        media: vec![],
+        reply_to: message
+            .message_reference
+            .as_ref()
+            .and_then(|r| r.message_id.clone()),
+        message_id: Some(message.id.clone()),
+        thread_id: in_thread.then(|| message.channel_id.clone()),
+        timestamp: chrono::Utc::now(),
+        raw: None,
+        reaction: None,
+    })
+}
+
+/// A MESSAGE_REACTION_ADD payload.
+#[derive(Debug, Deserialize)]
+struct ReactionEvent {
+    user_id: String,
+    channel_id: String,
+    message_id: String,
+    #[serde(default)]
+    guild_id: Option<String>,
+    #[serde(default)]
+    member: Option<InteractionMember>,
+    #[serde(default)]
+    message_author_id: Option<String>,
+    emoji: ReactionEmoji,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReactionEmoji {
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+/// Convert a reaction on one of the bot's messages into an inbound message.
+/// Custom emoji are reported as `:name:`.
+fn reaction_to_inbound(
+    event: &ReactionEvent,
+    bot_user_id: Option<&str>,
+    threads: &HashSet<String>,
+) -> Option<InboundMessage> {
+    if let Some(bot_id) = bot_user_id
+        && (event.user_id == bot_id
+            || event.message_author_id.as_deref().is_some_and(|author| author != bot_id))
+    {
+        return None;
+    }
+    let name = event.emoji.name.clone()?;
+    let emoji = match event.emoji.id {
+        Some(_) => format!(":{name}:"),
+        None => name,
+    };
+    let chat_type = if event.guild_id.is_none() {
+        ChatType::Dm
+    } else if threads.contains(&event.channel_id) {
+        ChatType::Thread
+    } else {
+        ChatType::Group
+    };
+    let user = event.member.as_ref().map(|m| &m.user);
+
+    Some(InboundMessage {
+        channel: "discord".into(),
+        account_id: event.channel_id.clone(),
+        chat_type,
+        sender: Sender {
+            id: event.user_id.clone(),
+            display_name: user.and_then(|u| u.global_name.clone()),
+            username: user.map(|u| u.username.clone()),
+        },
+        text: None,
+        media: vec![],
+        reply_to: None,
+        message_id: None,
+        thread_id: (chat_type == ChatType::Thread).then(|| event.channel_id.clone()),
+        timestamp: chrono::Utc::now(),
+        raw: None,
+        reaction: Some(Reaction {
+            emoji,
+            message_id: event.message_id.clone(),
+        }),
+    })
+}
+
+/// Thread name for a conversation: the first line of the reply, shortened.
+fn thread_name(text: &str) -> String {
+    let line = text.lines().find(|l| !l.trim().is_empty()).unwrap_or("Conversation").trim();
+    if line.chars().count() <= MAX_THREAD_NAME {
+        return line.to_string();
+    }
+    let mut name: String = line.chars().take(MAX_THREAD_NAME - 1).collect();
+    name.push('…');
+    name
+}
+
+/// State shared by the Gateway connection task.
+struct GatewayContext {
+    bot_token: String,
+    allowed_guilds: Vec<String>,
+    allowed_users: Vec<String>,
+    commands: Vec<DiscordCommandConfig>,
+    pending: PendingInteractions,
+    threads: Arc<Mutex<HashSet<String>>>,
+    intents: u64,
+    inbound_tx: InboundSender,
+    client: reqwest::Client,
+}
+
+impl GatewayContext {
+    fn is_allowed(&self, user_id: Option<&str>, guild_id: Option<&String>) -> bool {
+        let user_ok = self.allowed_users.is_empty()
+            || user_id.is_some_and(|id| self.allowed_users.iter().any(|u| u == id));
+        let guild_ok = match guild_id {
+            Some(guild) => self.allowed_guilds.is_empty() || self.allowed_guilds.contains(guild),
+            None => true,
+        };
+        user_ok && guild_ok
+    }
+
+    fn handle_reaction(&self, d: serde_json::Value, bot_user_id: Option<&str>) {
+        let event: ReactionEvent = match serde_json::from_value(d) {
+            Ok(e) => e,
+            Err(e) => {
+                debug!(%e, "Ignoring unparseable reaction");
+                return;
+            }
+        };
+        if !self.is_allowed(Some(&event.user_id), event.guild_id.as_ref()) {
+            return;
+        }
+        let inbound = {
+            let threads = self.threads.lock().unwrap();
+            reaction_to_inbound(&event, bot_user_id, &threads)
+        };
+        if let Some(inbound) = inbound {
+            let _ = self.inbound_tx.send(inbound);
+        }
+    }
+
+    fn handle_message(&self, d: serde_json::Value, bot_user_id: Option<&str>) {
+        let message: DiscordMessage = match serde_json::from_value(d) {
+            Ok(m) => m,
+            Err(e) => {
+                debug!(%e, "Ignoring unparseable message");
+                return;
+            }
+        };
+        if !self.is_allowed(Some(&message.author.id), message.guild_id.as_ref()) {
+            debug!(sender = %message.author.id, "Message from non-allowed user, ignoring");
+            return;
+        }
+        let inbound = {
+            let threads = self.threads.lock().unwrap();
+            message_to_inbound(&message, bot_user_id, &threads)
+        };
+        if let Some(inbound) = inbound {
+            let _ = self.inbound_tx.send(inbound);
         }
     }
+
+    async fn respond(&self, interaction: &Interaction, body: serde_json::Value) {
+        let url = format!(
+            "{API_BASE}/interactions/{}/{}/callback",
+            interaction.id, interaction.token
+        );
+        match self.client.post(url).json(&body).send().await {
+            Ok(r) if r.status().is_success() => {}
+            Ok(r) => warn!(status = %r.status(), "Discord interaction callback rejected"),
+            Err(e) => warn!(%e, "Discord interaction callback failed"),
+        }
+    }
+
+    async fn handle_interaction(&self, d: serde_json::Value) {
+        let interaction: Interaction = match serde_json::from_value(d) {
+            Ok(i) => i,
+            Err(e) => {
+                debug!(%e, "Ignoring unparseable interaction");
+                return;
+            }
+        };
+        if interaction.kind != APPLICATION_COMMAND {
+            return;
+        }
+        if !self.is_allowed(interaction.user().map(|u| u.id.as_str()), interaction.guild_id.as_ref()) {
+            // Ephemeral (flags 64) so only the caller sees it
+            self.respond(
+                &interaction,
+                json!({ "type": 4, "data": { "content": "You are not allowed to use this bot.", "flags": 64 } }),
+            )
+            .await;
+            return;
+        }
+        let Some(inbound) = interaction_to_inbound(&interaction, &self.commands) else {
+            self.respond(
+                &interaction,
+                json!({ "type": 4, "data": { "content": "Unknown command.", "flags": 64 } }),
+            )
+            .await;
+            return;
+        };
+
+        // Defer ("thinking...") so long agent runs don't hit the 3s deadline
+        self.respond(&interaction, json!({ "type": 5 })).await;
+        self.pending.lock().unwrap().insert(
+            (inbound.account_id.clone(), inbound.sender.id.clone()),
+            PendingInteraction {
+                application_id: interaction.application_id.clone(),
+                token: interaction.token.clone(),
+                created: Instant::now(),
+            },
+        );
+        let _ = self.inbound_tx.send(inbound);
+    }
+}
+
+/// Run one Gateway session until it closes or Discord asks us to reconnect.
+async fn run_gateway_session(ctx: &GatewayContext) -> anyhow::Result<()> {
+    let (ws, _) = tokio_tungstenite::connect_async(GATEWAY_URL).await?;
+    let (mut ws_tx, mut ws_rx) = ws.split();
+
+    let hello = loop {
+        match ws_rx.next().await {
+            Some(Ok(Message::Text(text))) => break serde_json::from_str::<GatewayPayload>(&text)?,
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Err(e.into()),
+            None => anyhow::bail!("Gateway closed before hello"),
+        }
+    };
+    if hello.op != OP_HELLO {
+        anyhow::bail!("Expected gateway hello, got op {}", hello.op);
+    }
+    let interval = hello.d["heartbeat_interval"].as_u64().unwrap_or(41_250);
+
+    let identify = json!({
+        "op": OP_IDENTIFY,
+        "d": {
+            "token": ctx.bot_token,
+            "intents": ctx.intents,
+            "properties": { "os": std::env::consts::OS, "browser": "rusty-claw", "device": "rusty-claw" },
+        }
+    });
+    ws_tx.send(Message::Text(identify.to_string().into())).await?;
+
+    let mut heartbeat = tokio::time::interval(Duration::from_millis(interval));
+    let mut seq: Option<u64> = None;
+    let mut bot_user_id: Option<String> = None;
+    loop {
+        tokio::select! {
+            _ = heartbeat.tick() => {
+                let beat = json!({ "op": OP_HEARTBEAT, "d": seq });
+                ws_tx.send(Message::Text(beat.to_string().into())).await?;
+            }
+            frame = ws_rx.next() => {
+                let text = match frame {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e.into()),
+                };
+                let payload: GatewayPayload = serde_json::from_str(&text)?;
+                if payload.s.is_some() {
+                    seq = payload.s;
+                }
+                match payload.op {
+                    OP_DISPATCH if payload.t.as_deref() == Some("INTERACTION_CREATE") => {
+                        ctx.handle_interaction(payload.d).await;
+                    }
+                    OP_DISPATCH if payload.t.as_deref() == Some("MESSAGE_CREATE") => {
+                        ctx.handle_message(payload.d, bot_user_id.as_deref());
+                    }
+                    OP_DISPATCH if payload.t.as_deref() == Some("MESSAGE_REACTION_ADD") => {
+                        ctx.handle_reaction(payload.d, bot_user_id.as_deref());
+                    }
+                    OP_DISPATCH if payload.t.as_deref() == Some("READY") => {
+                        bot_user_id = payload.d["user"]["id"].as_str().map(String::from);
+                        info!(user_id = ?bot_user_id, "Discord gateway ready");
+                    }
+                    OP_HEARTBEAT => {
+                        let beat = json!({ "op": OP_HEARTBEAT, "d": seq });
+                        ws_tx.send(Message::Text(beat.to_string().into())).await?;
+                    }
+                    OP_RECONNECT | OP_INVALID_SESSION => {
+                        info!(op = payload.op, "Discord gateway requested reconnect");
+                        return Ok(());
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Register commands, then keep a Gateway session open with backoff.
+async fn gateway_loop(ctx: GatewayContext) {
+    if !ctx.commands.is_empty()
+        && let Err(e) = register_commands(&ctx.client, &ctx.bot_token, &ctx.commands).await
+    {
+        warn!(%e, "Failed to register Discord slash commands");
+    }
+
+    let mut delay = Duration::from_secs(1);
+    loop {
+        match run_gateway_session(&ctx).await {
+            Ok(()) => delay = Duration::from_secs(1),
+            Err(e) => {
+                warn!(%e, retry_in = ?delay, "Discord gateway connection failed");
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+            }
+        }
+        if ctx.inbound_tx.is_closed() {
+            break;
+        }
+    }
+}
+
+/// Send one request that creates or edits a message, returning its id.
+/// Paced by `limiter`, which also retries Discord's 429s.
+async fn send_discord_message(
+    limiter: &RateLimiter,
+    request: reqwest::RequestBuilder,
+) -> Result<Option<String>, String> {
+    match limiter.send(request).await {
+        Ok(r) if r.status().is_success() => {
+            let body: serde_json::Value = r.json().await.unwrap_or_default();
+            Ok(body["id"].as_str().map(String::from))
+        }
+        Ok(r) => {
+            let status = r.status();
+            let body = r.text().await.unwrap_or_default();
+            error!(%status, body, "Discord send failed");
+            Err(format!("Discord API error {status}"))
+        }
+        Err(e) => Err(e.to_string()),
+    }
 }
 
 /// Split a message at Discord's 2000-char limit.
@@ -121,12 +844,31 @@ impl Channel for DiscordChannel {
         &self,
         _config: &serde_json::Value,
     ) -> anyhow::Result<(InboundReceiver, ChannelHandle)> {
-        let (_inbound_tx, inbound_rx) = mpsc::unbounded_channel::<InboundMessage>();
+        let (inbound_tx, inbound_rx) = mpsc::unbounded_channel::<InboundMessage>();
         let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
 
+        let ctx = GatewayContext {
+            bot_token: self.bot_token.clone(),
+            allowed_guilds: self.allowed_guilds.clone(),
+            allowed_users: self.allowed_users.clone(),
+            commands: self.commands.clone(),
+            pending: self.pending.clone(),
+            threads: self.threads.clone(),
+            intents: if self.reply_in_threads {
+                INTENTS | INTENT_MESSAGE_CONTENT
+            } else {
+                INTENTS
+            },
+            inbound_tx,
+            client: reqwest::Client::new(),
+        };
+
         tokio::spawn(async move {
             info!("Discord channel started");
-            let _ = shutdown_rx.await;
+            tokio::select! {
+                _ = gateway_loop(ctx) => {}
+                _ = shutdown_rx => {}
+            }
             info!("Discord channel stopped");
         });
 
@@ -142,6 +884,7 @@ impl Channel for DiscordChannel {
         if text.is_empty() {
             return Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: true,
                 error: None,
             });
@@ -149,48 +892,140 @@ impl Channel for DiscordChannel {
 
         let chunks = split_discord_message(&text);
         let client = reqwest::Client::new();
+        // A deferred slash command is answered through its interaction webhook
+        let interaction = self.take_pending(target);
+        let mut message_ids = Vec::with_capacity(chunks.len());
 
-        for chunk in &chunks {
-            let resp = client
-                .post(format!(
-                    "https://discord.com/api/v10/channels/{}/messages",
-                    target.chat_id
-                ))
-                .header("Authorization", format!("Bot {}", self.bot_token))
-                .header("Content-Type", "application/json")
-                .json(&serde_json::json!({ "content": chunk }))
-                .send()
-                .await;
-
-            match resp {
-                Ok(r) if r.status().is_success() => {}
-                Ok(r) => {
-                    let status = r.status();
-                    let body = r.text().await.unwrap_or_default();
-                    error!(%status, body, "Discord send failed");
-                    return Ok(SendResult {
-                        message_id: None,
-                        success: false,
-                        error: Some(format!("Discord API error {status}")),
-                    });
+        // Replies go to the channel the message came from (`account_id`),
+        // referencing the triggering message or in a thread opened on it
+        let mut channel_id = target.account_id.clone();
+        let mut reference = message.reply_to.clone();
+        if interaction.is_none()
+            && self.reply_in_threads
+            && target.chat_type == ChatType::Group
+            && let Some(message_id) = &reference
+        {
+            match self
+                .start_thread(&client, &channel_id, message_id, &thread_name(&text))
+                .await
+            {
+                Ok(thread_id) => {
+                    channel_id = thread_id;
+                    reference = None;
+                }
+                Err(e) => warn!(error = %e, "Failed to open Discord thread, replying inline"),
+            }
+        }
+
+        for (i, chunk) in chunks.iter().enumerate() {
+            let mut body = serde_json::json!({ "content": chunk });
+            if i == 0
+                && let Some(message_id) = &reference
+            {
+                body["message_reference"] =
+                    json!({ "message_id": message_id, "fail_if_not_exists": false });
+            }
+            let request = match &interaction {
+                // The first part replaces the "thinking..." placeholder
+                Some(pending) if i == 0 => client
+                    .patch(format!(
+                        "{API_BASE}/webhooks/{}/{}/messages/@original",
+                        pending.application_id, pending.token
+                    ))
+                    .json(&body),
+                Some(pending) => client
+                    .post(format!(
+                        "{API_BASE}/webhooks/{}/{}",
+                        pending.application_id, pending.token
+                    ))
+                    .json(&body),
+                None => client
+                    .post(format!("{API_BASE}/channels/{channel_id}/messages"))
+                    .header("Authorization", format!("Bot {}", self.bot_token))
+                    .json(&body),
+            };
+
+            match send_discord_message(&self.limiter, request).await {
+                Ok(id) => {
+                    if let Some(id) = &id {
+                        let route = match &interaction {
+                            Some(pending) => EditRoute::Webhook {
+                                application_id: pending.application_id.clone(),
+                                token: pending.token.clone(),
+                            },
+                            None => EditRoute::Channel(channel_id.clone()),
+                        };
+                        let mut routes = self.edit_routes.lock().unwrap();
+                        if routes.len() >= MAX_EDIT_ROUTES {
+                            routes.clear();
+                        }
+                        routes.insert(id.clone(), route);
+                    }
+                    message_ids.extend(id);
                 }
                 Err(e) => {
                     return Ok(SendResult {
-                        message_id: None,
+                        message_id: message_ids.last().cloned(),
+                        message_ids,
                         success: false,
-                        error: Some(e.to_string()),
+                        error: Some(e),
                     });
                 }
             }
         }
 
         Ok(SendResult {
-            message_id: None,
+            message_id: message_ids.last().cloned(),
+            message_ids,
             success: true,
             error: None,
         })
     }
 
+    async fn edit(&self, target: &SendTarget, message_id: &str, text: &str) -> anyhow::Result<()> {
+        let route = self
+            .edit_routes
+            .lock()
+            .unwrap()
+            .get(message_id)
+            .cloned()
+            .unwrap_or_else(|| EditRoute::Channel(target.account_id.clone()));
+        let client = reqwest::Client::new();
+        let body = json!({ "content": text });
+        let request = match route {
+            EditRoute::Channel(channel_id) => client
+                .patch(format!("{API_BASE}/channels/{channel_id}/messages/{message_id}"))
+                .header("Authorization", format!("Bot {}", self.bot_token))
+                .json(&body),
+            EditRoute::Webhook { application_id, token } => client
+                .patch(format!("{API_BASE}/webhooks/{application_id}/{token}/messages/{message_id}"))
+                .json(&body),
+        };
+        send_discord_message(&self.limiter, request)
+            .await
+            .map(|_| ())
+            .map_err(|e| anyhow::anyhow!(e))
+    }
+
+    /// DMs go to the user's DM channel, which is opened (or looked up) by
+    /// recipient.
+    async fn dm_target(&self, _account_id: &str, user_id: &str) -> anyhow::Result<SendTarget> {
+        let request = reqwest::Client::new()
+            .post(format!("{API_BASE}/users/@me/channels"))
+            .header("Authorization", format!("Bot {}", self.bot_token))
+            .json(&json!({ "recipient_id": user_id }));
+        let channel_id = send_discord_message(&self.limiter, request)
+            .await
+            .map_err(anyhow::Error::msg)?
+            .ok_or_else(|| anyhow::anyhow!("Discord returned no DM channel id"))?;
+        Ok(SendTarget {
+            channel: "discord".into(),
+            account_id: channel_id,
+            chat_id: user_id.to_string(),
+            chat_type: ChatType::Dm,
+        })
+    }
+
     async fn status(&self) -> ChannelStatus {
         ChannelStatus {
             connected: true,
@@ -230,6 +1065,8 @@ mod tests {
             bot_token_env: Some("TEST_DISCORD_TOKEN_RC".into()),
             allowed_guilds: vec![],
             allowed_users: vec![],
+            commands: None,
+            reply_in_threads: false,
         };
         assert_eq!(config.resolve_bot_token(), Some("disc-token-123".into()));
         unsafe { std::env::remove_var("TEST_DISCORD_TOKEN_RC") };
@@ -241,4 +1078,156 @@ mod tests {
         assert_eq!(channel.id(), "discord");
         assert_eq!(channel.capabilities().max_message_length, Some(2000));
     }
+
+    fn default_commands() -> Vec<DiscordCommandConfig> {
+        rusty_claw_core::config::DiscordConfig {
+            bot_token: None,
+            bot_token_env: None,
+            allowed_guilds: vec![],
+            allowed_users: vec![],
+            commands: None,
+            reply_in_threads: false,
+            rate_limit: None,
+            stream_replies: false,
+            access: Default::default(),
+        }
+        .slash_commands()
+    }
+
+    #[test]
+    fn test_command_payload() {
+        let payload = command_payload(&default_commands());
+        assert_eq!(payload[0]["name"], "ask");
+        assert_eq!(payload[0]["options"][0]["name"], "prompt");
+        assert_eq!(payload[0]["options"][0]["required"], true);
+        assert_eq!(payload[1]["name"], "reset");
+        assert_eq!(payload[1]["options"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_interaction_to_inbound() {
+        let commands = default_commands();
+        let guild: Interaction = serde_json::from_value(serde_json::json!({
+            "id": "111", "application_id": "999", "type": 2, "token": "tok",
+            "guild_id": "g1", "channel_id": "c1",
+            "member": {"user": {"id": "u1", "username": "ada", "global_name": "Ada"}},
+            "data": {"id": "cmd", "name": "ask", "type": 1,
+                     "options": [{"name": "prompt", "type": 3, "value": "what time is it?"}]}
+        }))
+        .unwrap();
+        let inbound = interaction_to_inbound(&guild, &commands).unwrap();
+        assert_eq!(inbound.text.as_deref(), Some("what time is it?"));
+        assert_eq!(inbound.account_id, "c1");
+        assert_eq!(inbound.sender.id, "u1");
+        assert_eq!(inbound.chat_type, ChatType::Group);
+
+        let dm: Interaction = serde_json::from_value(serde_json::json!({
+            "id": "112", "application_id": "999", "type": 2, "token": "tok",
+            "channel_id": "d1", "user": {"id": "u1", "username": "ada"},
+            "data": {"id": "cmd", "name": "reset", "type": 1}
+        }))
+        .unwrap();
+        let inbound = interaction_to_inbound(&dm, &commands).unwrap();
+        assert_eq!(inbound.text.as_deref(), Some("/reset"));
+        assert_eq!(inbound.chat_type, ChatType::Dm);
+
+        // Commands that are no longer configured are not routed
+        assert!(interaction_to_inbound(&dm, &commands[..1]).is_none());
+    }
+
+    #[test]
+    fn test_pending_interaction_lookup() {
+        let channel = DiscordChannel::new("token".into(), vec![], vec![]);
+        channel.pending.lock().unwrap().insert(
+            ("c1".into(), "u1".into()),
+            PendingInteraction {
+                application_id: "999".into(),
+                token: "tok".into(),
+                created: Instant::now(),
+            },
+        );
+        let target = SendTarget {
+            channel: "discord".into(),
+            account_id: "c1".into(),
+            chat_id: "u1".into(),
+            chat_type: ChatType::Group,
+        };
+        assert_eq!(channel.take_pending(&target).unwrap().token, "tok");
+        // Each deferral is answered once
+        assert!(channel.take_pending(&target).is_none());
+    }
+
+    #[test]
+    fn test_message_to_inbound() {
+        let message = |json: serde_json::Value| -> DiscordMessage { serde_json::from_value(json).unwrap() };
+        let mut threads = HashSet::new();
+
+        let mention = message(serde_json::json!({
+            "id": "m1", "channel_id": "c1", "guild_id": "g1",
+            "author": {"id": "u1", "username": "ada"},
+            "content": "<@42> summarize this", "mentions": [{"id": "42", "username": "bot", "bot": true}]
+        }));
+        let inbound = message_to_inbound(&mention, Some("42"), &threads).unwrap();
+        assert_eq!(inbound.text.as_deref(), Some("summarize this"));
+        assert_eq!(inbound.message_id.as_deref(), Some("m1"));
+        assert_eq!(inbound.account_id, "c1");
+        assert_eq!(inbound.chat_type, ChatType::Group);
+
+        // Unmentioned guild chatter is ignored, except in our own threads
+        let chatter = message(serde_json::json!({
+            "id": "m2", "channel_id": "t1", "guild_id": "g1",
+            "author": {"id": "u1", "username": "ada"}, "content": "and then?"
+        }));
+        assert!(message_to_inbound(&chatter, Some("42"), &threads).is_none());
+        threads.insert("t1".to_string());
+        let inbound = message_to_inbound(&chatter, Some("42"), &threads).unwrap();
+        assert_eq!(inbound.chat_type, ChatType::Thread);
+        assert_eq!(inbound.thread_id.as_deref(), Some("t1"));
+
+        let bot = message(serde_json::json!({
+            "id": "m3", "channel_id": "d1", "author": {"id": "42", "username": "bot", "bot": true},
+            "content": "echo"
+        }));
+        assert!(message_to_inbound(&bot, Some("42"), &threads).is_none());
+    }
+
+    #[test]
+    fn test_thread_name() {
+        assert_eq!(thread_name("\nShort answer\nmore"), "Short answer");
+        let long = thread_name(&"x".repeat(150));
+        assert_eq!(long.chars().count(), MAX_THREAD_NAME);
+        assert!(long.ends_with('…'));
+    }
+
+    #[test]
+    fn test_reaction_to_inbound() {
+        let event = |json: serde_json::Value| -> ReactionEvent { serde_json::from_value(json).unwrap() };
+        let threads = HashSet::new();
+
+        let stop = event(serde_json::json!({
+            "user_id": "u1", "channel_id": "c1", "message_id": "m9", "guild_id": "g1",
+            "message_author_id": "42", "emoji": {"id": null, "name": "🛑"}
+        }));
+        let inbound = reaction_to_inbound(&stop, Some("42"), &threads).unwrap();
+        let reaction = inbound.reaction.unwrap();
+        assert_eq!(reaction.emoji, "🛑");
+        assert_eq!(reaction.message_id, "m9");
+        assert_eq!(inbound.sender.id, "u1");
+
+        // Reactions on other people's messages and our own reactions are ignored
+        let other = event(serde_json::json!({
+            "user_id": "u1", "channel_id": "c1", "message_id": "m8",
+            "message_author_id": "u2", "emoji": {"name": "🛑"}
+        }));
+        assert!(reaction_to_inbound(&other, Some("42"), &threads).is_none());
+        let own = event(serde_json::json!({
+            "user_id": "42", "channel_id": "c1", "message_id": "m9", "emoji": {"name": "🔁"}
+        }));
+        assert!(reaction_to_inbound(&own, Some("42"), &threads).is_none());
+
+        let custom = event(serde_json::json!({
+            "user_id": "u1", "channel_id": "d1", "message_id": "m9", "emoji": {"id": "123", "name": "halt"}
+        }));
+        assert_eq!(reaction_to_inbound(&custom, Some("42"), &threads).unwrap().reaction.unwrap().emoji, ":halt:");
+    }
 }
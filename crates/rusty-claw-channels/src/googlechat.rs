@@ -138,9 +138,11 @@ impl Channel for GoogleChatChannel {
                                         text: Some(text),
                                         media: vec![],
                                         reply_to: None,
+                                        message_id: None,
                                         thread_id,
                                         timestamp: chrono::Utc::now(),
                                         raw: None,
+                                        reaction: None,
                                     };
                                     let _ = inbound_tx.send(msg);
                                 }
@@ -180,6 +182,7 @@ impl Channel for GoogleChatChannel {
         if text.is_empty() {
             return Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: true,
                 error: None,
             });
@@ -202,6 +205,7 @@ impl Channel for GoogleChatChannel {
         match resp {
             Ok(r) if r.status().is_success() => Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: true,
                 error: None,
             }),
@@ -210,12 +214,14 @@ impl Channel for GoogleChatChannel {
                 warn!(%status, "Google Chat send failed");
                 Ok(SendResult {
                     message_id: None,
+                    message_ids: vec![],
                     success: false,
                     error: Some(format!("Google Chat API error {status}")),
                 })
             }
             Err(e) => Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: false,
                 error: Some(e.to_string()),
             }),
@@ -262,6 +268,7 @@ mod tests {
             service_account_json: Some("{}".into()),
             project_id: Some("my-project".into()),
             webhook_port: 3102,
+            access: Default::default(),
         };
         assert_eq!(config.webhook_port, 3102);
         assert_eq!(config.project_id, Some("my-project".into()));
@@ -36,9 +36,14 @@ pub mod msteams;
 #[cfg(feature = "matrix")]
 pub mod matrix;
 
+#[cfg(feature = "matrix-e2ee")]
+pub mod matrix_crypto;
+
 #[cfg(feature = "bluebubbles")]
 pub mod bluebubbles;
 
+pub mod rate_limit;
+
 /// Channel metadata for UI display and discovery.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChannelMeta {
@@ -117,6 +122,25 @@ pub trait Channel: Send + Sync + 'static {
         message: OutboundMessage,
     ) -> anyhow::Result<SendResult>;
 
+    /// Replace the text of a message this channel sent earlier, identified
+    /// by an id from [`SendResult`]. Channels that cannot edit return an error.
+    async fn edit(&self, _target: &SendTarget, _message_id: &str, _text: &str) -> anyhow::Result<()> {
+        anyhow::bail!("Channel '{}' does not support editing messages", self.id())
+    }
+
+    /// Where to send a direct message to `user_id`, opening the
+    /// conversation first on platforms that need one. `account_id` is the
+    /// account of a message from that user. By default the user id is the
+    /// chat id.
+    async fn dm_target(&self, account_id: &str, user_id: &str) -> anyhow::Result<SendTarget> {
+        Ok(SendTarget {
+            channel: self.id().to_string(),
+            account_id: account_id.to_string(),
+            chat_id: user_id.to_string(),
+            chat_type: ChatType::Dm,
+        })
+    }
+
     /// Get current channel status/health.
     async fn status(&self) -> ChannelStatus;
 }
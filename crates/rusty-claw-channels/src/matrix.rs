@@ -1,4 +1,21 @@
 //! Matrix channel implementation (HTTP API, no SDK dependency).
+//!
+//! End-to-end encryption is opt-in via [`MatrixChannel::with_e2ee`] (the
+//! `matrix-e2ee` feature): the channel then uploads device keys, sets up
+//! cross-signing on first login, decrypts encrypted rooms during sync and
+//! encrypts replies to them, using [`crate::matrix_crypto`]. Without it,
+//! encrypted rooms deliver `m.room.encrypted` events that cannot be read,
+//! and these are reported once per Megolm session rather than silently
+//! dropped.
+
+use std::collections::HashSet;
+#[cfg(feature = "matrix-e2ee")]
+use std::path::PathBuf;
+#[cfg(feature = "matrix-e2ee")]
+use std::sync::Arc;
+
+#[cfg(feature = "matrix-e2ee")]
+use anyhow::{Context, bail};
 
 use async_trait::async_trait;
 use tokio::sync::mpsc;
@@ -8,6 +25,8 @@ use rusty_claw_core::types::{
     ChatType, InboundMessage, OutboundMessage, SendResult, SendTarget, Sender,
 };
 
+#[cfg(feature = "matrix-e2ee")]
+use crate::matrix_crypto::{MatrixCrypto, published_master_key};
 use crate::{
     Channel, ChannelCapabilities, ChannelHandle, ChannelMeta, ChannelStatus, InboundReceiver,
 };
@@ -16,6 +35,17 @@ pub struct MatrixChannel {
     homeserver_url: String,
     access_token: String,
     user_id: Option<String>,
+    #[cfg(feature = "matrix-e2ee")]
+    e2ee: Option<E2ee>,
+}
+
+#[cfg(feature = "matrix-e2ee")]
+struct E2ee {
+    store_dir: PathBuf,
+    password: Option<String>,
+    crypto: tokio::sync::OnceCell<Arc<tokio::sync::Mutex<MatrixCrypto>>>,
+    /// Rooms known to have `m.room.encryption` set; it cannot be unset.
+    encrypted_rooms: Arc<std::sync::Mutex<HashSet<String>>>,
 }
 
 impl MatrixChannel {
@@ -24,8 +54,25 @@ impl MatrixChannel {
             homeserver_url,
             access_token,
             user_id,
+            #[cfg(feature = "matrix-e2ee")]
+            e2ee: None,
         }
     }
+
+    /// Enable end-to-end encryption with its crypto store in `store_dir`.
+    ///
+    /// `password` is only used on first login, to answer the homeserver's
+    /// user-interactive auth when uploading cross-signing keys.
+    #[cfg(feature = "matrix-e2ee")]
+    pub fn with_e2ee(mut self, store_dir: PathBuf, password: Option<String>) -> Self {
+        self.e2ee = Some(E2ee {
+            store_dir,
+            password,
+            crypto: tokio::sync::OnceCell::new(),
+            encrypted_rooms: Arc::default(),
+        });
+        self
+    }
 }
 
 /// Parse a Matrix sync response for m.room.message events.
@@ -82,6 +129,36 @@ pub fn parse_sync_messages(
     messages
 }
 
+/// Rooms in a sync response that contain encrypted timeline events.
+pub fn encrypted_rooms(sync: &serde_json::Value) -> Vec<String> {
+    let mut rooms: Vec<String> = Vec::new();
+    for (room_id, _) in encrypted_timeline_events(sync) {
+        if !rooms.iter().any(|r| r == room_id) {
+            rooms.push(room_id.to_string());
+        }
+    }
+    rooms
+}
+
+/// The `m.room.encrypted` timeline events of a `/sync` response, with their
+/// room ids.
+pub fn encrypted_timeline_events(sync: &serde_json::Value) -> Vec<(&str, &serde_json::Value)> {
+    let Some(rooms) = sync.pointer("/rooms/join").and_then(|j| j.as_object()) else {
+        return Vec::new();
+    };
+    rooms
+        .iter()
+        .flat_map(|(room_id, room)| {
+            room.pointer("/timeline/events")
+                .and_then(|e| e.as_array())
+                .into_iter()
+                .flatten()
+                .filter(|e| e.get("type").and_then(|v| v.as_str()) == Some("m.room.encrypted"))
+                .map(move |e| (room_id.as_str(), e))
+        })
+        .collect()
+}
+
 #[async_trait]
 impl Channel for MatrixChannel {
     fn id(&self) -> &str {
@@ -121,10 +198,33 @@ impl Channel for MatrixChannel {
         let token = self.access_token.clone();
         let user_id = self.user_id.clone();
 
+        #[cfg(feature = "matrix-e2ee")]
+        let e2ee = match &self.e2ee {
+            Some(e2ee) => {
+                let crypto = e2ee
+                    .crypto
+                    .get_or_try_init(|| async {
+                        let crypto = setup_e2ee(&homeserver, &token, e2ee).await?;
+                        anyhow::Ok(Arc::new(tokio::sync::Mutex::new(crypto)))
+                    })
+                    .await?
+                    .clone();
+                Some((crypto, e2ee.encrypted_rooms.clone()))
+            }
+            None => None,
+        };
+        // Own echoes are filtered by the full user id whoami reported
+        #[cfg(feature = "matrix-e2ee")]
+        let user_id = match &e2ee {
+            Some((crypto, _)) => Some(crypto.lock().await.user_id().to_string()),
+            None => user_id,
+        };
+
         tokio::spawn(async move {
             info!("Matrix channel started, syncing");
             let client = reqwest::Client::new();
             let mut since: Option<String> = None;
+            let mut warned_sessions: HashSet<String> = HashSet::new();
 
             loop {
                 let mut url = format!("{homeserver}/_matrix/client/v3/sync?timeout=30000");
@@ -141,10 +241,55 @@ impl Channel for MatrixChannel {
                         match result {
                             Ok(resp) if resp.status().is_success() => {
                                 if let Ok(sync_data) = resp.json::<serde_json::Value>().await {
+                                    #[cfg(feature = "matrix-e2ee")]
+                                    let mut sync_data = sync_data;
                                     if let Some(next_batch) = sync_data.get("next_batch").and_then(|v| v.as_str()) {
                                         since = Some(next_batch.to_string());
                                     }
 
+                                    #[cfg(feature = "matrix-e2ee")]
+                                    if let Some((crypto, rooms)) = &e2ee {
+                                        rooms.lock().unwrap().extend(encrypted_rooms(&sync_data));
+                                        let mut crypto = crypto.lock().await;
+                                        if let Err(e) = crypto.receive_sync_keys(&sync_data) {
+                                            warn!(%e, "Matrix crypto store error");
+                                        }
+                                        // Room keys are only trusted from pinned devices of the sender
+                                        let senders = crypto.unpinned_senders(&sync_data);
+                                        if !senders.is_empty() {
+                                            if let Err(e) = query_devices(&client, &homeserver, &token, &mut crypto, &senders).await {
+                                                warn!(%e, "Matrix device key query failed");
+                                            }
+                                        }
+                                        if let Err(e) = crypto.decrypt_sync(&mut sync_data) {
+                                            warn!(%e, "Matrix crypto store error");
+                                        }
+                                        if let Some(counts) = sync_data.get("device_one_time_keys_count") {
+                                            let published = counts
+                                                .get("signed_curve25519")
+                                                .and_then(|v| v.as_u64())
+                                                .unwrap_or(0);
+                                            if let Err(e) = upload_keys(&client, &homeserver, &token, &mut crypto, published).await {
+                                                warn!(%e, "Matrix one-time key upload failed");
+                                            }
+                                        }
+                                    }
+
+                                    // With e2ee, decrypt_sync already logged why each event stayed encrypted
+                                    #[cfg(feature = "matrix-e2ee")]
+                                    let decrypting = e2ee.is_some();
+                                    #[cfg(not(feature = "matrix-e2ee"))]
+                                    let decrypting = false;
+                                    if !decrypting {
+                                        for (room_id, event) in encrypted_timeline_events(&sync_data) {
+                                            let session = event.pointer("/content/session_id").and_then(|v| v.as_str()).unwrap_or("?");
+                                            if warned_sessions.insert(session.to_string()) {
+                                                let event_id = event.get("event_id").and_then(|v| v.as_str()).unwrap_or("?");
+                                                warn!(room = %room_id, event = %event_id, session = %session, "Matrix event is end-to-end encrypted and e2ee is off; messages from this session cannot be read");
+                                            }
+                                        }
+                                    }
+
                                     let messages = parse_sync_messages(&sync_data, user_id.as_deref());
                                     for (sender, text, room_id) in messages {
                                         let msg = InboundMessage {
@@ -159,9 +304,11 @@ impl Channel for MatrixChannel {
                                             text: Some(text),
                                             media: vec![],
                                             reply_to: None,
+                                            message_id: None,
                                             thread_id: None,
                                             timestamp: chrono::Utc::now(),
                                             raw: None,
+                                            reaction: None,
                                         };
                                         let _ = inbound_tx.send(msg);
                                     }
@@ -193,13 +340,52 @@ impl Channel for MatrixChannel {
         if text.is_empty() {
             return Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: true,
                 error: None,
             });
         }
 
+        let content = serde_json::json!({
+            "msgtype": "m.text",
+            "body": text,
+        });
         let txn_id = uuid::Uuid::new_v4().to_string();
         let client = reqwest::Client::new();
+
+        #[cfg(feature = "matrix-e2ee")]
+        if let Some(crypto) = self.e2ee.as_ref().and_then(|e| e.crypto.get()) {
+            if self.room_is_encrypted(&client, &target.chat_id).await {
+                let mut crypto = crypto.lock().await;
+                let sent = send_encrypted(
+                    &client,
+                    &self.homeserver_url,
+                    &self.access_token,
+                    &mut crypto,
+                    &target.chat_id,
+                    &content,
+                )
+                .await;
+                return Ok(match sent {
+                    Ok(()) => SendResult {
+                        message_id: None,
+                        message_ids: vec![],
+                        success: true,
+                        error: None,
+                    },
+                    Err(e) => {
+                        error!(%e, "Matrix encrypted send failed");
+                        SendResult {
+                            message_id: None,
+                            message_ids: vec![],
+                            success: false,
+                            error: Some(e.to_string()),
+                        }
+                    }
+                });
+            }
+        }
+
         let url = format!(
             "{}/_matrix/client/v3/rooms/{}/send/m.room.message/{}",
             self.homeserver_url,
@@ -210,16 +396,14 @@ impl Channel for MatrixChannel {
         let resp = client
             .put(&url)
             .header("Authorization", format!("Bearer {}", self.access_token))
-            .json(&serde_json::json!({
-                "msgtype": "m.text",
-                "body": text,
-            }))
+            .json(&content)
             .send()
             .await;
 
         match resp {
             Ok(r) if r.status().is_success() => Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: true,
                 error: None,
             }),
@@ -228,12 +412,14 @@ impl Channel for MatrixChannel {
                 error!(%status, "Matrix send failed");
                 Ok(SendResult {
                     message_id: None,
+                    message_ids: vec![],
                     success: false,
                     error: Some(format!("Matrix API error {status}")),
                 })
             }
             Err(e) => Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: false,
                 error: Some(e.to_string()),
             }),
@@ -250,6 +436,309 @@ impl Channel for MatrixChannel {
     }
 }
 
+#[cfg(feature = "matrix-e2ee")]
+impl MatrixChannel {
+    async fn room_is_encrypted(&self, client: &reqwest::Client, room_id: &str) -> bool {
+        let Some(e2ee) = &self.e2ee else {
+            return false;
+        };
+        if e2ee.encrypted_rooms.lock().unwrap().contains(room_id) {
+            return true;
+        }
+        let path = format!("/rooms/{room_id}/state/m.room.encryption");
+        let encrypted = matrix_request(
+            client,
+            &self.homeserver_url,
+            &self.access_token,
+            reqwest::Method::GET,
+            &path,
+            None,
+        )
+        .await
+        .is_ok();
+        if encrypted {
+            e2ee.encrypted_rooms
+                .lock()
+                .unwrap()
+                .insert(room_id.to_string());
+        }
+        encrypted
+    }
+}
+
+/// Authenticated Client-Server API call; non-2xx responses become errors.
+#[cfg(feature = "matrix-e2ee")]
+async fn matrix_request(
+    client: &reqwest::Client,
+    homeserver: &str,
+    token: &str,
+    method: reqwest::Method,
+    path: &str,
+    body: Option<&serde_json::Value>,
+) -> anyhow::Result<serde_json::Value> {
+    let mut request = client
+        .request(method, format!("{homeserver}/_matrix/client/v3{path}"))
+        .header("Authorization", format!("Bearer {token}"));
+    if let Some(body) = body {
+        request = request.json(body);
+    }
+    let resp = request.send().await?;
+    let status = resp.status();
+    let body = resp.json::<serde_json::Value>().await.unwrap_or_default();
+    if !status.is_success() {
+        bail!("Matrix API {path} returned {status}: {body}");
+    }
+    Ok(body)
+}
+
+/// Open the crypto store for this access token's device and publish its keys.
+#[cfg(feature = "matrix-e2ee")]
+async fn setup_e2ee(homeserver: &str, token: &str, e2ee: &E2ee) -> anyhow::Result<MatrixCrypto> {
+    let client = reqwest::Client::new();
+    let get = reqwest::Method::GET;
+    let post = reqwest::Method::POST;
+
+    let whoami = matrix_request(&client, homeserver, token, get, "/account/whoami", None).await?;
+    let user_id = whoami
+        .get("user_id")
+        .and_then(|v| v.as_str())
+        .context("whoami returned no user_id")?;
+    let device_id = whoami
+        .get("device_id")
+        .and_then(|v| v.as_str())
+        .context("Matrix e2ee needs an access token bound to a device (from a password login)")?;
+    let mut crypto = MatrixCrypto::open(&e2ee.store_dir, user_id, device_id)?;
+
+    // An empty upload reports how many one-time keys the server still holds
+    let counts = matrix_request(
+        &client,
+        homeserver,
+        token,
+        post,
+        "/keys/upload",
+        Some(&serde_json::json!({})),
+    )
+    .await?;
+    let published = counts
+        .pointer("/one_time_key_counts/signed_curve25519")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    upload_keys(&client, homeserver, token, &mut crypto, published).await?;
+
+    if let Err(e) = setup_cross_signing(
+        &client,
+        homeserver,
+        token,
+        &mut crypto,
+        e2ee.password.as_deref(),
+    )
+    .await
+    {
+        warn!(%e, "Matrix cross-signing setup failed; verify this device from another session");
+    }
+    info!(user = %user_id, device = %device_id, "Matrix end-to-end encryption ready");
+    Ok(crypto)
+}
+
+#[cfg(feature = "matrix-e2ee")]
+async fn upload_keys(
+    client: &reqwest::Client,
+    homeserver: &str,
+    token: &str,
+    crypto: &mut MatrixCrypto,
+    published_one_time_keys: u64,
+) -> anyhow::Result<()> {
+    if let Some(body) = crypto.keys_for_upload(published_one_time_keys) {
+        let post = reqwest::Method::POST;
+        matrix_request(client, homeserver, token, post, "/keys/upload", Some(&body)).await?;
+        crypto.mark_keys_uploaded()?;
+    }
+    Ok(())
+}
+
+/// Publish cross-signing keys on first login and sign this device with them.
+///
+/// An account that already has a master key from another client is left
+/// alone: replacing it would break the user's existing verifications.
+#[cfg(feature = "matrix-e2ee")]
+async fn setup_cross_signing(
+    client: &reqwest::Client,
+    homeserver: &str,
+    token: &str,
+    crypto: &mut MatrixCrypto,
+    password: Option<&str>,
+) -> anyhow::Result<()> {
+    let post = reqwest::Method::POST;
+    let user_id = crypto.user_id().to_string();
+
+    if !crypto.cross_signing_published() {
+        let query = serde_json::json!({ "device_keys": { &user_id: [] } });
+        let own = matrix_request(
+            client,
+            homeserver,
+            token,
+            post.clone(),
+            "/keys/query",
+            Some(&query),
+        )
+        .await?;
+        if let Some(existing) = published_master_key(&own, &user_id) {
+            if crypto.master_public_key().as_ref() != Some(&existing) {
+                warn!(
+                    "Matrix account already has cross-signing keys; verify this device from an existing session"
+                );
+                return Ok(());
+            }
+        }
+
+        let mut body = crypto.bootstrap_cross_signing()?;
+        let url = format!("{homeserver}/_matrix/client/v3/keys/device_signing/upload");
+        let resp = client
+            .post(&url)
+            .header("Authorization", format!("Bearer {token}"))
+            .json(&body)
+            .send()
+            .await?;
+        if resp.status() == reqwest::StatusCode::UNAUTHORIZED {
+            // User-interactive auth: answer with the account password
+            let challenge = resp.json::<serde_json::Value>().await.unwrap_or_default();
+            let Some(password) = password else {
+                bail!("uploading cross-signing keys needs the account password");
+            };
+            body["auth"] = serde_json::json!({
+                "type": "m.login.password",
+                "identifier": { "type": "m.id.user", "user": user_id },
+                "password": password,
+                "session": challenge.get("session"),
+            });
+            let path = "/keys/device_signing/upload";
+            matrix_request(client, homeserver, token, post.clone(), path, Some(&body)).await?;
+        } else if !resp.status().is_success() {
+            bail!(
+                "Matrix API /keys/device_signing/upload returned {}",
+                resp.status()
+            );
+        }
+        crypto.mark_cross_signing_published()?;
+        info!("Matrix cross-signing keys published");
+    }
+
+    if let Some(signatures) = crypto.cross_signing_signatures() {
+        let path = "/keys/signatures/upload";
+        matrix_request(client, homeserver, token, post, path, Some(&signatures)).await?;
+    }
+    Ok(())
+}
+
+/// Fetch and verify the devices of `users`, pinning new ones.
+#[cfg(feature = "matrix-e2ee")]
+async fn query_devices(
+    client: &reqwest::Client,
+    homeserver: &str,
+    token: &str,
+    crypto: &mut MatrixCrypto,
+    users: &[String],
+) -> anyhow::Result<Vec<crate::matrix_crypto::Device>> {
+    let mut device_keys = serde_json::json!({});
+    for user in users {
+        device_keys[user] = serde_json::json!([]);
+    }
+    let query = serde_json::json!({ "device_keys": device_keys });
+    let keys = matrix_request(
+        client,
+        homeserver,
+        token,
+        reqwest::Method::POST,
+        "/keys/query",
+        Some(&query),
+    )
+    .await?;
+    Ok(crypto.devices_from_query(&keys))
+}
+
+/// Share the room key with every verified device in the room, then send
+/// `content` as an `m.room.encrypted` event.
+#[cfg(feature = "matrix-e2ee")]
+async fn send_encrypted(
+    client: &reqwest::Client,
+    homeserver: &str,
+    token: &str,
+    crypto: &mut MatrixCrypto,
+    room_id: &str,
+    content: &serde_json::Value,
+) -> anyhow::Result<()> {
+    use reqwest::Method;
+
+    let members_path = format!("/rooms/{room_id}/joined_members");
+    let members =
+        matrix_request(client, homeserver, token, Method::GET, &members_path, None).await?;
+    let users: Vec<String> = members
+        .get("joined")
+        .and_then(|j| j.as_object())
+        .map(|joined| joined.keys().cloned().collect())
+        .unwrap_or_default();
+    let devices = query_devices(client, homeserver, token, crypto, &users).await?;
+
+    let missing: Vec<_> = crypto
+        .devices_without_session(&devices)
+        .into_iter()
+        .cloned()
+        .collect();
+    if !missing.is_empty() {
+        let mut request = serde_json::json!({});
+        for device in &missing {
+            request[&device.user_id][&device.device_id] = serde_json::json!("signed_curve25519");
+        }
+        let body = serde_json::json!({ "one_time_keys": request });
+        let claimed = matrix_request(
+            client,
+            homeserver,
+            token,
+            Method::POST,
+            "/keys/claim",
+            Some(&body),
+        )
+        .await?;
+        for device in &missing {
+            let key = &claimed["one_time_keys"][&device.user_id][&device.device_id];
+            if let Err(e) = crypto.create_outbound_olm_session(device, key) {
+                warn!(user = %device.user_id, device = %device.device_id, %e, "Could not start Olm session");
+            }
+        }
+    }
+
+    let messages = crypto.share_room_key(room_id, &devices);
+    if messages.as_object().is_some_and(|m| !m.is_empty()) {
+        let path = format!("/sendToDevice/m.room.encrypted/{}", uuid::Uuid::new_v4());
+        let body = serde_json::json!({ "messages": messages });
+        if let Err(e) =
+            matrix_request(client, homeserver, token, Method::PUT, &path, Some(&body)).await
+        {
+            // Recipients may not have the key; start over with a fresh session next time
+            crypto.discard_room_session(room_id);
+            crypto.save()?;
+            return Err(e);
+        }
+    }
+
+    let encrypted = crypto.encrypt_room_event(room_id, "m.room.message", content)?;
+    crypto.save()?;
+    let path = format!(
+        "/rooms/{room_id}/send/m.room.encrypted/{}",
+        uuid::Uuid::new_v4()
+    );
+    matrix_request(
+        client,
+        homeserver,
+        token,
+        Method::PUT,
+        &path,
+        Some(&encrypted),
+    )
+    .await?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -264,6 +753,8 @@ mod tests {
             password_env: None,
             access_token: None,
             access_token_env: Some("TEST_MATRIX_TOKEN_RC".into()),
+            e2ee: false,
+            access: Default::default(),
         };
         assert_eq!(config.resolve_access_token(), Some("mx-token-123".into()));
         unsafe { std::env::remove_var("TEST_MATRIX_TOKEN_RC") };
@@ -297,4 +788,25 @@ mod tests {
         assert_eq!(messages[0].1, "Hello Matrix!");
         assert_eq!(messages[0].2, "!room:matrix.org");
     }
+
+    #[test]
+    fn test_encrypted_rooms() {
+        let sync = serde_json::json!({
+            "rooms": {
+                "join": {
+                    "!plain:matrix.org": {
+                        "timeline": {"events": [{"type": "m.room.message", "sender": "@a:matrix.org",
+                                                 "content": {"body": "hi"}}]}
+                    },
+                    "!secret:matrix.org": {
+                        "timeline": {"events": [{"type": "m.room.encrypted", "sender": "@a:matrix.org",
+                                                 "content": {"algorithm": "m.megolm.v1.aes-sha2", "ciphertext": "..."}}]}
+                    }
+                }
+            }
+        });
+        assert_eq!(encrypted_rooms(&sync), vec!["!secret:matrix.org".to_string()]);
+        // Encrypted events never surface as messages
+        assert_eq!(parse_sync_messages(&sync, None).len(), 1);
+    }
 }
@@ -0,0 +1,1519 @@
+//! Matrix end-to-end encryption (Olm/Megolm via vodozemac).
+//!
+//! [`MatrixCrypto`] owns this device's Olm account, its Olm and Megolm
+//! sessions, the pinned keys of every device it has talked to, and the
+//! cross-signing identity. Everything is persisted under a store directory
+//! (`data_dir()/matrix` in production): pickles are encrypted with a random
+//! pickle key kept next to them in an owner-only secrets file.
+//!
+//! The type is transport-free. The Matrix channel performs the HTTP calls
+//! and feeds the responses in, which keeps the crypto testable without a
+//! homeserver.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tracing::{debug, warn};
+use vodozemac::megolm::{
+    self, GroupSession, GroupSessionPickle, InboundGroupSession, InboundGroupSessionPickle,
+    MegolmMessage, SessionKey,
+};
+use vodozemac::olm::{self, Account, AccountPickle, OlmMessage, Session, SessionPickle};
+use vodozemac::{
+    Curve25519PublicKey, Ed25519PublicKey, Ed25519SecretKey, Ed25519Signature, base64_decode,
+    base64_encode,
+};
+
+pub const OLM_ALGORITHM: &str = "m.olm.v1.curve25519-aes-sha2";
+pub const MEGOLM_ALGORITHM: &str = "m.megolm.v1.aes-sha2";
+
+const SECRETS_FILE: &str = "secrets.json";
+const STORE_FILE: &str = "crypto_store.json";
+
+/// An outbound room session is replaced after this many messages...
+const ROTATION_MESSAGES: u32 = 100;
+/// ...or once it is a week old (the defaults of `m.room.encryption`).
+const ROTATION_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// A remote device whose keys passed self-signature and pin checks.
+#[derive(Debug, Clone)]
+pub struct Device {
+    pub user_id: String,
+    pub device_id: String,
+    pub ed25519: Ed25519PublicKey,
+    pub curve25519: Curve25519PublicKey,
+}
+
+impl Device {
+    fn key(&self) -> String {
+        format!("{}|{}", self.user_id, self.device_id)
+    }
+}
+
+/// Keys first seen for a device; later changes are refused (trust on first use).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct DevicePin {
+    ed25519: String,
+    curve25519: String,
+}
+
+struct InboundSession {
+    room_id: String,
+    sender_key: String,
+    session: InboundGroupSession,
+    /// Event id decrypted at each message index; a second event reusing an
+    /// index is a replay.
+    seen: HashMap<u32, String>,
+}
+
+struct OutboundSession {
+    session: GroupSession,
+    created_at: i64,
+    shared_with: HashSet<String>,
+}
+
+struct CrossSigningKeys {
+    master: Ed25519SecretKey,
+    self_signing: Ed25519SecretKey,
+    user_signing: Ed25519SecretKey,
+    published: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SecretsFile {
+    pickle_key: String,
+    #[serde(default)]
+    cross_signing: Option<CrossSigningSecrets>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CrossSigningSecrets {
+    master: String,
+    self_signing: String,
+    user_signing: String,
+    #[serde(default)]
+    published: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoreFile {
+    user_id: String,
+    device_id: String,
+    account: String,
+    #[serde(default)]
+    device_keys_uploaded: bool,
+    #[serde(default)]
+    olm_sessions: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    inbound_group_sessions: HashMap<String, StoredInboundSession>,
+    #[serde(default)]
+    outbound_group_sessions: HashMap<String, StoredOutboundSession>,
+    #[serde(default)]
+    device_pins: HashMap<String, DevicePin>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredInboundSession {
+    room_id: String,
+    sender_key: String,
+    pickle: String,
+    #[serde(default)]
+    seen: HashMap<u32, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredOutboundSession {
+    pickle: String,
+    created_at: i64,
+    shared_with: HashSet<String>,
+}
+
+pub struct MatrixCrypto {
+    dir: PathBuf,
+    pickle_key: [u8; 32],
+    user_id: String,
+    device_id: String,
+    account: Account,
+    device_keys_uploaded: bool,
+    /// Olm sessions keyed by the remote device's Curve25519 key, oldest first.
+    olm_sessions: HashMap<String, Vec<Session>>,
+    /// Megolm sessions received from other devices, keyed by session id.
+    inbound: HashMap<String, InboundSession>,
+    /// Our current Megolm session per room.
+    outbound: HashMap<String, OutboundSession>,
+    device_pins: HashMap<String, DevicePin>,
+    cross_signing: Option<CrossSigningKeys>,
+}
+
+impl MatrixCrypto {
+    /// Load the crypto store in `dir`, or create a new device identity.
+    ///
+    /// A store that belongs to a different user or device is replaced: Olm
+    /// identities are per device, so a new access token means a new account.
+    pub fn open(dir: &Path, user_id: &str, device_id: &str) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let secrets = load_or_create_secrets(dir)?;
+        let pickle_key: [u8; 32] = base64_decode(&secrets.pickle_key)?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Matrix pickle key must be 32 bytes"))?;
+        let cross_signing = secrets
+            .cross_signing
+            .map(|c| -> anyhow::Result<CrossSigningKeys> {
+                Ok(CrossSigningKeys {
+                    master: Ed25519SecretKey::from_base64(&c.master)?,
+                    self_signing: Ed25519SecretKey::from_base64(&c.self_signing)?,
+                    user_signing: Ed25519SecretKey::from_base64(&c.user_signing)?,
+                    published: c.published,
+                })
+            })
+            .transpose()?;
+
+        let mut crypto = Self {
+            dir: dir.to_path_buf(),
+            pickle_key,
+            user_id: user_id.to_string(),
+            device_id: device_id.to_string(),
+            account: Account::new(),
+            device_keys_uploaded: false,
+            olm_sessions: HashMap::new(),
+            inbound: HashMap::new(),
+            outbound: HashMap::new(),
+            device_pins: HashMap::new(),
+            cross_signing,
+        };
+
+        let store_path = dir.join(STORE_FILE);
+        if store_path.exists() {
+            let store: StoreFile = serde_json::from_str(&std::fs::read_to_string(&store_path)?)
+                .with_context(|| format!("reading {}", store_path.display()))?;
+            if store.user_id == user_id && store.device_id == device_id {
+                crypto.restore(store)?;
+                return Ok(crypto);
+            }
+            warn!(
+                old_device = %store.device_id,
+                new_device = %device_id,
+                "Matrix device changed; creating a new crypto identity"
+            );
+        }
+        crypto.save()?;
+        Ok(crypto)
+    }
+
+    fn restore(&mut self, store: StoreFile) -> anyhow::Result<()> {
+        let key = &self.pickle_key;
+        self.account = Account::from_pickle(AccountPickle::from_encrypted(&store.account, key)?);
+        self.device_keys_uploaded = store.device_keys_uploaded;
+        for (sender_key, pickles) in store.olm_sessions {
+            let sessions = pickles
+                .iter()
+                .map(|p| Ok(Session::from_pickle(SessionPickle::from_encrypted(p, key)?)))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            self.olm_sessions.insert(sender_key, sessions);
+        }
+        for (session_id, stored) in store.inbound_group_sessions {
+            let session = InboundGroupSession::from_pickle(
+                InboundGroupSessionPickle::from_encrypted(&stored.pickle, key)?,
+            );
+            self.inbound.insert(
+                session_id,
+                InboundSession {
+                    room_id: stored.room_id,
+                    sender_key: stored.sender_key,
+                    session,
+                    seen: stored.seen,
+                },
+            );
+        }
+        for (room_id, stored) in store.outbound_group_sessions {
+            let session =
+                GroupSession::from_pickle(GroupSessionPickle::from_encrypted(&stored.pickle, key)?);
+            self.outbound.insert(
+                room_id,
+                OutboundSession {
+                    session,
+                    created_at: stored.created_at,
+                    shared_with: stored.shared_with,
+                },
+            );
+        }
+        self.device_pins = store.device_pins;
+        Ok(())
+    }
+
+    /// Write the account and all sessions to the store.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let key = &self.pickle_key;
+        let store = StoreFile {
+            user_id: self.user_id.clone(),
+            device_id: self.device_id.clone(),
+            account: self.account.pickle().encrypt(key),
+            device_keys_uploaded: self.device_keys_uploaded,
+            olm_sessions: self
+                .olm_sessions
+                .iter()
+                .map(|(k, sessions)| {
+                    (
+                        k.clone(),
+                        sessions.iter().map(|s| s.pickle().encrypt(key)).collect(),
+                    )
+                })
+                .collect(),
+            inbound_group_sessions: self
+                .inbound
+                .iter()
+                .map(|(id, s)| {
+                    let stored = StoredInboundSession {
+                        room_id: s.room_id.clone(),
+                        sender_key: s.sender_key.clone(),
+                        pickle: s.session.pickle().encrypt(key),
+                        seen: s.seen.clone(),
+                    };
+                    (id.clone(), stored)
+                })
+                .collect(),
+            outbound_group_sessions: self
+                .outbound
+                .iter()
+                .map(|(room_id, s)| {
+                    let stored = StoredOutboundSession {
+                        pickle: s.session.pickle().encrypt(key),
+                        created_at: s.created_at,
+                        shared_with: s.shared_with.clone(),
+                    };
+                    (room_id.clone(), stored)
+                })
+                .collect(),
+            device_pins: self.device_pins.clone(),
+        };
+        write_private(
+            &self.dir.join(STORE_FILE),
+            &serde_json::to_string_pretty(&store)?,
+        )?;
+        Ok(())
+    }
+
+    fn save_secrets(&self) -> anyhow::Result<()> {
+        let secrets = SecretsFile {
+            pickle_key: base64_encode(self.pickle_key),
+            cross_signing: self.cross_signing.as_ref().map(|c| CrossSigningSecrets {
+                master: c.master.to_base64(),
+                self_signing: c.self_signing.to_base64(),
+                user_signing: c.user_signing.to_base64(),
+                published: c.published,
+            }),
+        };
+        write_private(
+            &self.dir.join(SECRETS_FILE),
+            &serde_json::to_string_pretty(&secrets)?,
+        )?;
+        Ok(())
+    }
+
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    pub fn device_id(&self) -> &str {
+        &self.device_id
+    }
+
+    pub fn curve25519_key(&self) -> String {
+        self.account.curve25519_key().to_base64()
+    }
+
+    pub fn ed25519_key(&self) -> String {
+        self.account.ed25519_key().to_base64()
+    }
+
+    fn device_key_id(&self) -> String {
+        format!("ed25519:{}", self.device_id)
+    }
+
+    fn sign_with_device(&self, value: &mut Value) {
+        let key_id = self.device_key_id();
+        sign_json(value, &self.user_id, &key_id, |m| self.account.sign(m));
+    }
+
+    /// This device's identity keys, self-signed, as published in `/keys/upload`.
+    pub fn device_keys(&self) -> Value {
+        let mut keys = json!({
+            "user_id": self.user_id,
+            "device_id": self.device_id,
+            "algorithms": [OLM_ALGORITHM, MEGOLM_ALGORITHM],
+            "keys": {
+                format!("curve25519:{}", self.device_id): self.curve25519_key(),
+                format!("ed25519:{}", self.device_id): self.ed25519_key(),
+            },
+        });
+        self.sign_with_device(&mut keys);
+        keys
+    }
+
+    /// Body for `/keys/upload`, or `None` when the server already has enough.
+    ///
+    /// `published_one_time_keys` is the server's `signed_curve25519` count
+    /// from the upload response or `device_one_time_keys_count` in `/sync`.
+    /// Call [`mark_keys_uploaded`](Self::mark_keys_uploaded) once the upload succeeds.
+    pub fn keys_for_upload(&mut self, published_one_time_keys: u64) -> Option<Value> {
+        let target = self.account.max_number_of_one_time_keys() / 2;
+        let missing = target.saturating_sub(published_one_time_keys as usize);
+        if missing > 0 && self.account.one_time_keys().is_empty() {
+            self.account.generate_one_time_keys(missing);
+        }
+
+        let mut body = serde_json::Map::new();
+        if !self.device_keys_uploaded {
+            body.insert("device_keys".into(), self.device_keys());
+            if self.account.fallback_key().is_empty() {
+                self.account.generate_fallback_key();
+            }
+        }
+        let one_time_keys = self.signed_keys(self.account.one_time_keys(), false);
+        if !one_time_keys.is_empty() {
+            body.insert("one_time_keys".into(), Value::Object(one_time_keys));
+        }
+        let fallback_keys = self.signed_keys(self.account.fallback_key(), true);
+        if !fallback_keys.is_empty() {
+            body.insert("fallback_keys".into(), Value::Object(fallback_keys));
+        }
+        (!body.is_empty()).then_some(Value::Object(body))
+    }
+
+    fn signed_keys(
+        &self,
+        keys: HashMap<vodozemac::KeyId, Curve25519PublicKey>,
+        fallback: bool,
+    ) -> serde_json::Map<String, Value> {
+        keys.into_iter()
+            .map(|(id, key)| {
+                let mut signed = json!({ "key": key.to_base64() });
+                if fallback {
+                    signed["fallback"] = json!(true);
+                }
+                self.sign_with_device(&mut signed);
+                (format!("signed_curve25519:{}", id.to_base64()), signed)
+            })
+            .collect()
+    }
+
+    pub fn mark_keys_uploaded(&mut self) -> anyhow::Result<()> {
+        self.account.mark_keys_as_published();
+        self.device_keys_uploaded = true;
+        self.save()
+    }
+
+    /// Verify the devices in a `/keys/query` response.
+    ///
+    /// A device is accepted when its key object is self-signed, names the
+    /// user and device it is listed under, and matches the keys pinned the
+    /// first time the device was seen. Our own device is left out.
+    pub fn devices_from_query(&mut self, response: &Value) -> Vec<Device> {
+        let mut devices = Vec::new();
+        let Some(users) = response.get("device_keys").and_then(|v| v.as_object()) else {
+            return devices;
+        };
+        for (user_id, user_devices) in users {
+            let Some(user_devices) = user_devices.as_object() else {
+                continue;
+            };
+            for (device_id, keys) in user_devices {
+                if *user_id == self.user_id && *device_id == self.device_id {
+                    continue;
+                }
+                match self.verify_device(user_id, device_id, keys) {
+                    Ok(device) => devices.push(device),
+                    Err(e) => {
+                        warn!(user = %user_id, device = %device_id, %e, "Ignoring Matrix device")
+                    }
+                }
+            }
+        }
+        devices
+    }
+
+    fn verify_device(
+        &mut self,
+        user_id: &str,
+        device_id: &str,
+        keys: &Value,
+    ) -> anyhow::Result<Device> {
+        if keys.get("user_id").and_then(|v| v.as_str()) != Some(user_id)
+            || keys.get("device_id").and_then(|v| v.as_str()) != Some(device_id)
+        {
+            bail!("key object does not match the user and device it is listed under");
+        }
+        let key = |algorithm: &str| {
+            keys.get("keys")
+                .and_then(|k| k.get(format!("{algorithm}:{device_id}")))
+                .and_then(|v| v.as_str())
+                .with_context(|| format!("missing {algorithm} key"))
+        };
+        let ed25519_b64 = key("ed25519")?;
+        let curve25519_b64 = key("curve25519")?;
+        let ed25519 = Ed25519PublicKey::from_base64(ed25519_b64)?;
+        let curve25519 = Curve25519PublicKey::from_base64(curve25519_b64)?;
+        if !verify_json(keys, user_id, &format!("ed25519:{device_id}"), &ed25519) {
+            bail!("device keys are not self-signed");
+        }
+
+        let pin = DevicePin {
+            ed25519: ed25519_b64.to_string(),
+            curve25519: curve25519_b64.to_string(),
+        };
+        let device = Device {
+            user_id: user_id.to_string(),
+            device_id: device_id.to_string(),
+            ed25519,
+            curve25519,
+        };
+        match self.device_pins.get(&device.key()) {
+            Some(pinned) if *pinned != pin => bail!("device keys changed since first seen"),
+            Some(_) => {}
+            None => {
+                self.device_pins.insert(device.key(), pin);
+            }
+        }
+        Ok(device)
+    }
+
+    /// Devices that need a one-time key claimed before a room key can be sent.
+    pub fn devices_without_session<'a>(&self, devices: &'a [Device]) -> Vec<&'a Device> {
+        devices
+            .iter()
+            .filter(|d| !self.olm_sessions.contains_key(&d.curve25519.to_base64()))
+            .collect()
+    }
+
+    /// Start an Olm session with `device` from a key claimed via `/keys/claim`.
+    ///
+    /// `claimed` is the device's entry in the response, a map with a single
+    /// `signed_curve25519:<id>` key that must carry the device's signature.
+    pub fn create_outbound_olm_session(
+        &mut self,
+        device: &Device,
+        claimed: &Value,
+    ) -> anyhow::Result<()> {
+        let Some(one_time_key) = claimed.as_object().and_then(|keys| {
+            keys.iter()
+                .find(|(id, _)| id.starts_with("signed_curve25519:"))
+                .map(|(_, key)| key)
+        }) else {
+            bail!(
+                "no signed one-time key for {} {}",
+                device.user_id,
+                device.device_id
+            );
+        };
+        let key_id = format!("ed25519:{}", device.device_id);
+        if !verify_json(one_time_key, &device.user_id, &key_id, &device.ed25519) {
+            bail!(
+                "one-time key for {} {} has an invalid signature",
+                device.user_id,
+                device.device_id
+            );
+        }
+        let one_time_key = Curve25519PublicKey::from_base64(
+            one_time_key
+                .get("key")
+                .and_then(|v| v.as_str())
+                .context("one-time key without a key")?,
+        )?;
+        let session = self.account.create_outbound_session(
+            olm::SessionConfig::version_1(),
+            device.curve25519,
+            one_time_key,
+        );
+        self.olm_sessions
+            .entry(device.curve25519.to_base64())
+            .or_default()
+            .push(session);
+        Ok(())
+    }
+
+    /// Handle one to-device event. Returns the session id of an imported room key.
+    pub fn receive_to_device(&mut self, event: &Value) -> anyhow::Result<Option<String>> {
+        if event.get("type").and_then(|v| v.as_str()) != Some("m.room.encrypted") {
+            return Ok(None);
+        }
+        let content = &event["content"];
+        let algorithm = content
+            .get("algorithm")
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        if algorithm != OLM_ALGORITHM {
+            bail!("unsupported to-device algorithm {algorithm:?}");
+        }
+        let sender = event.get("sender").and_then(|v| v.as_str()).unwrap_or("");
+        let sender_key_b64 = content
+            .get("sender_key")
+            .and_then(|v| v.as_str())
+            .context("to-device event without sender_key")?;
+        let sender_key = Curve25519PublicKey::from_base64(sender_key_b64)?;
+        let Some(ciphertext) = content
+            .get("ciphertext")
+            .and_then(|c| c.get(self.curve25519_key()))
+        else {
+            // Addressed to another of our user's devices
+            return Ok(None);
+        };
+        let message: OlmMessage = serde_json::from_value(ciphertext.clone())?;
+        let plaintext = self.olm_decrypt(sender_key, &message)?;
+        let payload: Value = serde_json::from_slice(&plaintext)?;
+
+        let field = |path: &str| payload.pointer(path).and_then(|v| v.as_str());
+        if field("/sender") != Some(sender) {
+            bail!("Olm payload sender does not match the event sender");
+        }
+        if field("/recipient") != Some(self.user_id.as_str())
+            || field("/recipient_keys/ed25519") != Some(self.ed25519_key().as_str())
+        {
+            bail!("Olm payload was not encrypted for this device");
+        }
+        let claimed_ed25519 = field("/keys/ed25519").unwrap_or("");
+        let pinned_mismatch = self.device_pins.iter().any(|(key, pin)| {
+            key.split_once('|').is_some_and(|(user, _)| user == sender)
+                && pin.curve25519 == sender_key_b64
+                && pin.ed25519 != claimed_ed25519
+        });
+        if pinned_mismatch {
+            bail!("Olm payload claims an Ed25519 key that differs from the sender's pinned device");
+        }
+
+        match field("/type") {
+            Some("m.room_key") => self
+                .import_room_key(sender_key_b64, &payload["content"])
+                .map(Some),
+            other => {
+                debug!(event_type = ?other, "Ignoring decrypted to-device event");
+                Ok(None)
+            }
+        }
+    }
+
+    fn olm_decrypt(
+        &mut self,
+        sender_key: Curve25519PublicKey,
+        message: &OlmMessage,
+    ) -> anyhow::Result<Vec<u8>> {
+        let sender_key_b64 = sender_key.to_base64();
+        if let Some(sessions) = self.olm_sessions.get_mut(&sender_key_b64) {
+            for session in sessions.iter_mut().rev() {
+                if let Ok(plaintext) = session.decrypt(message) {
+                    return Ok(plaintext);
+                }
+            }
+        }
+        match message {
+            OlmMessage::PreKey(pre_key) => {
+                let created = self.account.create_inbound_session(sender_key, pre_key)?;
+                self.olm_sessions
+                    .entry(sender_key_b64)
+                    .or_default()
+                    .push(created.session);
+                Ok(created.plaintext)
+            }
+            OlmMessage::Normal(_) => {
+                bail!("no Olm session with {sender_key_b64} could decrypt the message")
+            }
+        }
+    }
+
+    fn import_room_key(&mut self, sender_key: &str, content: &Value) -> anyhow::Result<String> {
+        let field = |name: &str| {
+            content
+                .get(name)
+                .and_then(|v| v.as_str())
+                .with_context(|| format!("m.room_key without {name}"))
+        };
+        if field("algorithm")? != MEGOLM_ALGORITHM {
+            bail!("unsupported room key algorithm");
+        }
+        let room_id = field("room_id")?;
+        let session_id = field("session_id")?;
+        let session_key = SessionKey::from_base64(field("session_key")?)?;
+        let session = InboundGroupSession::new(&session_key, megolm::SessionConfig::version_1());
+        if session.session_id() != session_id {
+            bail!("m.room_key session_id does not match its session_key");
+        }
+        // Keep the first copy: a later one may start at a higher index.
+        self.inbound
+            .entry(session_id.to_string())
+            .or_insert_with(|| InboundSession {
+                room_id: room_id.to_string(),
+                sender_key: sender_key.to_string(),
+                session,
+                seen: HashMap::new(),
+            });
+        Ok(session_id.to_string())
+    }
+
+    /// Whether `curve25519` is the identity key of a pinned device of
+    /// `user_id`, or of our own device.
+    fn is_device_of(&self, user_id: &str, curve25519: &str) -> bool {
+        if user_id == self.user_id && curve25519 == self.curve25519_key() {
+            return true;
+        }
+        self.device_pins.iter().any(|(key, pin)| {
+            key.split_once('|').is_some_and(|(user, _)| user == user_id)
+                && pin.curve25519 == curve25519
+        })
+    }
+
+    /// Decrypt an `m.room.encrypted` timeline event from `room_id`.
+    ///
+    /// The room key must have come from a pinned device of the event's
+    /// sender, and each message index may only be used by one event id.
+    /// Returns a copy of the event with the plaintext `type` and `content`.
+    pub fn decrypt_room_event(&mut self, room_id: &str, event: &Value) -> anyhow::Result<Value> {
+        let sender = event
+            .get("sender")
+            .and_then(|v| v.as_str())
+            .context("encrypted event without sender")?;
+        let event_id = event
+            .get("event_id")
+            .and_then(|v| v.as_str())
+            .context("encrypted event without event_id")?;
+        let content = &event["content"];
+        let algorithm = content
+            .get("algorithm")
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        if algorithm != MEGOLM_ALGORITHM {
+            bail!("unsupported room algorithm {algorithm:?}");
+        }
+        let session_id = content
+            .get("session_id")
+            .and_then(|v| v.as_str())
+            .context("encrypted event without session_id")?;
+        let ciphertext = content
+            .get("ciphertext")
+            .and_then(|v| v.as_str())
+            .context("encrypted event without ciphertext")?;
+        let Some(inbound) = self.inbound.get(session_id) else {
+            bail!("no room key for session {session_id}");
+        };
+        if inbound.room_id != room_id {
+            bail!("room key for session {session_id} belongs to another room");
+        }
+        if !self.is_device_of(sender, &inbound.sender_key) {
+            bail!("room key for session {session_id} was not sent by a known device of {sender}");
+        }
+        let Some(inbound) = self.inbound.get_mut(session_id) else {
+            bail!("no room key for session {session_id}");
+        };
+        let decrypted = inbound
+            .session
+            .decrypt(&MegolmMessage::from_base64(ciphertext)?)?;
+        let payload: Value = serde_json::from_slice(&decrypted.plaintext)?;
+        if payload.get("room_id").and_then(|v| v.as_str()) != Some(room_id) {
+            bail!("decrypted event names a different room");
+        }
+        match inbound.seen.get(&decrypted.message_index) {
+            Some(seen) if seen != event_id => bail!(
+                "message index {} of session {session_id} was already used by {seen}",
+                decrypted.message_index
+            ),
+            Some(_) => {}
+            None => {
+                inbound
+                    .seen
+                    .insert(decrypted.message_index, event_id.to_string());
+            }
+        }
+
+        let mut event = event.clone();
+        event["type"] = payload["type"].clone();
+        event["content"] = payload["content"].clone();
+        Ok(event)
+    }
+
+    /// Import the room keys from the to-device events of a `/sync` response.
+    pub fn receive_sync_keys(&mut self, sync: &Value) -> anyhow::Result<()> {
+        let Some(to_device) = sync
+            .pointer("/to_device/events")
+            .and_then(|e| e.as_array())
+            .filter(|events| !events.is_empty())
+        else {
+            return Ok(());
+        };
+        for event in to_device {
+            if let Err(e) = self.receive_to_device(event) {
+                warn!(%e, "Could not decrypt Matrix to-device event");
+            }
+        }
+        self.save()
+    }
+
+    /// Senders of encrypted timeline events whose room key came from a device
+    /// that is not pinned yet. Their devices have to be queried (see
+    /// [`Self::devices_from_query`]) before those events can be decrypted.
+    pub fn unpinned_senders(&self, sync: &Value) -> Vec<String> {
+        let mut senders = Vec::new();
+        for (_, event) in crate::matrix::encrypted_timeline_events(sync) {
+            let Some(sender) = event.get("sender").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            let Some(inbound) = event
+                .pointer("/content/session_id")
+                .and_then(|v| v.as_str())
+                .and_then(|id| self.inbound.get(id))
+            else {
+                continue;
+            };
+            if !self.is_device_of(sender, &inbound.sender_key)
+                && !senders.iter().any(|s| s == sender)
+            {
+                senders.push(sender.to_string());
+            }
+        }
+        senders
+    }
+
+    /// Replace every decryptable `m.room.encrypted` timeline event of a
+    /// `/sync` response by its plaintext, after [`Self::receive_sync_keys`].
+    ///
+    /// Events that cannot be decrypted stay encrypted and are logged.
+    /// Returns how many events were decrypted.
+    pub fn decrypt_sync(&mut self, sync: &mut Value) -> anyhow::Result<usize> {
+        let mut decrypted = 0;
+        if let Some(rooms) = sync
+            .pointer_mut("/rooms/join")
+            .and_then(|j| j.as_object_mut())
+        {
+            for (room_id, room) in rooms.iter_mut() {
+                let Some(events) = room
+                    .pointer_mut("/timeline/events")
+                    .and_then(|e| e.as_array_mut())
+                else {
+                    continue;
+                };
+                for event in events.iter_mut() {
+                    if event.get("type").and_then(|v| v.as_str()) != Some("m.room.encrypted") {
+                        continue;
+                    }
+                    match self.decrypt_room_event(room_id, event) {
+                        Ok(plain) => {
+                            *event = plain;
+                            decrypted += 1;
+                        }
+                        Err(e) => warn!(
+                            room = %room_id,
+                            event = event.get("event_id").and_then(|v| v.as_str()).unwrap_or("?"),
+                            session = event.pointer("/content/session_id").and_then(|v| v.as_str()).unwrap_or("?"),
+                            %e,
+                            "Could not decrypt Matrix room event"
+                        ),
+                    }
+                }
+            }
+        }
+
+        // Persist the replay indices of the decrypted messages
+        if decrypted > 0 {
+            self.save()?;
+        }
+        Ok(decrypted)
+    }
+
+    /// Make sure every device in `devices` holds our current room key.
+    ///
+    /// The room session is rotated first when it is too old, has sent too
+    /// many messages, or was shared with a device no longer in the room.
+    /// Returns the `messages` body for `/sendToDevice/m.room.encrypted`
+    /// (empty when nobody needs the key). Devices without an Olm session are
+    /// skipped and retried on the next send.
+    pub fn share_room_key(&mut self, room_id: &str, devices: &[Device]) -> Value {
+        let current: HashSet<String> = devices.iter().map(Device::key).collect();
+        let now = chrono::Utc::now().timestamp();
+        let rotate = self.outbound.get(room_id).is_none_or(|s| {
+            s.session.message_index() >= ROTATION_MESSAGES
+                || now - s.created_at >= ROTATION_SECS
+                || !s.shared_with.is_subset(&current)
+        });
+        if rotate {
+            let session = GroupSession::new(megolm::SessionConfig::version_1());
+            // Keep the inbound half so our own messages decrypt when they echo back
+            self.inbound.insert(
+                session.session_id(),
+                InboundSession {
+                    room_id: room_id.to_string(),
+                    sender_key: self.curve25519_key(),
+                    session: InboundGroupSession::from(&session),
+                    seen: HashMap::new(),
+                },
+            );
+            self.outbound.insert(
+                room_id.to_string(),
+                OutboundSession {
+                    session,
+                    created_at: now,
+                    shared_with: HashSet::new(),
+                },
+            );
+        }
+
+        let our_curve25519 = self.curve25519_key();
+        let our_ed25519 = self.ed25519_key();
+        let Some(outbound) = self.outbound.get_mut(room_id) else {
+            return json!({});
+        };
+        let room_key = json!({
+            "algorithm": MEGOLM_ALGORITHM,
+            "room_id": room_id,
+            "session_id": outbound.session.session_id(),
+            "session_key": outbound.session.session_key().to_base64(),
+        });
+
+        let mut messages = json!({});
+        for device in devices {
+            if outbound.shared_with.contains(&device.key()) {
+                continue;
+            }
+            let their_curve25519 = device.curve25519.to_base64();
+            let Some(session) = self
+                .olm_sessions
+                .get_mut(&their_curve25519)
+                .and_then(|s| s.last_mut())
+            else {
+                warn!(user = %device.user_id, device = %device.device_id, "No Olm session; room key not shared");
+                continue;
+            };
+            let payload = json!({
+                "type": "m.room_key",
+                "content": room_key,
+                "sender": self.user_id,
+                "sender_device": self.device_id,
+                "keys": { "ed25519": our_ed25519 },
+                "recipient": device.user_id,
+                "recipient_keys": { "ed25519": device.ed25519.to_base64() },
+            });
+            let message = session.encrypt(payload.to_string());
+            messages[&device.user_id][&device.device_id] = json!({
+                "algorithm": OLM_ALGORITHM,
+                "sender_key": our_curve25519,
+                "ciphertext": { their_curve25519: message },
+            });
+            outbound.shared_with.insert(device.key());
+        }
+        messages
+    }
+
+    /// Drop the room's outbound session, e.g. after its key failed to send.
+    pub fn discard_room_session(&mut self, room_id: &str) {
+        self.outbound.remove(room_id);
+    }
+
+    /// Encrypt an event for `room_id` with the session set up by
+    /// [`share_room_key`](Self::share_room_key). Returns the
+    /// `m.room.encrypted` content.
+    pub fn encrypt_room_event(
+        &mut self,
+        room_id: &str,
+        event_type: &str,
+        content: &Value,
+    ) -> anyhow::Result<Value> {
+        let Some(outbound) = self.outbound.get_mut(room_id) else {
+            bail!("no room key has been shared for {room_id}");
+        };
+        let payload = json!({ "type": event_type, "content": content, "room_id": room_id });
+        let message = outbound.session.encrypt(payload.to_string());
+        Ok(json!({
+            "algorithm": MEGOLM_ALGORITHM,
+            "sender_key": self.account.curve25519_key().to_base64(),
+            "device_id": self.device_id,
+            "session_id": outbound.session.session_id(),
+            "ciphertext": message.to_base64(),
+        }))
+    }
+
+    /// Public part of our cross-signing master key, if one was created here.
+    pub fn master_public_key(&self) -> Option<String> {
+        self.cross_signing
+            .as_ref()
+            .map(|c| c.master.public_key().to_base64())
+    }
+
+    pub fn cross_signing_published(&self) -> bool {
+        self.cross_signing.as_ref().is_some_and(|c| c.published)
+    }
+
+    /// Body for `/keys/device_signing/upload`, creating the cross-signing
+    /// keys on first use. The self-signing and user-signing keys are signed
+    /// by the master key.
+    pub fn bootstrap_cross_signing(&mut self) -> anyhow::Result<Value> {
+        if self.cross_signing.is_none() {
+            self.cross_signing = Some(CrossSigningKeys {
+                master: Ed25519SecretKey::new(),
+                self_signing: Ed25519SecretKey::new(),
+                user_signing: Ed25519SecretKey::new(),
+                published: false,
+            });
+            self.save_secrets()?;
+        }
+        let Some(keys) = self.cross_signing.as_ref() else {
+            bail!("cross-signing keys missing");
+        };
+        let master_id = format!("ed25519:{}", keys.master.public_key().to_base64());
+        let signed_by_master = |usage: &str, key: &Ed25519SecretKey| {
+            let mut object = cross_signing_key(&self.user_id, usage, key);
+            sign_json(&mut object, &self.user_id, &master_id, |m| {
+                keys.master.sign(m)
+            });
+            object
+        };
+        Ok(json!({
+            "master_key": cross_signing_key(&self.user_id, "master", &keys.master),
+            "self_signing_key": signed_by_master("self_signing", &keys.self_signing),
+            "user_signing_key": signed_by_master("user_signing", &keys.user_signing),
+        }))
+    }
+
+    pub fn mark_cross_signing_published(&mut self) -> anyhow::Result<()> {
+        if let Some(keys) = self.cross_signing.as_mut() {
+            keys.published = true;
+        }
+        self.save_secrets()
+    }
+
+    /// Body for `/keys/signatures/upload`: this device signed by the
+    /// self-signing key, and the master key signed by this device.
+    pub fn cross_signing_signatures(&self) -> Option<Value> {
+        let keys = self.cross_signing.as_ref()?;
+        let self_signing_id = format!("ed25519:{}", keys.self_signing.public_key().to_base64());
+        let mut device = self.device_keys();
+        sign_json(&mut device, &self.user_id, &self_signing_id, |m| {
+            keys.self_signing.sign(m)
+        });
+        let mut master = cross_signing_key(&self.user_id, "master", &keys.master);
+        self.sign_with_device(&mut master);
+
+        let mut body = json!({});
+        body[&self.user_id][&self.device_id] = device;
+        body[&self.user_id][keys.master.public_key().to_base64()] = master;
+        Some(body)
+    }
+}
+
+/// The master key published for `user_id` in a `/keys/query` response.
+pub fn published_master_key(response: &Value, user_id: &str) -> Option<String> {
+    response
+        .get("master_keys")
+        .and_then(|m| m.get(user_id))
+        .and_then(|k| k.get("keys"))
+        .and_then(|k| k.as_object())
+        .and_then(|k| k.values().next())
+        .and_then(|v| v.as_str())
+        .map(String::from)
+}
+
+fn cross_signing_key(user_id: &str, usage: &str, key: &Ed25519SecretKey) -> Value {
+    let public = key.public_key().to_base64();
+    json!({
+        "user_id": user_id,
+        "usage": [usage],
+        "keys": { format!("ed25519:{public}"): public },
+    })
+}
+
+fn load_or_create_secrets(dir: &Path) -> anyhow::Result<SecretsFile> {
+    let path = dir.join(SECRETS_FILE);
+    if path.exists() {
+        return serde_json::from_str(&std::fs::read_to_string(&path)?)
+            .with_context(|| format!("reading {}", path.display()));
+    }
+    let secrets = SecretsFile {
+        pickle_key: base64_encode(rand::random::<[u8; 32]>()),
+        cross_signing: None,
+    };
+    write_private(&path, &serde_json::to_string_pretty(&secrets)?)?;
+    Ok(secrets)
+}
+
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, contents)?;
+    restrict_permissions(&tmp);
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600));
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) {}
+
+/// Matrix canonical JSON: object keys sorted, no insignificant whitespace.
+fn canonical_json(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            let fields: Vec<String> = entries
+                .into_iter()
+                .map(|(k, v)| format!("{}:{}", Value::String(k.clone()), canonical_json(v)))
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(canonical_json).collect();
+            format!("[{}]", items.join(","))
+        }
+        other => other.to_string(),
+    }
+}
+
+/// The bytes a Matrix signature covers: the object without `signatures` and `unsigned`.
+fn signable_json(value: &Value) -> String {
+    let mut value = value.clone();
+    if let Some(object) = value.as_object_mut() {
+        object.remove("signatures");
+        object.remove("unsigned");
+    }
+    canonical_json(&value)
+}
+
+fn sign_json(
+    value: &mut Value,
+    user_id: &str,
+    key_id: &str,
+    sign: impl FnOnce(&[u8]) -> Ed25519Signature,
+) {
+    let signature = sign(signable_json(value).as_bytes()).to_base64();
+    value["signatures"][user_id][key_id] = Value::String(signature);
+}
+
+fn verify_json(value: &Value, user_id: &str, key_id: &str, key: &Ed25519PublicKey) -> bool {
+    value
+        .get("signatures")
+        .and_then(|s| s.get(user_id))
+        .and_then(|s| s.get(key_id))
+        .and_then(|s| s.as_str())
+        .and_then(|s| Ed25519Signature::from_base64(s).ok())
+        .is_some_and(|signature| {
+            key.verify(signable_json(value).as_bytes(), &signature)
+                .is_ok()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE: &str = "@alice:example.org";
+    const BOT: &str = "@bot:example.org";
+
+    fn open(dir: &tempfile::TempDir, user_id: &str, device_id: &str) -> MatrixCrypto {
+        MatrixCrypto::open(&dir.path().join(device_id), user_id, device_id).unwrap()
+    }
+
+    fn query_response(devices: &[&MatrixCrypto]) -> Value {
+        let mut response = json!({ "device_keys": {} });
+        for d in devices {
+            response["device_keys"][d.user_id()][d.device_id()] = d.device_keys();
+        }
+        response
+    }
+
+    /// Claim one of `target`'s uploaded one-time keys, as `/keys/claim` would.
+    fn claim(target: &mut MatrixCrypto) -> Value {
+        let upload = target.keys_for_upload(0).unwrap();
+        target.mark_keys_uploaded().unwrap();
+        let (id, key) = upload["one_time_keys"]
+            .as_object()
+            .unwrap()
+            .iter()
+            .next()
+            .unwrap();
+        json!({ id.clone(): key.clone() })
+    }
+
+    /// `from` shares its room key with `to`, which imports it and pins
+    /// `from`'s device, as the sync loop does for unpinned senders.
+    fn share(from: &mut MatrixCrypto, to: &mut MatrixCrypto, room_id: &str) {
+        let devices = from.devices_from_query(&query_response(&[to]));
+        assert_eq!(devices.len(), 1);
+        if !from.devices_without_session(&devices).is_empty() {
+            let claimed = claim(to);
+            from.create_outbound_olm_session(&devices[0], &claimed)
+                .unwrap();
+        }
+        let messages = from.share_room_key(room_id, &devices);
+        let content = messages[to.user_id()][to.device_id()].clone();
+        assert!(!content.is_null());
+        let event =
+            json!({ "type": "m.room.encrypted", "sender": from.user_id(), "content": content });
+        assert!(to.receive_to_device(&event).unwrap().is_some());
+        assert_eq!(to.devices_from_query(&query_response(&[from])).len(), 1);
+    }
+
+    #[test]
+    fn test_canonical_json_sorts_keys() {
+        let value = json!({"b": 1, "a": {"d": [true, null], "c": "é"}});
+        assert_eq!(
+            canonical_json(&value),
+            r#"{"a":{"c":"é","d":[true,null]},"b":1}"#
+        );
+    }
+
+    #[test]
+    fn test_device_keys_are_self_signed() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut alice = open(&dir, ALICE, "ALICEDEV");
+        let mut bot = open(&dir, BOT, "BOTDEV");
+
+        let devices = alice.devices_from_query(&query_response(&[&bot]));
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].device_id, "BOTDEV");
+
+        // Our own device is never a recipient
+        let own = query_response(&[&bot]);
+        assert!(bot.devices_from_query(&own).is_empty());
+
+        // A tampered key object fails the self-signature check
+        let mut response = query_response(&[&bot]);
+        response["device_keys"][BOT]["BOTDEV"]["keys"]["curve25519:BOTDEV"] =
+            json!(alice.curve25519_key());
+        let mut fresh = open(&dir, ALICE, "ALICEDEV2");
+        assert!(fresh.devices_from_query(&response).is_empty());
+    }
+
+    #[test]
+    fn test_changed_device_keys_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut alice = open(&dir, ALICE, "ALICEDEV");
+        let bot = open(&dir, BOT, "BOTDEV");
+        assert_eq!(alice.devices_from_query(&query_response(&[&bot])).len(), 1);
+
+        // Same user and device id, new identity keys: refused after pinning
+        let impostor_dir = tempfile::tempdir().unwrap();
+        let impostor = open(&impostor_dir, BOT, "BOTDEV");
+        assert!(
+            alice
+                .devices_from_query(&query_response(&[&impostor]))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_keys_for_upload() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut bot = open(&dir, BOT, "BOTDEV");
+
+        let upload = bot.keys_for_upload(0).unwrap();
+        assert_eq!(upload["device_keys"], bot.device_keys());
+        let one_time_keys = upload["one_time_keys"].as_object().unwrap();
+        assert_eq!(
+            one_time_keys.len(),
+            bot.account.max_number_of_one_time_keys() / 2
+        );
+        let ed25519 = Ed25519PublicKey::from_base64(&bot.ed25519_key()).unwrap();
+        for key in one_time_keys.values() {
+            assert!(verify_json(key, BOT, "ed25519:BOTDEV", &ed25519));
+        }
+        let fallback = upload["fallback_keys"].as_object().unwrap();
+        assert_eq!(fallback.len(), 1);
+        assert_eq!(fallback.values().next().unwrap()["fallback"], json!(true));
+
+        bot.mark_keys_uploaded().unwrap();
+        let target = (bot.account.max_number_of_one_time_keys() / 2) as u64;
+        assert!(bot.keys_for_upload(target).is_none());
+        // Top up after keys were claimed
+        let upload = bot.keys_for_upload(target - 3).unwrap();
+        assert!(upload.get("device_keys").is_none());
+        assert_eq!(upload["one_time_keys"].as_object().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_room_key_and_message_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut alice = open(&dir, ALICE, "ALICEDEV");
+        let mut bot = open(&dir, BOT, "BOTDEV");
+        let room = "!secret:example.org";
+
+        share(&mut alice, &mut bot, room);
+        let content = json!({"msgtype": "m.text", "body": "hello bot"});
+        let encrypted = alice
+            .encrypt_room_event(room, "m.room.message", &content)
+            .unwrap();
+        assert_eq!(encrypted["algorithm"], MEGOLM_ALGORITHM);
+        assert!(!encrypted.to_string().contains("hello bot"));
+
+        let mut sync = json!({
+            "rooms": {"join": {room: {"timeline": {"events": [
+                {"type": "m.room.encrypted", "sender": ALICE, "event_id": "$1", "content": encrypted}
+            ]}}}}
+        });
+        assert_eq!(bot.decrypt_sync(&mut sync).unwrap(), 1);
+        let event = &sync["rooms"]["join"][room]["timeline"]["events"][0];
+        assert_eq!(event["type"], "m.room.message");
+        assert_eq!(event["content"], content);
+        assert_eq!(event["event_id"], "$1");
+        let own_echo = sync["rooms"]["join"][room]["timeline"]["events"][0].clone();
+        let mut echo = own_echo.clone();
+        echo["type"] = json!("m.room.encrypted");
+        echo["content"] = encrypted.clone();
+        assert_eq!(alice.decrypt_room_event(room, &echo).unwrap(), own_echo);
+        assert_eq!(
+            crate::matrix::parse_sync_messages(&sync, Some(BOT)),
+            vec![(ALICE.to_string(), "hello bot".to_string(), room.to_string())]
+        );
+
+        // And back: the bot answers over the Olm session Alice started
+        share(&mut bot, &mut alice, room);
+        let reply = bot
+            .encrypt_room_event(room, "m.room.message", &json!({"body": "hi alice"}))
+            .unwrap();
+        let event =
+            json!({"type": "m.room.encrypted", "sender": BOT, "event_id": "$2", "content": reply});
+        let decrypted = alice.decrypt_room_event(room, &event).unwrap();
+        assert_eq!(decrypted["content"]["body"], "hi alice");
+    }
+
+    #[test]
+    fn test_room_key_is_bound_to_its_room() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut alice = open(&dir, ALICE, "ALICEDEV");
+        let mut bot = open(&dir, BOT, "BOTDEV");
+
+        share(&mut alice, &mut bot, "!a:example.org");
+        let encrypted = alice
+            .encrypt_room_event("!a:example.org", "m.room.message", &json!({"body": "x"}))
+            .unwrap();
+        let event =
+            json!({"type": "m.room.encrypted", "sender": ALICE, "event_id": "$1", "content": encrypted});
+        assert!(bot.decrypt_room_event("!b:example.org", &event).is_err());
+        assert!(bot.decrypt_room_event("!a:example.org", &event).is_ok());
+    }
+
+    #[test]
+    fn test_room_event_must_come_from_the_key_sender() {
+        const MALLORY: &str = "@mallory:example.org";
+        let dir = tempfile::tempdir().unwrap();
+        let mut alice = open(&dir, ALICE, "ALICEDEV");
+        let mut bot = open(&dir, BOT, "BOTDEV");
+        let mallory = open(&dir, MALLORY, "MALLORYDEV");
+        let room = "!a:example.org";
+
+        // Bot imports Alice's room key but has not pinned her device yet
+        let devices = alice.devices_from_query(&query_response(&[&bot]));
+        let claimed = claim(&mut bot);
+        alice
+            .create_outbound_olm_session(&devices[0], &claimed)
+            .unwrap();
+        let messages = alice.share_room_key(room, &devices);
+        let to_device =
+            json!({"type": "m.room.encrypted", "sender": ALICE, "content": messages[BOT]["BOTDEV"]});
+        bot.receive_to_device(&to_device).unwrap();
+        let encrypted = alice
+            .encrypt_room_event(room, "m.room.message", &json!({"body": "from alice"}))
+            .unwrap();
+        let event =
+            json!({"type": "m.room.encrypted", "sender": ALICE, "event_id": "$1", "content": encrypted});
+        let sync = json!({"rooms": {"join": {room: {"timeline": {"events": [event]}}}}});
+        assert_eq!(bot.unpinned_senders(&sync), vec![ALICE.to_string()]);
+        assert!(bot.decrypt_room_event(room, &event).is_err());
+
+        // Mallory's own pinned device does not make Alice's key hers
+        bot.devices_from_query(&query_response(&[&alice, &mallory]));
+        assert!(bot.unpinned_senders(&sync).is_empty());
+        let mut forged = event.clone();
+        forged["sender"] = json!(MALLORY);
+        assert!(bot.decrypt_room_event(room, &forged).is_err());
+        assert_eq!(
+            bot.decrypt_room_event(room, &event).unwrap()["content"]["body"],
+            "from alice"
+        );
+    }
+
+    #[test]
+    fn test_replayed_message_index_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut alice = open(&dir, ALICE, "ALICEDEV");
+        let mut bot = open(&dir, BOT, "BOTDEV");
+        let room = "!a:example.org";
+        share(&mut alice, &mut bot, room);
+
+        let encrypted = alice
+            .encrypt_room_event(room, "m.room.message", &json!({"body": "once"}))
+            .unwrap();
+        let event =
+            json!({"type": "m.room.encrypted", "sender": ALICE, "event_id": "$1", "content": encrypted});
+        assert!(bot.decrypt_room_event(room, &event).is_ok());
+        // The same event may come back (e.g. in a later sync), a copy may not
+        assert!(bot.decrypt_room_event(room, &event).is_ok());
+        let mut replay = event.clone();
+        replay["event_id"] = json!("$2");
+        assert!(bot.decrypt_room_event(room, &replay).is_err());
+
+        // The seen indices are persisted with the session
+        bot.save().unwrap();
+        let mut reopened = open(&dir, BOT, "BOTDEV");
+        assert!(reopened.decrypt_room_event(room, &replay).is_err());
+        assert!(reopened.decrypt_room_event(room, &event).is_ok());
+    }
+
+    #[test]
+    fn test_to_device_for_other_recipient_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut alice = open(&dir, ALICE, "ALICEDEV");
+        let mut bot = open(&dir, BOT, "BOTDEV");
+        let devices = alice.devices_from_query(&query_response(&[&bot]));
+        let claimed = claim(&mut bot);
+        alice
+            .create_outbound_olm_session(&devices[0], &claimed)
+            .unwrap();
+        let messages = alice.share_room_key("!a:example.org", &devices);
+
+        // Relayed under another sender's name
+        let event = json!({
+            "type": "m.room.encrypted",
+            "sender": "@mallory:example.org",
+            "content": messages[BOT]["BOTDEV"],
+        });
+        assert!(bot.receive_to_device(&event).is_err());
+    }
+
+    #[test]
+    fn test_outbound_session_rotates_when_a_device_leaves() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut alice = open(&dir, ALICE, "ALICEDEV");
+        let mut bot = open(&dir, BOT, "BOTDEV");
+        let mut other = open(&dir, "@carol:example.org", "CAROLDEV");
+        let room = "!a:example.org";
+
+        let devices = alice.devices_from_query(&query_response(&[&bot, &other]));
+        for device in devices.clone() {
+            let claimed = if device.user_id == BOT {
+                claim(&mut bot)
+            } else {
+                claim(&mut other)
+            };
+            alice
+                .create_outbound_olm_session(&device, &claimed)
+                .unwrap();
+        }
+        let first = alice.share_room_key(room, &devices);
+        assert_eq!(first.as_object().unwrap().len(), 2);
+        let session_id = alice.outbound[room].session.session_id();
+
+        // Nothing new to share while membership is unchanged
+        assert_eq!(alice.share_room_key(room, &devices), json!({}));
+        assert_eq!(alice.outbound[room].session.session_id(), session_id);
+
+        // Carol leaves: a fresh session goes to the remaining device only
+        let remaining: Vec<Device> = devices.into_iter().filter(|d| d.user_id == BOT).collect();
+        let second = alice.share_room_key(room, &remaining);
+        assert_ne!(alice.outbound[room].session.session_id(), session_id);
+        assert_eq!(second.as_object().unwrap().len(), 1);
+        assert!(second.get(BOT).is_some());
+    }
+
+    #[test]
+    fn test_store_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut alice = open(&dir, ALICE, "ALICEDEV");
+        let mut bot = open(&dir, BOT, "BOTDEV");
+        let room = "!secret:example.org";
+        share(&mut alice, &mut bot, room);
+        bot.save().unwrap();
+        let curve25519 = bot.curve25519_key();
+
+        let mut reopened = open(&dir, BOT, "BOTDEV");
+        assert_eq!(reopened.curve25519_key(), curve25519);
+        assert!(reopened.device_keys_uploaded);
+        let encrypted = alice
+            .encrypt_room_event(room, "m.room.message", &json!({"body": "after restart"}))
+            .unwrap();
+        let event =
+            json!({"type": "m.room.encrypted", "sender": ALICE, "event_id": "$1", "content": encrypted});
+        let decrypted = reopened.decrypt_room_event(room, &event).unwrap();
+        assert_eq!(decrypted["content"]["body"], "after restart");
+
+        // Nothing in the store is readable without the pickle key
+        let store = std::fs::read_to_string(dir.path().join("BOTDEV").join(STORE_FILE)).unwrap();
+        assert!(!store.contains("after restart"));
+        let secrets = dir.path().join("BOTDEV").join(SECRETS_FILE);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&secrets).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // A different device id starts a new identity
+        let replaced = MatrixCrypto::open(&dir.path().join("BOTDEV"), BOT, "NEWDEV").unwrap();
+        assert_ne!(replaced.curve25519_key(), curve25519);
+        assert!(!replaced.device_keys_uploaded);
+    }
+
+    #[test]
+    fn test_cross_signing_bootstrap() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut bot = open(&dir, BOT, "BOTDEV");
+        assert!(bot.cross_signing_signatures().is_none());
+
+        let upload = bot.bootstrap_cross_signing().unwrap();
+        let master_b64 = bot.master_public_key().unwrap();
+        let master = Ed25519PublicKey::from_base64(&master_b64).unwrap();
+        let master_id = format!("ed25519:{master_b64}");
+        assert_eq!(upload["master_key"]["usage"], json!(["master"]));
+        assert!(verify_json(
+            &upload["self_signing_key"],
+            BOT,
+            &master_id,
+            &master
+        ));
+        assert!(verify_json(
+            &upload["user_signing_key"],
+            BOT,
+            &master_id,
+            &master
+        ));
+        assert_eq!(
+            published_master_key(&json!({"master_keys": {BOT: upload["master_key"]}}), BOT),
+            Some(master_b64.clone())
+        );
+
+        // The device is signed by the self-signing key
+        let self_signing_b64 = upload["self_signing_key"]["keys"]
+            .as_object()
+            .unwrap()
+            .values()
+            .next()
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .to_string();
+        let self_signing = Ed25519PublicKey::from_base64(&self_signing_b64).unwrap();
+        let signatures = bot.cross_signing_signatures().unwrap();
+        let device = &signatures[BOT]["BOTDEV"];
+        assert!(verify_json(
+            device,
+            BOT,
+            &format!("ed25519:{self_signing_b64}"),
+            &self_signing
+        ));
+        let device_key = Ed25519PublicKey::from_base64(&bot.ed25519_key()).unwrap();
+        assert!(verify_json(
+            &signatures[BOT][&master_b64],
+            BOT,
+            "ed25519:BOTDEV",
+            &device_key
+        ));
+
+        // Keys and the published flag survive a restart; bootstrap is stable
+        assert!(!bot.cross_signing_published());
+        bot.mark_cross_signing_published().unwrap();
+        let mut reopened = open(&dir, BOT, "BOTDEV");
+        assert!(reopened.cross_signing_published());
+        assert_eq!(reopened.master_public_key(), Some(master_b64));
+        assert_eq!(reopened.bootstrap_cross_signing().unwrap(), upload);
+    }
+}
@@ -161,9 +161,11 @@ impl Channel for MsTeamsChannel {
                                     text: Some(text),
                                     media: vec![],
                                     reply_to: None,
+                                    message_id: None,
                                     thread_id: None,
                                     timestamp: chrono::Utc::now(),
                                     raw: None,
+                                    reaction: None,
                                 };
                                 let _ = inbound_tx.send(msg);
                             }
@@ -202,6 +204,7 @@ impl Channel for MsTeamsChannel {
         if text.is_empty() {
             return Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: true,
                 error: None,
             });
@@ -228,6 +231,7 @@ impl Channel for MsTeamsChannel {
                 let status = r.status();
                 return Ok(SendResult {
                     message_id: None,
+                    message_ids: vec![],
                     success: false,
                     error: Some(format!("OAuth error {status}")),
                 });
@@ -235,6 +239,7 @@ impl Channel for MsTeamsChannel {
             Err(e) => {
                 return Ok(SendResult {
                     message_id: None,
+                    message_ids: vec![],
                     success: false,
                     error: Some(format!("OAuth request failed: {e}")),
                 });
@@ -262,6 +267,7 @@ impl Channel for MsTeamsChannel {
         match resp {
             Ok(r) if r.status().is_success() => Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: true,
                 error: None,
             }),
@@ -270,12 +276,14 @@ impl Channel for MsTeamsChannel {
                 error!(%status, "Teams send failed");
                 Ok(SendResult {
                     message_id: None,
+                    message_ids: vec![],
                     success: false,
                     error: Some(format!("Teams API error {status}")),
                 })
             }
             Err(e) => Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: false,
                 error: Some(e.to_string()),
             }),
@@ -0,0 +1,200 @@
+//! Outbound rate limiting shared by channel implementations.
+//!
+//! A [`RateLimiter`] is a token bucket: `burst` sends can go out at once,
+//! after which sends are paced at `messages_per_second`. Waiting callers are
+//! served in order. When a platform answers 429, [`RateLimiter::pause`] holds
+//! every sender back for the `Retry-After` period; [`RateLimiter::send`]
+//! wraps a reqwest call with both.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use tracing::warn;
+
+use rusty_claw_core::config::ChannelRateLimitConfig;
+
+/// How often a rate-limited request is retried before giving up.
+const MAX_RETRIES: u32 = 3;
+
+/// Wait used when a 429 carries no usable `Retry-After`.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Longest a sender is held back, whatever the rate or `Retry-After` says.
+pub const MAX_WAIT: Duration = Duration::from_secs(3600);
+
+/// Token-bucket limiter for one channel's outbound sends.
+pub struct RateLimiter {
+    messages_per_second: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+    /// Held while a caller waits for its token, so callers queue in order.
+    turn: tokio::sync::Mutex<()>,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+    paused_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(messages_per_second: f64, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+        Self {
+            messages_per_second: messages_per_second.max(0.0),
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                refilled_at: Instant::now(),
+                paused_until: None,
+            }),
+            turn: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Build from channel config, falling back to the channel's defaults.
+    pub fn from_config(
+        config: Option<&ChannelRateLimitConfig>,
+        default_per_second: f64,
+        default_burst: u32,
+    ) -> Self {
+        match config {
+            Some(c) => Self::new(c.messages_per_second, c.burst),
+            None => Self::new(default_per_second, default_burst),
+        }
+    }
+
+    /// Wait until a send is allowed and take a token for it.
+    pub async fn acquire(&self) {
+        let _turn = self.turn.lock().await;
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let now = Instant::now();
+                match bucket.paused_until {
+                    Some(until) if until > now => until - now,
+                    _ => {
+                        bucket.paused_until = None;
+                        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+                        bucket.tokens =
+                            (bucket.tokens + elapsed * self.messages_per_second).min(self.burst);
+                        bucket.refilled_at = now;
+                        if bucket.tokens >= 1.0 {
+                            bucket.tokens -= 1.0;
+                            return;
+                        }
+                        capped_secs((1.0 - bucket.tokens) / self.messages_per_second)
+                    }
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Hold all sends back for `duration` (e.g. a platform's `Retry-After`).
+    pub fn pause(&self, duration: Duration) {
+        let mut bucket = self.bucket.lock().unwrap();
+        let until = Instant::now() + duration.min(MAX_WAIT);
+        bucket.paused_until = Some(bucket.paused_until.map_or(until, |current| current.max(until)));
+        bucket.tokens = 0.0;
+    }
+
+    /// Send a request at the limiter's pace, retrying on 429 after the
+    /// platform's `Retry-After`. The last response is returned as-is.
+    pub async fn send(&self, request: reqwest::RequestBuilder) -> reqwest::Result<reqwest::Response> {
+        let mut request = request;
+        let mut attempt = 0;
+        loop {
+            self.acquire().await;
+            let retry = request.try_clone();
+            let response = request.send().await?;
+            if response.status() != StatusCode::TOO_MANY_REQUESTS || attempt == MAX_RETRIES {
+                return Ok(response);
+            }
+            let Some(next) = retry else {
+                return Ok(response);
+            };
+            let wait = parse_retry_after(response.headers()).unwrap_or(DEFAULT_RETRY_AFTER);
+            warn!(retry_after = ?wait, attempt, "Rate limited by platform, retrying");
+            self.pause(wait);
+            request = next;
+            attempt += 1;
+        }
+    }
+}
+
+/// Parse a `Retry-After` header given in (possibly fractional) seconds,
+/// capped at [`MAX_WAIT`].
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let seconds: f64 = headers.get(RETRY_AFTER)?.to_str().ok()?.trim().parse().ok()?;
+    (seconds >= 0.0).then(|| capped_secs(seconds))
+}
+
+/// `seconds` as a duration, capped at [`MAX_WAIT`]. Infinite or too large
+/// values (e.g. from a zero rate) are capped rather than overflowing.
+fn capped_secs(seconds: f64) -> Duration {
+    Duration::try_from_secs_f64(seconds).map_or(MAX_WAIT, |wait| wait.min(MAX_WAIT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_burst_then_paced() {
+        let limiter = RateLimiter::new(20.0, 2);
+        let start = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert!(start.elapsed() < Duration::from_millis(20));
+
+        // The third send waits for a token (50ms at 20/s)
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn test_pause_holds_sends() {
+        let limiter = RateLimiter::new(1000.0, 5);
+        limiter.pause(Duration::from_millis(60));
+        let start = Instant::now();
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, "3".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(3)));
+        headers.insert(RETRY_AFTER, "0.5".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_millis(500)));
+        headers.insert(RETRY_AFTER, "soon".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, "-1".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), None);
+    }
+
+    #[test]
+    fn test_huge_retry_after_is_capped() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, "1e30".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), Some(MAX_WAIT));
+        headers.insert(RETRY_AFTER, "604800".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), Some(MAX_WAIT));
+        headers.insert(RETRY_AFTER, "inf".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), Some(MAX_WAIT));
+    }
+
+    #[tokio::test]
+    async fn test_zero_rate_waits_without_panicking() {
+        let limiter = RateLimiter::new(0.0, 1);
+        limiter.acquire().await;
+        // The bucket never refills; the wait is capped instead of overflowing
+        let next = tokio::time::timeout(Duration::from_millis(20), limiter.acquire()).await;
+        assert!(next.is_err());
+    }
+}
@@ -1,11 +1,15 @@
 //! Signal channel implementation via signal-cli REST bridge.
+//!
+//! Inbound attachments are downloaded from the bridge by id; outbound media
+//! is sent inline as base64 attachments.
 
 use async_trait::async_trait;
+use base64::Engine;
 use tokio::sync::mpsc;
 use tracing::{error, info, warn};
 
 use rusty_claw_core::types::{
-    ChatType, InboundMessage, OutboundMessage, SendResult, SendTarget, Sender,
+    ChatType, InboundMessage, MediaAttachment, OutboundMessage, SendResult, SendTarget, Sender,
 };
 
 use crate::{
@@ -28,8 +32,31 @@ impl SignalChannel {
     }
 }
 
+/// Attachments larger than this are passed on by URL only.
+const MAX_ATTACHMENT_BYTES: u64 = 25 * 1024 * 1024;
+
+/// An attachment listed in an inbound envelope.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignalAttachment {
+    pub id: String,
+    #[serde(default = "default_content_type")]
+    pub content_type: String,
+    #[serde(default)]
+    pub filename: Option<String>,
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+fn default_content_type() -> String {
+    "application/octet-stream".into()
+}
+
+/// (sender, text, group id, attachments) for one inbound message.
+pub type ParsedEnvelope = (String, String, Option<String>, Vec<SignalAttachment>);
+
 /// Parse signal-cli REST envelope response into (sender, text) pairs.
-pub fn parse_envelopes(envelopes: &[serde_json::Value]) -> Vec<(String, String, Option<String>)> {
+pub fn parse_envelopes(envelopes: &[serde_json::Value]) -> Vec<ParsedEnvelope> {
     let mut messages = Vec::new();
 
     for envelope in envelopes {
@@ -57,9 +84,13 @@ pub fn parse_envelopes(envelopes: &[serde_json::Value]) -> Vec<(String, String,
                 .and_then(|g| g.get("groupId"))
                 .and_then(|v| v.as_str())
                 .map(|s| s.to_string());
+            let attachments: Vec<SignalAttachment> = dm
+                .get("attachments")
+                .and_then(|a| serde_json::from_value(a.clone()).ok())
+                .unwrap_or_default();
 
-            if !source.is_empty() && !text.is_empty() {
-                messages.push((source, text, group_id));
+            if !source.is_empty() && (!text.is_empty() || !attachments.is_empty()) {
+                messages.push((source, text, group_id, attachments));
             }
         }
     }
@@ -67,6 +98,104 @@ pub fn parse_envelopes(envelopes: &[serde_json::Value]) -> Vec<(String, String,
     messages
 }
 
+/// Download an inbound attachment from the bridge. The URL is always kept;
+/// the bytes are included when the download succeeds and is not too large.
+async fn fetch_attachment(
+    client: &reqwest::Client,
+    api_url: &str,
+    attachment: &SignalAttachment,
+) -> MediaAttachment {
+    let url = format!("{api_url}/v1/attachments/{}", attachment.id);
+    let too_large = attachment.size.is_some_and(|s| s > MAX_ATTACHMENT_BYTES);
+    let data = if too_large {
+        None
+    } else {
+        match client.get(&url).send().await {
+            Ok(resp) if resp.status().is_success() => {
+                match read_capped(resp, MAX_ATTACHMENT_BYTES).await {
+                    Ok(data) => Some(data),
+                    Err(e) => {
+                        warn!(%e, id = %attachment.id, "Signal attachment download failed");
+                        None
+                    }
+                }
+            }
+            Ok(resp) => {
+                warn!(status = %resp.status(), id = %attachment.id, "Signal attachment download failed");
+                None
+            }
+            Err(e) => {
+                warn!(%e, id = %attachment.id, "Signal attachment download failed");
+                None
+            }
+        }
+    };
+    MediaAttachment {
+        size_bytes: attachment.size.or(data.as_ref().map(|d| d.len() as u64)),
+        url: Some(url),
+        data,
+        mime_type: attachment.content_type.clone(),
+        filename: attachment.filename.clone(),
+    }
+}
+
+/// Read a response body of at most `max` bytes, refusing a larger declared
+/// `content-length` up front and stopping as soon as the streamed body
+/// passes the limit.
+async fn read_capped(mut resp: reqwest::Response, max: u64) -> Result<Vec<u8>, String> {
+    if resp.content_length().is_some_and(|len| len > max) {
+        return Err(format!("Attachment exceeds {max} bytes"));
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = resp
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to read attachment: {e}"))?
+    {
+        if (body.len() + chunk.len()) as u64 > max {
+            return Err(format!("Attachment exceeds {max} bytes"));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Encode outbound media as the bridge's `base64_attachments` entries,
+/// fetching URL-only media first.
+async fn encode_attachments(
+    client: &reqwest::Client,
+    media: &[MediaAttachment],
+) -> Result<Vec<String>, String> {
+    let mut encoded = Vec::with_capacity(media.len());
+    for item in media {
+        let data = match (&item.data, &item.url) {
+            (Some(data), _) => data.clone(),
+            (None, Some(url)) => client
+                .get(url)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| format!("Failed to fetch attachment {url}: {e}"))?
+                .bytes()
+                .await
+                .map_err(|e| format!("Failed to fetch attachment {url}: {e}"))?
+                .to_vec(),
+            (None, None) => return Err("Attachment has neither data nor url".into()),
+        };
+        encoded.push(attachment_data_uri(item, &data));
+    }
+    Ok(encoded)
+}
+
+/// `data:<mime>;filename=<name>;base64,<data>`, as signal-cli-rest-api expects.
+fn attachment_data_uri(item: &MediaAttachment, data: &[u8]) -> String {
+    let b64 = base64::engine::general_purpose::STANDARD.encode(data);
+    match &item.filename {
+        Some(name) => format!("data:{};filename={name};base64,{b64}", item.mime_type),
+        None => format!("data:{};base64,{b64}", item.mime_type),
+    }
+}
+
 #[async_trait]
 impl Channel for SignalChannel {
     fn id(&self) -> &str {
@@ -122,7 +251,11 @@ impl Channel for SignalChannel {
                             Ok(resp) if resp.status().is_success() => {
                                 if let Ok(envelopes) = resp.json::<Vec<serde_json::Value>>().await {
                                     let parsed = parse_envelopes(&envelopes);
-                                    for (sender, text, group_id) in parsed {
+                                    for (sender, text, group_id, attachments) in parsed {
+                                        let mut media = Vec::with_capacity(attachments.len());
+                                        for attachment in &attachments {
+                                            media.push(fetch_attachment(&client, &api_url, attachment).await);
+                                        }
                                         let chat_type = if group_id.is_some() {
                                             ChatType::Group
                                         } else {
@@ -138,12 +271,14 @@ impl Channel for SignalChannel {
                                                 display_name: None,
                                                 username: None,
                                             },
-                                            text: Some(text),
-                                            media: vec![],
+                                            text: (!text.is_empty()).then_some(text),
+                                            media,
                                             reply_to: None,
+                                            message_id: None,
                                             thread_id: None,
                                             timestamp: chrono::Utc::now(),
                                             raw: None,
+                                            reaction: None,
                                         };
                                         let _ = inbound_tx.send(msg);
                                     }
@@ -170,20 +305,34 @@ impl Channel for SignalChannel {
         message: OutboundMessage,
     ) -> anyhow::Result<SendResult> {
         let text = message.text.unwrap_or_default();
-        if text.is_empty() {
+        if text.is_empty() && message.media.is_empty() {
             return Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: true,
                 error: None,
             });
         }
 
         let client = reqwest::Client::new();
-        let payload = serde_json::json!({
+        let mut payload = serde_json::json!({
             "message": text,
             "number": self.phone_number,
             "recipients": [target.chat_id],
         });
+        if !message.media.is_empty() {
+            match encode_attachments(&client, &message.media).await {
+                Ok(attachments) => payload["base64_attachments"] = serde_json::json!(attachments),
+                Err(e) => {
+                    return Ok(SendResult {
+                        message_id: None,
+                        message_ids: vec![],
+                        success: false,
+                        error: Some(e),
+                    });
+                }
+            }
+        }
 
         let resp = client
             .post(format!("{}/v2/send", self.api_url))
@@ -194,6 +343,7 @@ impl Channel for SignalChannel {
         match resp {
             Ok(r) if r.status().is_success() => Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: true,
                 error: None,
             }),
@@ -202,12 +352,14 @@ impl Channel for SignalChannel {
                 error!(%status, "Signal send failed");
                 Ok(SendResult {
                     message_id: None,
+                    message_ids: vec![],
                     success: false,
                     error: Some(format!("Signal API error {status}")),
                 })
             }
             Err(e) => Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: false,
                 error: Some(e.to_string()),
             }),
@@ -236,6 +388,7 @@ mod tests {
             phone_number: None,
             phone_number_env: Some("TEST_SIGNAL_PHONE_RC".into()),
             poll_interval_ms: 2000,
+            access: Default::default(),
         };
         assert_eq!(config.resolve_phone_number(), Some("+1234567890".into()));
         unsafe { std::env::remove_var("TEST_SIGNAL_PHONE_RC") };
@@ -260,6 +413,82 @@ mod tests {
         assert!(parsed[0].2.is_none());
     }
 
+    #[test]
+    fn test_envelope_attachments() {
+        let envelopes = vec![serde_json::json!({
+            "envelope": {
+                "source": "+15551234567",
+                "dataMessage": {
+                    "message": null,
+                    "attachments": [{
+                        "contentType": "image/jpeg",
+                        "filename": "photo.jpg",
+                        "id": "Xw3fk2.jpg",
+                        "size": 20480
+                    }]
+                }
+            }
+        })];
+
+        // Attachment-only messages are kept
+        let parsed = parse_envelopes(&envelopes);
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].1, "");
+        assert_eq!(parsed[0].3[0].id, "Xw3fk2.jpg");
+        assert_eq!(parsed[0].3[0].content_type, "image/jpeg");
+        assert_eq!(parsed[0].3[0].size, Some(20480));
+    }
+
+    #[tokio::test]
+    async fn test_read_capped() {
+        use axum::body::Body;
+        use axum::routing::get;
+
+        // Streamed bodies have no content-length, so only the running total
+        // can stop them
+        let streamed = || async {
+            let chunks = (0..3).map(|_| Ok::<_, std::io::Error>(vec![b'x'; 10]));
+            Body::from_stream(futures::stream::iter(chunks))
+        };
+        let app = axum::Router::new()
+            .route("/small", get(|| async { "hello" }))
+            .route("/declared", get(|| async { vec![b'x'; 30] }))
+            .route("/streamed", get(streamed));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let client = reqwest::Client::new();
+        let fetch = |path: &str| client.get(format!("http://{addr}{path}")).send();
+
+        let small = read_capped(fetch("/small").await.unwrap(), 16).await;
+        assert_eq!(small.unwrap(), b"hello");
+        let declared = fetch("/declared").await.unwrap();
+        assert_eq!(declared.content_length(), Some(30));
+        assert!(read_capped(declared, 16).await.is_err());
+        let streamed = fetch("/streamed").await.unwrap();
+        assert_eq!(streamed.content_length(), None);
+        assert!(read_capped(streamed, 16).await.is_err());
+        assert_eq!(read_capped(fetch("/streamed").await.unwrap(), 30).await.unwrap().len(), 30);
+    }
+
+    #[test]
+    fn test_attachment_data_uri() {
+        let mut item = MediaAttachment {
+            url: None,
+            data: Some(b"hi".to_vec()),
+            mime_type: "text/plain".into(),
+            filename: Some("note.txt".into()),
+            size_bytes: None,
+        };
+        assert_eq!(
+            attachment_data_uri(&item, b"hi"),
+            "data:text/plain;filename=note.txt;base64,aGk="
+        );
+        item.filename = None;
+        assert_eq!(attachment_data_uri(&item, b"hi"), "data:text/plain;base64,aGk=");
+    }
+
     #[test]
     fn test_send_payload() {
         let channel = SignalChannel::new(
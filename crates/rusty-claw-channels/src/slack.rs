@@ -1,21 +1,39 @@
 //! Slack channel implementation.
 //!
-//! Uses Slack Events API (webhook) for inbound messages and
-//! Web API (chat.postMessage) for sending.
+//! Inbound messages arrive either via the Events API (webhook on `port`) or,
+//! when an app-level token is set and no port is configured, via Socket
+//! Mode: a WebSocket opened with `apps.connections.open` that works behind
+//! NAT. Sending uses the Web API (chat.postMessage).
+
+use std::time::Duration;
 
 use async_trait::async_trait;
+use futures::{SinkExt, StreamExt};
 use serde::Deserialize;
 use tokio::sync::mpsc;
-use tracing::{error, info};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, error, info, warn};
 
 use rusty_claw_core::types::{
-    ChatType, InboundMessage, OutboundMessage, SendResult, SendTarget,
+    ChatType, InboundMessage, OutboundMessage, Reaction, SendResult, SendTarget, Sender,
 };
 
+use rusty_claw_core::config::ChannelRateLimitConfig;
+
+use crate::rate_limit::RateLimiter;
 use crate::{
     Channel, ChannelCapabilities, ChannelHandle, ChannelMeta, ChannelStatus, InboundReceiver,
+    InboundSender,
 };
 
+/// Longest wait between Socket Mode reconnect attempts.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Default pacing: chat.postMessage allows about one message per second per
+/// channel, with short bursts.
+const DEFAULT_MESSAGES_PER_SECOND: f64 = 1.0;
+const DEFAULT_BURST: u32 = 3;
+
 /// Slack channel configuration.
 #[derive(Debug, Clone, Deserialize)]
 pub struct SlackConfig {
@@ -30,6 +48,8 @@ pub struct SlackConfig {
     #[serde(default)]
     pub app_token: Option<String>,
     #[serde(default)]
+    pub app_token_env: Option<String>,
+    #[serde(default)]
     pub port: Option<u16>,
 }
 
@@ -41,6 +61,10 @@ impl SlackConfig {
     pub fn resolve_signing_secret(&self) -> Option<String> {
         resolve_secret(&self.signing_secret, &self.signing_secret_env)
     }
+
+    pub fn resolve_app_token(&self) -> Option<String> {
+        resolve_secret(&self.app_token, &self.app_token_env)
+    }
 }
 
 fn resolve_secret(direct: &Option<String>, env_var: &Option<String>) -> Option<String> {
@@ -62,7 +86,9 @@ fn resolve_secret(direct: &Option<String>, env_var: &Option<String>) -> Option<S
 pub struct SlackChannel {
     bot_token: String,
     _signing_secret: Option<String>,
-    _listen_port: u16,
+    listen_port: Option<u16>,
+    app_token: Option<String>,
+    limiter: RateLimiter,
 }
 
 impl SlackChannel {
@@ -74,7 +100,29 @@ impl SlackChannel {
         Self {
             bot_token,
             _signing_secret: signing_secret,
-            _listen_port: listen_port.unwrap_or(3100),
+            listen_port,
+            app_token: None,
+            limiter: RateLimiter::new(DEFAULT_MESSAGES_PER_SECOND, DEFAULT_BURST),
+        }
+    }
+
+    /// Override the default outbound pacing.
+    pub fn with_rate_limit(mut self, config: Option<&ChannelRateLimitConfig>) -> Self {
+        self.limiter = RateLimiter::from_config(config, DEFAULT_MESSAGES_PER_SECOND, DEFAULT_BURST);
+        self
+    }
+
+    /// Set the app-level token (`xapp-...`) used for Socket Mode.
+    pub fn with_app_token(mut self, app_token: Option<String>) -> Self {
+        self.app_token = app_token;
+        self
+    }
+
+    /// Socket Mode is used when an app token is set and no webhook port is.
+    fn socket_mode_token(&self) -> Option<&str> {
+        match (&self.app_token, self.listen_port) {
+            (Some(token), None) => Some(token),
+            _ => None,
         }
     }
 }
@@ -107,7 +155,7 @@ pub enum SlackEventPayload {
     #[serde(rename = "url_verification")]
     UrlVerification { challenge: String },
     #[serde(rename = "event_callback")]
-    EventCallback { event: SlackEvent },
+    EventCallback { event: Box<SlackEvent> },
 }
 
 #[derive(Debug, Deserialize)]
@@ -122,6 +170,234 @@ pub struct SlackEvent {
     pub channel: Option<String>,
     #[serde(default)]
     pub thread_ts: Option<String>,
+    #[serde(default)]
+    pub ts: Option<String>,
+    /// "im" for direct messages, "channel"/"group"/"mpim" otherwise.
+    #[serde(default)]
+    pub channel_type: Option<String>,
+    /// Set on messages posted by bots (including ourselves).
+    #[serde(default)]
+    pub bot_id: Option<String>,
+    /// Set on edits, joins and other non-plain messages.
+    #[serde(default)]
+    pub subtype: Option<String>,
+    /// Emoji name on `reaction_added` events.
+    #[serde(default)]
+    pub reaction: Option<String>,
+    /// What a `reaction_added` event reacted to.
+    #[serde(default)]
+    pub item: Option<SlackReactionItem>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SlackReactionItem {
+    #[serde(default)]
+    pub channel: Option<String>,
+    #[serde(default)]
+    pub ts: Option<String>,
+}
+
+impl SlackEvent {
+    /// Convert a user message event into an inbound message. Bot messages,
+    /// subtyped messages (edits, joins) and other event types are skipped.
+    pub fn into_inbound(self) -> Option<InboundMessage> {
+        if self.event_type == "reaction_added" {
+            return self.reaction_into_inbound();
+        }
+        if !matches!(self.event_type.as_str(), "message" | "app_mention")
+            || self.bot_id.is_some()
+            || self.subtype.is_some()
+        {
+            return None;
+        }
+        let user = self.user?;
+        let channel = self.channel?;
+        let chat_type = if self.thread_ts.is_some() {
+            ChatType::Thread
+        } else if self.channel_type.as_deref() == Some("im") {
+            ChatType::Dm
+        } else {
+            ChatType::Group
+        };
+        Some(InboundMessage {
+            channel: "slack".into(),
+            account_id: channel,
+            chat_type,
+            sender: Sender {
+                id: user,
+                display_name: None,
+                username: None,
+            },
+            text: self.text,
+            media: vec![],
+            reply_to: None,
+            message_id: self.ts,
+            thread_id: self.thread_ts,
+            timestamp: chrono::Utc::now(),
+            raw: None,
+            reaction: None,
+        })
+    }
+}
+
+impl SlackEvent {
+    /// Convert a `reaction_added` event on a message; the emoji is reported
+    /// as `:name:`.
+    fn reaction_into_inbound(self) -> Option<InboundMessage> {
+        let item = self.item?;
+        let channel = item.channel?;
+        // Channel ids starting with D are direct messages
+        let chat_type = if channel.starts_with('D') {
+            ChatType::Dm
+        } else {
+            ChatType::Group
+        };
+        Some(InboundMessage {
+            channel: "slack".into(),
+            account_id: channel,
+            chat_type,
+            sender: Sender {
+                id: self.user?,
+                display_name: None,
+                username: None,
+            },
+            text: None,
+            media: vec![],
+            reply_to: None,
+            message_id: None,
+            thread_id: None,
+            timestamp: chrono::Utc::now(),
+            raw: None,
+            reaction: Some(Reaction {
+                emoji: format!(":{}:", self.reaction?),
+                message_id: item.ts?,
+            }),
+        })
+    }
+}
+
+/// A Socket Mode envelope. Every envelope with an `envelope_id` must be
+/// acknowledged within a few seconds or Slack retries it.
+#[derive(Debug, Deserialize)]
+pub struct SocketModeEnvelope {
+    #[serde(rename = "type")]
+    pub envelope_type: String,
+    #[serde(default)]
+    pub envelope_id: Option<String>,
+    #[serde(default)]
+    pub payload: Option<serde_json::Value>,
+}
+
+/// What the Socket Mode loop should do after handling a frame.
+#[derive(Debug, PartialEq, Eq)]
+enum SocketAction {
+    Continue,
+    Reconnect,
+}
+
+/// Open a Socket Mode connection, returning its WebSocket URL.
+async fn open_socket_url(client: &reqwest::Client, app_token: &str) -> anyhow::Result<String> {
+    let body: serde_json::Value = client
+        .post("https://slack.com/api/apps.connections.open")
+        .bearer_auth(app_token)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .send()
+        .await?
+        .json()
+        .await?;
+    if body["ok"].as_bool() != Some(true) {
+        anyhow::bail!(
+            "apps.connections.open failed: {}",
+            body["error"].as_str().unwrap_or("unknown")
+        );
+    }
+    body["url"]
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| anyhow::anyhow!("apps.connections.open returned no url"))
+}
+
+/// Handle one Socket Mode text frame: returns the ack to send (if any) and
+/// what to do next. Event callbacks are forwarded to `inbound_tx`.
+fn handle_socket_frame(text: &str, inbound_tx: &InboundSender) -> (Option<String>, SocketAction) {
+    let envelope: SocketModeEnvelope = match serde_json::from_str(text) {
+        Ok(envelope) => envelope,
+        Err(e) => {
+            debug!(%e, "Ignoring unparseable Socket Mode frame");
+            return (None, SocketAction::Continue);
+        }
+    };
+    let ack = envelope
+        .envelope_id
+        .as_ref()
+        .map(|id| serde_json::json!({ "envelope_id": id }).to_string());
+
+    match envelope.envelope_type.as_str() {
+        "hello" => info!("Slack Socket Mode connected"),
+        // Slack rotates connections periodically and warns first
+        "disconnect" => return (ack, SocketAction::Reconnect),
+        "events_api" => {
+            let payload = envelope
+                .payload
+                .and_then(|p| serde_json::from_value::<SlackEventPayload>(p).ok());
+            if let Some(SlackEventPayload::EventCallback { event }) = payload
+                && let Some(message) = event.into_inbound()
+            {
+                let _ = inbound_tx.send(message);
+            }
+        }
+        other => debug!(envelope_type = other, "Acknowledging unhandled Socket Mode envelope"),
+    }
+    (ack, SocketAction::Continue)
+}
+
+/// Run one Socket Mode connection until it drops or Slack asks to reconnect.
+async fn run_socket_connection(
+    client: &reqwest::Client,
+    app_token: &str,
+    inbound_tx: &InboundSender,
+) -> anyhow::Result<()> {
+    let url = open_socket_url(client, app_token).await?;
+    let (ws, _) = tokio_tungstenite::connect_async(url.as_str()).await?;
+    let (mut ws_tx, mut ws_rx) = ws.split();
+
+    while let Some(frame) = ws_rx.next().await {
+        match frame? {
+            Message::Text(text) => {
+                let (ack, action) = handle_socket_frame(&text, inbound_tx);
+                if let Some(ack) = ack {
+                    ws_tx.send(Message::Text(ack.into())).await?;
+                }
+                if action == SocketAction::Reconnect {
+                    info!("Slack requested Socket Mode reconnect");
+                    break;
+                }
+            }
+            Message::Ping(data) => ws_tx.send(Message::Pong(data)).await?,
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Keep a Socket Mode connection open, reconnecting with backoff.
+async fn socket_mode_loop(app_token: String, inbound_tx: InboundSender) {
+    let client = reqwest::Client::new();
+    let mut delay = Duration::from_secs(1);
+    loop {
+        match run_socket_connection(&client, &app_token, &inbound_tx).await {
+            Ok(()) => delay = Duration::from_secs(1),
+            Err(e) => {
+                warn!(%e, retry_in = ?delay, "Slack Socket Mode connection failed");
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+            }
+        }
+        if inbound_tx.is_closed() {
+            break;
+        }
+    }
 }
 
 #[async_trait]
@@ -156,14 +432,27 @@ impl Channel for SlackChannel {
         &self,
         _config: &serde_json::Value,
     ) -> anyhow::Result<(InboundReceiver, ChannelHandle)> {
-        let (_inbound_tx, inbound_rx) = mpsc::unbounded_channel::<InboundMessage>();
+        let (inbound_tx, inbound_rx) = mpsc::unbounded_channel::<InboundMessage>();
         let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
 
-        tokio::spawn(async move {
-            info!("Slack events listener started");
-            let _ = shutdown_rx.await;
-            info!("Slack events listener stopped");
-        });
+        if let Some(app_token) = self.socket_mode_token() {
+            let app_token = app_token.to_string();
+            tokio::spawn(async move {
+                info!("Slack Socket Mode listener started");
+                tokio::select! {
+                    _ = socket_mode_loop(app_token, inbound_tx) => {}
+                    _ = shutdown_rx => {}
+                }
+                info!("Slack Socket Mode listener stopped");
+            });
+        } else {
+            tokio::spawn(async move {
+                let _inbound_tx = inbound_tx;
+                info!("Slack events listener started");
+                let _ = shutdown_rx.await;
+                info!("Slack events listener stopped");
+            });
+        }
 
         Ok((inbound_rx, ChannelHandle::new(shutdown_tx)))
     }
@@ -177,6 +466,7 @@ impl Channel for SlackChannel {
         if text.is_empty() {
             return Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: true,
                 error: None,
             });
@@ -192,13 +482,12 @@ impl Channel for SlackChannel {
         }
 
         let client = reqwest::Client::new();
-        let resp = client
+        let request = client
             .post("https://slack.com/api/chat.postMessage")
             .header("Authorization", format!("Bearer {}", self.bot_token))
             .header("Content-Type", "application/json; charset=utf-8")
-            .json(&payload)
-            .send()
-            .await;
+            .json(&payload);
+        let resp = self.limiter.send(request).await;
 
         match resp {
             Ok(r) => {
@@ -206,6 +495,7 @@ impl Channel for SlackChannel {
                 if body["ok"].as_bool() == Some(true) {
                     Ok(SendResult {
                         message_id: body["ts"].as_str().map(String::from),
+                        message_ids: vec![],
                         success: true,
                         error: None,
                     })
@@ -214,6 +504,7 @@ impl Channel for SlackChannel {
                     error!(error = %err, "Slack send failed");
                     Ok(SendResult {
                         message_id: None,
+                        message_ids: vec![],
                         success: false,
                         error: Some(err),
                     })
@@ -221,12 +512,34 @@ impl Channel for SlackChannel {
             }
             Err(e) => Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: false,
                 error: Some(e.to_string()),
             }),
         }
     }
 
+    /// Bots can't post to a user id reliably; open the DM conversation and
+    /// post there.
+    async fn dm_target(&self, _account_id: &str, user_id: &str) -> anyhow::Result<SendTarget> {
+        let request = reqwest::Client::new()
+            .post("https://slack.com/api/conversations.open")
+            .header("Authorization", format!("Bearer {}", self.bot_token))
+            .header("Content-Type", "application/json; charset=utf-8")
+            .json(&serde_json::json!({ "users": user_id }));
+        let body: serde_json::Value = self.limiter.send(request).await?.json().await?;
+        let Some(channel) = body["channel"]["id"].as_str() else {
+            let err = body["error"].as_str().unwrap_or("unknown");
+            anyhow::bail!("Slack conversations.open failed: {err}");
+        };
+        Ok(SendTarget {
+            channel: "slack".into(),
+            account_id: channel.to_string(),
+            chat_id: channel.to_string(),
+            chat_type: ChatType::Dm,
+        })
+    }
+
     async fn status(&self) -> ChannelStatus {
         ChannelStatus {
             connected: true,
@@ -309,10 +622,68 @@ mod tests {
             signing_secret: Some("secret123".into()),
             signing_secret_env: None,
             app_token: None,
+            app_token_env: None,
             port: None,
         };
         assert_eq!(config.resolve_bot_token(), Some("xoxb-test".into()));
         assert_eq!(config.resolve_signing_secret(), Some("secret123".into()));
         unsafe { std::env::remove_var("TEST_SLACK_TOKEN_RC") };
     }
+
+    #[test]
+    fn test_socket_mode_selection() {
+        let channel = SlackChannel::new("xoxb".into(), None, None).with_app_token(Some("xapp".into()));
+        assert_eq!(channel.socket_mode_token(), Some("xapp"));
+
+        // A configured webhook port keeps the Events API path
+        let channel = SlackChannel::new("xoxb".into(), None, Some(3100)).with_app_token(Some("xapp".into()));
+        assert_eq!(channel.socket_mode_token(), None);
+        assert_eq!(SlackChannel::new("xoxb".into(), None, None).socket_mode_token(), None);
+    }
+
+    #[test]
+    fn test_socket_frame_acks_and_forwards_messages() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let frame = r#"{
+            "envelope_id": "env-1", "type": "events_api", "accepts_response_payload": false,
+            "payload": {"type": "event_callback", "event": {
+                "type": "message", "user": "U1", "text": "hi", "channel": "D9",
+                "channel_type": "im", "ts": "1700000000.0001"
+            }}
+        }"#;
+        let (ack, action) = handle_socket_frame(frame, &tx);
+        assert_eq!(ack.as_deref(), Some(r#"{"envelope_id":"env-1"}"#));
+        assert_eq!(action, SocketAction::Continue);
+
+        let message = rx.try_recv().unwrap();
+        assert_eq!(message.sender.id, "U1");
+        assert_eq!(message.account_id, "D9");
+        assert_eq!(message.chat_type, ChatType::Dm);
+        assert_eq!(message.text.as_deref(), Some("hi"));
+
+        // Our own bot messages are acked but not forwarded
+        let bot = r#"{"envelope_id": "env-2", "type": "events_api", "payload": {"type": "event_callback",
+            "event": {"type": "message", "bot_id": "B1", "user": "U2", "text": "echo", "channel": "D9"}}}"#;
+        assert!(handle_socket_frame(bot, &tx).0.is_some());
+        assert!(rx.try_recv().is_err());
+
+        let (ack, action) = handle_socket_frame(r#"{"type": "disconnect", "reason": "refresh_requested"}"#, &tx);
+        assert_eq!(ack, None);
+        assert_eq!(action, SocketAction::Reconnect);
+    }
+
+    #[test]
+    fn test_reaction_added_event() {
+        let event: SlackEvent = serde_json::from_value(serde_json::json!({
+            "type": "reaction_added", "user": "U1", "reaction": "octagonal_sign",
+            "item_user": "UBOT", "item": {"type": "message", "channel": "C5", "ts": "1700000000.0002"}
+        }))
+        .unwrap();
+        let inbound = event.into_inbound().unwrap();
+        assert_eq!(inbound.chat_type, ChatType::Group);
+        assert_eq!(inbound.account_id, "C5");
+        let reaction = inbound.reaction.unwrap();
+        assert_eq!(reaction.emoji, ":octagonal_sign:");
+        assert_eq!(reaction.message_id, "1700000000.0002");
+    }
 }
@@ -4,22 +4,41 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use teloxide::prelude::*;
-use teloxide::types::{ChatKind, MediaKind, MessageKind, UpdateKind};
+use teloxide::types::{
+    AllowedUpdate, CallbackQuery, ChatKind, InlineKeyboardButton, InlineKeyboardMarkup, MediaKind,
+    MessageKind, MessageReactionUpdated, ReactionType, UpdateKind,
+};
 use tokio::sync::{mpsc, oneshot, RwLock};
 use tracing::{debug, error, info, warn};
 
 use rusty_claw_core::types::{
-    ChatType, InboundMessage, OutboundMessage, Sender, SendResult, SendTarget,
+    ChatType, InboundMessage, MessageButton, OutboundMessage, Reaction, Sender, SendResult,
+    SendTarget,
 };
 
+use rusty_claw_core::config::ChannelRateLimitConfig;
+
+use crate::rate_limit::RateLimiter;
 use crate::{
     Channel, ChannelCapabilities, ChannelHandle, ChannelMeta, ChannelStatus, InboundReceiver,
 };
 
+/// Telegram's limit on `callback_data`, in bytes.
+const MAX_CALLBACK_DATA: usize = 64;
+
+/// Default pacing: Telegram allows about one message per second per chat,
+/// with short bursts tolerated.
+const DEFAULT_MESSAGES_PER_SECOND: f64 = 1.0;
+const DEFAULT_BURST: u32 = 3;
+
+/// How often a send is retried after Telegram answers "retry after".
+const MAX_SEND_RETRIES: u32 = 3;
+
 pub struct TelegramChannel {
     bot_token: String,
     allowed_users: Vec<String>,
     bot_username: Arc<RwLock<Option<String>>>,
+    limiter: RateLimiter,
 }
 
 impl TelegramChannel {
@@ -28,9 +47,16 @@ impl TelegramChannel {
             bot_token,
             allowed_users,
             bot_username: Arc::new(RwLock::new(None)),
+            limiter: RateLimiter::new(DEFAULT_MESSAGES_PER_SECOND, DEFAULT_BURST),
         }
     }
 
+    /// Override the default outbound pacing.
+    pub fn with_rate_limit(mut self, config: Option<&ChannelRateLimitConfig>) -> Self {
+        self.limiter = RateLimiter::from_config(config, DEFAULT_MESSAGES_PER_SECOND, DEFAULT_BURST);
+        self
+    }
+
     /// Check if the message is addressed to our bot in a group.
     fn is_addressed_to_bot(text: &str, bot_username: &Option<String>) -> bool {
         if let Some(username) = bot_username {
@@ -113,6 +139,12 @@ impl Channel for TelegramChannel {
                     .get_updates()
                     .offset(offset)
                     .timeout(30)
+                    // Reactions are only delivered when requested explicitly
+                    .allowed_updates([
+                        AllowedUpdate::Message,
+                        AllowedUpdate::CallbackQuery,
+                        AllowedUpdate::MessageReaction,
+                    ])
                     .await;
 
                 match updates {
@@ -120,6 +152,51 @@ impl Channel for TelegramChannel {
                         for update in updates {
                             offset = update.id.as_offset();
 
+                            if let UpdateKind::MessageReaction(reaction) = &update.kind {
+                                let sender_id = reaction
+                                    .user()
+                                    .map(|u| u.id.0.to_string())
+                                    .unwrap_or_default();
+                                if !allowed_users.is_empty()
+                                    && !allowed_users.contains(&sender_id)
+                                {
+                                    continue;
+                                }
+                                for inbound in reaction_to_inbound(reaction) {
+                                    if inbound_tx.send(inbound).is_err() {
+                                        warn!("Inbound channel closed, stopping Telegram polling");
+                                        return;
+                                    }
+                                }
+                                continue;
+                            }
+
+                            if let UpdateKind::CallbackQuery(query) = &update.kind {
+                                // Always answer so the client stops its spinner
+                                if let Err(e) = bot_clone.answer_callback_query(&query.id).await {
+                                    warn!(%e, "Failed to answer callback query");
+                                }
+
+                                let sender_id = query.from.id.0.to_string();
+                                if !allowed_users.is_empty()
+                                    && !allowed_users.contains(&sender_id)
+                                {
+                                    debug!(
+                                        sender = %sender_id,
+                                        "Callback from non-allowed user, ignoring"
+                                    );
+                                    continue;
+                                }
+
+                                if let Some(inbound) = callback_to_inbound(query)
+                                    && inbound_tx.send(inbound).is_err()
+                                {
+                                    warn!("Inbound channel closed, stopping Telegram polling");
+                                    return;
+                                }
+                                continue;
+                            }
+
                             if let UpdateKind::Message(message) = &update.kind {
                                 // Extract text from the message
                                 let text = match &message.kind {
@@ -194,11 +271,13 @@ impl Channel for TelegramChannel {
                                     reply_to: message
                                         .reply_to_message()
                                         .map(|r| r.id.0.to_string()),
+                                    message_id: Some(message.id.0.to_string()),
                                     thread_id: message
                                         .thread_id
                                         .map(|t| t.0.to_string()),
                                     timestamp: chrono::Utc::now(),
                                     raw: None,
+                                    reaction: None,
                                 };
 
                                 if inbound_tx.send(inbound).is_err() {
@@ -235,41 +314,88 @@ impl Channel for TelegramChannel {
                 .send_chat_action(chat_id, teloxide::types::ChatAction::Typing)
                 .await;
 
-            // Split long messages (Telegram max is 4096)
-            let chunks = split_message(text, 4096);
-            let mut last_msg_id = None;
-
-            for chunk in chunks {
-                let req = bot.send_message(chat_id, &chunk);
-
-                match req.await {
-                    Ok(sent) => {
-                        last_msg_id = Some(sent.id.0.to_string());
+            let keyboard = match inline_keyboard(&message.buttons) {
+                Ok(keyboard) => keyboard,
+                Err(e) => {
+                    return Ok(SendResult {
+                        message_id: None,
+                        message_ids: vec![],
+                        success: false,
+                        error: Some(e),
+                    });
+                }
+            };
+
+            let max_len = self.capabilities().max_message_length.unwrap_or(4096);
+            let chunks = split_message(text, max_len);
+            let last = chunks.len() - 1;
+            let mut message_ids = Vec::with_capacity(chunks.len());
+
+            for (i, chunk) in chunks.into_iter().enumerate() {
+                let mut retries = 0;
+                let result = loop {
+                    self.limiter.acquire().await;
+                    let mut req = bot.send_message(chat_id, &chunk);
+                    // Buttons go under the final chunk so they follow the whole reply
+                    if i == last && let Some(keyboard) = &keyboard {
+                        req = req.reply_markup(keyboard.clone());
+                    }
+                    match req.await {
+                        Err(teloxide::RequestError::RetryAfter(wait)) if retries < MAX_SEND_RETRIES => {
+                            warn!(retry_after = ?wait.duration(), "Telegram rate limit hit, retrying");
+                            self.limiter.pause(wait.duration());
+                            retries += 1;
+                        }
+                        result => break result,
                     }
+                };
+
+                match result {
+                    Ok(sent) => message_ids.push(sent.id.0.to_string()),
                     Err(e) => {
+                        // Report the parts that did go out
                         return Ok(SendResult {
-                            message_id: None,
+                            message_id: message_ids.last().cloned(),
+                            message_ids,
                             success: false,
-                            error: Some(format!("Send error: {e}")),
+                            error: Some(format!("Send error on part {}/{}: {e}", i + 1, last + 1)),
                         });
                     }
                 }
             }
 
             Ok(SendResult {
-                message_id: last_msg_id,
+                message_id: message_ids.last().cloned(),
+                message_ids,
                 success: true,
                 error: None,
             })
         } else {
             Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: false,
                 error: Some("No text to send".into()),
             })
         }
     }
 
+    async fn edit(&self, target: &SendTarget, message_id: &str, text: &str) -> anyhow::Result<()> {
+        let bot = Bot::new(&self.bot_token);
+        let chat_id = ChatId(target.chat_id.parse::<i64>()?);
+        let message_id = teloxide::types::MessageId(message_id.parse::<i32>()?);
+        self.limiter.acquire().await;
+        match bot.edit_message_text(chat_id, message_id, text).await {
+            // Re-sending identical text is rejected but harmless
+            Ok(_) | Err(teloxide::RequestError::Api(teloxide::ApiError::MessageNotModified)) => Ok(()),
+            Err(teloxide::RequestError::RetryAfter(wait)) => {
+                self.limiter.pause(wait.duration());
+                anyhow::bail!("Telegram rate limit hit, retry after {:?}", wait.duration())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
     async fn status(&self) -> ChannelStatus {
         let username = self.bot_username.read().await.clone();
         ChannelStatus {
@@ -281,29 +407,212 @@ impl Channel for TelegramChannel {
     }
 }
 
-/// Split a message into chunks that fit within Telegram's limit.
+/// Render button rows as an inline keyboard with callback buttons.
+fn inline_keyboard(rows: &[Vec<MessageButton>]) -> Result<Option<InlineKeyboardMarkup>, String> {
+    if rows.iter().all(|row| row.is_empty()) {
+        return Ok(None);
+    }
+    let mut keyboard = Vec::with_capacity(rows.len());
+    for row in rows.iter().filter(|row| !row.is_empty()) {
+        let mut buttons = Vec::with_capacity(row.len());
+        for button in row {
+            if button.payload.len() > MAX_CALLBACK_DATA {
+                return Err(format!(
+                    "Button payload '{}' exceeds Telegram's {MAX_CALLBACK_DATA}-byte limit",
+                    button.label
+                ));
+            }
+            buttons.push(InlineKeyboardButton::callback(
+                button.label.clone(),
+                button.payload.clone(),
+            ));
+        }
+        keyboard.push(buttons);
+    }
+    Ok(Some(InlineKeyboardMarkup::new(keyboard)))
+}
+
+/// Convert a button press into an inbound message carrying the button
+/// payload as its text. Queries without data (games) are skipped.
+fn callback_to_inbound(query: &CallbackQuery) -> Option<InboundMessage> {
+    let data = query.data.clone()?;
+    let message = query.message.as_ref()?;
+    let chat = message.chat();
+    let chat_type = if matches!(chat.kind, ChatKind::Private(_)) {
+        ChatType::Dm
+    } else {
+        ChatType::Group
+    };
+
+    Some(InboundMessage {
+        channel: "telegram".into(),
+        account_id: chat.id.0.to_string(),
+        chat_type,
+        sender: Sender {
+            id: query.from.id.0.to_string(),
+            display_name: Some(query.from.full_name()),
+            username: query.from.username.clone(),
+        },
+        text: Some(data.clone()),
+        media: vec![],
+        reply_to: Some(message.id().0.to_string()),
+        message_id: None,
+        thread_id: None,
+        timestamp: chrono::Utc::now(),
+        raw: Some(serde_json::json!({
+            "callback_query": {
+                "id": query.id,
+                "data": data,
+                "message_id": message.id().0,
+            }
+        })),
+        reaction: None,
+    })
+}
+
+/// Convert newly added emoji reactions into inbound messages, one per emoji.
+/// Removed reactions and anonymous (channel) reactions are ignored.
+fn reaction_to_inbound(update: &MessageReactionUpdated) -> Vec<InboundMessage> {
+    let Some(user) = update.user() else {
+        return Vec::new();
+    };
+    let chat_type = if matches!(update.chat.kind, ChatKind::Private(_)) {
+        ChatType::Dm
+    } else {
+        ChatType::Group
+    };
+    update
+        .new_reaction
+        .iter()
+        .filter(|r| !update.old_reaction.contains(r))
+        .filter_map(|r| match r {
+            ReactionType::Emoji { emoji } => Some(emoji.clone()),
+            ReactionType::CustomEmoji { .. } => None,
+        })
+        .map(|emoji| InboundMessage {
+            channel: "telegram".into(),
+            account_id: update.chat.id.0.to_string(),
+            chat_type,
+            sender: Sender {
+                id: user.id.0.to_string(),
+                display_name: Some(user.full_name()),
+                username: user.username.clone(),
+            },
+            text: None,
+            media: vec![],
+            reply_to: None,
+            message_id: None,
+            thread_id: None,
+            timestamp: chrono::Utc::now(),
+            raw: None,
+            reaction: Some(Reaction {
+                emoji,
+                message_id: update.message_id.0.to_string(),
+            }),
+        })
+        .collect()
+}
+
+/// Split a message into parts of at most `max_len` characters.
+///
+/// Breaks on the latest paragraph, line, sentence or word boundary in the
+/// second half of each part, falling back to a hard split. Markdown code
+/// fences are never cut: a part ends before the fence instead, and a fence
+/// too long for one message is closed and reopened across parts.
 fn split_message(text: &str, max_len: usize) -> Vec<String> {
-    if text.len() <= max_len {
+    if text.chars().count() <= max_len {
         return vec![text.to_string()];
     }
 
     let mut chunks = Vec::new();
-    let mut remaining = text;
+    let mut remaining = text.to_string();
+
+    while remaining.chars().count() > max_len {
+        let window = &remaining[..char_boundary(&remaining, max_len)];
+        let (mut end, mut rest) = split_point(window);
+
+        if let Some((fence_start, opener)) = open_fence(&remaining, end) {
+            if fence_start > 0 {
+                end = fence_start;
+                rest = fence_start;
+            } else if let Some(split) = split_fence(&remaining, &opener, max_len) {
+                chunks.push(split.0);
+                remaining = split.1;
+                continue;
+            }
+        }
 
-    while !remaining.is_empty() {
-        if remaining.len() <= max_len {
-            chunks.push(remaining.to_string());
-            break;
+        let chunk = remaining[..end].trim_end();
+        if !chunk.is_empty() {
+            chunks.push(chunk.to_string());
         }
+        remaining = remaining[rest..].trim_start_matches('\n').to_string();
+    }
 
-        // Try to split at a newline
-        let split_at = remaining[..max_len].rfind('\n').unwrap_or(max_len);
+    if !remaining.trim().is_empty() {
+        chunks.push(remaining);
+    }
+    chunks
+}
 
-        chunks.push(remaining[..split_at].to_string());
-        remaining = remaining[split_at..].trim_start_matches('\n');
+/// Byte offset of the `n`th character (or the end of `text`).
+fn char_boundary(text: &str, n: usize) -> usize {
+    text.char_indices().nth(n).map_or(text.len(), |(i, _)| i)
+}
+
+/// Pick where to cut `window`: returns (end of this part, start of the next).
+fn split_point(window: &str) -> (usize, usize) {
+    let min = window.len() / 2;
+    // (separator, bytes of it kept on the current part)
+    let boundaries = [("\n\n", 0), ("\n", 0), (". ", 1), ("! ", 1), ("? ", 1), (" ", 0)];
+    for (sep, keep) in boundaries {
+        if let Some(pos) = window.rfind(sep)
+            && pos >= min
+        {
+            return (pos + keep, pos + sep.len());
+        }
     }
+    (window.len(), window.len())
+}
 
-    chunks
+/// If byte offset `pos` falls inside a code fence, return the byte offset
+/// of the fence's opening line and that line (e.g. "```rust").
+fn open_fence(text: &str, pos: usize) -> Option<(usize, String)> {
+    let mut open = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if offset >= pos {
+            break;
+        }
+        if line.trim_start().starts_with("```") {
+            open = match open {
+                Some(_) => None,
+                None => Some((offset, line.trim().to_string())),
+            };
+        }
+        offset += line.len();
+    }
+    open
+}
+
+/// Cut a code fence that starts at the beginning of `text` and does not fit
+/// in one part: close it at the last line break that fits and reopen it with
+/// the same opener at the start of the remainder.
+fn split_fence(text: &str, opener: &str, max_len: usize) -> Option<(String, String)> {
+    const CLOSE: &str = "\n```";
+    let window = &text[..char_boundary(text, max_len.saturating_sub(CLOSE.len()))];
+    let (end, rest) = match window.rfind('\n') {
+        Some(pos) if pos > opener.len() => (pos, pos + 1),
+        _ => (window.len(), window.len()),
+    };
+    // The remainder must shrink, or a tiny limit would loop forever
+    if rest <= opener.len() + 1 {
+        return None;
+    }
+    Some((
+        format!("{}{CLOSE}", &text[..end]),
+        format!("{opener}\n{}", &text[rest..]),
+    ))
 }
 
 #[cfg(test)]
@@ -325,6 +634,47 @@ mod tests {
         assert!(chunks[0].len() <= 4096);
     }
 
+    #[test]
+    fn test_split_message_multibyte() {
+        // 5000 characters but 15000 bytes; must not panic on char boundaries
+        let text = "€".repeat(5000);
+        let chunks = split_message(&text, 4096);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].chars().count(), 4096);
+        assert_eq!(chunks.concat(), text);
+    }
+
+    #[test]
+    fn test_split_message_prefers_boundaries() {
+        let text = format!("{}\n\n{}", "a".repeat(60), "b".repeat(30));
+        assert_eq!(split_message(&text, 80), vec!["a".repeat(60), "b".repeat(30)]);
+
+        let text = format!("{}. {}", "a".repeat(60), "b".repeat(30));
+        assert_eq!(split_message(&text, 80), vec![format!("{}.", "a".repeat(60)), "b".repeat(30)]);
+    }
+
+    #[test]
+    fn test_split_message_keeps_code_fences_whole() {
+        let code = "```rust\nfn main() {}\nlet x = 1;\n```";
+        let text = format!("{}\n{code}", "intro ".repeat(8));
+        let chunks = split_message(&text, 60);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1], code);
+    }
+
+    #[test]
+    fn test_split_message_reopens_long_fences() {
+        let body: Vec<String> = (0..40).map(|i| format!("line {i}")).collect();
+        let text = format!("```py\n{}\n```", body.join("\n"));
+        let chunks = split_message(&text, 100);
+        assert!(chunks.len() > 2);
+        for chunk in &chunks {
+            assert!(chunk.chars().count() <= 100, "{chunk}");
+            assert!(chunk.starts_with("```py\n"));
+            assert!(chunk.ends_with("\n```"));
+        }
+    }
+
     #[test]
     fn test_is_addressed_to_bot() {
         assert!(TelegramChannel::is_addressed_to_bot(
@@ -338,4 +688,72 @@ mod tests {
         // No username known = accept all
         assert!(TelegramChannel::is_addressed_to_bot("hello", &None));
     }
+
+    #[test]
+    fn test_inline_keyboard() {
+        let button = |label: &str, payload: &str| MessageButton {
+            label: label.into(),
+            payload: payload.into(),
+        };
+        assert!(inline_keyboard(&[]).unwrap().is_none());
+
+        let keyboard = inline_keyboard(&[
+            vec![button("Yes", "confirm:yes"), button("No", "confirm:no")],
+            vec![],
+            vec![button("Later", "confirm:later")],
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(keyboard.inline_keyboard.len(), 2);
+        assert_eq!(keyboard.inline_keyboard[0][1].text, "No");
+
+        assert!(inline_keyboard(&[vec![button("Big", &"x".repeat(65))]]).is_err());
+    }
+
+    #[test]
+    fn test_callback_to_inbound() {
+        let query: CallbackQuery = serde_json::from_value(serde_json::json!({
+            "id": "4382",
+            "from": {"id": 42, "is_bot": false, "first_name": "Ada", "username": "ada"},
+            "message": {
+                "message_id": 7,
+                "date": 1700000000,
+                "chat": {"id": 42, "type": "private", "first_name": "Ada"},
+                "text": "Proceed?"
+            },
+            "chat_instance": "-123",
+            "data": "confirm:yes"
+        }))
+        .unwrap();
+
+        let inbound = callback_to_inbound(&query).unwrap();
+        assert_eq!(inbound.text.as_deref(), Some("confirm:yes"));
+        assert_eq!(inbound.account_id, "42");
+        assert_eq!(inbound.sender.id, "42");
+        assert_eq!(inbound.chat_type, ChatType::Dm);
+        assert_eq!(inbound.reply_to.as_deref(), Some("7"));
+        assert_eq!(inbound.raw.unwrap()["callback_query"]["id"], "4382");
+    }
+
+    #[test]
+    fn test_reaction_to_inbound() {
+        let update: MessageReactionUpdated = serde_json::from_value(serde_json::json!({
+            "chat": {"id": 42, "type": "private", "first_name": "Ada"},
+            "message_id": 7,
+            "user": {"id": 42, "is_bot": false, "first_name": "Ada"},
+            "date": 1700000000,
+            "old_reaction": [{"type": "emoji", "emoji": "👍"}],
+            "new_reaction": [{"type": "emoji", "emoji": "👍"}, {"type": "emoji", "emoji": "👎"}]
+        }))
+        .unwrap();
+
+        // Only the newly added emoji is reported
+        let inbound = reaction_to_inbound(&update);
+        assert_eq!(inbound.len(), 1);
+        let reaction = inbound[0].reaction.as_ref().unwrap();
+        assert_eq!(reaction.emoji, "👎");
+        assert_eq!(reaction.message_id, "7");
+        assert_eq!(inbound[0].sender.id, "42");
+        assert!(inbound[0].text.is_none());
+    }
 }
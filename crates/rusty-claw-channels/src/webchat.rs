@@ -58,9 +58,11 @@ impl WebChatChannel {
             text: Some(msg.text.clone()),
             media: vec![],
             reply_to: None,
+            message_id: None,
             thread_id: None,
             timestamp: Utc::now(),
             raw: None,
+            reaction: None,
         }
     }
 }
@@ -115,6 +117,7 @@ impl Channel for WebChatChannel {
         // connection managed by the gateway. This is a placeholder.
         Ok(SendResult {
             message_id: None,
+            message_ids: vec![],
             success: true,
             error: None,
         })
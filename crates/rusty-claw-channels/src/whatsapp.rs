@@ -3,10 +3,10 @@
 use async_trait::async_trait;
 use sha2::Sha256;
 use tokio::sync::mpsc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use rusty_claw_core::types::{
-    ChatType, InboundMessage, OutboundMessage, SendResult, SendTarget, Sender,
+    ChatType, InboundMessage, MessageButton, OutboundMessage, SendResult, SendTarget, Sender,
 };
 
 use crate::{
@@ -27,6 +27,18 @@ fn default_port() -> u16 {
     3101
 }
 
+/// Interactive message limits from the Cloud API.
+const MAX_REPLY_BUTTONS: usize = 3;
+const MAX_BUTTON_TITLE: usize = 20;
+const MAX_BUTTON_ID: usize = 256;
+const MAX_LIST_ROWS: usize = 10;
+const MAX_ROW_TITLE: usize = 24;
+const MAX_ROW_ID: usize = 200;
+const MAX_INTERACTIVE_BODY: usize = 1024;
+
+/// Body used for the interactive part when the reply text is sent separately.
+const MENU_PROMPT: &str = "Choose an option:";
+
 pub struct WhatsAppChannel {
     config: WhatsAppChannelConfig,
 }
@@ -56,9 +68,18 @@ pub fn parse_webhook_messages(body: &serde_json::Value) -> Vec<(String, String)>
                                 .and_then(|v| v.as_str())
                                 .unwrap_or("")
                                 .to_string();
+                            // Button and list replies carry the chosen payload
                             let text = msg
                                 .get("text")
                                 .and_then(|v| v.get("body"))
+                                .or_else(|| {
+                                    let interactive = msg.get("interactive")?;
+                                    interactive
+                                        .get("button_reply")
+                                        .or_else(|| interactive.get("list_reply"))?
+                                        .get("id")
+                                })
+                                .or_else(|| msg.get("button").and_then(|b| b.get("payload")))
                                 .and_then(|v| v.as_str())
                                 .unwrap_or("")
                                 .to_string();
@@ -75,17 +96,132 @@ pub fn parse_webhook_messages(body: &serde_json::Value) -> Vec<(String, String)>
     messages
 }
 
-/// Verify Meta webhook signature (HMAC-SHA256).
+/// Render button rows as an `interactive` message body: reply buttons when
+/// they fit (up to 3 short titles), otherwise a single-section list (up to
+/// 10 rows). Rows are flattened; WhatsApp has no button grid.
+pub fn interactive_payload(
+    to: &str,
+    body: &str,
+    rows: &[Vec<MessageButton>],
+) -> Result<serde_json::Value, String> {
+    let buttons: Vec<&MessageButton> = rows.iter().flatten().collect();
+    if body.chars().count() > MAX_INTERACTIVE_BODY {
+        return Err(format!("Interactive body exceeds {MAX_INTERACTIVE_BODY} characters"));
+    }
+
+    let fits = |button: &&MessageButton, title: usize, id: usize| {
+        button.label.chars().count() <= title && button.payload.chars().count() <= id
+    };
+    let interactive = if buttons.len() <= MAX_REPLY_BUTTONS
+        && buttons.iter().all(|b| fits(b, MAX_BUTTON_TITLE, MAX_BUTTON_ID))
+    {
+        let buttons: Vec<serde_json::Value> = buttons
+            .iter()
+            .map(|b| serde_json::json!({ "type": "reply", "reply": { "id": b.payload, "title": b.label } }))
+            .collect();
+        serde_json::json!({
+            "type": "button",
+            "body": { "text": body },
+            "action": { "buttons": buttons },
+        })
+    } else {
+        if buttons.len() > MAX_LIST_ROWS {
+            return Err(format!(
+                "WhatsApp allows at most {MAX_LIST_ROWS} options per message, got {}",
+                buttons.len()
+            ));
+        }
+        if let Some(b) = buttons.iter().find(|b| !fits(b, MAX_ROW_TITLE, MAX_ROW_ID)) {
+            return Err(format!(
+                "WhatsApp option '{}' exceeds {MAX_ROW_TITLE} title or {MAX_ROW_ID} payload characters",
+                b.label
+            ));
+        }
+        let rows: Vec<serde_json::Value> = buttons
+            .iter()
+            .map(|b| serde_json::json!({ "id": b.payload, "title": b.label }))
+            .collect();
+        serde_json::json!({
+            "type": "list",
+            "body": { "text": body },
+            "action": { "button": "Options", "sections": [{ "rows": rows }] },
+        })
+    };
+
+    Ok(serde_json::json!({
+        "messaging_product": "whatsapp",
+        "to": to,
+        "type": "interactive",
+        "interactive": interactive,
+    }))
+}
+
+/// Verify Meta webhook signature (HMAC-SHA256), comparing in constant time.
 pub fn verify_signature(payload: &[u8], signature: &str, app_secret: &str) -> bool {
     use hmac::{Hmac, Mac};
     let expected = signature.strip_prefix("sha256=").unwrap_or(signature);
+    let Ok(expected) = hex::decode(expected) else {
+        return false;
+    };
 
     let mut mac =
         Hmac::<Sha256>::new_from_slice(app_secret.as_bytes()).expect("HMAC key length");
     mac.update(payload);
-    let result = hex::encode(mac.finalize().into_bytes());
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// Check the `X-Hub-Signature-256` header when an app secret is configured.
+/// Without a secret every request is accepted.
+fn is_authentic(headers: &axum::http::HeaderMap, body: &[u8], app_secret: Option<&str>) -> bool {
+    let Some(secret) = app_secret else {
+        return true;
+    };
+    headers
+        .get("x-hub-signature-256")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|signature| verify_signature(body, signature, secret))
+}
+
+impl WhatsAppChannel {
+    fn text_payload(&self, to: &str, text: &str) -> serde_json::Value {
+        serde_json::json!({
+            "messaging_product": "whatsapp",
+            "to": to,
+            "type": "text",
+            "text": { "body": text }
+        })
+    }
+
+    /// Send one message payload, returning its id.
+    async fn post_message(
+        &self,
+        client: &reqwest::Client,
+        payload: &serde_json::Value,
+    ) -> Result<Option<String>, String> {
+        let resp = client
+            .post(format!(
+                "https://graph.facebook.com/v21.0/{}/messages",
+                self.config.phone_number_id
+            ))
+            .header("Authorization", format!("Bearer {}", self.config.access_token))
+            .json(payload)
+            .send()
+            .await;
 
-    result == expected
+        match resp {
+            Ok(r) if r.status().is_success() => {
+                let body: serde_json::Value = r.json().await.unwrap_or_default();
+                Ok(body["messages"][0]["id"].as_str().map(String::from))
+            }
+            Ok(r) => {
+                let status = r.status();
+                let body = r.text().await.unwrap_or_default();
+                error!(%status, body, "WhatsApp send failed");
+                Err(format!("WhatsApp API error {status}"))
+            }
+            Err(e) => Err(e.to_string()),
+        }
+    }
 }
 
 #[async_trait]
@@ -127,6 +263,10 @@ impl Channel for WhatsAppChannel {
         let app_secret = self.config.app_secret.clone();
         let port = self.config.webhook_port;
 
+        if app_secret.is_none() {
+            warn!("WhatsApp app_secret is not set; webhook signatures will not be verified");
+        }
+
         tokio::spawn(async move {
             info!(port, "WhatsApp webhook listener starting");
 
@@ -159,12 +299,12 @@ impl Channel for WhatsAppChannel {
                 )
                 .route(
                     "/webhook",
-                    axum::routing::post(move |body: axum::body::Bytes| async move {
+                    axum::routing::post(move |headers: axum::http::HeaderMap, body: axum::body::Bytes| async move {
+                        if !is_authentic(&headers, &body, secret.as_deref()) {
+                            warn!("Rejected WhatsApp webhook with missing or invalid signature");
+                            return (axum::http::StatusCode::UNAUTHORIZED, "Invalid signature");
+                        }
                         if let Ok(payload) = serde_json::from_slice::<serde_json::Value>(&body) {
-                            // Optionally verify signature
-                            if let Some(ref _secret) = secret {
-                                // In production, verify X-Hub-Signature-256 header
-                            }
                             let messages = parse_webhook_messages(&payload);
                             for (from, text) in messages {
                                 let msg = InboundMessage {
@@ -179,14 +319,16 @@ impl Channel for WhatsAppChannel {
                                     text: Some(text),
                                     media: vec![],
                                     reply_to: None,
+                                    message_id: None,
                                     thread_id: None,
                                     timestamp: chrono::Utc::now(),
                                     raw: None,
+                                    reaction: None,
                                 };
                                 let _ = inbound.send(msg);
                             }
                         }
-                        "OK"
+                        (axum::http::StatusCode::OK, "OK")
                     }),
                 );
 
@@ -215,53 +357,65 @@ impl Channel for WhatsAppChannel {
         message: OutboundMessage,
     ) -> anyhow::Result<SendResult> {
         let text = message.text.unwrap_or_default();
-        if text.is_empty() {
+        let has_buttons = message.buttons.iter().any(|row| !row.is_empty());
+        if text.is_empty() && !has_buttons {
             return Ok(SendResult {
                 message_id: None,
+                message_ids: vec![],
                 success: true,
                 error: None,
             });
         }
 
-        let client = reqwest::Client::new();
-        let resp = client
-            .post(format!(
-                "https://graph.facebook.com/v21.0/{}/messages",
-                self.config.phone_number_id
-            ))
-            .header("Authorization", format!("Bearer {}", self.config.access_token))
-            .header("Content-Type", "application/json")
-            .json(&serde_json::json!({
-                "messaging_product": "whatsapp",
-                "to": target.chat_id,
-                "type": "text",
-                "text": { "body": text }
-            }))
-            .send()
-            .await;
+        // Build every payload first so limit errors are reported before
+        // anything is sent
+        let mut payloads = Vec::new();
+        if has_buttons {
+            let body = if text.is_empty() || text.chars().count() > MAX_INTERACTIVE_BODY {
+                if !text.is_empty() {
+                    payloads.push(self.text_payload(&target.chat_id, &text));
+                }
+                MENU_PROMPT
+            } else {
+                text.as_str()
+            };
+            match interactive_payload(&target.chat_id, body, &message.buttons) {
+                Ok(payload) => payloads.push(payload),
+                Err(e) => {
+                    return Ok(SendResult {
+                        message_id: None,
+                        message_ids: vec![],
+                        success: false,
+                        error: Some(e),
+                    });
+                }
+            }
+        } else {
+            payloads.push(self.text_payload(&target.chat_id, &text));
+        }
 
-        match resp {
-            Ok(r) if r.status().is_success() => Ok(SendResult {
-                message_id: None,
-                success: true,
-                error: None,
-            }),
-            Ok(r) => {
-                let status = r.status();
-                let body = r.text().await.unwrap_or_default();
-                error!(%status, body, "WhatsApp send failed");
-                Ok(SendResult {
-                    message_id: None,
-                    success: false,
-                    error: Some(format!("WhatsApp API error {status}")),
-                })
+        let client = reqwest::Client::new();
+        let mut message_ids = Vec::with_capacity(payloads.len());
+        for payload in &payloads {
+            match self.post_message(&client, payload).await {
+                Ok(id) => message_ids.extend(id),
+                Err(e) => {
+                    return Ok(SendResult {
+                        message_id: message_ids.last().cloned(),
+                        message_ids,
+                        success: false,
+                        error: Some(e),
+                    });
+                }
             }
-            Err(e) => Ok(SendResult {
-                message_id: None,
-                success: false,
-                error: Some(e.to_string()),
-            }),
         }
+
+        Ok(SendResult {
+            message_id: message_ids.last().cloned(),
+            message_ids,
+            success: true,
+            error: None,
+        })
     }
 
     async fn status(&self) -> ChannelStatus {
@@ -289,6 +443,7 @@ mod tests {
             app_secret: None,
             app_secret_env: None,
             webhook_port: 3101,
+            access: Default::default(),
         };
         assert_eq!(config.resolve_access_token(), Some("wa-token-123".into()));
         unsafe { std::env::remove_var("TEST_WA_TOKEN_RC") };
@@ -330,4 +485,89 @@ mod tests {
         assert_eq!(messages[0].0, "15551234567");
         assert_eq!(messages[0].1, "Hello bot!");
     }
+
+    #[test]
+    fn test_signature_verification() {
+        use hmac::{Hmac, Mac};
+        let body = br#"{"entry":[]}"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"app-secret").unwrap();
+        mac.update(body);
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+
+        assert!(verify_signature(body, &signature, "app-secret"));
+        assert!(!verify_signature(body, &signature, "other-secret"));
+        assert!(!verify_signature(b"tampered", &signature, "app-secret"));
+        assert!(!verify_signature(body, "sha256=not-hex", "app-secret"));
+
+        let mut headers = axum::http::HeaderMap::new();
+        assert!(is_authentic(&headers, body, None));
+        assert!(!is_authentic(&headers, body, Some("app-secret")));
+        headers.insert("X-Hub-Signature-256", signature.parse().unwrap());
+        assert!(is_authentic(&headers, body, Some("app-secret")));
+    }
+
+    fn button(label: &str, payload: &str) -> MessageButton {
+        MessageButton {
+            label: label.into(),
+            payload: payload.into(),
+        }
+    }
+
+    #[test]
+    fn test_interactive_reply_buttons() {
+        let payload = interactive_payload(
+            "15551234567",
+            "How can we help?",
+            &[vec![button("Billing", "menu:billing"), button("Support", "menu:support")]],
+        )
+        .unwrap();
+        assert_eq!(payload["type"], "interactive");
+        assert_eq!(payload["interactive"]["type"], "button");
+        let buttons = &payload["interactive"]["action"]["buttons"];
+        assert_eq!(buttons[1]["reply"]["id"], "menu:support");
+        assert_eq!(buttons[1]["reply"]["title"], "Support");
+    }
+
+    #[test]
+    fn test_interactive_list_and_limits() {
+        // More than three options become a list
+        let rows: Vec<Vec<MessageButton>> = (0..5)
+            .map(|i| vec![button(&format!("Option {i}"), &format!("opt:{i}"))])
+            .collect();
+        let payload = interactive_payload("1555", "Pick one", &rows).unwrap();
+        assert_eq!(payload["interactive"]["type"], "list");
+        let list_rows = &payload["interactive"]["action"]["sections"][0]["rows"];
+        assert_eq!(list_rows.as_array().unwrap().len(), 5);
+
+        let too_many: Vec<Vec<MessageButton>> = (0..11).map(|i| vec![button("x", &i.to_string())]).collect();
+        let err = interactive_payload("1555", "Pick", &too_many).unwrap_err();
+        assert!(err.contains("at most 10"), "{err}");
+
+        let long_title = [vec![button(&"y".repeat(30), "long")]];
+        assert!(interactive_payload("1555", "Pick", &long_title).is_err());
+    }
+
+    #[test]
+    fn test_interactive_reply_parsing() {
+        let body = serde_json::json!({
+            "entry": [{
+                "changes": [{
+                    "value": {
+                        "messages": [
+                            {"from": "1555", "type": "interactive",
+                             "interactive": {"type": "button_reply", "button_reply": {"id": "menu:billing", "title": "Billing"}}},
+                            {"from": "1556", "type": "interactive",
+                             "interactive": {"type": "list_reply", "list_reply": {"id": "opt:3", "title": "Option 3"}}}
+                        ]
+                    }
+                }]
+            }]
+        });
+
+        let messages = parse_webhook_messages(&body);
+        assert_eq!(messages, vec![
+            ("1555".to_string(), "menu:billing".to_string()),
+            ("1556".to_string(), "opt:3".to_string()),
+        ]);
+    }
 }
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
+use anyhow::Context;
 use clap::{Parser, Subcommand};
-use rusty_claw_core::session::SessionStore;
 
 #[derive(Parser)]
 #[command(
@@ -107,7 +107,7 @@ enum ConfigAction {
     Show,
     /// Get a specific config value
     Get { key: String },
-    /// Set a config value
+    /// Set a config value (parsed as JSON, otherwise taken as a string)
     Set { key: String, value: String },
 }
 
@@ -136,7 +136,16 @@ enum CronAction {
     /// List scheduled jobs
     List,
     /// Add a scheduled job
-    Add { schedule: String, task: String },
+    Add {
+        schedule: String,
+        task: String,
+        /// Job ID (generated if omitted)
+        #[arg(long)]
+        id: Option<String>,
+        /// Session key the job runs in
+        #[arg(long)]
+        session_key: Option<String>,
+    },
     /// Remove a scheduled job
     Remove { id: String },
 }
@@ -193,18 +202,16 @@ async fn main() -> anyhow::Result<()> {
             }
 
             // Create session store
-            let sessions: Arc<dyn rusty_claw_core::session::SessionStore> = Arc::new(
-                rusty_claw_core::session_store::JsonlSessionStore::new(
-                    rusty_claw_core::session_store::JsonlSessionStore::default_path(),
-                ),
-            );
+            let sessions = rusty_claw_core::session_store::open_session_store(&config).await?;
 
             // Create tool registry
             let mut tools = rusty_claw_tools::ToolRegistry::new();
             rusty_claw_tools::register_builtin_tools(&mut tools);
 
             // Initialize plugin system
-            let mut plugin_manager = rusty_claw_plugins::PluginManager::new();
+            let mut plugin_manager = rusty_claw_plugins::PluginManager::new().with_policy(
+                rusty_claw_plugins::PluginPolicy::from_config(config.plugins.as_ref()),
+            );
             // plugin_manager.add_plugin(Box::new(rusty_claw_plugins::logging_plugin::LoggingPlugin))?;
 
             // Load WASM plugins from workspace/plugins/ directory
@@ -218,7 +225,11 @@ async fn main() -> anyhow::Result<()> {
                                 for entry in entries.flatten() {
                                     let path = entry.path();
                                     if path.extension().is_some_and(|ext| ext == "wasm") {
-                                        match plugin_manager.add_wasm_plugin(&path, &loader) {
+                                        match plugin_manager.add_wasm_plugin(
+                                            &path,
+                                            &loader,
+                                            config.plugins.as_ref(),
+                                        ) {
                                             Ok(()) => tracing::info!(
                                                 path = %path.display(),
                                                 "Loaded WASM plugin"
@@ -252,17 +263,19 @@ async fn main() -> anyhow::Result<()> {
             let channels = Arc::new(create_channel_registry(&config));
 
             // Load skills from workspace
-            let skills_dir = config
-                .skills
-                .as_ref()
-                .and_then(|s| s.dir.as_ref())
-                .map(std::path::PathBuf::from)
-                .unwrap_or_else(|| config.workspace_dir().join("skills"));
+            let skills_dir = config.skills_dir();
             let skills = rusty_claw_gateway::skills::SkillRegistry::load_from_dir(&skills_dir);
 
             // Create pairing store
             let pairing = rusty_claw_core::pairing::PairingStore::new(
                 rusty_claw_core::pairing::PairingStore::default_path(),
+            )
+            .with_limits(
+                config
+                    .channels
+                    .as_ref()
+                    .map(|c| c.pairing.clone())
+                    .unwrap_or_default(),
             );
 
             // Create browser pool (if browser config exists)
@@ -280,7 +293,10 @@ async fn main() -> anyhow::Result<()> {
                 .cloned()
                 .unwrap_or_default();
             let cron = if !cron_jobs.is_empty() {
-                Some(Arc::new(rusty_claw_gateway::CronScheduler::new(cron_jobs)))
+                Some(Arc::new(
+                    rusty_claw_gateway::CronScheduler::new(cron_jobs)
+                        .with_history_path(rusty_claw_gateway::cron::default_history_path()),
+                ))
             } else {
                 None
             };
@@ -301,7 +317,10 @@ async fn main() -> anyhow::Result<()> {
                 pairing,
                 browser,
                 cron.clone(),
-            ));
+            )
+            .with_plugin_methods(plugin_regs.gateway_methods)
+            .with_plugin_routes(plugin_regs.http_routes)
+            .with_public_plugin_routes(plugin_regs.public_http_routes));
 
             // Start cron scheduler if configured
             if let Some(scheduler) = cron {
@@ -452,12 +471,7 @@ async fn main() -> anyhow::Result<()> {
             println!("Providers configured: {provider_count}");
 
             // Skills count
-            let skills_dir = config
-                .skills
-                .as_ref()
-                .and_then(|s| s.dir.as_ref())
-                .map(std::path::PathBuf::from)
-                .unwrap_or_else(|| config.workspace_dir().join("skills"));
+            let skills_dir = config.skills_dir();
             let skill_count = if skills_dir.exists() {
                 std::fs::read_dir(&skills_dir)
                     .map(|rd| rd.filter_map(|e| e.ok()).filter(|e| {
@@ -474,11 +488,15 @@ async fn main() -> anyhow::Result<()> {
                 "http://localhost:{}/health",
                 config.gateway_port()
             );
+            // A down gateway still answers, with 503 and the failing checks
             match reqwest::get(&url).await {
-                Ok(resp) if resp.status().is_success() => {
+                Ok(resp) if resp.status().is_success() || resp.status().as_u16() == 503 => {
                     println!("Gateway: running");
                     if let Ok(body) = resp.text().await {
                         if let Ok(health) = serde_json::from_str::<serde_json::Value>(&body) {
+                            if let Some(status) = health.get("status").and_then(|v| v.as_str()) {
+                                println!("  Health: {status}");
+                            }
                             if let Some(conns) = health.get("connections").and_then(|v| v.as_u64()) {
                                 println!("  Active connections: {conns}");
                             }
@@ -519,7 +537,16 @@ async fn main() -> anyhow::Result<()> {
                                 rusty_claw_providers::Credentials::ApiKey { api_key } => {
                                     !api_key.is_empty()
                                 }
-                                _ => true,
+                                rusty_claw_providers::Credentials::Token { token } => {
+                                    !token.is_empty()
+                                }
+                                rusty_claw_providers::Credentials::OAuth { access_token, .. } => {
+                                    !access_token.is_empty()
+                                }
+                                rusty_claw_providers::Credentials::Aws { .. } => {
+                                    println!("  [ok] Provider '{id}': AWS credentials present");
+                                    continue;
+                                }
                             };
                             if has_key {
                                 println!("  [ok] Provider '{id}': API key present");
@@ -583,8 +610,30 @@ async fn main() -> anyhow::Result<()> {
                     }
                 }
             }
-            ConfigAction::Set { key: _, value: _ } => {
-                eprintln!("Config set via CLI not yet supported. Edit the config file directly.");
+            ConfigAction::Set { key, value } => {
+                let value = serde_json::from_str(&value)
+                    .unwrap_or(serde_json::Value::String(value));
+                let before = config.get_path(&key);
+                let (updated, warnings) = match config.with_path(&key, value) {
+                    Ok(result) => result,
+                    Err(e) => {
+                        eprintln!("Error: {e}");
+                        std::process::exit(1);
+                    }
+                };
+                for warning in &warnings {
+                    println!("Warning: {warning}");
+                }
+                if let Some(parent) = config_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                updated.save(&config_path)?;
+
+                let show = |v: Option<serde_json::Value>| {
+                    v.map_or_else(|| "(unset)".to_string(), |v| v.to_string())
+                };
+                println!("{key}: {} -> {}", show(before), show(updated.get_path(&key)));
+                println!("Saved to {}", config_path.display());
             }
         },
         Commands::Channels { action } => {
@@ -614,9 +663,7 @@ async fn main() -> anyhow::Result<()> {
             }
         }
         Commands::Sessions { action } => {
-            let store = rusty_claw_core::session_store::JsonlSessionStore::new(
-                rusty_claw_core::session_store::JsonlSessionStore::default_path(),
-            );
+            let store = rusty_claw_core::session_store::open_session_store(&config).await?;
             match action {
                 SessionAction::List => {
                     match store.list().await {
@@ -640,7 +687,6 @@ async fn main() -> anyhow::Result<()> {
                     }
                 }
                 SessionAction::Reset { session } => {
-                    use rusty_claw_core::session::SessionStore;
                     let sessions = store.list().await?;
                     let target = session.as_deref().unwrap_or("");
 
@@ -662,7 +708,6 @@ async fn main() -> anyhow::Result<()> {
                     }
                 }
                 SessionAction::Delete { session } => {
-                    use rusty_claw_core::session::SessionStore;
                     let sessions = store.list().await?;
                     let found = sessions.iter().find(|s| s.key.hash_key() == session);
 
@@ -690,39 +735,100 @@ async fn main() -> anyhow::Result<()> {
                 if jobs.is_empty() {
                     println!("No cron jobs configured.");
                 } else {
+                    let history = rusty_claw_gateway::cron::load_history(
+                        &rusty_claw_gateway::cron::default_history_path(),
+                    );
                     println!("Cron jobs ({}):", jobs.len());
                     for job in &jobs {
                         let status = if job.enabled { "enabled" } else { "disabled" };
-                        println!("  {} | {} | {} | {}", job.id, job.schedule, job.task, status);
+                        let next_run = rusty_claw_gateway::cron::validate_schedule(&job.schedule)
+                            .map_or_else(|e| e, |t| format!("next run: {t}"));
+                        println!("  {} | {} | {} | {} | {}", job.id, job.schedule, job.task, status, next_run);
+                        let runs = history.get(&job.id).map(Vec::as_slice).unwrap_or_default();
+                        if let Some(last) = runs.last() {
+                            let failures = runs.iter().filter(|r| !r.success).count();
+                            println!(
+                                "      last run: {} ({}, {}ms) | {}/{} recent runs failed",
+                                last.started_at,
+                                if last.success { "ok" } else { "failed" },
+                                last.duration_ms,
+                                failures,
+                                runs.len(),
+                            );
+                            if let Some(snippet) = &last.snippet {
+                                println!("      {snippet}");
+                            }
+                        }
                     }
                 }
             }
-            CronAction::Add { schedule, task } => {
-                println!("To add a cron job, add it to the config file:");
-                println!("  cron.jobs: [{{ id: \"my-job\", schedule: \"{schedule}\", task: \"{task}\", enabled: true }}]");
-                println!("\nConfig file: {}", config_path.display());
+            CronAction::Add { schedule, task, id, session_key } => {
+                let id = id.unwrap_or_else(|| {
+                    format!("job-{}", &uuid::Uuid::new_v4().simple().to_string()[..8])
+                });
+                let job = rusty_claw_core::config::CronJob {
+                    id: id.clone(),
+                    schedule,
+                    task,
+                    session_key,
+                    enabled: true,
+                };
+                match rusty_claw_core::config::add_cron_job(&config_path, &job) {
+                    Ok(next_run) => {
+                        println!("Added cron job '{id}' to {}", config_path.display());
+                        println!("Next run: {next_run}");
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {e}");
+                        std::process::exit(1);
+                    }
+                }
             }
             CronAction::Remove { id } => {
-                println!("To remove cron job '{id}', edit the config file:");
-                println!("  {}", config_path.display());
+                if !rusty_claw_core::config::remove_cron_job(&config_path, &id)? {
+                    eprintln!("Cron job not found: {id}");
+                    std::process::exit(1);
+                }
+                println!("Removed cron job '{id}' from {}", config_path.display());
             }
         },
         Commands::Pairing { action } => {
+            use rusty_claw_core::pairing::PairingOutcome;
+
             let store = rusty_claw_core::pairing::PairingStore::new(
                 rusty_claw_core::pairing::PairingStore::default_path(),
+            )
+            .with_limits(
+                config
+                    .channels
+                    .as_ref()
+                    .map(|c| c.pairing.clone())
+                    .unwrap_or_default(),
             );
+            let report = |outcome: PairingOutcome, done: &str, channel: &str, code: &str| {
+                match outcome {
+                    PairingOutcome::Resolved => {
+                        println!("Pairing {done} for {channel} with code {code}")
+                    }
+                    PairingOutcome::Expired => {
+                        println!("Pairing code {code} for {channel} has expired")
+                    }
+                    PairingOutcome::Unknown => {
+                        println!("No pending pairing found for {channel} with code {code}")
+                    }
+                    PairingOutcome::LockedOut => {
+                        println!("Too many wrong pairing codes, try again later")
+                    }
+                }
+            };
             match action {
                 PairingAction::Approve { channel, code } => {
-                    match store.approve(&channel, &code)? {
-                        true => println!("Pairing approved for {channel} with code {code}"),
-                        false => println!("No pending pairing found for {channel} with code {code}"),
-                    }
+                    let outcome = store.approve(&channel, &code, "cli")?;
+                    report(outcome, "approved", &channel, &code);
                 }
                 PairingAction::Reject { channel, code } => {
-                    match store.reject(&channel, &code)? {
-                        true => println!("Pairing rejected for {channel} with code {code}"),
-                        false => println!("No pending pairing found for {channel} with code {code}"),
-                    }
+                    let outcome = store.reject(&channel, &code, "cli")?;
+                    report(outcome, "rejected", &channel, &code);
                 }
                 PairingAction::List => {
                     let pending = store.list_pending();
@@ -778,9 +884,19 @@ async fn run_agent_turn(
                 } => {
                     println!();
                 }
+                AgentEvent::SkillsActivated { skills } => {
+                    eprintln!("[skills: {}]", skills.join(", "));
+                }
                 AgentEvent::ToolCall { tool, .. } => {
                     eprintln!("\n[tool: {tool}]");
                 }
+                AgentEvent::ToolResult {
+                    content,
+                    partial: true,
+                    ..
+                } => {
+                    eprint!("{content}");
+                }
                 AgentEvent::ToolResult {
                     tool,
                     is_error,
@@ -951,10 +1067,11 @@ fn create_provider_registry(
         .cloned()
         .unwrap_or_default();
 
-    // Determine the default provider ID
+    // Determine the default provider ID (a failover group if the first
+    // provider belongs to one)
     let default_id = provider_configs
         .first()
-        .map(|p| p.id.clone())
+        .map(|p| p.failover_group.clone().unwrap_or_else(|| p.id.clone()))
         .unwrap_or_else(|| "anthropic".into());
 
     let mut registry = rusty_claw_providers::ProviderRegistry::new(default_id);
@@ -967,57 +1084,225 @@ fn create_provider_registry(
                 "No API key configured. Set ANTHROPIC_API_KEY or configure models.providers in config."
             );
         }
-        let provider = Arc::new(rusty_claw_providers::anthropic::AnthropicProvider::new(None));
+        let provider = Arc::new(rusty_claw_providers::retry::RetryingProvider::new(
+            Arc::new(rusty_claw_providers::timeout::TimeoutProvider::new(
+                Arc::new(rusty_claw_providers::anthropic::AnthropicProvider::new(None)),
+                std::time::Duration::from_millis(
+                    rusty_claw_providers::timeout::DEFAULT_REQUEST_TIMEOUT_MS,
+                ),
+            )),
+            rusty_claw_providers::retry::RetryPolicy::default(),
+        ));
         let credentials = rusty_claw_providers::Credentials::ApiKey { api_key };
         registry.register("anthropic".into(), provider, credentials);
     } else {
+        // (failover group, provider, credentials) in config order
+        let mut grouped: Vec<(String, Arc<dyn rusty_claw_providers::LlmProvider>, _)> =
+            Vec::new();
+
         for pc in &provider_configs {
             let api_key = pc
                 .resolve_api_key()
                 .or_else(|| default_env_key_for_provider(&pc.id))
                 .unwrap_or_default();
 
-            if api_key.is_empty() {
+            if api_key.is_empty() && pc.id != "bedrock" && pc.oauth.is_none() {
                 tracing::warn!(provider = %pc.id, "No API key found for provider");
             }
 
-            let credentials = rusty_claw_providers::Credentials::ApiKey {
+            let mut credentials = rusty_claw_providers::Credentials::ApiKey {
                 api_key: api_key.clone(),
             };
 
+            let client = rusty_claw_providers::HttpClientOptions {
+                proxy_url: pc.proxy_url.clone(),
+                headers: pc.headers.clone(),
+            }
+            .build()
+            .with_context(|| format!("Invalid proxy_url/headers for provider '{}'", pc.id))?;
+
             let provider: Arc<dyn rusty_claw_providers::LlmProvider> = match pc.id.as_str() {
                 "anthropic" => Arc::new(
                     rusty_claw_providers::anthropic::AnthropicProvider::new(
                         pc.base_url.as_deref(),
-                    ),
+                    )
+                    .with_client(client.clone()),
+                ),
+                "openai" if pc.api.as_deref() == Some("openai_responses") => Arc::new(
+                    rusty_claw_providers::openai::OpenAiProvider::openai_responses(
+                        pc.base_url.as_deref(),
+                    )
+                    .with_client(client.clone()),
                 ),
                 "openai" => Arc::new(
                     rusty_claw_providers::openai::OpenAiProvider::openai(
                         pc.base_url.as_deref(),
-                    ),
+                    )
+                    .with_client(client.clone()),
                 ),
+                "azure" => {
+                    let (Some(endpoint), Some(deployment)) = (&pc.base_url, &pc.deployment) else {
+                        tracing::warn!(
+                            provider = %pc.id,
+                            "Azure provider needs base_url (resource endpoint) and deployment, skipping"
+                        );
+                        continue;
+                    };
+                    Arc::new(
+                        rusty_claw_providers::openai::OpenAiProvider::azure(
+                            endpoint,
+                            deployment,
+                            pc.api_version.as_deref(),
+                        )
+                        .with_client(client.clone()),
+                    )
+                }
                 "openrouter" => Arc::new(
                     rusty_claw_providers::openai::OpenAiProvider::openrouter(
                         pc.base_url.as_deref(),
-                    ),
+                    )
+                    .with_client(client.clone()),
                 ),
                 "ollama" => Arc::new(
                     rusty_claw_providers::openai::OpenAiProvider::ollama(
                         pc.base_url.as_deref(),
-                    ),
+                    )
+                    .with_client(client.clone()),
                 ),
                 "google" | "gemini" => Arc::new(
                     rusty_claw_providers::google::GeminiProvider::new(
                         pc.base_url.as_deref(),
-                    ),
+                    )
+                    .with_client(client.clone()),
                 ),
+                "copilot" | "github-copilot" => {
+                    credentials = rusty_claw_providers::Credentials::Token {
+                        token: api_key.clone(),
+                    };
+                    Arc::new(
+                        rusty_claw_providers::copilot::CopilotProvider::new(
+                            pc.base_url.as_deref(),
+                        )
+                        .with_client(client.clone()),
+                    )
+                }
+                "bedrock" => {
+                    match rusty_claw_providers::bedrock::resolve_default_credentials(
+                        pc.region.as_deref(),
+                    ) {
+                        Some(aws) => credentials = aws,
+                        None => tracing::warn!(
+                            provider = %pc.id,
+                            "No AWS credentials found (set AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY or ~/.aws/credentials)"
+                        ),
+                    }
+                    Arc::new(
+                        rusty_claw_providers::bedrock::BedrockProvider::new(
+                            pc.region.as_deref(),
+                            pc.base_url.as_deref(),
+                        )
+                        .with_client(client.clone()),
+                    )
+                }
                 other => {
                     tracing::warn!(provider = other, "Unknown provider type, skipping");
                     continue;
                 }
             };
 
+            // Bound the initial response and every gap between chunks
+            let request_timeout_ms = pc
+                .request_timeout_ms
+                .unwrap_or(rusty_claw_providers::timeout::DEFAULT_REQUEST_TIMEOUT_MS);
+            let provider: Arc<dyn rusty_claw_providers::LlmProvider> = if request_timeout_ms > 0 {
+                Arc::new(rusty_claw_providers::timeout::TimeoutProvider::new(
+                    provider,
+                    std::time::Duration::from_millis(request_timeout_ms),
+                ))
+            } else {
+                provider
+            };
+
+            // Retry transient failures (429/5xx/timeouts) before any output is streamed
+            let max_retries = pc
+                .max_retries
+                .unwrap_or(rusty_claw_providers::retry::DEFAULT_MAX_RETRIES);
+            let provider: Arc<dyn rusty_claw_providers::LlmProvider> = if max_retries > 0 {
+                Arc::new(rusty_claw_providers::retry::RetryingProvider::new(
+                    provider,
+                    rusty_claw_providers::retry::RetryPolicy {
+                        max_retries,
+                        base_delay: std::time::Duration::from_millis(
+                            pc.retry_base_ms
+                                .unwrap_or(rusty_claw_providers::retry::DEFAULT_RETRY_BASE_MS),
+                        ),
+                    },
+                ))
+            } else {
+                provider
+            };
+
+            // OAuth: prefer tokens persisted by a previous refresh of the same
+            // configured tokens, and wrap the provider so expired access
+            // tokens are refreshed on 401.
+            let provider = match &pc.oauth {
+                Some(oauth) => {
+                    let configured = rusty_claw_providers::Credentials::OAuth {
+                        access_token: oauth.resolve_access_token().unwrap_or_default(),
+                        refresh_token: oauth.resolve_refresh_token(),
+                    };
+                    let store = rusty_claw_providers::oauth::TokenStore::new(
+                        rusty_claw_providers::oauth::TokenStore::default_path(),
+                    )
+                    .with_source(&configured);
+                    credentials = store.load(&pc.id).unwrap_or(configured);
+                    match &oauth.token_url {
+                        Some(token_url) => Arc::new(
+                            rusty_claw_providers::oauth::OAuthRefreshProvider::new(
+                                provider,
+                                pc.id.clone(),
+                                token_url.clone(),
+                                oauth.client_id.clone(),
+                                Some(store),
+                            )
+                            .with_client(client.clone()),
+                        ),
+                        None => {
+                            tracing::warn!(
+                                provider = %pc.id,
+                                "OAuth configured without token_url; tokens will not be refreshed"
+                            );
+                            provider
+                        }
+                    }
+                }
+                None => provider,
+            };
+
+            if let Some(group) = &pc.failover_group {
+                grouped.push((group.clone(), provider.clone(), credentials.clone()));
+            }
+
             registry.register(pc.id.clone(), provider, credentials);
+            registry.register_models(&pc.id, pc.models.iter().cloned().chain(pc.default_model.clone()));
+        }
+
+        let mut group_names: Vec<String> = Vec::new();
+        for (group, _, _) in &grouped {
+            if !group_names.contains(group) {
+                group_names.push(group.clone());
+            }
+        }
+        for name in group_names {
+            let members: Vec<_> = grouped
+                .iter()
+                .filter(|(g, _, _)| *g == name)
+                .map(|(_, p, c)| (p.clone(), c.clone()))
+                .collect();
+            let credentials = members[0].1.clone();
+            let failover =
+                rusty_claw_providers::failover::FailoverProvider::new(name.clone(), members);
+            registry.register(name, Arc::new(failover), credentials);
         }
     }
 
@@ -1031,6 +1316,8 @@ fn default_env_key_for_provider(provider_id: &str) -> Option<String> {
         "openai" => "OPENAI_API_KEY",
         "google" => "GOOGLE_AI_API_KEY",
         "openrouter" => "OPENROUTER_API_KEY",
+        "azure" => "AZURE_OPENAI_API_KEY",
+        "copilot" | "github-copilot" => "GITHUB_TOKEN",
         _ => return None,
     };
     std::env::var(env_var).ok().filter(|v| !v.is_empty())
@@ -1052,7 +1339,8 @@ fn create_channel_registry(
             let channel = rusty_claw_channels::telegram::TelegramChannel::new(
                 token,
                 tg_config.allowed_users.clone(),
-            );
+            )
+            .with_rate_limit(tg_config.rate_limit.as_ref());
             registry.register(Box::new(channel));
             tracing::info!("Telegram channel registered");
         } else {
@@ -1071,7 +1359,10 @@ fn create_channel_registry(
                 token,
                 dc_config.allowed_guilds.clone(),
                 dc_config.allowed_users.clone(),
-            );
+            )
+            .with_commands(dc_config.slash_commands())
+            .with_reply_in_threads(dc_config.reply_in_threads)
+            .with_rate_limit(dc_config.rate_limit.as_ref());
             registry.register(Box::new(channel));
             tracing::info!("Discord channel registered");
         } else {
@@ -1090,7 +1381,9 @@ fn create_channel_registry(
                 token,
                 sl_config.resolve_signing_secret(),
                 sl_config.port,
-            );
+            )
+            .with_app_token(sl_config.resolve_app_token())
+            .with_rate_limit(sl_config.rate_limit.as_ref());
             registry.register(Box::new(channel));
             tracing::info!("Slack channel registered");
         } else {
@@ -1195,6 +1488,21 @@ fn create_channel_registry(
             let channel = rusty_claw_channels::matrix::MatrixChannel::new(
                 homeserver, token, user_id,
             );
+            #[cfg(feature = "matrix-e2ee")]
+            let channel = if mx_config.e2ee {
+                channel.with_e2ee(
+                    rusty_claw_core::config::data_dir().join("matrix"),
+                    mx_config.resolve_password(),
+                )
+            } else {
+                channel
+            };
+            #[cfg(not(feature = "matrix-e2ee"))]
+            if mx_config.e2ee {
+                tracing::warn!(
+                    "Matrix e2ee is enabled in the config, but this build lacks the matrix-e2ee feature; encrypted rooms cannot be read"
+                );
+            }
             registry.register(Box::new(channel));
             tracing::info!("Matrix channel registered");
         } else {
@@ -1,5 +1,6 @@
 //! Configuration loading, validation, and hot-reload.
 
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
@@ -41,15 +42,32 @@ pub struct Config {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub memory: Option<MemoryConfig>,
+
+    /// Model ID (or ID prefix) → token prices, for cost estimates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pricing: Option<HashMap<String, ModelPricing>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// USD prices per million tokens for one model.
+///
+/// Cache prices fall back to the input price when unset.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelPricing {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_read_per_mtok: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_write_per_mtok: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AgentsConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub defaults: Option<AgentDefaults>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AgentDefaults {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub workspace: Option<String>,
@@ -63,6 +81,30 @@ pub struct AgentDefaults {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f64>,
 
+    /// Nucleus sampling cutoff.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+
+    /// Sample only from the `top_k` most likely tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+
+    /// Penalize tokens by how often they already appeared (OpenAI-style).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+
+    /// Penalize tokens that already appeared at all (OpenAI-style).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
+
+    /// Sampling seed sent to providers that support one, for reproducible runs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+
+    /// Strings that end a completion when the model produces them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_tool_iterations: Option<u32>,
 
@@ -76,6 +118,19 @@ pub struct AgentDefaults {
     /// Maximum spawn depth for multi-agent spawning (default: 3).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_spawn_depth: Option<u32>,
+
+    /// Maximum spawned agents running at once across the gateway (default: 8).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_spawned_agents: Option<u32>,
+
+    /// Maximum children running at once for a single parent session (default: 4).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_spawn_fanout: Option<u32>,
+
+    /// Mark the system prompt and tools as cacheable for providers that
+    /// support prompt caching (default: true).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_cache: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -109,7 +164,7 @@ pub struct ModelsConfig {
 }
 
 /// Configuration for a single LLM provider.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ProviderConfig {
     pub id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -120,6 +175,46 @@ pub struct ProviderConfig {
     pub base_url: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub default_model: Option<String>,
+    /// Models served by this provider, used to route sessions whose model
+    /// is set to the right provider.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+    /// API flavour override, e.g. `openai_responses` to use OpenAI's
+    /// Responses API instead of Chat Completions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api: Option<String>,
+    /// Deployment name (Azure OpenAI only); `base_url` is the resource endpoint.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deployment: Option<String>,
+    /// Azure OpenAI `api-version` query parameter.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+    /// AWS region (Bedrock only). Falls back to `AWS_REGION`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// OAuth credentials used instead of an API key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oauth: Option<ProviderOAuthConfig>,
+    /// Retries for rate limits / server errors (default: 3, 0 disables).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+    /// Base delay for exponential retry backoff in ms (default: 500).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_base_ms: Option<u64>,
+    /// Providers sharing a group name are chained in config order and
+    /// registered under the group name; later members are fallbacks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failover_group: Option<String>,
+    /// HTTP(S) proxy for all requests to this provider.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_url: Option<String>,
+    /// Extra headers sent on every request to this provider.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// Timeout in ms for the initial response and for each gap between
+    /// streamed chunks (default: 120000, 0 disables).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_timeout_ms: Option<u64>,
 }
 
 impl ProviderConfig {
@@ -129,6 +224,37 @@ impl ProviderConfig {
     }
 }
 
+/// OAuth token configuration for a provider.
+///
+/// Expired access tokens are refreshed against `token_url` and the new
+/// token pair is persisted, overriding these values on later runs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProviderOAuthConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_token_env: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token_env: Option<String>,
+    /// OAuth token endpoint used for the `refresh_token` grant.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+}
+
+impl ProviderOAuthConfig {
+    pub fn resolve_access_token(&self) -> Option<String> {
+        resolve_secret_field(&self.access_token, &self.access_token_env)
+    }
+
+    pub fn resolve_refresh_token(&self) -> Option<String> {
+        resolve_secret_field(&self.refresh_token, &self.refresh_token_env)
+    }
+}
+
 // --- Typed channel configs ---
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -159,6 +285,194 @@ pub struct ChannelsConfig {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bluebubbles: Option<BlueBubblesConfig>,
+
+    /// Emoji reactions that control the agent, keyed by emoji (Slack uses
+    /// `:shortcode:`). Defaults to 🛑 aborting the run and 🔁 retrying.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reactions: Option<HashMap<String, ReactionAction>>,
+
+    /// Retry policy for outbound sends.
+    #[serde(default)]
+    pub delivery: DeliveryConfig,
+
+    /// Expiry and brute-force limits for pairing codes.
+    #[serde(default)]
+    pub pairing: PairingConfig,
+}
+
+/// Pairing code limits. A code expires `ttl_secs` after it is issued; after
+/// `max_attempts` wrong codes an approver is locked out for `lockout_secs`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PairingConfig {
+    #[serde(default = "default_pairing_ttl_secs")]
+    pub ttl_secs: u64,
+    #[serde(default = "default_pairing_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_pairing_lockout_secs")]
+    pub lockout_secs: u64,
+}
+
+impl Default for PairingConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: default_pairing_ttl_secs(),
+            max_attempts: default_pairing_max_attempts(),
+            lockout_secs: default_pairing_lockout_secs(),
+        }
+    }
+}
+
+fn default_pairing_ttl_secs() -> u64 {
+    3600
+}
+
+fn default_pairing_max_attempts() -> u32 {
+    5
+}
+
+fn default_pairing_lockout_secs() -> u64 {
+    900
+}
+
+/// Outbound delivery retries. A send that still fails after `max_retries`
+/// retries is appended to the dead-letter log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeliveryConfig {
+    #[serde(default = "default_delivery_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled on each further attempt.
+    #[serde(default = "default_delivery_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    /// Dead-letter file (default: `<data dir>/dead-letter.jsonl`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dead_letter_path: Option<PathBuf>,
+}
+
+impl Default for DeliveryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: default_delivery_retries(),
+            initial_backoff_ms: default_delivery_backoff_ms(),
+            dead_letter_path: None,
+        }
+    }
+}
+
+impl DeliveryConfig {
+    pub fn dead_letter_path(&self) -> PathBuf {
+        self.dead_letter_path
+            .clone()
+            .unwrap_or_else(|| data_dir().join("dead-letter.jsonl"))
+    }
+}
+
+fn default_delivery_retries() -> u32 {
+    3
+}
+
+fn default_delivery_backoff_ms() -> u64 {
+    1000
+}
+
+impl ChannelsConfig {
+    /// Whether replies on `channel` are streamed via message edits.
+    pub fn stream_replies(&self, channel: &str) -> bool {
+        match channel {
+            "telegram" => self.telegram.as_ref().is_some_and(|c| c.stream_replies),
+            "discord" => self.discord.as_ref().is_some_and(|c| c.stream_replies),
+            _ => false,
+        }
+    }
+
+    /// Sender access settings for a channel by id.
+    pub fn sender_access(&self, channel: &str) -> Option<&SenderAccessConfig> {
+        match channel {
+            "telegram" => self.telegram.as_ref().map(|c| &c.access),
+            "discord" => self.discord.as_ref().map(|c| &c.access),
+            "slack" => self.slack.as_ref().map(|c| &c.access),
+            "whatsapp" => self.whatsapp.as_ref().map(|c| &c.access),
+            "signal" => self.signal.as_ref().map(|c| &c.access),
+            "googlechat" => self.googlechat.as_ref().map(|c| &c.access),
+            "msteams" => self.msteams.as_ref().map(|c| &c.access),
+            "matrix" => self.matrix.as_ref().map(|c| &c.access),
+            "bluebubbles" => self.bluebubbles.as_ref().map(|c| &c.access),
+            _ => None,
+        }
+    }
+
+    /// The action bound to `emoji`, if any.
+    pub fn reaction_action(&self, emoji: &str) -> Option<ReactionAction> {
+        match &self.reactions {
+            Some(reactions) => reactions.get(emoji).copied(),
+            None => default_reaction_action(emoji),
+        }
+    }
+}
+
+/// What a reaction on a bot message does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReactionAction {
+    /// Cancel the sender's running agent turn.
+    #[serde(rename = "agent.abort")]
+    Abort,
+    /// Run the sender's last message again.
+    #[serde(rename = "agent.retry")]
+    Retry,
+}
+
+/// Who may reach the agent through a channel. With neither `allowed_senders`
+/// nor `pairing` set every sender is let through.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SenderAccessConfig {
+    /// Sender ids allowed to talk to the agent.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_senders: Vec<String>,
+    /// Reply to unknown senders with a pairing code for the owner to approve
+    /// instead of dropping their messages.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pairing: bool,
+    /// Sender ids who may approve or reject pairings from the chat, with
+    /// `/approve <code>` / `/reject <code>` or a reaction on the pairing
+    /// notice they are sent. Admins are always allowed to talk to the agent.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub admin_senders: Vec<String>,
+}
+
+impl SenderAccessConfig {
+    /// Whether senders have to be allowlisted or paired.
+    pub fn is_restricted(&self) -> bool {
+        !self.allowed_senders.is_empty() || self.pairing
+    }
+
+    pub fn is_listed(&self, sender_id: &str) -> bool {
+        self.allowed_senders.iter().any(|s| s == sender_id) || self.is_admin(sender_id)
+    }
+
+    pub fn is_admin(&self, sender_id: &str) -> bool {
+        self.admin_senders.iter().any(|s| s == sender_id)
+    }
+}
+
+/// Built-in reaction bindings, used when `channels.reactions` is unset.
+pub fn default_reaction_action(emoji: &str) -> Option<ReactionAction> {
+    match emoji {
+        "🛑" | ":octagonal_sign:" => Some(ReactionAction::Abort),
+        "🔁" | ":repeat:" => Some(ReactionAction::Retry),
+        _ => None,
+    }
+}
+
+/// Outbound pacing for a channel: a token bucket refilled at
+/// `messages_per_second` holding up to `burst` sends.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelRateLimitConfig {
+    pub messages_per_second: f64,
+    #[serde(default = "default_rate_limit_burst")]
+    pub burst: u32,
+}
+
+fn default_rate_limit_burst() -> u32 {
+    1
 }
 
 /// Discord channel configuration.
@@ -172,12 +486,71 @@ pub struct DiscordConfig {
     pub allowed_guilds: Vec<String>,
     #[serde(default)]
     pub allowed_users: Vec<String>,
+    /// Slash commands to register. Defaults to `/ask` and `/reset`; an empty
+    /// list registers none.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commands: Option<Vec<DiscordCommandConfig>>,
+    /// Answer guild messages in a thread opened on the triggering message.
+    /// Requires the Message Content intent for follow-ups in the thread.
+    #[serde(default)]
+    pub reply_in_threads: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<ChannelRateLimitConfig>,
+
+    /// Post replies as they are generated and edit them in place, instead
+    /// of sending them when the run ends. `MessageSending` hooks are not run
+    /// on the intermediate edits.
+    #[serde(default)]
+    pub stream_replies: bool,
+
+    /// Sender allowlist, enforced by the gateway for every channel.
+    #[serde(flatten)]
+    pub access: SenderAccessConfig,
 }
 
 impl DiscordConfig {
     pub fn resolve_bot_token(&self) -> Option<String> {
         resolve_secret_field(&self.bot_token, &self.bot_token_env)
     }
+
+    /// The configured slash commands, or the default set.
+    pub fn slash_commands(&self) -> Vec<DiscordCommandConfig> {
+        self.commands.clone().unwrap_or_else(|| {
+            vec![
+                DiscordCommandConfig {
+                    name: "ask".into(),
+                    description: "Ask the assistant".into(),
+                    action: DiscordCommandAction::Prompt,
+                },
+                DiscordCommandConfig {
+                    name: "reset".into(),
+                    description: "Start a fresh conversation".into(),
+                    action: DiscordCommandAction::Reset,
+                },
+            ]
+        })
+    }
+}
+
+/// A Discord slash command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiscordCommandConfig {
+    /// Command name (lowercase, 1-32 chars).
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub action: DiscordCommandAction,
+}
+
+/// What invoking a slash command does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscordCommandAction {
+    /// Takes a required `prompt` option and sends it to the agent.
+    #[default]
+    Prompt,
+    /// Resets the caller's session.
+    Reset,
 }
 
 /// Slack channel configuration.
@@ -191,10 +564,20 @@ pub struct SlackConfig {
     pub signing_secret: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub signing_secret_env: Option<String>,
+    /// App-level token (`xapp-...`). With no `port`, enables Socket Mode.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub app_token: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_token_env: Option<String>,
+    /// Events API webhook port.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<ChannelRateLimitConfig>,
+
+    /// Sender allowlist, enforced by the gateway for every channel.
+    #[serde(flatten)]
+    pub access: SenderAccessConfig,
 }
 
 impl SlackConfig {
@@ -205,6 +588,10 @@ impl SlackConfig {
     pub fn resolve_signing_secret(&self) -> Option<String> {
         resolve_secret_field(&self.signing_secret, &self.signing_secret_env)
     }
+
+    pub fn resolve_app_token(&self) -> Option<String> {
+        resolve_secret_field(&self.app_token, &self.app_token_env)
+    }
 }
 
 /// Telegram channel configuration.
@@ -217,6 +604,18 @@ pub struct TelegramConfig {
     /// Optional list of allowed user IDs. Empty = allow all.
     #[serde(default)]
     pub allowed_users: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<ChannelRateLimitConfig>,
+
+    /// Post replies as they are generated and edit them in place, instead
+    /// of sending them when the run ends. `MessageSending` hooks are not run
+    /// on the intermediate edits.
+    #[serde(default)]
+    pub stream_replies: bool,
+
+    /// Sender allowlist, enforced by the gateway for every channel.
+    #[serde(flatten)]
+    pub access: SenderAccessConfig,
 }
 
 impl TelegramConfig {
@@ -243,6 +642,10 @@ pub struct WhatsAppConfig {
     pub app_secret_env: Option<String>,
     #[serde(default = "default_whatsapp_port")]
     pub webhook_port: u16,
+
+    /// Sender allowlist, enforced by the gateway for every channel.
+    #[serde(flatten)]
+    pub access: SenderAccessConfig,
 }
 
 fn default_whatsapp_port() -> u16 {
@@ -271,6 +674,10 @@ pub struct SignalConfig {
     /// Poll interval in milliseconds (default: 2000).
     #[serde(default = "default_signal_poll_interval")]
     pub poll_interval_ms: u64,
+
+    /// Sender allowlist, enforced by the gateway for every channel.
+    #[serde(flatten)]
+    pub access: SenderAccessConfig,
 }
 
 fn default_signal_api_url() -> String {
@@ -296,6 +703,10 @@ pub struct GoogleChatConfig {
     pub project_id: Option<String>,
     #[serde(default = "default_googlechat_port")]
     pub webhook_port: u16,
+
+    /// Sender allowlist, enforced by the gateway for every channel.
+    #[serde(flatten)]
+    pub access: SenderAccessConfig,
 }
 
 fn default_googlechat_port() -> u16 {
@@ -313,6 +724,10 @@ pub struct MsTeamsConfig {
     pub app_password_env: Option<String>,
     #[serde(default = "default_msteams_port")]
     pub webhook_port: u16,
+
+    /// Sender allowlist, enforced by the gateway for every channel.
+    #[serde(flatten)]
+    pub access: SenderAccessConfig,
 }
 
 fn default_msteams_port() -> u16 {
@@ -340,6 +755,16 @@ pub struct MatrixConfig {
     pub access_token: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub access_token_env: Option<String>,
+    /// End-to-end encrypted rooms (Olm/Megolm). The crypto store lives under
+    /// `<data_dir>/matrix`, and the access token must belong to a device.
+    /// `password` lets the first login publish cross-signing keys. Needs a
+    /// build with the `matrix-e2ee` feature.
+    #[serde(default)]
+    pub e2ee: bool,
+
+    /// Sender allowlist, enforced by the gateway for every channel.
+    #[serde(flatten)]
+    pub access: SenderAccessConfig,
 }
 
 impl MatrixConfig {
@@ -361,6 +786,10 @@ pub struct BlueBubblesConfig {
     pub password: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub password_env: Option<String>,
+
+    /// Sender allowlist, enforced by the gateway for every channel.
+    #[serde(flatten)]
+    pub access: SenderAccessConfig,
 }
 
 fn default_bluebubbles_api_url() -> String {
@@ -391,6 +820,11 @@ pub struct ToolsConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub search_api_key: Option<String>,
 
+    /// Web search backend: "brave", "searxng" or "tavily".
+    /// Detected from `search_api_url` when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_provider: Option<String>,
+
     /// Text-to-speech configuration.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tts: Option<TtsConfig>,
@@ -403,6 +837,10 @@ pub struct ToolsConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub transcription: Option<TranscriptionConfig>,
 
+    /// Voice activity detection for talk sessions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vad: Option<VadConfig>,
+
     /// Exec tool configuration.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub exec: Option<ExecConfig>,
@@ -410,12 +848,16 @@ pub struct ToolsConfig {
     /// Browser automation configuration.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub browser: Option<BrowserConfig>,
+
+    /// web_fetch tool limits and network policy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web_fetch: Option<WebFetchConfig>,
 }
 
 /// Text-to-speech (TTS) configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TtsConfig {
-    /// TTS provider (default: "elevenlabs").
+    /// TTS provider: "elevenlabs" (default), "openai", or "azure".
     #[serde(default = "default_tts_provider")]
     pub provider: String,
 
@@ -433,9 +875,14 @@ pub struct TtsConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub default_model: Option<String>,
 
-    /// Output format (default: "mp3_44100_128").
+    /// Output format in the provider's naming (default: "mp3_44100_128"
+    /// for ElevenLabs, "mp3" for OpenAI).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub output_format: Option<String>,
+
+    /// Azure Speech region (e.g. "westeurope"). Falls back to `AZURE_SPEECH_REGION`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
 }
 
 fn default_tts_provider() -> String {
@@ -500,6 +947,19 @@ pub struct TranscriptionConfig {
     /// Model name (e.g. "whisper-large-v3-turbo").
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
+
+    /// Default language hint (e.g. "en") when a call doesn't pass one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+
+    /// Send partial transcripts of talk-session speech while the user is
+    /// still speaking. Each partial is an extra transcription request.
+    #[serde(default)]
+    pub streaming: bool,
+
+    /// Audio between partial transcripts, in milliseconds (default: 1000).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partial_interval_ms: Option<u64>,
 }
 
 fn default_transcription_provider() -> String {
@@ -512,6 +972,73 @@ impl TranscriptionConfig {
     }
 }
 
+/// Voice activity detection tuning for talk sessions. Out-of-range values
+/// are clamped by [`VadConfig::clamped`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VadConfig {
+    /// RMS energy of a 16-bit PCM frame above which it counts as speech
+    /// (default: 300). Raise it in noisy environments.
+    #[serde(default = "default_vad_energy_threshold")]
+    pub energy_threshold: f64,
+
+    /// Speech must last this long before an utterance starts, so short
+    /// noises are ignored (default: 60).
+    #[serde(default = "default_vad_min_speech_ms")]
+    pub min_speech_ms: u64,
+
+    /// Silence that ends an utterance (default: 300).
+    #[serde(default = "default_vad_silence_hangover_ms")]
+    pub silence_hangover_ms: u64,
+
+    /// Longest utterance before it is cut and sent anyway (default: 30000).
+    #[serde(default = "default_vad_max_utterance_ms")]
+    pub max_utterance_ms: u64,
+}
+
+fn default_vad_energy_threshold() -> f64 {
+    300.0
+}
+
+fn default_vad_min_speech_ms() -> u64 {
+    60
+}
+
+fn default_vad_silence_hangover_ms() -> u64 {
+    300
+}
+
+fn default_vad_max_utterance_ms() -> u64 {
+    30_000
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            energy_threshold: default_vad_energy_threshold(),
+            min_speech_ms: default_vad_min_speech_ms(),
+            silence_hangover_ms: default_vad_silence_hangover_ms(),
+            max_utterance_ms: default_vad_max_utterance_ms(),
+        }
+    }
+}
+
+impl VadConfig {
+    /// The config with every value clamped to a usable range.
+    pub fn clamped(self) -> Self {
+        let energy_threshold = if self.energy_threshold.is_finite() {
+            self.energy_threshold.clamp(10.0, 20_000.0)
+        } else {
+            default_vad_energy_threshold()
+        };
+        Self {
+            energy_threshold,
+            min_speech_ms: self.min_speech_ms.clamp(20, 2_000),
+            silence_hangover_ms: self.silence_hangover_ms.clamp(100, 5_000),
+            max_utterance_ms: self.max_utterance_ms.clamp(1_000, 120_000),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GatewayConfig {
     #[serde(default = "default_port")]
@@ -531,12 +1058,20 @@ pub struct GatewayConfig {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tailscale: Option<TailscaleConfig>,
+
+    /// Seconds to let in-flight agent runs finish on shutdown before they
+    /// are cancelled (default: 30).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shutdown_grace_secs: Option<u64>,
 }
 
 fn default_port() -> u16 {
     18789
 }
 
+/// Default lifetime of gateway login tokens: 7 days.
+pub const DEFAULT_LOGIN_TTL_SECS: u64 = 7 * 24 * 3600;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GatewayAuthConfig {
     /// Auth mode: "none", "token", or "password". Default: "none".
@@ -554,6 +1089,47 @@ pub struct GatewayAuthConfig {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub password_env: Option<String>,
+
+    /// Secret for signing login tokens. Random per start when unset, which
+    /// logs everyone out on restart.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_secret: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_secret_env: Option<String>,
+
+    /// Lifetime of login tokens in seconds (default: 7 days).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub login_ttl_secs: Option<u64>,
+
+    /// Named API keys, each limited to a set of gateway methods. In token
+    /// mode they work without a shared `token`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<ApiKeyConfig>,
+}
+
+/// A named gateway API key, presented like the shared token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyConfig {
+    pub name: String,
+    /// Hex-encoded SHA-256 of the key, so the key itself is not stored.
+    pub key_hash: String,
+    /// Methods this key may call: exact names, `prefix.*`, or `*` for all.
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+impl ApiKeyConfig {
+    /// Whether this key's scopes include `method`.
+    pub fn allows(&self, method: &str) -> bool {
+        self.scopes.iter().any(|scope| {
+            scope == "*"
+                || scope == method
+                || scope
+                    .strip_suffix(".*")
+                    .is_some_and(|prefix| method.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('.')))
+        })
+    }
 }
 
 impl GatewayAuthConfig {
@@ -567,6 +1143,16 @@ impl GatewayAuthConfig {
         resolve_secret_field(&self.password, &self.password_env)
     }
 
+    /// Resolve the login token signing secret from direct value or env var.
+    pub fn resolve_token_secret(&self) -> Option<String> {
+        resolve_secret_field(&self.token_secret, &self.token_secret_env)
+    }
+
+    /// Lifetime of login tokens in seconds.
+    pub fn login_ttl_secs(&self) -> u64 {
+        self.login_ttl_secs.unwrap_or(DEFAULT_LOGIN_TTL_SECS)
+    }
+
     /// Get the effective auth mode.
     pub fn effective_mode(&self) -> &str {
         self.mode.as_deref().unwrap_or("none")
@@ -588,12 +1174,56 @@ pub struct RateLimitConfig {
     /// Max WebSocket connections per IP (default: 10).
     #[serde(default = "default_max_connections_per_ip")]
     pub max_connections_per_ip: u32,
+
+    /// Method calls per connection (default: 10/s, burst 40).
+    #[serde(default = "default_connection_requests")]
+    pub connection_requests: RequestRate,
+
+    /// Method calls per client IP across its connections (default: 30/s, burst 100).
+    #[serde(default = "default_ip_requests")]
+    pub ip_requests: RequestRate,
+
+    /// Calls per client IP to methods that start agent runs, `agent` and
+    /// `agents.spawn` (default: 30/min, burst 10). The per-IP limits apply to
+    /// loopback clients too, so behind a reverse proxy all clients share them.
+    #[serde(default = "default_expensive_requests")]
+    pub expensive_requests: RequestRate,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_connections_per_ip: default_max_connections_per_ip(),
+            connection_requests: default_connection_requests(),
+            ip_requests: default_ip_requests(),
+            expensive_requests: default_expensive_requests(),
+        }
+    }
+}
+
+/// A token bucket refilled at `per_second` holding up to `burst` requests.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RequestRate {
+    pub per_second: f64,
+    pub burst: u32,
 }
 
 fn default_max_connections_per_ip() -> u32 {
     10
 }
 
+fn default_connection_requests() -> RequestRate {
+    RequestRate { per_second: 10.0, burst: 40 }
+}
+
+fn default_ip_requests() -> RequestRate {
+    RequestRate { per_second: 30.0, burst: 100 }
+}
+
+fn default_expensive_requests() -> RequestRate {
+    RequestRate { per_second: 0.5, burst: 10 }
+}
+
 /// Exec tool configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecConfig {
@@ -601,17 +1231,65 @@ pub struct ExecConfig {
     #[serde(default = "default_exec_mode")]
     pub mode: String,
 
-    /// Allowed command prefixes (only used in allowlist mode).
+    /// Allowed commands (only used in allowlist mode). Each entry is a
+    /// program optionally followed by leading arguments, matched against the
+    /// command's parsed argv.
     #[serde(default)]
-    pub allowed_commands: Vec<String>,
+    pub allowed_commands: Vec<AllowedCommand>,
 
-    /// Docker image for sandboxed execution (optional).
+    /// Docker image for sandboxed execution (optional). When set, commands
+    /// run in a throwaway container with the workspace mounted, unless the
+    /// sandbox mode is `off`.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub docker_image: Option<String>,
 
+    /// Extra Docker volume mounts (`host:container[:ro]`) for sandboxed execution.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub docker_mounts: Vec<String>,
+
+    /// Allow network access inside the Docker sandbox (default: false).
+    #[serde(default)]
+    pub docker_network: bool,
+
     /// Maximum output size in bytes (default: 100KB).
     #[serde(default = "default_max_output_bytes")]
     pub max_output_bytes: usize,
+
+    /// Default command timeout in ms (default: 30000).
+    #[serde(default = "default_exec_timeout")]
+    pub timeout_ms: u64,
+}
+
+/// An exec allowlist entry: either `"git"` / `"cargo test"`, or
+/// `{ command = "make", shell = true }` to also permit shell operators
+/// (`;`, `&&`, `|`, redirection, substitution) after the command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AllowedCommand {
+    Command(String),
+    Detailed {
+        command: String,
+        #[serde(default)]
+        shell: bool,
+    },
+}
+
+impl AllowedCommand {
+    pub fn command(&self) -> &str {
+        match self {
+            Self::Command(command) | Self::Detailed { command, .. } => command,
+        }
+    }
+
+    pub fn shell(&self) -> bool {
+        matches!(self, Self::Detailed { shell: true, .. })
+    }
+}
+
+impl From<&str> for AllowedCommand {
+    fn from(command: &str) -> Self {
+        Self::Command(command.to_string())
+    }
 }
 
 fn default_exec_mode() -> String {
@@ -622,6 +1300,49 @@ fn default_max_output_bytes() -> usize {
     100_000
 }
 
+fn default_exec_timeout() -> u64 {
+    30_000
+}
+
+/// web_fetch tool configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebFetchConfig {
+    /// Maximum body size in bytes; longer responses are truncated (default: 2MB).
+    #[serde(default = "default_web_fetch_max_bytes")]
+    pub max_bytes: usize,
+
+    /// Request timeout in ms (default: 30000).
+    #[serde(default = "default_web_fetch_timeout")]
+    pub timeout_ms: u64,
+
+    /// Fetch and honor the site's robots.txt before retrieving a page.
+    #[serde(default)]
+    pub respect_robots: bool,
+
+    /// Allow URLs that resolve to private, loopback or link-local addresses.
+    #[serde(default)]
+    pub allow_private_networks: bool,
+}
+
+impl Default for WebFetchConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: default_web_fetch_max_bytes(),
+            timeout_ms: default_web_fetch_timeout(),
+            respect_robots: false,
+            allow_private_networks: false,
+        }
+    }
+}
+
+fn default_web_fetch_max_bytes() -> usize {
+    2 * 1024 * 1024
+}
+
+fn default_web_fetch_timeout() -> u64 {
+    30_000
+}
+
 /// Tailscale integration configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TailscaleConfig {
@@ -652,6 +1373,36 @@ pub struct BrowserConfig {
     /// Page operation timeout in ms (default: 30000).
     #[serde(default = "default_browser_timeout")]
     pub timeout_ms: u64,
+
+    /// Directory for persistent browser profiles, one per session. When set,
+    /// cookies and localStorage survive browser restarts; otherwise each
+    /// session gets an isolated in-memory profile.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profiles_dir: Option<String>,
+
+    /// Longest side of a screenshot in pixels; larger captures are scaled
+    /// down (default: 8000).
+    #[serde(default = "default_screenshot_max_dimension")]
+    pub screenshot_max_dimension: u32,
+
+    /// Largest screenshot returned, in bytes. Bigger PNGs are re-encoded as
+    /// JPEG, and refused if still too big (default: 5MB).
+    #[serde(default = "default_screenshot_max_bytes")]
+    pub screenshot_max_bytes: usize,
+
+    /// Seconds a session's page may go unused before it is closed and an
+    /// in-memory profile discarded (default: 600).
+    #[serde(default = "default_browser_idle_timeout")]
+    pub idle_timeout_secs: u64,
+}
+
+impl BrowserConfig {
+    /// Resolved persistent profiles directory, if configured.
+    pub fn profiles_path(&self) -> Option<PathBuf> {
+        self.profiles_dir
+            .as_ref()
+            .map(|dir| PathBuf::from(shellexpand::tilde(dir).as_ref()))
+    }
 }
 
 fn default_max_pages() -> usize {
@@ -662,6 +1413,18 @@ fn default_browser_timeout() -> u64 {
     30_000
 }
 
+fn default_screenshot_max_dimension() -> u32 {
+    8000
+}
+
+fn default_screenshot_max_bytes() -> usize {
+    5 * 1024 * 1024
+}
+
+fn default_browser_idle_timeout() -> u64 {
+    600
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SessionConfig {
     /// Maximum context tokens before compaction triggers (default: 100,000).
@@ -675,6 +1438,22 @@ pub struct SessionConfig {
     /// Number of recent transcript entries to keep during compaction (default: 10).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub compact_keep_recent: Option<usize>,
+
+    /// Session storage backend (default: jsonl).
+    #[serde(default)]
+    pub store: SessionStoreKind,
+}
+
+/// Session storage backend.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionStoreKind {
+    /// `sessions.json` index plus one JSONL transcript file per session.
+    #[default]
+    Jsonl,
+    /// Single SQLite database, `sessions.db`. Existing JSONL sessions are
+    /// imported the first time it is opened.
+    Sqlite,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -696,6 +1475,25 @@ pub struct CronJob {
     pub enabled: bool,
 }
 
+/// Parse a cron schedule: the standard 5-field form, or 6 fields with a
+/// leading seconds field.
+pub fn parse_cron_schedule(schedule: &str) -> Result<croner::Cron, String> {
+    croner::Cron::new(schedule)
+        .with_seconds_optional()
+        .parse()
+        .map_err(|e| format!("Invalid cron expression '{schedule}': {e}"))
+}
+
+/// Next time `schedule` fires after `after`.
+pub fn next_cron_run(
+    schedule: &str,
+    after: chrono::DateTime<chrono::Utc>,
+) -> Result<chrono::DateTime<chrono::Utc>, String> {
+    parse_cron_schedule(schedule)?
+        .find_next_occurrence(&after, false)
+        .map_err(|e| format!("Cron expression '{schedule}' never fires: {e}"))
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct LoggingConfig {
     /// Log format: "plain" (default) or "json".
@@ -724,7 +1522,49 @@ fn default_log_output() -> String {
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
-pub struct PluginsConfig {}
+pub struct PluginsConfig {
+    /// Per-plugin settings for WASM plugins, keyed by module name (the
+    /// `.wasm` file stem).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub wasm: HashMap<String, WasmPluginConfig>,
+
+    /// Let plugins whose manifest requests filesystem access load.
+    #[serde(default)]
+    pub allow_filesystem: bool,
+
+    /// Let plugins whose manifest requests network access load. WASM tools
+    /// additionally need a per-plugin `http` grant to reach the network.
+    #[serde(default)]
+    pub allow_network: bool,
+
+    /// Hook events plugins may subscribe to (snake_case names, e.g.
+    /// "before_tool_call"). Unset allows every event.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_hooks: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WasmPluginConfig {
+    /// Grants the plugin's tools the `http_fetch` host function. Plugins
+    /// without it cannot make network requests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http: Option<WasmHttpConfig>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WasmHttpConfig {
+    /// Domains the plugin may call. A domain also allows its subdomains.
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+
+    /// Largest response body returned to the plugin (default: 1 MiB).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_response_bytes: Option<usize>,
+
+    /// Per-request timeout in seconds (default: 15).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SkillsConfig {
@@ -735,6 +1575,35 @@ pub struct SkillsConfig {
     /// Automatically activate matching skills based on context.
     #[serde(default)]
     pub auto_activate: bool,
+
+    /// How the `tools` lists of active skills apply (default: restrict).
+    #[serde(default)]
+    pub tool_mode: SkillToolMode,
+
+    /// Tools kept available when skills restrict the tool set
+    /// (default: memory_search, memory_get).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub core_tools: Option<Vec<String>>,
+}
+
+/// Effect of an active skill's `tools` list on the tools offered to the model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkillToolMode {
+    /// Offer only the active skills' tools plus the core tools.
+    #[default]
+    Restrict,
+    /// Offer every registered tool; skill tool lists are informational.
+    Augment,
+}
+
+impl SkillsConfig {
+    /// Tools kept available when skills restrict the tool set.
+    pub fn core_tools(&self) -> Vec<String> {
+        self.core_tools
+            .clone()
+            .unwrap_or_else(|| vec!["memory_search".into(), "memory_get".into()])
+    }
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -746,6 +1615,28 @@ pub struct MemoryConfig {
     /// Maximum entries per namespace (0 = unlimited).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_entries: Option<usize>,
+
+    /// Embedding provider for semantic `memory_search`. Without it, search
+    /// falls back to keyword matching.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embeddings: Option<EmbeddingsConfig>,
+}
+
+/// Embedding provider used to vectorize memory entries.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmbeddingsConfig {
+    /// `openai` (or any OpenAI-compatible endpoint) or `ollama`.
+    pub provider: String,
+    /// Embedding model (default: `text-embedding-3-small` / `nomic-embed-text`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// Env var holding the API key (default: `OPENAI_API_KEY` for `openai`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key_env: Option<String>,
 }
 
 /// Resolve a secret: check the direct value first, then the env-var reference.
@@ -811,6 +1702,15 @@ impl Config {
             .unwrap_or_else(|| data_dir().join("workspace"))
     }
 
+    /// Directory skill definitions are loaded from.
+    pub fn skills_dir(&self) -> PathBuf {
+        self.skills
+            .as_ref()
+            .and_then(|s| s.dir.as_ref())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| self.workspace_dir().join("skills"))
+    }
+
     /// Gateway port.
     pub fn gateway_port(&self) -> u16 {
         self.gateway
@@ -855,6 +1755,54 @@ impl Config {
             .and_then(|d| d.temperature)
     }
 
+    /// Get top-p (nucleus sampling) setting.
+    pub fn top_p(&self) -> Option<f64> {
+        self.agents
+            .as_ref()
+            .and_then(|a| a.defaults.as_ref())
+            .and_then(|d| d.top_p)
+    }
+
+    /// Get top-k sampling setting.
+    pub fn top_k(&self) -> Option<u32> {
+        self.agents
+            .as_ref()
+            .and_then(|a| a.defaults.as_ref())
+            .and_then(|d| d.top_k)
+    }
+
+    /// Get frequency penalty setting.
+    pub fn frequency_penalty(&self) -> Option<f64> {
+        self.agents
+            .as_ref()
+            .and_then(|a| a.defaults.as_ref())
+            .and_then(|d| d.frequency_penalty)
+    }
+
+    /// Get presence penalty setting.
+    pub fn presence_penalty(&self) -> Option<f64> {
+        self.agents
+            .as_ref()
+            .and_then(|a| a.defaults.as_ref())
+            .and_then(|d| d.presence_penalty)
+    }
+
+    /// Get sampling seed setting.
+    pub fn seed(&self) -> Option<u64> {
+        self.agents
+            .as_ref()
+            .and_then(|a| a.defaults.as_ref())
+            .and_then(|d| d.seed)
+    }
+
+    /// Get configured stop sequences.
+    pub fn stop_sequences(&self) -> Option<Vec<String>> {
+        self.agents
+            .as_ref()
+            .and_then(|a| a.defaults.as_ref())
+            .and_then(|d| d.stop_sequences.clone())
+    }
+
     /// Get max context tokens setting.
     pub fn max_context_tokens(&self) -> usize {
         self.session
@@ -871,6 +1819,14 @@ impl Config {
             .unwrap_or(10)
     }
 
+    /// Get how long shutdown waits for in-flight agent runs.
+    pub fn shutdown_grace_secs(&self) -> u64 {
+        self.gateway
+            .as_ref()
+            .and_then(|g| g.shutdown_grace_secs)
+            .unwrap_or(30)
+    }
+
     /// Get the max spawn depth for multi-agent spawning.
     pub fn max_spawn_depth(&self) -> u32 {
         self.agents
@@ -880,6 +1836,33 @@ impl Config {
             .unwrap_or(3)
     }
 
+    /// Get the cap on concurrently running spawned agents.
+    pub fn max_spawned_agents(&self) -> u32 {
+        self.agents
+            .as_ref()
+            .and_then(|a| a.defaults.as_ref())
+            .and_then(|d| d.max_spawned_agents)
+            .unwrap_or(8)
+    }
+
+    /// Get the cap on concurrently running children per parent session.
+    pub fn max_spawn_fanout(&self) -> u32 {
+        self.agents
+            .as_ref()
+            .and_then(|a| a.defaults.as_ref())
+            .and_then(|d| d.max_spawn_fanout)
+            .unwrap_or(4)
+    }
+
+    /// Whether prompt caching is enabled for agent requests.
+    pub fn prompt_cache_enabled(&self) -> bool {
+        self.agents
+            .as_ref()
+            .and_then(|a| a.defaults.as_ref())
+            .and_then(|d| d.prompt_cache)
+            .unwrap_or(true)
+    }
+
     /// Find a provider config by id.
     pub fn provider(&self, id: &str) -> Option<&ProviderConfig> {
         self.models
@@ -897,16 +1880,21 @@ impl Config {
     }
 
     /// Get a config value by dotted path (e.g. "gateway.port", "agents.defaults.model").
+    /// Numeric segments index into arrays ("agents.list.0.id").
     pub fn get_path(&self, path: &str) -> Option<serde_json::Value> {
         let json = serde_json::to_value(self).ok()?;
         let mut current = &json;
         for segment in path.split('.') {
-            current = current.get(segment)?;
+            current = match current {
+                serde_json::Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+                _ => current.get(segment)?,
+            };
         }
         Some(current.clone())
     }
 
-    /// Set a config value by dotted path. Returns the modified config.
+    /// Set a config value by dotted path. Numeric segments index into arrays;
+    /// an index one past the end appends.
     pub fn set_path(&mut self, path: &str, value: serde_json::Value) -> anyhow::Result<()> {
         let mut json = serde_json::to_value(&*self)
             .map_err(|e| anyhow::anyhow!("Config serialization error: {e}"))?;
@@ -919,15 +1907,42 @@ impl Config {
         // Navigate to the parent of the target key
         let mut current = &mut json;
         for segment in &segments[..segments.len() - 1] {
-            if current.get(segment).is_none() {
-                current[segment] = serde_json::json!({});
-            }
-            current = current.get_mut(segment).unwrap();
+            current = match current {
+                serde_json::Value::Array(items) => {
+                    let len = items.len();
+                    segment
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|i| items.get_mut(i))
+                        .ok_or_else(|| anyhow::anyhow!("'{segment}' in '{path}' is not an index below {len}"))?
+                }
+                serde_json::Value::Object(map) => {
+                    let entry = map.entry(segment.to_string()).or_insert(serde_json::Value::Null);
+                    if entry.is_null() {
+                        *entry = serde_json::json!({});
+                    }
+                    entry
+                }
+                _ => anyhow::bail!("Parent of '{segment}' in '{path}' is not an object or array"),
+            };
         }
 
         // Set the value
         let last = segments.last().unwrap();
-        current[last] = value;
+        match current {
+            serde_json::Value::Array(items) => {
+                let len = items.len();
+                match last.parse::<usize>() {
+                    Ok(i) if i < len => items[i] = value,
+                    Ok(i) if i == len => items.push(value),
+                    _ => anyhow::bail!("'{last}' in '{path}' is not an index up to {len}"),
+                }
+            }
+            serde_json::Value::Object(map) => {
+                map.insert(last.to_string(), value);
+            }
+            _ => anyhow::bail!("Parent of '{last}' in '{path}' is not an object or array"),
+        }
 
         // Deserialize back
         *self = serde_json::from_value(json)
@@ -935,19 +1950,36 @@ impl Config {
         Ok(())
     }
 
+    /// The config with `value` set at `path`, if the result both deserializes
+    /// and passes [`validate`](Self::validate). `self` is left untouched;
+    /// validation warnings are returned alongside the new config.
+    pub fn with_path(&self, path: &str, value: serde_json::Value) -> anyhow::Result<(Config, Vec<String>)> {
+        let mut updated = self.clone();
+        updated.set_path(path, value)?;
+        let (warnings, errors) = updated.validate();
+        if !errors.is_empty() {
+            anyhow::bail!("Invalid config: {}", errors.join("; "));
+        }
+        Ok((updated, warnings))
+    }
+
     /// Validate config, returning (warnings, errors).
     pub fn validate(&self) -> (Vec<String>, Vec<String>) {
         let mut warnings = Vec::new();
         let mut errors = Vec::new();
 
-        // Check providers for API keys (skip ollama)
+        // Check providers for API keys (skip ollama, and bedrock which uses AWS credentials)
         if let Some(providers) = self
             .models
             .as_ref()
             .and_then(|m| m.providers.as_ref())
         {
             for p in providers {
-                if p.id != "ollama" && p.resolve_api_key().is_none() {
+                if p.id != "ollama"
+                    && p.id != "bedrock"
+                    && p.oauth.is_none()
+                    && p.resolve_api_key().is_none()
+                {
                     warnings.push(format!(
                         "Provider '{}' has no API key configured",
                         p.id
@@ -983,6 +2015,48 @@ impl Config {
             }
         }
 
+        // Request rates must refill
+        if let Some(limits) = self.gateway.as_ref().and_then(|g| g.rate_limit.as_ref()) {
+            let rates = [
+                ("connection_requests", limits.connection_requests),
+                ("ip_requests", limits.ip_requests),
+                ("expensive_requests", limits.expensive_requests),
+            ];
+            for (name, rate) in rates {
+                if !(rate.per_second > 0.0 && rate.per_second.is_finite()) {
+                    errors.push(format!(
+                        "gateway.rate_limit.{name}.per_second must be positive, got {}",
+                        rate.per_second
+                    ));
+                }
+            }
+        }
+
+        if let Some(channels) = &self.channels {
+            let rates = [
+                ("telegram", channels.telegram.as_ref().and_then(|c| c.rate_limit.as_ref())),
+                ("discord", channels.discord.as_ref().and_then(|c| c.rate_limit.as_ref())),
+                ("slack", channels.slack.as_ref().and_then(|c| c.rate_limit.as_ref())),
+            ];
+            for (name, rate) in rates {
+                if let Some(rate) = rate
+                    && !(rate.messages_per_second > 0.0 && rate.messages_per_second.is_finite())
+                {
+                    errors.push(format!(
+                        "channels.{name}.rate_limit.messages_per_second must be positive, got {}",
+                        rate.messages_per_second
+                    ));
+                }
+            }
+        }
+
+        // Check cron schedules parse
+        for job in self.cron.iter().flat_map(|c| c.jobs.iter().flatten()) {
+            if let Err(e) = parse_cron_schedule(&job.schedule) {
+                errors.push(format!("cron.jobs['{}']: {e}", job.id));
+            }
+        }
+
         (warnings, errors)
     }
 
@@ -992,6 +2066,83 @@ impl Config {
         std::fs::write(path, json)?;
         Ok(())
     }
+
+    /// Hash of this config's contents, which changes whenever any setting
+    /// does. Keys are serialized sorted, so equal configs hash equally.
+    pub fn version(&self) -> u64 {
+        use std::hash::{DefaultHasher, Hash, Hasher};
+        let mut hasher = DefaultHasher::new();
+        serde_json::to_value(self)
+            .map(|value| value.to_string())
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Add `job` to `cron.jobs` in the config file at `path`, creating the file
+/// if needed, and return its next run. The file's JSON is edited directly, so
+/// other keys and `${ENV_VAR}` references stay as written.
+pub fn add_cron_job(path: &Path, job: &CronJob) -> anyhow::Result<chrono::DateTime<chrono::Utc>> {
+    let next_run = next_cron_run(&job.schedule, chrono::Utc::now()).map_err(anyhow::Error::msg)?;
+
+    let mut doc = read_config_document(path)?;
+    let jobs = cron_jobs_mut(&mut doc)?;
+    if jobs.iter().any(|j| j.get("id").and_then(|v| v.as_str()) == Some(job.id.as_str())) {
+        anyhow::bail!("Cron job '{}' already exists", job.id);
+    }
+    jobs.push(serde_json::to_value(job)?);
+    write_config_document(path, &doc)?;
+    Ok(next_run)
+}
+
+/// Remove the cron job `id` from the config file at `path`. Returns `false`
+/// without touching the file if there is no such job.
+pub fn remove_cron_job(path: &Path, id: &str) -> anyhow::Result<bool> {
+    let mut doc = read_config_document(path)?;
+    let jobs = cron_jobs_mut(&mut doc)?;
+    let len_before = jobs.len();
+    jobs.retain(|j| j.get("id").and_then(|v| v.as_str()) != Some(id));
+    if jobs.len() == len_before {
+        return Ok(false);
+    }
+    write_config_document(path, &doc)?;
+    Ok(true)
+}
+
+/// Read a config file as a JSON document, without env substitution.
+fn read_config_document(path: &Path) -> anyhow::Result<serde_json::Value> {
+    if !path.exists() {
+        return Ok(serde_json::json!({}));
+    }
+    let raw = std::fs::read_to_string(path)?;
+    Ok(json5::from_str(&raw)?)
+}
+
+fn write_config_document(path: &Path, doc: &serde_json::Value) -> anyhow::Result<()> {
+    // The edited document must still be a loadable config
+    serde_json::from_value::<Config>(doc.clone())?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(doc)?)?;
+    Ok(())
+}
+
+/// The `cron.jobs` array of a config document, created if missing.
+fn cron_jobs_mut(doc: &mut serde_json::Value) -> anyhow::Result<&mut Vec<serde_json::Value>> {
+    let mut node = doc;
+    for key in ["cron", "jobs"] {
+        let object = node
+            .as_object_mut()
+            .ok_or_else(|| anyhow::anyhow!("Config key '{key}' is not inside an object"))?;
+        node = object.entry(key).or_insert(serde_json::Value::Null);
+        if node.is_null() {
+            *node = if key == "jobs" { serde_json::json!([]) } else { serde_json::json!({}) };
+        }
+    }
+    node.as_array_mut()
+        .ok_or_else(|| anyhow::anyhow!("Config key 'cron.jobs' is not an array"))
 }
 
 /// Base directory for Rusty Claw data: `~/.rusty_claw/`
@@ -1005,6 +2156,190 @@ pub fn data_dir() -> PathBuf {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_with_path_rejects_invalid_config() {
+        let config = Config::default();
+
+        // Type errors are caught by the round trip
+        let err = config.with_path("gateway.port", serde_json::json!("abc")).unwrap_err();
+        assert!(err.to_string().contains("deserialization"));
+
+        // Values that parse but fail validation are rejected too
+        let err = config.with_path("gateway.port", serde_json::json!(0)).unwrap_err();
+        assert!(err.to_string().contains("port cannot be 0"));
+
+        // Paths through non-objects are errors, not panics
+        assert!(config.with_path("gateway.port.x", serde_json::json!(1)).is_err());
+
+        let (updated, _) = config.with_path("gateway.port", serde_json::json!(19000)).unwrap();
+        assert_eq!(updated.gateway_port(), 19000);
+        assert_eq!(config.gateway_port(), Config::default().gateway_port());
+    }
+
+    #[test]
+    fn test_cron_schedule_validation() {
+        assert!(parse_cron_schedule("0 9 * * *").is_ok());
+        assert!(parse_cron_schedule("30 0 9 * * *").is_ok());
+        assert!(parse_cron_schedule("every day").is_err());
+
+        let after = chrono::DateTime::parse_from_rfc3339("2026-01-01T10:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let next = next_cron_run("0 9 * * *", after).unwrap();
+        assert_eq!(next.to_rfc3339(), "2026-01-02T09:00:00+00:00");
+
+        let config = Config {
+            cron: Some(CronConfig {
+                jobs: Some(vec![CronJob {
+                    id: "bad".into(),
+                    schedule: "61 * * * *".into(),
+                    task: "x".into(),
+                    session_key: None,
+                    enabled: true,
+                }]),
+            }),
+            ..Default::default()
+        };
+        let (_, errors) = config.validate();
+        assert!(errors.iter().any(|e| e.contains("cron.jobs['bad']")));
+    }
+
+    #[test]
+    fn test_add_and_remove_cron_job_in_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(
+            &path,
+            r#"{
+                // comments are allowed in the JSON5 source
+                gateway: { port: 19000 },
+                models: { providers: [{ id: "anthropic", api_key: "${ANTHROPIC_API_KEY}" }] },
+            }"#,
+        )
+        .unwrap();
+        let job = |id: &str, schedule: &str| CronJob {
+            id: id.into(),
+            schedule: schedule.into(),
+            task: "Summarize the news".into(),
+            session_key: None,
+            enabled: true,
+        };
+
+        add_cron_job(&path, &job("daily", "0 9 * * *")).unwrap();
+        let config = Config::load(&path).unwrap();
+        let jobs = config.cron.as_ref().and_then(|c| c.jobs.as_ref()).unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].id, "daily");
+        assert_eq!(config.gateway_port(), 19000);
+
+        let err = add_cron_job(&path, &job("daily", "0 10 * * *")).unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+        assert!(add_cron_job(&path, &job("bad", "61 * * * *")).is_err());
+
+        assert!(remove_cron_job(&path, "daily").unwrap());
+        assert!(!remove_cron_job(&path, "daily").unwrap());
+
+        // Other keys survive the edits, env references unsubstituted
+        let doc: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(doc["gateway"]["port"], 19000);
+        assert_eq!(doc["models"]["providers"][0]["api_key"], "${ANTHROPIC_API_KEY}");
+        assert_eq!(doc["cron"]["jobs"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_add_cron_job_creates_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("config.json");
+        let job = CronJob {
+            id: "hourly".into(),
+            schedule: "0 * * * *".into(),
+            task: "Check the inbox".into(),
+            session_key: Some("cron:hourly".into()),
+            enabled: true,
+        };
+        add_cron_job(&path, &job).unwrap();
+        let config = Config::load(&path).unwrap();
+        let jobs = config.cron.and_then(|c| c.jobs).unwrap();
+        assert_eq!(jobs[0].session_key.as_deref(), Some("cron:hourly"));
+    }
+
+    #[test]
+    fn test_rate_limit_validation() {
+        let config: Config = json5::from_str(
+            r#"{ gateway: { port: 18789, rate_limit: { expensive_requests: { per_second: 0, burst: 5 } } } }"#,
+        )
+        .unwrap();
+        let (_, errors) = config.validate();
+        assert!(errors.iter().any(|e| e.contains("expensive_requests.per_second")));
+
+        let config: Config = json5::from_str(
+            r#"{ channels: { slack: { rate_limit: { messages_per_second: -1 } } } }"#,
+        )
+        .unwrap();
+        let (_, errors) = config.validate();
+        assert!(errors.iter().any(|e| e.contains("channels.slack.rate_limit")));
+    }
+
+    #[test]
+    fn test_session_store_kind() {
+        let config: Config = json5::from_str(r#"{ session: { store: "jsonl" } }"#).unwrap();
+        assert!(config.validate().1.is_empty());
+
+        let config: Config = json5::from_str(r#"{ session: { store: "sqlite" } }"#).unwrap();
+        assert!(config.validate().1.is_empty());
+        assert_eq!(config.session.unwrap().store, SessionStoreKind::Sqlite);
+    }
+
+    #[test]
+    fn test_api_key_scopes() {
+        let key = ApiKeyConfig {
+            name: "reader".into(),
+            key_hash: String::new(),
+            scopes: vec!["sessions.*".into(), "config.get".into()],
+        };
+        assert!(key.allows("sessions.list"));
+        assert!(key.allows("config.get"));
+        assert!(!key.allows("config.set"));
+        assert!(!key.allows("sessionsx.list"));
+        assert!(!key.allows("sessions"));
+
+        let admin = ApiKeyConfig { scopes: vec!["*".into()], ..key };
+        assert!(admin.allows("config.set"));
+    }
+
+    #[test]
+    fn test_set_path_array_index() {
+        let mut config = Config::default();
+        config.set_path("tools.allow", serde_json::json!(["read"])).unwrap();
+        config.set_path("tools.allow.0", serde_json::json!("exec")).unwrap();
+        config.set_path("tools.allow.1", serde_json::json!("write")).unwrap();
+        assert_eq!(config.get_path("tools.allow"), Some(serde_json::json!(["exec", "write"])));
+        assert_eq!(config.get_path("tools.allow.1"), Some(serde_json::json!("write")));
+        assert!(config.set_path("tools.allow.5", serde_json::json!("x")).is_err());
+        assert!(config.set_path("tools.allow.name", serde_json::json!("x")).is_err());
+    }
+
+    #[test]
+    fn test_sender_access() {
+        let channels: ChannelsConfig = serde_json::from_value(serde_json::json!({
+            "signal": {"phone_number": "+15550000000", "allowed_senders": ["+15551234567"]},
+            "matrix": {"pairing": true},
+            "telegram": {}
+        }))
+        .unwrap();
+
+        let signal = channels.sender_access("signal").unwrap();
+        assert!(signal.is_restricted());
+        assert!(signal.is_listed("+15551234567"));
+        assert!(!signal.is_listed("+15559999999"));
+
+        let matrix = channels.sender_access("matrix").unwrap();
+        assert!(matrix.is_restricted() && matrix.pairing);
+
+        assert!(!channels.sender_access("telegram").unwrap().is_restricted());
+        assert!(channels.sender_access("webchat").is_none());
+    }
+
     #[test]
     fn test_env_var_substitution() {
         // SAFETY: test-only, single-threaded test runner
@@ -1041,6 +2376,7 @@ mod tests {
             api_key: None,
             base_url: None,
             default_model: None,
+            ..Default::default()
         };
         assert_eq!(provider.resolve_api_key(), Some("from-env".into()));
 
@@ -1050,6 +2386,7 @@ mod tests {
             api_key: Some("direct-key".into()),
             base_url: None,
             default_model: None,
+            ..Default::default()
         };
         // Direct key takes priority
         assert_eq!(provider2.resolve_api_key(), Some("direct-key".into()));
@@ -1064,6 +2401,9 @@ mod tests {
             bot_token: None,
             bot_token_env: Some("TEST_RC_TG_TOKEN".into()),
             allowed_users: vec![],
+            rate_limit: None,
+            stream_replies: false,
+            access: Default::default(),
         };
         assert_eq!(tg.resolve_bot_token(), Some("bot-token-123".into()));
         unsafe { std::env::remove_var("TEST_RC_TG_TOKEN") };
@@ -1128,6 +2468,7 @@ mod tests {
                     api_key_env: None,
                     base_url: None,
                     default_model: None,
+                    ..Default::default()
                 }]),
             }),
             ..Config::default()
@@ -1152,6 +2493,7 @@ mod tests {
                 }),
                 rate_limit: None,
                 tailscale: None,
+                shutdown_grace_secs: None,
             }),
             ..Config::default()
         };
@@ -1165,4 +2507,25 @@ mod tests {
             "Expected an error about cert file, got: {errors:?}"
         );
     }
+
+    #[test]
+    fn test_vad_config_defaults_and_clamping() {
+        let config: Config =
+            json5::from_str(r#"{ tools: { vad: { energy_threshold: 500 } } }"#).unwrap();
+        let vad = config.tools.unwrap().vad.unwrap();
+        assert_eq!(vad.energy_threshold, 500.0);
+        assert_eq!(vad.silence_hangover_ms, 300);
+
+        let clamped = VadConfig {
+            energy_threshold: f64::NAN,
+            min_speech_ms: 0,
+            silence_hangover_ms: 60_000,
+            max_utterance_ms: 10,
+        }
+        .clamped();
+        assert_eq!(clamped.energy_threshold, 300.0);
+        assert_eq!(clamped.min_speech_ms, 20);
+        assert_eq!(clamped.silence_hangover_ms, 5_000);
+        assert_eq!(clamped.max_utterance_ms, 1_000);
+    }
 }
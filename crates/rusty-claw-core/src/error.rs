@@ -32,6 +32,9 @@ pub enum RustyClawError {
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 
+    #[error("Database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
@@ -5,6 +5,8 @@ pub mod error;
 pub mod pairing;
 pub mod protocol;
 pub mod session;
+pub mod session_export;
+pub mod session_sqlite;
 pub mod session_store;
 pub mod skills;
 pub mod types;
@@ -2,14 +2,27 @@
 //!
 //! When an unknown sender messages from a channel, a pairing request is created
 //! with a short code. The owner approves/rejects via CLI.
+//!
+//! Codes expire after a TTL, and whoever submits codes is locked out for a
+//! while after too many wrong ones, so pending codes can't be brute-forced.
+//! Lockouts live in memory: they are per process and end on restart, while
+//! the requests themselves are persisted.
 
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::config::data_dir;
+use crate::config::{PairingConfig, data_dir};
+
+/// Characters pairing codes are drawn from: no 0/O or 1/I to misread.
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// Length of a pairing code; 8 characters of 32 give 40 bits.
+const CODE_LEN: usize = 8;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -29,14 +42,46 @@ pub struct PairingRequest {
     pub created_at: DateTime<Utc>,
 }
 
+/// Result of approving or rejecting by code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairingOutcome {
+    /// The pending request was approved or rejected.
+    Resolved,
+    /// The code matched a request that has expired.
+    Expired,
+    /// No pending request has that code.
+    Unknown,
+    /// Too many wrong codes from this approver; try again later.
+    LockedOut,
+}
+
+/// Wrong codes submitted by one approver.
+#[derive(Debug, Default)]
+struct Attempts {
+    failures: u32,
+    locked_until: Option<Instant>,
+}
+
 /// Persistent store for pairing requests.
 pub struct PairingStore {
     path: PathBuf,
+    limits: PairingConfig,
+    attempts: Mutex<HashMap<String, Attempts>>,
 }
 
 impl PairingStore {
     pub fn new(path: PathBuf) -> Self {
-        Self { path }
+        Self {
+            path,
+            limits: PairingConfig::default(),
+            attempts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Set the code TTL and attempt limits.
+    pub fn with_limits(mut self, limits: PairingConfig) -> Self {
+        self.limits = limits;
+        self
     }
 
     pub fn default_path() -> PathBuf {
@@ -67,12 +112,26 @@ impl PairingStore {
         format!("{channel}:{sender_id}")
     }
 
-    /// Generate a short random code.
+    /// Generate a random code. The thread RNG is a CSPRNG seeded by the OS.
     fn generate_code() -> String {
         use rand::Rng;
         let mut rng = rand::rng();
-        let code: u32 = rng.random_range(100_000..999_999);
-        code.to_string()
+        (0..CODE_LEN)
+            .map(|_| CODE_ALPHABET[rng.random_range(0..CODE_ALPHABET.len())] as char)
+            .collect()
+    }
+
+    /// Codes are case-insensitive and may be typed with spaces or dashes.
+    fn normalize_code(code: &str) -> String {
+        code.chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .map(|c| c.to_ascii_uppercase())
+            .collect()
+    }
+
+    fn is_expired(&self, request: &PairingRequest) -> bool {
+        let age = Utc::now().signed_duration_since(request.created_at);
+        age.num_seconds() >= self.limits.ttl_secs as i64
     }
 
     /// Check if a sender is approved.
@@ -94,10 +153,11 @@ impl PairingStore {
         let key = Self::pairing_key(channel, sender_id);
 
         // If already exists and pending, return existing code
-        if let Some(existing) = data.get(&key) {
-            if existing.status == PairingStatus::Pending {
-                return Ok(existing.code.clone());
-            }
+        if let Some(existing) = data.get(&key)
+            && existing.status == PairingStatus::Pending
+            && !self.is_expired(existing)
+        {
+            return Ok(existing.code.clone());
         }
 
         let code = Self::generate_code();
@@ -114,35 +174,86 @@ impl PairingStore {
         Ok(code)
     }
 
-    /// Approve a pairing request by channel + code.
-    pub fn approve(&self, channel: &str, code: &str) -> anyhow::Result<bool> {
+    /// Approve a pairing request by channel + code. `approver` identifies
+    /// who submitted the code, for the wrong-code lockout.
+    pub fn approve(
+        &self,
+        channel: &str,
+        code: &str,
+        approver: &str,
+    ) -> anyhow::Result<PairingOutcome> {
+        self.resolve(channel, code, approver, PairingStatus::Approved)
+    }
+
+    /// Reject a pairing request by channel + code.
+    pub fn reject(
+        &self,
+        channel: &str,
+        code: &str,
+        approver: &str,
+    ) -> anyhow::Result<PairingOutcome> {
+        self.resolve(channel, code, approver, PairingStatus::Rejected)
+    }
+
+    fn resolve(
+        &self,
+        channel: &str,
+        code: &str,
+        approver: &str,
+        status: PairingStatus,
+    ) -> anyhow::Result<PairingOutcome> {
+        if self.is_locked_out(approver) {
+            return Ok(PairingOutcome::LockedOut);
+        }
+
+        let code = Self::normalize_code(code);
         let mut data = self.load_all();
-        let found = data.values_mut().find(|r| {
-            r.channel == channel && r.code == code && r.status == PairingStatus::Pending
+        let found = data.iter().find_map(|(key, r)| {
+            (r.channel == channel && r.code == code && r.status == PairingStatus::Pending)
+                .then(|| key.clone())
         });
+        let Some(key) = found else {
+            self.record_failure(approver);
+            return Ok(PairingOutcome::Unknown);
+        };
 
-        if let Some(req) = found {
-            req.status = PairingStatus::Approved;
+        if self.is_expired(&data[&key]) {
+            data.remove(&key);
             self.save_all(&data)?;
-            Ok(true)
-        } else {
-            Ok(false)
+            return Ok(PairingOutcome::Expired);
+        }
+        if let Some(req) = data.get_mut(&key) {
+            req.status = status;
         }
+        self.save_all(&data)?;
+        self.attempts.lock().unwrap().remove(approver);
+        Ok(PairingOutcome::Resolved)
     }
 
-    /// Reject a pairing request by channel + code.
-    pub fn reject(&self, channel: &str, code: &str) -> anyhow::Result<bool> {
-        let mut data = self.load_all();
-        let found = data.values_mut().find(|r| {
-            r.channel == channel && r.code == code && r.status == PairingStatus::Pending
-        });
+    fn is_locked_out(&self, approver: &str) -> bool {
+        let mut attempts = self.attempts.lock().unwrap();
+        let Some(entry) = attempts.get_mut(approver) else {
+            return false;
+        };
+        match entry.locked_until {
+            Some(until) if Instant::now() < until => true,
+            Some(_) => {
+                // Lockout served; start counting afresh
+                *entry = Attempts::default();
+                false
+            }
+            None => false,
+        }
+    }
 
-        if let Some(req) = found {
-            req.status = PairingStatus::Rejected;
-            self.save_all(&data)?;
-            Ok(true)
-        } else {
-            Ok(false)
+    fn record_failure(&self, approver: &str) {
+        let mut attempts = self.attempts.lock().unwrap();
+        let entry = attempts.entry(approver.to_string()).or_default();
+        entry.failures += 1;
+        if entry.failures >= self.limits.max_attempts {
+            tracing::warn!(approver, "Too many wrong pairing codes, locking out");
+            entry.locked_until =
+                Some(Instant::now() + Duration::from_secs(self.limits.lockout_secs));
         }
     }
 
@@ -151,10 +262,17 @@ impl PairingStore {
         self.load_all().into_values().collect()
     }
 
-    /// List only pending pairing requests.
+    /// List only pending pairing requests, pruning expired ones.
     pub fn list_pending(&self) -> Vec<PairingRequest> {
-        self.load_all()
-            .into_values()
+        let mut data = self.load_all();
+        let before = data.len();
+        data.retain(|_, r| r.status != PairingStatus::Pending || !self.is_expired(r));
+        if data.len() < before
+            && let Err(e) = self.save_all(&data)
+        {
+            tracing::warn!(%e, "Failed to prune expired pairing requests");
+        }
+        data.into_values()
             .filter(|r| r.status == PairingStatus::Pending)
             .collect()
     }
@@ -173,7 +291,8 @@ mod tests {
         let code = store
             .create_request("telegram", "user123", Some("Alice".into()))
             .unwrap();
-        assert_eq!(code.len(), 6);
+        assert_eq!(code.len(), 8);
+        assert!(code.bytes().all(|b| CODE_ALPHABET.contains(&b)));
 
         // Not yet approved
         assert!(!store.is_approved("telegram", "user123"));
@@ -184,7 +303,10 @@ mod tests {
         assert_eq!(pending[0].sender_id, "user123");
 
         // Approve
-        assert!(store.approve("telegram", &code).unwrap());
+        assert_eq!(
+            store.approve("telegram", &code, "owner").unwrap(),
+            PairingOutcome::Resolved
+        );
         assert!(store.is_approved("telegram", "user123"));
 
         // No more pending
@@ -199,7 +321,13 @@ mod tests {
         let code = store
             .create_request("discord", "user456", None)
             .unwrap();
-        assert!(store.reject("discord", &code).unwrap());
+        // Codes are case-insensitive
+        assert_eq!(
+            store
+                .reject("discord", &code.to_lowercase(), "owner")
+                .unwrap(),
+            PairingOutcome::Resolved
+        );
         assert!(!store.is_approved("discord", "user456"));
     }
 
@@ -225,6 +353,82 @@ mod tests {
         store
             .create_request("telegram", "user000", None)
             .unwrap();
-        assert!(!store.approve("telegram", "000000").unwrap());
+        assert_eq!(
+            store.approve("telegram", "AAAAAAAA", "owner").unwrap(),
+            PairingOutcome::Unknown
+        );
+    }
+
+    #[test]
+    fn test_expired_code() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PairingStore::new(dir.path().join("pairing.json"));
+
+        let code = store.create_request("telegram", "late", None).unwrap();
+        // Backdate the request past the TTL
+        let mut data = store.load_all();
+        for r in data.values_mut() {
+            r.created_at -= chrono::Duration::hours(2);
+        }
+        store.save_all(&data).unwrap();
+
+        assert_eq!(
+            store.approve("telegram", &code, "owner").unwrap(),
+            PairingOutcome::Expired
+        );
+        assert!(!store.is_approved("telegram", "late"));
+        // Gone now, so the same code is just unknown
+        assert_eq!(
+            store.approve("telegram", &code, "owner").unwrap(),
+            PairingOutcome::Unknown
+        );
+    }
+
+    #[test]
+    fn test_list_pending_prunes_expired() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PairingStore::new(dir.path().join("pairing.json"));
+
+        store.create_request("telegram", "old", None).unwrap();
+        let mut data = store.load_all();
+        for r in data.values_mut() {
+            r.created_at -= chrono::Duration::hours(2);
+        }
+        store.save_all(&data).unwrap();
+        store.create_request("telegram", "new", None).unwrap();
+
+        let pending = store.list_pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].sender_id, "new");
+        assert_eq!(store.list().len(), 1);
+    }
+
+    #[test]
+    fn test_lockout_after_wrong_codes() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PairingStore::new(dir.path().join("pairing.json")).with_limits(PairingConfig {
+            max_attempts: 3,
+            ..Default::default()
+        });
+
+        let code = store.create_request("telegram", "target", None).unwrap();
+        for _ in 0..3 {
+            assert_eq!(
+                store.approve("telegram", "WRONGCOD", "guesser").unwrap(),
+                PairingOutcome::Unknown
+            );
+        }
+        // Even the right code is refused while locked out
+        assert_eq!(
+            store.approve("telegram", &code, "guesser").unwrap(),
+            PairingOutcome::LockedOut
+        );
+        assert!(!store.is_approved("telegram", "target"));
+
+        // Other approvers are unaffected
+        assert_eq!(
+            store.approve("telegram", &code, "owner").unwrap(),
+            PairingOutcome::Resolved
+        );
     }
 }
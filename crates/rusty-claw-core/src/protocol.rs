@@ -75,6 +75,10 @@ pub struct ConnectParams {
     pub auth: Option<AuthParams>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub device: Option<DeviceParams>,
+    /// Last event `seq` seen on a previous connection; events broadcast
+    /// since then are replayed before live events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume_from_seq: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -129,6 +133,9 @@ pub struct Features {
 pub struct Snapshot {
     pub state_version: StateVersion,
     pub auth_mode: String,
+    /// `seq` of the last event broadcast before this connection.
+    #[serde(default)]
+    pub seq: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
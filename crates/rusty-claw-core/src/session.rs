@@ -31,6 +31,9 @@ pub struct SessionMeta {
     pub key: SessionKey,
     pub label: Option<String>,
     pub model: Option<String>,
+    /// Provider to run this session on; resolved from `model` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_id: Option<String>,
     #[serde(default)]
     pub thinking_level: ThinkingLevel,
     pub last_channel: Option<String>,
@@ -45,6 +48,18 @@ pub struct SessionMeta {
     /// Custom system prompt override for this session.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub custom_system_prompt: Option<String>,
+    /// System prompt built for this session's last run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt_cache: Option<CachedSystemPrompt>,
+}
+
+/// A built system prompt, split around its current-time line, with a hash of
+/// the inputs it was built from so it is only reused while they are unchanged.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedSystemPrompt {
+    pub inputs_hash: u64,
+    pub identity: String,
+    pub rest: String,
 }
 
 /// A single entry in the JSONL transcript file.
@@ -61,6 +76,17 @@ pub enum TranscriptEntry {
         content: Vec<ContentBlock>,
         #[serde(skip_serializing_if = "Option::is_none")]
         usage: Option<Usage>,
+        /// Reasoning produced before this turn's content (extended thinking).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        thinking: Option<String>,
+        /// Provider signature over `thinking`, required to send it back.
+        /// Only set on turns saved before `thinking_blocks` was kept.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        thinking_signature: Option<String>,
+        /// Reasoning blocks in the order they were produced, each with its
+        /// own signature; `thinking` is their combined text.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        thinking_blocks: Vec<ThinkingBlock>,
         timestamp: DateTime<Utc>,
     },
     #[serde(rename = "tool_call")]
@@ -119,6 +145,7 @@ impl Session {
             key,
             label: None,
             model: None,
+            provider_id: None,
             thinking_level: ThinkingLevel::default(),
             last_channel: None,
             last_updated_at: Utc::now(),
@@ -127,6 +154,7 @@ impl Session {
             spawn_depth: 0,
             active_skill: None,
             custom_system_prompt: None,
+            system_prompt_cache: None,
         };
         Self {
             meta,
@@ -139,6 +167,179 @@ impl Session {
         self.meta.last_updated_at = Utc::now();
         self.transcript.push(entry);
     }
+
+    /// Answer tool uses left unanswered at the end of the transcript, as
+    /// happens when the process dies while tools are running. Each gets an
+    /// error tool result so the next turn is accepted by the provider.
+    /// Returns how many results were synthesized.
+    pub fn repair_interrupted_tool_calls(&mut self) -> usize {
+        // Only the last assistant turn can be mid-run
+        let Some(last) = self
+            .transcript
+            .iter()
+            .rposition(|e| matches!(e, TranscriptEntry::Assistant { .. }))
+        else {
+            return 0;
+        };
+        let TranscriptEntry::Assistant { content, .. } = &self.transcript[last] else {
+            return 0;
+        };
+        let answered: Vec<&str> = self.transcript[last + 1..]
+            .iter()
+            .filter_map(|e| match e {
+                TranscriptEntry::ToolResult { tool_use_id, .. } => Some(tool_use_id.as_str()),
+                _ => None,
+            })
+            .collect();
+        let missing: Vec<(String, String)> = content
+            .iter()
+            .filter_map(|b| match b {
+                ContentBlock::ToolUse { id, name, .. } if !answered.contains(&id.as_str()) => {
+                    Some((id.clone(), name.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+        for (tool_use_id, tool) in &missing {
+            self.transcript.push(TranscriptEntry::ToolResult {
+                tool_use_id: tool_use_id.clone(),
+                tool: tool.clone(),
+                content: "Tool call interrupted: the gateway stopped before it completed".into(),
+                is_error: true,
+                timestamp: Utc::now(),
+            });
+        }
+        missing.len()
+    }
+}
+
+/// Characters of context kept on each side of a search match.
+const SNIPPET_CONTEXT_CHARS: usize = 60;
+
+/// Snippets returned per session by a search.
+pub const MAX_SNIPPETS_PER_SESSION: usize = 5;
+
+/// Build the pattern for a transcript search: the query as a literal,
+/// case-insensitive string, or as a regex when `is_regex` is set.
+pub fn search_pattern(query: &str, is_regex: bool) -> crate::error::Result<regex::Regex> {
+    let pattern = if is_regex { query.to_string() } else { regex::escape(query) };
+    regex::RegexBuilder::new(&pattern)
+        .case_insensitive(true)
+        .size_limit(1 << 20)
+        .build()
+        .map_err(|e| crate::error::RustyClawError::Session(format!("invalid search pattern: {e}")))
+}
+
+/// A transcript entry matching a search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchSnippet {
+    /// Position of the entry in the transcript.
+    pub entry: usize,
+    pub role: String,
+    pub timestamp: DateTime<Utc>,
+    /// The match with surrounding context.
+    pub snippet: String,
+}
+
+/// A session with transcript entries matching a search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSearchResult {
+    pub key: SessionKey,
+    pub label: Option<String>,
+    pub snippets: Vec<SearchSnippet>,
+}
+
+/// One reasoning block of an assistant turn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThinkingBlock {
+    pub thinking: String,
+    /// Provider signature (Anthropic) or encrypted reasoning (OpenAI),
+    /// required to send the block back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl TranscriptEntry {
+    /// An assistant turn's reasoning blocks, in order. Turns saved before
+    /// blocks were kept yield their single thinking/signature pair.
+    pub fn thinking_blocks(&self) -> Vec<ThinkingBlock> {
+        match self {
+            Self::Assistant { thinking_blocks, .. } if !thinking_blocks.is_empty() => {
+                thinking_blocks.clone()
+            }
+            Self::Assistant {
+                thinking,
+                thinking_signature,
+                ..
+            } if thinking.is_some() || thinking_signature.is_some() => vec![ThinkingBlock {
+                thinking: thinking.clone().unwrap_or_default(),
+                signature: thinking_signature.clone(),
+            }],
+            _ => Vec::new(),
+        }
+    }
+
+    /// The entry's searchable text and its role label.
+    fn search_text(&self) -> Option<(&'static str, String, DateTime<Utc>)> {
+        let text_of = |content: &[ContentBlock]| {
+            content
+                .iter()
+                .filter_map(|b| match b {
+                    ContentBlock::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        match self {
+            Self::User { content, timestamp } => Some(("user", text_of(content), *timestamp)),
+            Self::Assistant { content, timestamp, .. } => {
+                Some(("assistant", text_of(content), *timestamp))
+            }
+            Self::ToolCall { tool, params, timestamp } => {
+                Some(("tool_call", format!("{tool} {params}"), *timestamp))
+            }
+            Self::ToolResult { content, timestamp, .. } => {
+                Some(("tool_result", content.clone(), *timestamp))
+            }
+            Self::System { .. } => None,
+        }
+    }
+
+    /// A snippet around the first match of `pattern` in this entry.
+    pub fn search(&self, index: usize, pattern: &regex::Regex) -> Option<SearchSnippet> {
+        let (role, text, timestamp) = self.search_text()?;
+        let found = pattern.find(&text)?;
+        Some(SearchSnippet {
+            entry: index,
+            role: role.into(),
+            timestamp,
+            snippet: snippet_around(&text, found.start(), found.end()),
+        })
+    }
+}
+
+/// `text[start..end]` with up to [`SNIPPET_CONTEXT_CHARS`] of context on
+/// each side, marked with ellipses where cut.
+fn snippet_around(text: &str, start: usize, end: usize) -> String {
+    let before: Vec<(usize, char)> = text[..start].char_indices().collect();
+    let from = before
+        .len()
+        .checked_sub(SNIPPET_CONTEXT_CHARS)
+        .map_or(0, |i| before[i].0);
+    let to = text[end..]
+        .char_indices()
+        .nth(SNIPPET_CONTEXT_CHARS)
+        .map_or(text.len(), |(i, _)| end + i);
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push('…');
+    }
+    snippet.push_str(text[from..to].trim());
+    if to < text.len() {
+        snippet.push('…');
+    }
+    snippet.replace('\n', " ")
 }
 
 /// Async session persistence trait.
@@ -165,4 +366,58 @@ pub trait SessionStore: Send + Sync {
 
     /// Reset a session's transcript (keeps metadata, clears transcript).
     async fn reset(&self, key: &SessionKey) -> crate::error::Result<()>;
+
+    /// Check that the store can currently be written to, for health checks.
+    async fn check_writable(&self) -> crate::error::Result<()> {
+        Ok(())
+    }
+
+    /// Find sessions whose transcripts match `pattern`, most recently
+    /// updated first, stopping after `limit` sessions. Sessions are loaded
+    /// one at a time; stores should override this to scan more cheaply.
+    async fn search(
+        &self,
+        pattern: &regex::Regex,
+        limit: usize,
+    ) -> crate::error::Result<Vec<SessionSearchResult>> {
+        let mut metas = self.list().await?;
+        metas.sort_by_key(|m| std::cmp::Reverse(m.last_updated_at));
+        let mut results = Vec::new();
+        for meta in metas {
+            if results.len() >= limit {
+                break;
+            }
+            let Some(session) = self.load(&meta.key).await? else {
+                continue;
+            };
+            let snippets: Vec<SearchSnippet> = session
+                .transcript
+                .iter()
+                .enumerate()
+                .filter_map(|(i, e)| e.search(i, pattern))
+                .take(MAX_SNIPPETS_PER_SESSION)
+                .collect();
+            if !snippets.is_empty() {
+                results.push(SessionSearchResult {
+                    key: meta.key,
+                    label: meta.label,
+                    snippets,
+                });
+            }
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippet_around() {
+        let text = format!("{}needle{}", "a".repeat(100), "b".repeat(100));
+        let snippet = snippet_around(&text, 100, 106);
+        assert_eq!(snippet, format!("…{}needle{}…", "a".repeat(60), "b".repeat(60)));
+        assert_eq!(snippet_around("short needle", 6, 12), "short needle");
+    }
 }
@@ -0,0 +1,294 @@
+//! Portable session export — a self-contained JSON document with metadata
+//! and the full transcript, plus a readable markdown rendering for sharing.
+//!
+//! Images stay inline as base64 by default so one document carries the whole
+//! conversation. They can instead be written out as files next to the export
+//! and referenced by name (`"type": "file"`); importing reads them back in.
+
+use std::path::Path;
+
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::session::{Session, SessionKey, SessionMeta, TranscriptEntry};
+use crate::types::ContentBlock;
+
+/// Identifies an export document.
+pub const EXPORT_FORMAT: &str = "rusty-claw-session";
+
+/// Current export document version.
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Tool results longer than this are truncated in markdown.
+const MARKDOWN_RESULT_CHARS: usize = 2000;
+
+/// A session serialized for backup or migration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionExport {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub meta: SessionMeta,
+    pub transcript: Vec<TranscriptEntry>,
+}
+
+impl SessionExport {
+    /// Export a session with images inline.
+    pub fn from_session(session: &Session) -> Self {
+        Self {
+            format: EXPORT_FORMAT.into(),
+            version: EXPORT_VERSION,
+            exported_at: Utc::now(),
+            meta: session.meta.clone(),
+            transcript: session.transcript.clone(),
+        }
+    }
+
+    /// Write inline base64 images to files in `dir` and reference them by
+    /// file name instead. Returns how many images were written.
+    pub fn externalize_media(&mut self, dir: &Path) -> anyhow::Result<usize> {
+        std::fs::create_dir_all(dir)?;
+        let mut written = 0;
+        for (entry_index, content) in self.transcript.iter_mut().filter_map(entry_content).enumerate() {
+            for (block_index, block) in content.iter_mut().enumerate() {
+                let ContentBlock::Image { source } = block else {
+                    continue;
+                };
+                if source.source_type != "base64" {
+                    continue;
+                }
+                let bytes = base64::engine::general_purpose::STANDARD.decode(&source.data)?;
+                let extension = source.media_type.rsplit('/').next().unwrap_or("bin");
+                let name = format!("image-{entry_index}-{block_index}.{extension}");
+                std::fs::write(dir.join(&name), bytes)?;
+                source.source_type = "file".into();
+                source.data = name;
+                written += 1;
+            }
+        }
+        Ok(written)
+    }
+
+    /// Read file-referenced images from `dir` back inline as base64.
+    pub fn inline_media(&mut self, dir: &Path) -> anyhow::Result<()> {
+        for content in self.transcript.iter_mut().filter_map(entry_content) {
+            for block in content.iter_mut() {
+                let ContentBlock::Image { source } = block else {
+                    continue;
+                };
+                if source.source_type != "file" {
+                    continue;
+                }
+                // References are bare file names; refuse anything that
+                // would resolve outside the media directory
+                let name = Path::new(&source.data);
+                if name.components().count() != 1 || name.file_name().is_none() {
+                    anyhow::bail!("Invalid media reference: {}", source.data);
+                }
+                let bytes = std::fs::read(dir.join(name))
+                    .map_err(|e| anyhow::anyhow!("Failed to read media {}: {e}", source.data))?;
+                source.source_type = "base64".into();
+                source.data = base64::engine::general_purpose::STANDARD.encode(bytes);
+            }
+        }
+        Ok(())
+    }
+
+    /// Recreate the session, under `key` if given, otherwise under the
+    /// original key.
+    pub fn into_session(self, key: Option<SessionKey>) -> anyhow::Result<Session> {
+        if self.format != EXPORT_FORMAT {
+            anyhow::bail!("Not a session export (format '{}')", self.format);
+        }
+        if self.version > EXPORT_VERSION {
+            anyhow::bail!("Unsupported session export version {}", self.version);
+        }
+        let unresolved = self
+            .transcript
+            .iter()
+            .filter_map(|e| match e {
+                TranscriptEntry::User { content, .. } | TranscriptEntry::Assistant { content, .. } => {
+                    Some(content)
+                }
+                _ => None,
+            })
+            .flatten()
+            .any(|b| matches!(b, ContentBlock::Image { source } if source.source_type == "file"));
+        if unresolved {
+            anyhow::bail!("Export references media files; import it with its media directory");
+        }
+
+        let mut meta = self.meta;
+        if let Some(key) = key {
+            meta.key = key;
+        }
+        meta.last_updated_at = Utc::now();
+        Ok(Session {
+            meta,
+            transcript: self.transcript,
+        })
+    }
+
+    /// Render the transcript as markdown for sharing.
+    pub fn to_markdown(&self) -> String {
+        let meta = &self.meta;
+        let title = meta.label.clone().unwrap_or_else(|| {
+            format!("{}:{}", meta.key.channel, meta.key.peer_id)
+        });
+        let mut out = format!("# Session: {title}\n\n");
+        out.push_str(&format!("- Channel: {}\n", meta.key.channel));
+        out.push_str(&format!("- Peer: {}\n", meta.key.peer_id));
+        if let Some(model) = &meta.model {
+            out.push_str(&format!("- Model: {model}\n"));
+        }
+        out.push_str(&format!("- Exported: {}\n", self.exported_at.to_rfc3339()));
+
+        for entry in &self.transcript {
+            match entry {
+                TranscriptEntry::User { content, timestamp } => {
+                    out.push_str(&format!("\n## User — {}\n\n", timestamp.to_rfc3339()));
+                    render_blocks(&mut out, content);
+                }
+                TranscriptEntry::Assistant { content, timestamp, .. } => {
+                    out.push_str(&format!("\n## Assistant — {}\n\n", timestamp.to_rfc3339()));
+                    render_blocks(&mut out, content);
+                }
+                TranscriptEntry::ToolCall { tool, params, .. } => {
+                    let params = serde_json::to_string_pretty(params).unwrap_or_default();
+                    out.push_str(&format!("\n**Tool call:** `{tool}`\n\n```json\n{params}\n```\n"));
+                }
+                TranscriptEntry::ToolResult { tool, content, is_error, .. } => {
+                    let label = if *is_error { "Tool error" } else { "Tool result" };
+                    let mut text: String = content.chars().take(MARKDOWN_RESULT_CHARS).collect();
+                    if text.len() < content.len() {
+                        text.push_str("\n… (truncated)");
+                    }
+                    out.push_str(&format!("\n**{label}:** `{tool}`\n\n```\n{text}\n```\n"));
+                }
+                TranscriptEntry::System { event, .. } => {
+                    out.push_str(&format!("\n_System: {event}_\n"));
+                }
+            }
+        }
+        out
+    }
+}
+
+/// The content blocks of a user or assistant entry.
+fn entry_content(entry: &mut TranscriptEntry) -> Option<&mut Vec<ContentBlock>> {
+    match entry {
+        TranscriptEntry::User { content, .. } | TranscriptEntry::Assistant { content, .. } => {
+            Some(content)
+        }
+        _ => None,
+    }
+}
+
+fn render_blocks(out: &mut String, content: &[ContentBlock]) {
+    for block in content {
+        match block {
+            ContentBlock::Text { text } => {
+                out.push_str(text.trim_end());
+                out.push_str("\n\n");
+            }
+            ContentBlock::Image { source } => match source.source_type.as_str() {
+                "file" | "url" => out.push_str(&format!("![image]({})\n\n", source.data)),
+                _ => out.push_str(&format!("_[image: {}, inline]_\n\n", source.media_type)),
+            },
+            // Tool calls and results are rendered from their own entries
+            ContentBlock::ToolUse { .. } | ContentBlock::ToolResult { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionScope;
+    use crate::types::{ChatType, ImageSource};
+
+    fn test_session() -> Session {
+        let key = SessionKey {
+            channel: "telegram".into(),
+            account_id: "bot".into(),
+            chat_type: ChatType::Dm,
+            peer_id: "alice".into(),
+            scope: SessionScope::PerSender,
+        };
+        let mut session = Session::new(key);
+        session.append(TranscriptEntry::User {
+            content: vec![
+                ContentBlock::Text { text: "What is this?".into() },
+                ContentBlock::Image {
+                    source: ImageSource {
+                        source_type: "base64".into(),
+                        media_type: "image/png".into(),
+                        data: base64::engine::general_purpose::STANDARD.encode(b"png-bytes"),
+                    },
+                },
+            ],
+            timestamp: Utc::now(),
+        });
+        session.append(TranscriptEntry::ToolCall {
+            tool: "web_search".into(),
+            params: serde_json::json!({"query": "png"}),
+            timestamp: Utc::now(),
+        });
+        session.append(TranscriptEntry::Assistant {
+            content: vec![ContentBlock::Text { text: "A small image.".into() }],
+            usage: None,
+            thinking: None,
+            thinking_signature: None,
+            thinking_blocks: Vec::new(),
+            timestamp: Utc::now(),
+        });
+        session
+    }
+
+    #[test]
+    fn test_round_trip_with_external_media() {
+        let dir = tempfile::tempdir().unwrap();
+        let session = test_session();
+        let mut export = SessionExport::from_session(&session);
+        assert_eq!(export.externalize_media(dir.path()).unwrap(), 1);
+        assert!(dir.path().join("image-0-1.png").exists());
+
+        // Serialized and read back, as when moved between machines
+        let json = serde_json::to_string(&export).unwrap();
+        let mut imported: SessionExport = serde_json::from_str(&json).unwrap();
+        assert!(imported.clone().into_session(None).is_err());
+        imported.inline_media(dir.path()).unwrap();
+
+        let new_key = SessionKey {
+            peer_id: "bob".into(),
+            ..session.meta.key.clone()
+        };
+        let restored = imported.into_session(Some(new_key)).unwrap();
+        assert_eq!(restored.meta.key.peer_id, "bob");
+        assert_eq!(restored.transcript.len(), 3);
+        let TranscriptEntry::User { content, .. } = &restored.transcript[0] else {
+            panic!("expected user entry");
+        };
+        let ContentBlock::Image { source } = &content[1] else {
+            panic!("expected image block");
+        };
+        assert_eq!(source.source_type, "base64");
+        assert_eq!(
+            base64::engine::general_purpose::STANDARD.decode(&source.data).unwrap(),
+            b"png-bytes"
+        );
+    }
+
+    #[test]
+    fn test_markdown_rendering() {
+        let md = SessionExport::from_session(&test_session()).to_markdown();
+        assert!(md.starts_with("# Session: telegram:alice"));
+        assert!(md.contains("## User"));
+        assert!(md.contains("What is this?"));
+        assert!(md.contains("_[image: image/png, inline]_"));
+        assert!(md.contains("**Tool call:** `web_search`"));
+        assert!(md.contains("## Assistant"));
+        assert!(md.contains("A small image."));
+    }
+}
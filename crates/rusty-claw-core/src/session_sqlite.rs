@@ -0,0 +1,519 @@
+//! SQLite-backed session store — one database holding session metadata and
+//! transcripts, with indexed lookups by key.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension, Transaction, TransactionBehavior, params};
+use tracing::{debug, info, warn};
+
+use crate::error::{Result, RustyClawError};
+use crate::session::{
+    MAX_SNIPPETS_PER_SESSION, SearchSnippet, Session, SessionKey, SessionMeta, SessionSearchResult,
+    SessionStore, TranscriptEntry,
+};
+use crate::session_store::JsonlSessionStore;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS sessions (
+        key TEXT PRIMARY KEY NOT NULL,
+        meta TEXT NOT NULL,
+        last_updated_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS sessions_last_updated_at ON sessions (last_updated_at);
+    CREATE TABLE IF NOT EXISTS entries (
+        key TEXT NOT NULL,
+        seq INTEGER NOT NULL,
+        entry TEXT NOT NULL,
+        PRIMARY KEY (key, seq)
+    ) WITHOUT ROWID;
+    CREATE TABLE IF NOT EXISTS store_meta (
+        name TEXT PRIMARY KEY NOT NULL,
+        value TEXT NOT NULL
+    );
+";
+
+/// `store_meta` row recording when the JSONL sessions were imported.
+const JSONL_IMPORTED_AT: &str = "jsonl_imported_at";
+
+/// Session store backed by a single SQLite database.
+///
+/// Tables:
+/// - `sessions` — the key and `SessionMeta` as JSON, one row per session
+/// - `entries` — transcript entries as JSON, keyed by session and position
+/// - `store_meta` — bookkeeping, such as whether JSONL sessions were imported
+///
+/// Every write is one transaction, so an append and its metadata update land
+/// together. The database runs in WAL mode with a busy timeout, so a CLI and
+/// a running gateway can share it.
+pub struct SqliteSessionStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+/// Sessions are keyed by their `SessionKey` JSON, which unlike
+/// [`SessionKey::hash_key`] cannot collide.
+fn key_column(key: &SessionKey) -> Result<String> {
+    Ok(serde_json::to_string(key)?)
+}
+
+/// Rewrite a session's metadata, if it exists, inside `tx`.
+fn update_meta(tx: &Transaction<'_>, key: &str, update: impl FnOnce(&mut SessionMeta)) -> Result<()> {
+    let meta: Option<String> = tx
+        .query_row("SELECT meta FROM sessions WHERE key = ?1", [key], |row| row.get(0))
+        .optional()?;
+    if let Some(meta) = meta {
+        let mut meta: SessionMeta = serde_json::from_str(&meta)?;
+        update(&mut meta);
+        tx.execute(
+            "UPDATE sessions SET meta = ?2, last_updated_at = ?3 WHERE key = ?1",
+            params![key, serde_json::to_string(&meta)?, meta.last_updated_at.timestamp_micros()],
+        )?;
+    }
+    Ok(())
+}
+
+impl SqliteSessionStore {
+    /// Open the database at `path`, creating it and its tables if needed.
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Default database location: `~/.rusty_claw/sessions.db`
+    pub fn default_path() -> PathBuf {
+        crate::config::data_dir().join("sessions.db")
+    }
+
+    /// Run `f` on the connection on the blocking thread pool.
+    async fn with_conn<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn
+                .lock()
+                .map_err(|_| RustyClawError::Session("session database lock poisoned".into()))?;
+            f(&mut conn)
+        })
+        .await
+        .map_err(|e| RustyClawError::Session(format!("session database task failed: {e}")))?
+    }
+
+    async fn contains(&self, key: &SessionKey) -> Result<bool> {
+        let key = key_column(key)?;
+        self.with_conn(move |conn| {
+            let found = conn
+                .query_row("SELECT 1 FROM sessions WHERE key = ?1", [&key], |_| Ok(()))
+                .optional()?;
+            Ok(found.is_some())
+        })
+        .await
+    }
+
+    /// Copy the sessions of a JSONL store into this database, once: the
+    /// import is recorded, and later calls return 0 without reading `jsonl`.
+    /// Sessions already in the database are left alone. Returns how many
+    /// sessions were imported.
+    pub async fn import_jsonl(&self, jsonl: &JsonlSessionStore) -> Result<usize> {
+        let done = self
+            .with_conn(|conn| {
+                Ok(conn
+                    .query_row(
+                        "SELECT value FROM store_meta WHERE name = ?1",
+                        [JSONL_IMPORTED_AT],
+                        |row| row.get::<_, String>(0),
+                    )
+                    .optional()?)
+            })
+            .await?;
+        if done.is_some() {
+            return Ok(0);
+        }
+
+        let mut imported = 0;
+        for meta in jsonl.list().await? {
+            if self.contains(&meta.key).await? {
+                continue;
+            }
+            if let Some(session) = jsonl.load(&meta.key).await? {
+                self.save(&session).await?;
+                imported += 1;
+            }
+        }
+
+        let now = Utc::now().to_rfc3339();
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO store_meta (name, value) VALUES (?1, ?2)",
+                params![JSONL_IMPORTED_AT, now],
+            )?;
+            Ok(())
+        })
+        .await?;
+        info!(imported, "Imported JSONL sessions into the SQLite session store");
+        Ok(imported)
+    }
+}
+
+#[async_trait]
+impl SessionStore for SqliteSessionStore {
+    async fn check_writable(&self) -> Result<()> {
+        self.with_conn(|conn| {
+            // Taking the write lock fails on a read-only or locked database
+            conn.transaction_with_behavior(TransactionBehavior::Immediate)?
+                .rollback()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn load(&self, key: &SessionKey) -> Result<Option<Session>> {
+        let column = key_column(key)?;
+        let loaded = self
+            .with_conn(move |conn| {
+                let meta: Option<String> = conn
+                    .query_row("SELECT meta FROM sessions WHERE key = ?1", [&column], |row| {
+                        row.get(0)
+                    })
+                    .optional()?;
+                let Some(meta) = meta else {
+                    return Ok(None);
+                };
+                let meta: SessionMeta = serde_json::from_str(&meta)?;
+                let mut stmt = conn.prepare("SELECT entry FROM entries WHERE key = ?1 ORDER BY seq")?;
+                let mut rows = stmt.query([&column])?;
+                let mut transcript = Vec::new();
+                while let Some(row) = rows.next()? {
+                    transcript.push(serde_json::from_str(&row.get::<_, String>(0)?)?);
+                }
+                Ok(Some(Session { meta, transcript }))
+            })
+            .await?;
+
+        Ok(loaded.map(|mut session| {
+            debug!(
+                key = %key.hash_key(),
+                entries = session.transcript.len(),
+                "Loaded session transcript"
+            );
+            let repaired = session.repair_interrupted_tool_calls();
+            if repaired > 0 {
+                warn!(key = %key.hash_key(), repaired, "Repaired interrupted tool calls");
+            }
+            session
+        }))
+    }
+
+    async fn save(&self, session: &Session) -> Result<()> {
+        let key = key_column(&session.meta.key)?;
+        let meta = serde_json::to_string(&session.meta)?;
+        let updated = session.meta.last_updated_at.timestamp_micros();
+        let entries = session
+            .transcript
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        self.with_conn(move |conn| {
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            tx.execute(
+                "INSERT INTO sessions (key, meta, last_updated_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (key) DO UPDATE SET meta = ?2, last_updated_at = ?3",
+                params![key, meta, updated],
+            )?;
+            tx.execute("DELETE FROM entries WHERE key = ?1", [&key])?;
+            {
+                let mut insert = tx.prepare("INSERT INTO entries (key, seq, entry) VALUES (?1, ?2, ?3)")?;
+                for (seq, entry) in entries.iter().enumerate() {
+                    insert.execute(params![key, seq as i64, entry])?;
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await?;
+
+        debug!(key = %session.meta.key.hash_key(), "Saved session");
+        Ok(())
+    }
+
+    async fn append_entry(&self, key: &SessionKey, entry: &TranscriptEntry) -> Result<()> {
+        let key = key_column(key)?;
+        let entry = serde_json::to_string(entry)?;
+        self.with_conn(move |conn| {
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            tx.execute(
+                "INSERT INTO entries (key, seq, entry)
+                 SELECT ?1, COALESCE(MAX(seq) + 1, 0), ?2 FROM entries WHERE key = ?1",
+                params![key, entry],
+            )?;
+            update_meta(&tx, &key, |meta| meta.last_updated_at = Utc::now())?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn list(&self) -> Result<Vec<SessionMeta>> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare("SELECT meta FROM sessions ORDER BY rowid")?;
+            let mut rows = stmt.query([])?;
+            let mut metas = Vec::new();
+            while let Some(row) = rows.next()? {
+                metas.push(serde_json::from_str(&row.get::<_, String>(0)?)?);
+            }
+            Ok(metas)
+        })
+        .await
+    }
+
+    async fn delete(&self, key: &SessionKey) -> Result<()> {
+        let column = key_column(key)?;
+        self.with_conn(move |conn| {
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            tx.execute("DELETE FROM entries WHERE key = ?1", [&column])?;
+            tx.execute("DELETE FROM sessions WHERE key = ?1", [&column])?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await?;
+
+        debug!(key = %key.hash_key(), "Deleted session");
+        Ok(())
+    }
+
+    async fn reset(&self, key: &SessionKey) -> Result<()> {
+        let column = key_column(key)?;
+        self.with_conn(move |conn| {
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            tx.execute("DELETE FROM entries WHERE key = ?1", [&column])?;
+            update_meta(&tx, &column, |meta| {
+                meta.last_reset_at = Some(Utc::now());
+                meta.last_updated_at = Utc::now();
+            })?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await?;
+
+        debug!(key = %key.hash_key(), "Reset session");
+        Ok(())
+    }
+
+    /// Walks sessions newest first via the `last_updated_at` index, reading
+    /// one transcript row at a time.
+    async fn search(&self, pattern: &regex::Regex, limit: usize) -> Result<Vec<SessionSearchResult>> {
+        let pattern = pattern.clone();
+        self.with_conn(move |conn| {
+            let mut sessions =
+                conn.prepare("SELECT key, meta FROM sessions ORDER BY last_updated_at DESC")?;
+            let mut entries = conn.prepare("SELECT entry FROM entries WHERE key = ?1 ORDER BY seq")?;
+            let mut session_rows = sessions.query([])?;
+            let mut results = Vec::new();
+            while results.len() < limit {
+                let Some(session) = session_rows.next()? else {
+                    break;
+                };
+                let key: String = session.get(0)?;
+                let meta: SessionMeta = serde_json::from_str(&session.get::<_, String>(1)?)?;
+
+                let mut snippets: Vec<SearchSnippet> = Vec::new();
+                let mut rows = entries.query([&key])?;
+                let mut index = 0;
+                while let Some(row) = rows.next()? {
+                    match serde_json::from_str::<TranscriptEntry>(&row.get::<_, String>(0)?) {
+                        Ok(entry) => snippets.extend(entry.search(index, &pattern)),
+                        Err(e) => warn!(key = %meta.key.hash_key(), %e, "Skipping corrupt transcript entry"),
+                    }
+                    if snippets.len() >= MAX_SNIPPETS_PER_SESSION {
+                        break;
+                    }
+                    index += 1;
+                }
+                if !snippets.is_empty() {
+                    results.push(SessionSearchResult {
+                        key: meta.key,
+                        label: meta.label,
+                        snippets,
+                    });
+                }
+            }
+            Ok(results)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ChatType, ContentBlock};
+
+    fn test_key() -> SessionKey {
+        SessionKey {
+            channel: "test".into(),
+            account_id: "acc1".into(),
+            chat_type: ChatType::Dm,
+            peer_id: "peer1".into(),
+            scope: crate::session::SessionScope::PerSender,
+        }
+    }
+
+    fn user_entry(text: &str) -> TranscriptEntry {
+        TranscriptEntry::User {
+            content: vec![ContentBlock::Text { text: text.into() }],
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn open_store(dir: &tempfile::TempDir) -> SqliteSessionStore {
+        SqliteSessionStore::open(&dir.path().join("sessions.db")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_save_load_and_append() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = open_store(&dir);
+        assert!(store.load(&test_key()).await.unwrap().is_none());
+
+        let mut session = Session::new(test_key());
+        session.append(user_entry("Hello"));
+        store.save(&session).await.unwrap();
+        store.append_entry(&test_key(), &user_entry("Again")).await.unwrap();
+
+        let loaded = store.load(&test_key()).await.unwrap().unwrap();
+        assert_eq!(loaded.meta.key, test_key());
+        assert_eq!(loaded.transcript.len(), 2);
+        assert!(loaded.meta.last_updated_at > session.meta.last_updated_at);
+
+        // Saving replaces the transcript
+        store.save(&session).await.unwrap();
+        assert_eq!(store.load(&test_key()).await.unwrap().unwrap().transcript.len(), 1);
+
+        // Persisted across reopening
+        drop(store);
+        let store = open_store(&dir);
+        assert_eq!(store.load(&test_key()).await.unwrap().unwrap().transcript.len(), 1);
+        store.check_writable().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_reset_and_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = open_store(&dir);
+        let other = SessionKey {
+            peer_id: "peer2".into(),
+            ..test_key()
+        };
+        let mut session = Session::new(test_key());
+        session.append(user_entry("Hello"));
+        store.save(&session).await.unwrap();
+        store.save(&Session::new(other.clone())).await.unwrap();
+        // Updating a session keeps its place in the list
+        store.save(&session).await.unwrap();
+
+        let keys: Vec<_> = store.list().await.unwrap().into_iter().map(|m| m.key).collect();
+        assert_eq!(keys, vec![test_key(), other.clone()]);
+
+        store.reset(&test_key()).await.unwrap();
+        let loaded = store.load(&test_key()).await.unwrap().unwrap();
+        assert!(loaded.transcript.is_empty());
+        assert!(loaded.meta.last_reset_at.is_some());
+
+        store.delete(&test_key()).await.unwrap();
+        assert!(store.load(&test_key()).await.unwrap().is_none());
+        assert_eq!(store.list().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_search_newest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = open_store(&dir);
+        for (peer, text) in [("old", "deploy the old script"), ("new", "Deploy the new script"), ("x", "Unrelated")] {
+            let mut session = Session::new(SessionKey {
+                peer_id: peer.into(),
+                ..test_key()
+            });
+            session.append(user_entry(text));
+            store.save(&session).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+
+        let pattern = crate::session::search_pattern("deploy", false).unwrap();
+        let results = store.search(&pattern, 10).await.unwrap();
+        let peers: Vec<_> = results.iter().map(|r| r.key.peer_id.as_str()).collect();
+        assert_eq!(peers, ["new", "old"]);
+        assert_eq!(results[0].snippets[0].role, "user");
+        assert_eq!(store.search(&pattern, 1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_appends() {
+        let dir = tempfile::tempdir().unwrap();
+        open_store(&dir).save(&Session::new(test_key())).await.unwrap();
+
+        // Separate connections stand in for separate processes
+        let mut tasks = Vec::new();
+        for i in 0..20 {
+            let path = dir.path().join("sessions.db");
+            tasks.push(tokio::spawn(async move {
+                let store = SqliteSessionStore::open(&path).unwrap();
+                store.append_entry(&test_key(), &user_entry(&format!("message {i}"))).await.unwrap();
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let loaded = open_store(&dir).load(&test_key()).await.unwrap().unwrap();
+        assert_eq!(loaded.transcript.len(), 20);
+    }
+
+    #[tokio::test]
+    async fn test_import_jsonl_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let jsonl = JsonlSessionStore::new(dir.path().join("sessions"));
+        let mut session = Session::new(test_key());
+        session.append(user_entry("From JSONL"));
+        jsonl.save(&session).await.unwrap();
+        jsonl
+            .save(&Session::new(SessionKey {
+                peer_id: "peer2".into(),
+                ..test_key()
+            }))
+            .await
+            .unwrap();
+
+        let store = open_store(&dir);
+        assert_eq!(store.import_jsonl(&jsonl).await.unwrap(), 2);
+        let loaded = store.load(&test_key()).await.unwrap().unwrap();
+        assert_eq!(loaded.transcript.len(), 1);
+
+        // Later JSONL changes are not imported again
+        jsonl.delete(&test_key()).await.unwrap();
+        jsonl
+            .save(&Session::new(SessionKey {
+                peer_id: "peer3".into(),
+                ..test_key()
+            }))
+            .await
+            .unwrap();
+        drop(store);
+        let store = open_store(&dir);
+        assert_eq!(store.import_jsonl(&jsonl).await.unwrap(), 0);
+        assert_eq!(store.list().await.unwrap().len(), 2);
+    }
+}
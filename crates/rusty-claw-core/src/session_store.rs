@@ -3,24 +3,93 @@
 use std::path::PathBuf;
 
 use async_trait::async_trait;
-use tokio::io::AsyncWriteExt;
-use tracing::debug;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tracing::{debug, warn};
 
 use crate::error::{Result, RustyClawError};
-use crate::session::{Session, SessionKey, SessionMeta, SessionStore, TranscriptEntry};
+use crate::session::{
+    MAX_SNIPPETS_PER_SESSION, SearchSnippet, Session, SessionKey, SessionMeta, SessionSearchResult,
+    SessionStore, TranscriptEntry,
+};
 
 /// File-based session store using JSONL for transcripts.
 ///
 /// Layout:
 /// - `<base>/sessions.json` — array of `SessionMeta`
 /// - `<base>/transcripts/<hash>.jsonl` — one transcript entry per line
+/// - `<base>/sessions.lock` — advisory lock held while writing
+///
+/// Every write holds an exclusive `flock` on `sessions.lock`, so a CLI and
+/// a running gateway sharing the directory never interleave read-modify-write
+/// cycles on the index. Whole files are replaced via temp file and rename,
+/// so a crash leaves either the old or the new contents.
 pub struct JsonlSessionStore {
     base: PathBuf,
+    /// Serializes this instance's writers before they take the OS lock, so
+    /// waiting happens on the async runtime rather than blocking threads.
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+/// Block until this process holds an exclusive advisory lock on `file`.
+/// The lock is released when the file is closed.
+#[cfg(unix)]
+fn lock_exclusive(file: &std::fs::File) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    loop {
+        // SAFETY: flock only operates on the descriptor, which `file` keeps open.
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } == 0 {
+            return Ok(());
+        }
+        let err = std::io::Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+/// Cross-process locking is unix-only; elsewhere only the in-process lock
+/// applies.
+#[cfg(not(unix))]
+fn lock_exclusive(_file: &std::fs::File) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Held for the duration of a write; dropping it releases the locks.
+struct WriteGuard<'a> {
+    // Closing the file releases the OS lock, before the in-process one
+    _file: std::fs::File,
+    _local: tokio::sync::MutexGuard<'a, ()>,
+}
+
+/// Open the session store selected by `session.store` in the config. The
+/// first time the SQLite store opens, it imports the JSONL sessions.
+pub async fn open_session_store(
+    config: &crate::config::Config,
+) -> Result<std::sync::Arc<dyn SessionStore>> {
+    use crate::config::SessionStoreKind;
+    use crate::session_sqlite::SqliteSessionStore;
+
+    match config.session.as_ref().map(|s| s.store).unwrap_or_default() {
+        SessionStoreKind::Jsonl => Ok(std::sync::Arc::new(JsonlSessionStore::new(
+            JsonlSessionStore::default_path(),
+        ))),
+        SessionStoreKind::Sqlite => {
+            let store = SqliteSessionStore::open(&SqliteSessionStore::default_path())?;
+            store
+                .import_jsonl(&JsonlSessionStore::new(JsonlSessionStore::default_path()))
+                .await?;
+            Ok(std::sync::Arc::new(store))
+        }
+    }
 }
 
 impl JsonlSessionStore {
     pub fn new(base: PathBuf) -> Self {
-        Self { base }
+        Self {
+            base,
+            write_lock: tokio::sync::Mutex::new(()),
+        }
     }
 
     /// Default store location: `~/.rusty_claw/sessions/`
@@ -46,6 +115,59 @@ impl JsonlSessionStore {
         Ok(())
     }
 
+    /// Take the in-process and cross-process write locks.
+    async fn lock(&self) -> Result<WriteGuard<'_>> {
+        let local = self.write_lock.lock().await;
+        self.ensure_dirs().await?;
+        let path = self.base.join("sessions.lock");
+        let file = tokio::task::spawn_blocking(move || {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(&path)?;
+            lock_exclusive(&file)?;
+            Ok::<_, std::io::Error>(file)
+        })
+        .await
+        .map_err(|e| RustyClawError::Session(format!("session lock task failed: {e}")))??;
+        Ok(WriteGuard {
+            _file: file,
+            _local: local,
+        })
+    }
+
+    /// Whether the file at `path` is non-empty and lacks a final newline.
+    async fn ends_mid_line(path: &std::path::Path) -> Result<bool> {
+        use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+        let mut file = match tokio::fs::File::open(path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        if file.metadata().await?.len() == 0 {
+            return Ok(false);
+        }
+        file.seek(std::io::SeekFrom::End(-1)).await?;
+        let mut last = [0u8; 1];
+        file.read_exact(&mut last).await?;
+        Ok(last[0] != b'\n')
+    }
+
+    /// Replace `path` with `data` via a temp file and rename.
+    async fn write_atomic(path: &std::path::Path, data: &[u8]) -> Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let mut file = tokio::fs::File::create(&tmp).await?;
+        file.write_all(data).await?;
+        file.sync_all().await?;
+        drop(file);
+        tokio::fs::rename(&tmp, path).await?;
+        Ok(())
+    }
+
     async fn load_index(&self) -> Result<Vec<SessionMeta>> {
         let path = self.index_path();
         if !path.exists() {
@@ -56,15 +178,10 @@ impl JsonlSessionStore {
         Ok(metas)
     }
 
+    /// Callers must hold the write lock.
     async fn save_index(&self, metas: &[SessionMeta]) -> Result<()> {
-        self.ensure_dirs().await?;
         let data = serde_json::to_string_pretty(metas)?;
-        let path = self.index_path();
-        // Atomic write: write to temp then rename
-        let tmp = path.with_extension("json.tmp");
-        tokio::fs::write(&tmp, data.as_bytes()).await?;
-        tokio::fs::rename(&tmp, &path).await?;
-        Ok(())
+        Self::write_atomic(&self.index_path(), data.as_bytes()).await
     }
 
     async fn load_transcript(&self, key: &SessionKey) -> Result<Vec<TranscriptEntry>> {
@@ -74,14 +191,22 @@ impl JsonlSessionStore {
         }
         let data = tokio::fs::read_to_string(&path).await?;
         let mut entries = Vec::new();
-        for line in data.lines() {
+        let mut lines = data.lines().peekable();
+        while let Some(line) = lines.next() {
             if line.trim().is_empty() {
                 continue;
             }
-            let entry: TranscriptEntry = serde_json::from_str(line).map_err(|e| {
-                RustyClawError::Session(format!("corrupt transcript line: {e}"))
-            })?;
-            entries.push(entry);
+            match serde_json::from_str(line) {
+                Ok(entry) => entries.push(entry),
+                // An unterminated last line is an append cut short by a
+                // crash; everything before it is intact
+                Err(e) if lines.peek().is_none() && !data.ends_with('\n') => {
+                    warn!(path = %path.display(), %e, "Ignoring partially written transcript line");
+                }
+                Err(e) => {
+                    return Err(RustyClawError::Session(format!("corrupt transcript line: {e}")));
+                }
+            }
         }
         Ok(entries)
     }
@@ -89,6 +214,14 @@ impl JsonlSessionStore {
 
 #[async_trait]
 impl SessionStore for JsonlSessionStore {
+    async fn check_writable(&self) -> Result<()> {
+        self.ensure_dirs().await?;
+        let probe = self.base.join(".health-check");
+        Self::write_atomic(&probe, b"ok").await?;
+        tokio::fs::remove_file(&probe).await?;
+        Ok(())
+    }
+
     async fn load(&self, key: &SessionKey) -> Result<Option<Session>> {
         let metas = self.load_index().await?;
         let meta = metas.into_iter().find(|m| &m.key == key);
@@ -100,14 +233,19 @@ impl SessionStore for JsonlSessionStore {
                     entries = transcript.len(),
                     "Loaded session transcript"
                 );
-                Ok(Some(Session { meta, transcript }))
+                let mut session = Session { meta, transcript };
+                let repaired = session.repair_interrupted_tool_calls();
+                if repaired > 0 {
+                    warn!(key = %key.hash_key(), repaired, "Repaired interrupted tool calls");
+                }
+                Ok(Some(session))
             }
             None => Ok(None),
         }
     }
 
     async fn save(&self, session: &Session) -> Result<()> {
-        self.ensure_dirs().await?;
+        let _guard = self.lock().await?;
 
         // Update index
         let mut metas = self.load_index().await?;
@@ -126,28 +264,37 @@ impl SessionStore for JsonlSessionStore {
             data.push_str(&line);
             data.push('\n');
         }
-        let tmp = path.with_extension("jsonl.tmp");
-        tokio::fs::write(&tmp, data.as_bytes()).await?;
-        tokio::fs::rename(&tmp, &path).await?;
+        Self::write_atomic(&path, data.as_bytes()).await?;
 
         debug!(key = %session.meta.key.hash_key(), "Saved session");
         Ok(())
     }
 
     async fn append_entry(&self, key: &SessionKey, entry: &TranscriptEntry) -> Result<()> {
-        self.ensure_dirs().await?;
+        let _guard = self.lock().await?;
 
         let path = self.transcript_path(key);
-        let line = serde_json::to_string(entry)?;
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        // Drop a line left unterminated by a crash, so it cannot run into
+        // this entry
+        if Self::ends_mid_line(&path).await? {
+            let data = tokio::fs::read(&path).await?;
+            let keep = data.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+            warn!(path = %path.display(), "Truncating partially written transcript line");
+            let file = tokio::fs::OpenOptions::new().write(true).open(&path).await?;
+            file.set_len(keep as u64).await?;
+        }
 
+        // One write per entry, so a crash can cut off at most the last line
         let mut file = tokio::fs::OpenOptions::new()
             .create(true)
             .append(true)
             .open(&path)
             .await?;
         file.write_all(line.as_bytes()).await?;
-        file.write_all(b"\n").await?;
-        file.flush().await?;
+        file.sync_data().await?;
 
         // Update last_updated_at in index
         let mut metas = self.load_index().await?;
@@ -164,6 +311,8 @@ impl SessionStore for JsonlSessionStore {
     }
 
     async fn delete(&self, key: &SessionKey) -> Result<()> {
+        let _guard = self.lock().await?;
+
         // Remove from index
         let mut metas = self.load_index().await?;
         metas.retain(|m| &m.key != key);
@@ -180,10 +329,12 @@ impl SessionStore for JsonlSessionStore {
     }
 
     async fn reset(&self, key: &SessionKey) -> Result<()> {
+        let _guard = self.lock().await?;
+
         // Clear transcript file
         let path = self.transcript_path(key);
         if path.exists() {
-            tokio::fs::write(&path, b"").await?;
+            Self::write_atomic(&path, b"").await?;
         }
 
         // Update metadata
@@ -197,6 +348,49 @@ impl SessionStore for JsonlSessionStore {
         debug!(key = %key.hash_key(), "Reset session");
         Ok(())
     }
+
+    /// Scans transcript files line by line, so only one entry is in memory
+    /// at a time.
+    async fn search(&self, pattern: &regex::Regex, limit: usize) -> Result<Vec<SessionSearchResult>> {
+        let mut metas = self.load_index().await?;
+        metas.sort_by_key(|m| std::cmp::Reverse(m.last_updated_at));
+        let mut results = Vec::new();
+        for meta in metas {
+            if results.len() >= limit {
+                break;
+            }
+            let path = self.transcript_path(&meta.key);
+            let file = match tokio::fs::File::open(&path).await {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let mut lines = tokio::io::BufReader::new(file).lines();
+            let mut snippets: Vec<SearchSnippet> = Vec::new();
+            let mut index = 0;
+            while let Some(line) = lines.next_line().await? {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<TranscriptEntry>(&line) {
+                    Ok(entry) => snippets.extend(entry.search(index, pattern)),
+                    Err(e) => warn!(key = %meta.key.hash_key(), %e, "Skipping corrupt transcript line"),
+                }
+                if snippets.len() >= MAX_SNIPPETS_PER_SESSION {
+                    break;
+                }
+                index += 1;
+            }
+            if !snippets.is_empty() {
+                results.push(SessionSearchResult {
+                    key: meta.key,
+                    label: meta.label,
+                    snippets,
+                });
+            }
+        }
+        Ok(results)
+    }
 }
 
 #[cfg(test)]
@@ -237,6 +431,63 @@ mod tests {
         assert_eq!(loaded.meta.key, test_key());
     }
 
+    #[tokio::test]
+    async fn test_load_repairs_interrupted_tool_call() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JsonlSessionStore::new(dir.path().to_path_buf());
+
+        // Saved mid-run: the assistant asked for two tools, one finished
+        let mut session = test_session();
+        session.append(TranscriptEntry::User {
+            content: vec![ContentBlock::Text {
+                text: "Check the weather".into(),
+            }],
+            timestamp: chrono::Utc::now(),
+        });
+        session.append(TranscriptEntry::Assistant {
+            content: vec![
+                ContentBlock::ToolUse {
+                    id: "call_1".into(),
+                    name: "web_fetch".into(),
+                    input: serde_json::json!({}),
+                },
+                ContentBlock::ToolUse {
+                    id: "call_2".into(),
+                    name: "exec".into(),
+                    input: serde_json::json!({}),
+                },
+            ],
+            usage: None,
+            thinking: None,
+            thinking_signature: None,
+            thinking_blocks: Vec::new(),
+            timestamp: chrono::Utc::now(),
+        });
+        session.append(TranscriptEntry::ToolResult {
+            tool_use_id: "call_1".into(),
+            tool: "web_fetch".into(),
+            content: "Sunny".into(),
+            is_error: false,
+            timestamp: chrono::Utc::now(),
+        });
+        store.save(&session).await.unwrap();
+
+        let loaded = store.load(&test_key()).await.unwrap().unwrap();
+        assert_eq!(loaded.transcript.len(), 4);
+        match loaded.transcript.last() {
+            Some(TranscriptEntry::ToolResult { tool_use_id, tool, is_error, .. }) => {
+                assert_eq!(tool_use_id, "call_2");
+                assert_eq!(tool, "exec");
+                assert!(is_error);
+            }
+            other => panic!("expected synthesized tool result, got {other:?}"),
+        }
+
+        // A complete transcript is left alone
+        let mut repaired = loaded.clone();
+        assert_eq!(repaired.repair_interrupted_tool_calls(), 0);
+    }
+
     #[tokio::test]
     async fn test_append_entry() {
         let dir = tempfile::tempdir().unwrap();
@@ -301,4 +552,101 @@ mod tests {
         let key2 = test_key();
         assert_eq!(key1.hash_key(), key2.hash_key());
     }
+
+    #[tokio::test]
+    async fn test_search() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JsonlSessionStore::new(dir.path().to_path_buf());
+
+        let mut session = test_session();
+        session.append(TranscriptEntry::User {
+            content: vec![ContentBlock::Text {
+                text: "Can you review the Deployment script before Friday?".into(),
+            }],
+            timestamp: chrono::Utc::now(),
+        });
+        store.save(&session).await.unwrap();
+
+        let mut other = Session::new(SessionKey {
+            peer_id: "peer2".into(),
+            ..test_key()
+        });
+        other.append(TranscriptEntry::User {
+            content: vec![ContentBlock::Text { text: "Unrelated".into() }],
+            timestamp: chrono::Utc::now(),
+        });
+        store.save(&other).await.unwrap();
+
+        // Case-insensitive literal search
+        let pattern = crate::session::search_pattern("deployment script", false).unwrap();
+        let results = store.search(&pattern, 10).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].key, test_key());
+        assert_eq!(results[0].snippets[0].role, "user");
+        assert!(results[0].snippets[0].snippet.contains("Deployment script"));
+
+        // Regex search
+        let pattern = crate::session::search_pattern(r"fri\w+", true).unwrap();
+        assert_eq!(store.search(&pattern, 10).await.unwrap().len(), 1);
+        assert!(crate::session::search_pattern("(", true).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_appends_and_saves() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().to_path_buf();
+        JsonlSessionStore::new(base.clone()).save(&test_session()).await.unwrap();
+
+        // Separate store instances stand in for separate processes: only the
+        // file lock coordinates them
+        let mut tasks = Vec::new();
+        for i in 0..20 {
+            let base = base.clone();
+            tasks.push(tokio::spawn(async move {
+                let store = JsonlSessionStore::new(base);
+                let entry = TranscriptEntry::User {
+                    content: vec![ContentBlock::Text { text: format!("message {i}") }],
+                    timestamp: chrono::Utc::now(),
+                };
+                store.append_entry(&test_key(), &entry).await.unwrap();
+
+                // Concurrent index updates must not lose each other
+                let key = SessionKey { peer_id: format!("peer-{i}"), ..test_key() };
+                store.save(&Session::new(key)).await.unwrap();
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let store = JsonlSessionStore::new(base);
+        let loaded = store.load(&test_key()).await.unwrap().unwrap();
+        assert_eq!(loaded.transcript.len(), 20);
+        assert_eq!(store.list().await.unwrap().len(), 21);
+    }
+
+    #[tokio::test]
+    async fn test_partial_last_line_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JsonlSessionStore::new(dir.path().to_path_buf());
+        let mut session = test_session();
+        session.append(TranscriptEntry::User {
+            content: vec![ContentBlock::Text { text: "Hello".into() }],
+            timestamp: chrono::Utc::now(),
+        });
+        store.save(&session).await.unwrap();
+
+        // Simulate a crash midway through an append
+        let path = store.transcript_path(&test_key());
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        std::io::Write::write_all(&mut file, br#"{"type":"user","con"#).unwrap();
+
+        let loaded = store.load(&test_key()).await.unwrap().unwrap();
+        assert_eq!(loaded.transcript.len(), 1);
+
+        // The next append discards the fragment instead of running into it
+        store.append_entry(&test_key(), &loaded.transcript[0]).await.unwrap();
+        let loaded = store.load(&test_key()).await.unwrap().unwrap();
+        assert_eq!(loaded.transcript.len(), 2);
+    }
 }
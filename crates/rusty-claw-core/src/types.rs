@@ -39,10 +39,25 @@ pub struct InboundMessage {
     pub text: Option<String>,
     pub media: Vec<MediaAttachment>,
     pub reply_to: Option<String>,
+    /// Platform id of this message, so a reply can reference it.
+    #[serde(default)]
+    pub message_id: Option<String>,
     pub thread_id: Option<String>,
     pub timestamp: DateTime<Utc>,
     /// Platform-specific raw payload for channel-specific processing.
     pub raw: Option<serde_json::Value>,
+    /// Set when this message is a reaction rather than text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reaction: Option<Reaction>,
+}
+
+/// A reaction a user added to a message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reaction {
+    /// The emoji, or `:shortcode:` on platforms that name them (Slack).
+    pub emoji: String,
+    /// Id of the message reacted to.
+    pub message_id: String,
 }
 
 /// Outbound message to send via a channel.
@@ -52,6 +67,18 @@ pub struct OutboundMessage {
     pub media: Vec<MediaAttachment>,
     pub reply_to: Option<String>,
     pub thread_id: Option<String>,
+    /// Quick-reply buttons, one inner `Vec` per row. Channels without
+    /// button support ignore them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub buttons: Vec<Vec<MessageButton>>,
+}
+
+/// A button attached to an outbound message. Pressing it comes back as an
+/// inbound message whose text is `payload`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageButton {
+    pub label: String,
+    pub payload: String,
 }
 
 /// Target for sending a message.
@@ -66,7 +93,11 @@ pub struct SendTarget {
 /// Result of sending a message.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SendResult {
+    /// Id of the last message sent.
     pub message_id: Option<String>,
+    /// Ids of every message sent, in order, when a reply was split into parts.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub message_ids: Vec<String>,
     pub success: bool,
     pub error: Option<String>,
 }
@@ -131,9 +162,11 @@ impl InboundMessage {
             text: Some(text.to_string()),
             media: Vec::new(),
             reply_to: None,
+            message_id: None,
             thread_id: None,
             timestamp: Utc::now(),
             raw: None,
+            reaction: None,
         }
     }
 }
@@ -1,20 +1,36 @@
 //! Canvas/A2UI WebSocket handler.
 //!
 //! Provides `/canvas/{session_id}` endpoint for real-time agent-to-UI communication.
+//! `session_id` is the agent session's hash key. Clients send
+//! `user_action` events back; actions on handlers the agent declared are
+//! delivered to that session as inbound messages and trigger an agent turn.
+//!
+//! Since actions run the agent with its tools, the socket takes the same
+//! credentials as `/ws`, passed as a `token` query parameter, and actions
+//! count against the caller's API key scopes and request limits like an
+//! `agent` call.
 
 use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 
 use axum::{
-    extract::{Path, State, WebSocketUpgrade},
+    extract::{ConnectInfo, Path, Query, State, WebSocketUpgrade},
+    http::StatusCode,
     response::IntoResponse,
 };
 use axum::extract::ws::{Message, WebSocket};
 use futures::{SinkExt, StreamExt};
 use tokio::sync::{mpsc, RwLock};
-use tracing::{debug, info};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
 
-use rusty_claw_canvas::{CanvasOperation, CanvasEvent};
+use rusty_claw_agent::{AgentEvent, AgentRunOptions};
+use rusty_claw_canvas::{CanvasEvent, CanvasOperation, CanvasSession, user_action_message};
+use rusty_claw_core::config::ApiKeyConfig;
+use crate::connection::{authenticate_token, TokenQuery};
+use crate::events::broadcast_event;
+use crate::methods::Caller;
 use crate::state::GatewayState;
 
 /// State for all active canvas sessions.
@@ -23,12 +39,21 @@ pub struct CanvasManager {
 }
 
 struct CanvasSessionState {
-    /// Accumulated HTML components
-    components: Vec<String>,
+    /// Accumulated components and incremental changes
+    canvas: CanvasSession,
     /// Connected client senders
     clients: Vec<mpsc::UnboundedSender<String>>,
 }
 
+impl CanvasSessionState {
+    fn new(session_id: &str) -> Self {
+        Self {
+            canvas: CanvasSession::new(session_id.to_string()),
+            clients: Vec::new(),
+        }
+    }
+}
+
 impl Default for CanvasManager {
     fn default() -> Self {
         Self::new()
@@ -45,34 +70,11 @@ impl CanvasManager {
     /// Push an operation from the agent into a canvas session.
     pub async fn push_operation(&self, session_id: &str, op: CanvasOperation) {
         let mut sessions = self.sessions.write().await;
-        let session = sessions.entry(session_id.to_string()).or_insert_with(|| {
-            CanvasSessionState {
-                components: Vec::new(),
-                clients: Vec::new(),
-            }
-        });
-
-        let event = match &op {
-            CanvasOperation::Push { html } => {
-                session.components.push(html.clone());
-                CanvasEvent::ComponentAdded {
-                    index: session.components.len() - 1,
-                    html: html.clone(),
-                }
-            }
-            CanvasOperation::Reset => {
-                session.components.clear();
-                CanvasEvent::Reset
-            }
-            CanvasOperation::Eval { js } => {
-                CanvasEvent::Eval { js: js.clone() }
-            }
-            CanvasOperation::Snapshot => {
-                CanvasEvent::Snapshot {
-                    components: session.components.clone(),
-                }
-            }
-        };
+        let session = sessions
+            .entry(session_id.to_string())
+            .or_insert_with(|| CanvasSessionState::new(session_id));
+
+        let event = session.canvas.apply(op);
 
         // Broadcast to all connected clients
         if let Ok(msg) = serde_json::to_string(&event) {
@@ -80,31 +82,72 @@ impl CanvasManager {
         }
     }
 
+    /// Whether `session_id` declared a handler for `element_id`.
+    pub async fn accepts_action(&self, session_id: &str, element_id: &str) -> bool {
+        self.sessions
+            .read()
+            .await
+            .get(session_id)
+            .is_some_and(|s| s.canvas.accepts_action(element_id))
+    }
+
     /// Get current snapshot of a session.
     pub async fn snapshot(&self, session_id: &str) -> Vec<String> {
         let sessions = self.sessions.read().await;
         sessions
             .get(session_id)
-            .map(|s| s.components.clone())
+            .map(|s| s.canvas.components.clone())
             .unwrap_or_default()
     }
 }
 
-/// WebSocket upgrade handler for canvas connections.
+/// WebSocket upgrade handler for canvas connections. The connection is
+/// authenticated before the upgrade.
 pub async fn canvas_ws_handler(
     ws: WebSocketUpgrade,
     Path(session_id): Path<String>,
+    Query(query): Query<TokenQuery>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     State(state): State<Arc<GatewayState>>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_canvas_connection(state, session_id, socket))
+    if state.shutdown.is_cancelled() {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+    if let Some(limiter) = &state.rate_limiter {
+        if !limiter.check(addr.ip()) {
+            return StatusCode::TOO_MANY_REQUESTS.into_response();
+        }
+    }
+
+    let config = state.read_config().await;
+    let api_key = match authenticate_token(&config, &state.login_tokens, query.token.as_deref()) {
+        Ok(key) => key,
+        Err(reason) => {
+            warn!(session_id = %session_id, %reason, "Canvas authentication failed");
+            return (StatusCode::UNAUTHORIZED, reason).into_response();
+        }
+    };
+
+    ws.on_upgrade(move |socket| {
+        handle_canvas_connection(state, session_id, api_key, addr.ip(), socket)
+    })
+    .into_response()
 }
 
 async fn handle_canvas_connection(
     state: Arc<GatewayState>,
     session_id: String,
+    api_key: Option<ApiKeyConfig>,
+    ip: IpAddr,
     ws: WebSocket,
 ) {
-    info!(session_id = %session_id, "Canvas client connected");
+    let conn_id = uuid::Uuid::new_v4().to_string();
+    let caller = Caller {
+        conn_id: &conn_id,
+        ip: Some(ip),
+        api_key: api_key.as_ref(),
+    };
+    info!(session_id = %session_id, conn_id = %conn_id, "Canvas client connected");
 
     let (mut ws_tx, mut ws_rx) = ws.split();
     let (tx, mut rx) = mpsc::unbounded_channel::<String>();
@@ -112,20 +155,16 @@ async fn handle_canvas_connection(
     // Register client
     {
         let mut sessions = state.canvas.sessions.write().await;
-        let session = sessions.entry(session_id.clone()).or_insert_with(|| {
-            CanvasSessionState {
-                components: Vec::new(),
-                clients: Vec::new(),
-            }
-        });
+        let session = sessions
+            .entry(session_id.clone())
+            .or_insert_with(|| CanvasSessionState::new(&session_id));
         session.clients.push(tx);
 
-        // Send current snapshot
-        let snapshot = CanvasEvent::Snapshot {
-            components: session.components.clone(),
-        };
-        if let Ok(msg) = serde_json::to_string(&snapshot) {
-            let _ = ws_tx.send(Message::Text(msg.into())).await;
+        // Send current snapshot plus any incremental changes made since
+        for event in session.canvas.replay() {
+            if let Ok(msg) = serde_json::to_string(&event) {
+                let _ = ws_tx.send(Message::Text(msg.into())).await;
+            }
         }
     }
 
@@ -138,9 +177,12 @@ async fn handle_canvas_connection(
         }
     });
 
-    // Read client messages (for future bidirectional use)
+    // Read user actions from the client
     while let Some(msg) = ws_rx.next().await {
         match msg {
+            Ok(Message::Text(text)) => {
+                handle_client_message(&state, &caller, &session_id, &text).await;
+            }
             Ok(Message::Close(_)) => break,
             Err(_) => break,
             _ => {}
@@ -148,5 +190,148 @@ async fn handle_canvas_connection(
     }
 
     send_task.abort();
+    state.request_limiter.forget_connection(&conn_id);
     debug!(session_id = %session_id, "Canvas client disconnected");
 }
+
+/// Validate a client message and route user actions to the agent session.
+async fn handle_client_message(
+    state: &Arc<GatewayState>,
+    caller: &Caller<'_>,
+    session_id: &str,
+    text: &str,
+) {
+    let Ok(CanvasEvent::UserAction { element_id, value, .. }) = serde_json::from_str(text) else {
+        debug!(session_id = %session_id, "Ignoring unrecognized canvas client message");
+        return;
+    };
+
+    if let Err(reason) = route_user_action(state, caller, session_id, &element_id, value).await {
+        warn!(session_id = %session_id, element_id = %element_id, %reason, "Canvas action rejected");
+        let rejected = CanvasEvent::ActionRejected { element_id, reason };
+        if let Ok(msg) = serde_json::to_string(&rejected) {
+            let mut sessions = state.canvas.sessions.write().await;
+            if let Some(session) = sessions.get_mut(session_id) {
+                session.clients.retain(|tx| tx.send(msg.clone()).is_ok());
+            }
+        }
+    }
+}
+
+/// Start an agent turn in the session owning this canvas. The session key
+/// comes from the connection path, so a client can only reach the session
+/// it connected to.
+async fn route_user_action(
+    state: &Arc<GatewayState>,
+    caller: &Caller<'_>,
+    session_id: &str,
+    element_id: &str,
+    value: serde_json::Value,
+) -> Result<(), String> {
+    if state.shutdown.is_cancelled() {
+        return Err("gateway is shutting down".into());
+    }
+    if let Some(key) = caller.api_key
+        && !key.allows("agent")
+    {
+        return Err(format!("API key '{}' is not allowed to start agent turns", key.name));
+    }
+    if let Err(retry_after) = state.request_limiter.check(caller.conn_id, caller.ip, "agent") {
+        return Err(format!(
+            "too many actions; retry in {}ms",
+            retry_after.as_millis().max(1)
+        ));
+    }
+    if !state.canvas.accepts_action(session_id, element_id).await {
+        return Err(format!("no handler declared for '{element_id}'"));
+    }
+
+    let metas = state.sessions.list().await.map_err(|e| e.to_string())?;
+    let key = metas
+        .into_iter()
+        .map(|m| m.key)
+        .find(|k| k.hash_key() == session_id)
+        .ok_or("no agent session for this canvas")?;
+
+    // Claim the session in one write so concurrent actions cannot both run
+    let cancel_token = CancellationToken::new();
+    {
+        let mut agents = state.active_agents.write().await;
+        if agents.contains_key(session_id) {
+            return Err("agent is busy with another turn".into());
+        }
+        agents.insert(session_id.to_string(), cancel_token.clone());
+    }
+
+    let message = user_action_message(session_id, element_id, &value);
+    info!(session_id = %session_id, element_id = %element_id, "Routing canvas action to agent");
+
+    let state = state.clone();
+    let session_id = session_id.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = run_action_turn(&state, key, message, cancel_token).await {
+            error!(session_id = %session_id, %e, "Canvas action turn failed");
+        }
+        state.active_agents.write().await.remove(&session_id);
+    });
+    Ok(())
+}
+
+/// Run the agent turn for an action. The caller holds the session's
+/// `active_agents` entry and releases it afterwards.
+async fn run_action_turn(
+    state: &Arc<GatewayState>,
+    key: rusty_claw_core::session::SessionKey,
+    message: rusty_claw_core::types::InboundMessage,
+    cancel_token: CancellationToken,
+) -> anyhow::Result<()> {
+    let mut session = state
+        .sessions
+        .load(&key)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("session disappeared"))?;
+
+    let (_, provider, credentials) = state
+        .providers
+        .resolve(session.meta.provider_id.as_deref(), session.meta.model.as_deref())
+        .ok_or_else(|| anyhow::anyhow!("No default provider configured"))?;
+
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel::<AgentEvent>();
+    let state_clone = state.clone();
+    let event_task = tokio::spawn(async move {
+        while let Some(event) = event_rx.recv().await {
+            #[cfg(feature = "metrics")]
+            crate::metrics::record_agent_event(&event);
+            if let Ok(payload) = serde_json::to_value(&event) {
+                broadcast_event(&state_clone, "agent.event", Some(payload)).await;
+            }
+        }
+    });
+
+    let config = Arc::new(state.read_config().await);
+
+    #[cfg(feature = "metrics")]
+    let provider_id = provider.id().to_string();
+    let result = rusty_claw_agent::run_agent_with_options(
+        &mut session,
+        message,
+        &config,
+        &state.tools,
+        provider,
+        credentials,
+        event_tx,
+        &state.hooks,
+        AgentRunOptions {
+            cancel: Some(cancel_token),
+            checkpoint: Some(state.sessions.clone()),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    #[cfg(feature = "metrics")]
+    crate::metrics::record_agent_run(&provider_id, &result);
+    let _ = event_task.await;
+    state.sessions.save(&session).await?;
+    result.map(|_| ())
+}
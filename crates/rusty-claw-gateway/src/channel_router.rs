@@ -1,14 +1,22 @@
 //! Routes inbound channel messages to agent runs.
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use tracing::{error, info};
+use serde_json::json;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
 
-use rusty_claw_core::session::{Session, SessionKey, SessionScope};
-use rusty_claw_core::types::InboundMessage;
-use rusty_claw_agent::AgentEvent;
+use rusty_claw_core::config::{PairingConfig, ReactionAction, default_reaction_action};
+use rusty_claw_core::pairing::PairingOutcome;
+use rusty_claw_core::session::{Session, SessionKey, SessionScope, TranscriptEntry};
+use rusty_claw_core::types::{ContentBlock, InboundMessage, OutboundMessage, Reaction, SendTarget};
+use rusty_claw_agent::{AgentEvent, AgentRunOptions};
 use rusty_claw_channels::InboundReceiver;
+use rusty_claw_plugins::{HookContext, HookEvent, HookRegistry};
 
+use crate::reply_stream::ReplyStream;
+use crate::session_hooks::{fire_session_end, fire_session_start};
 use crate::state::GatewayState;
 
 /// Start routing messages from a channel's inbound receiver to agent runs.
@@ -21,6 +29,10 @@ pub fn start_channel_router(
         info!(channel = %channel_id, "Channel router started");
 
         while let Some(message) = rx.recv().await {
+            if state.shutdown.is_cancelled() {
+                info!(channel = %channel_id, "Gateway shutting down, dropping inbound message");
+                continue;
+            }
             let state = state.clone();
             let channel_id = channel_id.clone();
 
@@ -40,21 +52,44 @@ async fn handle_inbound_message(
     channel_id: &str,
     message: InboundMessage,
 ) -> anyhow::Result<()> {
-    // Build session key from the message
-    let key = SessionKey {
-        channel: channel_id.to_string(),
-        account_id: message.account_id.clone(),
-        chat_type: message.chat_type,
-        peer_id: message.sender.id.clone(),
-        scope: SessionScope::PerSender,
+    let key = session_key(channel_id, &message);
+
+    if !sender_permitted(state, channel_id, &message).await {
+        return Ok(());
+    }
+
+    // --- Hook: MessageReceived (can drop or rewrite the message) ---
+    let Some(message) = run_received_hooks(&state.hooks, &key, message).await else {
+        return Ok(());
     };
 
+    if handle_pairing_admin(state, channel_id, &message).await {
+        return Ok(());
+    }
+
+    if let Some(reaction) = &message.reaction {
+        return handle_reaction(state, channel_id, &key, &message, reaction).await;
+    }
+
+    // Chat-level commands (e.g. Discord's `/reset` slash command) are handled
+    // here rather than by the agent
+    if message.text.as_deref().map(str::trim) == Some("/reset") {
+        state.sessions.reset(&key).await?;
+        fire_session_end(&state.hooks, &key, "reset").await;
+        info!(channel = channel_id, sender = %message.sender.id, "Session reset from channel");
+        send_reply(state, channel_id, &message, "Conversation reset.".into()).await;
+        return Ok(());
+    }
+
     // Load or create session
-    let mut session = match state.sessions.load(&key).await? {
-        Some(s) => s,
-        None => Session::new(key.clone()),
+    let (mut session, is_new) = match state.sessions.load(&key).await? {
+        Some(s) => (s, false),
+        None => (Session::new(key.clone()), true),
     };
 
+    // --- Hook: SessionStart ---
+    fire_session_start(&state.hooks, &key, is_new).await;
+
     // Set up event channel
     let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel::<AgentEvent>();
 
@@ -62,6 +97,24 @@ async fn handle_inbound_message(
     let response_text = Arc::new(tokio::sync::Mutex::new(String::new()));
     let response_text_clone = response_text.clone();
 
+    // Stream the reply into the chat as it is generated, if enabled
+    let stream_replies = state
+        .read_config()
+        .await
+        .channels
+        .as_ref()
+        .is_some_and(|c| c.stream_replies(channel_id));
+    let mut stream = match state.channels.get(channel_id) {
+        Some(channel) if stream_replies => Some(ReplyStream::new(
+            state.clone(),
+            reply_target(channel_id, &message),
+            message.message_id.clone(),
+            message.thread_id.clone(),
+            channel.capabilities().max_message_length.unwrap_or(usize::MAX),
+        )),
+        _ => None,
+    };
+
     // Forward events as broadcasts
     let state_clone = state.clone();
     let event_task = tokio::spawn(async move {
@@ -72,14 +125,28 @@ async fn handle_inbound_message(
                 *rt = text.clone();
             }
 
+            if let Some(stream) = stream.as_mut() {
+                match &event {
+                    AgentEvent::PartialReply { delta } => stream.push(delta).await,
+                    AgentEvent::ToolCall { .. } => stream.restart(),
+                    _ => {}
+                }
+            }
+
+            #[cfg(feature = "metrics")]
+            crate::metrics::record_agent_event(&event);
             if let Ok(payload) = serde_json::to_value(&event) {
                 crate::events::broadcast_event(&state_clone, "agent.event", Some(payload)).await;
             }
         }
+        stream
     });
 
     // Resolve provider
-    let (provider, credentials) = match state.providers.default() {
+    let (_, provider, credentials) = match state
+        .providers
+        .resolve(session.meta.provider_id.as_deref(), session.meta.model.as_deref())
+    {
         Some(pc) => pc,
         None => {
             anyhow::bail!("No default provider configured");
@@ -92,7 +159,24 @@ async fn handle_inbound_message(
     // Read config snapshot
     let config = Arc::new(state.read_config().await);
 
-    let result = rusty_claw_agent::run_agent(
+    // Register so `agent.abort` can cancel channel-initiated runs too
+    let session_hash = key.hash_key();
+    let cancel_token = CancellationToken::new();
+    state
+        .active_agents
+        .write()
+        .await
+        .insert(session_hash.clone(), cancel_token.clone());
+
+    let active_skills = state.skills.read().await.skills_for_turn(
+        &config,
+        session.meta.active_skill.as_deref(),
+        message.text.as_deref(),
+    );
+
+    #[cfg(feature = "metrics")]
+    let provider_id = provider.id().to_string();
+    let result = rusty_claw_agent::run_agent_with_options(
         &mut session,
         message.clone(),
         &config,
@@ -101,43 +185,631 @@ async fn handle_inbound_message(
         credentials,
         event_tx,
         &state.hooks,
+        AgentRunOptions {
+            cancel: Some(cancel_token),
+            checkpoint: Some(state.sessions.clone()),
+            active_skills,
+            ..Default::default()
+        },
     )
     .await;
 
+    #[cfg(feature = "metrics")]
+    crate::metrics::record_agent_run(&provider_id, &result);
+
     // Wait for event forwarding to complete
-    let _ = event_task.await;
+    let stream = event_task.await.ok().flatten();
 
     // Save session
-    state.sessions.save(&session).await?;
+    if let Err(e) = state.sessions.save(&session).await {
+        error!(channel = channel_id, %e, "Failed to save session");
+    }
 
-    // Send response back through the channel
+    // Send response back through the channel, finishing a streamed reply
+    // first and sending only what it could not hold
     let reply_text = response_text.lock().await.clone();
-    if !reply_text.is_empty() {
-        let target = rusty_claw_core::types::SendTarget {
-            channel: channel_id.to_string(),
-            account_id: message.account_id.clone(),
-            chat_id: message.sender.id.clone(),
-            chat_type: message.chat_type,
-        };
+    let remainder = match stream {
+        Some(stream) => stream.finish(&reply_text).await,
+        None => Some(reply_text).filter(|t| !t.is_empty()),
+    };
+    if let Some(text) = remainder {
+        send_reply(state, channel_id, &message, text).await;
+    }
+
+    // The run is done once its reply is out, which shutdown waits for
+    state.active_agents.write().await.remove(&session_hash);
+
+    if let Err(e) = result {
+        error!(channel = channel_id, %e, "Agent run failed");
+    }
+
+    Ok(())
+}
+
+/// Session key for a channel message.
+fn session_key(channel_id: &str, message: &InboundMessage) -> SessionKey {
+    SessionKey {
+        channel: channel_id.to_string(),
+        account_id: message.account_id.clone(),
+        chat_type: message.chat_type,
+        peer_id: message.sender.id.clone(),
+        scope: SessionScope::PerSender,
+    }
+}
+
+fn hook_ctx(key: &SessionKey) -> HookContext {
+    HookContext {
+        session_key: key.hash_key(),
+        timestamp: chrono::Utc::now(),
+        metadata: HashMap::new(),
+    }
+}
+
+/// Run `MessageReceived` hooks. Returns `None` when a hook cancelled the
+/// message; a hook may also return a rewritten message.
+async fn run_received_hooks(
+    hooks: &HookRegistry,
+    key: &SessionKey,
+    message: InboundMessage,
+) -> Option<InboundMessage> {
+    let data = serde_json::to_value(&message).unwrap_or_default();
+    match hooks.fire_or_cancel(HookEvent::MessageReceived, hook_ctx(key), data).await {
+        Ok(data) => Some(serde_json::from_value(data).unwrap_or_else(|e| {
+            warn!(%e, "Ignoring invalid message rewrite from MessageReceived hook");
+            message
+        })),
+        Err(reason) => {
+            info!(channel = %key.channel, sender = %key.peer_id, %reason, "Inbound message dropped by hook");
+            None
+        }
+    }
+}
+
+/// Run `MessageSending` hooks over `{ target, message }`. Returns `None` when
+/// a hook cancelled the send; a hook may rewrite `message`.
+async fn run_sending_hooks(
+    hooks: &HookRegistry,
+    key: &SessionKey,
+    target: &SendTarget,
+    message: OutboundMessage,
+) -> Option<OutboundMessage> {
+    let data = json!({ "target": target, "message": &message });
+    match hooks.fire_or_cancel(HookEvent::MessageSending, hook_ctx(key), data).await {
+        Ok(mut data) => Some(serde_json::from_value(data["message"].take()).unwrap_or_else(|e| {
+            warn!(%e, "Ignoring invalid message rewrite from MessageSending hook");
+            message
+        })),
+        Err(reason) => {
+            info!(channel = %target.channel, %reason, "Outbound message cancelled by hook");
+            None
+        }
+    }
+}
+
+/// Apply the action configured for a reaction, if any.
+async fn handle_reaction(
+    state: &Arc<GatewayState>,
+    channel_id: &str,
+    key: &SessionKey,
+    message: &InboundMessage,
+    reaction: &Reaction,
+) -> anyhow::Result<()> {
+    let action = {
+        let config = state.config.read().await;
+        match &config.channels {
+            Some(channels) => channels.reaction_action(&reaction.emoji),
+            None => default_reaction_action(&reaction.emoji),
+        }
+    };
+    let Some(action) = action else {
+        debug!(channel = channel_id, emoji = %reaction.emoji, "Ignoring unmapped reaction");
+        return Ok(());
+    };
+
+    let session_hash = key.hash_key();
+    match action {
+        ReactionAction::Abort => {
+            if let Some(token) = state.active_agents.read().await.get(&session_hash) {
+                token.cancel();
+                info!(channel = channel_id, session = %session_hash, "Agent run aborted by reaction");
+            }
+        }
+        ReactionAction::Retry => {
+            if state.active_agents.read().await.contains_key(&session_hash) {
+                debug!(channel = channel_id, "Retry reaction ignored while a run is active");
+                return Ok(());
+            }
+            let last_text = state
+                .sessions
+                .load(key)
+                .await?
+                .and_then(|session| last_user_text(&session));
+            let Some(text) = last_text else {
+                return Ok(());
+            };
+            info!(channel = channel_id, session = %session_hash, "Re-running last message by reaction");
+            let retry = InboundMessage {
+                text: Some(text),
+                reaction: None,
+                message_id: None,
+                ..message.clone()
+            };
+            Box::pin(handle_inbound_message(state, channel_id, retry)).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Text of the most recent user turn in the transcript.
+fn last_user_text(session: &Session) -> Option<String> {
+    session.transcript.iter().rev().find_map(|entry| match entry {
+        TranscriptEntry::User { content, .. } => {
+            let text: Vec<&str> = content
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect();
+            (!text.is_empty()).then(|| text.join("\n"))
+        }
+        _ => None,
+    })
+}
+
+/// Reactions on a pairing notice that approve or reject it.
+const APPROVE_REACTIONS: &[&str] = &["✅", "👍", ":white_check_mark:", ":+1:"];
+const REJECT_REACTIONS: &[&str] = &["❌", "👎", ":x:", ":-1:"];
+
+/// Parse `/approve <code>` or `/reject <code>` into (approve, code).
+fn parse_pairing_command(text: &str) -> Option<(bool, String)> {
+    let mut parts = text.split_whitespace();
+    let approve = match parts.next()? {
+        "/approve" => true,
+        "/reject" => false,
+        _ => return None,
+    };
+    // Allow the code to be typed in groups, e.g. "ABCD EFGH"
+    let code: String = parts.collect();
+    (!code.is_empty()).then_some((approve, code))
+}
+
+/// Resolve a pairing from a channel admin's `/approve` or `/reject` command,
+/// or their reaction on a pairing notice. Returns false if `message` is
+/// neither, or its sender is not one of the channel's `admin_senders`;
+/// non-admins get no hint that the commands exist.
+async fn handle_pairing_admin(
+    state: &Arc<GatewayState>,
+    channel_id: &str,
+    message: &InboundMessage,
+) -> bool {
+    let sender_id = &message.sender.id;
+    let is_admin = state
+        .read_config()
+        .await
+        .channels
+        .as_ref()
+        .and_then(|c| c.sender_access(channel_id))
+        .is_some_and(|a| a.is_admin(sender_id));
+    if !is_admin {
+        return false;
+    }
+
+    let (approve, code) = match &message.reaction {
+        Some(reaction) => {
+            state.prune_pairing_notices();
+            let notice = format!("{channel_id}:{}", reaction.message_id);
+            let Some(code) = state.pairing_notices.lock().unwrap().get(&notice).cloned() else {
+                return false;
+            };
+            let emoji = reaction.emoji.as_str();
+            if APPROVE_REACTIONS.contains(&emoji) {
+                (true, code)
+            } else if REJECT_REACTIONS.contains(&emoji) {
+                (false, code)
+            } else {
+                return false;
+            }
+        }
+        None => match message.text.as_deref().and_then(parse_pairing_command) {
+            Some(command) => command,
+            None => return false,
+        },
+    };
 
-        let outbound = rusty_claw_core::types::OutboundMessage {
-            text: Some(reply_text),
+    let approver = format!("{channel_id}:{sender_id}");
+    let result = if approve {
+        state.pairing.approve(channel_id, &code, &approver)
+    } else {
+        state.pairing.reject(channel_id, &code, &approver)
+    };
+    let text = match result {
+        Ok(PairingOutcome::Resolved) => {
+            state
+                .pairing_notices
+                .lock()
+                .unwrap()
+                .retain(|_, c| !c.eq_ignore_ascii_case(&code));
+            let done = if approve { "approved" } else { "rejected" };
+            info!(channel = channel_id, admin = %sender_id, "Pairing {done} from chat");
+            format!("Pairing {code} {done}.")
+        }
+        Ok(PairingOutcome::Expired) => format!("Pairing code {code} has expired."),
+        Ok(PairingOutcome::Unknown) => format!("No pending pairing with code {code}."),
+        Ok(PairingOutcome::LockedOut) => {
+            "Too many wrong pairing codes, try again later.".to_string()
+        }
+        Err(e) => {
+            error!(channel = channel_id, %e, "Failed to update pairing");
+            "Failed to update the pairing.".to_string()
+        }
+    };
+    send_reply(state, channel_id, message, text).await;
+    true
+}
+
+/// Send a new pairing request to each of the channel's admins, remembering
+/// the notices so a reaction on one resolves the request.
+async fn notify_pairing_admins(
+    state: &Arc<GatewayState>,
+    channel_id: &str,
+    message: &InboundMessage,
+    code: &str,
+    admins: &[String],
+) {
+    if admins.is_empty() {
+        return;
+    }
+    // Repeat messages from the sender get the same code; notify once
+    state.prune_pairing_notices();
+    if state.pairing_notices.lock().unwrap().values().any(|c| c == code) {
+        return;
+    }
+    let Some(channel) = state.channels.get(channel_id) else {
+        return;
+    };
+    let delivery = state
+        .read_config()
+        .await
+        .channels
+        .as_ref()
+        .map(|c| c.delivery.clone())
+        .unwrap_or_default();
+
+    let sender = &message.sender;
+    let who = match &sender.display_name {
+        Some(name) => format!("{name} ({})", sender.id),
+        None => sender.id.clone(),
+    };
+    let text = format!(
+        "Pairing request from {who}. Code: {code}\n\
+         Reply `/approve {code}` or `/reject {code}`, or react ✅ or ❌ to this message."
+    );
+    for admin in admins {
+        let target = match channel.dm_target(&message.account_id, admin).await {
+            Ok(target) => target,
+            Err(e) => {
+                warn!(channel = channel_id, admin = %admin, %e, "Failed to open pairing admin DM");
+                continue;
+            }
+        };
+        let outbound = OutboundMessage {
+            text: Some(text.clone()),
             media: vec![],
             reply_to: None,
-            thread_id: message.thread_id.clone(),
+            thread_id: None,
+            buttons: vec![],
         };
+        match crate::delivery::deliver(channel, &target, outbound, &delivery).await {
+            Ok(result) => {
+                if let Some(id) = result.message_id {
+                    state
+                        .pairing_notices
+                        .lock()
+                        .unwrap()
+                        .insert(format!("{channel_id}:{id}"), code.to_string());
+                }
+            }
+            Err(e) => {
+                warn!(channel = channel_id, admin = %admin, %e, "Failed to notify pairing admin")
+            }
+        }
+    }
+}
+
+/// Enforce the channel's sender allowlist. Unknown senders are dropped, or
+/// sent a pairing code for the owner to approve when pairing is enabled.
+async fn sender_permitted(state: &Arc<GatewayState>, channel_id: &str, message: &InboundMessage) -> bool {
+    let (access, ttl_secs) = {
+        let config = state.config.read().await;
+        let channels = config.channels.as_ref();
+        (
+            channels.and_then(|c| c.sender_access(channel_id)).cloned(),
+            channels.map_or_else(|| PairingConfig::default().ttl_secs, |c| c.pairing.ttl_secs),
+        )
+    };
+    let Some(access) = access.filter(|a| a.is_restricted()) else {
+        return true;
+    };
+    let sender_id = &message.sender.id;
+    if access.is_listed(sender_id) || state.pairing.is_approved(channel_id, sender_id) {
+        return true;
+    }
+    if !access.pairing || message.reaction.is_some() {
+        debug!(channel = channel_id, sender = %sender_id, "Dropping message from unknown sender");
+        return false;
+    }
+
+    match state
+        .pairing
+        .create_request(channel_id, sender_id, message.sender.display_name.clone())
+    {
+        Ok(code) => {
+            info!(channel = channel_id, sender = %sender_id, "Pairing requested by unknown sender");
+            let text = format!(
+                "You are not paired with this assistant yet. Pairing code: {code}\n\
+                 Ask the owner to run `rusty-claw pairing approve {channel_id} {code}` \
+                 within {} minutes.",
+                ttl_secs.div_ceil(60)
+            );
+            send_reply(state, channel_id, message, text).await;
+            notify_pairing_admins(state, channel_id, message, &code, &access.admin_senders).await;
+        }
+        Err(e) => error!(channel = channel_id, %e, "Failed to create pairing request"),
+    }
+    false
+}
+
+/// Where replies to `message` are sent.
+fn reply_target(channel_id: &str, message: &InboundMessage) -> SendTarget {
+    SendTarget {
+        channel: channel_id.to_string(),
+        account_id: message.account_id.clone(),
+        chat_id: message.sender.id.clone(),
+        chat_type: message.chat_type,
+    }
+}
+
+/// Send `text` back to the chat `message` came from.
+async fn send_reply(
+    state: &Arc<GatewayState>,
+    channel_id: &str,
+    message: &InboundMessage,
+    text: String,
+) {
+    let target = reply_target(channel_id, message);
 
-        if let Some(channel) = state.channels.get(channel_id) {
-            match channel.send(&target, outbound).await {
-                Ok(_) => info!(channel = channel_id, "Response sent"),
-                Err(e) => error!(channel = channel_id, %e, "Failed to send response"),
+    let outbound = OutboundMessage {
+        text: Some(text),
+        media: vec![],
+        reply_to: message.message_id.clone(),
+        thread_id: message.thread_id.clone(),
+        buttons: vec![],
+    };
+
+    // --- Hook: MessageSending (can cancel or rewrite the reply) ---
+    let key = session_key(channel_id, message);
+    let Some(outbound) = run_sending_hooks(&state.hooks, &key, &target, outbound).await else {
+        return;
+    };
+
+    if let Some(channel) = state.channels.get(channel_id) {
+        let delivery = state
+            .config
+            .read()
+            .await
+            .channels
+            .as_ref()
+            .map(|c| c.delivery.clone())
+            .unwrap_or_default();
+        match crate::delivery::deliver(channel, &target, outbound.clone(), &delivery).await {
+            Ok(result) => {
+                info!(channel = channel_id, "Response sent");
+                // --- Hook: MessageSent ---
+                let _ = state
+                    .hooks
+                    .fire(
+                        HookEvent::MessageSent,
+                        hook_ctx(&key),
+                        json!({ "target": target, "message": outbound, "result": result }),
+                    )
+                    .await;
             }
+            Err(e) => error!(channel = channel_id, %e, "Failed to send response"),
         }
     }
+}
 
-    if let Err(e) = result {
-        error!(channel = channel_id, %e, "Agent run failed");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusty_claw_core::types::ChatType;
+    use rusty_claw_plugins::HookResult;
+
+    #[test]
+    fn test_parse_pairing_command() {
+        assert_eq!(
+            parse_pairing_command("/approve ABCD2345"),
+            Some((true, "ABCD2345".into()))
+        );
+        assert_eq!(
+            parse_pairing_command("  /reject abcd 2345 "),
+            Some((false, "abcd2345".into()))
+        );
+        assert_eq!(parse_pairing_command("/approve"), None);
+        assert_eq!(parse_pairing_command("please /approve ABCD2345"), None);
     }
 
-    Ok(())
+    #[tokio::test]
+    async fn test_admin_resolves_pairing_from_chat() {
+        let dir = tempfile::tempdir().unwrap();
+        let config: rusty_claw_core::config::Config = serde_json::from_value(json!({
+            "channels": {
+                "telegram": { "pairing": true, "admin_senders": ["admin"] }
+            }
+        }))
+        .unwrap();
+        let mut state = crate::state::test_state(dir.path());
+        state.config = Arc::new(tokio::sync::RwLock::new(config));
+        let state = Arc::new(state);
+        let from = |sender: &str, text: &str| {
+            let mut message = InboundMessage::from_cli_text(text);
+            message.channel = "telegram".into();
+            message.sender.id = sender.into();
+            message
+        };
+
+        let code = state.pairing.create_request("telegram", "alice", None).unwrap();
+        let command = format!("/approve {code}");
+
+        // Non-admins' commands are ordinary messages
+        assert!(!handle_pairing_admin(&state, "telegram", &from("mallory", &command)).await);
+        assert!(!state.pairing.is_approved("telegram", "alice"));
+
+        assert!(handle_pairing_admin(&state, "telegram", &from("admin", &command)).await);
+        assert!(state.pairing.is_approved("telegram", "alice"));
+
+        // A reaction on the notice rejects
+        let code = state.pairing.create_request("telegram", "bob", None).unwrap();
+        state
+            .pairing_notices
+            .lock()
+            .unwrap()
+            .insert("telegram:42".into(), code);
+        let mut reaction = from("admin", "");
+        reaction.text = None;
+        reaction.reaction = Some(Reaction {
+            emoji: "❌".into(),
+            message_id: "42".into(),
+        });
+        assert!(handle_pairing_admin(&state, "telegram", &reaction).await);
+        assert!(state.pairing.list_pending().is_empty());
+        assert!(!state.pairing.is_approved("telegram", "bob"));
+        assert!(state.pairing_notices.lock().unwrap().is_empty());
+
+        // Ordinary messages are left to the agent
+        assert!(!handle_pairing_admin(&state, "telegram", &from("admin", "hello")).await);
+
+        // Notices for requests resolved elsewhere (e.g. the CLI) are dropped
+        let code = state.pairing.create_request("telegram", "carol", None).unwrap();
+        state
+            .pairing_notices
+            .lock()
+            .unwrap()
+            .insert("telegram:43".into(), code.clone());
+        state.pairing.approve("telegram", &code, "cli").unwrap();
+        reaction.reaction = Some(Reaction {
+            emoji: "✅".into(),
+            message_id: "43".into(),
+        });
+        assert!(!handle_pairing_admin(&state, "telegram", &reaction).await);
+        assert!(state.pairing_notices.lock().unwrap().is_empty());
+
+        // Without configured admins the commands are not handled at all
+        let config = serde_json::from_value(json!({
+            "channels": { "telegram": { "pairing": true, "allowed_senders": ["admin"] } }
+        }))
+        .unwrap();
+        *state.config.write().await = config;
+        let code = state.pairing.create_request("telegram", "dave", None).unwrap();
+        let command = format!("/approve {code}");
+        assert!(!handle_pairing_admin(&state, "telegram", &from("admin", &command)).await);
+        assert!(!state.pairing.is_approved("telegram", "dave"));
+    }
+
+    #[test]
+    fn test_last_user_text() {
+        let mut session = Session::new(SessionKey {
+            channel: "telegram".into(),
+            account_id: "1".into(),
+            chat_type: ChatType::Dm,
+            peer_id: "1".into(),
+            scope: SessionScope::PerSender,
+        });
+        assert_eq!(last_user_text(&session), None);
+
+        let user = |text: &str| TranscriptEntry::User {
+            content: vec![ContentBlock::Text { text: text.into() }],
+            timestamp: chrono::Utc::now(),
+        };
+        session.transcript.push(user("first"));
+        session.transcript.push(user("second"));
+        session.transcript.push(TranscriptEntry::Assistant {
+            content: vec![ContentBlock::Text { text: "answer".into() }],
+            usage: None,
+            thinking: None,
+            thinking_signature: None,
+            thinking_blocks: Vec::new(),
+            timestamp: chrono::Utc::now(),
+        });
+        assert_eq!(last_user_text(&session).as_deref(), Some("second"));
+    }
+
+    fn test_key() -> SessionKey {
+        SessionKey {
+            channel: "signal".into(),
+            account_id: "+15550000000".into(),
+            chat_type: ChatType::Dm,
+            peer_id: "+15551234567".into(),
+            scope: SessionScope::PerSender,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_received_hook_drops_spam() {
+        let hooks = HookRegistry::new();
+        hooks
+            .register(
+                HookEvent::MessageReceived,
+                Box::new(|_ctx, data| {
+                    Box::pin(async move {
+                        if data["text"].as_str().is_some_and(|t| t.contains("free crypto")) {
+                            Ok(HookResult::Cancel("spam".into()))
+                        } else {
+                            Ok(HookResult::Continue)
+                        }
+                    })
+                }),
+            )
+            .await;
+
+        let spam = InboundMessage::from_cli_text("free crypto here");
+        assert!(run_received_hooks(&hooks, &test_key(), spam).await.is_none());
+        let hello = InboundMessage::from_cli_text("hello");
+        let kept = run_received_hooks(&hooks, &test_key(), hello).await.unwrap();
+        assert_eq!(kept.text.as_deref(), Some("hello"));
+    }
+
+    #[tokio::test]
+    async fn test_sending_hook_rewrites_payload() {
+        let hooks = HookRegistry::new();
+        hooks
+            .register(
+                HookEvent::MessageSending,
+                Box::new(|_ctx, mut data| {
+                    Box::pin(async move {
+                        let text = data["message"]["text"].as_str().unwrap_or_default().to_uppercase();
+                        data["message"]["text"] = json!(text);
+                        Ok(HookResult::Modified(data))
+                    })
+                }),
+            )
+            .await;
+
+        let target = SendTarget {
+            channel: "signal".into(),
+            account_id: "+15550000000".into(),
+            chat_id: "+15551234567".into(),
+            chat_type: ChatType::Dm,
+        };
+        let outbound = OutboundMessage {
+            text: Some("done".into()),
+            media: vec![],
+            reply_to: None,
+            thread_id: None,
+            buttons: vec![],
+        };
+        let sent = run_sending_hooks(&hooks, &test_key(), &target, outbound).await.unwrap();
+        assert_eq!(sent.text.as_deref(), Some("DONE"));
+    }
 }
@@ -2,22 +2,28 @@
 
 use std::sync::Arc;
 
-use axum::extract::ws::{Message, WebSocket};
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket};
 use futures::{SinkExt, StreamExt};
 use sha2::{Digest, Sha256};
-use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
-use rusty_claw_core::config::Config;
+use rusty_claw_core::config::{ApiKeyConfig, Config};
 use rusty_claw_core::protocol::{
-    ConnectParams, Features, GatewayFrame, HelloOk, Policy, ServerInfo, Snapshot, StateVersion,
+    AuthParams, ClientInfo, ConnectParams, Features, GatewayFrame, HelloOk, Policy, ServerInfo, Snapshot, StateVersion,
     PROTOCOL_VERSION,
 };
 
-use crate::methods::dispatch_method;
+use crate::login::LoginTokens;
+use crate::events::Replay;
+use crate::methods::{dispatch_method, Caller};
+use crate::outbound::{outbound_channel, Outbound};
 use crate::state::{ConnectionState, GatewayState};
 
+/// Bytes that may wait in a connection's send queue before the client is
+/// dropped as too slow. Advertised to clients in the hello policy.
+pub const MAX_BUFFERED_BYTES: usize = 10_485_760;
+
 /// Determine the auth mode from config.
 fn auth_mode(config: &Config) -> &str {
     config
@@ -29,7 +35,7 @@ fn auth_mode(config: &Config) -> &str {
 }
 
 /// Constant-time string comparison to prevent timing attacks.
-fn constant_time_eq(a: &str, b: &str) -> bool {
+pub(crate) fn constant_time_eq(a: &str, b: &str) -> bool {
     if a.len() != b.len() {
         return false;
     }
@@ -39,9 +45,46 @@ fn constant_time_eq(a: &str, b: &str) -> bool {
         == 0
 }
 
+/// Check `password` against the configured gateway password.
+fn check_password(config: &Config, password: &str) -> Result<(), String> {
+    let expected = config
+        .gateway
+        .as_ref()
+        .and_then(|g| g.auth.as_ref())
+        .and_then(|a| a.resolve_password())
+        .ok_or_else(|| "Server password not configured".to_string())?;
+
+    // Compare SHA-256 hashes
+    let expected_hash = format!("{:x}", Sha256::digest(expected.as_bytes()));
+    let provided_hash = format!("{:x}", Sha256::digest(password.as_bytes()));
+    if constant_time_eq(&provided_hash, &expected_hash) {
+        Ok(())
+    } else {
+        Err("Invalid password".to_string())
+    }
+}
+
+/// The configured API key matching `token`, if any.
+fn find_api_key(config: &Config, token: &str) -> Option<ApiKeyConfig> {
+    let hash = format!("{:x}", Sha256::digest(token.as_bytes()));
+    config
+        .gateway
+        .as_ref()
+        .and_then(|g| g.auth.as_ref())?
+        .keys
+        .iter()
+        .find(|k| constant_time_eq(&k.key_hash.to_ascii_lowercase(), &hash))
+        .cloned()
+}
+
 /// Authenticate a client connection using ConnectParams.
-/// Returns Ok(()) on success, Err(message) on failure.
-fn authenticate(config: &Config, params: &ConnectParams) -> Result<(), String> {
+/// On success returns the API key used, whose scopes then limit the
+/// connection; `None` means unrestricted. Err(message) on failure.
+fn authenticate(
+    config: &Config,
+    login: &LoginTokens,
+    params: &ConnectParams,
+) -> Result<Option<ApiKeyConfig>, String> {
     let mode = auth_mode(config);
     let auth_config = config
         .gateway
@@ -49,48 +92,114 @@ fn authenticate(config: &Config, params: &ConnectParams) -> Result<(), String> {
         .and_then(|g| g.auth.as_ref());
 
     match mode {
-        "none" => Ok(()),
-        "token" => {
-            let expected = auth_config
-                .and_then(|a| a.resolve_token())
-                .ok_or_else(|| "Server token not configured".to_string())?;
-
-            match &params.auth {
-                Some(rusty_claw_core::protocol::AuthParams::Token { token }) => {
-                    if constant_time_eq(token, &expected) {
-                        Ok(())
-                    } else {
-                        Err("Invalid token".to_string())
-                    }
+        "none" => Ok(None),
+        "token" => match &params.auth {
+            Some(rusty_claw_core::protocol::AuthParams::Token { token }) => {
+                // API keys work on their own; the shared token is optional
+                // when `gateway.auth.keys` is set
+                let expected = auth_config.and_then(|a| a.resolve_token());
+                if expected.as_deref().is_some_and(|e| constant_time_eq(token, e))
+                    || login.verify(token).is_ok()
+                {
+                    Ok(None)
+                } else if let Some(key) = find_api_key(config, token) {
+                    Ok(Some(key))
+                } else if expected.is_none() && auth_config.is_none_or(|a| a.keys.is_empty()) {
+                    Err("Server token not configured".to_string())
+                } else {
+                    Err("Invalid token".to_string())
                 }
-                _ => Err("Token authentication required".to_string()),
             }
-        }
-        "password" => {
-            let expected = auth_config
-                .and_then(|a| a.resolve_password())
-                .ok_or_else(|| "Server password not configured".to_string())?;
-
-            match &params.auth {
-                Some(rusty_claw_core::protocol::AuthParams::Password { password }) => {
-                    // Compare SHA-256 hashes
-                    let expected_hash = format!("{:x}", Sha256::digest(expected.as_bytes()));
-                    let provided_hash = format!("{:x}", Sha256::digest(password.as_bytes()));
-                    if constant_time_eq(&provided_hash, &expected_hash) {
-                        Ok(())
-                    } else {
-                        Err("Invalid password".to_string())
-                    }
+            _ => Err("Token authentication required".to_string()),
+        },
+        "password" => match &params.auth {
+            Some(rusty_claw_core::protocol::AuthParams::Password { password }) => {
+                check_password(config, password).map(|_| None)
+            }
+            // A token from a previous `login`, or an API key
+            Some(rusty_claw_core::protocol::AuthParams::Token { token }) => {
+                match login.verify(token) {
+                    Ok(_) => Ok(None),
+                    Err(e) => find_api_key(config, token).map(Some).ok_or(e),
                 }
-                _ => Err("Password authentication required".to_string()),
             }
-        }
+            _ => Err("Password authentication required".to_string()),
+        },
         other => Err(format!("Unknown auth mode: {other}")),
     }
 }
 
+/// Authenticate a bare token sent outside the `/ws` handshake, e.g. as a
+/// `token` query parameter on another socket. Accepts what `authenticate`
+/// accepts as a token: the gateway token, a login token or an API key.
+/// Credentials passed as a `token` query parameter, for endpoints whose
+/// clients cannot send a connect frame or headers (canvas, plugin routes).
+#[derive(Debug, serde::Deserialize)]
+pub struct TokenQuery {
+    pub token: Option<String>,
+}
+
+pub(crate) fn authenticate_token(
+    config: &Config,
+    login: &LoginTokens,
+    token: Option<&str>,
+) -> Result<Option<ApiKeyConfig>, String> {
+    let params = ConnectParams {
+        min_protocol: PROTOCOL_VERSION,
+        max_protocol: PROTOCOL_VERSION,
+        client: ClientInfo {
+            id: "token".into(),
+            display_name: None,
+            version: None,
+            platform: None,
+            device_family: None,
+            mode: None,
+        },
+        caps: Vec::new(),
+        role: None,
+        auth: token.map(|token| AuthParams::Token { token: token.to_string() }),
+        device: None,
+        resume_from_seq: None,
+    };
+    authenticate(config, login, &params)
+}
+
+/// Handle a `login` request made in place of ConnectParams: exchange the
+/// password for a login token. The connection counts as authenticated.
+fn handle_login(
+    config: &Config,
+    login: &LoginTokens,
+    request_id: &str,
+    params: Option<serde_json::Value>,
+) -> Result<GatewayFrame, String> {
+    if auth_mode(config) != "password" {
+        return Err("Login requires password auth mode".to_string());
+    }
+    let password = params
+        .as_ref()
+        .and_then(|p| p.get("password"))
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| "password is required".to_string())?;
+    check_password(config, password)?;
+
+    let issued = login.issue();
+    Ok(GatewayFrame::Response {
+        id: request_id.to_string(),
+        ok: true,
+        payload: Some(serde_json::json!({
+            "token": issued.token,
+            "expires_at": issued.expires_at.to_rfc3339(),
+        })),
+        error: None,
+    })
+}
+
 /// Handle a new WebSocket connection.
-pub async fn handle_ws_connection(state: Arc<GatewayState>, ws: WebSocket) {
+pub async fn handle_ws_connection(
+    state: Arc<GatewayState>,
+    ws: WebSocket,
+    ip: Option<std::net::IpAddr>,
+) {
     let conn_id = Uuid::new_v4().to_string();
     info!(conn_id = %conn_id, "New WebSocket connection");
 
@@ -100,15 +209,18 @@ pub async fn handle_ws_connection(state: Arc<GatewayState>, ws: WebSocket) {
     let (mut ws_tx, mut ws_rx) = ws.split();
 
     // Create event channel for this connection
-    let (event_tx, mut event_rx) = mpsc::unbounded_channel::<String>();
+    let (event_tx, mut event_rx) = outbound_channel(MAX_BUFFERED_BYTES);
+    let overflowed = event_tx.overflowed().clone();
 
     // Read config snapshot for auth
     let config = state.read_config().await;
     let mode = auth_mode(&config).to_string();
     let needs_auth = mode != "none";
 
-    // Register connection (not yet authenticated if auth required)
-    {
+    // Register connection (not yet authenticated if auth required). Once
+    // authenticated, events after `live_from` are queued for it; earlier ones
+    // can only be replayed.
+    let hello_seq = {
         let mut connections = state.connections.write().await;
         connections.insert(
             conn_id.clone(),
@@ -116,11 +228,12 @@ pub async fn handle_ws_connection(state: Arc<GatewayState>, ws: WebSocket) {
                 conn_id: conn_id.clone(),
                 event_tx: event_tx.clone(),
                 authenticated: !needs_auth,
+                api_key: None,
                 voice_session: None,
-                binary_event_tx: None,
             },
         );
-    }
+        state.event_log.lock().unwrap().last_seq()
+    };
 
     // Send HelloOk
     let hello = HelloOk {
@@ -131,44 +244,20 @@ pub async fn handle_ws_connection(state: Arc<GatewayState>, ws: WebSocket) {
             conn_id: conn_id.clone(),
         },
         features: Features {
-            methods: vec![
-                "sessions.list".into(),
-                "sessions.preview".into(),
-                "sessions.delete".into(),
-                "sessions.reset".into(),
-                "sessions.patch".into(),
-                "sessions.compact".into(),
-                "agent".into(),
-                "agent.abort".into(),
-                "agent.status".into(),
-                "wake".into(),
-                "models.list".into(),
-                "channels.status".into(),
-                "channels.login".into(),
-                "channels.logout".into(),
-                "config.get".into(),
-                "config.set".into(),
-                "cron.list".into(),
-                "cron.add".into(),
-                "cron.remove".into(),
-                "skills.list".into(),
-                "skills.get".into(),
-                "talk.config".into(),
-                "talk.start".into(),
-                "talk.stop".into(),
-                "talk.mode".into(),
-                "node.pair.request".into(),
-                "node.pair.approve".into(),
-                "node.invoke".into(),
-                "node.event".into(),
-                "agents.spawn".into(),
-            ],
+            methods: crate::methods::METHODS
+                .iter()
+                .map(|m| m.to_string())
+                .chain(state.plugin_methods.keys().cloned())
+                .collect(),
             events: vec![
                 "agent.event".into(),
                 "session.updated".into(),
                 "canvas.operation".into(),
                 "config.changed".into(),
+                "skills.changed".into(),
                 "audio.delta".into(),
+                "talk.audio".into(),
+                "talk.partial_transcript".into(),
             ],
         },
         snapshot: Snapshot {
@@ -181,10 +270,11 @@ pub async fn handle_ws_connection(state: Arc<GatewayState>, ws: WebSocket) {
                     .load(std::sync::atomic::Ordering::SeqCst),
             },
             auth_mode: mode.clone(),
+            seq: hello_seq,
         },
         policy: Policy {
             max_payload: 1_048_576, // 1MB
-            max_buffered_bytes: 10_485_760,
+            max_buffered_bytes: MAX_BUFFERED_BYTES,
             tick_interval_ms: 30_000,
         },
     };
@@ -192,7 +282,7 @@ pub async fn handle_ws_connection(state: Arc<GatewayState>, ws: WebSocket) {
     let hello_frame = GatewayFrame::Event {
         event: "hello".into(),
         payload: serde_json::to_value(&hello).ok(),
-        seq: Some(0),
+        seq: None,
         state_version: None,
     };
 
@@ -204,34 +294,62 @@ pub async fn handle_ws_connection(state: Arc<GatewayState>, ws: WebSocket) {
     }
 
     // If auth required, wait for ConnectParams as first message
+    let mut api_key = None;
+    let mut live_from = hello_seq;
     if needs_auth {
         let auth_result = tokio::time::timeout(
             std::time::Duration::from_secs(10),
-            wait_for_auth(&config, &mut ws_rx, &conn_id),
+            wait_for_auth(&config, &state.login_tokens, &mut ws_rx, &conn_id),
         )
         .await;
 
         match auth_result {
-            Ok(Ok(())) => {
-                // Mark as authenticated
-                let mut connections = state.connections.write().await;
-                if let Some(conn) = connections.get_mut(&conn_id) {
-                    conn.authenticated = true;
+            Ok(Ok(AuthOutcome { api_key: key, login_response, resume_from_seq })) => {
+                api_key = key;
+                // Mark as authenticated; events from here on are queued
+                // live, filtered by the key's scopes
+                {
+                    let mut connections = state.connections.write().await;
+                    if let Some(conn) = connections.get_mut(&conn_id) {
+                        conn.authenticated = true;
+                        conn.api_key = api_key.clone();
+                    }
+                    live_from = state.event_log.lock().unwrap().last_seq();
+                }
+                // Answer a login request with its token
+                if let Some(response) = login_response
+                    && let Ok(msg) = serde_json::to_string(&response)
+                    && ws_tx.send(Message::Text(msg.into())).await.is_err()
+                {
+                    cleanup_connection(&state, &conn_id).await;
+                    return;
                 }
-                // Send auth success event
+                // Send auth success event, then the events missed since
+                // `resume_from_seq` (or since the hello) ahead of the live
+                // ones already queued
+                let replay = state.event_log.lock().unwrap().replay(
+                    resume_from_seq.unwrap_or(hello_seq),
+                    live_from,
+                    api_key.as_ref(),
+                );
                 let ok_event = GatewayFrame::Event {
                     event: "auth.ok".into(),
-                    payload: None,
-                    seq: Some(1),
+                    payload: resume_from_seq.map(|_| resume_payload(&replay)),
+                    seq: None,
                     state_version: None,
                 };
-                if let Ok(msg) = serde_json::to_string(&ok_event) {
+                let mut frames: Vec<String> = serde_json::to_string(&ok_event).into_iter().collect();
+                frames.extend(replay.events);
+                for msg in frames {
                     if ws_tx.send(Message::Text(msg.into())).await.is_err() {
                         cleanup_connection(&state, &conn_id).await;
                         return;
                     }
                 }
-                info!(conn_id = %conn_id, "Client authenticated");
+                match &api_key {
+                    Some(key) => info!(conn_id = %conn_id, key = %key.name, "Client authenticated with API key"),
+                    None => info!(conn_id = %conn_id, "Client authenticated"),
+                }
             }
             Ok(Err(reason)) => {
                 warn!(conn_id = %conn_id, %reason, "Authentication failed");
@@ -257,44 +375,96 @@ pub async fn handle_ws_connection(state: Arc<GatewayState>, ws: WebSocket) {
         }
     }
 
-    // Create binary event channel for voice audio
-    let (binary_tx, mut binary_rx) = mpsc::unbounded_channel::<Vec<u8>>();
-    {
-        let mut connections = state.connections.write().await;
-        if let Some(conn) = connections.get_mut(&conn_id) {
-            conn.binary_event_tx = Some(binary_tx);
-        }
-    }
-
     // Spawn event sender task (handles both text and binary)
+    let send_overflowed = overflowed.clone();
     let send_task = tokio::spawn(async move {
         loop {
-            tokio::select! {
-                Some(msg) = event_rx.recv() => {
-                    if ws_tx.send(Message::Text(msg.into())).await.is_err() {
-                        break;
-                    }
+            let frame = tokio::select! {
+                frame = event_rx.recv() => frame,
+                _ = send_overflowed.cancelled() => {
+                    let close = CloseFrame {
+                        code: close_code::AGAIN,
+                        reason: "backpressure".into(),
+                    };
+                    let _ = ws_tx.send(Message::Close(Some(close))).await;
+                    break;
                 }
-                Some(data) = binary_rx.recv() => {
-                    if ws_tx.send(Message::Binary(data.into())).await.is_err() {
-                        break;
-                    }
+            };
+            let message = match frame {
+                Some(Outbound::Text(text)) => Message::Text(text.into()),
+                Some(Outbound::Binary(data)) => Message::Binary(data.into()),
+                Some(Outbound::Close { code, reason }) => {
+                    let close = CloseFrame { code, reason: reason.into() };
+                    let _ = ws_tx.send(Message::Close(Some(close))).await;
+                    break;
                 }
-                else => break,
+                None => break,
+            };
+            if ws_tx.send(message).await.is_err() {
+                break;
             }
         }
     });
 
+    // Without auth there is no handshake, so a client resumes with a
+    // `connect` request instead (once, as its first message)
+    let mut can_resume = !needs_auth;
+
     // Main read loop
-    while let Some(msg_result) = ws_rx.next().await {
+    loop {
+        let msg_result = tokio::select! {
+            msg = ws_rx.next() => match msg {
+                Some(msg) => msg,
+                None => break,
+            },
+            _ = overflowed.cancelled() => {
+                warn!(conn_id = %conn_id, "Closing connection: send buffer full");
+                break;
+            }
+        };
         match msg_result {
             Ok(Message::Text(text)) => {
                 let text = text.to_string();
                 match serde_json::from_str::<GatewayFrame>(&text) {
+                    Ok(GatewayFrame::Request { id, method, params }) if method == "connect" && can_resume => {
+                        can_resume = false;
+                        let from = params
+                            .and_then(|p| serde_json::from_value::<ConnectParams>(p).ok())
+                            .and_then(|c| c.resume_from_seq);
+                        let payload = from.map(|from| {
+                            let replay = state
+                                .event_log
+                                .lock()
+                                .unwrap()
+                                .replay(from, live_from, api_key.as_ref());
+                            // Replayed after events already queued; clients
+                            // order by seq
+                            let payload = resume_payload(&replay);
+                            for msg in replay.events {
+                                event_tx.send_text(msg);
+                            }
+                            payload
+                        });
+                        let response = GatewayFrame::Response {
+                            id,
+                            ok: true,
+                            payload: Some(payload.unwrap_or_else(|| serde_json::json!({}))),
+                            error: None,
+                        };
+                        if let Ok(msg) = serde_json::to_string(&response) {
+                            event_tx.send_text(msg);
+                        }
+                    }
                     Ok(GatewayFrame::Request { id, method, params }) => {
-                        let response = dispatch_method(&state, &id, &method, params).await;
+                        can_resume = false;
+                        let caller = Caller {
+                            conn_id: &conn_id,
+                            ip,
+                            api_key: api_key.as_ref(),
+                        };
+                        let response = dispatch_method(&state, &caller, &id, &method, params).await;
                         if let Ok(response_json) = serde_json::to_string(&response) {
-                            let _ = event_tx.send(response_json);
+                            event_tx.send_text(response_json);
                         }
                     }
                     Ok(_) => {
@@ -313,7 +483,7 @@ pub async fn handle_ws_connection(state: Arc<GatewayState>, ws: WebSocket) {
                             }),
                         };
                         if let Ok(msg) = serde_json::to_string(&error_frame) {
-                            let _ = event_tx.send(msg);
+                            event_tx.send_text(msg);
                         }
                     }
                 }
@@ -344,31 +514,69 @@ pub async fn handle_ws_connection(state: Arc<GatewayState>, ws: WebSocket) {
         }
     }
 
-    // Cleanup
-    send_task.abort();
+    // Cleanup, letting the backpressure close frame go out first
+    if overflowed.is_cancelled() {
+        let _ = tokio::time::timeout(std::time::Duration::from_secs(1), send_task).await;
+    } else {
+        send_task.abort();
+    }
     cleanup_connection(&state, &conn_id).await;
     info!(conn_id = %conn_id, "WebSocket connection closed");
 }
 
-/// Wait for the client's ConnectParams message and authenticate.
+/// How a connection authenticated.
+#[derive(Default)]
+struct AuthOutcome {
+    /// API key limiting the connection's methods, if one was used.
+    api_key: Option<ApiKeyConfig>,
+    /// Response to send for a `login` request.
+    login_response: Option<GatewayFrame>,
+    /// Last event seq the client saw before reconnecting.
+    resume_from_seq: Option<u64>,
+}
+
+/// Summary of a resume, sent to the client ahead of the replayed events.
+fn resume_payload(replay: &Replay) -> serde_json::Value {
+    serde_json::json!({
+        "replayed": replay.events.len(),
+        "complete": replay.complete,
+    })
+}
+
+/// Wait for the client's ConnectParams, or a `login` request.
 async fn wait_for_auth(
     config: &Config,
+    login: &LoginTokens,
     ws_rx: &mut futures::stream::SplitStream<WebSocket>,
     conn_id: &str,
-) -> Result<(), String> {
+) -> Result<AuthOutcome, String> {
+    let connected = |api_key, connect: &ConnectParams| AuthOutcome {
+        api_key,
+        login_response: None,
+        resume_from_seq: connect.resume_from_seq,
+    };
     while let Some(msg_result) = ws_rx.next().await {
         match msg_result {
             Ok(Message::Text(text)) => {
                 let text = text.to_string();
-                // Try to parse as a ConnectParams (wrapped in a request or raw)
-                if let Ok(GatewayFrame::Request { params: Some(params), .. }) = serde_json::from_str::<GatewayFrame>(&text) {
-                    if let Ok(connect) = serde_json::from_value::<ConnectParams>(params) {
-                        return authenticate(config, &connect);
+                match serde_json::from_str::<GatewayFrame>(&text) {
+                    Ok(GatewayFrame::Request { id, method, params }) if method == "login" => {
+                        return handle_login(config, login, &id, params).map(|response| AuthOutcome {
+                            login_response: Some(response),
+                            ..Default::default()
+                        });
                     }
+                    // Try to parse as a ConnectParams (wrapped in a request or raw)
+                    Ok(GatewayFrame::Request { params: Some(params), .. }) => {
+                        if let Ok(connect) = serde_json::from_value::<ConnectParams>(params) {
+                            return authenticate(config, login, &connect).map(|key| connected(key, &connect));
+                        }
+                    }
+                    _ => {}
                 }
                 // Also try direct ConnectParams parse
                 if let Ok(connect) = serde_json::from_str::<ConnectParams>(&text) {
-                    return authenticate(config, &connect);
+                    return authenticate(config, login, &connect).map(|key| connected(key, &connect));
                 }
                 debug!(conn_id = %conn_id, "Received non-auth message during handshake");
                 return Err("Expected ConnectParams for authentication".to_string());
@@ -384,6 +592,7 @@ async fn wait_for_auth(
 async fn cleanup_connection(state: &Arc<GatewayState>, conn_id: &str) {
     let mut connections = state.connections.write().await;
     connections.remove(conn_id);
+    state.request_limiter.forget_connection(conn_id);
 
     #[cfg(feature = "metrics")]
     crate::metrics::record_ws_disconnect();
@@ -393,7 +602,6 @@ async fn cleanup_connection(state: &Arc<GatewayState>, conn_id: &str) {
 mod tests {
     use super::*;
     use rusty_claw_core::config::{GatewayAuthConfig, GatewayConfig};
-    use rusty_claw_core::protocol::{AuthParams, ClientInfo, ConnectParams};
 
     fn make_config_with_auth(mode: &str, token: Option<&str>, password: Option<&str>) -> Config {
         Config {
@@ -406,10 +614,15 @@ mod tests {
                     token_env: None,
                     password: password.map(|s| s.to_string()),
                     password_env: None,
+                    token_secret: None,
+                    token_secret_env: None,
+                    login_ttl_secs: None,
+                    keys: vec![],
                 }),
                 tls: None,
                 rate_limit: None,
                 tailscale: None,
+                shutdown_grace_secs: None,
             }),
             ..Default::default()
         }
@@ -431,6 +644,7 @@ mod tests {
             role: None,
             auth,
             device: None,
+            resume_from_seq: None,
         }
     }
 
@@ -438,7 +652,7 @@ mod tests {
     fn test_auth_mode_none() {
         let config = make_config_with_auth("none", None, None);
         let params = make_connect_params(None);
-        assert!(authenticate(&config, &params).is_ok());
+        assert!(authenticate(&config, &LoginTokens::new(None, 60), &params).is_ok());
     }
 
     #[test]
@@ -447,7 +661,7 @@ mod tests {
         let params = make_connect_params(Some(AuthParams::Token {
             token: "secret-token".into(),
         }));
-        assert!(authenticate(&config, &params).is_ok());
+        assert!(authenticate(&config, &LoginTokens::new(None, 60), &params).is_ok());
     }
 
     #[test]
@@ -456,14 +670,14 @@ mod tests {
         let params = make_connect_params(Some(AuthParams::Token {
             token: "wrong-token".into(),
         }));
-        assert!(authenticate(&config, &params).is_err());
+        assert!(authenticate(&config, &LoginTokens::new(None, 60), &params).is_err());
     }
 
     #[test]
     fn test_auth_token_missing() {
         let config = make_config_with_auth("token", Some("secret-token"), None);
         let params = make_connect_params(None);
-        assert!(authenticate(&config, &params).is_err());
+        assert!(authenticate(&config, &LoginTokens::new(None, 60), &params).is_err());
     }
 
     #[test]
@@ -472,7 +686,7 @@ mod tests {
         let params = make_connect_params(Some(AuthParams::Password {
             password: "my-password".into(),
         }));
-        assert!(authenticate(&config, &params).is_ok());
+        assert!(authenticate(&config, &LoginTokens::new(None, 60), &params).is_ok());
     }
 
     #[test]
@@ -481,7 +695,75 @@ mod tests {
         let params = make_connect_params(Some(AuthParams::Password {
             password: "wrong".into(),
         }));
-        assert!(authenticate(&config, &params).is_err());
+        assert!(authenticate(&config, &LoginTokens::new(None, 60), &params).is_err());
+    }
+
+    #[test]
+    fn test_login_token_replaces_password() {
+        let config = make_config_with_auth("password", None, Some("my-password"));
+        let login = LoginTokens::new(None, 60);
+
+        assert!(handle_login(&config, &login, "1", Some(serde_json::json!({"password": "wrong"}))).is_err());
+        let response = handle_login(&config, &login, "1", Some(serde_json::json!({"password": "my-password"})))
+            .unwrap();
+        let GatewayFrame::Response { payload: Some(payload), .. } = response else {
+            panic!("expected response payload");
+        };
+        let token = payload["token"].as_str().unwrap().to_string();
+
+        let params = make_connect_params(Some(AuthParams::Token { token: token.clone() }));
+        assert_eq!(authenticate(&config, &login, &params).map(|k| k.is_none()), Ok(true));
+
+        // Logged out tokens no longer authenticate
+        assert!(login.revoke(&token));
+        assert!(authenticate(&config, &login, &params).is_err());
+    }
+
+    #[test]
+    fn test_auth_api_key_scoped() {
+        let mut config = make_config_with_auth("token", Some("shared"), None);
+        config.gateway.as_mut().unwrap().auth.as_mut().unwrap().keys = vec![ApiKeyConfig {
+            name: "reader".into(),
+            key_hash: format!("{:x}", Sha256::digest(b"reader-key")),
+            scopes: vec!["sessions.list".into()],
+        }];
+        let login = LoginTokens::new(None, 60);
+
+        let params = make_connect_params(Some(AuthParams::Token { token: "reader-key".into() }));
+        let key = authenticate(&config, &login, &params).unwrap().unwrap();
+        assert_eq!(key.name, "reader");
+        assert!(key.allows("sessions.list"));
+        assert!(!key.allows("config.set"));
+
+        // The shared token stays unrestricted
+        let params = make_connect_params(Some(AuthParams::Token { token: "shared".into() }));
+        assert!(authenticate(&config, &login, &params).unwrap().is_none());
+
+        let params = make_connect_params(Some(AuthParams::Token { token: "other".into() }));
+        assert!(authenticate(&config, &login, &params).is_err());
+    }
+
+    #[test]
+    fn test_auth_api_keys_without_shared_token() {
+        let mut config = make_config_with_auth("token", None, None);
+        config.gateway.as_mut().unwrap().auth.as_mut().unwrap().keys = vec![ApiKeyConfig {
+            name: "reader".into(),
+            key_hash: format!("{:x}", Sha256::digest(b"reader-key")),
+            scopes: vec!["sessions.list".into()],
+        }];
+        let login = LoginTokens::new(None, 60);
+
+        let params = make_connect_params(Some(AuthParams::Token { token: "reader-key".into() }));
+        let key = authenticate(&config, &login, &params).unwrap().unwrap();
+        assert_eq!(key.name, "reader");
+
+        let params = make_connect_params(Some(AuthParams::Token { token: "other".into() }));
+        assert_eq!(authenticate(&config, &login, &params).unwrap_err(), "Invalid token");
+
+        // With neither a shared token nor keys nothing can authenticate
+        config.gateway.as_mut().unwrap().auth.as_mut().unwrap().keys.clear();
+        let params = make_connect_params(Some(AuthParams::Token { token: "reader-key".into() }));
+        assert_eq!(authenticate(&config, &login, &params).unwrap_err(), "Server token not configured");
     }
 
     #[test]
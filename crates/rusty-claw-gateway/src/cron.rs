@@ -1,25 +1,81 @@
 //! Cron scheduler — runs scheduled agent tasks.
 //!
 //! Uses `croner` for cron expression parsing and a background tokio task
-//! that checks job schedules every 30 seconds.
+//! that checks job schedules every 30 seconds. The outcome of each run is
+//! kept in a per-job history, persisted to disk when a path is configured.
 
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use chrono::Utc;
-use croner::Cron;
+use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 
-use rusty_claw_core::config::CronJob;
+use rusty_claw_core::config::{parse_cron_schedule, next_cron_run, CronJob};
 use rusty_claw_core::types::InboundMessage;
 
 use crate::state::GatewayState;
 
+/// Runs kept per job in the history.
+pub const MAX_RUNS_PER_JOB: usize = 20;
+
+/// Maximum characters of result or error text kept per run.
+const SNIPPET_CHARS: usize = 200;
+
+/// How often job schedules are checked.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Scheduler state, as reported by `/health`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CronStatus {
+    /// Whether the background loop has been started.
+    pub running: bool,
+    pub jobs: usize,
+    pub enabled_jobs: usize,
+    /// When due jobs were last checked (and run).
+    pub last_check: chrono::DateTime<Utc>,
+}
+
+/// The outcome of one cron job run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CronRun {
+    pub started_at: chrono::DateTime<Utc>,
+    pub duration_ms: u64,
+    pub success: bool,
+    /// Start of the reply, or the error on failure.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+}
+
+/// Recent runs per job ID, newest last.
+pub type CronHistory = HashMap<String, Vec<CronRun>>;
+
+/// Default location of the persisted run history.
+pub fn default_history_path() -> PathBuf {
+    rusty_claw_core::config::data_dir().join("cron_history.json")
+}
+
+/// Load run history from `path`, empty if missing or unreadable.
+pub fn load_history(path: &Path) -> CronHistory {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
 /// A running cron scheduler.
 pub struct CronScheduler {
     jobs: Arc<RwLock<Vec<CronJob>>>,
     /// Timestamp of the last check, to avoid re-triggering.
     last_check: Arc<RwLock<chrono::DateTime<Utc>>>,
+    history: RwLock<CronHistory>,
+    /// Where the history is persisted; in memory only when unset.
+    history_path: Option<PathBuf>,
+    started: AtomicBool,
 }
 
 impl CronScheduler {
@@ -28,15 +84,26 @@ impl CronScheduler {
         Self {
             jobs: Arc::new(RwLock::new(jobs)),
             last_check: Arc::new(RwLock::new(Utc::now())),
+            history: RwLock::new(CronHistory::new()),
+            history_path: None,
+            started: AtomicBool::new(false),
         }
     }
 
+    /// Persist run history at `path`, picking up runs already recorded there.
+    pub fn with_history_path(mut self, path: PathBuf) -> Self {
+        self.history = RwLock::new(load_history(&path));
+        self.history_path = Some(path);
+        self
+    }
+
     /// Start the background scheduler loop.
     pub fn start(self: Arc<Self>, state: Arc<GatewayState>) {
+        self.started.store(true, Ordering::SeqCst);
         let scheduler = self.clone();
         tokio::spawn(async move {
             info!("Cron scheduler started");
-            let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
             loop {
                 interval.tick().await;
                 scheduler.check_and_run(&state).await;
@@ -46,6 +113,9 @@ impl CronScheduler {
 
     /// Check which jobs are due and execute them.
     async fn check_and_run(&self, state: &Arc<GatewayState>) {
+        if state.shutdown.is_cancelled() {
+            return;
+        }
         let now = Utc::now();
         let last = *self.last_check.read().await;
 
@@ -55,7 +125,7 @@ impl CronScheduler {
                 continue;
             }
 
-            let cron = match Cron::new(&job.schedule).parse() {
+            let cron = match parse_cron_schedule(&job.schedule) {
                 Ok(c) => c,
                 Err(e) => {
                     warn!(job_id = %job.id, %e, "Invalid cron expression");
@@ -90,16 +160,20 @@ impl CronScheduler {
             scope: rusty_claw_core::session::SessionScope::PerSender,
         };
 
-        let mut session = match state.sessions.load(&key).await {
-            Ok(Some(s)) => s,
-            Ok(None) => rusty_claw_core::session::Session::new(key.clone()),
+        let (mut session, is_new) = match state.sessions.load(&key).await {
+            Ok(Some(s)) => (s, false),
+            Ok(None) => (rusty_claw_core::session::Session::new(key.clone()), true),
             Err(e) => {
                 error!(job_id = %job.id, %e, "Failed to load cron session");
                 return;
             }
         };
+        crate::session_hooks::fire_session_start(&state.hooks, &key, is_new).await;
 
-        let (provider, credentials) = match state.providers.default() {
+        let (_, provider, credentials) = match state
+            .providers
+            .resolve(session.meta.provider_id.as_deref(), session.meta.model.as_deref())
+        {
             Some(pc) => pc,
             None => {
                 error!("No default provider for cron job");
@@ -112,7 +186,11 @@ impl CronScheduler {
         // Read config snapshot
         let config = std::sync::Arc::new(state.read_config().await);
 
-        match rusty_claw_agent::run_agent(
+        let started_at = Utc::now();
+        let timer = std::time::Instant::now();
+        #[cfg(feature = "metrics")]
+        let provider_id = provider.id().to_string();
+        let result = rusty_claw_agent::run_agent(
             &mut session,
             message,
             &config,
@@ -122,18 +200,39 @@ impl CronScheduler {
             event_tx,
             &state.hooks,
         )
-        .await
-        {
+        .await;
+        #[cfg(feature = "metrics")]
+        crate::metrics::record_agent_run(&provider_id, &result);
+        let outcome = match result {
             Ok(result) => {
                 debug!(job_id = %job.id, "Cron job completed");
-                if let Some(err) = &result.meta.error {
-                    warn!(job_id = %job.id, error = %err.message, "Cron job had error");
+                match &result.meta.error {
+                    Some(err) => {
+                        warn!(job_id = %job.id, error = %err.message, "Cron job had error");
+                        Err(err.message.clone())
+                    }
+                    None => Ok(result.final_text().map(String::from)),
                 }
             }
             Err(e) => {
                 error!(job_id = %job.id, %e, "Cron job failed");
+                Err(e.to_string())
             }
-        }
+        };
+        let (success, text) = match outcome {
+            Ok(text) => (true, text),
+            Err(e) => (false, Some(e)),
+        };
+        self.record_run(
+            &job.id,
+            CronRun {
+                started_at,
+                duration_ms: timer.elapsed().as_millis() as u64,
+                success,
+                snippet: text.map(|t| t.chars().take(SNIPPET_CHARS).collect()),
+            },
+        )
+        .await;
 
         // Save session
         if let Err(e) = state.sessions.save(&session).await {
@@ -141,19 +240,16 @@ impl CronScheduler {
         }
     }
 
-    /// Add a new job.
-    pub async fn add_job(&self, job: CronJob) -> Result<(), String> {
-        // Validate cron expression
-        Cron::new(&job.schedule)
-            .parse()
-            .map_err(|e| format!("Invalid cron expression: {e}"))?;
+    /// Add a new job, returning when it will next run.
+    pub async fn add_job(&self, job: CronJob) -> Result<chrono::DateTime<Utc>, String> {
+        let next_run = validate_schedule(&job.schedule)?;
 
         let mut jobs = self.jobs.write().await;
         if jobs.iter().any(|j| j.id == job.id) {
             return Err(format!("Job '{}' already exists", job.id));
         }
         jobs.push(job);
-        Ok(())
+        Ok(next_run)
     }
 
     /// Remove a job by ID.
@@ -168,6 +264,53 @@ impl CronScheduler {
     pub async fn list_jobs(&self) -> Vec<CronJob> {
         self.jobs.read().await.clone()
     }
+
+    /// Current scheduler state.
+    pub async fn status(&self) -> CronStatus {
+        let jobs = self.jobs.read().await;
+        CronStatus {
+            running: self.started.load(Ordering::SeqCst),
+            jobs: jobs.len(),
+            enabled_jobs: jobs.iter().filter(|j| j.enabled).count(),
+            last_check: *self.last_check.read().await,
+        }
+    }
+
+    /// Recent runs of every job.
+    pub async fn history(&self) -> CronHistory {
+        self.history.read().await.clone()
+    }
+
+    /// Append a run to the job's history, dropping the oldest beyond
+    /// [`MAX_RUNS_PER_JOB`], and persist it.
+    async fn record_run(&self, job_id: &str, run: CronRun) {
+        let mut history = self.history.write().await;
+        let runs = history.entry(job_id.to_string()).or_default();
+        runs.push(run);
+        if runs.len() > MAX_RUNS_PER_JOB {
+            runs.drain(..runs.len() - MAX_RUNS_PER_JOB);
+        }
+
+        let Some(path) = &self.history_path else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = tokio::fs::create_dir_all(parent).await;
+        }
+        let result = match serde_json::to_string_pretty(&*history) {
+            Ok(json) => tokio::fs::write(path, json).await.map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = result {
+            warn!(%e, path = %path.display(), "Failed to persist cron history");
+        }
+    }
+}
+
+/// Check that `schedule` is a cron expression the scheduler can run,
+/// returning its next fire time.
+pub fn validate_schedule(schedule: &str) -> Result<chrono::DateTime<Utc>, String> {
+    next_cron_run(schedule, Utc::now())
 }
 
 #[cfg(test)]
@@ -177,11 +320,11 @@ mod tests {
     #[test]
     fn test_cron_expression_parsing() {
         // Valid expression
-        let result = Cron::new("0 9 * * *").parse();
+        let result = parse_cron_schedule("0 9 * * *");
         assert!(result.is_ok());
 
         // Invalid expression
-        let result = Cron::new("invalid").parse();
+        let result = parse_cron_schedule("invalid");
         assert!(result.is_err());
     }
 
@@ -220,6 +363,31 @@ mod tests {
         assert!(!scheduler.remove_job("nonexistent").await);
     }
 
+    #[tokio::test]
+    async fn test_run_history_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        let scheduler = CronScheduler::new(vec![]).with_history_path(path.clone());
+
+        for i in 0..MAX_RUNS_PER_JOB + 2 {
+            let run = CronRun {
+                started_at: Utc::now(),
+                duration_ms: i as u64,
+                success: i % 2 == 0,
+                snippet: Some("x".repeat(10)),
+            };
+            scheduler.record_run("daily", run).await;
+        }
+
+        let runs = &scheduler.history().await["daily"];
+        assert_eq!(runs.len(), MAX_RUNS_PER_JOB);
+        assert_eq!(runs[0].duration_ms, 2);
+
+        // A new scheduler picks up the persisted runs
+        let reloaded = CronScheduler::new(vec![]).with_history_path(path);
+        assert_eq!(reloaded.history().await["daily"].len(), MAX_RUNS_PER_JOB);
+    }
+
     #[tokio::test]
     async fn test_invalid_cron_expression() {
         let scheduler = CronScheduler::new(vec![]);
@@ -0,0 +1,204 @@
+//! Outbound delivery with retries.
+//!
+//! A failed `Channel::send` is retried with exponential backoff. When every
+//! attempt fails the message is appended to the dead-letter log (one JSON
+//! object per line) so replies from unattended runs such as cron jobs are not
+//! silently lost.
+
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tracing::{error, warn};
+
+use rusty_claw_channels::Channel;
+use rusty_claw_core::config::DeliveryConfig;
+use rusty_claw_core::types::{OutboundMessage, SendResult, SendTarget};
+
+/// An undelivered message as written to the dead-letter log.
+#[derive(Debug, Serialize)]
+struct DeadLetter<'a> {
+    timestamp: chrono::DateTime<chrono::Utc>,
+    target: &'a SendTarget,
+    message: &'a OutboundMessage,
+    error: String,
+    attempts: u32,
+}
+
+/// Send `message`, retrying failures per `config`. On final failure the
+/// message is dead-lettered and the last error returned.
+pub async fn deliver(
+    channel: &dyn Channel,
+    target: &SendTarget,
+    message: OutboundMessage,
+    config: &DeliveryConfig,
+) -> anyhow::Result<SendResult> {
+    let mut backoff = Duration::from_millis(config.initial_backoff_ms);
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        // Channels report some platform errors as an unsuccessful result
+        let err = match channel.send(target, message.clone()).await {
+            Ok(result) if result.success => {
+                #[cfg(feature = "metrics")]
+                crate::metrics::record_channel_send(&target.channel, true);
+                return Ok(result);
+            }
+            Ok(result) => anyhow::anyhow!(result.error.unwrap_or_else(|| "send failed".into())),
+            Err(e) => e,
+        };
+        if attempt > config.max_retries {
+            #[cfg(feature = "metrics")]
+            crate::metrics::record_channel_send(&target.channel, false);
+            let entry = DeadLetter {
+                timestamp: chrono::Utc::now(),
+                target,
+                message: &message,
+                error: format!("{err:#}"),
+                attempts: attempt,
+            };
+            let path = config.dead_letter_path();
+            if let Err(e) = append_dead_letter(&path, &entry).await {
+                error!(path = %path.display(), %e, "Failed to write dead letter");
+            }
+            return Err(err);
+        }
+        warn!(
+            channel = %target.channel,
+            attempt,
+            retry_in = ?backoff,
+            error = %err,
+            "Send failed, retrying"
+        );
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+    }
+}
+
+async fn append_dead_letter(path: &Path, entry: &DeadLetter<'_>) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    file.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use async_trait::async_trait;
+    use rusty_claw_channels::{ChannelCapabilities, ChannelHandle, ChannelMeta, ChannelStatus, InboundReceiver};
+    use rusty_claw_core::types::ChatType;
+
+    use super::*;
+
+    /// Fails the first `failures` sends.
+    struct FlakyChannel {
+        failures: u32,
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl Channel for FlakyChannel {
+        fn id(&self) -> &str {
+            "flaky"
+        }
+
+        fn meta(&self) -> ChannelMeta {
+            unimplemented!()
+        }
+
+        fn capabilities(&self) -> ChannelCapabilities {
+            unimplemented!()
+        }
+
+        async fn start(
+            &self,
+            _config: &serde_json::Value,
+        ) -> anyhow::Result<(InboundReceiver, ChannelHandle)> {
+            unimplemented!()
+        }
+
+        async fn send(&self, _target: &SendTarget, _message: OutboundMessage) -> anyhow::Result<SendResult> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            if call < self.failures {
+                anyhow::bail!("connection reset");
+            }
+            Ok(SendResult {
+                message_id: Some("ok".into()),
+                message_ids: vec![],
+                success: true,
+                error: None,
+            })
+        }
+
+        async fn status(&self) -> ChannelStatus {
+            unimplemented!()
+        }
+    }
+
+    fn target() -> SendTarget {
+        SendTarget {
+            channel: "flaky".into(),
+            account_id: "acct".into(),
+            chat_id: "chat".into(),
+            chat_type: ChatType::Dm,
+        }
+    }
+
+    fn message() -> OutboundMessage {
+        OutboundMessage {
+            text: Some("Daily report".into()),
+            media: vec![],
+            reply_to: None,
+            thread_id: None,
+            buttons: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_delivered() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = DeliveryConfig {
+            max_retries: 2,
+            initial_backoff_ms: 1,
+            dead_letter_path: Some(dir.path().join("dead.jsonl")),
+        };
+        let channel = FlakyChannel { failures: 2, calls: AtomicU32::new(0) };
+        let result = deliver(&channel, &target(), message(), &config).await.unwrap();
+        assert_eq!(result.message_id.as_deref(), Some("ok"));
+        assert_eq!(channel.calls.load(Ordering::SeqCst), 3);
+        assert!(!dir.path().join("dead.jsonl").exists());
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_after_final_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logs").join("dead.jsonl");
+        let config = DeliveryConfig {
+            max_retries: 1,
+            initial_backoff_ms: 1,
+            dead_letter_path: Some(path.clone()),
+        };
+        let channel = FlakyChannel { failures: u32::MAX, calls: AtomicU32::new(0) };
+        assert!(deliver(&channel, &target(), message(), &config).await.is_err());
+        assert_eq!(channel.calls.load(Ordering::SeqCst), 2);
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        let entry: serde_json::Value = serde_json::from_str(log.trim()).unwrap();
+        assert_eq!(entry["target"]["chat_id"], "chat");
+        assert_eq!(entry["message"]["text"], "Daily report");
+        assert_eq!(entry["error"], "connection reset");
+        assert_eq!(entry["attempts"], 2);
+    }
+}
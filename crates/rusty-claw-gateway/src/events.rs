@@ -1,39 +1,205 @@
 //! Event broadcasting to all connected WebSocket clients.
+//!
+//! Every broadcast event gets a gateway-wide, monotonic `seq`. The last
+//! [`EVENT_REPLAY_CAPACITY`] events are kept in an [`EventLog`] so a client
+//! reconnecting with `resume_from_seq` can be sent what it missed.
+//!
+//! A connection authenticated with an API key only receives the events its
+//! scopes cover; see [`receives`].
 
+use std::collections::VecDeque;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
+use rusty_claw_core::config::ApiKeyConfig;
 use rusty_claw_core::protocol::{GatewayFrame, StateVersion};
 use tracing::debug;
 
 use crate::state::GatewayState;
 
-/// Broadcast an event to all connected clients.
-pub async fn broadcast_event(state: &Arc<GatewayState>, event: &str, payload: Option<serde_json::Value>) {
-    let frame = GatewayFrame::Event {
-        event: event.to_string(),
-        payload,
-        seq: None,
-        state_version: Some(StateVersion {
-            presence: state.state_version.load(Ordering::SeqCst),
-            health: state.health_version.load(Ordering::SeqCst),
-        }),
-    };
+/// Number of recent events kept for replay.
+pub const EVENT_REPLAY_CAPACITY: usize = 1000;
+
+/// Recently broadcast events, serialized, with their sequence numbers and
+/// names.
+pub struct EventLog {
+    last_seq: u64,
+    events: VecDeque<(u64, String, String)>,
+    capacity: usize,
+}
+
+/// Events to replay to a resuming client.
+pub struct Replay {
+    pub events: Vec<String>,
+    /// False if some missed events are no longer buffered (or the client's
+    /// seq is from before a gateway restart), so the client should refetch
+    /// its state.
+    pub complete: bool,
+}
 
-    let msg = match serde_json::to_string(&frame) {
-        Ok(m) => m,
-        Err(e) => {
-            tracing::error!(%e, "Failed to serialize event");
-            return;
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            last_seq: 0,
+            events: VecDeque::new(),
+            capacity,
         }
-    };
+    }
 
+    /// Sequence number of the most recent event, 0 if none yet.
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+
+    /// Assign the next sequence number to an event serialized by `build`
+    /// and keep it. Nothing is assigned if serialization fails.
+    fn record(&mut self, event: &str, build: impl FnOnce(u64) -> Option<String>) -> Option<String> {
+        let seq = self.last_seq + 1;
+        let msg = build(seq)?;
+        self.last_seq = seq;
+        if self.events.len() >= self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back((seq, event.to_string(), msg.clone()));
+        Some(msg)
+    }
+
+    /// Events after `from` up to and including `until` that a connection
+    /// authenticated with `api_key` receives.
+    pub fn replay(&self, from: u64, until: u64, api_key: Option<&ApiKeyConfig>) -> Replay {
+        let events = self
+            .events
+            .iter()
+            .filter(|(seq, event, _)| *seq > from && *seq <= until && receives(api_key, event))
+            .map(|(_, _, msg)| msg.clone())
+            .collect();
+        let oldest = self.events.front().map_or(self.last_seq + 1, |(seq, _, _)| *seq);
+        Replay {
+            events,
+            complete: from <= until && (from == until || oldest <= from + 1),
+        }
+    }
+}
+
+/// The method scope an API key needs to receive `event`: the method that
+/// reads what the event reports. Other events need their own name.
+fn event_scope(event: &str) -> &str {
+    match event {
+        "agent.event" => "agent",
+        "child.completed" => "agents.spawn",
+        "config.changed" => "config.get",
+        "skills.changed" => "skills.list",
+        other => other,
+    }
+}
+
+/// Whether a connection authenticated with `api_key` receives `event`.
+/// Connections without a key (shared token, password, or no auth) receive
+/// every event.
+pub fn receives(api_key: Option<&ApiKeyConfig>, event: &str) -> bool {
+    api_key.is_none_or(|key| key.allows(event_scope(event)))
+}
+
+/// Broadcast an event to all authenticated clients whose scopes cover it.
+pub async fn broadcast_event(state: &Arc<GatewayState>, event: &str, payload: Option<serde_json::Value>) {
+    // Hold the connections lock while numbering so a connection registered
+    // concurrently either receives an event live or can replay it, never
+    // neither
     let connections = state.connections.read().await;
+    let mut log = state.event_log.lock().unwrap();
+    let msg = log.record(event, |seq| {
+        let frame = GatewayFrame::Event {
+            event: event.to_string(),
+            payload,
+            seq: Some(seq),
+            state_version: Some(StateVersion {
+                presence: state.state_version.load(Ordering::SeqCst),
+                health: state.health_version.load(Ordering::SeqCst),
+            }),
+        };
+        serde_json::to_string(&frame)
+            .map_err(|e| tracing::error!(%e, "Failed to serialize event"))
+            .ok()
+    });
+    let Some(msg) = msg else {
+        return;
+    };
+
+    // Sends never block: a client too far behind is skipped and dropped by
+    // its connection instead of holding up everyone else
     let mut sent = 0;
-    for conn in connections.values() {
-        if conn.event_tx.send(msg.clone()).is_ok() {
+    let mut lagging = 0;
+    let recipients = connections
+        .values()
+        .filter(|conn| conn.authenticated && receives(conn.api_key.as_ref(), event));
+    for conn in recipients {
+        if conn.event_tx.send_text(msg.clone()) {
             sent += 1;
+        } else if conn.event_tx.overflowed().is_cancelled() {
+            lagging += 1;
+        }
+    }
+    debug!(event, sent, lagging, "Broadcast event");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(log: &mut EventLog) -> u64 {
+        log.record("agent.event", |seq| Some(seq.to_string()));
+        log.last_seq()
+    }
+
+    #[test]
+    fn test_event_log_replay() {
+        let mut log = EventLog::new(3);
+        for _ in 0..5 {
+            record(&mut log);
+        }
+        assert_eq!(log.last_seq(), 5);
+
+        // Events 3..=5 are still buffered
+        let replay = log.replay(2, 5, None);
+        assert_eq!(replay.events, ["3", "4", "5"]);
+        assert!(replay.complete);
+
+        // Only up to the point the connection started receiving live events
+        assert_eq!(log.replay(3, 4, None).events, ["4"]);
+
+        // Event 2 was evicted
+        let replay = log.replay(1, 5, None);
+        assert_eq!(replay.events.len(), 3);
+        assert!(!replay.complete);
+
+        // Nothing missed
+        let replay = log.replay(5, 5, None);
+        assert!(replay.events.is_empty() && replay.complete);
+
+        // A seq from before a restart cannot be resumed
+        assert!(!log.replay(9, 5, None).complete);
+    }
+
+    #[test]
+    fn test_replay_filtered_by_api_key_scopes() {
+        let mut log = EventLog::new(10);
+        for event in ["agent.event", "config.changed", "child.completed", "skills.changed"] {
+            log.record(event, |_| Some(event.to_string()));
         }
+        let key = |scopes: &[&str]| ApiKeyConfig {
+            name: "test".into(),
+            key_hash: String::new(),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+        };
+
+        assert_eq!(log.replay(0, 4, None).events.len(), 4);
+        assert_eq!(log.replay(0, 4, Some(&key(&["*"]))).events.len(), 4);
+        assert_eq!(log.replay(0, 4, Some(&key(&["agent"]))).events, ["agent.event"]);
+        assert_eq!(
+            log.replay(0, 4, Some(&key(&["config.*", "skills.list"]))).events,
+            ["config.changed", "skills.changed"]
+        );
+        let replay = log.replay(0, 4, Some(&key(&["sessions.list"])));
+        assert!(replay.events.is_empty() && replay.complete);
     }
-    debug!(event, sent, "Broadcast event");
 }
@@ -0,0 +1,244 @@
+//! Component health reported by `/health`.
+//!
+//! Checking a provider means a network call, so the report is refreshed on a
+//! timer every [`REFRESH_INTERVAL`] and served from a cache rather than
+//! computed per request. The overall status is `down` when the gateway cannot
+//! serve agent runs at all (the session store is not writable, or no provider
+//! is reachable) and `degraded` when only some component is unhealthy.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use rusty_claw_channels::ChannelStatus;
+
+use crate::cron::CronStatus;
+use crate::state::GatewayState;
+
+/// How often the cached report is refreshed.
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Time allowed for one provider to answer.
+const PROVIDER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A scheduler that has not checked its jobs for this long is stuck.
+const CRON_STALL_SECS: i64 = 600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    Degraded,
+    Down,
+}
+
+/// Health of every component, as of `checked_at`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub checked_at: DateTime<Utc>,
+    pub providers: Vec<ProviderHealth>,
+    pub channels: Vec<ChannelHealth>,
+    pub session_store: StoreHealth,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cron: Option<CronHealth>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderHealth {
+    pub id: String,
+    pub reachable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelHealth {
+    pub id: String,
+    #[serde(flatten)]
+    pub status: ChannelStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreHealth {
+    pub writable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CronHealth {
+    #[serde(flatten)]
+    pub status: CronStatus,
+    pub stalled: bool,
+}
+
+impl HealthReport {
+    /// Overall status from the component results.
+    fn overall(&self) -> HealthStatus {
+        let no_provider = !self.providers.is_empty() && !self.providers.iter().any(|p| p.reachable);
+        if !self.session_store.writable || no_provider {
+            return HealthStatus::Down;
+        }
+        let degraded = self.providers.iter().any(|p| !p.reachable)
+            || self.channels.iter().any(|c| !c.status.connected)
+            || self.cron.as_ref().is_some_and(|c| c.stalled);
+        if degraded {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Ok
+        }
+    }
+}
+
+/// Check every component now.
+pub async fn check(state: &Arc<GatewayState>) -> HealthReport {
+    let providers = futures::future::join_all(state.providers.list_ids().into_iter().map(|id| async move {
+        let error = match state.providers.get(id) {
+            Some((provider, credentials)) => {
+                match tokio::time::timeout(PROVIDER_TIMEOUT, provider.list_models(credentials)).await {
+                    Ok(Ok(_)) => None,
+                    Ok(Err(e)) => Some(e.to_string()),
+                    Err(_) => Some("timed out".to_string()),
+                }
+            }
+            None => Some("not registered".to_string()),
+        };
+        ProviderHealth {
+            id: id.to_string(),
+            reachable: error.is_none(),
+            error,
+        }
+    }))
+    .await;
+
+    let mut channels = Vec::new();
+    for id in state.channels.list() {
+        if let Some(channel) = state.channels.get(id) {
+            channels.push(ChannelHealth {
+                id: id.to_string(),
+                status: channel.status().await,
+            });
+        }
+    }
+
+    let session_store = match state.sessions.check_writable().await {
+        Ok(()) => StoreHealth { writable: true, error: None },
+        Err(e) => StoreHealth {
+            writable: false,
+            error: Some(e.to_string()),
+        },
+    };
+
+    let cron = match &state.cron {
+        Some(scheduler) => {
+            let status = scheduler.status().await;
+            let stalled =
+                status.running && (Utc::now() - status.last_check).num_seconds() > CRON_STALL_SECS;
+            Some(CronHealth { status, stalled })
+        }
+        None => None,
+    };
+
+    let mut report = HealthReport {
+        status: HealthStatus::Ok,
+        checked_at: Utc::now(),
+        providers,
+        channels,
+        session_store,
+        cron,
+    };
+    report.status = report.overall();
+    report
+}
+
+/// Re-check and cache the report, bumping the health version when the
+/// overall status changes.
+pub async fn refresh(state: &Arc<GatewayState>) -> HealthReport {
+    let report = check(state).await;
+    let previous = state.health.write().await.replace(report.clone());
+    if previous.is_some_and(|p| p.status != report.status) {
+        match report.status {
+            HealthStatus::Ok => info!("Gateway health recovered"),
+            status => warn!(?status, "Gateway health changed"),
+        }
+        state.bump_health_version();
+    }
+    report
+}
+
+/// The cached report, checking now if there is none yet.
+pub async fn current(state: &Arc<GatewayState>) -> HealthReport {
+    if let Some(report) = state.health.read().await.clone() {
+        return report;
+    }
+    refresh(state).await
+}
+
+/// Refreshes the cached report every [`REFRESH_INTERVAL`] until dropped.
+pub struct HealthMonitor {
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl HealthMonitor {
+    pub fn start(state: Arc<GatewayState>) -> Self {
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+            loop {
+                interval.tick().await;
+                refresh(&state).await;
+            }
+        });
+        Self { task }
+    }
+}
+
+impl Drop for HealthMonitor {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unwritable_store_is_down() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = Arc::new(crate::state::test_state(dir.path()));
+        let report = current(&state).await;
+        assert_eq!(report.status, HealthStatus::Ok);
+        assert!(report.session_store.writable);
+
+        // A file where the sessions directory's parent should be
+        let blocked = dir.path().join("blocked");
+        std::fs::write(&blocked, "").unwrap();
+        let state = Arc::new(crate::state::test_state(&blocked));
+        let report = refresh(&state).await;
+        assert_eq!(report.status, HealthStatus::Down);
+        assert!(report.session_store.error.is_some());
+    }
+
+    #[test]
+    fn test_overall_status() {
+        let mut report = HealthReport {
+            status: HealthStatus::Ok,
+            checked_at: Utc::now(),
+            providers: vec![
+                ProviderHealth { id: "a".into(), reachable: true, error: None },
+                ProviderHealth { id: "b".into(), reachable: false, error: Some("401".into()) },
+            ],
+            channels: vec![],
+            session_store: StoreHealth { writable: true, error: None },
+            cron: None,
+        };
+        assert_eq!(report.overall(), HealthStatus::Degraded);
+
+        report.providers[0].reachable = false;
+        assert_eq!(report.overall(), HealthStatus::Down);
+    }
+}
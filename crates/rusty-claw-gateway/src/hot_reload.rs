@@ -1,16 +1,29 @@
 //! Config hot-reload via filesystem watcher.
 //!
-//! Watches the config file and re-parses on change, broadcasting
-//! `ConfigChange` events via a tokio broadcast channel.
-
-use std::path::PathBuf;
+//! Watches the config file and re-parses it on change. A reload that fails
+//! to parse or validate is logged and the previous config kept; a valid one
+//! is swapped into the shared config and announced as a `ConfigChange` via a
+//! tokio broadcast channel. [`apply_config_changes`] propagates changes to
+//! the gateway: it broadcasts `config.changed` and fires the `ConfigReload`
+//! hook.
+
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use notify::{EventKind, RecursiveMode, Watcher};
-use tokio::sync::{broadcast, RwLock};
+use serde_json::json;
+use tokio::sync::{broadcast, mpsc, RwLock};
 use tracing::{debug, error, info, warn};
 
 use rusty_claw_core::config::Config;
+use rusty_claw_plugins::{HookContext, HookEvent};
+
+use crate::state::GatewayState;
+
+/// Editors often write a file in several steps; changes are coalesced over
+/// this window before reloading.
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(200);
 
 /// A config change event.
 #[derive(Debug, Clone)]
@@ -32,44 +45,30 @@ impl ConfigWatcher {
         config_path: PathBuf,
         initial_config: Config,
     ) -> anyhow::Result<(Self, broadcast::Receiver<ConfigChange>)> {
-        let config = Arc::new(RwLock::new(initial_config));
-        let (change_tx, change_rx) = broadcast::channel(16);
+        Self::start_shared(config_path, Arc::new(RwLock::new(initial_config)))
+    }
 
-        let config_clone = config.clone();
-        let tx_clone = change_tx.clone();
-        let path_clone = config_path.clone();
+    /// Start watching `config_path`, reloading into an existing shared
+    /// config (e.g. [`GatewayState::config`]). Must be called from within a
+    /// tokio runtime.
+    pub fn start_shared(
+        config_path: PathBuf,
+        config: Arc<RwLock<Config>>,
+    ) -> anyhow::Result<(Self, broadcast::Receiver<ConfigChange>)> {
+        let (change_tx, change_rx) = broadcast::channel(16);
+        let (notify_tx, mut notify_rx) = mpsc::unbounded_channel::<()>();
 
+        let file_name = config_path.file_name().map(|n| n.to_os_string());
         let mut watcher =
             notify::recommended_watcher(move |res: Result<notify::Event, notify::Error>| {
                 match res {
                     Ok(event) => {
-                        if matches!(
-                            event.kind,
-                            EventKind::Modify(_) | EventKind::Create(_)
-                        ) {
-                            debug!("Config file changed, reloading");
-                            match Config::load(&path_clone) {
-                                Ok(new_config) => {
-                                    let new_config = Arc::new(new_config);
-                                    // Update the shared config
-                                    let config_ref = config_clone.clone();
-                                    let tx = tx_clone.clone();
-                                    let nc = new_config.clone();
-                                    // We're in a sync callback, use try_write
-                                    if let Ok(mut guard) = config_ref.try_write() {
-                                        *guard = (*nc).clone();
-                                        let _ = tx.send(ConfigChange {
-                                            new_config: nc,
-                                        });
-                                        info!("Config reloaded successfully");
-                                    } else {
-                                        warn!("Could not acquire config write lock during reload");
-                                    }
-                                }
-                                Err(e) => {
-                                    error!(%e, "Failed to reload config");
-                                }
-                            }
+                        let ours = event
+                            .paths
+                            .iter()
+                            .any(|p| p.file_name().map(|n| n.to_os_string()) == file_name);
+                        if ours && matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_)) {
+                            let _ = notify_tx.send(());
                         }
                     }
                     Err(e) => {
@@ -81,12 +80,44 @@ impl ConfigWatcher {
         // Watch the config file's parent directory (to catch renames/recreates)
         let watch_path = config_path
             .parent()
+            .filter(|p| !p.as_os_str().is_empty())
             .map(|p| p.to_path_buf())
             .unwrap_or_else(|| PathBuf::from("."));
 
         watcher.watch(&watch_path, RecursiveMode::NonRecursive)?;
         info!(path = %config_path.display(), "Config file watcher started");
 
+        let shared = config.clone();
+        let tx = change_tx.clone();
+        tokio::spawn(async move {
+            while notify_rx.recv().await.is_some() {
+                tokio::time::sleep(RELOAD_DEBOUNCE).await;
+                while notify_rx.try_recv().is_ok() {}
+
+                debug!("Config file changed, reloading");
+                let new_config = match load_valid_config(&config_path) {
+                    Ok(c) => c,
+                    Err(e) => {
+                        error!(%e, "Rejected config reload, keeping previous config");
+                        continue;
+                    }
+                };
+                {
+                    let mut guard = shared.write().await;
+                    // Our own saves (e.g. `config.set`) come back as file events
+                    if serde_json::to_value(&*guard).ok() == serde_json::to_value(&new_config).ok() {
+                        debug!("Config file unchanged, skipping reload");
+                        continue;
+                    }
+                    *guard = new_config.clone();
+                }
+                info!("Config reloaded successfully");
+                let _ = tx.send(ConfigChange {
+                    new_config: Arc::new(new_config),
+                });
+            }
+        });
+
         Ok((
             Self {
                 config,
@@ -108,6 +139,53 @@ impl ConfigWatcher {
     }
 }
 
+/// Parse and validate the config at `path`.
+fn load_valid_config(path: &Path) -> anyhow::Result<Config> {
+    // A missing file mid-rename would otherwise load as the default config
+    if !path.exists() {
+        anyhow::bail!("config file {} is missing", path.display());
+    }
+    let config = Config::load(path)?;
+    let (warnings, errors) = config.validate();
+    for warning in &warnings {
+        warn!(%warning, "Config warning");
+    }
+    if !errors.is_empty() {
+        anyhow::bail!("invalid config: {}", errors.join("; "));
+    }
+    Ok(config)
+}
+
+/// Propagate reloaded configs to the gateway until the watcher stops.
+pub async fn apply_config_changes(
+    state: Arc<GatewayState>,
+    mut changes: broadcast::Receiver<ConfigChange>,
+) {
+    loop {
+        match changes.recv().await {
+            Ok(_) => {
+                            crate::events::broadcast_event(&state, "config.changed", Some(json!({"source": "file"})))
+                    .await;
+                state.bump_state_version();
+
+                // --- Hook: ConfigReload ---
+                let ctx = HookContext {
+                    session_key: String::new(),
+                    timestamp: chrono::Utc::now(),
+                    metadata: Default::default(),
+                };
+                let path = state.config_path.as_ref().map(|p| p.display().to_string());
+                let _ = state
+                    .hooks
+                    .fire(HookEvent::ConfigReload, ctx, json!({ "path": path }))
+                    .await;
+            }
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -148,4 +226,23 @@ mod tests {
         // Note: On some CI environments the file watcher may not trigger,
         // so we don't assert failure here.
     }
+
+    #[test]
+    fn test_invalid_reload_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.json");
+
+        std::fs::write(&config_path, r#"{ "gateway": { "port": 0 } }"#).unwrap();
+        let err = load_valid_config(&config_path).unwrap_err();
+        assert!(err.to_string().contains("port cannot be 0"));
+
+        std::fs::write(&config_path, r#"{ "gateway": { "port": "#).unwrap();
+        assert!(load_valid_config(&config_path).is_err());
+
+        std::fs::remove_file(&config_path).unwrap();
+        assert!(load_valid_config(&config_path).is_err());
+
+        std::fs::write(&config_path, r#"{ "gateway": { "port": 18790 } }"#).unwrap();
+        assert_eq!(load_valid_config(&config_path).unwrap().gateway_port(), 18790);
+    }
 }
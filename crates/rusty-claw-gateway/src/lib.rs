@@ -8,17 +8,25 @@ pub mod canvas;
 pub mod channel_router;
 pub mod connection;
 pub mod cron;
+pub mod delivery;
 pub mod events;
+pub mod health;
 pub mod hot_reload;
+pub mod login;
 #[cfg(feature = "metrics")]
 pub mod metrics;
 pub mod methods;
 pub mod nodes;
+pub mod outbound;
 pub mod rate_limit;
+pub mod reply_stream;
 pub mod server;
+pub mod session_hooks;
 pub mod skills;
+pub mod spawn;
 pub mod state;
 pub mod tailscale;
+pub mod talk;
 
 pub use cron::CronScheduler;
 pub use hot_reload::ConfigWatcher;
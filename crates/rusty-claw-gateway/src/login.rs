@@ -0,0 +1,135 @@
+//! Login tokens — exchange the gateway password for a signed, expiring token.
+//!
+//! With `auth.mode = "password"` a client sends the password once in a
+//! `login` request during the handshake and receives a token to present on
+//! later connections (as `{"type": "token", "token": ...}`), so the password
+//! need not be kept on the client.
+//!
+//! Tokens have the form `v1.<id>.<expiry>.<signature>`, where the signature
+//! is an HMAC-SHA256 over the rest under a server secret. The secret comes
+//! from `auth.token_secret` when configured; otherwise it is generated at
+//! startup and tokens do not survive a restart. `logout` adds a token's ID to
+//! a revocation list kept until the token would have expired anyway.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+const TOKEN_VERSION: &str = "v1";
+
+/// Issues and verifies login tokens.
+pub struct LoginTokens {
+    secret: Vec<u8>,
+    ttl_secs: u64,
+    /// Revoked token IDs, with the expiry after which they can be forgotten.
+    revoked: Mutex<HashMap<String, i64>>,
+}
+
+/// A freshly issued token.
+pub struct IssuedToken {
+    pub token: String,
+    pub expires_at: chrono::DateTime<Utc>,
+}
+
+impl LoginTokens {
+    /// Sign with `secret`, or a random per-process secret if none.
+    pub fn new(secret: Option<String>, ttl_secs: u64) -> Self {
+        let secret = match secret {
+            Some(s) => s.into_bytes(),
+            None => rand::random::<[u8; 32]>().to_vec(),
+        };
+        Self {
+            secret,
+            ttl_secs,
+            revoked: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Issue a new token valid for the configured TTL.
+    pub fn issue(&self) -> IssuedToken {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let expires_at = Utc::now() + chrono::Duration::seconds(self.ttl_secs as i64);
+        let payload = format!("{TOKEN_VERSION}.{id}.{}", expires_at.timestamp());
+        let token = format!("{payload}.{}", self.sign(&payload));
+        IssuedToken { token, expires_at }
+    }
+
+    /// Check a token's signature, expiry and revocation. Returns its ID.
+    pub fn verify(&self, token: &str) -> Result<String, String> {
+        let (payload, signature) = token
+            .rsplit_once('.')
+            .ok_or_else(|| "Malformed login token".to_string())?;
+        let mut parts = payload.split('.');
+        let (Some(TOKEN_VERSION), Some(id), Some(expiry), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err("Malformed login token".to_string());
+        };
+        if !crate::connection::constant_time_eq(&self.sign(payload), signature) {
+            return Err("Invalid login token".to_string());
+        }
+        let expiry: i64 = expiry.parse().map_err(|_| "Malformed login token".to_string())?;
+        if expiry <= Utc::now().timestamp() {
+            return Err("Login token expired".to_string());
+        }
+        if self.revoked.lock().unwrap().contains_key(id) {
+            return Err("Login token revoked".to_string());
+        }
+        Ok(id.to_string())
+    }
+
+    /// Revoke a valid token. Returns false if it was not valid to begin with.
+    pub fn revoke(&self, token: &str) -> bool {
+        let Ok(id) = self.verify(token) else {
+            return false;
+        };
+        // Validity was just checked, so the expiry parses
+        let expiry = token.split('.').nth(2).and_then(|e| e.parse().ok()).unwrap_or(0);
+        let now = Utc::now().timestamp();
+        let mut revoked = self.revoked.lock().unwrap();
+        revoked.retain(|_, exp| *exp > now);
+        revoked.insert(id, expiry);
+        true
+    }
+
+    fn sign(&self, payload: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret)
+            .expect("HMAC accepts keys of any length");
+        mac.update(payload.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_verify_revoke() {
+        let tokens = LoginTokens::new(None, 3600);
+        let issued = tokens.issue();
+        assert!(tokens.verify(&issued.token).is_ok());
+
+        // Tampering breaks the signature
+        let tampered = issued.token.replacen("v1.", "v1.0", 1);
+        assert!(tokens.verify(&tampered).is_err());
+
+        // Tokens from another secret are rejected
+        let other = LoginTokens::new(Some("other".into()), 3600);
+        assert!(other.verify(&issued.token).is_err());
+
+        assert!(tokens.revoke(&issued.token));
+        assert_eq!(tokens.verify(&issued.token).unwrap_err(), "Login token revoked");
+        assert!(!tokens.revoke(&issued.token));
+    }
+
+    #[test]
+    fn test_expired_token_rejected() {
+        let tokens = LoginTokens::new(Some("secret".into()), 0);
+        let issued = tokens.issue();
+        assert_eq!(tokens.verify(&issued.token).unwrap_err(), "Login token expired");
+    }
+}
@@ -6,29 +6,115 @@ use serde_json::json;
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
 
-use rusty_claw_core::config::CronJob;
+use rusty_claw_core::config::{ApiKeyConfig, CronJob, VadConfig};
 use rusty_claw_core::protocol::{ErrorShape, GatewayFrame};
 use rusty_claw_core::session::{Session, SessionKey};
+use rusty_claw_core::session_export::SessionExport;
 use rusty_claw_core::types::{ChatType, InboundMessage};
 use rusty_claw_agent::AgentEvent;
+use rusty_claw_media::resample::InputFormat;
 use rusty_claw_media::voice_session::{TalkMode, VoiceSession};
 
 use crate::events::broadcast_event;
+use crate::session_hooks::{fire_session_end, fire_session_start};
+use crate::spawn::SpawnRequest;
 use crate::state::GatewayState;
 
-/// Dispatch a method request and return the response frame.
+/// Built-in methods, advertised to clients in `HelloOk`.
+pub const METHODS: &[&str] = &[
+    "sessions.list",
+    "sessions.preview",
+    "sessions.delete",
+    "sessions.reset",
+    "sessions.patch",
+    "sessions.search",
+    "sessions.export",
+    "sessions.import",
+    "sessions.compact",
+    "agent",
+    "agent.abort",
+    "agent.status",
+    "wake",
+    "models.list",
+    "channels.status",
+    "channels.login",
+    "channels.logout",
+    "config.get",
+    "config.set",
+    "cron.list",
+    "cron.add",
+    "cron.remove",
+    "cron.history",
+    "skills.list",
+    "skills.get",
+    "skills.reload",
+    "talk.config",
+    "talk.start",
+    "talk.stop",
+    "talk.mode",
+    "node.pair.request",
+    "node.pair.approve",
+    "node.invoke",
+    "node.event",
+    "agents.spawn",
+    "login",
+    "logout",
+    "connect",
+];
+
+/// The connection a method call arrives on.
+pub struct Caller<'a> {
+    pub conn_id: &'a str,
+    /// Client address, when known.
+    pub ip: Option<std::net::IpAddr>,
+    /// API key the connection authenticated with, limiting its methods.
+    pub api_key: Option<&'a ApiKeyConfig>,
+}
+
+/// Dispatch a method request and return the response frame. Calls are
+/// rejected when outside the caller's API key scopes or over its rate limits.
 pub async fn dispatch_method(
     state: &Arc<GatewayState>,
+    caller: &Caller<'_>,
     request_id: &str,
     method: &str,
     params: Option<serde_json::Value>,
 ) -> GatewayFrame {
     debug!(method, "Dispatching method");
 
+    if let Some(key) = caller.api_key
+        && !key.allows(method)
+    {
+        warn!(method, key = %key.name, "Method outside API key scopes");
+        return error_response(
+            request_id,
+            "unauthorized",
+            &format!("API key '{}' is not allowed to call {method}", key.name),
+        );
+    }
+
+    if let Err(retry_after) = state.request_limiter.check(caller.conn_id, caller.ip, method) {
+        let retry_after_ms = retry_after.as_millis().max(1) as u64;
+        return GatewayFrame::Response {
+            id: request_id.to_string(),
+            ok: false,
+            payload: None,
+            error: Some(ErrorShape {
+                code: "rate_limited".into(),
+                message: format!("Too many requests; retry in {retry_after_ms}ms"),
+                details: Some(json!({ "retry_after_ms": retry_after_ms })),
+            }),
+        };
+    }
+
+    if state.shutdown.is_cancelled() && matches!(method, "agent" | "agents.spawn") {
+        return error_response(request_id, "shutting_down", "Gateway is shutting down");
+    }
+
     #[cfg(feature = "metrics")]
     let start = std::time::Instant::now();
 
-    let response = dispatch_method_inner(state, request_id, method, params).await;
+    let response = dispatch_method_inner(state, caller, request_id, method, params).await;
 
     #[cfg(feature = "metrics")]
     crate::metrics::record_request(method, start.elapsed().as_secs_f64());
@@ -38,6 +124,7 @@ pub async fn dispatch_method(
 
 async fn dispatch_method_inner(
     state: &Arc<GatewayState>,
+    caller: &Caller<'_>,
     request_id: &str,
     method: &str,
     params: Option<serde_json::Value>,
@@ -48,6 +135,9 @@ async fn dispatch_method_inner(
         "sessions.delete" => handle_sessions_delete(state, request_id, params).await,
         "sessions.reset" => handle_sessions_reset(state, request_id, params).await,
         "sessions.patch" => handle_sessions_patch(state, request_id, params).await,
+        "sessions.search" => handle_sessions_search(state, request_id, params).await,
+        "sessions.export" => handle_sessions_export(state, request_id, params).await,
+        "sessions.import" => handle_sessions_import(state, request_id, params).await,
         "agent" => handle_agent(state, request_id, params).await,
         "agent.abort" => handle_agent_abort(state, request_id, params).await,
         "agent.status" => handle_agent_status(state, request_id, params).await,
@@ -61,8 +151,12 @@ async fn dispatch_method_inner(
         "cron.list" => handle_cron_list(state, request_id).await,
         "cron.add" => handle_cron_add(state, request_id, params).await,
         "cron.remove" => handle_cron_remove(state, request_id, params).await,
+        "cron.history" => handle_cron_history(state, request_id, params).await,
         "skills.list" => handle_skills_list(state, request_id).await,
         "skills.get" => handle_skills_get(state, request_id, params).await,
+        "skills.reload" => {
+            ok_response(request_id, crate::skills::reload_skills(state).await)
+        }
         "sessions.compact" => handle_sessions_compact(state, request_id, params).await,
         "talk.config" => handle_talk_config(state, request_id, params).await,
         "talk.start" => handle_talk_start(state, request_id, params).await,
@@ -72,16 +166,38 @@ async fn dispatch_method_inner(
             crate::nodes::handle_pair_request(&state.pairing, request_id, params)
         }
         "node.pair.approve" => {
-            crate::nodes::handle_pair_approve(&state.pairing, request_id, params)
+            let response =
+                crate::nodes::handle_pair_approve(&state.pairing, caller, request_id, params);
+            state.prune_pairing_notices();
+            response
         }
         "agents.spawn" => handle_agents_spawn(state, request_id, params).await,
-        "node.invoke" => crate::nodes::handle_invoke(request_id, params),
-        "node.event" => crate::nodes::handle_event(request_id, params),
-        _ => error_response(
+        "login" => error_response(
             request_id,
-            "method_not_found",
-            &format!("Unknown method: {method}"),
+            "invalid_request",
+            "login is only accepted as the first message of a connection",
         ),
+        "logout" => {
+            let token = params
+                .as_ref()
+                .and_then(|p| p.get("token"))
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            ok_response(request_id, json!({ "revoked": state.login_tokens.revoke(token) }))
+        }
+        "node.invoke" => crate::nodes::handle_invoke(request_id, params),
+        "node.event" => crate::nodes::handle_event(request_id, params),
+        _ => match state.plugin_methods.get(method) {
+            Some(handler) => match handler(params).await {
+                Ok(payload) => ok_response(request_id, payload),
+                Err(e) => error_response(request_id, "plugin_error", &e.to_string()),
+            },
+            None => error_response(
+                request_id,
+                "method_not_found",
+                &format!("Unknown method: {method}"),
+            ),
+        },
     }
 }
 
@@ -132,6 +248,154 @@ async fn handle_sessions_preview(
     }
 }
 
+async fn handle_sessions_search(
+    state: &Arc<GatewayState>,
+    request_id: &str,
+    params: Option<serde_json::Value>,
+) -> GatewayFrame {
+    let params = params.unwrap_or_default();
+    let query = params.get("query").and_then(|v| v.as_str()).unwrap_or("");
+    if query.is_empty() {
+        return error_response(request_id, "invalid_params", "query is required");
+    }
+    let is_regex = params.get("regex").and_then(|v| v.as_bool()).unwrap_or(false);
+    let limit = params.get("limit").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+
+    let pattern = match rusty_claw_core::session::search_pattern(query, is_regex) {
+        Ok(pattern) => pattern,
+        Err(e) => return error_response(request_id, "invalid_params", &e.to_string()),
+    };
+    match state.sessions.search(&pattern, limit).await {
+        Ok(results) => ok_response(request_id, json!({ "results": results })),
+        Err(e) => error_response(request_id, "session_error", &e.to_string()),
+    }
+}
+
+/// Directory that `media_dir` in `sessions.export` / `sessions.import` is
+/// resolved under: `~/.rusty_claw/exports/`.
+fn media_root() -> std::path::PathBuf {
+    rusty_claw_core::config::data_dir().join("exports")
+}
+
+/// Resolve a client-supplied `media_dir` under `root`. Only plain relative
+/// paths are accepted, so a client cannot read or write files elsewhere on
+/// the gateway host.
+fn resolve_media_dir(root: &std::path::Path, dir: &str) -> Result<std::path::PathBuf, String> {
+    let relative = std::path::Path::new(dir);
+    let plain = relative
+        .components()
+        .all(|c| matches!(c, std::path::Component::Normal(_)));
+    if dir.is_empty() || !plain {
+        return Err(format!(
+            "media_dir must be a relative path inside {} without '..'",
+            root.display()
+        ));
+    }
+    Ok(root.join(relative))
+}
+
+async fn handle_sessions_export(
+    state: &Arc<GatewayState>,
+    request_id: &str,
+    params: Option<serde_json::Value>,
+) -> GatewayFrame {
+    let params = params.unwrap_or_default();
+    let key: SessionKey = match serde_json::from_value(params.get("key").cloned().unwrap_or_default()) {
+        Ok(k) => k,
+        Err(e) => return error_response(request_id, "invalid_params", &e.to_string()),
+    };
+    let format = params.get("format").and_then(|v| v.as_str()).unwrap_or("json");
+
+    let session = match state.sessions.load(&key).await {
+        Ok(Some(session)) => session,
+        Ok(None) => return error_response(request_id, "not_found", "Session not found"),
+        Err(e) => return error_response(request_id, "session_error", &e.to_string()),
+    };
+    let mut export = SessionExport::from_session(&session);
+
+    // Optionally move images out to files instead of inlining them
+    if let Some(dir) = params.get("media_dir").and_then(|v| v.as_str()) {
+        let dir = match resolve_media_dir(&media_root(), dir) {
+            Ok(dir) => dir,
+            Err(e) => return error_response(request_id, "invalid_params", &e),
+        };
+        if let Err(e) = export.externalize_media(&dir) {
+            return error_response(request_id, "export_error", &e.to_string());
+        }
+    }
+
+    match format {
+        "json" => ok_response(request_id, json!({ "export": export })),
+        "markdown" => ok_response(request_id, json!({ "markdown": export.to_markdown() })),
+        other => error_response(
+            request_id,
+            "invalid_params",
+            &format!("Unknown export format '{other}' (expected json or markdown)"),
+        ),
+    }
+}
+
+async fn handle_sessions_import(
+    state: &Arc<GatewayState>,
+    request_id: &str,
+    params: Option<serde_json::Value>,
+) -> GatewayFrame {
+    let params = params.unwrap_or_default();
+    let mut export: SessionExport =
+        match serde_json::from_value(params.get("export").cloned().unwrap_or_default()) {
+            Ok(export) => export,
+            Err(e) => return error_response(request_id, "invalid_params", &e.to_string()),
+        };
+    let key: Option<SessionKey> = match params.get("key") {
+        Some(value) => match serde_json::from_value(value.clone()) {
+            Ok(k) => Some(k),
+            Err(e) => return error_response(request_id, "invalid_params", &e.to_string()),
+        },
+        None => None,
+    };
+
+    if let Some(dir) = params.get("media_dir").and_then(|v| v.as_str()) {
+        let dir = match resolve_media_dir(&media_root(), dir) {
+            Ok(dir) => dir,
+            Err(e) => return error_response(request_id, "invalid_params", &e),
+        };
+        if let Err(e) = export.inline_media(&dir) {
+            return error_response(request_id, "import_error", &e.to_string());
+        }
+    }
+
+    // Never overwrite: an explicit key must be free, and an import under the
+    // original key moves to a fresh peer ID if that session still exists
+    let key = match key {
+        Some(key) => key,
+        None => {
+            let mut key = export.meta.key.clone();
+            if matches!(state.sessions.load(&key).await, Ok(Some(_))) {
+                key.peer_id = format!("{}-import-{}", key.peer_id, &uuid::Uuid::new_v4().simple().to_string()[..8]);
+            }
+            key
+        }
+    };
+    match state.sessions.load(&key).await {
+        Ok(None) => {}
+        Ok(Some(_)) => return error_response(request_id, "already_exists", "A session with this key already exists"),
+        Err(e) => return error_response(request_id, "session_error", &e.to_string()),
+    }
+
+    let session = match export.into_session(Some(key)) {
+        Ok(session) => session,
+        Err(e) => return error_response(request_id, "import_error", &e.to_string()),
+    };
+    if let Err(e) = state.sessions.save(&session).await {
+        return error_response(request_id, "session_error", &e.to_string());
+    }
+    state.bump_state_version();
+    ok_response(
+        request_id,
+        json!({ "imported": true, "key": session.meta.key, "entries": session.transcript.len() }),
+    )
+}
+
 async fn handle_sessions_delete(
     state: &Arc<GatewayState>,
     request_id: &str,
@@ -145,6 +409,7 @@ async fn handle_sessions_delete(
 
     match state.sessions.delete(&key).await {
         Ok(()) => {
+            fire_session_end(&state.hooks, &key, "delete").await;
             state.bump_state_version();
             ok_response(request_id, json!({"deleted": true}))
         }
@@ -165,6 +430,7 @@ async fn handle_sessions_reset(
 
     match state.sessions.reset(&key).await {
         Ok(()) => {
+            fire_session_end(&state.hooks, &key, "reset").await;
             state.bump_state_version();
             ok_response(request_id, json!({"reset": true}))
         }
@@ -192,6 +458,20 @@ async fn handle_sessions_patch(
             if let Some(model) = params.get("model").and_then(|v| v.as_str()) {
                 session.meta.model = Some(model.to_string());
             }
+            if let Some(provider) = params.get("provider") {
+                if provider.is_null() {
+                    session.meta.provider_id = None;
+                } else if let Some(id) = provider.as_str() {
+                    if !state.providers.list_ids().contains(&id) {
+                        return error_response(
+                            request_id,
+                            "invalid_params",
+                            &format!("Unknown provider '{id}'"),
+                        );
+                    }
+                    session.meta.provider_id = Some(id.to_string());
+                }
+            }
             if let Some(thinking) = params.get("thinking_level").and_then(|v| v.as_str()) {
                 if let Ok(level) = serde_json::from_value(json!(thinking)) {
                     session.meta.thinking_level = level;
@@ -236,7 +516,10 @@ async fn handle_sessions_compact(
         Err(e) => return error_response(request_id, "session_error", &e.to_string()),
     };
 
-    let (provider, credentials) = match state.providers.default() {
+    let (_, provider, credentials) = match state
+        .providers
+        .resolve(session.meta.provider_id.as_deref(), session.meta.model.as_deref())
+    {
         Some(pc) => pc,
         None => {
             return error_response(request_id, "no_provider", "No default provider configured")
@@ -278,7 +561,7 @@ async fn handle_sessions_compact(
 // Agent methods
 // ============================================================
 
-async fn handle_agent(
+pub(crate) async fn handle_agent(
     state: &Arc<GatewayState>,
     request_id: &str,
     params: Option<serde_json::Value>,
@@ -292,6 +575,23 @@ async fn handle_agent(
 
     let message = InboundMessage::from_cli_text(&text);
 
+    // Optional forced tool choice for the first turn, e.g.
+    // {"type": "tool", "name": "submit_answer"}
+    let mut options = rusty_claw_agent::AgentRunOptions {
+        tool_choice: params
+            .get("tool_choice")
+            .and_then(|v| serde_json::from_value(v.clone()).ok()),
+        include_thinking: params
+            .get("include_thinking")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false),
+        // {"type": "json_schema", "schema": {...}}
+        response_format: params
+            .get("response_format")
+            .and_then(|v| serde_json::from_value(v.clone()).ok()),
+        ..Default::default()
+    };
+
     let key = SessionKey {
         channel: "gateway".into(),
         account_id: "ws-client".into(),
@@ -302,12 +602,15 @@ async fn handle_agent(
 
     let session_hash = key.hash_key();
 
-    let mut session = match state.sessions.load(&key).await {
-        Ok(Some(s)) => s,
-        Ok(None) => Session::new(key.clone()),
+    let (mut session, is_new) = match state.sessions.load(&key).await {
+        Ok(Some(s)) => (s, false),
+        Ok(None) => (Session::new(key.clone()), true),
         Err(e) => return error_response(request_id, "session_error", &e.to_string()),
     };
 
+    // --- Hook: SessionStart ---
+    fire_session_start(&state.hooks, &key, is_new).await;
+
     let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel::<AgentEvent>();
 
     // Create cancellation token for this agent run
@@ -316,18 +619,30 @@ async fn handle_agent(
         let mut active = state.active_agents.write().await;
         active.insert(session_hash.clone(), cancel_token.clone());
     }
+    options.cancel = Some(cancel_token);
+    options.checkpoint = Some(state.sessions.clone());
+    options.active_skills = state.skills.read().await.skills_for_turn(
+        &state.read_config().await,
+        session.meta.active_skill.as_deref(),
+        Some(&text),
+    );
 
     // Spawn event forwarder
     let state_clone = state.clone();
     tokio::spawn(async move {
         while let Some(event) = event_rx.recv().await {
+            #[cfg(feature = "metrics")]
+            crate::metrics::record_agent_event(&event);
             if let Ok(payload) = serde_json::to_value(&event) {
                 broadcast_event(&state_clone, "agent.event", Some(payload)).await;
             }
         }
     });
 
-    let (provider, credentials) = match state.providers.default() {
+    let (provider_id, provider, credentials) = match state
+        .providers
+        .resolve(session.meta.provider_id.as_deref(), session.meta.model.as_deref())
+    {
         Some(pc) => pc,
         None => return error_response(request_id, "no_provider", "No default provider configured"),
     };
@@ -335,8 +650,8 @@ async fn handle_agent(
     // Read config snapshot
     let config = Arc::new(state.read_config().await);
 
-    info!("Starting agent run via gateway");
-    let result = rusty_claw_agent::run_agent(
+    info!(provider = provider_id, "Starting agent run via gateway");
+    let result = rusty_claw_agent::run_agent_with_options(
         &mut session,
         message,
         &config,
@@ -345,22 +660,30 @@ async fn handle_agent(
         credentials,
         event_tx,
         &state.hooks,
+        options,
     )
     .await;
 
-    // Remove from active agents
-    {
-        let mut active = state.active_agents.write().await;
-        active.remove(&session_hash);
-    }
+    #[cfg(feature = "metrics")]
+    crate::metrics::record_agent_run(provider_id, &result);
 
     // Save session
     if let Err(e) = state.sessions.save(&session).await {
         tracing::error!(%e, "Failed to save session");
     }
 
+    // Remove from active agents
+    {
+        let mut active = state.active_agents.write().await;
+        active.remove(&session_hash);
+    }
+
     match result {
-        Ok(run_result) => ok_response(request_id, serde_json::to_value(&run_result).unwrap_or_default()),
+        Ok(run_result) => {
+            let mut payload = serde_json::to_value(&run_result).unwrap_or_default();
+            payload["provider"] = json!(provider_id);
+            ok_response(request_id, payload)
+        }
         Err(e) => error_response(request_id, "agent_error", &e.to_string()),
     }
 }
@@ -376,17 +699,28 @@ async fn handle_agent_abort(
         .and_then(|v| v.as_str())
         .unwrap_or("");
 
+    // Spawned children are cancelled with their parent's token; they can
+    // also be aborted on their own by child session key
     if session_key.is_empty() {
         let active = state.active_agents.read().await;
-        let count = active.len();
-        for token in active.values() {
+        let spawned = state.spawned_agents.lock().unwrap();
+        let count = active.len() + spawned.len();
+        for token in active.values().chain(spawned.values().map(|c| &c.cancel)) {
             token.cancel();
         }
         return ok_response(request_id, json!({"aborted": count}));
     }
 
-    let active = state.active_agents.read().await;
-    if let Some(token) = active.get(session_key) {
+    let token = match state.active_agents.read().await.get(session_key) {
+        Some(token) => Some(token.clone()),
+        None => state
+            .spawned_agents
+            .lock()
+            .unwrap()
+            .get(session_key)
+            .map(|c| c.cancel.clone()),
+    };
+    if let Some(token) = token {
         token.cancel();
         ok_response(request_id, json!({"aborted": true, "session_key": session_key}))
     } else {
@@ -567,9 +901,16 @@ async fn handle_config_set(
 
     {
         let mut config = state.config.write().await;
-        if let Err(e) = config.set_path(&path, value.clone()) {
-            return error_response(request_id, "config_error", &e.to_string());
+        // Validate on a copy: an invalid config is never applied or saved,
+        // since the gateway could not start from it
+        let (updated, warnings) = match config.with_path(&path, value.clone()) {
+            Ok(result) => result,
+            Err(e) => return error_response(request_id, "config_error", &e.to_string()),
+        };
+        for warning in &warnings {
+            warn!(%warning, %path, "Config warning after config.set");
         }
+        *config = updated;
 
         if let Some(ref config_path) = state.config_path {
             if let Err(e) = config.save(config_path) {
@@ -606,6 +947,9 @@ async fn handle_cron_list(state: &Arc<GatewayState>, request_id: &str) -> Gatewa
                         "schedule": j.schedule,
                         "task": j.task,
                         "enabled": j.enabled,
+                        "next_run": crate::cron::validate_schedule(&j.schedule)
+                            .ok()
+                            .map(|t| t.to_rfc3339()),
                     })
                 })
                 .collect();
@@ -639,13 +983,33 @@ async fn handle_cron_add(
 
     match &state.cron {
         Some(scheduler) => match scheduler.add_job(job).await {
-            Ok(()) => ok_response(request_id, json!({"added": true, "id": id})),
+            Ok(next_run) => ok_response(
+                request_id,
+                json!({"added": true, "id": id, "next_run": next_run.to_rfc3339()}),
+            ),
             Err(e) => error_response(request_id, "cron_error", &e),
         },
         None => error_response(request_id, "not_available", "Cron scheduler not running"),
     }
 }
 
+async fn handle_cron_history(
+    state: &Arc<GatewayState>,
+    request_id: &str,
+    params: Option<serde_json::Value>,
+) -> GatewayFrame {
+    let params = params.unwrap_or_default();
+    let Some(scheduler) = &state.cron else {
+        return ok_response(request_id, json!({ "history": {} }));
+    };
+
+    let mut history = scheduler.history().await;
+    if let Some(id) = params.get("id").and_then(|v| v.as_str()) {
+        history.retain(|job_id, _| job_id == id);
+    }
+    ok_response(request_id, json!({ "history": history }))
+}
+
 async fn handle_cron_remove(
     state: &Arc<GatewayState>,
     request_id: &str,
@@ -685,7 +1049,10 @@ async fn handle_skills_list(state: &Arc<GatewayState>, request_id: &str) -> Gate
             })
         })
         .collect();
-    ok_response(request_id, json!({ "skills": skill_list }))
+    ok_response(
+        request_id,
+        json!({ "skills": skill_list, "errors": skills.errors() }),
+    )
 }
 
 async fn handle_skills_get(
@@ -736,10 +1103,61 @@ async fn handle_talk_config(
                 .as_ref()
                 .and_then(|t| t.transcription.as_ref())
                 .map(|t| json!({"provider": t.provider, "model": t.model}));
-            ok_response(request_id, json!({"tts": tts, "transcription": transcription}))
+            let vad = configured_vad(&config);
+            ok_response(
+                request_id,
+                json!({"tts": tts, "transcription": transcription, "vad": vad}),
+            )
+        }
+        // Store VAD defaults for new talk sessions, clamped to usable values
+        "set" => {
+            let Some(overrides) = params.get("vad") else {
+                return error_response(request_id, "invalid_params", "vad is required");
+            };
+            let vad = match merge_vad(configured_vad(&config), overrides) {
+                Ok(vad) => vad,
+                Err(e) => return error_response(request_id, "invalid_params", &e),
+            };
+            let value = serde_json::to_value(vad).unwrap_or_default();
+            let frame = handle_config_set(
+                state,
+                request_id,
+                Some(json!({"path": "tools.vad", "value": value})),
+            )
+            .await;
+            match frame {
+                GatewayFrame::Response { ok: true, .. } => {
+                    ok_response(request_id, json!({"vad": value}))
+                }
+                other => other,
+            }
         }
-        _ => error_response(request_id, "invalid_params", "action must be 'get'"),
+        _ => error_response(request_id, "invalid_params", "action must be 'get' or 'set'"),
+    }
+}
+
+/// VAD settings from config, or the defaults.
+fn configured_vad(config: &rusty_claw_core::config::Config) -> VadConfig {
+    config
+        .tools
+        .as_ref()
+        .and_then(|t| t.vad)
+        .unwrap_or_default()
+        .clamped()
+}
+
+/// Apply the fields set in `overrides` (a partial VAD object) to `base`.
+fn merge_vad(base: VadConfig, overrides: &serde_json::Value) -> Result<VadConfig, String> {
+    let Some(overrides) = overrides.as_object() else {
+        return Err("vad must be an object".into());
+    };
+    let mut merged = serde_json::to_value(base).unwrap_or_default();
+    for (key, value) in overrides {
+        merged[key] = value.clone();
     }
+    serde_json::from_value::<VadConfig>(merged)
+        .map(VadConfig::clamped)
+        .map_err(|e| format!("invalid vad: {e}"))
 }
 
 // ============================================================
@@ -787,7 +1205,33 @@ async fn handle_talk_start(
         }
     }
 
-    let (handle, mut utterance_rx) = VoiceSession::start(mode);
+    // Per-session VAD overrides on top of the configured defaults
+    let base_vad = configured_vad(&state.read_config().await);
+    let vad = match params.get("vad") {
+        Some(overrides) => match merge_vad(base_vad, overrides) {
+            Ok(vad) => vad,
+            Err(e) => return error_response(request_id, "invalid_params", &e),
+        },
+        None => base_vad,
+    };
+
+    // Format the client captures in; converted to 16kHz mono for STT
+    let defaults = InputFormat::default();
+    let input = InputFormat {
+        sample_rate: params
+            .get("sample_rate")
+            .and_then(|v| v.as_u64())
+            .map_or(defaults.sample_rate, |v| v.min(u32::MAX as u64) as u32),
+        channels: params
+            .get("channels")
+            .and_then(|v| v.as_u64())
+            .map_or(defaults.channels, |v| v.min(u16::MAX as u64) as u16),
+    };
+    if let Err(e) = input.validate() {
+        return error_response(request_id, "invalid_params", &e);
+    }
+
+    let (handle, events) = VoiceSession::start(mode, vad, input);
 
     // Store voice session handle in connection state
     {
@@ -797,60 +1241,23 @@ async fn handle_talk_start(
         }
     }
 
-    // Spawn utterance processing task
-    let state_clone = state.clone();
-    let conn_id_owned = conn_id.to_string();
-    tokio::spawn(async move {
-        while let Some(utterance) = utterance_rx.recv().await {
-            debug!(
-                duration_ms = utterance.duration_ms,
-                samples = utterance.pcm_data.len(),
-                "Utterance received"
-            );
-
-            // Get transcription config
-            let config = state_clone.read_config().await;
-            let transcription_config = config
-                .tools
-                .as_ref()
-                .and_then(|t| t.transcription.as_ref());
-
-            if let Some(tc) = transcription_config {
-                match rusty_claw_media::stt::transcribe_audio_bytes(&utterance.pcm_data, tc).await {
-                    Ok(text) if !text.is_empty() => {
-                        info!(text = %text, "Transcribed utterance");
-
-                        // Send transcription as agent event
-                        let event = AgentEvent::BlockReply {
-                            text: format!("[Voice] {text}"),
-                            is_final: true,
-                        };
-                        if let Ok(payload) = serde_json::to_value(&event) {
-                            broadcast_event(&state_clone, "agent.event", Some(payload)).await;
-                        }
-                    }
-                    Ok(_) => {
-                        debug!("Empty transcription result");
-                    }
-                    Err(e) => {
-                        warn!(%e, "Transcription failed");
-                    }
-                }
-            } else {
-                warn!("No transcription config, cannot process voice");
-            }
-        }
-
-        // Cleanup voice session on task end
-        let mut connections = state_clone.connections.write().await;
-        if let Some(conn) = connections.get_mut(&conn_id_owned) {
-            conn.voice_session = None;
-        }
-    });
+    // Transcribe utterances, run the agent, and speak its replies
+    tokio::spawn(crate::talk::run_voice_turns(
+        state.clone(),
+        conn_id.to_string(),
+        events,
+    ));
 
     ok_response(
         request_id,
-        json!({"started": true, "mode": mode_str, "conn_id": conn_id}),
+        json!({
+            "started": true,
+            "mode": mode_str,
+            "conn_id": conn_id,
+            "vad": vad,
+            "sample_rate": input.sample_rate,
+            "channels": input.channels,
+        }),
     )
 }
 
@@ -934,85 +1341,45 @@ async fn handle_agents_spawn(
         Some(t) if !t.is_empty() => t.to_string(),
         _ => return error_response(request_id, "invalid_params", "task is required"),
     };
-    let model = params.get("model").and_then(|v| v.as_str()).map(String::from);
-
-    // Check spawn depth
-    let parent_depth = params
-        .get("spawn_depth")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(0) as u32;
-
-    let config = state.read_config().await;
-    let max_depth = config.max_spawn_depth();
+    let request = SpawnRequest {
+        task,
+        model: params.get("model").and_then(|v| v.as_str()).map(String::from),
+        parent_depth: params
+            .get("spawn_depth")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0) as u32,
+        parent_session_key: params
+            .get("parent_session_key")
+            .and_then(|v| v.as_str())
+            .map(String::from),
+    };
+    let wait = params.get("wait").and_then(|v| v.as_bool()).unwrap_or(false);
 
-    if parent_depth >= max_depth {
-        return error_response(
+    let child = match crate::spawn::spawn_agent(state, request).await {
+        Ok(child) => child,
+        Err(e) => return error_response(request_id, e.code(), &e.to_string()),
+    };
+    let child_key = child.session_key.clone();
+    if !wait {
+        return ok_response(
             request_id,
-            "spawn_depth_exceeded",
-            &format!("Max spawn depth of {max_depth} exceeded"),
+            json!({"spawned": true, "child_session_key": child_key}),
         );
     }
 
-    // Create child session
-    let child_key = SessionKey {
-        channel: "spawned".into(),
-        account_id: "agent".into(),
-        chat_type: ChatType::Dm,
-        peer_id: format!("spawn-{}", uuid::Uuid::new_v4()),
-        scope: rusty_claw_core::session::SessionScope::PerSender,
-    };
-    let child_hash = child_key.hash_key();
-
-    let mut child_session = Session::new(child_key);
-    child_session.meta.spawned_by = Some("parent".to_string());
-    child_session.meta.spawn_depth = parent_depth + 1;
-    if let Some(ref m) = model {
-        child_session.meta.model = Some(m.clone());
-    }
-
-    // Save child session
-    if let Err(e) = state.sessions.save(&child_session).await {
-        return error_response(request_id, "session_error", &e.to_string());
+    // Wait mode: hand the child's result back to the caller
+    match child.wait().await {
+        Ok(result) => ok_response(
+            request_id,
+            json!({
+                "spawned": true,
+                "child_session_key": child_key,
+                "text": result.final_text(),
+                "result": result,
+            }),
+        ),
+        Err(e) => error_response(request_id, "agent_error", &e.to_string()),
     }
-
-    // Spawn agent task for the child
-    let state_clone = state.clone();
-    let task_clone = task.clone();
-    tokio::spawn(async move {
-        let message = InboundMessage::from_cli_text(&task_clone);
-        let (event_tx, _event_rx) = tokio::sync::mpsc::unbounded_channel();
-
-        let (provider, credentials) = match state_clone.providers.default() {
-            Some(pc) => pc,
-            None => {
-                warn!("No provider for spawned agent");
-                return;
-            }
-        };
-
-        let config = Arc::new(state_clone.read_config().await);
-        let _ = rusty_claw_agent::run_agent(
-            &mut child_session,
-            message,
-            &config,
-            &state_clone.tools,
-            provider,
-            credentials,
-            event_tx,
-            &state_clone.hooks,
-        )
-        .await;
-
-        if let Err(e) = state_clone.sessions.save(&child_session).await {
-            warn!(%e, "Failed to save spawned session");
-        }
-    });
-
-    info!(child = %child_hash, "Spawned child agent");
-    ok_response(
-        request_id,
-        json!({"spawned": true, "child_session_key": child_hash}),
-    )
 }
 
 // ============================================================
@@ -1040,3 +1407,62 @@ fn error_response(id: &str, code: &str, message: &str) -> GatewayFrame {
         }),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusty_claw_core::session::SessionScope;
+
+    #[test]
+    fn test_resolve_media_dir() {
+        let root = std::path::Path::new("/data/exports");
+        assert_eq!(
+            resolve_media_dir(root, "work/chat-1").unwrap(),
+            root.join("work/chat-1")
+        );
+        for outside in ["", "/etc", "../secrets", "work/../../etc", "./work"] {
+            assert!(resolve_media_dir(root, outside).is_err(), "{outside:?} accepted");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sessions_export_rejects_outside_media_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = Arc::new(crate::state::test_state(dir.path()));
+        let key = SessionKey {
+            channel: "test".into(),
+            account_id: "default".into(),
+            chat_type: ChatType::Dm,
+            peer_id: "user-1".into(),
+            scope: SessionScope::PerSender,
+        };
+        state.sessions.save(&Session::new(key.clone())).await.unwrap();
+
+        let target = dir.path().join("outside");
+        let frame = handle_sessions_export(
+            &state,
+            "r1",
+            Some(json!({ "key": key, "media_dir": target.to_string_lossy() })),
+        )
+        .await;
+        let GatewayFrame::Response { error: Some(error), .. } = frame else {
+            panic!("export with an absolute media_dir succeeded");
+        };
+        assert_eq!(error.code, "invalid_params");
+        assert!(!target.exists());
+
+        let frame = handle_sessions_import(
+            &state,
+            "r2",
+            Some(json!({
+                "export": SessionExport::from_session(&Session::new(key)),
+                "media_dir": "../../etc",
+            })),
+        )
+        .await;
+        let GatewayFrame::Response { error: Some(error), .. } = frame else {
+            panic!("import with a '..' media_dir succeeded");
+        };
+        assert_eq!(error.code, "invalid_params");
+    }
+}
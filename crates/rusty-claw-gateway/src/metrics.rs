@@ -1,13 +1,33 @@
 //! Prometheus metrics recording and endpoint.
+//!
+//! Metrics are exposed in the Prometheus text format at `GET /metrics`:
+//!
+//! - `ws_connections_active`, `ws_requests_total{method}`,
+//!   `ws_request_duration_seconds{method}`
+//! - `agent_active`, `agent_runs_total{provider,outcome}`,
+//!   `agent_run_duration_seconds{provider}`
+//! - `llm_tokens_total{provider,direction}` (`input` / `output`)
+//! - `tool_executions_total{tool}`, `tool_errors_total{tool}`
+//! - `channel_sends_total{channel,outcome}` (`success` / `failure`)
+//! - `errors_total{kind}`
+
+use std::sync::OnceLock;
 
 use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use rusty_claw_agent::{AgentEvent, AgentRunResult};
+
+static PROMETHEUS_HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
 
 /// Install the Prometheus metrics recorder and return the handle for rendering.
+/// The recorder is process-wide, so later calls return the same handle.
 pub fn install_prometheus_recorder() -> PrometheusHandle {
-    let builder = PrometheusBuilder::new();
-    builder
-        .install_recorder()
-        .expect("Failed to install Prometheus recorder")
+    PROMETHEUS_HANDLE
+        .get_or_init(|| {
+            PrometheusBuilder::new()
+                .install_recorder()
+                .expect("Failed to install Prometheus recorder")
+        })
+        .clone()
 }
 
 /// Record a new WebSocket connection.
@@ -37,6 +57,45 @@ pub fn record_agent_end() {
     metrics::gauge!("agent_active").decrement(1.0);
 }
 
+/// Record a finished agent run: its outcome, duration and token usage.
+pub fn record_agent_run(provider: &str, result: &anyhow::Result<AgentRunResult>) {
+    let provider = provider.to_string();
+    let outcome = match result {
+        Ok(run) if run.meta.error.is_none() => "ok",
+        _ => "error",
+    };
+    let labels = [("provider", provider.clone()), ("outcome", outcome.to_string())];
+    metrics::counter!("agent_runs_total", &labels).increment(1);
+
+    let Ok(run) = result else {
+        return;
+    };
+    let labels = [("provider", provider.clone())];
+    metrics::histogram!("agent_run_duration_seconds", &labels).record(run.meta.duration_ms as f64 / 1000.0);
+    for (direction, tokens) in [("input", run.meta.input_tokens), ("output", run.meta.output_tokens)] {
+        let labels = [("provider", provider.clone()), ("direction", direction.to_string())];
+        metrics::counter!("llm_tokens_total", &labels).increment(tokens);
+    }
+}
+
+/// Record tool executions as their results stream out of a run.
+pub fn record_agent_event(event: &AgentEvent) {
+    if let AgentEvent::ToolResult { tool, is_error, partial: false, .. } = event {
+        let labels = [("tool", tool.clone())];
+        metrics::counter!("tool_executions_total", &labels).increment(1);
+        if *is_error {
+            metrics::counter!("tool_errors_total", &labels).increment(1);
+        }
+    }
+}
+
+/// Record the outcome of delivering a message to a channel.
+pub fn record_channel_send(channel: &str, success: bool) {
+    let outcome = if success { "success" } else { "failure" };
+    let labels = [("channel", channel.to_string()), ("outcome", outcome.to_string())];
+    metrics::counter!("channel_sends_total", &labels).increment(1);
+}
+
 /// Record an error of a given kind.
 pub fn record_error(kind: &str) {
     let labels = [("kind", kind.to_string())];
@@ -74,4 +133,19 @@ mod tests {
     fn test_record_error_does_not_panic() {
         record_error("test_error");
     }
+
+    #[test]
+    fn test_rendered_metrics() {
+        let handle = install_prometheus_recorder();
+        record_channel_send("telegram", true);
+        record_agent_event(&AgentEvent::ToolResult {
+            tool: "exec".into(),
+            content: String::new(),
+            is_error: true,
+            partial: false,
+        });
+        let output = handle.render();
+        assert!(output.contains("channel_sends_total{channel=\"telegram\",outcome=\"success\"}"));
+        assert!(output.contains("tool_errors_total{tool=\"exec\"}"));
+    }
 }
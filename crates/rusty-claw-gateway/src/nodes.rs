@@ -2,9 +2,11 @@
 
 use serde_json::json;
 
-use rusty_claw_core::pairing::PairingStore;
+use rusty_claw_core::pairing::{PairingOutcome, PairingStore};
 use rusty_claw_core::protocol::{ErrorShape, GatewayFrame};
 
+use crate::methods::Caller;
+
 /// Handle `node.pair.request` — initiate a pairing request from a device.
 pub fn handle_pair_request(
     pairing: &PairingStore,
@@ -36,8 +38,13 @@ pub fn handle_pair_request(
 }
 
 /// Handle `node.pair.approve` — approve a pending pairing request.
+///
+/// Wrong codes count against the caller's IP, or its connection when the
+/// address is unknown, so one client's guesses don't lock out the others.
+/// Lockouts are kept in memory and end when the gateway restarts.
 pub fn handle_pair_approve(
     pairing: &PairingStore,
+    caller: &Caller<'_>,
     request_id: &str,
     params: Option<serde_json::Value>,
 ) -> GatewayFrame {
@@ -49,9 +56,23 @@ pub fn handle_pair_approve(
         return error_response(request_id, "invalid_params", "code is required");
     }
 
-    match pairing.approve(channel, code) {
-        Ok(true) => ok_response(request_id, json!({"approved": true})),
-        Ok(false) => error_response(request_id, "not_found", "No pending pairing with that code"),
+    let approver = match caller.ip {
+        Some(ip) => format!("gateway:{ip}"),
+        None => format!("gateway:{}", caller.conn_id),
+    };
+    match pairing.approve(channel, code, &approver) {
+        Ok(PairingOutcome::Resolved) => ok_response(request_id, json!({"approved": true})),
+        Ok(PairingOutcome::Expired) => {
+            error_response(request_id, "expired", "Pairing code has expired")
+        }
+        Ok(PairingOutcome::Unknown) => {
+            error_response(request_id, "not_found", "No pending pairing with that code")
+        }
+        Ok(PairingOutcome::LockedOut) => error_response(
+            request_id,
+            "rate_limited",
+            "Too many wrong pairing codes, try again later",
+        ),
         Err(e) => error_response(request_id, "pairing_error", &e.to_string()),
     }
 }
@@ -0,0 +1,133 @@
+//! Per-connection outbound queue with a bound on buffered bytes.
+//!
+//! Sends never block, so one slow client cannot hold up a broadcast. Instead
+//! the queue tracks how many bytes are waiting to be written to the socket;
+//! a send that would exceed the limit is dropped and the connection marked
+//! as overflowed, after which the connection closes it with a
+//! `backpressure` reason.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+/// A frame queued for a client.
+pub enum Outbound {
+    Text(String),
+    Binary(Vec<u8>),
+    /// Close the socket once everything queued before it is sent.
+    Close { code: u16, reason: &'static str },
+}
+
+impl Outbound {
+    fn len(&self) -> usize {
+        match self {
+            Self::Text(text) => text.len(),
+            Self::Binary(data) => data.len(),
+            Self::Close { .. } => 0,
+        }
+    }
+}
+
+/// Sending half of a connection's queue. Cheap to clone.
+#[derive(Clone)]
+pub struct OutboundSender {
+    tx: mpsc::UnboundedSender<Outbound>,
+    buffered: Arc<AtomicUsize>,
+    max_buffered: usize,
+    overflowed: CancellationToken,
+}
+
+/// Receiving half, drained by the connection's socket writer.
+pub struct OutboundReceiver {
+    rx: mpsc::UnboundedReceiver<Outbound>,
+    buffered: Arc<AtomicUsize>,
+}
+
+/// Create a queue holding at most `max_buffered` bytes.
+pub fn outbound_channel(max_buffered: usize) -> (OutboundSender, OutboundReceiver) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let buffered = Arc::new(AtomicUsize::new(0));
+    (
+        OutboundSender {
+            tx,
+            buffered: buffered.clone(),
+            max_buffered,
+            overflowed: CancellationToken::new(),
+        },
+        OutboundReceiver { rx, buffered },
+    )
+}
+
+impl OutboundSender {
+    /// Queue a text frame. Returns false if it was dropped.
+    pub fn send_text(&self, text: String) -> bool {
+        self.send(Outbound::Text(text))
+    }
+
+    /// Queue a binary frame. Returns false if it was dropped.
+    pub fn send_binary(&self, data: Vec<u8>) -> bool {
+        self.send(Outbound::Binary(data))
+    }
+
+    fn send(&self, frame: Outbound) -> bool {
+        if self.overflowed.is_cancelled() {
+            return false;
+        }
+        let len = frame.len();
+        let buffered = self.buffered.fetch_add(len, Ordering::SeqCst) + len;
+        if buffered > self.max_buffered {
+            self.buffered.fetch_sub(len, Ordering::SeqCst);
+            warn!(buffered, limit = self.max_buffered, "Client not keeping up, dropping connection");
+            self.overflowed.cancel();
+            return false;
+        }
+        if self.tx.send(frame).is_err() {
+            self.buffered.fetch_sub(len, Ordering::SeqCst);
+            return false;
+        }
+        true
+    }
+
+    /// Close the connection after the frames already queued.
+    pub fn close(&self, code: u16, reason: &'static str) {
+        let _ = self.tx.send(Outbound::Close { code, reason });
+    }
+
+    /// Cancelled once the client falls more than the limit behind.
+    pub fn overflowed(&self) -> &CancellationToken {
+        &self.overflowed
+    }
+}
+
+impl OutboundReceiver {
+    /// Take the next frame to write to the socket.
+    pub async fn recv(&mut self) -> Option<Outbound> {
+        let frame = self.rx.recv().await?;
+        self.buffered.fetch_sub(frame.len(), Ordering::SeqCst);
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_overflow_marks_sender() {
+        let (tx, mut rx) = outbound_channel(10);
+        assert!(tx.send_text("12345".into()));
+        assert!(tx.send_binary(vec![0; 5]));
+
+        // Draining frees space
+        assert!(matches!(rx.recv().await, Some(Outbound::Text(_))));
+        assert!(tx.send_text("abcde".into()));
+
+        // Exceeding the limit drops the frame and marks the connection
+        assert!(!tx.send_text("x".repeat(6)));
+        assert!(tx.overflowed().is_cancelled());
+        assert!(!tx.send_text("y".into()));
+    }
+}
@@ -1,12 +1,24 @@
-//! Per-IP WebSocket connection rate limiter.
+//! Per-IP WebSocket connection rate limiter, and token-bucket limits on
+//! method calls per connection and per IP.
 
 use std::collections::HashMap;
 use std::net::IpAddr;
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use tracing::{debug, warn};
 
+use rusty_claw_core::config::{RateLimitConfig, RequestRate};
+
+/// Methods that start agent runs, limited by `expensive_requests`.
+pub const EXPENSIVE_METHODS: &[&str] = &["agent", "agents.spawn"];
+
+/// Bucket count above which idle, full buckets are dropped.
+const MAX_IDLE_BUCKETS: usize = 1024;
+
+/// Longest retry delay reported to a client.
+const MAX_WAIT: Duration = Duration::from_secs(3600);
+
 /// Simple in-memory per-IP rate limiter for WebSocket connections.
 pub struct RateLimiter {
     max_connections_per_ip: u32,
@@ -75,6 +87,119 @@ impl RateLimiter {
     }
 }
 
+/// What a request bucket is keyed on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum BucketKey {
+    Connection(String),
+    Ip(IpAddr),
+    ExpensiveIp(IpAddr),
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl Bucket {
+    /// Refill for the time elapsed since the last call.
+    fn refill(&mut self, rate: RequestRate, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate.per_second).min(f64::from(rate.burst.max(1)));
+        self.refilled_at = now;
+    }
+
+    /// How long until a token is available, if one is not now.
+    fn wait(&self, rate: RequestRate) -> Option<Duration> {
+        (self.tokens < 1.0).then(|| {
+            Duration::try_from_secs_f64((1.0 - self.tokens) / rate.per_second)
+                .map_or(MAX_WAIT, |wait| wait.min(MAX_WAIT))
+        })
+    }
+}
+
+/// Token-bucket limits on method calls, per connection and per client IP,
+/// with a stricter bucket for [`EXPENSIVE_METHODS`].
+pub struct RequestLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<BucketKey, Bucket>>,
+}
+
+impl RequestLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token for a call to `method`. Returns how long to wait before
+    /// retrying when any applicable bucket is empty; nothing is taken then.
+    pub fn check(&self, conn_id: &str, ip: Option<IpAddr>, method: &str) -> Result<(), Duration> {
+        self.check_at(conn_id, ip, method, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        conn_id: &str,
+        ip: Option<IpAddr>,
+        method: &str,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let mut keys = vec![(BucketKey::Connection(conn_id.to_string()), self.config.connection_requests)];
+        if let Some(ip) = ip {
+            keys.push((BucketKey::Ip(ip), self.config.ip_requests));
+            if EXPENSIVE_METHODS.contains(&method) {
+                keys.push((BucketKey::ExpensiveIp(ip), self.config.expensive_requests));
+            }
+        }
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() > MAX_IDLE_BUCKETS {
+            let config = &self.config;
+            buckets.retain(|key, bucket| {
+                let rate = match key {
+                    BucketKey::Connection(_) => return true,
+                    BucketKey::Ip(_) => config.ip_requests,
+                    BucketKey::ExpensiveIp(_) => config.expensive_requests,
+                };
+                bucket.refill(rate, now);
+                bucket.tokens < f64::from(rate.burst.max(1))
+            });
+        }
+
+        // Check every bucket before taking from any
+        let mut wait = None;
+        for (key, rate) in &keys {
+            let bucket = buckets.entry(key.clone()).or_insert_with(|| Bucket {
+                tokens: f64::from(rate.burst.max(1)),
+                refilled_at: now,
+            });
+            bucket.refill(*rate, now);
+            if let Some(w) = bucket.wait(*rate) {
+                wait = Some(wait.map_or(w, |current: Duration| current.max(w)));
+            }
+        }
+        if let Some(wait) = wait {
+            warn!(conn_id, ?ip, method, retry_after = ?wait, "Rate limited: too many requests");
+            return Err(wait);
+        }
+        for (key, _) in &keys {
+            if let Some(bucket) = buckets.get_mut(key) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop a closed connection's bucket.
+    pub fn forget_connection(&self, conn_id: &str) {
+        self.buckets
+            .lock()
+            .unwrap()
+            .remove(&BucketKey::Connection(conn_id.to_string()));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -122,4 +247,57 @@ mod tests {
         limiter.release(ip);
         assert!(limiter.check(ip));
     }
+
+    #[test]
+    fn test_request_limiter_buckets() {
+        let config = RateLimitConfig {
+            connection_requests: RequestRate { per_second: 1.0, burst: 3 },
+            ip_requests: RequestRate { per_second: 1.0, burst: 4 },
+            expensive_requests: RequestRate { per_second: 0.5, burst: 1 },
+            ..Default::default()
+        };
+        let limiter = RequestLimiter::new(config);
+        let ip = Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+        let now = Instant::now();
+
+        // Per-connection burst
+        for _ in 0..3 {
+            assert!(limiter.check_at("a", ip, "sessions.list", now).is_ok());
+        }
+        let wait = limiter.check_at("a", ip, "sessions.list", now).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(1));
+
+        // The IP's remaining token is shared by its other connections
+        assert!(limiter.check_at("b", ip, "sessions.list", now).is_ok());
+        assert!(limiter.check_at("c", ip, "sessions.list", now).is_err());
+
+        // Expensive methods have their own, stricter bucket
+        let later = now + Duration::from_secs(10);
+        assert!(limiter.check_at("b", ip, "agent", later).is_ok());
+        let wait = limiter.check_at("b", ip, "agent", later).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(2));
+        assert!(limiter.check_at("b", ip, "sessions.list", later).is_ok());
+    }
+
+    #[test]
+    fn test_request_limiter_loopback_and_zero_rate() {
+        let config = RateLimitConfig {
+            connection_requests: RequestRate { per_second: 0.0, burst: 1 },
+            expensive_requests: RequestRate { per_second: 0.1, burst: 1 },
+            ..Default::default()
+        };
+        let limiter = RequestLimiter::new(config);
+        let now = Instant::now();
+
+        // Loopback callers (e.g. everyone behind a reverse proxy) share the
+        // per-IP buckets like any other address
+        let local = Some("::ffff:127.0.0.1".parse().unwrap());
+        assert!(limiter.check_at("a", local, "agent", now).is_ok());
+        let wait = limiter.check_at("b", local, "agent", now).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(10));
+
+        // A bucket that never refills reports the capped wait, not a panic
+        let wait = limiter.check_at("a", local, "sessions.list", now).unwrap_err();
+        assert_eq!(wait, MAX_WAIT);
+    }
 }
@@ -0,0 +1,194 @@
+//! Streaming channel replies via message edits.
+//!
+//! With `stream_replies` enabled for a channel, the reply is posted as soon
+//! as its first sentence is complete and then edited in place as the agent
+//! generates more text. Edits are flushed on sentence boundaries and at most
+//! once per [`MIN_EDIT_INTERVAL`] to stay within platform rate limits. If the
+//! reply outgrows one message, or a send or edit fails, streaming stops and
+//! the rest is delivered normally when the run ends.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, warn};
+
+use rusty_claw_core::types::{OutboundMessage, SendTarget};
+
+use crate::state::GatewayState;
+
+/// Minimum time between two edits of the streamed message.
+pub const MIN_EDIT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A reply being streamed to one chat.
+pub struct ReplyStream {
+    state: Arc<GatewayState>,
+    target: SendTarget,
+    reply_to: Option<String>,
+    thread_id: Option<String>,
+    max_len: usize,
+    /// Text generated so far in the current iteration.
+    text: String,
+    /// Text currently shown in the channel.
+    shown: String,
+    message_id: Option<String>,
+    last_flush: Option<Instant>,
+    stopped: bool,
+}
+
+impl ReplyStream {
+    pub fn new(
+        state: Arc<GatewayState>,
+        target: SendTarget,
+        reply_to: Option<String>,
+        thread_id: Option<String>,
+        max_len: usize,
+    ) -> Self {
+        Self {
+            state,
+            target,
+            reply_to,
+            thread_id,
+            max_len,
+            text: String::new(),
+            shown: String::new(),
+            message_id: None,
+            last_flush: None,
+            stopped: false,
+        }
+    }
+
+    /// Append a text delta, flushing complete sentences when due.
+    pub async fn push(&mut self, delta: &str) {
+        if self.stopped {
+            return;
+        }
+        self.text.push_str(delta);
+        if self.text.chars().count() > self.max_len {
+            debug!(channel = %self.target.channel, "Reply too long to stream, finishing at end of run");
+            self.stopped = true;
+            return;
+        }
+        if self.last_flush.is_some_and(|t| t.elapsed() < MIN_EDIT_INTERVAL) {
+            return;
+        }
+        let Some(end) = sentence_end(&self.text) else {
+            return;
+        };
+        let flushed = self.text[..end].trim_end().to_string();
+        if flushed.is_empty() || flushed == self.shown {
+            return;
+        }
+        if !self.show(&flushed).await {
+            self.stopped = true;
+        }
+    }
+
+    /// Start over after a tool call: only the last iteration's text is the
+    /// final reply, so the streamed message is rewritten from scratch.
+    pub fn restart(&mut self) {
+        self.text.clear();
+    }
+
+    /// Complete the stream with the run's final text. Returns whatever still
+    /// needs to be sent normally: everything when nothing was streamed, or
+    /// the part that did not fit in the streamed message.
+    pub async fn finish(mut self, final_text: &str) -> Option<String> {
+        if self.message_id.is_none() {
+            return Some(final_text.to_string()).filter(|t| !t.is_empty());
+        }
+        let (head, rest) = split_for_edit(final_text, self.max_len);
+        if head != self.shown && !self.show(head).await {
+            return Some(final_text.to_string()).filter(|t| !t.is_empty());
+        }
+        Some(rest.trim_start().to_string()).filter(|t| !t.is_empty())
+    }
+
+    /// Post or edit the streamed message to show `text`.
+    async fn show(&mut self, text: &str) -> bool {
+        let Some(channel) = self.state.channels.get(&self.target.channel) else {
+            return false;
+        };
+        self.last_flush = Some(Instant::now());
+        match &self.message_id {
+            Some(id) => {
+                if let Err(e) = channel.edit(&self.target, id, text).await {
+                    warn!(channel = %self.target.channel, %e, "Failed to edit streamed reply");
+                    return false;
+                }
+            }
+            None => {
+                let outbound = OutboundMessage {
+                    text: Some(text.to_string()),
+                    media: vec![],
+                    reply_to: self.reply_to.clone(),
+                    thread_id: self.thread_id.clone(),
+                    buttons: vec![],
+                };
+                match channel.send(&self.target, outbound).await {
+                    Ok(result) if result.success && result.message_id.is_some() => {
+                        self.message_id = result.message_id;
+                    }
+                    Ok(_) => return false,
+                    Err(e) => {
+                        warn!(channel = %self.target.channel, %e, "Failed to start streamed reply");
+                        return false;
+                    }
+                }
+            }
+        }
+        self.shown = text.to_string();
+        true
+    }
+}
+
+/// Byte offset just past the last complete sentence in `text`: after `.`,
+/// `!` or `?` followed by whitespace, or after a newline.
+fn sentence_end(text: &str) -> Option<usize> {
+    let mut end = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next_is_space = chars.peek().is_some_and(|(_, n)| n.is_whitespace());
+        if c == '\n' || (matches!(c, '.' | '!' | '?') && next_is_space) {
+            end = Some(i + c.len_utf8());
+        }
+    }
+    end
+}
+
+/// Split `text` into the part that fits in one message of `max_len`
+/// characters (broken at a newline or space where possible) and the rest.
+fn split_for_edit(text: &str, max_len: usize) -> (&str, &str) {
+    let Some((limit, _)) = text.char_indices().nth(max_len) else {
+        return (text, "");
+    };
+    let head = &text[..limit];
+    let split = head
+        .rfind('\n')
+        .or_else(|| head.rfind(' '))
+        .filter(|&i| i > 0)
+        .unwrap_or(limit);
+    (text[..split].trim_end(), &text[split..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sentence_end() {
+        assert_eq!(sentence_end("Hello"), None);
+        assert_eq!(sentence_end("Hello. Wor"), Some(6));
+        assert_eq!(sentence_end("Done! Next? Mor"), Some(11));
+        assert_eq!(sentence_end("line one\nline"), Some(9));
+        // Decimal points are not sentence ends
+        assert_eq!(sentence_end("It costs 3.50"), None);
+    }
+
+    #[test]
+    fn test_split_for_edit() {
+        assert_eq!(split_for_edit("short", 10), ("short", ""));
+        assert_eq!(split_for_edit("one two three", 9), ("one two", " three"));
+        assert_eq!(split_for_edit("para\nnext words", 12), ("para", "\nnext words"));
+        assert_eq!(split_for_edit("abcdefgh", 4), ("abcd", "efgh"));
+    }
+}
@@ -2,18 +2,24 @@
 
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use axum::{
-    extract::{ConnectInfo, State, WebSocketUpgrade},
-    response::IntoResponse,
+    extract::ws::close_code,
+    extract::{ConnectInfo, Query, Request, State, WebSocketUpgrade},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
     routing::get,
     Router,
 };
+use rusty_claw_plugins::{HookContext, HookEvent};
 use serde_json::json;
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::canvas::canvas_ws_handler;
-use crate::connection::handle_ws_connection;
+use crate::connection::{authenticate_token, handle_ws_connection, TokenQuery};
+use crate::health::HealthStatus;
 use crate::state::GatewayState;
 
 /// Start the gateway WebSocket server.
@@ -31,7 +37,8 @@ pub async fn start_gateway(
         .and_then(|g| g.bind.clone())
         .unwrap_or_else(|| "0.0.0.0".to_string());
 
-    // /ws and /health are registered first so they take priority over the UI catch-all
+    // /ws, /health and /metrics are registered first so they take priority
+    // over the UI catch-all
     let app = Router::new()
         .route("/ws", get(ws_handler))
         .route("/health", get(health_handler))
@@ -42,6 +49,20 @@ pub async fn start_gateway(
 
     let mut app = app.with_state(state.clone());
 
+    // Plugin routes are namespaced by plugin ID so plugins cannot collide,
+    // and mounted before the UI so its catch-all cannot shadow them
+    for (plugin_id, router) in plugin_routers(&state) {
+        if !is_route_safe_id(&plugin_id) {
+            warn!(
+                plugin_id = %plugin_id,
+                "Plugin ID cannot be used in a URL path, skipping its routes"
+            );
+            continue;
+        }
+        app = app.nest(&format!("/plugins/{plugin_id}"), router);
+        info!(plugin_id = %plugin_id, "Mounted plugin routes at /plugins/{plugin_id}");
+    }
+
     if ui_enabled {
         app = app.merge(rusty_claw_web::ui_router());
         info!("Control UI available at http://{bind_addr}:{port}/");
@@ -49,6 +70,35 @@ pub async fn start_gateway(
 
     let addr = format!("{bind_addr}:{port}");
 
+    // Reload the config when the file changes on disk; the watcher stops
+    // when it is dropped with the server
+    let _config_watcher = match &state.config_path {
+        Some(path) => match crate::hot_reload::ConfigWatcher::start_shared(path.clone(), state.config.clone()) {
+            Ok((watcher, changes)) => {
+                tokio::spawn(crate::hot_reload::apply_config_changes(state.clone(), changes));
+                Some(watcher)
+            }
+            Err(e) => {
+                tracing::warn!(%e, "Config hot-reload unavailable");
+                None
+            }
+        },
+        None => None,
+    };
+
+    // Pick up added or edited skill files without a restart
+    let skills_dir = config.skills_dir();
+    let _skills_watcher = if skills_dir.is_dir() {
+        crate::skills::watch_skills_dir(state.clone(), &skills_dir)
+            .inspect_err(|e| tracing::warn!(%e, "Skills hot-reload unavailable"))
+            .ok()
+    } else {
+        None
+    };
+
+    // Keep the cached health report fresh; stops with the server
+    let _health_monitor = crate::health::HealthMonitor::start(state.clone());
+
     // Check for TLS config
     #[cfg(feature = "tls")]
     if let Some(tls_config) = config.gateway.as_ref().and_then(|g| g.tls.as_ref()) {
@@ -59,7 +109,16 @@ pub async fn start_gateway(
 
         info!("Gateway listening on {addr} (TLS enabled)");
         let socket_addr: SocketAddr = addr.parse()?;
+        let handle = axum_server::Handle::new();
+        let shutdown_handle = handle.clone();
+        let shutdown_state = state.clone();
+        tokio::spawn(async move {
+            shutdown_signal(shutdown_state, port).await;
+            shutdown_handle.graceful_shutdown(Some(CLOSE_TIMEOUT));
+        });
+        fire_lifecycle_hook(&state, HookEvent::GatewayStart, port).await;
         axum_server::bind_rustls(socket_addr, tls)
+            .handle(handle)
             .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await?;
 
@@ -69,29 +128,117 @@ pub async fn start_gateway(
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     info!("Gateway listening on {addr}");
 
+    fire_lifecycle_hook(&state, HookEvent::GatewayStart, port).await;
     axum::serve(
         listener,
         app.into_make_service_with_connect_info::<SocketAddr>(),
     )
-    .with_graceful_shutdown(shutdown_signal(state.clone()))
+    .with_graceful_shutdown(shutdown_signal(state.clone(), port))
     .await?;
 
     Ok(())
 }
 
+/// Whether a plugin ID is a single plain URL path segment.
+/// One router per plugin: its authenticated routes behind [`plugin_auth`],
+/// merged with its public routes.
+fn plugin_routers(state: &Arc<GatewayState>) -> Vec<(String, Router)> {
+    let mut routers: Vec<(String, Router)> = Vec::new();
+    for (plugin_id, router) in &state.plugin_routes {
+        let auth = axum::middleware::from_fn_with_state(
+            (state.clone(), plugin_id.clone()),
+            plugin_auth,
+        );
+        routers.push((plugin_id.clone(), router.clone().layer(auth)));
+    }
+    for (plugin_id, router) in &state.public_plugin_routes {
+        match routers.iter_mut().find(|(id, _)| id == plugin_id) {
+            Some((_, existing)) => *existing = std::mem::take(existing).merge(router.clone()),
+            None => routers.push((plugin_id.clone(), router.clone())),
+        }
+    }
+    routers
+}
+
+/// Authenticate a request to a plugin route like a `/ws` connect, from an
+/// `Authorization: Bearer` header or a `token` query parameter. API keys
+/// need the `plugins.{plugin_id}` scope.
+async fn plugin_auth(
+    State((state, plugin_id)): State<(Arc<GatewayState>, String)>,
+    Query(query): Query<TokenQuery>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(String::from);
+    let token = bearer.or(query.token);
+    let config = state.read_config().await;
+    match authenticate_token(&config, &state.login_tokens, token.as_deref()) {
+        Ok(Some(key)) if !key.allows(&format!("plugins.{plugin_id}")) => (
+            StatusCode::FORBIDDEN,
+            format!("API key '{}' is not allowed to use plugin '{plugin_id}'", key.name),
+        )
+            .into_response(),
+        Ok(_) => {
+            drop(config);
+            next.run(request).await
+        }
+        Err(reason) => (StatusCode::UNAUTHORIZED, reason).into_response(),
+    }
+}
+
+fn is_route_safe_id(id: &str) -> bool {
+    !id.is_empty()
+        && !id.starts_with(':')
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | ':'))
+}
+
+/// Fire `GatewayStart` or `GatewayStop` with what plugins need to set up or
+/// tear down: the bound address, enabled channels and provider IDs.
+async fn fire_lifecycle_hook(state: &Arc<GatewayState>, event: HookEvent, port: u16) {
+    let config = state.read_config().await;
+    let mut data = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "bind": config.gateway.as_ref().and_then(|g| g.bind.clone()).unwrap_or_else(|| "0.0.0.0".into()),
+        "port": port,
+        "tls": config.gateway.as_ref().is_some_and(|g| g.tls.is_some()),
+        "channels": state.channels.list(),
+        "providers": state.providers.list_ids(),
+    });
+    if event == HookEvent::GatewayStop {
+        data["uptime_secs"] = json!(state.startup_time.elapsed().as_secs());
+    }
+    let ctx = HookContext {
+        session_key: String::new(),
+        timestamp: chrono::Utc::now(),
+        metadata: Default::default(),
+    };
+    state.hooks.fire(event, ctx, data).await;
+}
+
 async fn ws_handler(
     ws: WebSocketUpgrade,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     State(state): State<Arc<GatewayState>>,
 ) -> impl IntoResponse {
+    if state.shutdown.is_cancelled() {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+
     // Rate limiting check
     if let Some(limiter) = &state.rate_limiter {
         if !limiter.check(addr.ip()) {
-            return axum::http::StatusCode::TOO_MANY_REQUESTS.into_response();
+            return StatusCode::TOO_MANY_REQUESTS.into_response();
         }
     }
 
-    ws.on_upgrade(move |socket| handle_ws_connection(state, socket))
+    ws.on_upgrade(move |socket| handle_ws_connection(state, socket, Some(addr.ip())))
         .into_response()
 }
 
@@ -101,40 +248,19 @@ async fn health_handler(State(state): State<Arc<GatewayState>>) -> impl IntoResp
     let uptime_seconds = state.startup_time.elapsed().as_secs();
     let active_agents = state.active_agents.read().await.len();
 
-    // Provider reachability (lightweight: just check if they exist)
-    let providers: Vec<serde_json::Value> = state
-        .providers
-        .list_ids()
-        .iter()
-        .map(|id| {
-            json!({
-                "id": id,
-                "reachable": state.providers.get(id).is_some(),
-            })
-        })
-        .collect();
-
-    // Channel status
-    let mut channels = Vec::new();
-    for ch_id in state.channels.list() {
-        if let Some(ch) = state.channels.get(ch_id) {
-            let status = ch.status().await;
-            channels.push(json!({
-                "id": ch_id,
-                "connected": status.connected,
-            }));
-        }
-    }
-
-    axum::Json(json!({
-        "status": "ok",
-        "version": version,
-        "connections": connections,
-        "uptime_seconds": uptime_seconds,
-        "active_agents": active_agents,
-        "providers": providers,
-        "channels": channels,
-    }))
+    // Component checks come from the cached report
+    let report = crate::health::current(&state).await;
+    let code = match report.status {
+        HealthStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::OK,
+    };
+    let mut body = serde_json::to_value(&report).unwrap_or_default();
+    body["version"] = json!(version);
+    body["connections"] = json!(connections);
+    body["uptime_seconds"] = json!(uptime_seconds);
+    body["active_agents"] = json!(active_agents);
+
+    (code, axum::Json(body))
 }
 
 #[cfg(feature = "metrics")]
@@ -145,7 +271,7 @@ async fn metrics_handler(State(state): State<Arc<GatewayState>>) -> impl IntoRes
     }
 }
 
-async fn shutdown_signal(state: Arc<GatewayState>) {
+async fn shutdown_signal(state: Arc<GatewayState>, port: u16) {
     let ctrl_c = tokio::signal::ctrl_c();
 
     #[cfg(unix)]
@@ -165,95 +291,96 @@ async fn shutdown_signal(state: Arc<GatewayState>) {
     }
 
     info!("Shutdown signal received, draining connections...");
-    graceful_drain(&state).await;
+    graceful_drain(&state, port).await;
 }
 
-/// Cancel active agents and wait for connections to drain (up to 30s).
-async fn graceful_drain(state: &Arc<GatewayState>) {
-    // Cancel all active agents
-    {
+/// Time cancelled agent runs get to stop and save their sessions.
+const CANCELLED_RUN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time clients get to acknowledge the close frame.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Stop accepting work, let in-flight agent runs finish within the grace
+/// period (cancelling any still running after it), fire `GatewayStop`, and
+/// close WebSockets with a going-away close frame.
+async fn graceful_drain(state: &Arc<GatewayState>, port: u16) {
+    state.shutdown.cancel();
+
+    // Runs hold their active_agents entry until their session is saved
+    let grace = Duration::from_secs(state.read_config().await.shutdown_grace_secs());
+    let runs_done = || async {
+        state.active_agents.read().await.is_empty() && state.spawned_agents.lock().unwrap().is_empty()
+    };
+    if !wait_until(grace, runs_done).await {
         let agents = state.active_agents.read().await;
-        let count = agents.len();
         for token in agents.values() {
             token.cancel();
         }
-        if count > 0 {
-            info!(count, "Cancelled active agents");
+        let mut count = agents.len();
+        drop(agents);
+        // Children of a finished parent are not covered by its token
+        for child in state.spawned_agents.lock().unwrap().values() {
+            child.cancel.cancel();
+            count += 1;
+        }
+        info!(count, "Grace period over, cancelled active agents");
+        if !wait_until(CANCELLED_RUN_TIMEOUT, runs_done).await {
+            warn!("Cancelled agent runs did not finish in time");
         }
     }
 
-    // Wait for connections to drain (poll every 500ms, max 30s)
-    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(30);
+    // --- Hook: GatewayStop (before sockets and other resources go) ---
+    fire_lifecycle_hook(state, HookEvent::GatewayStop, port).await;
+
+    // Close sockets after anything still queued for them
+    for conn in state.connections.read().await.values() {
+        conn.event_tx.close(close_code::AWAY, "shutdown");
+    }
+    let connections_closed = || async { state.connections.read().await.is_empty() };
+    if !wait_until(CLOSE_TIMEOUT, connections_closed).await {
+        let remaining = state.connections.read().await.len();
+        info!(remaining, "Drain timeout, forcing shutdown");
+    }
+
+    let uptime = state.startup_time.elapsed();
+    info!(uptime_secs = uptime.as_secs(), "Gateway shutting down");
+}
+
+/// Poll `done` every 100ms until it holds or `timeout` passes.
+async fn wait_until<F, Fut>(timeout: Duration, done: F) -> bool
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    let deadline = tokio::time::Instant::now() + timeout;
     loop {
-        let conn_count = state.connections.read().await.len();
-        if conn_count == 0 {
-            break;
+        if done().await {
+            return true;
         }
         if tokio::time::Instant::now() >= deadline {
-            info!(remaining = conn_count, "Drain timeout, forcing shutdown");
-            break;
+            return false;
         }
-        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
     }
-
-    let uptime = state.startup_time.elapsed();
-    info!(uptime_secs = uptime.as_secs(), "Gateway shutting down");
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    /// Helper to build a minimal GatewayState for testing.
-    async fn test_state() -> Arc<GatewayState> {
-        let config = rusty_claw_core::config::Config::default();
-        let config_rw = Arc::new(tokio::sync::RwLock::new(config));
-
-        let sessions: Arc<dyn rusty_claw_core::session::SessionStore> = Arc::new(
-            rusty_claw_core::session_store::JsonlSessionStore::new(
-                std::env::temp_dir().join(format!(
-                    "rusty-claw-test-drain-{}",
-                    std::process::id()
-                )),
-            ),
-        );
-
-        let channels = Arc::new(rusty_claw_channels::ChannelRegistry::new());
-        let mut tools = rusty_claw_tools::ToolRegistry::new();
-        rusty_claw_tools::register_builtin_tools(&mut tools);
-        let tools = Arc::new(tools);
-
-        let providers = Arc::new(rusty_claw_providers::ProviderRegistry::new(
-            "none".into(),
-        ));
-
-        let hooks = Arc::new(rusty_claw_plugins::HookRegistry::new());
-        let skills = crate::skills::SkillRegistry::new();
-        let pairing = rusty_claw_core::pairing::PairingStore::new(
-            std::env::temp_dir().join(format!(
-                "rusty-claw-pairing-drain-{}",
-                std::process::id()
-            )),
-        );
-
-        Arc::new(GatewayState::new(
-            config_rw,
-            None,
-            sessions,
-            channels,
-            tools,
-            providers,
-            hooks,
-            skills,
-            pairing,
-            None,
-            None,
-        ))
-    }
-
     #[tokio::test]
     async fn test_graceful_drain_cancels_agents() {
-        let state = test_state().await;
+        let dir = tempfile::tempdir().unwrap();
+        let state = Arc::new(crate::state::test_state(dir.path()));
+        state.config.write().await.gateway = Some(rusty_claw_core::config::GatewayConfig {
+            port: 18789,
+            bind: None,
+            auth: None,
+            tls: None,
+            rate_limit: None,
+            tailscale: None,
+            shutdown_grace_secs: Some(0),
+        });
 
         // Register an active agent with a CancellationToken
         let token = tokio_util::sync::CancellationToken::new();
@@ -266,20 +393,121 @@ mod tests {
         // The token should not be cancelled yet
         assert!(!token.is_cancelled());
 
+        // Like a real run, stop when cancelled and then release the entry
+        let run_state = state.clone();
+        let run_token = token.clone();
+        let run = tokio::spawn(async move {
+            run_token.cancelled().await;
+            run_state.active_agents.write().await.remove("test-agent-1");
+        });
+
         // Call graceful_drain — it should cancel the agent token
-        graceful_drain(&state).await;
+        graceful_drain(&state, 18789).await;
 
-        // Verify the token has been cancelled
+        // Verify the token has been cancelled and the run finished
         assert!(token.is_cancelled());
+        assert!(run.is_finished());
+        assert!(state.shutdown.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_graceful_drain_cancels_spawned_children() {
+        use rusty_claw_providers::testing::{ChunkStream, StubProvider};
+        use rusty_claw_providers::{Credentials, ProviderRegistry};
+
+        use crate::spawn::{SpawnRequest, spawn_agent};
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = crate::state::test_state(dir.path());
+        // A child whose model never answers
+        let mut providers = ProviderRegistry::new("hanging".into());
+        providers.register(
+            "hanging".into(),
+            Arc::new(StubProvider::new(|_, _| async {
+                Ok(Box::pin(futures::stream::pending()) as ChunkStream)
+            })),
+            Credentials::ApiKey { api_key: "k".into() },
+        );
+        state.providers = Arc::new(providers);
+        let state = Arc::new(state);
+        state.config.write().await.gateway = Some(rusty_claw_core::config::GatewayConfig {
+            port: 18789,
+            bind: None,
+            auth: None,
+            tls: None,
+            rate_limit: None,
+            tailscale: None,
+            shutdown_grace_secs: Some(0),
+        });
+
+        let request = SpawnRequest {
+            task: "never finishes".into(),
+            ..Default::default()
+        };
+        let child = spawn_agent(&state, request.clone()).await.unwrap();
+        assert_eq!(state.spawned_agents.lock().unwrap().len(), 1);
+
+        graceful_drain(&state, 18789).await;
+        assert!(state.spawned_agents.lock().unwrap().is_empty());
+        tokio::time::timeout(Duration::from_secs(1), child.wait())
+            .await
+            .expect("cancelled child finished")
+            .ok();
+
+        // No new children once shutdown began
+        let err = spawn_agent(&state, request).await.err().unwrap();
+        assert_eq!(err.code(), "shutting_down");
+    }
+
+    #[tokio::test]
+    async fn test_graceful_drain_fires_gateway_stop() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = Arc::new(crate::state::test_state(dir.path()));
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        state
+            .hooks
+            .register(
+                HookEvent::GatewayStop,
+                Box::new(move |_ctx, data| {
+                    let _ = tx.send(data);
+                    Box::pin(async { Ok(rusty_claw_plugins::HookResult::Continue) })
+                }),
+            )
+            .await;
+
+        graceful_drain(&state, 18789).await;
+        let data = rx.try_recv().expect("GatewayStop fired");
+        assert_eq!(data["port"], 18789);
+        assert!(data["providers"].is_array());
+        assert!(data["uptime_secs"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn test_graceful_drain_waits_for_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = Arc::new(crate::state::test_state(dir.path()));
+        let token = tokio_util::sync::CancellationToken::new();
+        state.active_agents.write().await.insert("test-agent-2".into(), token.clone());
+
+        // A run that completes on its own within the grace period
+        let run_state = state.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            run_state.active_agents.write().await.remove("test-agent-2");
+        });
+
+        graceful_drain(&state, 18789).await;
+        assert!(!token.is_cancelled());
     }
 
     #[tokio::test]
     async fn test_graceful_drain_timeout() {
-        let state = test_state().await;
+        let dir = tempfile::tempdir().unwrap();
+        let state = Arc::new(crate::state::test_state(dir.path()));
         // No active agents, no connections — drain should complete quickly
 
         let start = std::time::Instant::now();
-        graceful_drain(&state).await;
+        graceful_drain(&state, 18789).await;
         let elapsed = start.elapsed();
 
         // With no agents and no connections, drain should complete well under 30s
@@ -0,0 +1,88 @@
+//! `SessionStart` and `SessionEnd` hooks.
+//!
+//! `SessionStart` fires when an agent run picks up its session, with `is_new`
+//! telling a freshly created session from a resumed one, so a plugin can greet
+//! new users or load external context. `SessionEnd` fires when a session is
+//! deleted or reset. Both carry the session key and channel; the hook
+//! context's `session_key` is the key's hash.
+
+use std::collections::HashMap;
+
+use serde_json::json;
+
+use rusty_claw_core::session::SessionKey;
+use rusty_claw_plugins::{HookContext, HookEvent, HookRegistry};
+
+fn hook_ctx(key: &SessionKey) -> HookContext {
+    HookContext {
+        session_key: key.hash_key(),
+        timestamp: chrono::Utc::now(),
+        metadata: HashMap::from([("channel".to_string(), json!(key.channel))]),
+    }
+}
+
+/// Fire `SessionStart` for a run on `key`.
+pub async fn fire_session_start(hooks: &HookRegistry, key: &SessionKey, is_new: bool) {
+    let data = json!({
+        "key": key,
+        "channel": key.channel,
+        "is_new": is_new,
+    });
+    hooks.fire(HookEvent::SessionStart, hook_ctx(key), data).await;
+}
+
+/// Fire `SessionEnd` for a session ended by `reason` (`delete` or `reset`).
+pub async fn fire_session_end(hooks: &HookRegistry, key: &SessionKey, reason: &str) {
+    let data = json!({
+        "key": key,
+        "channel": key.channel,
+        "reason": reason,
+    });
+    hooks.fire(HookEvent::SessionEnd, hook_ctx(key), data).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_claw_core::session::SessionScope;
+    use rusty_claw_core::types::ChatType;
+    use rusty_claw_plugins::HookResult;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_session_hooks_carry_key_and_channel() {
+        let hooks = HookRegistry::new();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        for event in [HookEvent::SessionStart, HookEvent::SessionEnd] {
+            let tx = tx.clone();
+            hooks
+                .register(
+                    event,
+                    Box::new(move |ctx, data| {
+                        let _ = tx.send((ctx, data));
+                        Box::pin(async { Ok(HookResult::Continue) })
+                    }),
+                )
+                .await;
+        }
+
+        let key = SessionKey {
+            channel: "telegram".into(),
+            account_id: "bot".into(),
+            chat_type: ChatType::Dm,
+            peer_id: "alice".into(),
+            scope: SessionScope::PerSender,
+        };
+        fire_session_start(&hooks, &key, true).await;
+        let (ctx, data) = rx.try_recv().unwrap();
+        assert_eq!(ctx.session_key, key.hash_key());
+        assert_eq!(ctx.metadata["channel"], "telegram");
+        assert_eq!(data["is_new"], true);
+        assert_eq!(data["key"]["peer_id"], "alice");
+
+        fire_session_end(&hooks, &key, "reset").await;
+        let (_, data) = rx.try_recv().unwrap();
+        assert_eq!(data["reason"], "reset");
+        assert_eq!(data["channel"], "telegram");
+    }
+}
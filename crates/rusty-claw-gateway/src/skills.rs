@@ -1,14 +1,58 @@
 //! Skill registry — loads and manages YAML skill definitions.
+//!
+//! With `skills.auto_activate`, each agent turn also activates the skills
+//! whose name, tags or description match the inbound message (see
+//! [`SkillRegistry::skills_for_turn`]).
+//!
+//! The registry is rebuilt by the `skills.reload` gateway method and by
+//! [`watch_skills_dir`] when files in the skills directory change. A file
+//! that fails to parse is reported in [`SkillRegistry::errors`] without
+//! affecting the other skills.
 
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use serde_json::json;
+
+use rusty_claw_core::config::Config;
 use rusty_claw_core::skills::SkillDefinition;
 use tracing::{debug, info, warn};
 
+use crate::state::GatewayState;
+
+/// Most skills auto-activated for one turn.
+const MAX_AUTO_SKILLS: usize = 3;
+
+/// Score a skill needs to auto-activate: one tag or name match, or two
+/// description keywords.
+const AUTO_ACTIVATE_SCORE: u32 = 2;
+
+/// Words too common to count as description keywords.
+const STOPWORDS: &[&str] = &[
+    "about", "after", "also", "and", "any", "are", "been", "before", "but", "can", "could", "does",
+    "for", "from", "have", "help", "into", "just", "like", "make", "more", "need", "other", "please",
+    "should", "some", "than", "that", "the", "them", "then", "there", "these", "they", "this",
+    "what", "when", "where", "which", "while", "will", "with", "would", "your",
+];
+
+/// Changes in the skills directory are coalesced over this window.
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// A skill file that could not be loaded.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkillLoadError {
+    pub path: PathBuf,
+    pub error: String,
+}
+
 /// Registry of loaded skill definitions.
 pub struct SkillRegistry {
     skills: HashMap<String, SkillDefinition>,
+    errors: Vec<SkillLoadError>,
 }
 
 impl Default for SkillRegistry {
@@ -19,7 +63,10 @@ impl Default for SkillRegistry {
 
 impl SkillRegistry {
     pub fn new() -> Self {
-        Self { skills: HashMap::new() }
+        Self {
+            skills: HashMap::new(),
+            errors: Vec::new(),
+        }
     }
 
     /// Load all `.yaml`/`.yml` skills from a directory.
@@ -49,6 +96,10 @@ impl SkillRegistry {
                     }
                     Err(e) => {
                         warn!(%e, path = %path.display(), "Failed to load skill");
+                        registry.errors.push(SkillLoadError {
+                            path,
+                            error: e.to_string(),
+                        });
                     }
                 }
             }
@@ -63,6 +114,11 @@ impl SkillRegistry {
         *self = Self::load_from_dir(dir);
     }
 
+    /// Files that failed to load at the last (re)load.
+    pub fn errors(&self) -> &[SkillLoadError] {
+        &self.errors
+    }
+
     /// Get a skill by name.
     pub fn get(&self, name: &str) -> Option<&SkillDefinition> {
         self.skills.get(name)
@@ -77,4 +133,195 @@ impl SkillRegistry {
     pub fn all(&self) -> Vec<&SkillDefinition> {
         self.skills.values().collect()
     }
+
+    /// Skills whose name, tags or description match `text`, best first.
+    pub fn matching(&self, text: &str) -> Vec<&SkillDefinition> {
+        let words = words(text);
+        let mut scored: Vec<(u32, &SkillDefinition)> = self
+            .skills
+            .values()
+            .map(|skill| (match_score(skill, &words), skill))
+            .filter(|(score, _)| *score >= AUTO_ACTIVATE_SCORE)
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.name.cmp(&b.1.name)));
+        scored
+            .into_iter()
+            .take(MAX_AUTO_SKILLS)
+            .map(|(_, skill)| skill)
+            .collect()
+    }
+
+    /// Skills to activate for a turn: the session's selected skill, plus
+    /// skills matching the message when `skills.auto_activate` is on.
+    pub fn skills_for_turn(
+        &self,
+        config: &Config,
+        session_skill: Option<&str>,
+        text: Option<&str>,
+    ) -> Vec<SkillDefinition> {
+        let mut active: Vec<&SkillDefinition> = session_skill.and_then(|name| self.get(name)).into_iter().collect();
+        let auto_activate = config.skills.as_ref().is_some_and(|s| s.auto_activate);
+        if auto_activate && let Some(text) = text {
+            for skill in self.matching(text) {
+                if !active.iter().any(|s| s.name == skill.name) {
+                    active.push(skill);
+                }
+            }
+        }
+        active.into_iter().cloned().collect()
+    }
+}
+
+/// Reload the gateway's skills from the configured directory, swapping the
+/// registry and announcing the new set with a `skills.changed` event.
+pub async fn reload_skills(state: &Arc<GatewayState>) -> serde_json::Value {
+    let dir = state.read_config().await.skills_dir();
+    let registry = SkillRegistry::load_from_dir(&dir);
+    let mut names: Vec<String> = registry.list().into_iter().map(String::from).collect();
+    names.sort();
+    let summary = json!({
+        "dir": dir,
+        "count": names.len(),
+        "skills": names,
+        "errors": registry.errors(),
+    });
+    *state.skills.write().await = registry;
+    crate::events::broadcast_event(state, "skills.changed", Some(summary.clone())).await;
+    summary
+}
+
+/// Reload skills whenever a file in the skills directory changes. The
+/// returned watcher stops watching when dropped.
+pub fn watch_skills_dir(state: Arc<GatewayState>, dir: &Path) -> anyhow::Result<notify::RecommendedWatcher> {
+    let (notify_tx, mut notify_rx) = tokio::sync::mpsc::unbounded_channel::<()>();
+    let mut watcher = notify::recommended_watcher(move |res: Result<notify::Event, notify::Error>| {
+        match res {
+            Ok(event)
+                if matches!(
+                    event.kind,
+                    EventKind::Modify(_) | EventKind::Create(_) | EventKind::Remove(_)
+                ) =>
+            {
+                let _ = notify_tx.send(());
+            }
+            Ok(_) => {}
+            Err(e) => warn!(%e, "Skills directory watch error"),
+        }
+    })?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    info!(dir = %dir.display(), "Skills directory watcher started");
+
+    tokio::spawn(async move {
+        while notify_rx.recv().await.is_some() {
+            tokio::time::sleep(RELOAD_DEBOUNCE).await;
+            while notify_rx.try_recv().is_ok() {}
+            let summary = reload_skills(&state).await;
+            info!(count = %summary["count"], "Skills reloaded");
+        }
+    });
+    Ok(watcher)
+}
+
+/// Lowercase words of `text`, split on anything but letters and digits.
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Whether every word of `phrase` occurs in `words`.
+fn phrase_matches(phrase: &str, words: &HashSet<String>) -> bool {
+    let phrase = self::words(phrase);
+    !phrase.is_empty() && phrase.iter().all(|w| words.contains(w))
+}
+
+/// Two points per matching tag or for the skill name, one per description
+/// keyword (four letters or longer, not a stopword).
+fn match_score(skill: &SkillDefinition, words: &HashSet<String>) -> u32 {
+    let mut score = 0;
+    if phrase_matches(&skill.name, words) {
+        score += 2;
+    }
+    score += 2 * skill.tags.iter().filter(|t| phrase_matches(t, words)).count() as u32;
+    let keywords: HashSet<String> = self::words(&skill.description)
+        .into_iter()
+        .filter(|w| w.chars().count() >= 4 && !STOPWORDS.contains(&w.as_str()))
+        .collect();
+    score += keywords.iter().filter(|k| words.contains(*k)).count() as u32;
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skill(name: &str, description: &str, tags: &[&str]) -> SkillDefinition {
+        SkillDefinition {
+            name: name.into(),
+            description: description.into(),
+            system_prompt: format!("You are in {name} mode."),
+            tools: vec![],
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            examples: vec![],
+            file_path: Default::default(),
+        }
+    }
+
+    fn registry() -> SkillRegistry {
+        let mut registry = SkillRegistry::new();
+        for s in [
+            skill("code_review", "Review code for bugs, style, and best practices", &["review", "pull request"]),
+            skill("travel", "Plan trips, flights and hotel bookings", &["travel"]),
+        ] {
+            registry.skills.insert(s.name.clone(), s);
+        }
+        registry
+    }
+
+    #[test]
+    fn test_bad_skill_file_reported_without_dropping_others() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("good.yaml"),
+            "name: good\ndescription: A working skill\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("bad.yaml"), "name: [unclosed\n").unwrap();
+
+        let registry = SkillRegistry::load_from_dir(dir.path());
+        assert_eq!(registry.list(), vec!["good"]);
+        assert_eq!(registry.errors().len(), 1);
+        assert!(registry.errors()[0].path.ends_with("bad.yaml"));
+    }
+
+    #[test]
+    fn test_matching_by_tag_name_and_description() {
+        let registry = registry();
+        // Tag phrase
+        let names: Vec<_> = registry.matching("Can you look at my pull request?").iter().map(|s| s.name.clone()).collect();
+        assert_eq!(names, vec!["code_review"]);
+        // Skill name
+        assert_eq!(registry.matching("code review please")[0].name, "code_review");
+        // Two description keywords
+        assert_eq!(registry.matching("Book flights and a hotel for May")[0].name, "travel");
+        // One keyword alone is not enough
+        assert!(registry.matching("The hotel wifi is down").is_empty());
+    }
+
+    #[test]
+    fn test_skills_for_turn_respects_auto_activate() {
+        let registry = registry();
+        let mut config = Config::default();
+        let text = Some("Review my pull request");
+        assert!(registry.skills_for_turn(&config, None, text).is_empty());
+
+        config.skills = Some(rusty_claw_core::config::SkillsConfig {
+            auto_activate: true,
+            ..Default::default()
+        });
+        let active = registry.skills_for_turn(&config, Some("travel"), text);
+        let names: Vec<_> = active.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["travel", "code_review"]);
+    }
 }
@@ -0,0 +1,426 @@
+//! Spawning child agents.
+//!
+//! A spawned agent runs in its own session on a background task. The caller
+//! gets a [`SpawnedAgent`] it can [`wait`](SpawnedAgent::wait) on for the
+//! child's [`AgentRunResult`], or drop to let the child run on its own. Either
+//! way a `child.completed` event carrying the child session key and its final
+//! payload is broadcast when the run ends, so parents and UIs can correlate
+//! asynchronous results.
+//!
+//! Besides the depth limit, spawns are capped by the number of spawned agents
+//! running gateway-wide and by the number running for one parent session.
+//! Each running child holds a [`SpawnSlot`] in [`GatewayState::spawned_agents`]
+//! that is released when its task ends, including by panic.
+//!
+//! A child's cancel token is a child of its parent's run token, so aborting
+//! the parent aborts its children; shutdown cancels them directly.
+
+use std::fmt;
+use std::sync::Arc;
+
+use serde_json::json;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use rusty_claw_agent::{AgentRunOptions, AgentRunResult};
+use rusty_claw_core::session::{Session, SessionKey, SessionScope};
+use rusty_claw_core::types::{ChatType, InboundMessage};
+
+use crate::events::broadcast_event;
+use crate::state::GatewayState;
+
+/// What to spawn.
+#[derive(Debug, Clone, Default)]
+pub struct SpawnRequest {
+    /// Task given to the child as its first message.
+    pub task: String,
+    /// Model override for the child.
+    pub model: Option<String>,
+    /// Spawn depth of the parent (0 for a top-level run).
+    pub parent_depth: u32,
+    /// Session key of the parent, recorded on the child and echoed in
+    /// `child.completed`.
+    pub parent_session_key: Option<String>,
+}
+
+/// Why a child could not be spawned.
+#[derive(Debug)]
+pub enum SpawnError {
+    DepthExceeded { max_depth: u32 },
+    TooManyAgents { limit: u32 },
+    FanoutExceeded { limit: u32 },
+    NoProvider,
+    ShuttingDown,
+    Session(anyhow::Error),
+}
+
+impl SpawnError {
+    /// Error code reported to gateway clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::DepthExceeded { .. } => "spawn_depth_exceeded",
+            Self::TooManyAgents { .. } => "spawn_limit_exceeded",
+            Self::FanoutExceeded { .. } => "spawn_fanout_exceeded",
+            Self::NoProvider => "no_provider",
+            Self::ShuttingDown => "shutting_down",
+            Self::Session(_) => "session_error",
+        }
+    }
+}
+
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DepthExceeded { max_depth } => write!(f, "Max spawn depth of {max_depth} exceeded"),
+            Self::TooManyAgents { limit } => {
+                write!(f, "Too many spawned agents running (limit {limit})")
+            }
+            Self::FanoutExceeded { limit } => {
+                write!(f, "Parent already has {limit} spawned agents running")
+            }
+            Self::NoProvider => write!(f, "No provider configured for spawned agent"),
+            Self::ShuttingDown => write!(f, "Gateway is shutting down"),
+            Self::Session(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for SpawnError {}
+
+/// A running child in [`GatewayState::spawned_agents`].
+#[derive(Debug, Clone)]
+pub struct SpawnedChild {
+    /// Session key of the parent, if any.
+    pub parent: Option<String>,
+    /// Cancels the child's run.
+    pub cancel: CancellationToken,
+}
+
+/// A child's entry in [`GatewayState::spawned_agents`], removed on drop.
+pub struct SpawnSlot {
+    state: Arc<GatewayState>,
+    child: String,
+}
+
+impl Drop for SpawnSlot {
+    fn drop(&mut self) {
+        if let Ok(mut agents) = self.state.spawned_agents.lock() {
+            agents.remove(&self.child);
+        }
+    }
+}
+
+/// Claim a slot for `child` under the global and per-parent limits.
+fn reserve_slot(
+    state: &Arc<GatewayState>,
+    child: &str,
+    parent: Option<&str>,
+    cancel: CancellationToken,
+    max_agents: u32,
+    max_fanout: u32,
+) -> Result<SpawnSlot, SpawnError> {
+    let mut agents = state.spawned_agents.lock().unwrap();
+    if agents.len() >= max_agents as usize {
+        return Err(SpawnError::TooManyAgents { limit: max_agents });
+    }
+    if let Some(parent) = parent {
+        let siblings = agents
+            .values()
+            .filter(|c| c.parent.as_deref() == Some(parent))
+            .count();
+        if siblings >= max_fanout as usize {
+            return Err(SpawnError::FanoutExceeded { limit: max_fanout });
+        }
+    }
+    agents.insert(
+        child.to_string(),
+        SpawnedChild {
+            parent: parent.map(String::from),
+            cancel,
+        },
+    );
+    Ok(SpawnSlot {
+        state: state.clone(),
+        child: child.to_string(),
+    })
+}
+
+/// A running child agent.
+pub struct SpawnedAgent {
+    /// Hash key of the child's session.
+    pub session_key: String,
+    handle: tokio::task::JoinHandle<anyhow::Result<AgentRunResult>>,
+}
+
+impl SpawnedAgent {
+    /// Wait for the child run to finish.
+    pub async fn wait(self) -> anyhow::Result<AgentRunResult> {
+        self.handle.await?
+    }
+}
+
+/// The run token of `parent`, whether it is a top-level run or a spawned
+/// child itself.
+async fn parent_cancel_token(state: &GatewayState, parent: &str) -> Option<CancellationToken> {
+    if let Some(token) = state.active_agents.read().await.get(parent) {
+        return Some(token.clone());
+    }
+    let agents = state.spawned_agents.lock().unwrap();
+    agents.get(parent).map(|c| c.cancel.clone())
+}
+
+/// Create a child session for `request` and start its agent run.
+pub async fn spawn_agent(state: &Arc<GatewayState>, request: SpawnRequest) -> Result<SpawnedAgent, SpawnError> {
+    if state.shutdown.is_cancelled() {
+        return Err(SpawnError::ShuttingDown);
+    }
+    let config = state.read_config().await;
+    let max_depth = config.max_spawn_depth();
+    if request.parent_depth >= max_depth {
+        return Err(SpawnError::DepthExceeded { max_depth });
+    }
+
+    let child_key = SessionKey {
+        channel: "spawned".into(),
+        account_id: "agent".into(),
+        chat_type: ChatType::Dm,
+        peer_id: format!("spawn-{}", uuid::Uuid::new_v4()),
+        scope: SessionScope::PerSender,
+    };
+    let child_hash = child_key.hash_key();
+    let cancel = match request.parent_session_key.as_deref() {
+        Some(parent) => parent_cancel_token(state, parent).await.map(|t| t.child_token()),
+        None => None,
+    }
+    .unwrap_or_default();
+    let slot = reserve_slot(
+        state,
+        &child_hash,
+        request.parent_session_key.as_deref(),
+        cancel.clone(),
+        config.max_spawned_agents(),
+        config.max_spawn_fanout(),
+    )?;
+
+    let mut child_session = Session::new(child_key);
+    child_session.meta.spawned_by = Some(
+        request
+            .parent_session_key
+            .clone()
+            .unwrap_or_else(|| "parent".to_string()),
+    );
+    child_session.meta.spawn_depth = request.parent_depth + 1;
+    child_session.meta.model = request.model.clone();
+
+    if state
+        .providers
+        .resolve(child_session.meta.provider_id.as_deref(), child_session.meta.model.as_deref())
+        .is_none()
+    {
+        return Err(SpawnError::NoProvider);
+    }
+
+    state
+        .sessions
+        .save(&child_session)
+        .await
+        .map_err(|e| SpawnError::Session(e.into()))?;
+
+    let state = state.clone();
+    let session_key = child_hash.clone();
+    let handle = tokio::spawn(async move {
+        let _slot = slot;
+        let result = run_child(&state, &mut child_session, &request.task, cancel).await;
+        if let Err(e) = state.sessions.save(&child_session).await {
+            warn!(%e, "Failed to save spawned session");
+        }
+
+        // --- Event: child.completed ---
+        let payload = match &result {
+            Ok(run) => json!({
+                "child_session_key": session_key,
+                "parent_session_key": request.parent_session_key,
+                "ok": true,
+                "text": run.final_text(),
+                "result": run,
+            }),
+            Err(e) => json!({
+                "child_session_key": session_key,
+                "parent_session_key": request.parent_session_key,
+                "ok": false,
+                "error": e.to_string(),
+            }),
+        };
+        broadcast_event(&state, "child.completed", Some(payload)).await;
+        result
+    });
+
+    info!(child = %child_hash, "Spawned child agent");
+    Ok(SpawnedAgent {
+        session_key: child_hash,
+        handle,
+    })
+}
+
+/// Spawn a child, wait for it and return its final reply text.
+pub async fn spawn_and_collect(state: &Arc<GatewayState>, request: SpawnRequest) -> anyhow::Result<String> {
+    let result = spawn_agent(state, request).await?.wait().await?;
+    if let Some(error) = &result.meta.error {
+        anyhow::bail!("Child agent failed: {}", error.message);
+    }
+    Ok(result.final_text().unwrap_or_default().to_string())
+}
+
+async fn run_child(
+    state: &Arc<GatewayState>,
+    session: &mut Session,
+    task: &str,
+    cancel: CancellationToken,
+) -> anyhow::Result<AgentRunResult> {
+    let (_, provider, credentials) = state
+        .providers
+        .resolve(session.meta.provider_id.as_deref(), session.meta.model.as_deref())
+        .ok_or(SpawnError::NoProvider)?;
+    let (event_tx, _event_rx) = tokio::sync::mpsc::unbounded_channel();
+    let config = Arc::new(state.read_config().await);
+    #[cfg(feature = "metrics")]
+    let provider_id = provider.id().to_string();
+    let result = rusty_claw_agent::run_agent_with_options(
+        session,
+        InboundMessage::from_cli_text(task),
+        &config,
+        &state.tools,
+        provider,
+        credentials,
+        event_tx,
+        &state.hooks,
+        AgentRunOptions {
+            cancel: Some(cancel),
+            checkpoint: Some(state.sessions.clone()),
+            ..Default::default()
+        },
+    )
+    .await;
+    #[cfg(feature = "metrics")]
+    crate::metrics::record_agent_run(&provider_id, &result);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_claw_providers::testing::{ChunkStream, StubProvider};
+    use rusty_claw_providers::{Credentials, ProviderRegistry};
+
+    use super::*;
+
+    fn fixed_reply_state(dir: &std::path::Path) -> Arc<GatewayState> {
+        let mut providers = ProviderRegistry::new("fixed".into());
+        providers.register(
+            "fixed".into(),
+            Arc::new(StubProvider::reply("The answer is 42.")),
+            Credentials::ApiKey { api_key: "k".into() },
+        );
+        let mut state = crate::state::test_state(dir);
+        state.providers = Arc::new(providers);
+        Arc::new(state)
+    }
+
+    #[tokio::test]
+    async fn test_spawn_and_collect_returns_child_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = fixed_reply_state(dir.path());
+        let text = spawn_and_collect(
+            &state,
+            SpawnRequest {
+                task: "What is the answer?".into(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(text, "The answer is 42.");
+        // The child's slot is released once it finishes
+        assert!(state.spawned_agents.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_spawn_slots_enforce_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = fixed_reply_state(dir.path());
+
+        let a = reserve_slot(&state, "a", Some("p1"), CancellationToken::new(), 3, 2).unwrap();
+        let _b = reserve_slot(&state, "b", Some("p1"), CancellationToken::new(), 3, 2).unwrap();
+        let err = reserve_slot(&state, "c", Some("p1"), CancellationToken::new(), 3, 2).err().unwrap();
+        assert_eq!(err.code(), "spawn_fanout_exceeded");
+
+        let _d = reserve_slot(&state, "d", Some("p2"), CancellationToken::new(), 3, 2).unwrap();
+        let err = reserve_slot(&state, "e", None, CancellationToken::new(), 3, 2).err().unwrap();
+        assert_eq!(err.code(), "spawn_limit_exceeded");
+
+        // Dropping a slot frees it, even when the child task panicked
+        let panicked = tokio::spawn(async move {
+            let _slot = a;
+            panic!("child crashed");
+        });
+        assert!(panicked.await.is_err());
+        assert_eq!(state.spawned_agents.lock().unwrap().len(), 2);
+        assert!(reserve_slot(&state, "c", Some("p1"), CancellationToken::new(), 3, 2).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_aborting_parent_cancels_child() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut providers = ProviderRegistry::new("hanging".into());
+        providers.register(
+            "hanging".into(),
+            Arc::new(StubProvider::new(|_, _| async {
+                Ok(Box::pin(futures::stream::pending()) as ChunkStream)
+            })),
+            Credentials::ApiKey { api_key: "k".into() },
+        );
+        let mut state = crate::state::test_state(dir.path());
+        state.providers = Arc::new(providers);
+        let state = Arc::new(state);
+        let parent = CancellationToken::new();
+        state.active_agents.write().await.insert("parent".into(), parent.clone());
+
+        let child = spawn_agent(
+            &state,
+            SpawnRequest {
+                task: "never finishes".into(),
+                parent_session_key: Some("parent".into()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let token = state.spawned_agents.lock().unwrap()[&child.session_key].cancel.clone();
+        assert!(!token.is_cancelled());
+
+        parent.cancel();
+        tokio::time::timeout(std::time::Duration::from_secs(1), child.wait())
+            .await
+            .expect("child stopped with its parent")
+            .ok();
+        assert!(token.is_cancelled());
+        assert!(state.spawned_agents.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_spawn_depth_exceeded() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = fixed_reply_state(dir.path());
+        let err = spawn_agent(
+            &state,
+            SpawnRequest {
+                task: "recurse".into(),
+                parent_depth: 3,
+                ..Default::default()
+            },
+        )
+        .await
+        .err()
+        .unwrap();
+        assert_eq!(err.code(), "spawn_depth_exceeded");
+    }
+}
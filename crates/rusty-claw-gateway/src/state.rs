@@ -5,22 +5,27 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::RwLock;
 use tokio_util::sync::CancellationToken;
 
 use rusty_claw_browser::BrowserPool;
 use rusty_claw_channels::ChannelRegistry;
-use rusty_claw_core::config::Config;
+use rusty_claw_core::config::{ApiKeyConfig, Config, DEFAULT_LOGIN_TTL_SECS};
 use rusty_claw_core::pairing::PairingStore;
 use rusty_claw_core::session::SessionStore;
-use rusty_claw_plugins::HookRegistry;
+use rusty_claw_plugins::{GatewayMethodHandler, HookRegistry};
 use rusty_claw_providers::ProviderRegistry;
 use rusty_claw_tools::ToolRegistry;
 
 use crate::canvas::CanvasManager;
 use crate::cron::CronScheduler;
-use crate::rate_limit::RateLimiter;
+use crate::events::{EventLog, EVENT_REPLAY_CAPACITY};
+use crate::health::HealthReport;
+use crate::login::LoginTokens;
+use crate::outbound::OutboundSender;
+use crate::rate_limit::{RateLimiter, RequestLimiter};
 use crate::skills::SkillRegistry;
+use crate::spawn::SpawnedChild;
 
 /// Shared gateway state accessible from all connections and handlers.
 pub struct GatewayState {
@@ -31,14 +36,37 @@ pub struct GatewayState {
     pub tools: Arc<ToolRegistry>,
     pub providers: Arc<ProviderRegistry>,
     pub hooks: Arc<HookRegistry>,
+    /// Methods registered by plugins, tried after the built-in ones.
+    pub plugin_methods: HashMap<String, GatewayMethodHandler>,
+    /// HTTP routes registered by plugins, by plugin ID. Requests need the
+    /// same credentials as `/ws`.
+    pub plugin_routes: Vec<(String, axum::Router)>,
+    /// Plugin HTTP routes that skip gateway authentication, by plugin ID.
+    pub public_plugin_routes: Vec<(String, axum::Router)>,
     pub skills: Arc<RwLock<SkillRegistry>>,
     pub canvas: Arc<CanvasManager>,
     pub pairing: Arc<PairingStore>,
+    /// Pairing notices sent to admins, by `channel:message_id`, to the code
+    /// they announce; a reaction on one resolves that pairing.
+    pub pairing_notices: std::sync::Mutex<HashMap<String, String>>,
     pub browser: Option<Arc<BrowserPool>>,
     pub cron: Option<Arc<CronScheduler>>,
     pub rate_limiter: Option<Arc<RateLimiter>>,
+    pub request_limiter: RequestLimiter,
+    pub login_tokens: LoginTokens,
+    /// Running agent runs by session key. A run keeps its entry until its
+    /// session is saved and its reply delivered.
     pub active_agents: RwLock<HashMap<String, CancellationToken>>,
+    /// Cancelled when shutdown begins; no new connections or agent runs are
+    /// accepted after that.
+    pub shutdown: CancellationToken,
+    /// Running spawned agents by child session key.
+    pub spawned_agents: std::sync::Mutex<HashMap<String, SpawnedChild>>,
     pub connections: RwLock<HashMap<String, ConnectionState>>,
+    /// Recent broadcast events for clients resuming after a reconnect.
+    pub event_log: std::sync::Mutex<EventLog>,
+    /// Last component health report, refreshed on a timer.
+    pub health: RwLock<Option<HealthReport>>,
     pub state_version: AtomicU64,
     pub health_version: AtomicU64,
     pub startup_time: Instant,
@@ -49,12 +77,14 @@ pub struct GatewayState {
 /// Per-connection state.
 pub struct ConnectionState {
     pub conn_id: String,
-    pub event_tx: mpsc::UnboundedSender<String>,
+    /// Text and binary frames to the client, bounded by buffered bytes.
+    pub event_tx: OutboundSender,
     pub authenticated: bool,
+    /// API key the connection authenticated with; its scopes limit the
+    /// events it receives.
+    pub api_key: Option<ApiKeyConfig>,
     /// Voice session handle (if active).
     pub voice_session: Option<rusty_claw_media::voice_session::VoiceSessionHandle>,
-    /// Sender for binary audio frames back to the client.
-    pub binary_event_tx: Option<mpsc::UnboundedSender<Vec<u8>>>,
 }
 
 impl GatewayState {
@@ -72,13 +102,25 @@ impl GatewayState {
         browser: Option<Arc<BrowserPool>>,
         cron: Option<Arc<CronScheduler>>,
     ) -> Self {
-        // Set up rate limiter from config (read once at startup)
-        let rate_limiter = {
-            let config_guard = config.try_read();
-            config_guard
+        // Set up rate limiters from config (read once at startup). Request
+        // limits apply with their defaults even without a rate_limit section.
+        let rate_limit_config = config
+            .try_read()
+            .ok()
+            .and_then(|c| c.gateway.as_ref().and_then(|g| g.rate_limit.as_ref()).cloned());
+        let rate_limiter = rate_limit_config
+            .as_ref()
+            .map(|rl| Arc::new(RateLimiter::new(rl.max_connections_per_ip)));
+        let request_limiter = RequestLimiter::new(rate_limit_config.unwrap_or_default());
+        let login_tokens = {
+            let auth = config
+                .try_read()
                 .ok()
-                .and_then(|c| c.gateway.as_ref().and_then(|g| g.rate_limit.as_ref()).cloned())
-                .map(|rl| Arc::new(RateLimiter::new(rl.max_connections_per_ip)))
+                .and_then(|c| c.gateway.as_ref().and_then(|g| g.auth.clone()));
+            LoginTokens::new(
+                auth.as_ref().and_then(|a| a.resolve_token_secret()),
+                auth.as_ref().map_or(DEFAULT_LOGIN_TTL_SECS, |a| a.login_ttl_secs()),
+            )
         };
 
         Self {
@@ -89,22 +131,69 @@ impl GatewayState {
             tools,
             providers,
             hooks,
+            plugin_methods: HashMap::new(),
+            plugin_routes: Vec::new(),
+            public_plugin_routes: Vec::new(),
             skills: Arc::new(RwLock::new(skills)),
             canvas: Arc::new(CanvasManager::new()),
             pairing: Arc::new(pairing),
+            pairing_notices: std::sync::Mutex::new(HashMap::new()),
             browser,
             cron,
             rate_limiter,
+            request_limiter,
+            login_tokens,
             active_agents: RwLock::new(HashMap::new()),
+            shutdown: CancellationToken::new(),
+            spawned_agents: std::sync::Mutex::new(HashMap::new()),
             connections: RwLock::new(HashMap::new()),
+            event_log: std::sync::Mutex::new(EventLog::new(EVENT_REPLAY_CAPACITY)),
+            health: RwLock::new(None),
             state_version: AtomicU64::new(1),
             health_version: AtomicU64::new(1),
             startup_time: Instant::now(),
             #[cfg(feature = "metrics")]
-            prometheus_handle: None,
+            prometheus_handle: Some(crate::metrics::install_prometheus_recorder()),
         }
     }
 
+    /// Add plugin-registered gateway methods. Names shadowed by a built-in
+    /// method are dropped with a warning.
+    pub fn with_plugin_methods(mut self, methods: HashMap<String, GatewayMethodHandler>) -> Self {
+        for (name, handler) in methods {
+            if crate::methods::METHODS.contains(&name.as_str()) {
+                tracing::warn!(method = %name, "Plugin method shadowed by a built-in method");
+                continue;
+            }
+            self.plugin_methods.insert(name, handler);
+        }
+        self
+    }
+
+    /// Add plugin-registered HTTP routes, served under `/plugins/{plugin_id}`.
+    pub fn with_plugin_routes(mut self, routes: Vec<(String, axum::Router)>) -> Self {
+        self.plugin_routes.extend(routes);
+        self
+    }
+
+    /// Add plugin-registered HTTP routes served without gateway
+    /// authentication, under `/plugins/{plugin_id}` as well.
+    pub fn with_public_plugin_routes(mut self, routes: Vec<(String, axum::Router)>) -> Self {
+        self.public_plugin_routes.extend(routes);
+        self
+    }
+
+    /// Forget pairing notices whose request is no longer pending: resolved
+    /// from the CLI or another client, or expired.
+    pub fn prune_pairing_notices(&self) {
+        let pending: std::collections::HashSet<String> =
+            self.pairing.list_pending().into_iter().map(|r| r.code).collect();
+        self.pairing_notices
+            .lock()
+            .unwrap()
+            .retain(|_, code| pending.contains(code));
+    }
+
     pub fn bump_state_version(&self) -> u64 {
         self.state_version.fetch_add(1, Ordering::SeqCst) + 1
     }
@@ -118,3 +207,23 @@ impl GatewayState {
         self.config.read().await.clone()
     }
 }
+
+/// Minimal state for unit tests: default config, no providers or tools, and
+/// session and pairing stores under `dir`. Tests swap in what they need
+/// before wrapping it in an `Arc`.
+#[cfg(test)]
+pub(crate) fn test_state(dir: &std::path::Path) -> GatewayState {
+    GatewayState::new(
+        Arc::new(tokio::sync::RwLock::new(Config::default())),
+        None,
+        Arc::new(rusty_claw_core::session_store::JsonlSessionStore::new(dir.join("sessions"))),
+        Arc::new(ChannelRegistry::new()),
+        Arc::new(ToolRegistry::new()),
+        Arc::new(ProviderRegistry::new("none".into())),
+        Arc::new(HookRegistry::new()),
+        SkillRegistry::new(),
+        PairingStore::new(dir.join("pairing")),
+        None,
+        None,
+    )
+}
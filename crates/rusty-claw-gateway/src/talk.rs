@@ -0,0 +1,297 @@
+//! Voice turns — transcribe utterances, run the agent, and speak the reply.
+//!
+//! Each utterance from a voice session becomes an agent run. The final reply
+//! is synthesized with the configured TTS and streamed to the speaking
+//! connection as `talk.audio` events:
+//!
+//! ```json
+//! {"data": "<base64 PCM>", "format": "pcm_16000", "is_final": false}
+//! {"data": "", "format": "pcm_16000", "is_final": true}
+//! {"data": "", "format": "pcm_16000", "is_final": true, "interrupted": true}
+//! ```
+//!
+//! New speech while a reply is pending or playing cancels it (barge-in); the
+//! client gets an `interrupted` event and should drop any buffered audio.
+//!
+//! With streaming transcription enabled, captions of the speech in progress
+//! are sent as `talk.partial_transcript` events (`{"text", "is_final"}`),
+//! ending with the final transcript when the utterance completes.
+
+use std::sync::Arc;
+
+use base64::Engine;
+use serde_json::json;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use rusty_claw_agent::{AgentEvent, AgentRunResult};
+use rusty_claw_core::config::TtsConfig;
+use rusty_claw_core::protocol::GatewayFrame;
+use rusty_claw_media::stt::StreamingTranscription;
+use rusty_claw_media::voice_session::{Utterance, VoiceEvent};
+
+use crate::events::broadcast_event;
+use crate::state::GatewayState;
+
+/// Format of the audio in `talk.audio` events: 16-bit LE PCM, 16kHz mono.
+const AUDIO_FORMAT: &str = "pcm_16000";
+
+/// Process a voice session's events until it ends, then clear the
+/// connection's voice session.
+pub async fn run_voice_turns(
+    state: Arc<GatewayState>,
+    conn_id: String,
+    mut events: mpsc::UnboundedReceiver<VoiceEvent>,
+) {
+    // Cancelled on barge-in, or by the turn itself once its reply is done
+    let mut current_turn: Option<CancellationToken> = None;
+    // Partial transcription of the utterance in progress, if enabled
+    let mut partials: Option<StreamingTranscription> = None;
+
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => match event {
+                Some(event) => event,
+                None => break,
+            },
+            Some(text) = next_partial(&mut partials) => {
+                send_to_conn(
+                    &state,
+                    &conn_id,
+                    "talk.partial_transcript",
+                    json!({"text": text, "is_final": false}),
+                )
+                .await;
+                continue;
+            }
+        };
+        match event {
+            VoiceEvent::SpeechStarted => {
+                if let Some(turn) = current_turn.take()
+                    && !turn.is_cancelled()
+                {
+                    info!(conn_id = %conn_id, "Barge-in, cancelling spoken reply");
+                    turn.cancel();
+                    let payload = json!({
+                        "data": "",
+                        "format": AUDIO_FORMAT,
+                        "is_final": true,
+                        "interrupted": true,
+                    });
+                    send_talk_audio(&state, &conn_id, payload).await;
+                }
+            }
+            VoiceEvent::SpeechAudio(pcm) => {
+                if partials.is_none() {
+                    let config = state.read_config().await;
+                    partials = config
+                        .tools
+                        .as_ref()
+                        .and_then(|t| t.transcription.as_ref())
+                        .and_then(StreamingTranscription::start);
+                }
+                if let Some(stream) = &partials {
+                    stream.push(pcm);
+                }
+            }
+            VoiceEvent::Utterance(utterance) => {
+                // Partials still in flight are stale now
+                partials = None;
+                if let Some(turn) = current_turn.take() {
+                    turn.cancel();
+                }
+                let turn = CancellationToken::new();
+                current_turn = Some(turn.clone());
+                tokio::spawn(voice_turn(state.clone(), conn_id.clone(), utterance, turn));
+            }
+        }
+    }
+
+    if let Some(turn) = current_turn {
+        turn.cancel();
+    }
+
+    // Cleanup voice session on task end
+    let mut connections = state.connections.write().await;
+    if let Some(conn) = connections.get_mut(&conn_id) {
+        conn.voice_session = None;
+    }
+}
+
+/// Transcribe one utterance, run the agent on it, and speak the reply
+/// unless `turn` is cancelled first.
+async fn voice_turn(
+    state: Arc<GatewayState>,
+    conn_id: String,
+    utterance: Utterance,
+    turn: CancellationToken,
+) {
+    debug!(
+        duration_ms = utterance.duration_ms,
+        samples = utterance.pcm_data.len(),
+        "Utterance received"
+    );
+
+    let config = state.read_config().await;
+    let Some(tc) = config.tools.as_ref().and_then(|t| t.transcription.as_ref()) else {
+        warn!("No transcription config, cannot process voice");
+        return;
+    };
+    let text = match rusty_claw_media::stt::transcribe_audio_bytes(&utterance.pcm_data, tc).await {
+        Ok(text) if !text.is_empty() => text,
+        Ok(_) => {
+            debug!("Empty transcription result");
+            return;
+        }
+        Err(e) => {
+            warn!(%e, "Transcription failed");
+            return;
+        }
+    };
+    info!(text = %text, "Transcribed utterance");
+    if rusty_claw_media::stt::supports_streaming(tc) {
+        send_to_conn(
+            &state,
+            &conn_id,
+            "talk.partial_transcript",
+            json!({"text": text, "is_final": true}),
+        )
+        .await;
+    }
+
+    // Send transcription as agent event
+    let event = AgentEvent::BlockReply {
+        text: format!("[Voice] {text}"),
+        is_final: true,
+    };
+    if let Ok(payload) = serde_json::to_value(&event) {
+        broadcast_event(&state, "agent.event", Some(payload)).await;
+    }
+
+    if state.shutdown.is_cancelled() {
+        return;
+    }
+    let request_id = format!("talk-{}", uuid::Uuid::new_v4());
+    let frame =
+        crate::methods::handle_agent(&state, &request_id, Some(json!({ "text": text }))).await;
+    let Some(reply) = reply_text(frame) else {
+        debug!("Agent run produced no reply to speak");
+        return;
+    };
+
+    let Some(tts) = config.tools.as_ref().and_then(|t| t.tts.as_ref()) else {
+        debug!("No TTS config, reply is text only");
+        return;
+    };
+    if !turn.is_cancelled() {
+        speak(&state, &conn_id, &reply, tts, &turn).await;
+    }
+    turn.cancel();
+}
+
+/// Final reply text of a successful agent response.
+fn reply_text(frame: GatewayFrame) -> Option<String> {
+    let GatewayFrame::Response {
+        ok: true,
+        payload: Some(payload),
+        ..
+    } = frame
+    else {
+        return None;
+    };
+    let result: AgentRunResult = serde_json::from_value(payload).ok()?;
+    result
+        .final_text()
+        .filter(|t| !t.trim().is_empty())
+        .map(String::from)
+}
+
+/// Stream `text` through TTS to the connection until done or cancelled.
+async fn speak(
+    state: &Arc<GatewayState>,
+    conn_id: &str,
+    text: &str,
+    tts: &TtsConfig,
+    cancel: &CancellationToken,
+) {
+    let (chunk_tx, mut chunk_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+    let stream = rusty_claw_media::tts_stream::stream_tts(text, tts, chunk_tx);
+    tokio::pin!(stream);
+    let mut streaming = true;
+    // Odd trailing byte of the last chunk, so every event holds whole samples
+    let mut carry: Vec<u8> = Vec::new();
+
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            result = &mut stream, if streaming => {
+                streaming = false;
+                if let Err(e) = result {
+                    warn!(%e, "TTS stream failed");
+                }
+            }
+            chunk = chunk_rx.recv() => {
+                let Some(chunk) = chunk else { break };
+                carry.extend_from_slice(&chunk);
+                let whole = carry.len() - carry.len() % 2;
+                if whole == 0 {
+                    continue;
+                }
+                let samples: Vec<u8> = carry.drain(..whole).collect();
+                let data = base64::engine::general_purpose::STANDARD.encode(&samples);
+                let payload = json!({"data": data, "format": AUDIO_FORMAT, "is_final": false});
+                if !send_talk_audio(state, conn_id, payload).await {
+                    return;
+                }
+            }
+        }
+    }
+
+    send_talk_audio(
+        state,
+        conn_id,
+        json!({"data": "", "format": AUDIO_FORMAT, "is_final": true}),
+    )
+    .await;
+}
+
+/// Wait for the next partial transcript, or forever without a stream.
+async fn next_partial(partials: &mut Option<StreamingTranscription>) -> Option<String> {
+    match partials {
+        Some(stream) => stream.next_partial().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Send a `talk.audio` event to one connection. Returns false if it is gone
+/// or not keeping up.
+async fn send_talk_audio(
+    state: &Arc<GatewayState>,
+    conn_id: &str,
+    payload: serde_json::Value,
+) -> bool {
+    send_to_conn(state, conn_id, "talk.audio", payload).await
+}
+
+/// Send an event to one connection rather than broadcasting it.
+async fn send_to_conn(
+    state: &Arc<GatewayState>,
+    conn_id: &str,
+    event: &str,
+    payload: serde_json::Value,
+) -> bool {
+    let frame = GatewayFrame::Event {
+        event: event.into(),
+        payload: Some(payload),
+        seq: None,
+        state_version: None,
+    };
+    let Ok(msg) = serde_json::to_string(&frame) else {
+        return false;
+    };
+    let connections = state.connections.read().await;
+    connections
+        .get(conn_id)
+        .is_some_and(|conn| conn.event_tx.send_text(msg))
+}
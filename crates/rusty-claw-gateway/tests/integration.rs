@@ -2,6 +2,7 @@
 //!
 //! Run with: `cargo test -p rusty-claw-gateway --test integration`
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use futures::{SinkExt, StreamExt};
@@ -17,6 +18,15 @@ fn find_free_port() -> u16 {
 
 /// Build a minimal gateway and return its state + port.
 async fn start_test_gateway() -> (Arc<rusty_claw_gateway::GatewayState>, u16) {
+    start_test_gateway_with(|state| state, false).await
+}
+
+/// Build a minimal gateway, letting the test add to its state (e.g. plugin
+/// registrations) before it starts.
+async fn start_test_gateway_with(
+    customize: impl FnOnce(rusty_claw_gateway::GatewayState) -> rusty_claw_gateway::GatewayState,
+    ui: bool,
+) -> (Arc<rusty_claw_gateway::GatewayState>, u16) {
     let port = find_free_port();
 
     let config = rusty_claw_core::config::Config::default();
@@ -43,7 +53,7 @@ async fn start_test_gateway() -> (Arc<rusty_claw_gateway::GatewayState>, u16) {
         std::env::temp_dir().join(format!("rusty-claw-pairing-{port}")),
     );
 
-    let state = Arc::new(rusty_claw_gateway::GatewayState::new(
+    let state = Arc::new(customize(rusty_claw_gateway::GatewayState::new(
         config_rw,
         None,
         sessions,
@@ -55,12 +65,12 @@ async fn start_test_gateway() -> (Arc<rusty_claw_gateway::GatewayState>, u16) {
         pairing,
         None,
         None,
-    ));
+    )));
 
     // Start gateway in background
     let state_clone = state.clone();
     tokio::spawn(async move {
-        let _ = rusty_claw_gateway::start_gateway(state_clone, port, false).await;
+        let _ = rusty_claw_gateway::start_gateway(state_clone, port, ui).await;
     });
 
     // Wait for gateway to be ready
@@ -101,7 +111,7 @@ async fn test_ws_hello_and_sessions_list() {
     // Should receive HelloOk event
     let msg = ws.next().await.unwrap().unwrap();
     let hello: serde_json::Value =
-        serde_json::from_str(&msg.to_text().unwrap()).unwrap();
+        serde_json::from_str(msg.to_text().unwrap()).unwrap();
     assert_eq!(hello["event"], "hello");
 
     let payload = &hello["payload"];
@@ -119,7 +129,7 @@ async fn test_ws_hello_and_sessions_list() {
     // Read response
     let resp_msg = ws.next().await.unwrap().unwrap();
     let resp: serde_json::Value =
-        serde_json::from_str(&resp_msg.to_text().unwrap()).unwrap();
+        serde_json::from_str(resp_msg.to_text().unwrap()).unwrap();
     assert_eq!(resp["id"], "test-1");
     assert_eq!(resp["ok"], true);
     assert!(resp["payload"]["sessions"].is_array());
@@ -147,7 +157,7 @@ async fn test_ws_config_get() {
 
     let resp_msg = ws.next().await.unwrap().unwrap();
     let resp: serde_json::Value =
-        serde_json::from_str(&resp_msg.to_text().unwrap()).unwrap();
+        serde_json::from_str(resp_msg.to_text().unwrap()).unwrap();
     assert_eq!(resp["id"], "cfg-1");
     assert_eq!(resp["ok"], true);
 
@@ -180,7 +190,7 @@ async fn test_ws_config_set_and_get() {
     let mut set_ok = false;
     for _ in 0..5 {
         let msg = ws.next().await.unwrap().unwrap();
-        let resp: serde_json::Value = serde_json::from_str(&msg.to_text().unwrap()).unwrap();
+        let resp: serde_json::Value = serde_json::from_str(msg.to_text().unwrap()).unwrap();
         if resp.get("id").and_then(|v| v.as_str()) == Some("set-1") {
             assert_eq!(resp["ok"], true);
             set_ok = true;
@@ -212,7 +222,7 @@ async fn test_ws_unknown_method() {
 
     let resp_msg = ws.next().await.unwrap().unwrap();
     let resp: serde_json::Value =
-        serde_json::from_str(&resp_msg.to_text().unwrap()).unwrap();
+        serde_json::from_str(resp_msg.to_text().unwrap()).unwrap();
     assert_eq!(resp["id"], "bad-1");
     assert_eq!(resp["ok"], false);
     assert!(resp["error"]["code"]
@@ -223,6 +233,277 @@ async fn test_ws_unknown_method() {
     ws.close(None).await.ok();
 }
 
+#[tokio::test]
+async fn test_ws_plugin_method() {
+    let mut methods: HashMap<String, rusty_claw_plugins::GatewayMethodHandler> = HashMap::new();
+    methods.insert(
+        "weather.get".into(),
+        Box::new(|params| {
+            Box::pin(async move {
+                let city = params
+                    .as_ref()
+                    .and_then(|p| p.get("city"))
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("city is required"))?
+                    .to_string();
+                Ok(json!({ "city": city, "temp": 21 }))
+            })
+        }),
+    );
+    // Shadowed by the built-in and never dispatched to the plugin
+    methods.insert(
+        "wake".into(),
+        Box::new(|_| Box::pin(async { Ok(json!({ "status": "plugin" })) })),
+    );
+    let (_state, port) =
+        start_test_gateway_with(|state| state.with_plugin_methods(methods), false).await;
+
+    let url = format!("ws://127.0.0.1:{port}/ws");
+    let (mut ws, _) = connect_async(&url).await.expect("WS connect failed");
+
+    let msg = ws.next().await.unwrap().unwrap();
+    let hello: serde_json::Value = serde_json::from_str(msg.to_text().unwrap()).unwrap();
+    let advertised = hello["payload"]["features"]["methods"].as_array().unwrap();
+    assert!(advertised.contains(&json!("weather.get")));
+    assert_eq!(advertised.iter().filter(|m| *m == "wake").count(), 1);
+
+    let requests = [
+        json!({
+            "type": "req",
+            "id": "p-1",
+            "method": "weather.get",
+            "params": { "city": "Graz" },
+        }),
+        json!({ "type": "req", "id": "p-2", "method": "weather.get" }),
+        json!({ "type": "req", "id": "p-3", "method": "wake" }),
+    ];
+    for req in &requests {
+        ws.send(Message::Text(req.to_string().into())).await.unwrap();
+    }
+    let mut responses = HashMap::new();
+    while responses.len() < requests.len() {
+        let msg = ws.next().await.unwrap().unwrap();
+        let resp: serde_json::Value = serde_json::from_str(msg.to_text().unwrap()).unwrap();
+        if resp["type"] == "res" {
+            responses.insert(resp["id"].as_str().unwrap().to_string(), resp);
+        }
+    }
+
+    assert_eq!(responses["p-1"]["ok"], true);
+    assert_eq!(responses["p-1"]["payload"]["city"], "Graz");
+    assert_eq!(responses["p-2"]["ok"], false);
+    assert_eq!(responses["p-2"]["error"]["code"], "plugin_error");
+    assert_eq!(responses["p-3"]["payload"]["status"], "ok");
+
+    ws.close(None).await.ok();
+}
+
+#[tokio::test]
+async fn test_plugin_http_routes() {
+    use axum::routing::get;
+
+    let routes = vec![
+        (
+            "weather".to_string(),
+            axum::Router::new().route("/forecast", get(|| async { "sunny" })),
+        ),
+        (
+            "news".to_string(),
+            axum::Router::new().route("/forecast", get(|| async { "headlines" })),
+        ),
+    ];
+    // UI enabled so its catch-all is mounted too
+    let (_state, port) =
+        start_test_gateway_with(|state| state.with_plugin_routes(routes), true).await;
+
+    for (plugin_id, expected) in [("weather", "sunny"), ("news", "headlines")] {
+        let resp = reqwest::get(format!("http://127.0.0.1:{port}/plugins/{plugin_id}/forecast"))
+            .await
+            .unwrap();
+        assert!(resp.status().is_success());
+        assert_eq!(resp.text().await.unwrap(), expected);
+    }
+
+    // Core routes still take priority
+    let resp = reqwest::get(format!("http://127.0.0.1:{port}/health"))
+        .await
+        .unwrap();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["status"], "ok");
+}
+
+#[tokio::test]
+async fn test_plugin_http_routes_require_gateway_token() {
+    use axum::routing::{get, post};
+
+    let protected = vec![(
+        "weather".to_string(),
+        axum::Router::new().route("/forecast", get(|| async { "sunny" })),
+    )];
+    let public = vec![(
+        "weather".to_string(),
+        axum::Router::new().route("/webhook", post(|| async { "received" })),
+    )];
+    let (_state, port) = start_test_gateway_with(
+        |state| {
+            state.config.try_write().unwrap().gateway = Some(
+                serde_json::from_value(json!({
+                    "auth": { "mode": "token", "token": "plugin-secret" }
+                }))
+                .unwrap(),
+            );
+            state.with_plugin_routes(protected).with_public_plugin_routes(public)
+        },
+        false,
+    )
+    .await;
+    let client = reqwest::Client::new();
+    let forecast = format!("http://127.0.0.1:{port}/plugins/weather/forecast");
+
+    let resp = client.get(&forecast).send().await.unwrap();
+    assert_eq!(resp.status(), 401);
+    let resp = client.get(&forecast).bearer_auth("wrong").send().await.unwrap();
+    assert_eq!(resp.status(), 401);
+
+    let resp = client.get(&forecast).bearer_auth("plugin-secret").send().await.unwrap();
+    assert_eq!(resp.text().await.unwrap(), "sunny");
+    let resp = client
+        .get(format!("{forecast}?token=plugin-secret"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.text().await.unwrap(), "sunny");
+
+    // Routes registered as public skip gateway auth
+    let resp = client
+        .post(format!("http://127.0.0.1:{port}/plugins/weather/webhook"))
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.status().is_success());
+    assert_eq!(resp.text().await.unwrap(), "received");
+}
+
+#[tokio::test]
+async fn test_canvas_requires_gateway_token() {
+    let (_state, port) = start_test_gateway_with(
+        |state| {
+            state.config.try_write().unwrap().gateway = Some(
+                serde_json::from_value(json!({
+                    "auth": { "mode": "token", "token": "canvas-secret" }
+                }))
+                .unwrap(),
+            );
+            state
+        },
+        false,
+    )
+    .await;
+
+    for url in [
+        format!("ws://127.0.0.1:{port}/canvas/abc"),
+        format!("ws://127.0.0.1:{port}/canvas/abc?token=wrong"),
+    ] {
+        match connect_async(&url).await {
+            Err(tokio_tungstenite::tungstenite::Error::Http(resp)) => {
+                assert_eq!(resp.status(), 401, "{url}");
+            }
+            other => panic!("{url} was not refused: {other:?}"),
+        }
+    }
+
+    let (mut ws, _) = connect_async(format!("ws://127.0.0.1:{port}/canvas/abc?token=canvas-secret"))
+        .await
+        .expect("valid token should connect");
+    ws.close(None).await.ok();
+}
+
+#[tokio::test]
+async fn test_scoped_api_key_only_receives_events_it_may_read() {
+    use sha2::{Digest, Sha256};
+
+    let (_state, port) = start_test_gateway_with(
+        |state| {
+            state.config.try_write().unwrap().gateway = Some(
+                serde_json::from_value(json!({
+                    "auth": {
+                        "mode": "token",
+                        "token": "shared",
+                        "keys": [{
+                            "name": "reader",
+                            "key_hash": format!("{:x}", Sha256::digest(b"reader-key")),
+                            "scopes": ["sessions.list"]
+                        }]
+                    }
+                }))
+                .unwrap(),
+            );
+            state
+        },
+        false,
+    )
+    .await;
+
+    // Connect and authenticate, returning the socket and the auth.ok payload
+    let connect = |token: &'static str, resume_from_seq: Option<u64>| async move {
+        let (mut ws, _) = connect_async(format!("ws://127.0.0.1:{port}/ws")).await.unwrap();
+        let _hello = ws.next().await;
+        let connect = json!({
+            "type": "req",
+            "id": "connect-1",
+            "method": "connect",
+            "params": {
+                "min_protocol": 3,
+                "max_protocol": 3,
+                "client": {"id": "test"},
+                "auth": {"type": "token", "token": token},
+                "resume_from_seq": resume_from_seq,
+            }
+        });
+        ws.send(Message::Text(connect.to_string().into())).await.unwrap();
+        let msg = ws.next().await.unwrap().unwrap();
+        let ok: serde_json::Value = serde_json::from_str(msg.to_text().unwrap()).unwrap();
+        assert_eq!(ok["event"], "auth.ok", "{ok}");
+        (ws, ok["payload"].clone())
+    };
+    let (mut admin, _) = connect("shared", None).await;
+    let (mut reader, _) = connect("reader-key", None).await;
+
+    let set = json!({
+        "type": "req",
+        "id": "set-1",
+        "method": "config.set",
+        "params": {"path": "agents.defaults.model", "value": "gpt-4o"}
+    });
+    admin.send(Message::Text(set.to_string().into())).await.unwrap();
+    let mut events = Vec::new();
+    while let Some(Ok(msg)) = admin.next().await {
+        let frame: serde_json::Value = serde_json::from_str(msg.to_text().unwrap()).unwrap();
+        if frame["id"] == "set-1" {
+            break;
+        }
+        events.push(frame["event"].clone());
+    }
+    assert_eq!(events, [json!("config.changed")]);
+
+    // The change was broadcast before the reader's request, so a leaked
+    // event would arrive ahead of the response
+    let list = json!({"type": "req", "id": "list-1", "method": "sessions.list"});
+    reader.send(Message::Text(list.to_string().into())).await.unwrap();
+    let msg = reader.next().await.unwrap().unwrap();
+    let frame: serde_json::Value = serde_json::from_str(msg.to_text().unwrap()).unwrap();
+    assert_eq!(frame["id"], "list-1", "reader received {frame}");
+
+    // Nor is it replayed on resume
+    let (_, resumed) = connect("reader-key", Some(0)).await;
+    assert_eq!(resumed["replayed"], 0);
+    let (_, resumed) = connect("shared", Some(0)).await;
+    assert_eq!(resumed["replayed"], 1);
+
+    admin.close(None).await.ok();
+    reader.close(None).await.ok();
+}
+
 #[tokio::test]
 async fn test_ws_skills_list() {
     let (_state, port) = start_test_gateway().await;
@@ -242,7 +523,7 @@ async fn test_ws_skills_list() {
 
     let resp_msg = ws.next().await.unwrap().unwrap();
     let resp: serde_json::Value =
-        serde_json::from_str(&resp_msg.to_text().unwrap()).unwrap();
+        serde_json::from_str(resp_msg.to_text().unwrap()).unwrap();
     assert_eq!(resp["id"], "sk-1");
     assert_eq!(resp["ok"], true);
     assert!(resp["payload"]["skills"].is_array());
@@ -269,7 +550,7 @@ async fn test_ws_cron_list() {
 
     let resp_msg = ws.next().await.unwrap().unwrap();
     let resp: serde_json::Value =
-        serde_json::from_str(&resp_msg.to_text().unwrap()).unwrap();
+        serde_json::from_str(resp_msg.to_text().unwrap()).unwrap();
     assert_eq!(resp["id"], "cr-1");
     assert_eq!(resp["ok"], true);
     assert!(resp["payload"]["jobs"].is_array());
@@ -296,7 +577,7 @@ async fn test_ws_agent_status() {
 
     let resp_msg = ws.next().await.unwrap().unwrap();
     let resp: serde_json::Value =
-        serde_json::from_str(&resp_msg.to_text().unwrap()).unwrap();
+        serde_json::from_str(resp_msg.to_text().unwrap()).unwrap();
     assert_eq!(resp["id"], "as-1");
     assert_eq!(resp["ok"], true);
     assert!(resp["payload"]["active_agents"].is_array());
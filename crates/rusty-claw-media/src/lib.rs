@@ -1,5 +1,6 @@
 //! Media pipeline — audio processing, VAD, STT, TTS streaming.
 
+pub mod resample;
 pub mod stt;
 pub mod tts_stream;
 pub mod vad;
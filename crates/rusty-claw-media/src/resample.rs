@@ -0,0 +1,173 @@
+//! Input audio conversion — downmix to mono and resample to the STT rate.
+
+use serde::{Deserialize, Serialize};
+
+/// Sample rate the voice pipeline and STT backends work at.
+pub const STT_SAMPLE_RATE: u32 = 16000;
+
+/// Format of the PCM a client sends: 16-bit LE, interleaved channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl Default for InputFormat {
+    fn default() -> Self {
+        Self {
+            sample_rate: STT_SAMPLE_RATE,
+            channels: 1,
+        }
+    }
+}
+
+impl InputFormat {
+    /// Check the format is one we can convert. Returns why not.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(8000..=192_000).contains(&self.sample_rate) {
+            return Err(format!(
+                "sample_rate must be between 8000 and 192000, got {}",
+                self.sample_rate
+            ));
+        }
+        if !(1..=8).contains(&self.channels) {
+            return Err(format!("channels must be between 1 and 8, got {}", self.channels));
+        }
+        Ok(())
+    }
+}
+
+/// Streaming converter from an [`InputFormat`] to mono at a target rate.
+///
+/// Audio may arrive in chunks of any size; partial frames and the
+/// interpolation position carry over between calls.
+pub struct Resampler {
+    channels: usize,
+    /// Input samples per output sample.
+    step: f64,
+    /// Samples of an incomplete multi-channel frame from the last chunk.
+    partial_frame: Vec<i16>,
+    /// Last mono sample of the previous chunk, at position 0.
+    prev: i16,
+    /// Position of the next output sample; chunk samples start at 1.
+    pos: f64,
+}
+
+impl Resampler {
+    pub fn new(input: InputFormat, target_rate: u32) -> Self {
+        Self {
+            channels: input.channels.max(1) as usize,
+            step: input.sample_rate as f64 / target_rate as f64,
+            partial_frame: Vec::new(),
+            prev: 0,
+            pos: 1.0,
+        }
+    }
+
+    /// True if input passes through unchanged.
+    pub fn is_passthrough(&self) -> bool {
+        self.channels == 1 && self.step == 1.0
+    }
+
+    /// Convert a chunk of interleaved input samples.
+    pub fn process(&mut self, samples: &[i16]) -> Vec<i16> {
+        let mono = self.downmix(samples);
+        if self.step == 1.0 {
+            return mono;
+        }
+
+        // Linear interpolation between neighbouring input samples
+        let mut out = Vec::with_capacity((mono.len() as f64 / self.step) as usize + 1);
+        while (self.pos as usize) < mono.len() {
+            let index = self.pos as usize;
+            let frac = self.pos - index as f64;
+            let a = if index == 0 { self.prev } else { mono[index - 1] } as f64;
+            let b = mono[index] as f64;
+            out.push((a + (b - a) * frac).round() as i16);
+            self.pos += self.step;
+        }
+        if let Some(&last) = mono.last() {
+            self.prev = last;
+            self.pos -= mono.len() as f64;
+        }
+        out
+    }
+
+    fn downmix(&mut self, samples: &[i16]) -> Vec<i16> {
+        if self.channels == 1 {
+            return samples.to_vec();
+        }
+        self.partial_frame.extend_from_slice(samples);
+        let whole = self.partial_frame.len() - self.partial_frame.len() % self.channels;
+        let mono = self.partial_frame[..whole]
+            .chunks_exact(self.channels)
+            .map(|frame| {
+                let sum: i32 = frame.iter().map(|&s| s as i32).sum();
+                (sum / self.channels as i32) as i16
+            })
+            .collect();
+        self.partial_frame.drain(..whole);
+        mono
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_48khz_to_16khz_length() {
+        let input = InputFormat {
+            sample_rate: 48000,
+            channels: 1,
+        };
+        let mut resampler = Resampler::new(input, STT_SAMPLE_RATE);
+        let samples: Vec<i16> = (0..48000).map(|i| (i % 1000) as i16).collect();
+        assert_eq!(resampler.process(&samples).len(), 16000);
+
+        // Same result when the audio arrives in odd-sized chunks
+        let mut resampler = Resampler::new(input, STT_SAMPLE_RATE);
+        let total: usize = samples.chunks(997).map(|c| resampler.process(c).len()).sum();
+        assert_eq!(total, 16000);
+    }
+
+    #[test]
+    fn test_stereo_downmix() {
+        let input = InputFormat {
+            sample_rate: 16000,
+            channels: 2,
+        };
+        let mut resampler = Resampler::new(input, STT_SAMPLE_RATE);
+        assert!(!resampler.is_passthrough());
+        // A frame split across chunks is kept until complete
+        assert_eq!(resampler.process(&[100, 300, 1000]), vec![200]);
+        assert_eq!(resampler.process(&[-1000, 50]), vec![0]);
+    }
+
+    #[test]
+    fn test_upsample_interpolates() {
+        let input = InputFormat {
+            sample_rate: 8000,
+            channels: 1,
+        };
+        let mut resampler = Resampler::new(input, STT_SAMPLE_RATE);
+        assert_eq!(resampler.process(&[0, 100, 200]), vec![0, 50, 100, 150]);
+        // The last sample is interpolated once the next chunk arrives
+        assert_eq!(resampler.process(&[300]), vec![200, 250]);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(InputFormat::default().validate().is_ok());
+        let bad_rate = InputFormat {
+            sample_rate: 100,
+            channels: 1,
+        };
+        assert!(bad_rate.validate().is_err());
+        let bad_channels = InputFormat {
+            sample_rate: 48000,
+            channels: 0,
+        };
+        assert!(bad_channels.validate().is_err());
+    }
+}
@@ -1,10 +1,19 @@
 //! Speech-to-text from raw audio bytes.
+//!
+//! [`transcribe_audio_bytes`] transcribes a complete utterance. For live
+//! captions, [`StreamingTranscription`] produces partial transcripts while
+//! the utterance is still being spoken.
+
+use std::future::Future;
 
 use anyhow::Result;
+use tokio::sync::mpsc;
 use tracing::debug;
 
 use rusty_claw_core::config::TranscriptionConfig;
 
+use crate::resample::STT_SAMPLE_RATE;
+
 /// Wrap raw 16-bit PCM in a WAV container.
 pub fn pcm_to_wav(pcm: &[i16], sample_rate: u32, channels: u16, bits_per_sample: u16) -> Vec<u8> {
     let data_len = pcm.len() * 2; // 2 bytes per i16 sample
@@ -57,7 +66,7 @@ pub async fn transcribe_audio_bytes(
         .resolve_api_key()
         .ok_or_else(|| anyhow::anyhow!("No transcription API key configured"))?;
 
-    let wav_data = pcm_to_wav(pcm, 16000, 1, 16);
+    let wav_data = pcm_to_wav(pcm, STT_SAMPLE_RATE, 1, 16);
     let url = provider_url(config);
     let model = config
         .model
@@ -93,6 +102,97 @@ pub async fn transcribe_audio_bytes(
     Ok(text.trim().to_string())
 }
 
+/// Default audio between partial transcripts.
+const DEFAULT_PARTIAL_INTERVAL_MS: u64 = 1000;
+
+/// Whether partial transcripts are enabled and the provider can produce
+/// them. Groq and OpenAI only transcribe whole files, so partials come from
+/// transcribing the audio so far again as it grows.
+pub fn supports_streaming(config: &TranscriptionConfig) -> bool {
+    config.streaming && matches!(config.provider.as_str(), "groq" | "openai")
+}
+
+/// Partial transcripts of an utterance in progress.
+///
+/// Audio pushed in is transcribed once per interval of new audio, and each
+/// changed result is delivered as a partial. Dropping it stops
+/// transcription; the final text comes from [`transcribe_audio_bytes`] on
+/// the complete utterance.
+pub struct StreamingTranscription {
+    audio_tx: mpsc::UnboundedSender<Vec<i16>>,
+    partials: mpsc::UnboundedReceiver<String>,
+}
+
+impl StreamingTranscription {
+    /// Start partial transcription, or `None` if [`supports_streaming`] is
+    /// false for `config`.
+    pub fn start(config: &TranscriptionConfig) -> Option<Self> {
+        if !supports_streaming(config) {
+            return None;
+        }
+        let interval_ms = config
+            .partial_interval_ms
+            .unwrap_or(DEFAULT_PARTIAL_INTERVAL_MS)
+            .max(200);
+        let config = config.clone();
+        Some(Self::with_transcriber(
+            (interval_ms * 16) as usize, // 16 samples per ms at 16kHz
+            move |pcm| {
+                let config = config.clone();
+                async move { transcribe_audio_bytes(&pcm, &config).await }
+            },
+        ))
+    }
+
+    fn with_transcriber<F, Fut>(interval_samples: usize, transcribe: F) -> Self
+    where
+        F: Fn(Vec<i16>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<String>> + Send,
+    {
+        let (audio_tx, mut audio_rx) = mpsc::unbounded_channel::<Vec<i16>>();
+        let (partial_tx, partials) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            let mut audio = Vec::new();
+            let mut transcribed_len = 0;
+            let mut last = String::new();
+            while let Some(chunk) = audio_rx.recv().await {
+                audio.extend(chunk);
+                // Catch up on audio that arrived during the last request
+                while let Ok(chunk) = audio_rx.try_recv() {
+                    audio.extend(chunk);
+                }
+                if audio.len() - transcribed_len < interval_samples {
+                    continue;
+                }
+                transcribed_len = audio.len();
+                match transcribe(audio.clone()).await {
+                    Ok(text) if !text.is_empty() && text != last => {
+                        last = text.clone();
+                        if partial_tx.send(text).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => debug!(%e, "Partial transcription failed"),
+                }
+            }
+        });
+
+        Self { audio_tx, partials }
+    }
+
+    /// Add audio (16-bit PCM, 16kHz, mono) of the utterance in progress.
+    pub fn push(&self, pcm: Vec<i16>) {
+        let _ = self.audio_tx.send(pcm);
+    }
+
+    /// Wait for the next partial transcript.
+    pub async fn next_partial(&mut self) -> Option<String> {
+        self.partials.recv().await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -123,6 +223,9 @@ mod tests {
             api_key: None,
             api_key_env: None,
             model: None,
+            language: None,
+            streaming: false,
+            partial_interval_ms: None,
         };
         assert!(provider_url(&groq).contains("groq.com"));
 
@@ -131,7 +234,49 @@ mod tests {
             api_key: None,
             api_key_env: None,
             model: None,
+            language: None,
+            streaming: false,
+            partial_interval_ms: None,
         };
         assert!(provider_url(&openai).contains("openai.com"));
     }
+
+    #[test]
+    fn test_supports_streaming() {
+        let mut config = TranscriptionConfig {
+            provider: "groq".into(),
+            api_key: None,
+            api_key_env: None,
+            model: None,
+            language: None,
+            streaming: false,
+            partial_interval_ms: None,
+        };
+        assert!(!supports_streaming(&config));
+        config.streaming = true;
+        assert!(supports_streaming(&config));
+        config.provider = "local".into();
+        assert!(StreamingTranscription::start(&config).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_partials_per_interval() {
+        // "Transcribes" to the number of samples heard so far
+        let mut stream = StreamingTranscription::with_transcriber(320, |pcm| async move {
+            Ok(format!("{} samples", pcm.len()))
+        });
+
+        stream.push(vec![0; 160]);
+        stream.push(vec![0; 160]);
+        assert_eq!(stream.next_partial().await.unwrap(), "320 samples");
+
+        // Not a full interval of new audio yet
+        stream.push(vec![0; 160]);
+        let pending =
+            tokio::time::timeout(std::time::Duration::from_millis(50), stream.next_partial());
+        assert!(pending.await.is_err());
+
+        stream.push(vec![0; 160]);
+        assert_eq!(stream.next_partial().await.unwrap(), "640 samples");
+    }
 }
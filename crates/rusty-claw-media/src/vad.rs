@@ -1,15 +1,21 @@
 //! Energy-based Voice Activity Detection (VAD).
 
+use rusty_claw_core::config::VadConfig;
+
 /// Voice Activity Detector using RMS energy threshold on 16-bit PCM.
 pub struct VoiceActivityDetector {
     /// RMS threshold for speech detection.
     threshold: f64,
     /// Minimum consecutive silent frames before declaring speech end.
     min_silent_frames: usize,
+    /// Minimum consecutive speech frames before declaring speech start.
+    min_speech_frames: usize,
     /// Current state: true = speech active.
     speech_active: bool,
     /// Count of consecutive silent frames.
     silent_count: usize,
+    /// Count of consecutive speech frames before speech start.
+    speech_count: usize,
 }
 
 impl VoiceActivityDetector {
@@ -18,14 +24,30 @@ impl VoiceActivityDetector {
         Self {
             threshold,
             min_silent_frames,
+            min_speech_frames: 1,
             speech_active: false,
             silent_count: 0,
+            speech_count: 0,
         }
     }
 
+    /// Require this many consecutive speech frames before speech starts.
+    pub fn with_min_speech_frames(mut self, frames: usize) -> Self {
+        self.min_speech_frames = frames.max(1);
+        self
+    }
+
+    /// Create from `config` (clamped) for frames of `frame_ms` milliseconds.
+    pub fn from_config(config: &VadConfig, frame_ms: u64) -> Self {
+        let config = config.clamped();
+        let frames = |ms: u64| ms.div_ceil(frame_ms).max(1) as usize;
+        Self::new(config.energy_threshold, frames(config.silence_hangover_ms))
+            .with_min_speech_frames(frames(config.min_speech_ms))
+    }
+
     /// Create with sensible defaults for 16kHz 20ms frames.
     pub fn default_16khz() -> Self {
-        Self::new(300.0, 15) // ~300ms of silence at 20ms frames
+        Self::from_config(&VadConfig::default(), 20)
     }
 
     /// Compute RMS energy of a PCM frame.
@@ -50,10 +72,16 @@ impl VoiceActivityDetector {
         if is_speech {
             self.silent_count = 0;
             if !self.speech_active {
-                self.speech_active = true;
-                return Some(false); // speech started
+                self.speech_count += 1;
+                if self.speech_count >= self.min_speech_frames {
+                    self.speech_active = true;
+                    self.speech_count = 0;
+                    return Some(false); // speech started
+                }
             }
-        } else if self.speech_active {
+        } else if !self.speech_active {
+            self.speech_count = 0;
+        } else {
             self.silent_count += 1;
             if self.silent_count >= self.min_silent_frames {
                 self.speech_active = false;
@@ -70,10 +98,16 @@ impl VoiceActivityDetector {
         self.speech_active
     }
 
+    /// Whether speech frames are being heard but speech has not started yet.
+    pub fn is_pending(&self) -> bool {
+        self.speech_count > 0
+    }
+
     /// Reset the detector state.
     pub fn reset(&mut self) {
         self.speech_active = false;
         self.silent_count = 0;
+        self.speech_count = 0;
     }
 }
 
@@ -120,6 +154,39 @@ mod tests {
         assert!(!vad.is_active());
     }
 
+    #[test]
+    fn test_min_speech_frames_ignores_short_noise() {
+        let mut vad = VoiceActivityDetector::new(50.0, 3).with_min_speech_frames(3);
+        let silence = vec![0i16; 320];
+        let speech = vec![500i16; 320];
+
+        // Two loud frames then silence — too short to count
+        assert_eq!(vad.process_frame(&speech), None);
+        assert_eq!(vad.process_frame(&speech), None);
+        assert!(vad.is_pending());
+        assert_eq!(vad.process_frame(&silence), None);
+        assert!(!vad.is_pending());
+
+        assert_eq!(vad.process_frame(&speech), None);
+        assert_eq!(vad.process_frame(&speech), None);
+        assert_eq!(vad.process_frame(&speech), Some(false));
+        assert!(vad.is_active());
+    }
+
+    #[test]
+    fn test_from_config() {
+        let config = VadConfig {
+            energy_threshold: 1_000_000.0,
+            min_speech_ms: 100,
+            silence_hangover_ms: 500,
+            max_utterance_ms: 10_000,
+        };
+        let vad = VoiceActivityDetector::from_config(&config, 20);
+        assert_eq!(vad.threshold, 20_000.0);
+        assert_eq!(vad.min_speech_frames, 5);
+        assert_eq!(vad.min_silent_frames, 25);
+    }
+
     #[test]
     fn test_vad_reset() {
         let mut vad = VoiceActivityDetector::new(50.0, 3);
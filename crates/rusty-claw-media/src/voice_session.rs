@@ -1,27 +1,26 @@
-//! Voice session — buffers audio, feeds VAD, emits completed utterances.
+//! Voice session — buffers audio, feeds VAD, emits speech starts and
+//! completed utterances.
 
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, info};
 
+use rusty_claw_core::config::VadConfig;
+
+use crate::resample::{InputFormat, Resampler, STT_SAMPLE_RATE};
 use crate::vad::VoiceActivityDetector;
 
 /// Talk mode for voice interaction.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum TalkMode {
     /// Push-to-talk: client sends explicit start/stop signals.
     Push,
     /// Voice activity detection: automatic speech boundary detection.
+    #[default]
     Vad,
 }
 
-impl Default for TalkMode {
-    fn default() -> Self {
-        Self::Vad
-    }
-}
-
 /// A completed utterance ready for STT processing.
 pub struct Utterance {
     /// Raw 16-bit PCM audio at 16kHz mono.
@@ -30,9 +29,21 @@ pub struct Utterance {
     pub duration_ms: u64,
 }
 
+/// What a voice session reports while it runs.
+pub enum VoiceEvent {
+    /// The user started speaking (VAD mode). Used for barge-in.
+    SpeechStarted,
+    /// Audio of the utterance in progress (VAD mode), for partial
+    /// transcripts. The first one after `SpeechStarted` covers everything
+    /// heard so far.
+    SpeechAudio(Vec<i16>),
+    /// The user finished speaking.
+    Utterance(Utterance),
+}
+
 /// Handle for controlling a voice session from outside.
 pub struct VoiceSessionHandle {
-    /// Send raw audio bytes (16-bit PCM, 16kHz, mono).
+    /// Send raw audio bytes (16-bit PCM in the session's input format).
     pub audio_tx: mpsc::UnboundedSender<Vec<u8>>,
     /// Cancellation token to stop the session.
     pub cancel: CancellationToken,
@@ -44,30 +55,52 @@ pub struct VoiceSessionHandle {
 pub struct VoiceSession {
     mode: TalkMode,
     vad: VoiceActivityDetector,
+    /// Converts client audio to mono at `sample_rate`.
+    resampler: Resampler,
     buffer: Vec<i16>,
+    /// Audio of the utterance in progress (VAD mode).
+    speech: Vec<i16>,
     frame_size: usize, // samples per frame (e.g., 320 for 20ms at 16kHz)
     sample_rate: u32,
+    /// Utterances longer than this are cut and emitted while speech goes on.
+    max_utterance_samples: usize,
 }
 
 impl VoiceSession {
-    pub fn new(mode: TalkMode, sample_rate: u32) -> Self {
+    pub fn new(mode: TalkMode, sample_rate: u32, vad: VadConfig) -> Self {
         let frame_size = (sample_rate as usize) / 50; // 20ms frames
+        let vad = vad.clamped();
         Self {
             mode,
-            vad: VoiceActivityDetector::default_16khz(),
+            vad: VoiceActivityDetector::from_config(&vad, 20),
+            resampler: Resampler::new(InputFormat::default(), sample_rate),
             buffer: Vec::new(),
+            speech: Vec::new(),
             frame_size,
             sample_rate,
+            max_utterance_samples: (vad.max_utterance_ms * sample_rate as u64 / 1000) as usize,
         }
     }
 
-    /// Start the voice session, returning a handle and an utterance receiver.
+    /// Set the format clients send audio in; it is downmixed and resampled
+    /// to the session's rate. Defaults to mono at the session's rate.
+    pub fn with_input_format(mut self, input: InputFormat) -> Self {
+        self.resampler = Resampler::new(input, self.sample_rate);
+        self
+    }
+
+    /// Start the voice session, returning a handle and an event receiver.
     ///
     /// The session runs in a background task, processing incoming audio
-    /// and emitting complete utterances.
-    pub fn start(mode: TalkMode) -> (VoiceSessionHandle, mpsc::UnboundedReceiver<Utterance>) {
+    /// (in the `input` format) and emitting speech starts and complete
+    /// utterances at the STT sample rate.
+    pub fn start(
+        mode: TalkMode,
+        vad: VadConfig,
+        input: InputFormat,
+    ) -> (VoiceSessionHandle, mpsc::UnboundedReceiver<VoiceEvent>) {
         let (audio_tx, audio_rx) = mpsc::unbounded_channel::<Vec<u8>>();
-        let (utterance_tx, utterance_rx) = mpsc::unbounded_channel::<Utterance>();
+        let (event_tx, event_rx) = mpsc::unbounded_channel::<VoiceEvent>();
         let cancel = CancellationToken::new();
 
         let handle = VoiceSessionHandle {
@@ -76,28 +109,28 @@ impl VoiceSession {
             mode,
         };
 
-        let mut session = Self::new(mode, 16000);
+        let mut session = Self::new(mode, STT_SAMPLE_RATE, vad).with_input_format(input);
 
         tokio::spawn(async move {
-            info!(?mode, "Voice session started");
-            session.run(audio_rx, utterance_tx, cancel).await;
+            info!(?mode, ?input, "Voice session started");
+            session.run(audio_rx, event_tx, cancel).await;
             info!("Voice session ended");
         });
 
-        (handle, utterance_rx)
+        (handle, event_rx)
     }
 
     async fn run(
         &mut self,
         mut audio_rx: mpsc::UnboundedReceiver<Vec<u8>>,
-        utterance_tx: mpsc::UnboundedSender<Utterance>,
+        event_tx: mpsc::UnboundedSender<VoiceEvent>,
         cancel: CancellationToken,
     ) {
         loop {
             tokio::select! {
                 _ = cancel.cancelled() => break,
                 Some(raw_bytes) = audio_rx.recv() => {
-                    self.process_audio(&raw_bytes, &utterance_tx);
+                    self.process_audio(&raw_bytes, &event_tx);
                 }
                 else => break,
             }
@@ -107,16 +140,21 @@ impl VoiceSession {
     fn process_audio(
         &mut self,
         raw_bytes: &[u8],
-        utterance_tx: &mpsc::UnboundedSender<Utterance>,
+        event_tx: &mpsc::UnboundedSender<VoiceEvent>,
     ) {
         // Convert bytes to i16 samples (little-endian)
         let samples: Vec<i16> = raw_bytes
             .chunks_exact(2)
             .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]))
             .collect();
+        let samples = if self.resampler.is_passthrough() {
+            samples
+        } else {
+            self.resampler.process(&samples)
+        };
 
         match self.mode {
-            TalkMode::Vad => self.process_vad(&samples, utterance_tx),
+            TalkMode::Vad => self.process_vad(&samples, event_tx),
             TalkMode::Push => {
                 // In push mode, just accumulate — utterance is emitted on stop
                 self.buffer.extend_from_slice(&samples);
@@ -124,26 +162,52 @@ impl VoiceSession {
         }
     }
 
-    fn process_vad(
-        &mut self,
-        samples: &[i16],
-        _utterance_tx: &mpsc::UnboundedSender<Utterance>,
-    ) {
+    fn process_vad(&mut self, samples: &[i16], event_tx: &mpsc::UnboundedSender<VoiceEvent>) {
         self.buffer.extend_from_slice(samples);
 
-        // Process complete frames through VAD
+        // Process complete frames through VAD, keeping the frames from the
+        // first speech frame through the trailing silence that ends it
         while self.buffer.len() >= self.frame_size {
             let frame: Vec<i16> = self.buffer.drain(..self.frame_size).collect();
-            if let Some(true) = self.vad.process_frame(&frame) {
-                // Speech ended — emit utterance from accumulated audio
-                // (The buffer still has remaining samples after drain)
-                // We need to reconstruct the full utterance from what came before
-                // For simplicity, we track the utterance audio separately
-                debug!("VAD detected speech end");
+            let transition = self.vad.process_frame(&frame);
+            if self.vad.is_active() || self.vad.is_pending() || transition == Some(true) {
+                self.speech.extend_from_slice(&frame);
+            } else {
+                // Noise too short to count as speech
+                self.speech.clear();
+            }
+            match transition {
+                Some(false) => {
+                    debug!("VAD detected speech start");
+                    let _ = event_tx.send(VoiceEvent::SpeechStarted);
+                    let _ = event_tx.send(VoiceEvent::SpeechAudio(self.speech.clone()));
+                }
+                Some(true) => {
+                    debug!("VAD detected speech end");
+                    self.emit_speech(event_tx);
+                }
+                None if self.vad.is_active() && self.speech.len() >= self.max_utterance_samples => {
+                    // Keep listening; the rest becomes the next utterance
+                    debug!("Utterance reached max length, cutting");
+                    self.emit_speech(event_tx);
+                }
+                None if self.vad.is_active() => {
+                    let _ = event_tx.send(VoiceEvent::SpeechAudio(frame));
+                }
+                None => {}
             }
         }
     }
 
+    fn emit_speech(&mut self, event_tx: &mpsc::UnboundedSender<VoiceEvent>) {
+        let pcm_data = std::mem::take(&mut self.speech);
+        let duration_ms = (pcm_data.len() as u64 * 1000) / self.sample_rate as u64;
+        let _ = event_tx.send(VoiceEvent::Utterance(Utterance {
+            pcm_data,
+            duration_ms,
+        }));
+    }
+
     /// Flush the buffer as an utterance (used in push mode on stop).
     pub fn flush(&mut self) -> Option<Utterance> {
         if self.buffer.is_empty() {
@@ -163,6 +227,7 @@ impl VoiceSession {
         self.mode = mode;
         self.vad.reset();
         self.buffer.clear();
+        self.speech.clear();
     }
 }
 
@@ -172,7 +237,7 @@ mod tests {
 
     #[test]
     fn test_buffer_accumulation() {
-        let mut session = VoiceSession::new(TalkMode::Push, 16000);
+        let mut session = VoiceSession::new(TalkMode::Push, 16000, VadConfig::default());
 
         // Simulate adding audio
         let samples: Vec<i16> = vec![100; 320];
@@ -194,17 +259,102 @@ mod tests {
         assert_eq!(u.duration_ms, 20); // 320 samples at 16kHz = 20ms
     }
 
+    #[test]
+    fn test_stereo_48khz_input_is_converted() {
+        let input = InputFormat {
+            sample_rate: 48000,
+            channels: 2,
+        };
+        let mut session =
+            VoiceSession::new(TalkMode::Push, 16000, VadConfig::default()).with_input_format(input);
+
+        // One second of 48kHz stereo
+        let bytes: Vec<u8> = vec![100i16; 48000 * 2]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        session.process_audio(&bytes, &tx);
+
+        let u = session.flush().unwrap();
+        assert_eq!(u.pcm_data.len(), 16000);
+        assert_eq!(u.duration_ms, 1000);
+    }
+
     #[test]
     fn test_mode_switch() {
-        let mut session = VoiceSession::new(TalkMode::Push, 16000);
+        let mut session = VoiceSession::new(TalkMode::Push, 16000, VadConfig::default());
         session.buffer.extend_from_slice(&[100i16; 100]);
         session.set_mode(TalkMode::Vad);
         assert!(session.buffer.is_empty());
     }
 
+    #[test]
+    fn test_vad_emits_speech_start_and_utterance() {
+        let mut session = VoiceSession::new(TalkMode::Vad, 16000, VadConfig::default());
+        let to_bytes =
+            |samples: Vec<i16>| -> Vec<u8> { samples.iter().flat_map(|s| s.to_le_bytes()).collect() };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        // 10 frames of speech, then enough silence to end it
+        session.process_audio(&to_bytes(vec![1000; 320 * 10]), &tx);
+        assert!(matches!(rx.try_recv(), Ok(VoiceEvent::SpeechStarted)));
+        // Speech audio so far streams out as it is heard
+        let mut streamed = 0;
+        while let Ok(VoiceEvent::SpeechAudio(pcm)) = rx.try_recv() {
+            streamed += pcm.len();
+        }
+        assert_eq!(streamed, 320 * 10);
+
+        session.process_audio(&to_bytes(vec![0; 320 * 20]), &tx);
+        let utterance = loop {
+            match rx.try_recv() {
+                Ok(VoiceEvent::SpeechAudio(_)) => continue,
+                other => break other,
+            }
+        };
+        match utterance {
+            Ok(VoiceEvent::Utterance(u)) => {
+                // Speech plus the 15 silent frames that ended it
+                assert_eq!(u.pcm_data.len(), 320 * 25);
+                assert_eq!(u.duration_ms, 500);
+            }
+            _ => panic!("expected an utterance"),
+        }
+        assert!(session.speech.is_empty());
+    }
+
+    #[test]
+    fn test_vad_cuts_long_utterances() {
+        let vad = VadConfig {
+            max_utterance_ms: 1000,
+            ..Default::default()
+        };
+        let mut session = VoiceSession::new(TalkMode::Vad, 16000, vad);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        // 1.5s of continuous speech
+        let bytes: Vec<u8> = vec![1000i16; 16000 * 3 / 2]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        session.process_audio(&bytes, &tx);
+
+        assert!(matches!(rx.try_recv(), Ok(VoiceEvent::SpeechStarted)));
+        let mut events = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter(|e| !matches!(e, VoiceEvent::SpeechAudio(_)));
+        match events.next() {
+            Some(VoiceEvent::Utterance(u)) => assert_eq!(u.duration_ms, 1000),
+            _ => panic!("expected a cut utterance"),
+        }
+        // Still speaking: no new speech start, remainder is buffered
+        assert!(events.next().is_none());
+        assert_eq!(session.speech.len(), 8000);
+    }
+
     #[tokio::test]
     async fn test_session_lifecycle() {
-        let (handle, _utterance_rx) = VoiceSession::start(TalkMode::Push);
+        let (handle, _utterance_rx) = VoiceSession::start(TalkMode::Push, VadConfig::default(), InputFormat::default());
 
         // Send some audio
         let samples: Vec<u8> = vec![0u8; 640]; // 320 samples worth
@@ -3,10 +3,22 @@
 //! [`PluginApi`] is passed to each plugin during initialization so it can
 //! register tools, hooks, and other extensions with the runtime.
 
+use std::future::Future;
+use std::pin::Pin;
+
 use crate::hooks::HookHandler;
 use crate::HookEvent;
 use rusty_claw_tools::Tool;
 
+/// Async handler for a plugin-registered gateway method. Receives the
+/// request params and returns the response payload; an error is sent back
+/// to the client as a `plugin_error` response.
+pub type GatewayMethodHandler = Box<
+    dyn Fn(Option<serde_json::Value>) -> Pin<Box<dyn Future<Output = anyhow::Result<serde_json::Value>> + Send>>
+        + Send
+        + Sync,
+>;
+
 /// Registration API handed to plugins during [`Plugin::register`].
 ///
 /// Collected hooks are applied to the [`HookRegistry`] by the
@@ -14,6 +26,9 @@ use rusty_claw_tools::Tool;
 pub struct PluginApi {
     tools: Vec<Box<dyn Tool>>,
     pending_hooks: Vec<(HookEvent, HookHandler)>,
+    gateway_methods: Vec<(String, GatewayMethodHandler)>,
+    http_routes: Option<axum::Router>,
+    public_http_routes: Option<axum::Router>,
 }
 
 impl PluginApi {
@@ -21,6 +36,9 @@ impl PluginApi {
         Self {
             tools: Vec::new(),
             pending_hooks: Vec::new(),
+            gateway_methods: Vec::new(),
+            http_routes: None,
+            public_http_routes: None,
         }
     }
 
@@ -37,6 +55,45 @@ impl PluginApi {
         self.pending_hooks.push((event, handler));
     }
 
+    /// Register a gateway method that clients can call over the WebSocket
+    /// protocol. Names that clash with a built-in method are ignored.
+    pub fn register_gateway_method(
+        &mut self,
+        name: impl Into<String>,
+        handler: GatewayMethodHandler,
+    ) {
+        self.gateway_methods.push((name.into(), handler));
+    }
+
+    /// Register HTTP routes served by the gateway under
+    /// `/plugins/{plugin_id}`. Calling this more than once merges the routers.
+    ///
+    /// The gateway authenticates requests to these routes like `/ws`: the
+    /// token or password goes in an `Authorization: Bearer` header or a
+    /// `token` query parameter, and API keys need the
+    /// `plugins.{plugin_id}` scope.
+    pub fn register_http_routes(&mut self, router: axum::Router) {
+        self.http_routes = Some(match self.http_routes.take() {
+            Some(existing) => existing.merge(router),
+            None => router,
+        });
+    }
+
+    /// Register HTTP routes like [`Self::register_http_routes`], but served
+    /// without gateway authentication, e.g. for webhooks from third-party
+    /// services. These routes must authenticate requests themselves.
+    pub fn register_public_http_routes(&mut self, router: axum::Router) {
+        self.public_http_routes = Some(match self.public_http_routes.take() {
+            Some(existing) => existing.merge(router),
+            None => router,
+        });
+    }
+
+    /// Events of the hooks queued so far.
+    pub(crate) fn hook_events(&self) -> impl Iterator<Item = HookEvent> + '_ {
+        self.pending_hooks.iter().map(|(event, _)| *event)
+    }
+
     /// Take all registered tools out of this API (consuming them).
     pub(crate) fn take_tools(&mut self) -> Vec<Box<dyn Tool>> {
         std::mem::take(&mut self.tools)
@@ -46,6 +103,21 @@ impl PluginApi {
     pub(crate) fn take_hooks(&mut self) -> Vec<(HookEvent, HookHandler)> {
         std::mem::take(&mut self.pending_hooks)
     }
+
+    /// Take the registered HTTP routes out of this API, if any.
+    pub(crate) fn take_http_routes(&mut self) -> Option<axum::Router> {
+        self.http_routes.take()
+    }
+
+    /// Take the registered public HTTP routes out of this API, if any.
+    pub(crate) fn take_public_http_routes(&mut self) -> Option<axum::Router> {
+        self.public_http_routes.take()
+    }
+
+    /// Take all registered gateway methods out of this API (consuming them).
+    pub(crate) fn take_gateway_methods(&mut self) -> Vec<(String, GatewayMethodHandler)> {
+        std::mem::take(&mut self.gateway_methods)
+    }
 }
 
 #[cfg(test)]
@@ -107,4 +179,18 @@ mod tests {
 
         assert_eq!(hooks.count(HookEvent::BeforeToolCall).await, 1);
     }
+
+    #[tokio::test]
+    async fn test_register_gateway_method() {
+        let mut api = PluginApi::new();
+        api.register_gateway_method(
+            "weather.get",
+            Box::new(|params| Box::pin(async move { Ok(serde_json::json!({ "echo": params })) })),
+        );
+        let methods = api.take_gateway_methods();
+        assert_eq!(methods.len(), 1);
+        assert_eq!(methods[0].0, "weather.get");
+        let payload = (methods[0].1)(Some(serde_json::json!(1))).await.unwrap();
+        assert_eq!(payload["echo"], 1);
+    }
 }
@@ -0,0 +1,260 @@
+//! Sandboxed HTTP access for WASM plugin tools.
+//!
+//! A plugin granted `plugins.wasm.<name>.http` in config can call the
+//! `http_fetch` host function. Requests are only allowed to `http`/`https`
+//! URLs on the plugin's allowlisted domains (redirects included), the
+//! response body is capped, and every request has a timeout. Failures are
+//! returned to the plugin as an error result rather than trapping.
+//!
+//! Requests and results cross the sandbox as JSON:
+//!
+//! ```json
+//! {"url": "https://api.example.com/v1/items", "method": "GET", "headers": {}, "body": null}
+//! {"Ok": {"status": 200, "headers": {"content-type": "application/json"}, "body": "..."}}
+//! {"Err": "Domain not allowed: evil.example"}
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use rusty_claw_core::config::WasmHttpConfig;
+
+/// Default cap on a response body: 1 MiB.
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 1024 * 1024;
+
+/// Default per-request timeout.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Most redirects followed for one request.
+const MAX_REDIRECTS: usize = 5;
+
+/// A request from a plugin.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HttpFetchRequest {
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+fn default_method() -> String {
+    "GET".into()
+}
+
+/// A response returned to a plugin. Non-2xx statuses are still `Ok`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HttpFetchResponse {
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+/// What the plugin receives: a response or an error message.
+pub type HttpFetchResult = Result<HttpFetchResponse, String>;
+
+/// One plugin's HTTP grant.
+pub struct HttpCapability {
+    allowed_domains: Arc<Vec<String>>,
+    max_response_bytes: usize,
+    client: reqwest::Client,
+}
+
+impl HttpCapability {
+    pub fn from_config(config: &WasmHttpConfig) -> anyhow::Result<Self> {
+        let allowed_domains: Arc<Vec<String>> = Arc::new(
+            config
+                .allowed_domains
+                .iter()
+                .map(|d| d.trim().trim_start_matches("*.").to_ascii_lowercase())
+                .filter(|d| !d.is_empty())
+                .collect(),
+        );
+        let redirect_domains = allowed_domains.clone();
+        let client = reqwest::Client::builder()
+            .timeout(
+                config
+                    .timeout_secs
+                    .map(Duration::from_secs)
+                    .unwrap_or(DEFAULT_TIMEOUT),
+            )
+            .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    return attempt.error("Too many redirects");
+                }
+                match check_url(&redirect_domains, attempt.url()) {
+                    Ok(()) => attempt.follow(),
+                    Err(e) => attempt.error(e),
+                }
+            }))
+            .build()?;
+        Ok(Self {
+            allowed_domains,
+            max_response_bytes: config
+                .max_response_bytes
+                .unwrap_or(DEFAULT_MAX_RESPONSE_BYTES),
+            client,
+        })
+    }
+
+    /// Perform a request on behalf of a plugin.
+    pub async fn fetch(&self, request: HttpFetchRequest) -> HttpFetchResult {
+        let url = Url::parse(&request.url).map_err(|e| format!("Invalid URL: {e}"))?;
+        check_url(&self.allowed_domains, &url)?;
+        let method = reqwest::Method::from_bytes(request.method.to_ascii_uppercase().as_bytes())
+            .map_err(|_| format!("Invalid method: {}", request.method))?;
+        if let Some(body) = &request.body
+            && body.len() > self.max_response_bytes
+        {
+            return Err(format!(
+                "Request body exceeds {} bytes",
+                self.max_response_bytes
+            ));
+        }
+
+        debug!(%method, %url, "WASM plugin HTTP request");
+        let mut builder = self.client.request(method, url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+        let mut response = builder
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {e}"))?;
+
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| format!("Failed to read response: {e}"))?
+        {
+            if body.len() + chunk.len() > self.max_response_bytes {
+                return Err(format!(
+                    "Response body exceeds {} bytes",
+                    self.max_response_bytes
+                ));
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(HttpFetchResponse {
+            status,
+            headers,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+}
+
+/// Allow only http(s) URLs whose host is an allowlisted domain or one of
+/// its subdomains.
+fn check_url(allowed_domains: &[String], url: &Url) -> Result<(), String> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("Unsupported URL scheme: {}", url.scheme()));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| "URL has no host".to_string())?
+        .to_ascii_lowercase();
+    let allowed = allowed_domains.iter().any(|domain| {
+        host == *domain
+            || host
+                .strip_suffix(domain.as_str())
+                .is_some_and(|prefix| prefix.ends_with('.'))
+    });
+    if allowed {
+        Ok(())
+    } else {
+        Err(format!("Domain not allowed: {host}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn capability(domains: &[&str], max_response_bytes: usize) -> HttpCapability {
+        HttpCapability::from_config(&WasmHttpConfig {
+            allowed_domains: domains.iter().map(|d| d.to_string()).collect(),
+            max_response_bytes: Some(max_response_bytes),
+            timeout_secs: Some(5),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_domain_allowlist() {
+        let allowed = vec!["example.com".to_string()];
+        let check = |url: &str| check_url(&allowed, &Url::parse(url).unwrap());
+        assert!(check("https://example.com/path").is_ok());
+        assert!(check("https://API.example.com/v1").is_ok());
+        assert!(check("https://badexample.com/").is_err());
+        assert!(check("https://example.com.evil.net/").is_err());
+        assert!(check("file:///etc/passwd").is_err());
+        assert!(check_url(&[], &Url::parse("https://example.com").unwrap()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_limits_and_errors() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                let body = "x".repeat(100);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        let request = HttpFetchRequest {
+            url: format!("http://127.0.0.1:{port}/"),
+            method: "get".into(),
+            headers: HashMap::new(),
+            body: None,
+        };
+
+        let response = capability(&["127.0.0.1"], 1000)
+            .fetch(request.clone())
+            .await
+            .unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body.len(), 100);
+
+        let err = capability(&["127.0.0.1"], 50)
+            .fetch(request.clone())
+            .await
+            .unwrap_err();
+        assert!(err.contains("exceeds 50 bytes"));
+
+        let err = capability(&["example.com"], 1000)
+            .fetch(request)
+            .await
+            .unwrap_err();
+        assert_eq!(err, "Domain not allowed: 127.0.0.1");
+
+        // Errors serialize as a result the plugin can match on
+        let json = serde_json::to_value(HttpFetchResult::Err(err)).unwrap();
+        assert_eq!(json["Err"], "Domain not allowed: 127.0.0.1");
+    }
+}
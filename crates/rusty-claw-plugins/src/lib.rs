@@ -4,21 +4,27 @@
 //! gateway methods, and CLI commands.
 
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 
 pub mod api;
 pub mod hooks;
+pub mod http_capability;
 pub mod logging_plugin;
 pub mod manager;
+pub mod manifest;
 #[cfg(feature = "wasm")]
 pub mod wasm_adapter;
 #[cfg(feature = "wasm")]
 pub mod wasm_runtime;
 
+pub use api::GatewayMethodHandler;
 pub use hooks::{HookContext, HookHandler, HookRegistry, HookResult};
 pub use manager::{PluginManager, PluginRegistrations};
+pub use manifest::{PluginManifest, PluginPermissions, PluginPolicy};
 
 /// Lifecycle hook events that plugins can subscribe to.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum HookEvent {
     BeforeAgentStart,
     LlmInput,
@@ -37,6 +43,8 @@ pub enum HookEvent {
     SessionEnd,
     GatewayStart,
     GatewayStop,
+    /// The config file changed on disk and was reloaded.
+    ConfigReload,
 }
 
 /// Plugin registration API.
@@ -66,6 +74,14 @@ pub trait Plugin: Send + Sync + 'static {
     /// Human-readable plugin name.
     fn name(&self) -> &str;
 
+    /// Version, requested permissions, and compatibility constraints.
+    ///
+    /// Checked by [`PluginManager::initialize`] before the plugin's
+    /// registrations are applied.
+    fn manifest(&self) -> PluginManifest {
+        PluginManifest::default()
+    }
+
     /// Register extensions with the runtime.
     fn register(&self, api: &mut PluginApi);
 }
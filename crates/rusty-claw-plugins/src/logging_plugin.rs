@@ -2,6 +2,7 @@
 
 use crate::api::PluginApi;
 use crate::hooks::HookResult;
+use crate::manifest::{PluginManifest, PluginPermissions};
 use crate::HookEvent;
 use crate::Plugin;
 
@@ -17,6 +18,21 @@ impl Plugin for LoggingPlugin {
         "Logging Plugin"
     }
 
+    fn manifest(&self) -> PluginManifest {
+        PluginManifest {
+            version: env!("CARGO_PKG_VERSION").into(),
+            permissions: PluginPermissions {
+                hooks: vec![
+                    HookEvent::BeforeAgentStart,
+                    HookEvent::BeforeToolCall,
+                    HookEvent::AgentEnd,
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
     fn register(&self, api: &mut PluginApi) {
         api.register_hook(
             HookEvent::BeforeAgentStart,
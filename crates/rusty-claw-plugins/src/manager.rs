@@ -1,12 +1,14 @@
 //! Plugin manager — collects plugins and drives their initialization.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
-use tracing::info;
+use semver::{Version, VersionReq};
+use tracing::{info, warn};
 
-use crate::api::PluginApi;
+use crate::api::{GatewayMethodHandler, PluginApi};
 use crate::hooks::HookRegistry;
+use crate::manifest::{PluginManifest, PluginPolicy};
 use crate::Plugin;
 use rusty_claw_tools::Tool;
 
@@ -15,11 +17,21 @@ pub struct PluginManager {
     plugins: Vec<Box<dyn Plugin>>,
     hooks: Arc<HookRegistry>,
     plugin_ids: HashSet<String>,
+    policy: PluginPolicy,
 }
 
 /// Collected registrations from all plugins after initialization.
 pub struct PluginRegistrations {
     pub tools: Vec<Box<dyn Tool>>,
+    /// Gateway methods by name. When two plugins register the same name,
+    /// the first one wins.
+    pub gateway_methods: HashMap<String, GatewayMethodHandler>,
+    /// HTTP routes by plugin ID, to be served under `/plugins/{plugin_id}`
+    /// to authenticated callers.
+    pub http_routes: Vec<(String, axum::Router)>,
+    /// HTTP routes by plugin ID served under the same prefix without
+    /// gateway authentication.
+    pub public_http_routes: Vec<(String, axum::Router)>,
 }
 
 impl PluginManager {
@@ -28,9 +40,16 @@ impl PluginManager {
             plugins: Vec::new(),
             hooks: Arc::new(HookRegistry::new()),
             plugin_ids: HashSet::new(),
+            policy: PluginPolicy::default(),
         }
     }
 
+    /// Set the permissions plugins may request.
+    pub fn with_policy(mut self, policy: PluginPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
     /// Add a plugin. Returns an error if a plugin with the same ID is already registered.
     pub fn add_plugin(&mut self, plugin: Box<dyn Plugin>) -> anyhow::Result<()> {
         let id = plugin.id().to_string();
@@ -43,22 +62,98 @@ impl PluginManager {
     }
 
     /// Initialize all plugins: call their `register` method and collect registrations.
+    ///
+    /// Plugins whose manifest is invalid, targets another host version,
+    /// requests permissions outside the policy, registers hooks it did not
+    /// declare, or depends on a missing or refused plugin are skipped with
+    /// a warning, and none of their registrations are applied.
     pub async fn initialize(&mut self) -> anyhow::Result<PluginRegistrations> {
-        let mut all_tools: Vec<Box<dyn Tool>> = Vec::new();
-
+        let mut candidates: Vec<(&dyn Plugin, PluginManifest, PluginApi)> = Vec::new();
         for plugin in &self.plugins {
             info!(plugin_id = %plugin.id(), "Initializing plugin");
+            let manifest = plugin.manifest();
+            if let Err(reason) = manifest.check(&self.policy) {
+                refuse(plugin.id(), &reason);
+                continue;
+            }
+
             let mut api = PluginApi::new();
             plugin.register(&mut api);
+            if let Some(event) = api
+                .hook_events()
+                .find(|e| !manifest.permissions.hooks.contains(e))
+            {
+                refuse(
+                    plugin.id(),
+                    &format!("registers hook {event:?} not declared in its manifest"),
+                );
+                continue;
+            }
+            candidates.push((plugin.as_ref(), manifest, api));
+        }
+
+        // Drop plugins with unmet dependencies until the set is stable, so
+        // refusing one plugin also refuses everything that needs it.
+        loop {
+            let versions: HashMap<&str, &str> = candidates
+                .iter()
+                .map(|(plugin, manifest, _)| (plugin.id(), manifest.version.as_str()))
+                .collect();
+            let unmet = candidates.iter().position(|(plugin, manifest, _)| {
+                match unmet_dependency(manifest, &versions) {
+                    Some(reason) => {
+                        refuse(plugin.id(), &reason);
+                        true
+                    }
+                    None => false,
+                }
+            });
+            match unmet {
+                Some(index) => {
+                    candidates.remove(index);
+                }
+                None => break,
+            }
+        }
+
+        let mut all_tools: Vec<Box<dyn Tool>> = Vec::new();
+        let mut gateway_methods = HashMap::new();
+        let mut http_routes = Vec::new();
+        let mut public_http_routes = Vec::new();
+        for (plugin, _, mut api) in candidates {
             all_tools.extend(api.take_tools());
 
+            if let Some(router) = api.take_http_routes() {
+                http_routes.push((plugin.id().to_string(), router));
+            }
+            if let Some(router) = api.take_public_http_routes() {
+                public_http_routes.push((plugin.id().to_string(), router));
+            }
+
+            for (name, handler) in api.take_gateway_methods() {
+                if gateway_methods.contains_key(&name) {
+                    warn!(
+                        plugin_id = %plugin.id(),
+                        method = %name,
+                        "Gateway method already registered by another plugin"
+                    );
+                    continue;
+                }
+                gateway_methods.insert(name, handler);
+            }
+
             // Apply collected hooks to the registry
             for (event, handler) in api.take_hooks() {
                 self.hooks.register(event, handler).await;
             }
         }
 
-        Ok(PluginRegistrations { tools: all_tools })
+        Ok(PluginRegistrations {
+            tools: all_tools,
+            gateway_methods,
+            http_routes,
+            public_http_routes,
+        })
     }
 
     /// Get a reference to the shared hook registry.
@@ -66,21 +161,50 @@ impl PluginManager {
         self.hooks.clone()
     }
 
-    /// Load and add a WASM plugin from a file path.
+    /// Load and add a WASM plugin from a file path, applying its settings
+    /// from `config` (keyed by module name) if any.
     #[cfg(feature = "wasm")]
     pub fn add_wasm_plugin(
         &mut self,
         path: &std::path::Path,
         loader: &crate::wasm_runtime::WasmPluginLoader,
+        config: Option<&rusty_claw_core::config::PluginsConfig>,
     ) -> anyhow::Result<()> {
+        let manifest = PluginManifest::load_sidecar(path)?;
         let module = loader.load_module(path)?;
         let id = format!("wasm:{}", module.name);
         let name = module.name.clone();
-        let adapter = crate::wasm_adapter::WasmPluginAdapter::new(id, name, module);
+        let plugin_config = config.and_then(|c| c.wasm.get(&name));
+        let mut adapter =
+            crate::wasm_adapter::WasmPluginAdapter::new(id, name, module).with_manifest(manifest);
+        if let Some(http) = plugin_config.and_then(|c| c.http.as_ref()) {
+            adapter = adapter.with_http(crate::http_capability::HttpCapability::from_config(http)?);
+        }
         self.add_plugin(Box::new(adapter))
     }
 }
 
+fn refuse(plugin_id: &str, reason: &str) {
+    warn!(plugin_id = %plugin_id, reason = %reason, "Refusing to load plugin");
+}
+
+/// The first dependency of `manifest` that is not among the loaded plugins
+/// (`versions`, by ID) at a matching version.
+fn unmet_dependency(manifest: &PluginManifest, versions: &HashMap<&str, &str>) -> Option<String> {
+    manifest.dependencies.iter().find_map(|(id, req)| {
+        let Ok(req) = VersionReq::parse(req) else {
+            return Some(format!("invalid version requirement {req:?} for {id}"));
+        };
+        match versions.get(id.as_str()) {
+            None => Some(format!("depends on {id}, which is not loaded")),
+            Some(version) if !Version::parse(version).is_ok_and(|v| req.matches(&v)) => {
+                Some(format!("depends on {id} {req}, found {version}"))
+            }
+            Some(_) => None,
+        }
+    })
+}
+
 impl Default for PluginManager {
     fn default() -> Self {
         Self::new()
@@ -91,6 +215,7 @@ impl Default for PluginManager {
 mod tests {
     use super::*;
     use crate::hooks::HookResult;
+    use crate::manifest::PluginPermissions;
 
     struct TestPlugin {
         id: String,
@@ -103,6 +228,15 @@ mod tests {
         fn name(&self) -> &str {
             "Test Plugin"
         }
+        fn manifest(&self) -> PluginManifest {
+            PluginManifest {
+                permissions: PluginPermissions {
+                    hooks: vec![crate::HookEvent::BeforeAgentStart],
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+        }
         fn register(&self, api: &mut PluginApi) {
             api.register_hook(
                 crate::HookEvent::BeforeAgentStart,
@@ -120,6 +254,9 @@ mod tests {
         .unwrap();
         let regs = mgr.initialize().await.unwrap();
         assert!(regs.tools.is_empty());
+        assert!(regs.gateway_methods.is_empty());
+        assert!(regs.http_routes.is_empty());
+        assert!(regs.public_http_routes.is_empty());
         assert_eq!(
             mgr.hooks().count(crate::HookEvent::BeforeAgentStart).await,
             1
@@ -139,4 +276,88 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("Duplicate plugin ID"));
     }
+
+    struct ManifestPlugin {
+        id: &'static str,
+        manifest: PluginManifest,
+    }
+
+    impl Plugin for ManifestPlugin {
+        fn id(&self) -> &str {
+            self.id
+        }
+        fn name(&self) -> &str {
+            "Manifest Plugin"
+        }
+        fn manifest(&self) -> PluginManifest {
+            self.manifest.clone()
+        }
+        fn register(&self, api: &mut PluginApi) {
+            api.register_hook(
+                crate::HookEvent::AgentEnd,
+                Box::new(|_ctx, _data| Box::pin(async { Ok(HookResult::Continue) })),
+            );
+        }
+    }
+
+    fn manifest_plugin(id: &'static str, manifest: PluginManifest) -> Box<dyn Plugin> {
+        let mut manifest = manifest;
+        manifest.permissions.hooks.push(crate::HookEvent::AgentEnd);
+        Box::new(ManifestPlugin { id, manifest })
+    }
+
+    #[tokio::test]
+    async fn test_initialize_refuses_incompatible_plugins() {
+        let mut mgr = PluginManager::new();
+        // Undeclared hook
+        mgr.add_plugin(Box::new(ManifestPlugin {
+            id: "undeclared",
+            manifest: PluginManifest::default(),
+        }))
+        .unwrap();
+        // Over-privileged under the default policy
+        let mut networked = PluginManifest::default();
+        networked.permissions.network = true;
+        mgr.add_plugin(manifest_plugin("networked", networked))
+            .unwrap();
+        // Depends on a refused plugin
+        mgr.add_plugin(manifest_plugin(
+            "dependent",
+            PluginManifest {
+                dependencies: HashMap::from([("networked".into(), "*".into())]),
+                ..Default::default()
+            },
+        ))
+        .unwrap();
+
+        mgr.initialize().await.unwrap();
+        assert_eq!(mgr.hooks().count(crate::HookEvent::AgentEnd).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_initialize_checks_dependency_versions() {
+        let mut mgr = PluginManager::new();
+        mgr.add_plugin(manifest_plugin(
+            "base",
+            PluginManifest {
+                version: "1.4.0".into(),
+                ..Default::default()
+            },
+        ))
+        .unwrap();
+        for (id, req) in [("wants-1", "^1.2"), ("wants-2", "^2")] {
+            mgr.add_plugin(manifest_plugin(
+                id,
+                PluginManifest {
+                    dependencies: HashMap::from([("base".into(), req.into())]),
+                    ..Default::default()
+                },
+            ))
+            .unwrap();
+        }
+
+        mgr.initialize().await.unwrap();
+        // base and wants-1 load; wants-2 is refused
+        assert_eq!(mgr.hooks().count(crate::HookEvent::AgentEnd).await, 2);
+    }
 }
@@ -0,0 +1,219 @@
+//! Plugin manifests — version, requested permissions, and compatibility.
+//!
+//! Native plugins return their manifest from [`Plugin::manifest`](crate::Plugin::manifest).
+//! WASM plugins read it from a JSON sidecar next to the module
+//! (`weather.wasm` → `weather.manifest.json`):
+//!
+//! ```json
+//! {
+//!   "version": "1.2.0",
+//!   "host_version": ">=0.1, <0.3",
+//!   "permissions": { "network": true, "hooks": ["before_tool_call"] },
+//!   "dependencies": { "wasm:geo": "^1" }
+//! }
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use rusty_claw_core::config::PluginsConfig;
+
+use crate::HookEvent;
+
+/// Version of the host that plugins are checked against.
+pub const HOST_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Metadata a plugin declares about itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginManifest {
+    /// Plugin version (semver).
+    #[serde(default = "default_version")]
+    pub version: String,
+
+    /// Host versions the plugin works with, as a semver requirement
+    /// (e.g. ">=0.1, <0.3"). Unset means any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_version: Option<String>,
+
+    /// What the plugin needs access to.
+    #[serde(default)]
+    pub permissions: PluginPermissions,
+
+    /// Other plugins this one requires, by plugin ID, with a semver
+    /// requirement on their version.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub dependencies: HashMap<String, String>,
+}
+
+fn default_version() -> String {
+    "0.0.0".into()
+}
+
+impl Default for PluginManifest {
+    fn default() -> Self {
+        Self {
+            version: default_version(),
+            host_version: None,
+            permissions: PluginPermissions::default(),
+            dependencies: HashMap::new(),
+        }
+    }
+}
+
+/// Permissions a plugin requests.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PluginPermissions {
+    #[serde(default)]
+    pub filesystem: bool,
+    #[serde(default)]
+    pub network: bool,
+    /// Hook events the plugin registers handlers for. Registering any
+    /// other event gets the plugin refused.
+    #[serde(default)]
+    pub hooks: Vec<HookEvent>,
+}
+
+impl PluginManifest {
+    /// Path of the sidecar manifest for a WASM module.
+    pub fn sidecar_path(wasm_path: &Path) -> PathBuf {
+        wasm_path.with_extension("manifest.json")
+    }
+
+    /// Load the sidecar manifest for a WASM module, or the default manifest
+    /// if there is none.
+    pub fn load_sidecar(wasm_path: &Path) -> anyhow::Result<Self> {
+        let path = Self::sidecar_path(wasm_path);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid plugin manifest {}: {e}", path.display()))
+    }
+
+    /// Check the manifest on its own: a valid version, a compatible host,
+    /// and permissions within `policy`. Returns why the plugin is refused.
+    pub fn check(&self, policy: &PluginPolicy) -> Result<(), String> {
+        Version::parse(&self.version)
+            .map_err(|e| format!("invalid version {:?}: {e}", self.version))?;
+
+        if let Some(req) = &self.host_version {
+            let req =
+                VersionReq::parse(req).map_err(|e| format!("invalid host_version {req:?}: {e}"))?;
+            let host = Version::parse(HOST_VERSION).expect("host version is semver");
+            if !req.matches(&host) {
+                return Err(format!("requires host {req}, running {host}"));
+            }
+        }
+
+        let permissions = &self.permissions;
+        if permissions.filesystem && !policy.allow_filesystem {
+            return Err("requests filesystem access, which is not allowed".into());
+        }
+        if permissions.network && !policy.allow_network {
+            return Err("requests network access, which is not allowed".into());
+        }
+        if let Some(allowed) = &policy.allowed_hooks
+            && let Some(event) = permissions.hooks.iter().find(|e| !allowed.contains(e))
+        {
+            return Err(format!("requests hook {event:?}, which is not allowed"));
+        }
+        Ok(())
+    }
+}
+
+/// What the host lets plugins do. Plugins requesting more are not loaded.
+#[derive(Debug, Clone, Default)]
+pub struct PluginPolicy {
+    pub allow_filesystem: bool,
+    pub allow_network: bool,
+    /// Hook events plugins may subscribe to; `None` allows all.
+    pub allowed_hooks: Option<HashSet<HookEvent>>,
+}
+
+impl PluginPolicy {
+    /// Build the policy from the `plugins` config section. Unknown hook
+    /// names are ignored with a warning.
+    pub fn from_config(config: Option<&PluginsConfig>) -> Self {
+        let Some(config) = config else {
+            return Self::default();
+        };
+        let allowed_hooks = config.allowed_hooks.as_ref().map(|names| {
+            names
+                .iter()
+                .filter_map(|name| {
+                    let event = serde_json::from_value(serde_json::Value::String(name.clone()));
+                    if event.is_err() {
+                        tracing::warn!(hook = %name, "Unknown hook event in plugins.allowed_hooks");
+                    }
+                    event.ok()
+                })
+                .collect()
+        });
+        Self {
+            allow_filesystem: config.allow_filesystem,
+            allow_network: config.allow_network,
+            allowed_hooks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sidecar_json() {
+        let manifest: PluginManifest = serde_json::from_str(
+            r#"{
+                "version": "1.2.0",
+                "host_version": ">=0.1",
+                "permissions": { "network": true, "hooks": ["before_tool_call"] },
+                "dependencies": { "wasm:geo": "^1" }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.version, "1.2.0");
+        assert!(manifest.permissions.network);
+        assert!(!manifest.permissions.filesystem);
+        assert_eq!(manifest.permissions.hooks, vec![HookEvent::BeforeToolCall]);
+        assert_eq!(manifest.dependencies["wasm:geo"], "^1");
+    }
+
+    #[test]
+    fn test_check_host_version_and_permissions() {
+        let policy = PluginPolicy::default();
+        assert!(PluginManifest::default().check(&policy).is_ok());
+
+        let incompatible = PluginManifest {
+            host_version: Some(">=99".into()),
+            ..Default::default()
+        };
+        assert!(
+            incompatible
+                .check(&policy)
+                .unwrap_err()
+                .contains("requires host")
+        );
+
+        let mut networked = PluginManifest::default();
+        networked.permissions.network = true;
+        assert!(networked.check(&policy).unwrap_err().contains("network"));
+        let open = PluginPolicy {
+            allow_network: true,
+            ..Default::default()
+        };
+        assert!(networked.check(&open).is_ok());
+
+        let mut hooked = PluginManifest::default();
+        hooked.permissions.hooks = vec![HookEvent::LlmInput];
+        let restricted = PluginPolicy {
+            allowed_hooks: Some(HashSet::from([HookEvent::BeforeToolCall])),
+            ..Default::default()
+        };
+        assert!(hooked.check(&restricted).unwrap_err().contains("LlmInput"));
+    }
+}
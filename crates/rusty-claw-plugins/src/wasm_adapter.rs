@@ -1,10 +1,20 @@
 //! WASM plugin and tool adapters — bridge between WASM modules and Rust traits.
+//!
+//! Tools may import one host function, `env.http_fetch(ptr, len) -> i64`.
+//! It takes a JSON request (see [`crate::http_capability`]) and returns the
+//! JSON result, allocated with the module's `alloc` and packed the same way
+//! as `execute`'s output. Without an HTTP grant every call returns an error
+//! result.
+
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use tracing::debug;
-use wasmtime::{Instance, Linker, Memory, Store};
+use wasmtime::{Caller, Instance, Linker, Memory, Store};
 
 use crate::api::PluginApi;
+use crate::http_capability::{HttpCapability, HttpFetchRequest, HttpFetchResult};
+use crate::manifest::PluginManifest;
 use crate::wasm_runtime::WasmModule;
 use crate::Plugin;
 use rusty_claw_tools::{Tool, ToolContext, ToolOutput};
@@ -14,17 +24,62 @@ pub struct WasmPluginAdapter {
     id: String,
     name: String,
     module: WasmModule,
+    manifest: PluginManifest,
+    http: Option<Arc<HttpCapability>>,
 }
 
 impl WasmPluginAdapter {
     pub fn new(id: String, name: String, module: WasmModule) -> Self {
-        Self { id, name, module }
+        Self {
+            id,
+            name,
+            module,
+            manifest: PluginManifest::default(),
+            http: None,
+        }
+    }
+
+    /// Use `manifest` (usually the module's sidecar) for compatibility and
+    /// permission checks.
+    pub fn with_manifest(mut self, manifest: PluginManifest) -> Self {
+        self.manifest = manifest;
+        self
+    }
+
+    /// Grant the plugin's tools outbound HTTP under `capability`.
+    pub fn with_http(mut self, capability: HttpCapability) -> Self {
+        self.http = Some(Arc::new(capability));
+        self
     }
 
     /// Get the underlying WASM module for tool discovery.
     pub fn module(&self) -> &WasmModule {
         &self.module
     }
+
+    /// The plugin's HTTP grant, to pass on to its tools.
+    pub fn http(&self) -> Option<Arc<HttpCapability>> {
+        self.http.clone()
+    }
+
+    /// Build the tool for a module that exports `execute`, carrying the
+    /// plugin's HTTP grant.
+    pub fn tool(&self) -> Option<WasmToolAdapter> {
+        let exports_execute = self.module.module.exports().any(|e| e.name() == "execute");
+        if !exports_execute {
+            return None;
+        }
+        let tool = WasmToolAdapter::new(
+            self.name.clone(),
+            format!("Tool provided by the WASM plugin '{}'", self.name),
+            serde_json::json!({"type": "object"}),
+            self.module.clone(),
+        );
+        Some(match self.http() {
+            Some(http) => tool.with_http(http),
+            None => tool,
+        })
+    }
 }
 
 impl Plugin for WasmPluginAdapter {
@@ -36,8 +91,14 @@ impl Plugin for WasmPluginAdapter {
         &self.name
     }
 
-    fn register(&self, _api: &mut PluginApi) {
-        // Tools are discovered and registered by the manager separately
+    fn manifest(&self) -> PluginManifest {
+        self.manifest.clone()
+    }
+
+    fn register(&self, api: &mut PluginApi) {
+        if let Some(tool) = self.tool() {
+            api.register_tool(Box::new(tool));
+        }
     }
 }
 
@@ -47,6 +108,12 @@ pub struct WasmToolAdapter {
     description: String,
     schema: serde_json::Value,
     module: WasmModule,
+    http: Option<Arc<HttpCapability>>,
+}
+
+/// Per-call store data, visible to host functions.
+struct HostState {
+    http: Option<Arc<HttpCapability>>,
 }
 
 impl WasmToolAdapter {
@@ -61,8 +128,15 @@ impl WasmToolAdapter {
             description,
             schema,
             module,
+            http: None,
         }
     }
+
+    /// Let this tool call `http_fetch` under `capability`.
+    pub fn with_http(mut self, capability: Arc<HttpCapability>) -> Self {
+        self.http = Some(capability);
+        self
+    }
 }
 
 #[async_trait]
@@ -87,8 +161,14 @@ impl Tool for WasmToolAdapter {
         debug!(tool = %self.tool_name, "Executing WASM tool");
 
         // Create a fresh Store per call for isolation
-        let mut store = Store::new(&self.module.engine, ());
-        let linker = Linker::new(&self.module.engine);
+        let mut store = Store::new(
+            &self.module.engine,
+            HostState {
+                http: self.http.clone(),
+            },
+        );
+        let mut linker = Linker::new(&self.module.engine);
+        add_host_functions(&mut linker)?;
         let instance = linker
             .instantiate_async(&mut store, &self.module.module)
             .await?;
@@ -119,9 +199,69 @@ impl Tool for WasmToolAdapter {
     }
 }
 
+/// Define the host functions tools may import.
+fn add_host_functions(linker: &mut Linker<HostState>) -> anyhow::Result<()> {
+    linker.func_wrap_async(
+        "env",
+        "http_fetch",
+        |mut caller: Caller<'_, HostState>, (ptr, len): (i32, i32)| {
+            Box::new(async move {
+                let request = read_caller_memory(&mut caller, ptr as u32 as usize, len as u32 as usize)?;
+                let result: HttpFetchResult = match caller.data().http.clone() {
+                    Some(http) => match serde_json::from_slice::<HttpFetchRequest>(&request) {
+                        Ok(request) => http.fetch(request).await,
+                        Err(e) => Err(format!("Invalid http_fetch request: {e}")),
+                    },
+                    None => Err("HTTP access not granted to this plugin".to_string()),
+                };
+                let output = serde_json::to_vec(&result)?;
+                write_caller_output(&mut caller, &output).await
+            })
+        },
+    )?;
+    Ok(())
+}
+
+fn caller_memory(caller: &mut Caller<'_, HostState>) -> anyhow::Result<Memory> {
+    caller
+        .get_export("memory")
+        .and_then(|e| e.into_memory())
+        .ok_or_else(|| anyhow::anyhow!("WASM module has no 'memory' export"))
+}
+
+fn read_caller_memory(
+    caller: &mut Caller<'_, HostState>,
+    offset: usize,
+    len: usize,
+) -> anyhow::Result<Vec<u8>> {
+    let memory = caller_memory(caller)?;
+    let mem_data = memory.data(&*caller);
+    let range = memory_range(offset, len, mem_data.len(), "read")?;
+    Ok(mem_data[range].to_vec())
+}
+
+/// Copy `data` into a buffer from the module's `alloc` and return it packed
+/// as ptr << 32 | len.
+async fn write_caller_output(
+    caller: &mut Caller<'_, HostState>,
+    data: &[u8],
+) -> anyhow::Result<i64> {
+    let memory = caller_memory(caller)?;
+    let alloc = caller
+        .get_export("alloc")
+        .and_then(|e| e.into_func())
+        .ok_or_else(|| anyhow::anyhow!("WASM module has no 'alloc' export"))?
+        .typed::<i32, i32>(&*caller)?;
+    let ptr = alloc.call_async(&mut *caller, data.len() as i32).await?;
+    memory
+        .write(&mut *caller, ptr as u32 as usize, data)
+        .map_err(|_| anyhow::anyhow!("WASM memory write out of bounds"))?;
+    Ok(((ptr as u32 as i64) << 32) | data.len() as i64)
+}
+
 /// Call the WASM module's "execute" export with input bytes.
 async fn call_wasm_execute(
-    store: &mut Store<()>,
+    store: &mut Store<HostState>,
     instance: &Instance,
     input: &[u8],
 ) -> anyhow::Result<Vec<u8>> {
@@ -140,7 +280,7 @@ async fn call_wasm_execute(
 
     // Allocate input buffer in WASM memory
     let input_ptr = alloc.call_async(&mut *store, input.len() as i32).await?;
-    write_to_memory(&memory, store, input_ptr as usize, input)?;
+    write_to_memory(&memory, store, input_ptr as u32 as usize, input)?;
 
     // Call execute(ptr, len) -> packed(ptr, len) as i64
     let result = execute
@@ -148,8 +288,8 @@ async fn call_wasm_execute(
         .await?;
 
     // Unpack result: high 32 bits = ptr, low 32 bits = len
-    let out_ptr = (result >> 32) as usize;
-    let out_len = (result & 0xFFFF_FFFF) as usize;
+    let out_ptr = (result as u64 >> 32) as usize;
+    let out_len = (result as u64 & 0xFFFF_FFFF) as usize;
 
     // Read output from WASM memory
     read_from_memory(&memory, store, out_ptr, out_len)
@@ -157,29 +297,39 @@ async fn call_wasm_execute(
 
 fn write_to_memory(
     memory: &Memory,
-    store: &mut Store<()>,
+    store: &mut Store<HostState>,
     offset: usize,
     data: &[u8],
 ) -> anyhow::Result<()> {
     let mem_data = memory.data_mut(store);
-    if offset + data.len() > mem_data.len() {
-        anyhow::bail!("WASM memory write out of bounds");
-    }
-    mem_data[offset..offset + data.len()].copy_from_slice(data);
+    let range = memory_range(offset, data.len(), mem_data.len(), "write")?;
+    mem_data[range].copy_from_slice(data);
     Ok(())
 }
 
 fn read_from_memory(
     memory: &Memory,
-    store: &mut Store<()>,
+    store: &mut Store<HostState>,
     offset: usize,
     len: usize,
 ) -> anyhow::Result<Vec<u8>> {
     let mem_data = memory.data(store);
-    if offset + len > mem_data.len() {
-        anyhow::bail!("WASM memory read out of bounds");
+    let range = memory_range(offset, len, mem_data.len(), "read")?;
+    Ok(mem_data[range].to_vec())
+}
+
+/// The byte range `offset..offset + len`, or an error if it does not fit
+/// in `memory_size` bytes. Guest pointers and lengths are unsigned 32-bit.
+fn memory_range(
+    offset: usize,
+    len: usize,
+    memory_size: usize,
+    access: &str,
+) -> anyhow::Result<std::ops::Range<usize>> {
+    match offset.checked_add(len) {
+        Some(end) if end <= memory_size => Ok(offset..end),
+        _ => anyhow::bail!("WASM memory {access} out of bounds"),
     }
-    Ok(mem_data[offset..offset + len].to_vec())
 }
 
 #[cfg(test)]
@@ -205,6 +355,94 @@ mod tests {
         drop(loader);
     }
 
+    /// Echoes its input to `http_fetch` and returns the result.
+    const FETCH_WAT: &str = r#"
+        (module
+          (import "env" "http_fetch" (func $http_fetch (param i32 i32) (result i64)))
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+          (func (export "execute") (param $ptr i32) (param $len i32) (result i64)
+            (call $http_fetch (local.get $ptr) (local.get $len))))
+    "#;
+
+    fn test_context() -> ToolContext {
+        ToolContext {
+            session_key: "test".into(),
+            workspace: std::env::temp_dir(),
+            config: Arc::new(rusty_claw_core::config::Config::default()),
+            restrict_to_workspace: false,
+            sandbox_mode: rusty_claw_core::config::SandboxMode::Off,
+            browser_pool: None,
+            progress: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_http_fetch_end_to_end() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                let response =
+                    "HTTP/1.1 200 OK\r\ncontent-length: 5\r\nconnection: close\r\n\r\nhello";
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let loader = crate::wasm_runtime::WasmPluginLoader::new().unwrap();
+        let module = loader.load_bytes("fetcher", FETCH_WAT.as_bytes()).unwrap();
+        let http = HttpCapability::from_config(&rusty_claw_core::config::WasmHttpConfig {
+            allowed_domains: vec!["127.0.0.1".into()],
+            max_response_bytes: None,
+            timeout_secs: Some(5),
+        })
+        .unwrap();
+        let params = serde_json::json!({"url": format!("http://127.0.0.1:{port}/")});
+
+        // The grant reaches the tool built from the plugin
+        let plugin = WasmPluginAdapter::new("wasm:fetcher".into(), "fetcher".into(), module)
+            .with_http(http);
+        let tool = plugin.tool().expect("module exports execute");
+        let output = tool.execute(params.clone(), &test_context()).await.unwrap();
+        assert!(!output.is_error, "{}", output.content);
+        let result: serde_json::Value = serde_json::from_str(&output.content).unwrap();
+        assert_eq!(result["Ok"]["status"], 200);
+        assert_eq!(result["Ok"]["body"], "hello");
+
+        // Without a grant the call fails inside the plugin
+        let plugin = WasmPluginAdapter::new(
+            "wasm:fetcher".into(),
+            "fetcher".into(),
+            plugin.module().clone(),
+        );
+        let output = plugin
+            .tool()
+            .unwrap()
+            .execute(params, &test_context())
+            .await
+            .unwrap();
+        let result: serde_json::Value = serde_json::from_str(&output.content).unwrap();
+        assert_eq!(result["Err"], "HTTP access not granted to this plugin");
+    }
+
+    #[test]
+    fn test_memory_range_bounds() {
+        assert_eq!(memory_range(10, 5, 16, "read").unwrap(), 10..15);
+        assert!(memory_range(10, 7, 16, "read").is_err());
+        assert!(memory_range(usize::MAX, 2, 16, "read").is_err());
+        // A negative guest pointer is a large unsigned offset, not a wrap
+        assert!(memory_range(-1i32 as u32 as usize, 1, 16, "write").is_err());
+    }
+
     #[test]
     fn test_tool_schema_passthrough() {
         // Test that WasmToolAdapter properly returns the schema
@@ -11,6 +11,7 @@ pub struct WasmPluginLoader {
 }
 
 /// A loaded WASM module ready for instantiation.
+#[derive(Clone)]
 pub struct WasmModule {
     pub engine: Engine,
     pub module: Module,
@@ -9,7 +9,7 @@ use async_trait::async_trait;
 use futures::Stream;
 use serde::{Deserialize, Serialize};
 use tokio_stream::StreamExt;
-use tracing::{debug, trace};
+use tracing::{debug, trace, warn};
 
 use rusty_claw_core::session::TranscriptEntry;
 use rusty_claw_core::types::ContentBlock;
@@ -17,11 +17,13 @@ use rusty_claw_core::types::ContentBlock;
 use crate::sse::parse_sse_stream;
 use crate::{
     ChunkUsage, CompletionChunk, CompletionRequest, Credentials, LlmProvider, ModelApi, ModelInfo,
-    ToolDefinition, ToolUseChunk,
+    ProviderHttpError, ResponseFormat, STRUCTURED_OUTPUT_NAME, ToolChoice, ToolDefinition,
+    ToolUseChunk,
 };
 
 const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
 const API_VERSION: &str = "2023-06-01";
+const OAUTH_BETA: &str = "oauth-2025-04-20";
 
 pub struct AnthropicProvider {
     pub base_url: String,
@@ -35,6 +37,13 @@ impl AnthropicProvider {
             client: reqwest::Client::new(),
         }
     }
+
+    /// Send Messages API requests through `client` instead of a default one,
+    /// e.g. a client built with the provider's proxy and extra headers.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
 }
 
 // --- Anthropic request/response types ---
@@ -43,16 +52,25 @@ impl AnthropicProvider {
 struct AnthropicRequest {
     model: String,
     max_tokens: u32,
+    /// Plain string, or an array of text blocks when prompt caching is on.
     #[serde(skip_serializing_if = "Option::is_none")]
-    system: Option<String>,
+    system: Option<serde_json::Value>,
     messages: Vec<serde_json::Value>,
     stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<serde_json::Value>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     thinking: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -69,6 +87,44 @@ struct MessageUsage {
     input_tokens: u64,
     #[serde(default)]
     output_tokens: u64,
+    #[serde(default)]
+    cache_creation_input_tokens: Option<u64>,
+    #[serde(default)]
+    cache_read_input_tokens: Option<u64>,
+}
+
+impl MessageUsage {
+    fn to_chunk_usage(&self) -> ChunkUsage {
+        ChunkUsage {
+            input_tokens: Some(self.input_tokens),
+            output_tokens: Some(self.output_tokens),
+            cache_creation_input_tokens: self.cache_creation_input_tokens,
+            cache_read_input_tokens: self.cache_read_input_tokens,
+        }
+    }
+}
+
+/// Apply `cache_control` breakpoints to the system prompt and the last tool
+/// definition so the stable prefix of each request can be served from cache.
+fn apply_prompt_cache(
+    system: Option<&str>,
+    tools: Option<Vec<serde_json::Value>>,
+) -> (Option<serde_json::Value>, Option<Vec<serde_json::Value>>) {
+    let cache_control = serde_json::json!({ "type": "ephemeral" });
+    let system = system.map(|s| {
+        serde_json::json!([{
+            "type": "text",
+            "text": s,
+            "cache_control": cache_control,
+        }])
+    });
+    let tools = tools.map(|mut tools| {
+        if let Some(last) = tools.last_mut().and_then(|t| t.as_object_mut()) {
+            last.insert("cache_control".into(), cache_control.clone());
+        }
+        tools
+    });
+    (system, tools)
 }
 
 #[derive(Debug, Deserialize)]
@@ -110,6 +166,8 @@ enum DeltaInfo {
     InputJsonDelta { partial_json: String },
     #[serde(rename = "thinking_delta")]
     ThinkingDelta { thinking: String },
+    #[serde(rename = "signature_delta")]
+    SignatureDelta { signature: String },
 }
 
 #[derive(Debug, Deserialize)]
@@ -173,8 +231,20 @@ impl LlmProvider for AnthropicProvider {
                     }));
                 }
                 TranscriptEntry::Assistant { content, .. } => {
-                    let blocks: Vec<serde_json::Value> =
-                        content.iter().map(anthropic_content_block).collect();
+                    let mut blocks: Vec<serde_json::Value> = Vec::new();
+                    // Signed thinking must lead the turn, each block with its
+                    // own signature and in order, so extended-thinking context
+                    // survives tool iterations; unsigned is rejected.
+                    for block in entry.thinking_blocks() {
+                        if let Some(signature) = block.signature {
+                            blocks.push(serde_json::json!({
+                                "type": "thinking",
+                                "thinking": block.thinking,
+                                "signature": signature,
+                            }));
+                        }
+                    }
+                    blocks.extend(content.iter().map(anthropic_content_block));
                     if !blocks.is_empty() {
                         messages.push(serde_json::json!({
                             "role": "assistant",
@@ -214,45 +284,83 @@ impl LlmProvider for AnthropicProvider {
         request: &CompletionRequest,
         credentials: &Credentials,
     ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<CompletionChunk>> + Send>>> {
-        let api_key = match credentials {
-            Credentials::ApiKey { api_key } => api_key.clone(),
-            _ => anyhow::bail!("Anthropic requires ApiKey credentials"),
-        };
+        let (auth_header, auth_value) = auth_header(credentials)?;
+
+        // Structured output rides on a forced tool call whose input is the reply
+        let structured_tool = request.response_format.as_ref().map(|format| match format {
+            ResponseFormat::JsonSchema { schema } => serde_json::json!({
+                "name": STRUCTURED_OUTPUT_NAME,
+                "description": "Respond with the final answer as structured data.",
+                "input_schema": schema,
+            }),
+        });
+        let structured = structured_tool.is_some();
+        let mut request_tools = request.tools.clone();
+        if let Some(tool) = structured_tool {
+            request_tools.get_or_insert_with(Vec::new).push(tool);
+        }
 
-        let thinking = request.thinking_budget_tokens.map(|budget| {
+        // The API rejects extended thinking combined with a forced tool
+        let thinking = request.thinking_budget_tokens.filter(|_| !structured).map(|budget| {
             serde_json::json!({
                 "type": "enabled",
                 "budget_tokens": budget,
             })
         });
 
+        let (system, tools) = if request.enable_prompt_cache {
+            apply_prompt_cache(request.system.as_deref(), request_tools)
+        } else {
+            (
+                request.system.clone().map(serde_json::Value::String),
+                request_tools,
+            )
+        };
+
+        // tool_choice is rejected by the API when no tools are sent
+        let tool_choice = if structured {
+            Some(anthropic_tool_choice(&ToolChoice::Tool {
+                name: STRUCTURED_OUTPUT_NAME.into(),
+            }))
+        } else {
+            request
+                .tool_choice
+                .as_ref()
+                .filter(|_| tools.is_some())
+                .map(anthropic_tool_choice)
+        };
+
         let body = AnthropicRequest {
             model: request.model.clone(),
             max_tokens: request.max_tokens,
-            system: request.system.clone(),
+            system,
             messages: request.messages.clone(),
             stream: true,
             temperature: if thinking.is_some() { None } else { request.temperature },
-            tools: request.tools.clone(),
+            // Sampling overrides are rejected alongside extended thinking
+            top_p: if thinking.is_some() { None } else { request.top_p },
+            top_k: if thinking.is_some() { None } else { request.top_k },
+            tools,
             thinking,
+            stop_sequences: request.stop_sequences.clone(),
+            tool_choice,
         };
 
         debug!(model = %body.model, "Streaming Anthropic Messages API");
 
-        let response = self
+        let mut req = self
             .client
             .post(format!("{}/v1/messages", self.base_url))
-            .header("x-api-key", &api_key)
+            .header(auth_header, &auth_value)
             .header("anthropic-version", API_VERSION)
-            .header("content-type", "application/json")
-            .json(&body)
-            .send()
-            .await?;
+            .header("content-type", "application/json");
+        if matches!(credentials, Credentials::OAuth { .. }) {
+            req = req.header("anthropic-beta", OAUTH_BETA);
+        }
+        let response = req.json(&body).send().await?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            anyhow::bail!("Anthropic API error {status}: {body}");
+            return Err(ProviderHttpError::from_response("Anthropic", response).await.into());
         }
 
         let sse_stream = parse_sse_stream(response);
@@ -262,6 +370,7 @@ impl LlmProvider for AnthropicProvider {
             ChunkState {
                 sse: Box::pin(sse_stream),
                 blocks: Vec::new(),
+                structured,
             },
             |mut state| async move {
                 loop {
@@ -287,11 +396,10 @@ impl LlmProvider for AnthropicProvider {
                                                     delta: None,
                                                     thinking: None,
                                                     tool_use: None,
-                                                    usage: Some(ChunkUsage {
-                                                        input_tokens: Some(usage.input_tokens),
-                                                        output_tokens: Some(usage.output_tokens),
-                                                    }),
+                                                    usage: Some(usage.to_chunk_usage()),
                                                     stop_reason: None,
+                                                    thinking_signature: None,
+                                                    system_fingerprint: None,
                                                 };
                                                 return Some((Ok(chunk), state));
                                             }
@@ -336,6 +444,8 @@ impl LlmProvider for AnthropicProvider {
                                                     tool_use: None,
                                                     usage: None,
                                                     stop_reason: None,
+                                                    thinking_signature: None,
+                                                    system_fingerprint: None,
                                                 };
                                                 return Some((Ok(chunk), state));
                                             }
@@ -358,6 +468,20 @@ impl LlmProvider for AnthropicProvider {
                                                     tool_use: None,
                                                     usage: None,
                                                     stop_reason: None,
+                                                    thinking_signature: None,
+                                                    system_fingerprint: None,
+                                                };
+                                                return Some((Ok(chunk), state));
+                                            }
+                                            DeltaInfo::SignatureDelta { signature } => {
+                                                let chunk = CompletionChunk {
+                                                    delta: None,
+                                                    thinking: None,
+                                                    tool_use: None,
+                                                    usage: None,
+                                                    stop_reason: None,
+                                                    thinking_signature: Some(signature),
+                                                    system_fingerprint: None,
                                                 };
                                                 return Some((Ok(chunk), state));
                                             }
@@ -379,6 +503,19 @@ impl LlmProvider for AnthropicProvider {
                                                 input_json,
                                             }) = state.blocks.get(idx as usize)
                                             {
+                                                // The forced structured-output call is the reply itself
+                                                if state.structured && name == STRUCTURED_OUTPUT_NAME {
+                                                    let chunk = CompletionChunk {
+                                                        delta: Some(input_json.clone()),
+                                                        thinking: None,
+                                                        tool_use: None,
+                                                        usage: None,
+                                                        stop_reason: None,
+                                                        thinking_signature: None,
+                                                        system_fingerprint: None,
+                                                    };
+                                                    return Some((Ok(chunk), state));
+                                                }
                                                 let chunk = CompletionChunk {
                                                     delta: None,
                                                     thinking: None,
@@ -389,6 +526,8 @@ impl LlmProvider for AnthropicProvider {
                                                     }),
                                                     usage: None,
                                                     stop_reason: None,
+                                                    thinking_signature: None,
+                                                    system_fingerprint: None,
                                                 };
                                                 return Some((Ok(chunk), state));
                                             }
@@ -400,15 +539,20 @@ impl LlmProvider for AnthropicProvider {
                                     if let Ok(md) =
                                         serde_json::from_str::<MessageDelta>(&sse_event.data)
                                     {
+                                        let stop_reason = match md.delta.stop_reason {
+                                            Some(reason) if state.structured && reason == "tool_use" => {
+                                                Some("end_turn".to_string())
+                                            }
+                                            other => other,
+                                        };
                                         let chunk = CompletionChunk {
                                             delta: None,
                                             thinking: None,
                                             tool_use: None,
-                                            usage: md.usage.map(|u| ChunkUsage {
-                                                input_tokens: Some(u.input_tokens),
-                                                output_tokens: Some(u.output_tokens),
-                                            }),
-                                            stop_reason: md.delta.stop_reason,
+                                            usage: md.usage.map(|u| u.to_chunk_usage()),
+                                            stop_reason,
+                                            thinking_signature: None,
+                                            system_fingerprint: None,
                                         };
                                         return Some((Ok(chunk), state));
                                     }
@@ -448,40 +592,181 @@ impl LlmProvider for AnthropicProvider {
     }
 
     async fn list_models(&self, credentials: &Credentials) -> anyhow::Result<Vec<ModelInfo>> {
-        // Return known Anthropic models (the API doesn't have a list endpoint)
-        let _ = credentials;
-        Ok(vec![
-            ModelInfo {
-                id: "claude-opus-4-20250514".into(),
-                name: "Claude Opus 4".into(),
-                api: ModelApi::AnthropicMessages,
-                reasoning: true,
-                context_window: 200_000,
-                max_tokens: 32_768,
-            },
-            ModelInfo {
-                id: "claude-sonnet-4-20250514".into(),
-                name: "Claude Sonnet 4".into(),
-                api: ModelApi::AnthropicMessages,
-                reasoning: true,
-                context_window: 200_000,
-                max_tokens: 16_384,
-            },
-            ModelInfo {
-                id: "claude-haiku-3-5-20241022".into(),
-                name: "Claude 3.5 Haiku".into(),
-                api: ModelApi::AnthropicMessages,
-                reasoning: false,
-                context_window: 200_000,
-                max_tokens: 8_192,
-            },
-        ])
+        match self.fetch_models(credentials).await {
+            Ok(models) if !models.is_empty() => Ok(models),
+            Ok(_) => Ok(fallback_models()),
+            Err(e) => {
+                warn!(%e, "Failed to list Anthropic models, using built-in list");
+                Ok(fallback_models())
+            }
+        }
+    }
+
+    /// Exact count from `POST /v1/messages/count_tokens`.
+    async fn count_tokens(
+        &self,
+        request: &CompletionRequest,
+        credentials: &Credentials,
+    ) -> anyhow::Result<usize> {
+        let (auth_header, auth_value) = auth_header(credentials)?;
+        let mut body = serde_json::json!({
+            "model": request.model,
+            "messages": request.messages,
+        });
+        if let Some(system) = &request.system {
+            body["system"] = serde_json::json!(system);
+        }
+        if let Some(tools) = &request.tools {
+            body["tools"] = serde_json::json!(tools);
+        }
+
+        let mut req = self
+            .client
+            .post(format!("{}/v1/messages/count_tokens", self.base_url))
+            .header(auth_header, &auth_value)
+            .header("anthropic-version", API_VERSION);
+        if matches!(credentials, Credentials::OAuth { .. }) {
+            req = req.header("anthropic-beta", OAUTH_BETA);
+        }
+        let response = req.json(&body).send().await?;
+        if !response.status().is_success() {
+            return Err(ProviderHttpError::from_response("Anthropic", response).await.into());
+        }
+        let count: TokenCount = response.json().await?;
+        Ok(count.input_tokens as usize)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenCount {
+    input_tokens: u64,
+}
+
+impl AnthropicProvider {
+    /// Page through `GET /v1/models`.
+    async fn fetch_models(&self, credentials: &Credentials) -> anyhow::Result<Vec<ModelInfo>> {
+        let (auth_header, auth_value) = auth_header(credentials)?;
+        let mut models = Vec::new();
+        let mut after_id: Option<String> = None;
+
+        loop {
+            let mut req = self
+                .client
+                .get(format!("{}/v1/models", self.base_url))
+                .query(&[("limit", MODELS_PAGE_SIZE)])
+                .header(auth_header, &auth_value)
+                .header("anthropic-version", API_VERSION);
+            if let Some(ref after) = after_id {
+                req = req.query(&[("after_id", after)]);
+            }
+            if matches!(credentials, Credentials::OAuth { .. }) {
+                req = req.header("anthropic-beta", OAUTH_BETA);
+            }
+
+            let response = req.send().await?;
+            if !response.status().is_success() {
+                return Err(ProviderHttpError::from_response("Anthropic", response).await.into());
+            }
+
+            let page: ModelsPage = response.json().await?;
+            models.extend(
+                page.data
+                    .into_iter()
+                    .map(|m| model_info(&m.id, m.display_name.as_deref())),
+            );
+
+            match page.last_id {
+                Some(last) if page.has_more => after_id = Some(last),
+                _ => break,
+            }
+        }
+
+        Ok(models)
     }
 }
 
+/// Translate a [`ToolChoice`] into Anthropic's `tool_choice` object.
+fn anthropic_tool_choice(choice: &ToolChoice) -> serde_json::Value {
+    match choice {
+        ToolChoice::Auto => serde_json::json!({ "type": "auto" }),
+        ToolChoice::None => serde_json::json!({ "type": "none" }),
+        ToolChoice::Required => serde_json::json!({ "type": "any" }),
+        ToolChoice::Tool { name } => serde_json::json!({ "type": "tool", "name": name }),
+    }
+}
+
+// API keys go in x-api-key; OAuth access tokens use bearer auth
+fn auth_header(credentials: &Credentials) -> anyhow::Result<(&'static str, String)> {
+    match credentials {
+        Credentials::ApiKey { api_key } => Ok(("x-api-key", api_key.clone())),
+        Credentials::OAuth { access_token, .. } => {
+            Ok(("authorization", format!("Bearer {access_token}")))
+        }
+        _ => anyhow::bail!("Anthropic requires ApiKey or OAuth credentials"),
+    }
+}
+
+const MODELS_PAGE_SIZE: u32 = 100;
+
+#[derive(Debug, Deserialize)]
+struct ModelsPage {
+    data: Vec<ModelEntry>,
+    #[serde(default)]
+    has_more: bool,
+    #[serde(default)]
+    last_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+    #[serde(default)]
+    display_name: Option<String>,
+}
+
+/// Build [`ModelInfo`] for a model ID, inferring limits and extended
+/// thinking support from the model family.
+fn model_info(id: &str, display_name: Option<&str>) -> ModelInfo {
+    let (reasoning, max_tokens) = if id.contains("claude-3-7") {
+        (true, 64_000)
+    } else if id.contains("claude-3-5") {
+        (false, 8_192)
+    } else if id.contains("claude-3") || id.contains("claude-2") || id.contains("instant") {
+        (false, 4_096)
+    } else if ["claude-opus-4-0", "claude-opus-4-1", "claude-opus-4-2025"]
+        .iter()
+        .any(|p| id.starts_with(p))
+    {
+        (true, 32_000)
+    } else {
+        // Claude 4 family and later
+        (true, 64_000)
+    };
+
+    ModelInfo {
+        id: id.to_string(),
+        name: display_name.unwrap_or(id).to_string(),
+        api: ModelApi::AnthropicMessages,
+        reasoning,
+        context_window: 200_000,
+        max_tokens,
+    }
+}
+
+/// Built-in model list, used when the models endpoint is unreachable.
+fn fallback_models() -> Vec<ModelInfo> {
+    vec![
+        model_info("claude-opus-4-20250514", Some("Claude Opus 4")),
+        model_info("claude-sonnet-4-20250514", Some("Claude Sonnet 4")),
+        model_info("claude-3-5-haiku-20241022", Some("Claude Haiku 3.5")),
+    ]
+}
+
 struct ChunkState {
     sse: Pin<Box<dyn Stream<Item = anyhow::Result<crate::sse::SseEvent>> + Send>>,
     blocks: Vec<BlockState>,
+    /// Whether the request forced the structured-output tool.
+    structured: bool,
 }
 
 /// Convert a ContentBlock to Anthropic JSON format.
@@ -522,6 +807,200 @@ fn anthropic_content_block(block: &ContentBlock) -> serde_json::Value {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_model_info_infers_family() {
+        let opus = model_info("claude-opus-4-1-20250805", Some("Claude Opus 4.1"));
+        assert_eq!(opus.name, "Claude Opus 4.1");
+        assert!(opus.reasoning);
+        assert_eq!(opus.max_tokens, 32_000);
+
+        let sonnet = model_info("claude-sonnet-4-5-20250929", None);
+        assert_eq!(sonnet.name, "claude-sonnet-4-5-20250929");
+        assert!(sonnet.reasoning);
+        assert_eq!(sonnet.max_tokens, 64_000);
+
+        assert!(model_info("claude-3-7-sonnet-20250219", None).reasoning);
+        let haiku = model_info("claude-3-5-haiku-20241022", None);
+        assert!(!haiku.reasoning);
+        assert_eq!(haiku.max_tokens, 8_192);
+        assert!(!model_info("claude-3-opus-20240229", None).reasoning);
+    }
+
+    #[tokio::test]
+    async fn test_list_models_pages_through_results() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for _ in 0..2 {
+                let (mut sock, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 4096];
+                let n = sock.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]);
+                let body = if request.contains("after_id=m1") {
+                    r#"{"data":[{"id":"claude-3-5-haiku-20241022","display_name":"Claude Haiku 3.5"}],"has_more":false,"last_id":"claude-3-5-haiku-20241022"}"#
+                } else {
+                    r#"{"data":[{"id":"claude-sonnet-4-20250514","display_name":"Claude Sonnet 4"}],"has_more":true,"last_id":"m1"}"#
+                };
+                let resp = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = sock.write_all(resp.as_bytes()).await;
+            }
+        });
+
+        let provider = AnthropicProvider::new(Some(&format!("http://{addr}")));
+        let credentials = Credentials::ApiKey {
+            api_key: "k".into(),
+        };
+        let models = provider.list_models(&credentials).await.unwrap();
+        let ids: Vec<&str> = models.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["claude-sonnet-4-20250514", "claude-3-5-haiku-20241022"]);
+        assert_eq!(models[1].name, "Claude Haiku 3.5");
+    }
+
+    #[tokio::test]
+    async fn test_count_tokens_uses_endpoint() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut sock, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let n = sock.read(&mut buf).await.unwrap();
+            let request = String::from_utf8_lossy(&buf[..n]);
+            assert!(request.starts_with("POST /v1/messages/count_tokens"));
+            let body = r#"{"input_tokens":1234}"#;
+            let resp = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = sock.write_all(resp.as_bytes()).await;
+        });
+
+        let provider = AnthropicProvider::new(Some(&format!("http://{addr}")));
+        let credentials = Credentials::ApiKey {
+            api_key: "k".into(),
+        };
+        let request = CompletionRequest {
+            model: "claude-sonnet-4-20250514".into(),
+            messages: vec![serde_json::json!({"role": "user", "content": "hello"})],
+            max_tokens: 16,
+            temperature: None,
+            tools: None,
+            system: Some("Be brief.".into()),
+            thinking_budget_tokens: None,
+            enable_prompt_cache: false,
+            stop_sequences: None,
+            tool_choice: None,
+            response_format: None,
+            top_p: None,
+            top_k: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            seed: None,
+        };
+        assert_eq!(provider.count_tokens(&request, &credentials).await.unwrap(), 1234);
+    }
+
+    #[tokio::test]
+    async fn test_structured_output_streams_as_text() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (body_tx, body_rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let (mut sock, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            // Read headers plus the full JSON body
+            loop {
+                let n = sock.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let len = head
+                        .lines()
+                        .find_map(|l| l.to_lowercase().strip_prefix("content-length: ").map(String::from))
+                        .and_then(|v| v.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if body.len() >= len {
+                        let _ = body_tx.send(body.to_string());
+                        break;
+                    }
+                }
+            }
+            let events = [
+                r#"{"type":"content_block_start","index":0,"content_block":{"type":"tool_use","id":"t1","name":"structured_output"}}"#,
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"input_json_delta","partial_json":"{\"ok\":"}}"#,
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"input_json_delta","partial_json":"true}"}}"#,
+                r#"{"type":"content_block_stop","index":0}"#,
+                r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"}}"#,
+                r#"{"type":"message_stop"}"#,
+            ];
+            let names = [
+                "content_block_start",
+                "content_block_delta",
+                "content_block_delta",
+                "content_block_stop",
+                "message_delta",
+                "message_stop",
+            ];
+            let sse: String = names
+                .iter()
+                .zip(events)
+                .map(|(name, data)| format!("event: {name}\ndata: {data}\n\n"))
+                .collect();
+            let resp = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{sse}",
+                sse.len()
+            );
+            let _ = sock.write_all(resp.as_bytes()).await;
+        });
+
+        let provider = AnthropicProvider::new(Some(&format!("http://{addr}")));
+        let request = CompletionRequest {
+            model: "claude-sonnet-4-20250514".into(),
+            max_tokens: 64,
+            response_format: Some(ResponseFormat::JsonSchema {
+                schema: serde_json::json!({"type": "object"}),
+            }),
+            ..Default::default()
+        };
+        let credentials = Credentials::ApiKey {
+            api_key: "k".into(),
+        };
+        let mut stream = provider.stream(&request, &credentials).await.unwrap();
+        let mut text = String::new();
+        let mut stop_reason = None;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.unwrap();
+            assert!(chunk.tool_use.is_none());
+            text.extend(chunk.delta);
+            stop_reason = chunk.stop_reason.or(stop_reason);
+        }
+        assert_eq!(text, r#"{"ok":true}"#);
+        assert_eq!(stop_reason.as_deref(), Some("end_turn"));
+
+        let sent: serde_json::Value = serde_json::from_str(&body_rx.await.unwrap()).unwrap();
+        assert_eq!(sent["tools"][0]["name"], STRUCTURED_OUTPUT_NAME);
+        assert_eq!(sent["tool_choice"]["name"], STRUCTURED_OUTPUT_NAME);
+    }
+
+    #[tokio::test]
+    async fn test_list_models_falls_back_offline() {
+        let provider = AnthropicProvider::new(Some("http://127.0.0.1:1"));
+        let credentials = Credentials::ApiKey {
+            api_key: "k".into(),
+        };
+        let models = provider.list_models(&credentials).await.unwrap();
+        assert_eq!(models.len(), fallback_models().len());
+    }
+
     #[test]
     fn test_content_block_start_text() {
         let json = r#"{"index":0,"content_block":{"type":"text","text":""}}"#;
@@ -634,6 +1113,9 @@ mod tests {
                     text: "Hi there".into(),
                 }],
                 usage: None,
+                thinking: None,
+                thinking_signature: None,
+                thinking_blocks: Vec::new(),
                 timestamp: Utc::now(),
             },
         ];
@@ -644,6 +1126,74 @@ mod tests {
         assert_eq!(messages[1]["content"][0]["text"], "Hi there");
     }
 
+    #[test]
+    fn test_content_block_delta_signature() {
+        let json = r#"{"index":0,"delta":{"type":"signature_delta","signature":"sig=="}}"#;
+        let cbd: ContentBlockDelta = serde_json::from_str(json).unwrap();
+        match cbd.delta {
+            DeltaInfo::SignatureDelta { signature } => assert_eq!(signature, "sig=="),
+            _ => panic!("expected SignatureDelta"),
+        }
+    }
+
+    #[test]
+    fn test_format_messages_replays_signed_thinking() {
+        use chrono::Utc;
+        let provider = AnthropicProvider::new(None);
+        let assistant = |signature: Option<&str>| TranscriptEntry::Assistant {
+            content: vec![ContentBlock::Text {
+                text: "Answer".into(),
+            }],
+            usage: None,
+            thinking: Some("Let me think".into()),
+            thinking_signature: signature.map(String::from),
+            thinking_blocks: Vec::new(),
+            timestamp: Utc::now(),
+        };
+
+        let messages = provider.format_messages(&[assistant(Some("sig=="))]);
+        let content = messages[0]["content"].as_array().unwrap();
+        assert_eq!(content.len(), 2);
+        assert_eq!(content[0]["type"], "thinking");
+        assert_eq!(content[0]["thinking"], "Let me think");
+        assert_eq!(content[0]["signature"], "sig==");
+        assert_eq!(content[1]["text"], "Answer");
+
+        // Unsigned thinking cannot be replayed and is dropped.
+        let messages = provider.format_messages(&[assistant(None)]);
+        assert_eq!(messages[0]["content"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_format_messages_replays_each_thinking_block() {
+        use chrono::Utc;
+        use rusty_claw_core::session::ThinkingBlock;
+        let provider = AnthropicProvider::new(None);
+        let block = |thinking: &str, signature: &str| ThinkingBlock {
+            thinking: thinking.into(),
+            signature: Some(signature.into()),
+        };
+        let assistant = TranscriptEntry::Assistant {
+            content: vec![ContentBlock::Text {
+                text: "Answer".into(),
+            }],
+            usage: None,
+            thinking: Some("FirstSecond".into()),
+            thinking_signature: None,
+            thinking_blocks: vec![block("First", "sig1"), block("Second", "sig2")],
+            timestamp: Utc::now(),
+        };
+
+        let messages = provider.format_messages(&[assistant]);
+        let content = messages[0]["content"].as_array().unwrap();
+        assert_eq!(content.len(), 3);
+        assert_eq!(content[0]["thinking"], "First");
+        assert_eq!(content[0]["signature"], "sig1");
+        assert_eq!(content[1]["thinking"], "Second");
+        assert_eq!(content[1]["signature"], "sig2");
+        assert_eq!(content[2]["text"], "Answer");
+    }
+
     // --- 6c-1: Thinking Token Pass-through tests ---
 
     #[test]
@@ -664,8 +1214,12 @@ mod tests {
             messages: vec![],
             stream: true,
             temperature: None, // temperature is None when thinking is enabled
+            top_p: None,
+            top_k: None,
             tools: None,
             thinking,
+            stop_sequences: None,
+            tool_choice: None,
         };
 
         let serialized = serde_json::to_value(&body).unwrap();
@@ -688,8 +1242,14 @@ mod tests {
             messages: vec![],
             stream: true,
             temperature: Some(0.7),
+            top_p: Some(0.9),
+            top_k: Some(40),
             tools: None,
             thinking,
+            stop_sequences: Some(vec!["</answer>".into()]),
+            tool_choice: Some(anthropic_tool_choice(&ToolChoice::Tool {
+                name: "submit_answer".into(),
+            })),
         };
 
         let serialized = serde_json::to_value(&body).unwrap();
@@ -699,5 +1259,41 @@ mod tests {
         );
         // Temperature should be present when thinking is disabled
         assert_eq!(serialized["temperature"], 0.7);
+        assert_eq!(serialized["top_p"], 0.9);
+        assert_eq!(serialized["top_k"], 40);
+        assert_eq!(serialized["stop_sequences"][0], "</answer>");
+        assert_eq!(serialized["tool_choice"]["type"], "tool");
+        assert_eq!(serialized["tool_choice"]["name"], "submit_answer");
+    }
+
+    #[test]
+    fn test_apply_prompt_cache_marks_system_and_last_tool() {
+        let tools = vec![
+            serde_json::json!({"name": "a", "input_schema": {}}),
+            serde_json::json!({"name": "b", "input_schema": {}}),
+        ];
+        let (system, tools) = apply_prompt_cache(Some("You are helpful."), Some(tools));
+
+        let system = system.unwrap();
+        assert_eq!(system[0]["type"], "text");
+        assert_eq!(system[0]["text"], "You are helpful.");
+        assert_eq!(system[0]["cache_control"]["type"], "ephemeral");
+
+        let tools = tools.unwrap();
+        assert!(tools[0].get("cache_control").is_none());
+        assert_eq!(tools[1]["cache_control"]["type"], "ephemeral");
+    }
+
+    #[test]
+    fn test_message_usage_cache_fields() {
+        let json = r#"{"input_tokens":10,"output_tokens":1,"cache_creation_input_tokens":2000,"cache_read_input_tokens":0}"#;
+        let usage: MessageUsage = serde_json::from_str(json).unwrap();
+        let chunk = usage.to_chunk_usage();
+        assert_eq!(chunk.input_tokens, Some(10));
+        assert_eq!(chunk.cache_creation_input_tokens, Some(2000));
+        assert_eq!(chunk.cache_read_input_tokens, Some(0));
+
+        let usage: MessageUsage = serde_json::from_str(r#"{"output_tokens":5}"#).unwrap();
+        assert!(usage.to_chunk_usage().cache_read_input_tokens.is_none());
     }
 }
@@ -0,0 +1,1141 @@
+//! AWS Bedrock provider (Converse streaming API).
+//!
+//! Implements streaming chat completions via the Bedrock Runtime
+//! `ConverseStream` endpoint. Requests are signed with AWS Signature V4 and
+//! responses use the AWS event-stream binary framing rather than SSE.
+
+use std::collections::HashMap;
+use std::pin::Pin;
+
+use async_trait::async_trait;
+use futures::Stream;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tokio_stream::StreamExt;
+use tracing::{debug, trace};
+
+use rusty_claw_core::session::TranscriptEntry;
+use rusty_claw_core::types::ContentBlock;
+
+use crate::{
+    ChunkUsage, CompletionChunk, CompletionRequest, Credentials, LlmProvider, ModelApi, ModelInfo,
+    ProviderHttpError, ToolChoice, ToolDefinition, ToolUseChunk,
+};
+
+const DEFAULT_REGION: &str = "us-east-1";
+const SERVICE: &str = "bedrock";
+
+pub struct BedrockProvider {
+    /// Region used when the credentials don't specify one.
+    pub region: String,
+    /// Optional endpoint override (e.g. a VPC endpoint). Defaults to the
+    /// public `bedrock-runtime.<region>.amazonaws.com` endpoint.
+    pub base_url: Option<String>,
+    client: reqwest::Client,
+}
+
+impl BedrockProvider {
+    pub fn new(region: Option<&str>, base_url: Option<&str>) -> Self {
+        let region = region
+            .map(String::from)
+            .or_else(|| env_non_empty("AWS_REGION"))
+            .or_else(|| env_non_empty("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|| DEFAULT_REGION.into());
+        Self {
+            region,
+            base_url: base_url.map(|u| u.trim_end_matches('/').to_string()),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Use `client` for the signed `ConverseStream` calls, e.g. to reach
+    /// Bedrock through a proxy.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn endpoint(&self, region: &str) -> String {
+        self.base_url
+            .clone()
+            .unwrap_or_else(|| format!("https://bedrock-runtime.{region}.amazonaws.com"))
+    }
+}
+
+/// Resolve AWS credentials from the default credential chain.
+///
+/// Checks `AWS_ACCESS_KEY_ID` / `AWS_SECRET_ACCESS_KEY` (+ `AWS_SESSION_TOKEN`)
+/// first, then the shared credentials file (`~/.aws/credentials`, honoring
+/// `AWS_SHARED_CREDENTIALS_FILE` and `AWS_PROFILE`).
+pub fn resolve_default_credentials(region: Option<&str>) -> Option<Credentials> {
+    let region = region
+        .map(String::from)
+        .or_else(|| env_non_empty("AWS_REGION"))
+        .or_else(|| env_non_empty("AWS_DEFAULT_REGION"));
+
+    if let (Some(access_key_id), Some(secret_access_key)) = (
+        env_non_empty("AWS_ACCESS_KEY_ID"),
+        env_non_empty("AWS_SECRET_ACCESS_KEY"),
+    ) {
+        return Some(Credentials::Aws {
+            access_key_id,
+            secret_access_key,
+            session_token: env_non_empty("AWS_SESSION_TOKEN"),
+            region,
+        });
+    }
+
+    let path = env_non_empty("AWS_SHARED_CREDENTIALS_FILE")
+        .map(std::path::PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|h| h.join(".aws").join("credentials")))?;
+    let contents = std::fs::read_to_string(path).ok()?;
+    let profile = env_non_empty("AWS_PROFILE").unwrap_or_else(|| "default".into());
+    let mut creds = parse_credentials_file(&contents, &profile)?;
+    if let Credentials::Aws { region: r, .. } = &mut creds {
+        *r = region;
+    }
+    Some(creds)
+}
+
+fn env_non_empty(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+/// Parse a profile out of an INI-style AWS shared credentials file.
+fn parse_credentials_file(contents: &str, profile: &str) -> Option<Credentials> {
+    let mut in_profile = false;
+    let mut values: HashMap<String, String> = HashMap::new();
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_profile = name.trim() == profile;
+            continue;
+        }
+        if in_profile {
+            if let Some((k, v)) = line.split_once('=') {
+                values.insert(k.trim().to_string(), v.trim().to_string());
+            }
+        }
+    }
+
+    Some(Credentials::Aws {
+        access_key_id: values.remove("aws_access_key_id")?,
+        secret_access_key: values.remove("aws_secret_access_key")?,
+        session_token: values.remove("aws_session_token"),
+        region: None,
+    })
+}
+
+// --- SigV4 signing ---
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// URI-encode a string per the SigV4 rules (RFC 3986 unreserved set).
+fn uri_encode(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for b in input.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{b:02X}")),
+        }
+    }
+    out
+}
+
+/// Inputs for a single SigV4 signature.
+struct SigningParams<'a> {
+    access_key_id: &'a str,
+    secret_access_key: &'a str,
+    session_token: Option<&'a str>,
+    region: &'a str,
+    service: &'a str,
+    /// Timestamp in `YYYYMMDD'T'HHMMSS'Z'` format.
+    amz_date: &'a str,
+}
+
+/// Compute SigV4 headers for a request.
+///
+/// `canonical_uri` must already be encoded the way it should appear in the
+/// canonical request. Returns the headers to add (`x-amz-date`,
+/// `authorization`, and `x-amz-security-token` when a session token is set).
+fn sign_request(
+    params: &SigningParams<'_>,
+    method: &str,
+    host: &str,
+    canonical_uri: &str,
+    payload: &[u8],
+) -> Vec<(&'static str, String)> {
+    let date = &params.amz_date[..8];
+
+    let mut headers: Vec<(&str, String)> = vec![
+        ("host", host.to_string()),
+        ("x-amz-date", params.amz_date.to_string()),
+    ];
+    if let Some(token) = params.session_token {
+        headers.push(("x-amz-security-token", token.to_string()));
+    }
+    headers.sort_by(|a, b| a.0.cmp(b.0));
+
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(k, v)| format!("{k}:{}\n", v.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(k, _)| *k)
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{method}\n{canonical_uri}\n\n{canonical_headers}\n{signed_headers}\n{}",
+        sha256_hex(payload)
+    );
+
+    let scope = format!("{date}/{}/{}/aws4_request", params.region, params.service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{scope}\n{}",
+        params.amz_date,
+        sha256_hex(canonical_request.as_bytes())
+    );
+
+    let k_date = hmac_sha256(
+        format!("AWS4{}", params.secret_access_key).as_bytes(),
+        date.as_bytes(),
+    );
+    let k_region = hmac_sha256(&k_date, params.region.as_bytes());
+    let k_service = hmac_sha256(&k_region, params.service.as_bytes());
+    let k_signing = hmac_sha256(&k_service, b"aws4_request");
+    let signature = hex::encode(hmac_sha256(&k_signing, string_to_sign.as_bytes()));
+
+    let mut out = vec![
+        ("x-amz-date", params.amz_date.to_string()),
+        (
+            "authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+                params.access_key_id
+            ),
+        ),
+    ];
+    if let Some(token) = params.session_token {
+        out.push(("x-amz-security-token", token.to_string()));
+    }
+    out
+}
+
+// --- Event-stream framing ---
+
+/// A decoded `application/vnd.amazon.eventstream` message.
+#[derive(Debug, Clone)]
+struct EventMessage {
+    headers: HashMap<String, String>,
+    payload: Vec<u8>,
+}
+
+impl EventMessage {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|s| s.as_str())
+    }
+}
+
+/// Incremental decoder for the AWS event-stream binary framing.
+///
+/// Each message is: total length (u32), headers length (u32), prelude CRC
+/// (u32), headers, payload, message CRC (u32). All integers are big-endian.
+#[derive(Default)]
+struct EventStreamDecoder {
+    buffer: Vec<u8>,
+}
+
+impl EventStreamDecoder {
+    fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Pop the next complete message, if one is buffered.
+    fn next_message(&mut self) -> anyhow::Result<Option<EventMessage>> {
+        if self.buffer.len() < 12 {
+            return Ok(None);
+        }
+        let total_len = read_u32(&self.buffer[0..4]) as usize;
+        let headers_len = read_u32(&self.buffer[4..8]) as usize;
+        let prelude_crc = read_u32(&self.buffer[8..12]);
+
+        if total_len < 16 || headers_len > total_len - 16 {
+            anyhow::bail!("Invalid event-stream prelude (total {total_len}, headers {headers_len})");
+        }
+        if crc32fast::hash(&self.buffer[0..8]) != prelude_crc {
+            anyhow::bail!("Event-stream prelude CRC mismatch");
+        }
+        if self.buffer.len() < total_len {
+            return Ok(None);
+        }
+
+        let frame: Vec<u8> = self.buffer.drain(..total_len).collect();
+        let message_crc = read_u32(&frame[total_len - 4..]);
+        if crc32fast::hash(&frame[..total_len - 4]) != message_crc {
+            anyhow::bail!("Event-stream message CRC mismatch");
+        }
+
+        let headers = parse_headers(&frame[12..12 + headers_len])?;
+        let payload = frame[12 + headers_len..total_len - 4].to_vec();
+        Ok(Some(EventMessage { headers, payload }))
+    }
+}
+
+fn read_u32(b: &[u8]) -> u32 {
+    u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+}
+
+/// Parse event-stream headers. Only string values are kept; other value
+/// types are skipped since Bedrock only uses string headers.
+fn parse_headers(mut buf: &[u8]) -> anyhow::Result<HashMap<String, String>> {
+    let mut headers = HashMap::new();
+
+    fn take<'a>(buf: &mut &'a [u8], n: usize) -> anyhow::Result<&'a [u8]> {
+        if buf.len() < n {
+            anyhow::bail!("Truncated event-stream header");
+        }
+        let (head, tail) = buf.split_at(n);
+        *buf = tail;
+        Ok(head)
+    }
+
+    while !buf.is_empty() {
+        let name_len = take(&mut buf, 1)?[0] as usize;
+        let name = String::from_utf8_lossy(take(&mut buf, name_len)?).to_string();
+        let value_type = take(&mut buf, 1)?[0];
+        match value_type {
+            // bool true / bool false
+            0 | 1 => {}
+            // byte
+            2 => {
+                take(&mut buf, 1)?;
+            }
+            // short
+            3 => {
+                take(&mut buf, 2)?;
+            }
+            // int
+            4 => {
+                take(&mut buf, 4)?;
+            }
+            // long, timestamp
+            5 | 8 => {
+                take(&mut buf, 8)?;
+            }
+            // byte array, string
+            6 | 7 => {
+                let len_bytes = take(&mut buf, 2)?;
+                let len = u16::from_be_bytes([len_bytes[0], len_bytes[1]]) as usize;
+                let value = take(&mut buf, len)?;
+                if value_type == 7 {
+                    headers.insert(name, String::from_utf8_lossy(value).to_string());
+                }
+            }
+            // uuid
+            9 => {
+                take(&mut buf, 16)?;
+            }
+            other => anyhow::bail!("Unknown event-stream header type {other}"),
+        }
+    }
+
+    Ok(headers)
+}
+
+// --- Converse stream events ---
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContentBlockStartEvent {
+    content_block_index: usize,
+    start: BlockStart,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockStart {
+    #[serde(default)]
+    tool_use: Option<ToolUseStart>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolUseStart {
+    tool_use_id: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContentBlockDeltaEvent {
+    content_block_index: usize,
+    delta: BlockDelta,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockDelta {
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    tool_use: Option<ToolUseDelta>,
+    #[serde(default)]
+    reasoning_content: Option<ReasoningDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ToolUseDelta {
+    #[serde(default)]
+    input: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReasoningDelta {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContentBlockStopEvent {
+    content_block_index: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MessageStopEvent {
+    stop_reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetadataEvent {
+    #[serde(default)]
+    usage: Option<ConverseUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConverseUsage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+}
+
+/// A tool-use block being accumulated across deltas.
+#[derive(Debug, Clone)]
+struct PendingToolUse {
+    id: String,
+    name: String,
+    input_json: String,
+}
+
+/// Translate a single Converse stream event into a chunk.
+///
+/// Returns `Ok(None)` for events that only update internal state.
+fn process_event(
+    event_type: &str,
+    payload: &[u8],
+    tools: &mut HashMap<usize, PendingToolUse>,
+) -> anyhow::Result<Option<CompletionChunk>> {
+    match event_type {
+        "contentBlockStart" => {
+            let ev: ContentBlockStartEvent = serde_json::from_slice(payload)?;
+            if let Some(tu) = ev.start.tool_use {
+                tools.insert(
+                    ev.content_block_index,
+                    PendingToolUse {
+                        id: tu.tool_use_id,
+                        name: tu.name,
+                        input_json: String::new(),
+                    },
+                );
+            }
+            Ok(None)
+        }
+        "contentBlockDelta" => {
+            let ev: ContentBlockDeltaEvent = serde_json::from_slice(payload)?;
+            if let Some(text) = ev.delta.text {
+                return Ok(Some(CompletionChunk {
+                    delta: Some(text),
+                    ..Default::default()
+                }));
+            }
+            if let Some(tu) = ev.delta.tool_use {
+                if let Some(pending) = tools.get_mut(&ev.content_block_index) {
+                    pending.input_json.push_str(&tu.input);
+                }
+                return Ok(None);
+            }
+            if let Some(text) = ev.delta.reasoning_content.and_then(|r| r.text) {
+                return Ok(Some(CompletionChunk {
+                    thinking: Some(text),
+                    ..Default::default()
+                }));
+            }
+            Ok(None)
+        }
+        "contentBlockStop" => {
+            let ev: ContentBlockStopEvent = serde_json::from_slice(payload)?;
+            Ok(tools.remove(&ev.content_block_index).map(|t| CompletionChunk {
+                tool_use: Some(ToolUseChunk {
+                    id: t.id,
+                    name: t.name,
+                    input_json: if t.input_json.is_empty() {
+                        "{}".into()
+                    } else {
+                        t.input_json
+                    },
+                }),
+                ..Default::default()
+            }))
+        }
+        "messageStop" => {
+            let ev: MessageStopEvent = serde_json::from_slice(payload)?;
+            Ok(Some(CompletionChunk {
+                stop_reason: Some(ev.stop_reason),
+                ..Default::default()
+            }))
+        }
+        "metadata" => {
+            let ev: MetadataEvent = serde_json::from_slice(payload)?;
+            Ok(ev.usage.map(|u| CompletionChunk {
+                usage: Some(ChunkUsage {
+                    input_tokens: Some(u.input_tokens),
+                    output_tokens: Some(u.output_tokens),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }))
+        }
+        "messageStart" => Ok(None),
+        other => {
+            trace!(event = other, "Unhandled Bedrock stream event");
+            Ok(None)
+        }
+    }
+}
+
+/// Convert a content block into the Converse API shape.
+fn converse_content_block(block: &ContentBlock) -> Option<serde_json::Value> {
+    match block {
+        ContentBlock::Text { text } => Some(json!({ "text": text })),
+        ContentBlock::Image { source } if source.source_type == "base64" => {
+            let format = source
+                .media_type
+                .strip_prefix("image/")
+                .unwrap_or("png")
+                .replace("jpg", "jpeg");
+            Some(json!({
+                "image": {
+                    "format": format,
+                    "source": { "bytes": source.data },
+                }
+            }))
+        }
+        ContentBlock::Image { .. } => None,
+        ContentBlock::ToolUse { id, name, input } => Some(json!({
+            "toolUse": {
+                "toolUseId": id,
+                "name": name,
+                "input": input,
+            }
+        })),
+        ContentBlock::ToolResult {
+            tool_use_id,
+            content,
+            is_error,
+        } => Some(json!({
+            "toolResult": {
+                "toolUseId": tool_use_id,
+                "content": [{ "text": content }],
+                "status": if *is_error { "error" } else { "success" },
+            }
+        })),
+    }
+}
+
+/// Append content to the conversation, merging into the previous message
+/// when the role repeats (Converse requires strictly alternating roles).
+fn push_message(messages: &mut Vec<serde_json::Value>, role: &str, content: Vec<serde_json::Value>) {
+    if content.is_empty() {
+        return;
+    }
+    if let Some(last) = messages.last_mut() {
+        if last["role"] == role {
+            if let Some(existing) = last["content"].as_array_mut() {
+                existing.extend(content);
+                return;
+            }
+        }
+    }
+    messages.push(json!({ "role": role, "content": content }));
+}
+
+#[async_trait]
+impl LlmProvider for BedrockProvider {
+    fn id(&self) -> &str {
+        "bedrock"
+    }
+
+    fn api(&self) -> ModelApi {
+        ModelApi::BedrockConverseStream
+    }
+
+    fn format_tools(&self, tools: &[ToolDefinition]) -> Vec<serde_json::Value> {
+        tools
+            .iter()
+            .map(|t| {
+                json!({
+                    "toolSpec": {
+                        "name": t.name,
+                        "description": t.description,
+                        "inputSchema": { "json": t.parameters_schema },
+                    }
+                })
+            })
+            .collect()
+    }
+
+    fn format_messages(&self, transcript: &[TranscriptEntry]) -> Vec<serde_json::Value> {
+        let mut messages: Vec<serde_json::Value> = Vec::new();
+
+        for entry in transcript {
+            match entry {
+                TranscriptEntry::User { content, .. } => {
+                    let blocks = content.iter().filter_map(converse_content_block).collect();
+                    push_message(&mut messages, "user", blocks);
+                }
+                TranscriptEntry::Assistant { content, .. } => {
+                    let blocks = content.iter().filter_map(converse_content_block).collect();
+                    push_message(&mut messages, "assistant", blocks);
+                }
+                TranscriptEntry::ToolResult {
+                    tool_use_id,
+                    content,
+                    is_error,
+                    ..
+                } => {
+                    let block = converse_content_block(&ContentBlock::ToolResult {
+                        tool_use_id: tool_use_id.clone(),
+                        content: content.clone(),
+                        is_error: *is_error,
+                    });
+                    push_message(&mut messages, "user", block.into_iter().collect());
+                }
+                TranscriptEntry::ToolCall { .. } | TranscriptEntry::System { .. } => {}
+            }
+        }
+
+        messages
+    }
+
+    fn is_tool_use_stop(&self, stop_reason: &str) -> bool {
+        stop_reason == "tool_use"
+    }
+
+    async fn stream(
+        &self,
+        request: &CompletionRequest,
+        credentials: &Credentials,
+    ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<CompletionChunk>> + Send>>> {
+        let (access_key_id, secret_access_key, session_token, cred_region) = match credentials {
+            Credentials::Aws {
+                access_key_id,
+                secret_access_key,
+                session_token,
+                region,
+            } => (access_key_id, secret_access_key, session_token, region),
+            _ => anyhow::bail!("Bedrock requires Aws credentials"),
+        };
+        let region = cred_region.as_deref().unwrap_or(&self.region);
+
+        let mut inference_config = json!({ "maxTokens": request.max_tokens });
+        if let Some(t) = request.temperature {
+            if request.thinking_budget_tokens.is_none() {
+                inference_config["temperature"] = json!(t);
+            }
+        }
+        if let Some(top_p) = request.top_p {
+            if request.thinking_budget_tokens.is_none() {
+                inference_config["topP"] = json!(top_p);
+            }
+        }
+        if let Some(stop) = &request.stop_sequences {
+            inference_config["stopSequences"] = json!(stop);
+        }
+
+        let mut body = json!({
+            "messages": request.messages,
+            "inferenceConfig": inference_config,
+        });
+        if let Some(system) = &request.system {
+            body["system"] = json!([{ "text": system }]);
+        }
+        if let Some(tools) = request.tools.as_ref().filter(|t| !t.is_empty()) {
+            body["toolConfig"] = json!({ "tools": tools });
+            // Converse has no "none" mode; tools must stay declared whenever
+            // the transcript contains tool use, so None falls back to auto.
+            let choice = match &request.tool_choice {
+                Some(ToolChoice::Required) => Some(json!({ "any": {} })),
+                Some(ToolChoice::Tool { name }) => Some(json!({ "tool": { "name": name } })),
+                _ => None,
+            };
+            if let Some(choice) = choice {
+                body["toolConfig"]["toolChoice"] = choice;
+            }
+        }
+        if let Some(budget) = request.thinking_budget_tokens {
+            body["additionalModelRequestFields"] = json!({
+                "thinking": { "type": "enabled", "budget_tokens": budget }
+            });
+        }
+        let payload = serde_json::to_vec(&body)?;
+
+        let endpoint = self.endpoint(region);
+        let url = reqwest::Url::parse(&endpoint)?;
+        let host = match url.port() {
+            Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let base_path = url.path().trim_end_matches('/');
+
+        // The request path carries the model ID encoded once; SigV4 for
+        // non-S3 services encodes each path segment a second time.
+        let model_segment = uri_encode(&request.model);
+        let request_path = format!("{base_path}/model/{model_segment}/converse-stream");
+        let canonical_uri = request_path
+            .split('/')
+            .map(uri_encode)
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let signed = sign_request(
+            &SigningParams {
+                access_key_id,
+                secret_access_key,
+                session_token: session_token.as_deref(),
+                region,
+                service: SERVICE,
+                amz_date: &amz_date,
+            },
+            "POST",
+            &host,
+            &canonical_uri,
+            &payload,
+        );
+
+        debug!(model = %request.model, region, "Streaming Bedrock ConverseStream API");
+
+        let mut req = self
+            .client
+            .post(format!(
+                "{}://{host}{request_path}",
+                url.scheme()
+            ))
+            .header("content-type", "application/json")
+            .header("accept", "application/vnd.amazon.eventstream");
+        for (name, value) in signed {
+            req = req.header(name, value);
+        }
+        let response = req.body(payload).send().await?;
+
+        if !response.status().is_success() {
+            return Err(ProviderHttpError::from_response("Bedrock", response).await.into());
+        }
+
+        let chunk_stream = futures::stream::unfold(
+            BedrockChunkState {
+                bytes: Box::pin(response.bytes_stream()),
+                decoder: EventStreamDecoder::default(),
+                tools: HashMap::new(),
+                done: false,
+            },
+            |mut state| async move {
+                if state.done {
+                    return None;
+                }
+                loop {
+                    match state.decoder.next_message() {
+                        Ok(Some(msg)) => {
+                            let message_type = msg.header(":message-type").unwrap_or("event");
+                            if message_type != "event" {
+                                let kind = msg
+                                    .header(":exception-type")
+                                    .or(msg.header(":error-code"))
+                                    .unwrap_or("unknown")
+                                    .to_string();
+                                let detail = String::from_utf8_lossy(&msg.payload).to_string();
+                                state.done = true;
+                                return Some((
+                                    Err(anyhow::anyhow!("Bedrock stream error {kind}: {detail}")),
+                                    state,
+                                ));
+                            }
+                            let event_type = msg.header(":event-type").unwrap_or("").to_string();
+                            match process_event(&event_type, &msg.payload, &mut state.tools) {
+                                Ok(Some(chunk)) => return Some((Ok(chunk), state)),
+                                Ok(None) => continue,
+                                Err(e) => {
+                                    trace!(%e, event = %event_type, "Failed to parse Bedrock event");
+                                    continue;
+                                }
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            state.done = true;
+                            return Some((Err(e), state));
+                        }
+                    }
+
+                    match state.bytes.next().await {
+                        Some(Ok(bytes)) => state.decoder.push(&bytes),
+                        Some(Err(e)) => {
+                            state.done = true;
+                            return Some((
+                                Err(anyhow::anyhow!("Bedrock stream error: {e}")),
+                                state,
+                            ));
+                        }
+                        None => return None,
+                    }
+                }
+            },
+        );
+
+        Ok(Box::pin(chunk_stream))
+    }
+
+    async fn list_models(&self, credentials: &Credentials) -> anyhow::Result<Vec<ModelInfo>> {
+        if !matches!(credentials, Credentials::Aws { .. }) {
+            anyhow::bail!("Bedrock requires Aws credentials");
+        }
+
+        // Model availability is per-account and per-region; return the
+        // well-known Anthropic model IDs served through Bedrock.
+        Ok(vec![
+            ModelInfo {
+                id: "anthropic.claude-3-5-sonnet-20241022-v2:0".into(),
+                name: "Claude 3.5 Sonnet v2 (Bedrock)".into(),
+                api: ModelApi::BedrockConverseStream,
+                reasoning: false,
+                context_window: 200_000,
+                max_tokens: 8_192,
+            },
+            ModelInfo {
+                id: "anthropic.claude-3-5-haiku-20241022-v1:0".into(),
+                name: "Claude 3.5 Haiku (Bedrock)".into(),
+                api: ModelApi::BedrockConverseStream,
+                reasoning: false,
+                context_window: 200_000,
+                max_tokens: 8_192,
+            },
+            ModelInfo {
+                id: "anthropic.claude-3-7-sonnet-20250219-v1:0".into(),
+                name: "Claude 3.7 Sonnet (Bedrock)".into(),
+                api: ModelApi::BedrockConverseStream,
+                reasoning: true,
+                context_window: 200_000,
+                max_tokens: 64_000,
+            },
+        ])
+    }
+}
+
+struct BedrockChunkState {
+    bytes: Pin<Box<dyn Stream<Item = Result<bytes::Bytes, reqwest::Error>> + Send>>,
+    decoder: EventStreamDecoder,
+    tools: HashMap<usize, PendingToolUse>,
+    done: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build an event-stream frame with string headers.
+    fn encode_message(headers: &[(&str, &str)], payload: &[u8]) -> Vec<u8> {
+        let mut hdr = Vec::new();
+        for (name, value) in headers {
+            hdr.push(name.len() as u8);
+            hdr.extend_from_slice(name.as_bytes());
+            hdr.push(7);
+            hdr.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            hdr.extend_from_slice(value.as_bytes());
+        }
+        let total = 12 + hdr.len() + payload.len() + 4;
+        let mut out = Vec::new();
+        out.extend_from_slice(&(total as u32).to_be_bytes());
+        out.extend_from_slice(&(hdr.len() as u32).to_be_bytes());
+        let prelude_crc = crc32fast::hash(&out);
+        out.extend_from_slice(&prelude_crc.to_be_bytes());
+        out.extend_from_slice(&hdr);
+        out.extend_from_slice(payload);
+        let crc = crc32fast::hash(&out);
+        out.extend_from_slice(&crc.to_be_bytes());
+        out
+    }
+
+    fn event(event_type: &str, payload: &str) -> Vec<u8> {
+        encode_message(
+            &[(":message-type", "event"), (":event-type", event_type)],
+            payload.as_bytes(),
+        )
+    }
+
+    #[test]
+    fn test_bedrock_provider_creation() {
+        let provider = BedrockProvider::new(Some("eu-west-1"), None);
+        assert_eq!(provider.id(), "bedrock");
+        assert_eq!(provider.api(), ModelApi::BedrockConverseStream);
+        assert_eq!(
+            provider.endpoint("eu-west-1"),
+            "https://bedrock-runtime.eu-west-1.amazonaws.com"
+        );
+    }
+
+    #[test]
+    fn test_sigv4_get_vanilla() {
+        // From the AWS SigV4 test suite ("get-vanilla").
+        let headers = sign_request(
+            &SigningParams {
+                access_key_id: "AKIDEXAMPLE",
+                secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                session_token: None,
+                region: "us-east-1",
+                service: "service",
+                amz_date: "20150830T123600Z",
+            },
+            "GET",
+            "example.amazonaws.com",
+            "/",
+            b"",
+        );
+        let auth = &headers.iter().find(|(k, _)| *k == "authorization").unwrap().1;
+        assert_eq!(
+            auth,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn test_sigv4_session_token_is_signed() {
+        let headers = sign_request(
+            &SigningParams {
+                access_key_id: "AKID",
+                secret_access_key: "secret",
+                session_token: Some("tok"),
+                region: "us-west-2",
+                service: SERVICE,
+                amz_date: "20240101T000000Z",
+            },
+            "POST",
+            "bedrock-runtime.us-west-2.amazonaws.com",
+            "/model/x/converse-stream",
+            b"{}",
+        );
+        let auth = &headers.iter().find(|(k, _)| *k == "authorization").unwrap().1;
+        assert!(auth.contains("SignedHeaders=host;x-amz-date;x-amz-security-token"));
+        assert!(headers.iter().any(|(k, v)| *k == "x-amz-security-token" && v == "tok"));
+    }
+
+    #[test]
+    fn test_uri_encode_model_id() {
+        assert_eq!(
+            uri_encode("anthropic.claude-3-5-sonnet-20241022-v2:0"),
+            "anthropic.claude-3-5-sonnet-20241022-v2%3A0"
+        );
+        assert_eq!(uri_encode("%3A"), "%253A");
+    }
+
+    #[test]
+    fn test_event_stream_decoder_split_frames() {
+        let mut bytes = event("contentBlockDelta", r#"{"contentBlockIndex":0,"delta":{"text":"Hi"}}"#);
+        bytes.extend(event("messageStop", r#"{"stopReason":"end_turn"}"#));
+
+        let mut decoder = EventStreamDecoder::default();
+        // Feed the first frame in two halves
+        let (a, b) = bytes.split_at(10);
+        decoder.push(a);
+        assert!(decoder.next_message().unwrap().is_none());
+        decoder.push(b);
+
+        let first = decoder.next_message().unwrap().unwrap();
+        assert_eq!(first.header(":event-type"), Some("contentBlockDelta"));
+        let second = decoder.next_message().unwrap().unwrap();
+        assert_eq!(second.header(":event-type"), Some("messageStop"));
+        assert!(decoder.next_message().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_event_stream_decoder_rejects_bad_crc() {
+        let mut bytes = event("messageStart", r#"{"role":"assistant"}"#);
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        let mut decoder = EventStreamDecoder::default();
+        decoder.push(&bytes);
+        assert!(decoder.next_message().is_err());
+    }
+
+    #[test]
+    fn test_process_events_text_tool_and_usage() {
+        let mut tools = HashMap::new();
+
+        let chunk = process_event(
+            "contentBlockDelta",
+            br#"{"contentBlockIndex":0,"delta":{"text":"Hello"}}"#,
+            &mut tools,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(chunk.delta.as_deref(), Some("Hello"));
+
+        assert!(process_event(
+            "contentBlockStart",
+            br#"{"contentBlockIndex":1,"start":{"toolUse":{"toolUseId":"tu_1","name":"exec"}}}"#,
+            &mut tools,
+        )
+        .unwrap()
+        .is_none());
+        for part in [r#"{\"comm"#, r#"and\":\"ls\"}"#] {
+            let payload = format!(
+                r#"{{"contentBlockIndex":1,"delta":{{"toolUse":{{"input":"{part}"}}}}}}"#
+            );
+            assert!(process_event("contentBlockDelta", payload.as_bytes(), &mut tools)
+                .unwrap()
+                .is_none());
+        }
+        let chunk = process_event("contentBlockStop", br#"{"contentBlockIndex":1}"#, &mut tools)
+            .unwrap()
+            .unwrap();
+        let tu = chunk.tool_use.unwrap();
+        assert_eq!(tu.id, "tu_1");
+        assert_eq!(tu.name, "exec");
+        assert_eq!(tu.input_json, r#"{"command":"ls"}"#);
+
+        let chunk = process_event("messageStop", br#"{"stopReason":"tool_use"}"#, &mut tools)
+            .unwrap()
+            .unwrap();
+        assert_eq!(chunk.stop_reason.as_deref(), Some("tool_use"));
+
+        let chunk = process_event(
+            "metadata",
+            br#"{"usage":{"inputTokens":12,"outputTokens":34,"totalTokens":46},"metrics":{"latencyMs":100}}"#,
+            &mut tools,
+        )
+        .unwrap()
+        .unwrap();
+        let usage = chunk.usage.unwrap();
+        assert_eq!(usage.input_tokens, Some(12));
+        assert_eq!(usage.output_tokens, Some(34));
+    }
+
+    #[test]
+    fn test_format_tools_tool_spec() {
+        let provider = BedrockProvider::new(Some("us-east-1"), None);
+        let tools = vec![ToolDefinition {
+            name: "exec".into(),
+            description: "Run a command".into(),
+            parameters_schema: json!({"type": "object"}),
+        }];
+        let formatted = provider.format_tools(&tools);
+        assert_eq!(formatted[0]["toolSpec"]["name"], "exec");
+        assert_eq!(formatted[0]["toolSpec"]["inputSchema"]["json"]["type"], "object");
+    }
+
+    #[test]
+    fn test_format_messages_merges_tool_results() {
+        use chrono::Utc;
+        let provider = BedrockProvider::new(Some("us-east-1"), None);
+        let transcript = vec![
+            TranscriptEntry::User {
+                content: vec![ContentBlock::Text { text: "Run two".into() }],
+                timestamp: Utc::now(),
+            },
+            TranscriptEntry::Assistant {
+                content: vec![
+                    ContentBlock::ToolUse {
+                        id: "a".into(),
+                        name: "exec".into(),
+                        input: json!({}),
+                    },
+                    ContentBlock::ToolUse {
+                        id: "b".into(),
+                        name: "exec".into(),
+                        input: json!({}),
+                    },
+                ],
+                usage: None,
+                thinking: None,
+                thinking_signature: None,
+                thinking_blocks: Vec::new(),
+                timestamp: Utc::now(),
+            },
+            TranscriptEntry::ToolResult {
+                tool_use_id: "a".into(),
+                tool: "exec".into(),
+                content: "ok".into(),
+                is_error: false,
+                timestamp: Utc::now(),
+            },
+            TranscriptEntry::ToolResult {
+                tool_use_id: "b".into(),
+                tool: "exec".into(),
+                content: "fail".into(),
+                is_error: true,
+                timestamp: Utc::now(),
+            },
+        ];
+        let messages = provider.format_messages(&transcript);
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1]["content"][0]["toolUse"]["toolUseId"], "a");
+        let results = messages[2]["content"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["toolResult"]["status"], "success");
+        assert_eq!(results[1]["toolResult"]["status"], "error");
+    }
+
+    #[test]
+    fn test_parse_credentials_file_profile() {
+        let contents = "[default]\naws_access_key_id = AKID1\naws_secret_access_key = S1\n\n\
+                        [work]\naws_access_key_id=AKID2\naws_secret_access_key=S2\naws_session_token=T2\n";
+        match parse_credentials_file(contents, "work").unwrap() {
+            Credentials::Aws {
+                access_key_id,
+                secret_access_key,
+                session_token,
+                ..
+            } => {
+                assert_eq!(access_key_id, "AKID2");
+                assert_eq!(secret_access_key, "S2");
+                assert_eq!(session_token.as_deref(), Some("T2"));
+            }
+            other => panic!("unexpected credentials: {other:?}"),
+        }
+        assert!(parse_credentials_file(contents, "missing").is_none());
+    }
+}
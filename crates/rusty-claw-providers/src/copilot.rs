@@ -0,0 +1,354 @@
+//! GitHub Copilot provider.
+//!
+//! Exchanges a GitHub OAuth token for a short-lived Copilot chat token, caches
+//! it until shortly before expiry, and streams against the Copilot chat
+//! completions endpoint using the OpenAI-compatible request/SSE format.
+
+use std::pin::Pin;
+
+use async_trait::async_trait;
+use futures::Stream;
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tracing::debug;
+
+use rusty_claw_core::session::TranscriptEntry;
+
+use crate::openai::OpenAiProvider;
+use crate::{
+    CompletionChunk, CompletionRequest, Credentials, LlmProvider, ModelApi, ModelInfo,
+    ProviderHttpError, ToolDefinition,
+};
+
+pub(crate) const COPILOT_API_BASE_URL: &str = "https://api.githubcopilot.com";
+const TOKEN_EXCHANGE_URL: &str = "https://api.github.com/copilot_internal/v2/token";
+const EDITOR_VERSION: &str = "vscode/1.95.0";
+const EDITOR_PLUGIN_VERSION: &str = "copilot-chat/0.22.0";
+const INTEGRATION_ID: &str = "vscode-chat";
+
+/// Refresh the chat token this many seconds before it actually expires.
+const EXPIRY_MARGIN_SECS: i64 = 60;
+
+/// Headers the Copilot API requires on every request.
+pub(crate) fn editor_headers() -> [(&'static str, &'static str); 4] {
+    [
+        ("Copilot-Integration-Id", INTEGRATION_ID),
+        ("Editor-Version", EDITOR_VERSION),
+        ("Editor-Plugin-Version", EDITOR_PLUGIN_VERSION),
+        ("User-Agent", "GitHubCopilotChat/0.22.0"),
+    ]
+}
+
+/// A cached Copilot chat token.
+#[derive(Debug, Clone)]
+struct CopilotToken {
+    token: String,
+    /// Unix timestamp (seconds) at which the token expires.
+    expires_at: i64,
+    /// API base URL advertised by the token endpoint.
+    api_base: String,
+}
+
+impl CopilotToken {
+    fn is_fresh(&self, now: i64) -> bool {
+        now + EXPIRY_MARGIN_SECS < self.expires_at
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    token: String,
+    expires_at: i64,
+    #[serde(default)]
+    endpoints: Option<TokenEndpoints>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenEndpoints {
+    #[serde(default)]
+    api: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+pub struct CopilotProvider {
+    /// Token exchange endpoint (overridable for GitHub Enterprise).
+    pub token_url: String,
+    inner: OpenAiProvider,
+    token: Mutex<Option<CopilotToken>>,
+    client: reqwest::Client,
+}
+
+impl CopilotProvider {
+    /// Create a Copilot provider. `base_url` overrides the chat API base; by
+    /// default the endpoint advertised by the token exchange is used.
+    pub fn new(base_url: Option<&str>) -> Self {
+        Self {
+            token_url: TOKEN_EXCHANGE_URL.into(),
+            inner: OpenAiProvider::copilot(base_url),
+            token: Mutex::new(None),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Replace the HTTP client for both the GitHub token exchange and the
+    /// chat requests.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.inner = self.inner.with_client(client.clone());
+        self.client = client;
+        self
+    }
+
+    /// Return a valid chat token, exchanging the GitHub token if the cached
+    /// one is missing or about to expire.
+    async fn chat_token(&self, github_token: &str) -> anyhow::Result<CopilotToken> {
+        let mut cached = self.token.lock().await;
+        let now = chrono::Utc::now().timestamp();
+        if let Some(tok) = cached.as_ref().filter(|t| t.is_fresh(now)) {
+            return Ok(tok.clone());
+        }
+
+        debug!("Exchanging GitHub token for Copilot chat token");
+        let mut req = self
+            .client
+            .get(&self.token_url)
+            .header("authorization", format!("token {github_token}"))
+            .header("accept", "application/json");
+        for (name, value) in editor_headers() {
+            req = req.header(name, value);
+        }
+        let response = req.send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Copilot token exchange failed {status}: {body}");
+        }
+
+        let body: TokenResponse = response.json().await?;
+        let tok = CopilotToken {
+            token: body.token,
+            expires_at: body.expires_at,
+            api_base: body
+                .endpoints
+                .and_then(|e| e.api)
+                .unwrap_or_else(|| COPILOT_API_BASE_URL.into())
+                .trim_end_matches('/')
+                .to_string(),
+        };
+        *cached = Some(tok.clone());
+        Ok(tok)
+    }
+
+    async fn invalidate_token(&self) {
+        *self.token.lock().await = None;
+    }
+
+    /// Base URL to stream against: an explicit override wins over the
+    /// endpoint advertised with the token.
+    fn api_base<'a>(&'a self, token: &'a CopilotToken) -> &'a str {
+        if self.inner.base_url != COPILOT_API_BASE_URL {
+            &self.inner.base_url
+        } else {
+            &token.api_base
+        }
+    }
+}
+
+/// Extract the GitHub token from any credential shape.
+fn github_token(credentials: &Credentials) -> anyhow::Result<&str> {
+    let token = match credentials {
+        Credentials::OAuth { access_token, .. } => access_token,
+        Credentials::Token { token } => token,
+        Credentials::ApiKey { api_key } => api_key,
+        _ => anyhow::bail!("Copilot requires a GitHub OAuth token"),
+    };
+    if token.is_empty() {
+        anyhow::bail!("Copilot requires a GitHub OAuth token");
+    }
+    Ok(token)
+}
+
+#[async_trait]
+impl LlmProvider for CopilotProvider {
+    fn id(&self) -> &str {
+        "copilot"
+    }
+
+    fn api(&self) -> ModelApi {
+        ModelApi::GithubCopilot
+    }
+
+    fn format_tools(&self, tools: &[ToolDefinition]) -> Vec<serde_json::Value> {
+        self.inner.format_tools(tools)
+    }
+
+    fn format_messages(&self, transcript: &[TranscriptEntry]) -> Vec<serde_json::Value> {
+        self.inner.format_messages(transcript)
+    }
+
+    fn is_tool_use_stop(&self, stop_reason: &str) -> bool {
+        self.inner.is_tool_use_stop(stop_reason)
+    }
+
+    async fn stream(
+        &self,
+        request: &CompletionRequest,
+        credentials: &Credentials,
+    ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<CompletionChunk>> + Send>>> {
+        let github_token = github_token(credentials)?;
+
+        let tok = self.chat_token(github_token).await?;
+        match self
+            .inner
+            .stream_to(self.api_base(&tok), request, &tok.token)
+            .await
+        {
+            Ok(stream) => Ok(stream),
+            // The chat token can be revoked before its advertised expiry;
+            // exchange a fresh one and retry once.
+            Err(e) if is_unauthorized(&e) => {
+                debug!("Copilot chat token rejected, refreshing");
+                self.invalidate_token().await;
+                let tok = self.chat_token(github_token).await?;
+                self.inner
+                    .stream_to(self.api_base(&tok), request, &tok.token)
+                    .await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn list_models(&self, credentials: &Credentials) -> anyhow::Result<Vec<ModelInfo>> {
+        let tok = self.chat_token(github_token(credentials)?).await?;
+
+        let mut req = self
+            .client
+            .get(format!("{}/models", self.api_base(&tok)))
+            .header("authorization", format!("Bearer {}", tok.token));
+        for (name, value) in editor_headers() {
+            req = req.header(name, value);
+        }
+
+        let response = req.send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Failed to list models {status}: {body}");
+        }
+
+        let body: ModelsResponse = response.json().await?;
+        Ok(body
+            .data
+            .into_iter()
+            .map(|m| ModelInfo {
+                name: m.name.unwrap_or_else(|| m.id.clone()),
+                id: m.id,
+                api: ModelApi::GithubCopilot,
+                reasoning: false,
+                context_window: 128_000,
+                max_tokens: 4_096,
+            })
+            .collect())
+    }
+}
+
+/// Whether `err` is an HTTP 401 from the API.
+fn is_unauthorized(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<ProviderHttpError>()
+        .is_some_and(|e| e.status == reqwest::StatusCode::UNAUTHORIZED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copilot_provider_creation() {
+        let provider = CopilotProvider::new(None);
+        assert_eq!(provider.id(), "copilot");
+        assert_eq!(provider.api(), ModelApi::GithubCopilot);
+        assert_eq!(provider.token_url, TOKEN_EXCHANGE_URL);
+    }
+
+    #[test]
+    fn test_is_unauthorized() {
+        let http = |status: u16, body: &str| {
+            anyhow::Error::from(ProviderHttpError {
+                provider: "OpenAI".into(),
+                status: reqwest::StatusCode::from_u16(status).unwrap(),
+                retry_after: None,
+                body: body.into(),
+            })
+        };
+        assert!(is_unauthorized(&http(401, "")));
+        // A 401 mentioned in some other error is not a rejected token
+        assert!(!is_unauthorized(&http(400, "max_tokens must be below 4010")));
+        assert!(!is_unauthorized(&anyhow::anyhow!("HTTP 401")));
+    }
+
+    #[test]
+    fn test_token_freshness_margin() {
+        let tok = CopilotToken {
+            token: "t".into(),
+            expires_at: 1_000,
+            api_base: COPILOT_API_BASE_URL.into(),
+        };
+        assert!(tok.is_fresh(900));
+        assert!(!tok.is_fresh(1_000 - EXPIRY_MARGIN_SECS));
+        assert!(!tok.is_fresh(2_000));
+    }
+
+    #[test]
+    fn test_token_response_deserialization() {
+        let json = r#"{"token":"tid=abc;exp=1","expires_at":1700000000,"refresh_in":1500,"endpoints":{"api":"https://api.individual.githubcopilot.com"}}"#;
+        let resp: TokenResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(resp.expires_at, 1_700_000_000);
+        assert_eq!(
+            resp.endpoints.unwrap().api.as_deref(),
+            Some("https://api.individual.githubcopilot.com")
+        );
+    }
+
+    #[test]
+    fn test_api_base_prefers_override() {
+        let tok = CopilotToken {
+            token: "t".into(),
+            expires_at: 0,
+            api_base: "https://api.individual.githubcopilot.com".into(),
+        };
+        let provider = CopilotProvider::new(None);
+        assert_eq!(provider.api_base(&tok), "https://api.individual.githubcopilot.com");
+
+        let provider = CopilotProvider::new(Some("https://copilot.example.com/"));
+        assert_eq!(provider.api_base(&tok), "https://copilot.example.com");
+    }
+
+    #[test]
+    fn test_github_token_from_credentials() {
+        let creds = Credentials::OAuth {
+            access_token: "gho_abc".into(),
+            refresh_token: None,
+        };
+        assert_eq!(github_token(&creds).unwrap(), "gho_abc");
+        let empty = Credentials::Token { token: String::new() };
+        assert!(github_token(&empty).is_err());
+    }
+
+    #[test]
+    fn test_editor_headers_include_integration_id() {
+        let headers = editor_headers();
+        assert!(headers.iter().any(|(k, v)| *k == "Copilot-Integration-Id" && *v == "vscode-chat"));
+        assert!(headers.iter().any(|(k, _)| *k == "Editor-Version"));
+    }
+}
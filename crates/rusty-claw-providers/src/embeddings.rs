@@ -0,0 +1,256 @@
+//! Text embedding providers.
+//!
+//! Used for semantic memory search. Each provider turns a batch of texts into
+//! vectors; [`cosine_similarity`] ranks them against a query vector.
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+
+use rusty_claw_core::config::{EmbeddingsConfig, resolve_secret_field};
+
+use crate::ProviderHttpError;
+
+const OPENAI_BASE_URL: &str = "https://api.openai.com";
+const OLLAMA_BASE_URL: &str = "http://localhost:11434";
+const OPENAI_DEFAULT_MODEL: &str = "text-embedding-3-small";
+const OLLAMA_DEFAULT_MODEL: &str = "nomic-embed-text";
+
+/// A model that maps text to fixed-length vectors.
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Provider identifier (e.g. "openai", "ollama").
+    fn id(&self) -> &str;
+
+    /// Embedding model in use.
+    fn model(&self) -> &str;
+
+    /// Embed a batch of texts, returning one vector per input in order.
+    async fn embed(&self, texts: &[String]) -> anyhow::Result<Vec<Vec<f32>>>;
+}
+
+/// Build the embedding provider described by `config`.
+pub fn from_config(config: &EmbeddingsConfig) -> anyhow::Result<Box<dyn EmbeddingProvider>> {
+    let base_url = config.base_url.as_deref();
+    let model = config.model.as_deref();
+    match config.provider.as_str() {
+        "openai" => {
+            let api_key_env = config
+                .api_key_env
+                .clone()
+                .or_else(|| Some("OPENAI_API_KEY".into()));
+            let api_key = resolve_secret_field(&config.api_key, &api_key_env).ok_or_else(|| {
+                anyhow::anyhow!("OpenAI embeddings require an API key (api_key or api_key_env)")
+            })?;
+            Ok(Box::new(OpenAiEmbeddings::new(api_key, model, base_url)))
+        }
+        "ollama" => Ok(Box::new(OllamaEmbeddings::new(model, base_url))),
+        other => anyhow::bail!("Unknown embeddings provider '{other}' (expected openai or ollama)"),
+    }
+}
+
+/// Cosine similarity of two vectors; 0.0 when lengths differ or either is zero.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+// --- OpenAI ---
+
+/// OpenAI `/v1/embeddings` (also works with OpenAI-compatible servers).
+pub struct OpenAiEmbeddings {
+    api_key: String,
+    model: String,
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl OpenAiEmbeddings {
+    pub fn new(api_key: String, model: Option<&str>, base_url: Option<&str>) -> Self {
+        Self {
+            api_key,
+            model: model.unwrap_or(OPENAI_DEFAULT_MODEL).to_string(),
+            base_url: base_url
+                .unwrap_or(OPENAI_BASE_URL)
+                .trim_end_matches('/')
+                .to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct OpenAiEmbeddingResponse {
+    data: Vec<OpenAiEmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiEmbeddingData {
+    #[serde(default)]
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+fn parse_openai_response(body: OpenAiEmbeddingResponse, expected: usize) -> anyhow::Result<Vec<Vec<f32>>> {
+    let mut data = body.data;
+    data.sort_by_key(|d| d.index);
+    if data.len() != expected {
+        anyhow::bail!("OpenAI returned {} embeddings for {expected} inputs", data.len());
+    }
+    Ok(data.into_iter().map(|d| d.embedding).collect())
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiEmbeddings {
+    fn id(&self) -> &str {
+        "openai"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn embed(&self, texts: &[String]) -> anyhow::Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let response = self
+            .client
+            .post(format!("{}/v1/embeddings", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&json!({ "model": self.model, "input": texts }))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(ProviderHttpError::from_response("OpenAI", response).await.into());
+        }
+        parse_openai_response(response.json().await?, texts.len())
+    }
+}
+
+// --- Ollama ---
+
+/// Ollama `/api/embed`.
+pub struct OllamaEmbeddings {
+    model: String,
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl OllamaEmbeddings {
+    pub fn new(model: Option<&str>, base_url: Option<&str>) -> Self {
+        Self {
+            model: model.unwrap_or(OLLAMA_DEFAULT_MODEL).to_string(),
+            base_url: base_url
+                .unwrap_or(OLLAMA_BASE_URL)
+                .trim_end_matches('/')
+                .to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct OllamaEmbeddingResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+#[async_trait]
+impl EmbeddingProvider for OllamaEmbeddings {
+    fn id(&self) -> &str {
+        "ollama"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn embed(&self, texts: &[String]) -> anyhow::Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let response = self
+            .client
+            .post(format!("{}/api/embed", self.base_url))
+            .json(&json!({ "model": self.model, "input": texts }))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(ProviderHttpError::from_response("Ollama", response).await.into());
+        }
+        let body: OllamaEmbeddingResponse = response.json().await?;
+        if body.embeddings.len() != texts.len() {
+            anyhow::bail!(
+                "Ollama returned {} embeddings for {} inputs",
+                body.embeddings.len(),
+                texts.len()
+            );
+        }
+        Ok(body.embeddings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 3.0]).abs() < 1e-6);
+        assert!((cosine_similarity(&[1.0, 1.0], &[-1.0, -1.0]) + 1.0).abs() < 1e-6);
+        // Mismatched or degenerate vectors never match
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_openai_response_sorted_by_index() {
+        let body: OpenAiEmbeddingResponse = serde_json::from_str(
+            r#"{"object":"list","data":[
+                {"object":"embedding","index":1,"embedding":[0.0,1.0]},
+                {"object":"embedding","index":0,"embedding":[1.0,0.0]}
+            ],"model":"text-embedding-3-small"}"#,
+        )
+        .unwrap();
+        let vectors = parse_openai_response(body, 2).unwrap();
+        assert_eq!(vectors, vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+    }
+
+    #[test]
+    fn test_from_config() {
+        let ollama = from_config(&EmbeddingsConfig {
+            provider: "ollama".into(),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(ollama.id(), "ollama");
+        assert_eq!(ollama.model(), "nomic-embed-text");
+
+        let openai = from_config(&EmbeddingsConfig {
+            provider: "openai".into(),
+            model: Some("text-embedding-3-large".into()),
+            api_key: Some("sk-test".into()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(openai.model(), "text-embedding-3-large");
+
+        assert!(from_config(&EmbeddingsConfig {
+            provider: "nope".into(),
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
@@ -1,10 +1,17 @@
 //! Failover provider — wraps multiple providers in priority order.
 //!
-//! On error (rate limit, auth failure, timeout), falls back to the next
-//! provider in the list.
+//! On a connection error, timeout, rate limit, auth failure or 5xx, falls
+//! back to the next provider in the list and puts the failing one on a short
+//! cooldown so subsequent requests skip it. Other client errors (e.g. a 400
+//! for a malformed request) are returned immediately since every provider
+//! would reject them.
+//!
+//! Requests are formatted by the primary provider, so fallbacks must speak
+//! the same wire format ([`LlmProvider::api`]); mismatched ones are skipped.
 
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use futures::Stream;
@@ -12,14 +19,20 @@ use tracing::{info, warn};
 
 use crate::{
     CompletionChunk, CompletionRequest, Credentials, LlmProvider, ModelApi, ModelInfo,
-    ToolDefinition,
+    ProviderHttpError, ToolDefinition,
 };
 use rusty_claw_core::session::TranscriptEntry;
 
+/// Default time a failing provider is skipped for.
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
 /// A failover provider that tries multiple underlying providers in order.
 pub struct FailoverProvider {
     providers: Vec<(Arc<dyn LlmProvider>, Credentials)>,
     label: String,
+    cooldown: Duration,
+    /// Per-provider instant until which it is skipped.
+    cooling_until: Mutex<Vec<Option<Instant>>>,
 }
 
 impl FailoverProvider {
@@ -29,12 +42,51 @@ impl FailoverProvider {
         label: String,
         providers: Vec<(Arc<dyn LlmProvider>, Credentials)>,
     ) -> Self {
-        Self { providers, label }
+        let cooling_until = Mutex::new(vec![None; providers.len()]);
+        Self {
+            providers,
+            label,
+            cooldown: DEFAULT_COOLDOWN,
+            cooling_until,
+        }
+    }
+
+    /// Override how long a failing provider is skipped.
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
     }
 
     fn primary(&self) -> Option<&(Arc<dyn LlmProvider>, Credentials)> {
         self.providers.first()
     }
+
+    /// Provider indices in try order: healthy ones first (in priority
+    /// order), then cooling ones, so a request is never refused outright.
+    fn try_order(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let cooling = self.cooling_until.lock().unwrap();
+        let (healthy, cooling): (Vec<usize>, Vec<usize>) = (0..self.providers.len())
+            .partition(|&i| cooling[i].is_none_or(|until| until <= now));
+        healthy.into_iter().chain(cooling).collect()
+    }
+
+    fn mark(&self, index: usize, healthy: bool) {
+        let mut cooling = self.cooling_until.lock().unwrap();
+        cooling[index] = (!healthy).then(|| Instant::now() + self.cooldown);
+    }
+}
+
+/// Whether an error means the provider is unhealthy and the next one
+/// should be tried.
+fn should_fail_over(err: &anyhow::Error) -> bool {
+    match err.downcast_ref::<ProviderHttpError>() {
+        Some(http) => {
+            http.status.is_server_error() || matches!(http.status.as_u16(), 401 | 403 | 408 | 429)
+        }
+        // Connection errors, timeouts, missing credentials
+        None => true,
+    }
 }
 
 #[async_trait]
@@ -74,28 +126,41 @@ impl LlmProvider for FailoverProvider {
     ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<CompletionChunk>> + Send>>> {
         // Try each provider in order using its own credentials.
         let mut last_error = None;
+        let api = self.api();
 
-        for (i, (provider, creds)) in self.providers.iter().enumerate() {
+        for (attempt, i) in self.try_order().into_iter().enumerate() {
+            let (provider, creds) = &self.providers[i];
+            if provider.api() != api {
+                warn!(
+                    provider = provider.id(),
+                    "Skipping failover provider with a different API format"
+                );
+                continue;
+            }
             match provider.stream(request, creds).await {
                 Ok(stream) => {
-                    if i > 0 {
+                    self.mark(i, true);
+                    if attempt > 0 {
                         info!(
                             provider = provider.id(),
-                            attempt = i + 1,
+                            attempt = attempt + 1,
                             "Failover succeeded"
                         );
                     }
                     return Ok(stream);
                 }
-                Err(e) => {
+                Err(e) if should_fail_over(&e) => {
+                    self.mark(i, false);
                     warn!(
                         provider = provider.id(),
-                        attempt = i + 1,
+                        attempt = attempt + 1,
+                        cooldown_secs = self.cooldown.as_secs(),
                         %e,
                         "Provider failed, trying next"
                     );
                     last_error = Some(e);
                 }
+                Err(e) => return Err(e),
             }
         }
 
@@ -115,11 +180,116 @@ impl LlmProvider for FailoverProvider {
         }
         Ok(all_models)
     }
+
+    async fn count_tokens(
+        &self,
+        request: &CompletionRequest,
+        _credentials: &Credentials,
+    ) -> anyhow::Result<usize> {
+        match self.primary() {
+            Some((provider, creds)) => provider.count_tokens(request, creds).await,
+            None => Ok(crate::tokens::estimate_tokens(&crate::tokens::request_text(request))),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::{ChunkStream, StubProvider};
+
+    /// Fails with `status` if set, otherwise succeeds.
+    fn stub(id: &'static str, status: Option<u16>) -> StubProvider {
+        StubProvider::new(move |_, _| async move {
+            match status {
+                Some(status) => Err(ProviderHttpError {
+                    provider: id.into(),
+                    status: reqwest::StatusCode::from_u16(status).unwrap(),
+                    retry_after: None,
+                    body: String::new(),
+                }
+                .into()),
+                None => Ok(Box::pin(futures::stream::empty()) as ChunkStream),
+            }
+        })
+        .with_id(id)
+        .with_api(ModelApi::OpenAiCompletions)
+    }
+
+    fn mock(id: &'static str, status: Option<u16>) -> Arc<StubProvider> {
+        Arc::new(stub(id, status))
+    }
+
+    fn creds() -> Credentials {
+        Credentials::ApiKey {
+            api_key: "k".into(),
+        }
+    }
+
+    fn chain(providers: &[Arc<StubProvider>]) -> FailoverProvider {
+        FailoverProvider::new(
+            "group".into(),
+            providers
+                .iter()
+                .map(|p| (p.clone() as Arc<dyn LlmProvider>, creds()))
+                .collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_failover_on_server_error_with_cooldown() {
+        let primary = mock("primary", Some(503));
+        let backup = mock("backup", None);
+        let provider = chain(&[primary.clone(), backup.clone()]);
+        let request = CompletionRequest::default();
+
+        assert!(provider.stream(&request, &creds()).await.is_ok());
+        assert_eq!(primary.calls(), 1);
+        assert_eq!(backup.calls(), 1);
+
+        // Primary is cooling down, so the next request goes straight to backup
+        assert!(provider.stream(&request, &creds()).await.is_ok());
+        assert_eq!(primary.calls(), 1);
+        assert_eq!(backup.calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cooldown_expires() {
+        let primary = mock("primary", Some(502));
+        let backup = mock("backup", None);
+        let provider =
+            chain(&[primary.clone(), backup.clone()]).with_cooldown(Duration::from_millis(0));
+        let request = CompletionRequest::default();
+
+        assert!(provider.stream(&request, &creds()).await.is_ok());
+        assert!(provider.stream(&request, &creds()).await.is_ok());
+        assert_eq!(primary.calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_client_error_does_not_fail_over() {
+        let primary = mock("primary", Some(400));
+        let backup = mock("backup", None);
+        let provider = chain(&[primary, backup.clone()]);
+
+        let err = provider
+            .stream(&CompletionRequest::default(), &creds())
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("400"));
+        assert_eq!(backup.calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_skips_mismatched_api() {
+        let primary = mock("primary", Some(500));
+        let other = Arc::new(stub("other", None).with_api(ModelApi::AnthropicMessages));
+        let provider = chain(&[primary, other.clone()]);
+
+        assert!(provider.stream(&CompletionRequest::default(), &creds()).await.is_err());
+        assert_eq!(other.calls(), 0);
+    }
 
     #[test]
     fn test_failover_provider_creation() {
@@ -13,12 +13,13 @@ use tokio_stream::StreamExt;
 use tracing::{debug, trace};
 
 use rusty_claw_core::session::TranscriptEntry;
-use rusty_claw_core::types::ContentBlock;
+use rusty_claw_core::types::{ContentBlock, ImageSource};
 
 use crate::sse::parse_sse_stream;
+use crate::tokens::{approximate_bpe_tokens, request_text};
 use crate::{
     ChunkUsage, CompletionChunk, CompletionRequest, Credentials, LlmProvider, ModelApi, ModelInfo,
-    ToolDefinition, ToolUseChunk,
+    ProviderHttpError, ResponseFormat, ToolChoice, ToolDefinition, ToolUseChunk,
 };
 
 const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com";
@@ -38,6 +39,13 @@ impl GeminiProvider {
             client: reqwest::Client::new(),
         }
     }
+
+    /// Replace the HTTP client for Gemini requests. The API key is still
+    /// passed in the query string.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
 }
 
 // --- Gemini request/response types ---
@@ -51,6 +59,8 @@ struct GeminiRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<serde_json::Value>>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    tool_config: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     generation_config: Option<GenerationConfig>,
 }
 
@@ -61,6 +71,18 @@ struct GenerationConfig {
     max_output_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_mime_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_schema: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -112,6 +134,37 @@ struct UsageMetadata {
     candidates_token_count: u64,
 }
 
+/// Translate a [`ToolChoice`] into `toolConfig.functionCallingConfig`.
+fn gemini_tool_config(choice: &ToolChoice) -> serde_json::Value {
+    let config = match choice {
+        ToolChoice::Auto => json!({ "mode": "AUTO" }),
+        ToolChoice::None => json!({ "mode": "NONE" }),
+        ToolChoice::Required => json!({ "mode": "ANY" }),
+        ToolChoice::Tool { name } => json!({ "mode": "ANY", "allowedFunctionNames": [name] }),
+    };
+    json!({ "functionCallingConfig": config })
+}
+
+/// Map an image source to a `streamGenerateContent` part: base64 data is
+/// sent inline, URLs are referenced via `fileData`.
+fn gemini_image_part(source: &ImageSource) -> serde_json::Value {
+    if source.source_type == "base64" {
+        json!({
+            "inlineData": {
+                "mimeType": source.media_type,
+                "data": source.data,
+            }
+        })
+    } else {
+        json!({
+            "fileData": {
+                "mimeType": source.media_type,
+                "fileUri": source.data,
+            }
+        })
+    }
+}
+
 #[async_trait]
 impl LlmProvider for GeminiProvider {
     fn id(&self) -> &str {
@@ -148,24 +201,7 @@ impl LlmProvider for GeminiProvider {
                         .iter()
                         .filter_map(|b| match b {
                             ContentBlock::Text { text } => Some(json!({ "text": text })),
-                            ContentBlock::Image { source } => {
-                                if source.source_type == "base64" {
-                                    Some(json!({
-                                        "inline_data": {
-                                            "mime_type": source.media_type,
-                                            "data": source.data,
-                                        }
-                                    }))
-                                } else {
-                                    // URL-based images: use fileData
-                                    Some(json!({
-                                        "file_data": {
-                                            "mime_type": source.media_type,
-                                            "file_uri": source.data,
-                                        }
-                                    }))
-                                }
-                            }
+                            ContentBlock::Image { source } => Some(gemini_image_part(source)),
                             _ => None,
                         })
                         .collect();
@@ -246,9 +282,25 @@ impl LlmProvider for GeminiProvider {
             contents: request.messages.clone(),
             system_instruction,
             tools: request.tools.clone(),
+            tool_config: request
+                .tool_choice
+                .as_ref()
+                .filter(|_| request.tools.is_some())
+                .map(gemini_tool_config),
             generation_config: Some(GenerationConfig {
                 max_output_tokens: Some(request.max_tokens),
                 temperature: request.temperature,
+                top_p: request.top_p,
+                top_k: request.top_k,
+                seed: request.seed,
+                stop_sequences: request.stop_sequences.clone(),
+                response_mime_type: request
+                    .response_format
+                    .as_ref()
+                    .map(|_| "application/json".into()),
+                response_schema: request.response_format.as_ref().map(|format| match format {
+                    ResponseFormat::JsonSchema { schema } => schema.clone(),
+                }),
             }),
         };
 
@@ -268,9 +320,7 @@ impl LlmProvider for GeminiProvider {
             .await?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            anyhow::bail!("Gemini API error {status}: {body}");
+            return Err(ProviderHttpError::from_response("Gemini", response).await.into());
         }
 
         let sse_stream = parse_sse_stream(response);
@@ -302,8 +352,11 @@ impl LlmProvider for GeminiProvider {
                                     usage: Some(ChunkUsage {
                                         input_tokens: Some(usage.prompt_token_count),
                                         output_tokens: Some(usage.candidates_token_count),
+                                        ..Default::default()
                                     }),
                                     stop_reason: None,
+                                    thinking_signature: None,
+                                    system_fingerprint: None,
                                 };
                                 return Some((Ok(c), state));
                             }
@@ -323,6 +376,8 @@ impl LlmProvider for GeminiProvider {
                                             tool_use: None,
                                             usage: None,
                                             stop_reason: None,
+                                            thinking_signature: None,
+                                            system_fingerprint: None,
                                         };
                                         return Some((Ok(c), state));
                                     }
@@ -350,6 +405,8 @@ impl LlmProvider for GeminiProvider {
                                             usage: None,
                                             // Set TOOL_USE stop reason so the agent loop knows
                                             stop_reason: Some("TOOL_USE".into()),
+                                            thinking_signature: None,
+                                            system_fingerprint: None,
                                         };
                                         return Some((Ok(c), state));
                                     }
@@ -367,6 +424,8 @@ impl LlmProvider for GeminiProvider {
                                     tool_use: None,
                                     usage: None,
                                     stop_reason: Some(reason.clone()),
+                                    thinking_signature: None,
+                                    system_fingerprint: None,
                                 };
                                 return Some((Ok(c), state));
                             }
@@ -387,6 +446,14 @@ impl LlmProvider for GeminiProvider {
         Ok(Box::pin(chunk_stream))
     }
 
+    async fn count_tokens(
+        &self,
+        request: &CompletionRequest,
+        _credentials: &Credentials,
+    ) -> anyhow::Result<usize> {
+        Ok(approximate_bpe_tokens(&request_text(request)))
+    }
+
     async fn list_models(&self, credentials: &Credentials) -> anyhow::Result<Vec<ModelInfo>> {
         let api_key = match credentials {
             Credentials::ApiKey { api_key } => api_key.clone(),
@@ -479,6 +546,9 @@ mod tests {
                     text: "Hi there".into(),
                 }],
                 usage: None,
+                thinking: None,
+                thinking_signature: None,
+                thinking_blocks: Vec::new(),
                 timestamp: Utc::now(),
             },
         ];
@@ -508,6 +578,9 @@ mod tests {
                     input: json!({"command": "ls"}),
                 }],
                 usage: None,
+                thinking: None,
+                thinking_signature: None,
+                thinking_blocks: Vec::new(),
                 timestamp: Utc::now(),
             },
             TranscriptEntry::ToolCall {
@@ -529,6 +602,60 @@ mod tests {
         assert!(messages[2]["parts"][0]["functionResponse"].is_object());
     }
 
+    #[test]
+    fn test_generation_config_stop_sequences() {
+        let config = GenerationConfig {
+            max_output_tokens: Some(256),
+            temperature: None,
+            top_p: Some(0.9),
+            top_k: Some(40),
+            seed: Some(7),
+            stop_sequences: Some(vec!["</answer>".into()]),
+            response_mime_type: None,
+            response_schema: None,
+        };
+        let value = serde_json::to_value(&config).unwrap();
+        assert_eq!(value["maxOutputTokens"], 256);
+        assert_eq!(value["stopSequences"][0], "</answer>");
+        assert_eq!(value["topP"], 0.9);
+        assert_eq!(value["topK"], 40);
+        assert_eq!(value["seed"], 7);
+        assert!(value.get("temperature").is_none());
+    }
+
+    #[test]
+    fn test_generation_config_response_schema() {
+        let config = GenerationConfig {
+            max_output_tokens: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            stop_sequences: None,
+            response_mime_type: Some("application/json".into()),
+            response_schema: Some(json!({"type": "object"})),
+        };
+        let value = serde_json::to_value(&config).unwrap();
+        assert_eq!(value["responseMimeType"], "application/json");
+        assert_eq!(value["responseSchema"]["type"], "object");
+    }
+
+    #[test]
+    fn test_gemini_tool_config() {
+        let forced = gemini_tool_config(&ToolChoice::Tool {
+            name: "submit_answer".into(),
+        });
+        assert_eq!(forced["functionCallingConfig"]["mode"], "ANY");
+        assert_eq!(
+            forced["functionCallingConfig"]["allowedFunctionNames"][0],
+            "submit_answer"
+        );
+        assert_eq!(
+            gemini_tool_config(&ToolChoice::None)["functionCallingConfig"]["mode"],
+            "NONE"
+        );
+    }
+
     #[test]
     fn test_is_tool_use_stop_gemini() {
         let provider = GeminiProvider::new(None);
@@ -607,13 +734,36 @@ mod tests {
         // First part: text
         assert_eq!(parts[0]["text"], "Describe this image");
 
-        // Second part: inline_data format for base64 images
-        let inline_data = &parts[1]["inline_data"];
+        // Second part: inlineData format for base64 images
+        let inline_data = &parts[1]["inlineData"];
         assert!(
             inline_data.is_object(),
-            "Expected inline_data object for base64 image, got: {parts:?}"
+            "Expected inlineData object for base64 image, got: {parts:?}"
         );
-        assert_eq!(inline_data["mime_type"], "image/jpeg");
+        assert_eq!(inline_data["mimeType"], "image/jpeg");
         assert_eq!(inline_data["data"], "ZmFrZWpwZWc=");
     }
+
+    #[test]
+    fn test_format_messages_with_image_url() {
+        use chrono::Utc;
+        use rusty_claw_core::types::ImageSource;
+
+        let provider = GeminiProvider::new(None);
+        let transcript = vec![TranscriptEntry::User {
+            content: vec![ContentBlock::Image {
+                source: ImageSource {
+                    source_type: "url".into(),
+                    media_type: "image/png".into(),
+                    data: "https://example.com/cat.png".into(),
+                },
+            }],
+            timestamp: Utc::now(),
+        }];
+
+        let messages = provider.format_messages(&transcript);
+        let file_data = &messages[0]["parts"][0]["fileData"];
+        assert_eq!(file_data["mimeType"], "image/png");
+        assert_eq!(file_data["fileUri"], "https://example.com/cat.png");
+    }
 }
@@ -6,6 +6,7 @@
 use std::collections::HashMap;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use futures::Stream;
@@ -14,10 +15,50 @@ use serde::{Deserialize, Serialize};
 use rusty_claw_core::session::TranscriptEntry;
 
 pub mod anthropic;
+pub mod bedrock;
+pub mod copilot;
+pub mod embeddings;
 pub mod failover;
 pub mod google;
+pub mod oauth;
 pub mod openai;
+pub mod pricing;
+pub mod retry;
 pub mod sse;
+#[cfg(any(test, feature = "test-support"))]
+pub mod testing;
+pub mod timeout;
+pub mod tokens;
+
+/// HTTP client settings applied to a provider's `reqwest::Client`.
+#[derive(Debug, Clone, Default)]
+pub struct HttpClientOptions {
+    /// Route all requests through this proxy (e.g. `http://proxy:3128`).
+    pub proxy_url: Option<String>,
+    /// Extra headers sent on every request.
+    pub headers: HashMap<String, String>,
+}
+
+impl HttpClientOptions {
+    /// Build a client with the proxy and default headers applied.
+    pub fn build(&self) -> anyhow::Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(url) = &self.proxy_url {
+            builder = builder.proxy(reqwest::Proxy::all(url)?);
+        }
+        if !self.headers.is_empty() {
+            let mut headers = reqwest::header::HeaderMap::new();
+            for (name, value) in &self.headers {
+                headers.insert(
+                    reqwest::header::HeaderName::from_bytes(name.as_bytes())?,
+                    reqwest::header::HeaderValue::from_str(value)?,
+                );
+            }
+            builder = builder.default_headers(headers);
+        }
+        Ok(builder.build()?)
+    }
+}
 
 /// Supported LLM API protocols.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -42,6 +83,16 @@ pub enum Credentials {
     OAuth { access_token: String, refresh_token: Option<String> },
     #[serde(rename = "token")]
     Token { token: String },
+    /// AWS access keys for SigV4-signed providers (Bedrock).
+    #[serde(rename = "aws")]
+    Aws {
+        access_key_id: String,
+        secret_access_key: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        session_token: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        region: Option<String>,
+    },
 }
 
 /// Provider-agnostic tool definition.
@@ -55,8 +106,34 @@ pub struct ToolDefinition {
     pub parameters_schema: serde_json::Value,
 }
 
+/// Whether and which tool the model must call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ToolChoice {
+    /// The model decides (provider default).
+    Auto,
+    /// Tools are offered but must not be called.
+    None,
+    /// The model must call at least one tool.
+    Required,
+    /// The model must call the named tool.
+    Tool { name: String },
+}
+
+/// Name used for the schema (OpenAI) or forced tool (Anthropic) that carries
+/// a [`ResponseFormat::JsonSchema`] reply.
+pub const STRUCTURED_OUTPUT_NAME: &str = "structured_output";
+
+/// Constraint on the shape of the model's final text output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Reply with a single JSON value matching `schema` (a JSON Schema).
+    JsonSchema { schema: serde_json::Value },
+}
+
 /// A request to the LLM.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CompletionRequest {
     pub model: String,
     pub messages: Vec<serde_json::Value>,
@@ -67,16 +144,51 @@ pub struct CompletionRequest {
     /// Budget for thinking/reasoning tokens (Anthropic extended thinking).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub thinking_budget_tokens: Option<u32>,
+    /// Mark the system prompt and tool definitions as cacheable
+    /// (Anthropic prompt caching). Ignored by providers without caching.
+    #[serde(default)]
+    pub enable_prompt_cache: bool,
+    /// Sequences that end generation when produced. A stop-sequence finish
+    /// is an ordinary end of turn, never a tool-use stop.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+    /// Force or forbid tool use. `None` leaves the provider default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    /// Require structured output. Delivered as ordinary text deltas
+    /// whichever mechanism the provider uses to enforce it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    /// Sampling controls. Each provider sends the ones its API supports and
+    /// silently drops the rest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
+    /// Sampling seed for best-effort reproducible output (OpenAI, Gemini).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
 }
 
 /// A streamed chunk from the LLM.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CompletionChunk {
     pub delta: Option<String>,
     pub thinking: Option<String>,
     pub tool_use: Option<ToolUseChunk>,
     pub usage: Option<ChunkUsage>,
     pub stop_reason: Option<String>,
+    /// Signature closing a thinking block; must accompany the thinking text
+    /// when it is sent back to the provider (Anthropic extended thinking).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking_signature: Option<String>,
+    /// Backend configuration fingerprint (OpenAI), for reproducibility checks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_fingerprint: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -90,6 +202,13 @@ pub struct ToolUseChunk {
 pub struct ChunkUsage {
     pub input_tokens: Option<u64>,
     pub output_tokens: Option<u64>,
+    /// Tokens written to the prompt cache on this request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation_input_tokens: Option<u64>,
+    /// Tokens served from the prompt cache on this request (not included
+    /// in `input_tokens`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_input_tokens: Option<u64>,
 }
 
 /// Model metadata.
@@ -103,6 +222,49 @@ pub struct ModelInfo {
     pub max_tokens: u32,
 }
 
+/// A non-success HTTP response from a provider API, returned by
+/// [`LlmProvider::stream`] before any chunk has been produced.
+///
+/// Decorators downcast to this to decide whether a failure is retryable
+/// or an authentication problem.
+#[derive(Debug, thiserror::Error)]
+#[error("{provider} API error {status}: {body}")]
+pub struct ProviderHttpError {
+    pub provider: String,
+    pub status: reqwest::StatusCode,
+    /// Parsed `Retry-After` header (delta-seconds form).
+    pub retry_after: Option<Duration>,
+    pub body: String,
+}
+
+impl ProviderHttpError {
+    /// Consume a failed response into an error, capturing `Retry-After`.
+    pub async fn from_response(provider: &str, response: reqwest::Response) -> Self {
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        let body = response.text().await.unwrap_or_default();
+        Self {
+            provider: provider.to_string(),
+            status,
+            retry_after,
+            body,
+        }
+    }
+}
+
+/// A provider call that produced no response or chunk within its timeout.
+#[derive(Debug, thiserror::Error)]
+#[error("{provider} request timed out after {}ms", .timeout.as_millis())]
+pub struct ProviderTimeoutError {
+    pub provider: String,
+    pub timeout: Duration,
+}
+
 /// The core LLM provider trait.
 #[async_trait]
 pub trait LlmProvider: Send + Sync {
@@ -130,6 +292,17 @@ pub trait LlmProvider: Send + Sync {
 
     /// List available models from this provider.
     async fn list_models(&self, credentials: &Credentials) -> anyhow::Result<Vec<ModelInfo>>;
+
+    /// Count the prompt tokens `request` would use. The default is the
+    /// character heuristic; providers with a tokenizer or counting endpoint
+    /// override it.
+    async fn count_tokens(
+        &self,
+        request: &CompletionRequest,
+        _credentials: &Credentials,
+    ) -> anyhow::Result<usize> {
+        Ok(tokens::estimate_tokens(&tokens::request_text(request)))
+    }
 }
 
 /// Registry of named LLM providers with credentials.
@@ -138,6 +311,8 @@ pub trait LlmProvider: Send + Sync {
 /// and select one by name or fall back to the default.
 pub struct ProviderRegistry {
     providers: HashMap<String, (Arc<dyn LlmProvider>, Credentials)>,
+    /// Model name → ID of the provider serving it.
+    models: HashMap<String, String>,
     default_id: String,
 }
 
@@ -145,10 +320,35 @@ impl ProviderRegistry {
     pub fn new(default_id: String) -> Self {
         Self {
             providers: HashMap::new(),
+            models: HashMap::new(),
             default_id,
         }
     }
 
+    /// Record the models served by provider `id`. A model listed by several
+    /// providers stays with the first one registered.
+    pub fn register_models(&mut self, id: &str, models: impl IntoIterator<Item = String>) {
+        for model in models {
+            self.models.entry(model).or_insert_with(|| id.to_string());
+        }
+    }
+
+    /// Pick the provider for a run: `provider_id` if registered, else the
+    /// provider serving `model`, else the default. Returns the chosen ID.
+    pub fn resolve(
+        &self,
+        provider_id: Option<&str>,
+        model: Option<&str>,
+    ) -> Option<(&str, &dyn LlmProvider, &Credentials)> {
+        let id = provider_id
+            .filter(|id| self.providers.contains_key(*id))
+            .or_else(|| model.and_then(|m| self.models.get(m)).map(String::as_str))
+            .unwrap_or(&self.default_id);
+        self.providers
+            .get_key_value(id)
+            .map(|(id, (p, c))| (id.as_str(), p.as_ref(), c))
+    }
+
     /// Register a provider with its credentials under a given ID.
     pub fn register(&mut self, id: String, provider: Arc<dyn LlmProvider>, credentials: Credentials) {
         self.providers.insert(id, (provider, credentials));
@@ -179,6 +379,50 @@ impl ProviderRegistry {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_http_client_options_validation() {
+        assert!(HttpClientOptions::default().build().is_ok());
+        assert!(HttpClientOptions {
+            proxy_url: Some("http://proxy.internal:3128".into()),
+            ..Default::default()
+        }
+        .build()
+        .is_ok());
+        assert!(HttpClientOptions {
+            headers: HashMap::from([("bad header".into(), "x".into())]),
+            ..Default::default()
+        }
+        .build()
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_http_client_sends_default_headers() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut sock, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let n = sock.read(&mut buf).await.unwrap();
+            let _ = sock
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                .await;
+            String::from_utf8_lossy(&buf[..n]).to_lowercase()
+        });
+
+        let client = HttpClientOptions {
+            headers: HashMap::from([("X-Org-Id".into(), "acme".into())]),
+            ..Default::default()
+        }
+        .build()
+        .unwrap();
+        client.get(format!("http://{addr}/")).send().await.unwrap();
+
+        assert!(server.await.unwrap().contains("x-org-id: acme"));
+    }
+
     #[test]
     fn test_provider_registry_register_and_get() {
         let provider = Arc::new(anthropic::AnthropicProvider::new(None));
@@ -221,4 +465,23 @@ mod tests {
         assert!(ids.contains(&"anthropic"));
         assert!(ids.contains(&"openai"));
     }
+
+    #[test]
+    fn test_provider_registry_resolve() {
+        let mut registry = ProviderRegistry::new("anthropic".into());
+        let provider = Arc::new(anthropic::AnthropicProvider::new(None));
+        let creds = Credentials::ApiKey {
+            api_key: "k".into(),
+        };
+        registry.register("anthropic".into(), provider.clone(), creds.clone());
+        registry.register("openai".into(), provider, creds);
+        registry.register_models("openai", ["gpt-4o".to_string(), "o3".to_string()]);
+
+        let id = |provider: Option<&str>, model: Option<&str>| registry.resolve(provider, model).map(|(id, _, _)| id);
+        assert_eq!(id(None, Some("gpt-4o")), Some("openai"));
+        assert_eq!(id(Some("anthropic"), Some("gpt-4o")), Some("anthropic"));
+        // Unknown provider or model falls back to the default
+        assert_eq!(id(Some("mistral"), None), Some("anthropic"));
+        assert_eq!(id(None, Some("claude-sonnet-4-20250514")), Some("anthropic"));
+    }
 }
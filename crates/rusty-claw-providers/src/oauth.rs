@@ -0,0 +1,470 @@
+//! OAuth refresh provider — wraps a provider that authenticates with
+//! [`Credentials::OAuth`] and transparently refreshes expired access tokens.
+//!
+//! When the inner provider rejects a request with a 401, the refresh token is
+//! exchanged at the configured token endpoint, the new token pair is persisted
+//! to a [`TokenStore`], and the request is retried once. Persisted tokens
+//! remember the configured tokens they came from and are ignored once the
+//! config changes.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::{
+    CompletionChunk, CompletionRequest, Credentials, LlmProvider, ModelApi, ModelInfo,
+    ProviderHttpError, ToolDefinition,
+};
+use rusty_claw_core::session::TranscriptEntry;
+
+/// Returns true if a provider error looks like an authentication failure.
+pub fn is_auth_error(err: &anyhow::Error) -> bool {
+    if let Some(http) = err.downcast_ref::<ProviderHttpError>() {
+        return http.status == reqwest::StatusCode::UNAUTHORIZED;
+    }
+    let msg = err.to_string();
+    msg.contains("401") || msg.contains("token_expired") || msg.contains("invalid_token")
+}
+
+/// File-backed store for refreshed OAuth credentials, keyed by provider ID.
+#[derive(Debug, Clone)]
+pub struct TokenStore {
+    path: PathBuf,
+    /// Fingerprint of the configured credentials new tokens derive from.
+    source: Option<String>,
+}
+
+/// A persisted entry and the configured credentials it was refreshed from.
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredToken {
+    #[serde(flatten)]
+    credentials: Credentials,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+}
+
+impl TokenStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, source: None }
+    }
+
+    /// Tie tokens to the credentials in the config. Entries saved from other
+    /// configured credentials are not loaded, so editing the config takes
+    /// effect instead of being shadowed by an older refresh.
+    pub fn with_source(mut self, configured: &Credentials) -> Self {
+        let json = serde_json::to_vec(configured).unwrap_or_default();
+        self.source = Some(hex::encode(Sha256::digest(json)));
+        self
+    }
+
+    /// Default location: `~/.rusty_claw/oauth_tokens.json`
+    pub fn default_path() -> PathBuf {
+        rusty_claw_core::config::data_dir().join("oauth_tokens.json")
+    }
+
+    fn read_all(&self) -> HashMap<String, StoredToken> {
+        std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    /// Load persisted credentials for a provider, if they were refreshed
+    /// from the current configured credentials.
+    pub fn load(&self, provider_id: &str) -> Option<Credentials> {
+        self.read_all()
+            .remove(provider_id)
+            .filter(|stored| stored.source == self.source)
+            .map(|stored| stored.credentials)
+    }
+
+    /// Persist credentials for a provider, replacing any previous entry.
+    pub fn save(&self, provider_id: &str, credentials: &Credentials) -> anyhow::Result<()> {
+        let mut all = self.read_all();
+        all.insert(
+            provider_id.to_string(),
+            StoredToken {
+                credentials: credentials.clone(),
+                source: self.source.clone(),
+            },
+        );
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(&all)?)?;
+        restrict_permissions(&tmp);
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600));
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) {}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+}
+
+/// A provider decorator that refreshes OAuth access tokens on 401.
+pub struct OAuthRefreshProvider {
+    inner: Arc<dyn LlmProvider>,
+    provider_id: String,
+    token_url: String,
+    client_id: Option<String>,
+    store: Option<TokenStore>,
+    /// Latest credentials; supersedes those handed out by the registry once
+    /// a refresh has happened.
+    current: RwLock<Option<Credentials>>,
+    client: reqwest::Client,
+}
+
+impl OAuthRefreshProvider {
+    pub fn new(
+        inner: Arc<dyn LlmProvider>,
+        provider_id: String,
+        token_url: String,
+        client_id: Option<String>,
+        store: Option<TokenStore>,
+    ) -> Self {
+        Self {
+            inner,
+            provider_id,
+            token_url,
+            client_id,
+            store,
+            current: RwLock::new(None),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Use `client` for calls to the token endpoint. The wrapped provider
+    /// keeps its own client.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    async fn effective_credentials(&self, passed: &Credentials) -> Credentials {
+        self.current
+            .read()
+            .await
+            .clone()
+            .unwrap_or_else(|| passed.clone())
+    }
+
+    /// Exchange the refresh token for a new access token and persist it.
+    ///
+    /// `stale` is the credential set that was rejected; if another caller
+    /// already refreshed in the meantime, its result is reused.
+    async fn refresh(&self, stale: &Credentials) -> anyhow::Result<Credentials> {
+        let mut current = self.current.write().await;
+        if let Some(existing) = current.as_ref() {
+            if access_token(existing) != access_token(stale) {
+                return Ok(existing.clone());
+            }
+        }
+
+        let refresh_token = match stale {
+            Credentials::OAuth {
+                refresh_token: Some(rt),
+                ..
+            } => rt.clone(),
+            _ => anyhow::bail!("No refresh token available for '{}'", self.provider_id),
+        };
+
+        let mut form = vec![
+            ("grant_type", "refresh_token".to_string()),
+            ("refresh_token", refresh_token.clone()),
+        ];
+        if let Some(client_id) = &self.client_id {
+            form.push(("client_id", client_id.clone()));
+        }
+
+        let response = self
+            .client
+            .post(&self.token_url)
+            .header("accept", "application/json")
+            .form(&form)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("OAuth token refresh failed {status}: {body}");
+        }
+
+        let body: TokenResponse = response.json().await?;
+        let refreshed = Credentials::OAuth {
+            access_token: body.access_token,
+            // Servers that don't rotate refresh tokens omit the field
+            refresh_token: Some(body.refresh_token.unwrap_or(refresh_token)),
+        };
+
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save(&self.provider_id, &refreshed) {
+                warn!(provider = %self.provider_id, %e, "Failed to persist refreshed OAuth token");
+            }
+        }
+        info!(provider = %self.provider_id, "OAuth access token refreshed");
+
+        *current = Some(refreshed.clone());
+        Ok(refreshed)
+    }
+}
+
+fn access_token(credentials: &Credentials) -> Option<&str> {
+    match credentials {
+        Credentials::OAuth { access_token, .. } => Some(access_token),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OAuthRefreshProvider {
+    fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    fn api(&self) -> ModelApi {
+        self.inner.api()
+    }
+
+    fn format_tools(&self, tools: &[ToolDefinition]) -> Vec<serde_json::Value> {
+        self.inner.format_tools(tools)
+    }
+
+    fn format_messages(&self, transcript: &[TranscriptEntry]) -> Vec<serde_json::Value> {
+        self.inner.format_messages(transcript)
+    }
+
+    fn is_tool_use_stop(&self, stop_reason: &str) -> bool {
+        self.inner.is_tool_use_stop(stop_reason)
+    }
+
+    async fn stream(
+        &self,
+        request: &CompletionRequest,
+        credentials: &Credentials,
+    ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<CompletionChunk>> + Send>>> {
+        let creds = self.effective_credentials(credentials).await;
+        match self.inner.stream(request, &creds).await {
+            Err(e) if is_auth_error(&e) && matches!(creds, Credentials::OAuth { .. }) => {
+                warn!(provider = %self.provider_id, %e, "Access token rejected, refreshing");
+                let refreshed = self.refresh(&creds).await?;
+                self.inner.stream(request, &refreshed).await
+            }
+            other => other,
+        }
+    }
+
+    async fn list_models(&self, credentials: &Credentials) -> anyhow::Result<Vec<ModelInfo>> {
+        let creds = self.effective_credentials(credentials).await;
+        match self.inner.list_models(&creds).await {
+            Err(e) if is_auth_error(&e) && matches!(creds, Credentials::OAuth { .. }) => {
+                let refreshed = self.refresh(&creds).await?;
+                self.inner.list_models(&refreshed).await
+            }
+            other => other,
+        }
+    }
+
+    async fn count_tokens(&self, request: &CompletionRequest, credentials: &Credentials) -> anyhow::Result<usize> {
+        let creds = self.effective_credentials(credentials).await;
+        self.inner.count_tokens(request, &creds).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{ChunkStream, StubProvider};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_stream::StreamExt;
+
+    /// Accepts only the access token "fresh"; anything else gets a 401.
+    fn token_checking_provider() -> Arc<StubProvider> {
+        Arc::new(StubProvider::new(|_, credentials| {
+            let fresh = access_token(credentials) == Some("fresh");
+            async move {
+                if !fresh {
+                    anyhow::bail!("Mock API error 401 Unauthorized: expired");
+                }
+                let chunk = CompletionChunk {
+                    delta: Some("ok".into()),
+                    ..Default::default()
+                };
+                Ok(Box::pin(futures::stream::iter(vec![Ok(chunk)])) as ChunkStream)
+            }
+        }))
+    }
+
+    /// Serve a single HTTP response with the given JSON body.
+    async fn serve_token_once(body: &'static str) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut sock, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = sock.read(&mut buf).await;
+            let resp = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = sock.write_all(resp.as_bytes()).await;
+        });
+        format!("http://{addr}/token")
+    }
+
+    fn request() -> CompletionRequest {
+        CompletionRequest {
+            model: "m".into(),
+            messages: vec![],
+            max_tokens: 16,
+            temperature: None,
+            tools: None,
+            system: None,
+            thinking_budget_tokens: None,
+            enable_prompt_cache: false,
+            stop_sequences: None,
+            tool_choice: None,
+            response_format: None,
+            top_p: None,
+            top_k: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            seed: None,
+        }
+    }
+
+    #[test]
+    fn test_is_auth_error() {
+        assert!(is_auth_error(&anyhow::anyhow!("Anthropic API error 401 Unauthorized: {{}}")));
+        assert!(!is_auth_error(&anyhow::anyhow!("Anthropic API error 529 Overloaded")));
+    }
+
+    #[test]
+    fn test_token_store_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = TokenStore::new(dir.path().join("tokens.json"));
+        assert!(store.load("anthropic").is_none());
+
+        let creds = Credentials::OAuth {
+            access_token: "a".into(),
+            refresh_token: Some("r".into()),
+        };
+        store.save("anthropic", &creds).unwrap();
+        store
+            .save("openai", &Credentials::Token { token: "t".into() })
+            .unwrap();
+
+        match store.load("anthropic").unwrap() {
+            Credentials::OAuth {
+                access_token,
+                refresh_token,
+            } => {
+                assert_eq!(access_token, "a");
+                assert_eq!(refresh_token.as_deref(), Some("r"));
+            }
+            other => panic!("unexpected credentials: {other:?}"),
+        }
+        assert!(store.load("openai").is_some());
+    }
+
+    #[test]
+    fn test_token_store_ignores_tokens_from_old_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tokens.json");
+        let configured = |token: &str| Credentials::OAuth {
+            access_token: token.into(),
+            refresh_token: Some("r".into()),
+        };
+        let refreshed = Credentials::OAuth {
+            access_token: "refreshed".into(),
+            refresh_token: Some("r2".into()),
+        };
+        let store = TokenStore::new(path.clone()).with_source(&configured("a"));
+        store.save("anthropic", &refreshed).unwrap();
+        assert!(store.load("anthropic").is_some());
+
+        // The configured token changed since the refresh
+        let store = TokenStore::new(path).with_source(&configured("b"));
+        assert!(store.load("anthropic").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_refreshes_and_retries_on_401() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = TokenStore::new(dir.path().join("tokens.json"));
+        let token_url =
+            serve_token_once(r#"{"access_token":"fresh","refresh_token":"r2","expires_in":3600}"#)
+                .await;
+
+        let provider = OAuthRefreshProvider::new(
+            token_checking_provider(),
+            "mock".into(),
+            token_url,
+            Some("client".into()),
+            Some(store.clone()),
+        );
+        let stale = Credentials::OAuth {
+            access_token: "stale".into(),
+            refresh_token: Some("r1".into()),
+        };
+
+        let mut stream = provider.stream(&request(), &stale).await.unwrap();
+        let chunk = stream.next().await.unwrap().unwrap();
+        assert_eq!(chunk.delta.as_deref(), Some("ok"));
+
+        // Persisted for the next run
+        match store.load("mock").unwrap() {
+            Credentials::OAuth {
+                access_token,
+                refresh_token,
+            } => {
+                assert_eq!(access_token, "fresh");
+                assert_eq!(refresh_token.as_deref(), Some("r2"));
+            }
+            other => panic!("unexpected credentials: {other:?}"),
+        }
+
+        // Subsequent calls reuse the refreshed token without hitting the endpoint
+        assert!(provider.stream(&request(), &stale).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_no_refresh_token_surfaces_error() {
+        let provider = OAuthRefreshProvider::new(
+            token_checking_provider(),
+            "mock".into(),
+            "http://127.0.0.1:1/token".into(),
+            None,
+            None,
+        );
+        let creds = Credentials::OAuth {
+            access_token: "stale".into(),
+            refresh_token: None,
+        };
+        let err = provider.stream(&request(), &creds).await.err().unwrap();
+        assert!(err.to_string().contains("No refresh token"));
+    }
+}
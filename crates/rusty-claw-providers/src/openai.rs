@@ -2,7 +2,9 @@
 //!
 //! Implements streaming chat completions via OpenAI's `/v1/chat/completions` API.
 //! Also serves as the base for OpenRouter, Ollama, and other OpenAI-compatible providers.
+//! [`ApiStyle::OpenAiResponses`] targets `/v1/responses` instead.
 
+use std::collections::HashMap;
 use std::pin::Pin;
 
 use async_trait::async_trait;
@@ -16,14 +18,18 @@ use rusty_claw_core::session::TranscriptEntry;
 use rusty_claw_core::types::ContentBlock;
 
 use crate::sse::parse_sse_stream;
+use crate::tokens::{approximate_bpe_tokens, request_text};
 use crate::{
     ChunkUsage, CompletionChunk, CompletionRequest, Credentials, LlmProvider, ModelApi, ModelInfo,
-    ToolDefinition, ToolUseChunk,
+    ProviderHttpError, ResponseFormat, STRUCTURED_OUTPUT_NAME, ToolChoice, ToolDefinition,
+    ToolUseChunk,
 };
 
 const OPENAI_BASE_URL: &str = "https://api.openai.com";
 const OPENROUTER_BASE_URL: &str = "https://openrouter.ai/api";
 const OLLAMA_BASE_URL: &str = "http://localhost:11434";
+/// Azure OpenAI data-plane API version used when none is configured.
+pub const AZURE_DEFAULT_API_VERSION: &str = "2024-10-21";
 
 /// API style — determines minor behavior differences.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -31,6 +37,19 @@ pub enum ApiStyle {
     OpenAi,
     OpenRouter,
     Ollama,
+    /// GitHub Copilot chat endpoint (no `/v1` prefix, editor headers).
+    Copilot,
+    /// OpenAI Responses API (`/v1/responses`, `input` item array).
+    OpenAiResponses,
+    /// Azure OpenAI deployment (`/openai/deployments/{name}/...`, `api-key` header).
+    Azure,
+}
+
+impl ApiStyle {
+    /// Whether the server accepts `top_k`, which the OpenAI API lacks.
+    fn accepts_top_k(self) -> bool {
+        matches!(self, ApiStyle::Ollama | ApiStyle::OpenRouter)
+    }
 }
 
 pub struct OpenAiProvider {
@@ -38,6 +57,14 @@ pub struct OpenAiProvider {
     pub api_style: ApiStyle,
     provider_id: String,
     client: reqwest::Client,
+    /// Deployment name and API version (Azure only).
+    azure: Option<AzureDeployment>,
+}
+
+#[derive(Debug, Clone)]
+struct AzureDeployment {
+    deployment: String,
+    api_version: String,
 }
 
 impl OpenAiProvider {
@@ -50,6 +77,7 @@ impl OpenAiProvider {
             api_style: ApiStyle::OpenAi,
             provider_id: "openai".into(),
             client: reqwest::Client::new(),
+            azure: None,
         }
     }
 
@@ -62,6 +90,7 @@ impl OpenAiProvider {
             api_style: ApiStyle::OpenRouter,
             provider_id: "openrouter".into(),
             client: reqwest::Client::new(),
+            azure: None,
         }
     }
 
@@ -74,6 +103,62 @@ impl OpenAiProvider {
             api_style: ApiStyle::Ollama,
             provider_id: "ollama".into(),
             client: reqwest::Client::new(),
+            azure: None,
+        }
+    }
+
+    pub fn copilot(base_url: Option<&str>) -> Self {
+        Self {
+            base_url: base_url
+                .unwrap_or(crate::copilot::COPILOT_API_BASE_URL)
+                .trim_end_matches('/')
+                .to_string(),
+            api_style: ApiStyle::Copilot,
+            provider_id: "copilot".into(),
+            client: reqwest::Client::new(),
+            azure: None,
+        }
+    }
+
+    /// Replace the HTTP client, whichever OpenAI-compatible backend this
+    /// instance targets.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// OpenAI via the Responses API rather than Chat Completions.
+    pub fn openai_responses(base_url: Option<&str>) -> Self {
+        Self {
+            api_style: ApiStyle::OpenAiResponses,
+            ..Self::openai(base_url)
+        }
+    }
+
+    /// Azure OpenAI deployment on the resource `endpoint`
+    /// (e.g. `https://my-resource.openai.azure.com`).
+    pub fn azure(endpoint: &str, deployment: &str, api_version: Option<&str>) -> Self {
+        Self {
+            base_url: endpoint.trim_end_matches('/').to_string(),
+            api_style: ApiStyle::Azure,
+            provider_id: "azure".into(),
+            client: reqwest::Client::new(),
+            azure: Some(AzureDeployment {
+                deployment: deployment.to_string(),
+                api_version: api_version.unwrap_or(AZURE_DEFAULT_API_VERSION).to_string(),
+            }),
+        }
+    }
+
+    /// Chat completions URL for the given base URL.
+    fn completions_url(&self, base_url: &str) -> String {
+        match (self.api_style, &self.azure) {
+            (ApiStyle::Azure, Some(azure)) => format!(
+                "{base_url}/openai/deployments/{}/chat/completions?api-version={}",
+                azure.deployment, azure.api_version
+            ),
+            (ApiStyle::Copilot, _) => format!("{base_url}/chat/completions"),
+            _ => format!("{base_url}/v1/chat/completions"),
         }
     }
 }
@@ -92,6 +177,23 @@ struct OpenAiRequest {
     tools: Option<Vec<serde_json::Value>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     stream_options: Option<StreamOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    /// Not part of the OpenAI API; only sent to compatible servers that take it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -105,6 +207,8 @@ struct ChatCompletionChunk {
     choices: Vec<ChunkChoice>,
     #[serde(default)]
     usage: Option<OpenAiUsage>,
+    #[serde(default)]
+    system_fingerprint: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -173,10 +277,27 @@ impl LlmProvider for OpenAiProvider {
     }
 
     fn api(&self) -> ModelApi {
-        ModelApi::OpenAiCompletions
+        match self.api_style {
+            ApiStyle::OpenAiResponses => ModelApi::OpenAiResponses,
+            _ => ModelApi::OpenAiCompletions,
+        }
     }
 
     fn format_tools(&self, tools: &[ToolDefinition]) -> Vec<serde_json::Value> {
+        if self.api_style == ApiStyle::OpenAiResponses {
+            // Responses API tools are flat: no nested "function" object
+            return tools
+                .iter()
+                .map(|t| {
+                    json!({
+                        "type": "function",
+                        "name": t.name,
+                        "description": t.description,
+                        "parameters": t.parameters_schema,
+                    })
+                })
+                .collect();
+        }
         tools
             .iter()
             .map(|t| {
@@ -193,6 +314,10 @@ impl LlmProvider for OpenAiProvider {
     }
 
     fn format_messages(&self, transcript: &[TranscriptEntry]) -> Vec<serde_json::Value> {
+        if self.api_style == ApiStyle::OpenAiResponses {
+            return format_responses_input(transcript);
+        }
+
         let mut messages: Vec<serde_json::Value> = Vec::new();
 
         for entry in transcript {
@@ -301,11 +426,120 @@ impl LlmProvider for OpenAiProvider {
         request: &CompletionRequest,
         credentials: &Credentials,
     ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<CompletionChunk>> + Send>>> {
-        let api_key = match credentials {
-            Credentials::ApiKey { api_key } => api_key.clone(),
-            _ => anyhow::bail!("OpenAI-compatible providers require ApiKey credentials"),
-        };
+        let api_key = bearer_token(credentials)?;
+
+        if self.api_style == ApiStyle::OpenAiResponses {
+            return self.stream_responses(request, &api_key).await;
+        }
+        self.stream_to(&self.base_url, request, &api_key).await
+    }
+
+    /// No tiktoken build is bundled, so this uses the approximate BPE count.
+    async fn count_tokens(
+        &self,
+        request: &CompletionRequest,
+        _credentials: &Credentials,
+    ) -> anyhow::Result<usize> {
+        Ok(approximate_bpe_tokens(&request_text(request)))
+    }
+
+    async fn list_models(&self, credentials: &Credentials) -> anyhow::Result<Vec<ModelInfo>> {
+        let api_key = bearer_token(credentials)?;
+
+        // An Azure provider serves exactly its configured deployment
+        if let Some(azure) = &self.azure {
+            return Ok(vec![ModelInfo {
+                id: azure.deployment.clone(),
+                name: azure.deployment.clone(),
+                api: self.api(),
+                reasoning: false,
+                context_window: 128_000,
+                max_tokens: 4_096,
+            }]);
+        }
 
+        let mut req = self
+            .client
+            .get(format!("{}/v1/models", self.base_url))
+            .header("content-type", "application/json");
+
+        if self.api_style != ApiStyle::Ollama {
+            req = req.header("authorization", format!("Bearer {api_key}"));
+        }
+
+        let response = req.send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Failed to list models {status}: {body}");
+        }
+
+        let body: ModelsResponse = response.json().await?;
+        let api = self.api();
+        Ok(body
+            .data
+            .into_iter()
+            .map(|m| ModelInfo {
+                name: m.id.clone(),
+                id: m.id,
+                api,
+                reasoning: false,
+                context_window: 128_000,
+                max_tokens: 4_096,
+            })
+            .collect())
+    }
+}
+
+/// Translate a [`ToolChoice`] into OpenAI's `tool_choice`. The Responses API
+/// names a forced function at the top level rather than under `function`.
+fn openai_tool_choice(request: &CompletionRequest, responses: bool) -> Option<serde_json::Value> {
+    // tool_choice without tools is a request error
+    request.tools.as_ref().filter(|t| !t.is_empty())?;
+    Some(match request.tool_choice.as_ref()? {
+        ToolChoice::Auto => json!("auto"),
+        ToolChoice::None => json!("none"),
+        ToolChoice::Required => json!("required"),
+        ToolChoice::Tool { name } if responses => json!({"type": "function", "name": name}),
+        ToolChoice::Tool { name } => json!({"type": "function", "function": {"name": name}}),
+    })
+}
+
+/// Translate a [`ResponseFormat`] into the Chat Completions `response_format`
+/// or, for the Responses API, the `text.format` object.
+fn openai_response_format(request: &CompletionRequest, responses: bool) -> Option<serde_json::Value> {
+    Some(match request.response_format.as_ref()? {
+        ResponseFormat::JsonSchema { schema } if responses => json!({
+            "format": {"type": "json_schema", "name": STRUCTURED_OUTPUT_NAME, "schema": schema},
+        }),
+        ResponseFormat::JsonSchema { schema } => json!({
+            "type": "json_schema",
+            "json_schema": {"name": STRUCTURED_OUTPUT_NAME, "schema": schema},
+        }),
+    })
+}
+
+/// Bearer token for OpenAI-compatible APIs (API key or OAuth access token).
+fn bearer_token(credentials: &Credentials) -> anyhow::Result<String> {
+    match credentials {
+        Credentials::ApiKey { api_key } => Ok(api_key.clone()),
+        Credentials::OAuth { access_token, .. } => Ok(access_token.clone()),
+        Credentials::Token { token } => Ok(token.clone()),
+        _ => anyhow::bail!("OpenAI-compatible providers require ApiKey or OAuth credentials"),
+    }
+}
+
+impl OpenAiProvider {
+    /// Stream a chat completion against an explicit base URL.
+    ///
+    /// Used by wrappers (e.g. Copilot) whose endpoint is only known at
+    /// request time.
+    pub(crate) async fn stream_to(
+        &self,
+        base_url: &str,
+        request: &CompletionRequest,
+        api_key: &str,
+    ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<CompletionChunk>> + Send>>> {
         // Build system message if present
         let mut messages = Vec::new();
         if let Some(ref system) = request.system {
@@ -323,29 +557,44 @@ impl LlmProvider for OpenAiProvider {
             stream_options: Some(StreamOptions {
                 include_usage: true,
             }),
+            stop: request.stop_sequences.clone(),
+            tool_choice: openai_tool_choice(request, false),
+            response_format: openai_response_format(request, false),
+            top_p: request.top_p,
+            top_k: request.top_k.filter(|_| self.api_style.accepts_top_k()),
+            frequency_penalty: request.frequency_penalty,
+            presence_penalty: request.presence_penalty,
+            seed: request.seed,
         };
 
-        debug!(model = %body.model, base_url = %self.base_url, "Streaming OpenAI-compatible API");
+        debug!(model = %body.model, base_url, "Streaming OpenAI-compatible API");
 
         let mut req_builder = self
             .client
-            .post(format!("{}/v1/chat/completions", self.base_url))
+            .post(self.completions_url(base_url))
             .header("content-type", "application/json");
 
         // Auth differs by style
-        if self.api_style != ApiStyle::Ollama {
-            req_builder = req_builder.header("authorization", format!("Bearer {api_key}"));
+        match self.api_style {
+            ApiStyle::Ollama => {}
+            ApiStyle::Azure => req_builder = req_builder.header("api-key", api_key),
+            _ => {
+                req_builder = req_builder.header("authorization", format!("Bearer {api_key}"));
+            }
         }
         if self.api_style == ApiStyle::OpenRouter {
             req_builder = req_builder.header("HTTP-Referer", "https://rusty-claw.dev");
         }
+        if self.api_style == ApiStyle::Copilot {
+            for (name, value) in crate::copilot::editor_headers() {
+                req_builder = req_builder.header(name, value);
+            }
+        }
 
         let response = req_builder.json(&body).send().await?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            anyhow::bail!("OpenAI API error {status}: {body}");
+            return Err(ProviderHttpError::from_response("OpenAI", response).await.into());
         }
 
         let sse_stream = parse_sse_stream(response);
@@ -378,6 +627,8 @@ impl LlmProvider for OpenAiProvider {
                                             }),
                                             usage: None,
                                             stop_reason: None,
+                                            thinking_signature: None,
+                                            system_fingerprint: None,
                                         })
                                         .collect();
 
@@ -409,8 +660,11 @@ impl LlmProvider for OpenAiProvider {
                                     usage: Some(ChunkUsage {
                                         input_tokens: Some(usage.prompt_tokens),
                                         output_tokens: Some(usage.completion_tokens),
+                                        ..Default::default()
                                     }),
                                     stop_reason: None,
+                                    thinking_signature: None,
+                                    system_fingerprint: chunk.system_fingerprint,
                                 };
                                 return Some((Ok(c), state));
                             }
@@ -455,6 +709,8 @@ impl LlmProvider for OpenAiProvider {
                                         tool_use: None,
                                         usage: None,
                                         stop_reason: None,
+                                        thinking_signature: None,
+                                        system_fingerprint: None,
                                     };
                                     return Some((Ok(c), state));
                                 }
@@ -479,6 +735,8 @@ impl LlmProvider for OpenAiProvider {
                                         } else {
                                             None
                                         },
+                                        thinking_signature: None,
+                                        system_fingerprint: chunk.system_fingerprint.clone(),
                                     };
                                     return Some((Ok(c), state));
                                 }
@@ -489,6 +747,8 @@ impl LlmProvider for OpenAiProvider {
                                     tool_use: None,
                                     usage: None,
                                     stop_reason: Some(reason.clone()),
+                                    thinking_signature: None,
+                                    system_fingerprint: chunk.system_fingerprint.clone(),
                                 };
                                 return Some((Ok(c), state));
                             }
@@ -511,6 +771,8 @@ impl LlmProvider for OpenAiProvider {
                                     }),
                                     usage: None,
                                     stop_reason: None,
+                                    thinking_signature: None,
+                                    system_fingerprint: None,
                                 };
                                 return Some((Ok(c), state));
                             }
@@ -523,48 +785,418 @@ impl LlmProvider for OpenAiProvider {
 
         Ok(Box::pin(chunk_stream))
     }
+}
 
-    async fn list_models(&self, credentials: &Credentials) -> anyhow::Result<Vec<ModelInfo>> {
-        let api_key = match credentials {
-            Credentials::ApiKey { api_key } => api_key.clone(),
-            _ => anyhow::bail!("OpenAI-compatible providers require ApiKey credentials"),
-        };
+struct OpenAiChunkState {
+    sse: Pin<Box<dyn Stream<Item = anyhow::Result<crate::sse::SseEvent>> + Send>>,
+    tool_calls: Vec<ToolCallAccumulator>,
+}
 
-        let mut req = self
-            .client
-            .get(format!("{}/v1/models", self.base_url))
-            .header("content-type", "application/json");
+// --- Responses API ---
 
-        if self.api_style != ApiStyle::Ollama {
-            req = req.header("authorization", format!("Bearer {api_key}"));
+#[derive(Debug, Serialize)]
+struct ResponsesRequest {
+    model: String,
+    input: Vec<serde_json::Value>,
+    max_output_tokens: u32,
+    stream: bool,
+    /// Don't keep server-side state; the transcript is replayed every turn.
+    store: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instructions: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<serde_json::Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    /// Extra output to return; `reasoning.encrypted_content` lets reasoning
+    /// be replayed without server-side state.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    include: Vec<&'static str>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponsesEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    delta: Option<String>,
+    #[serde(default)]
+    item_id: Option<String>,
+    #[serde(default)]
+    item: Option<ResponsesItem>,
+    #[serde(default)]
+    response: Option<ResponsesObject>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponsesItem {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    call_id: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    arguments: Option<String>,
+    #[serde(default)]
+    encrypted_content: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponsesObject {
+    #[serde(default)]
+    usage: Option<ResponsesUsage>,
+    #[serde(default)]
+    error: Option<ResponsesError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponsesUsage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+    #[serde(default)]
+    input_tokens_details: Option<ResponsesInputDetails>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponsesInputDetails {
+    #[serde(default)]
+    cached_tokens: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponsesError {
+    #[serde(default)]
+    message: String,
+}
+
+/// Map a transcript to the Responses API `input` item array.
+fn format_responses_input(transcript: &[TranscriptEntry]) -> Vec<serde_json::Value> {
+    let mut input = Vec::new();
+
+    for entry in transcript {
+        match entry {
+            TranscriptEntry::User { content, .. } => {
+                let parts: Vec<serde_json::Value> = content
+                    .iter()
+                    .filter_map(|b| match b {
+                        ContentBlock::Text { text } => {
+                            Some(json!({"type": "input_text", "text": text}))
+                        }
+                        ContentBlock::Image { source } => {
+                            let url = if source.source_type == "base64" {
+                                format!("data:{};base64,{}", source.media_type, source.data)
+                            } else {
+                                source.data.clone()
+                            };
+                            Some(json!({"type": "input_image", "image_url": url}))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                if !parts.is_empty() {
+                    input.push(json!({"role": "user", "content": parts}));
+                }
+            }
+            TranscriptEntry::Assistant { content, .. } => {
+                // Encrypted reasoning leads the turn, with its summary
+                for block in entry.thinking_blocks() {
+                    let Some(encrypted) = block.signature else {
+                        continue;
+                    };
+                    let summary = if block.thinking.is_empty() {
+                        json!([])
+                    } else {
+                        json!([{"type": "summary_text", "text": block.thinking}])
+                    };
+                    input.push(json!({
+                        "type": "reasoning",
+                        "summary": summary,
+                        "encrypted_content": encrypted,
+                    }));
+                }
+                // Text and function calls are separate items, in order
+                for block in content {
+                    match block {
+                        ContentBlock::Text { text } if !text.is_empty() => {
+                            input.push(json!({
+                                "role": "assistant",
+                                "content": [{"type": "output_text", "text": text}],
+                            }));
+                        }
+                        ContentBlock::ToolUse { id, name, input: args } => {
+                            input.push(json!({
+                                "type": "function_call",
+                                "call_id": id,
+                                "name": name,
+                                "arguments": args.to_string(),
+                            }));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            TranscriptEntry::ToolResult {
+                tool_use_id,
+                content,
+                ..
+            } => {
+                input.push(json!({
+                    "type": "function_call_output",
+                    "call_id": tool_use_id,
+                    "output": content,
+                }));
+            }
+            TranscriptEntry::ToolCall { .. } | TranscriptEntry::System { .. } => {}
         }
+    }
+
+    input
+}
+
+/// Reasoning effort for a thinking budget (Responses API has no token budget).
+fn reasoning_effort(budget_tokens: u32) -> &'static str {
+    match budget_tokens {
+        0..=2048 => "low",
+        2049..=8192 => "medium",
+        _ => "high",
+    }
+}
+
+/// In-flight function calls and turn state for a Responses stream.
+#[derive(Debug, Default)]
+struct ResponsesDecoder {
+    /// Function calls keyed by output item ID.
+    calls: HashMap<String, ToolCallAccumulator>,
+    saw_function_call: bool,
+}
+
+impl ResponsesDecoder {
+    /// Translate a single Responses SSE event into a chunk.
+    ///
+    /// Returns `Ok(None)` for events that only update internal state.
+    fn process(&mut self, event: ResponsesEvent) -> anyhow::Result<Option<CompletionChunk>> {
+        match event.kind.as_str() {
+            "response.output_text.delta" => Ok(event.delta.map(|d| CompletionChunk {
+                delta: Some(d),
+                ..Default::default()
+            })),
+            "response.reasoning_summary_text.delta" => Ok(event.delta.map(|d| CompletionChunk {
+                thinking: Some(d),
+                ..Default::default()
+            })),
+            "response.output_item.added" => {
+                if let Some(item) = event.item.filter(|i| i.kind == "function_call") {
+                    let key = item.id.clone().unwrap_or_default();
+                    self.calls.insert(
+                        key,
+                        ToolCallAccumulator {
+                            id: item.call_id.unwrap_or_default(),
+                            name: item.name.unwrap_or_default(),
+                            arguments: item.arguments.unwrap_or_default(),
+                        },
+                    );
+                }
+                Ok(None)
+            }
+            "response.function_call_arguments.delta" => {
+                let key = event.item_id.unwrap_or_default();
+                if let (Some(acc), Some(delta)) = (self.calls.get_mut(&key), event.delta) {
+                    acc.arguments.push_str(&delta);
+                }
+                Ok(None)
+            }
+            "response.output_item.done" => {
+                let Some(item) = event.item else {
+                    return Ok(None);
+                };
+                if item.kind == "reasoning" {
+                    // Nothing is stored server-side; the encrypted reasoning
+                    // is kept like a thinking signature and sent back
+                    return Ok(item.encrypted_content.map(|encrypted| CompletionChunk {
+                        thinking_signature: Some(encrypted),
+                        ..Default::default()
+                    }));
+                }
+                if item.kind != "function_call" {
+                    return Ok(None);
+                }
+                let acc = self.calls.remove(item.id.as_deref().unwrap_or_default());
+                // The done event carries the final item; deltas are the fallback
+                let arguments = item
+                    .arguments
+                    .filter(|a| !a.is_empty())
+                    .or_else(|| acc.as_ref().map(|a| a.arguments.clone()))
+                    .filter(|a| !a.is_empty())
+                    .unwrap_or_else(|| "{}".into());
+                self.saw_function_call = true;
+                Ok(Some(CompletionChunk {
+                    tool_use: Some(ToolUseChunk {
+                        id: item
+                            .call_id
+                            .or_else(|| acc.as_ref().map(|a| a.id.clone()))
+                            .unwrap_or_default(),
+                        name: item
+                            .name
+                            .or_else(|| acc.map(|a| a.name))
+                            .unwrap_or_default(),
+                        input_json: arguments,
+                    }),
+                    ..Default::default()
+                }))
+            }
+            "response.completed" | "response.incomplete" => {
+                // Normalize to Chat Completions stop reasons so
+                // `is_tool_use_stop` works for both styles.
+                let stop_reason = if self.saw_function_call {
+                    "tool_calls"
+                } else if event.kind == "response.incomplete" {
+                    "length"
+                } else {
+                    "stop"
+                };
+                let usage = event.response.and_then(|r| r.usage).map(|u| {
+                    // OpenAI counts cached tokens inside input_tokens; report
+                    // them separately like Anthropic does.
+                    let cached = u.input_tokens_details.and_then(|d| d.cached_tokens);
+                    ChunkUsage {
+                        input_tokens: Some(u.input_tokens.saturating_sub(cached.unwrap_or(0))),
+                        output_tokens: Some(u.output_tokens),
+                        cache_read_input_tokens: cached,
+                        ..Default::default()
+                    }
+                });
+                Ok(Some(CompletionChunk {
+                    usage,
+                    stop_reason: Some(stop_reason.into()),
+                    ..Default::default()
+                }))
+            }
+            "response.failed" => {
+                let message = event
+                    .response
+                    .and_then(|r| r.error)
+                    .map(|e| e.message)
+                    .unwrap_or_else(|| "unknown error".into());
+                anyhow::bail!("OpenAI response failed: {message}")
+            }
+            "error" => anyhow::bail!(
+                "OpenAI stream error: {}",
+                event.message.unwrap_or_else(|| "unknown error".into())
+            ),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl OpenAiProvider {
+    /// Stream a response via the Responses API.
+    async fn stream_responses(
+        &self,
+        request: &CompletionRequest,
+        api_key: &str,
+    ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<CompletionChunk>> + Send>>> {
+        let body = ResponsesRequest {
+            model: request.model.clone(),
+            input: request.messages.clone(),
+            max_output_tokens: request.max_tokens,
+            stream: true,
+            store: false,
+            instructions: request.system.clone(),
+            temperature: request.temperature,
+            tools: request.tools.clone(),
+            reasoning: request.thinking_budget_tokens.map(|budget| {
+                json!({"effort": reasoning_effort(budget), "summary": "auto"})
+            }),
+            tool_choice: openai_tool_choice(request, true),
+            text: openai_response_format(request, true),
+            top_p: request.top_p,
+            include: if request.thinking_budget_tokens.is_some() {
+                vec!["reasoning.encrypted_content"]
+            } else {
+                vec![]
+            },
+        };
+
+        debug!(model = %body.model, base_url = %self.base_url, "Streaming OpenAI Responses API");
+
+        let response = self
+            .client
+            .post(format!("{}/v1/responses", self.base_url))
+            .header("content-type", "application/json")
+            .header("authorization", format!("Bearer {api_key}"))
+            .json(&body)
+            .send()
+            .await?;
 
-        let response = req.send().await?;
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            anyhow::bail!("Failed to list models {status}: {body}");
+            return Err(ProviderHttpError::from_response("OpenAI", response).await.into());
         }
 
-        let body: ModelsResponse = response.json().await?;
-        Ok(body
-            .data
-            .into_iter()
-            .map(|m| ModelInfo {
-                name: m.id.clone(),
-                id: m.id,
-                api: ModelApi::OpenAiCompletions,
-                reasoning: false,
-                context_window: 128_000,
-                max_tokens: 4_096,
-            })
-            .collect())
+        let sse_stream = parse_sse_stream(response);
+
+        let chunk_stream = futures::stream::unfold(
+            ResponsesChunkState {
+                sse: Box::pin(sse_stream),
+                decoder: ResponsesDecoder::default(),
+                done: false,
+            },
+            |mut state| async move {
+                if state.done {
+                    return None;
+                }
+                loop {
+                    match state.sse.next().await {
+                        Some(Ok(sse_event)) => {
+                            let event: ResponsesEvent =
+                                match serde_json::from_str(sse_event.data.trim()) {
+                                    Ok(e) => e,
+                                    Err(e) => {
+                                        trace!(%e, data = %sse_event.data, "Failed to parse Responses event");
+                                        continue;
+                                    }
+                                };
+                            match state.decoder.process(event) {
+                                Ok(Some(chunk)) => return Some((Ok(chunk), state)),
+                                Ok(None) => continue,
+                                Err(e) => {
+                                    state.done = true;
+                                    return Some((Err(e), state));
+                                }
+                            }
+                        }
+                        Some(Err(e)) => {
+                            state.done = true;
+                            return Some((Err(e), state));
+                        }
+                        None => return None,
+                    }
+                }
+            },
+        );
+
+        Ok(Box::pin(chunk_stream))
     }
 }
 
-struct OpenAiChunkState {
+struct ResponsesChunkState {
     sse: Pin<Box<dyn Stream<Item = anyhow::Result<crate::sse::SseEvent>> + Send>>,
-    tool_calls: Vec<ToolCallAccumulator>,
+    decoder: ResponsesDecoder,
+    done: bool,
 }
 
 #[cfg(test)]
@@ -593,6 +1225,35 @@ mod tests {
         assert_eq!(provider.base_url, OLLAMA_BASE_URL);
     }
 
+    #[test]
+    fn test_azure_provider_creation() {
+        let provider =
+            OpenAiProvider::azure("https://my-res.openai.azure.com/", "gpt-4o-prod", None);
+        assert_eq!(provider.id(), "azure");
+        assert_eq!(provider.api(), ModelApi::OpenAiCompletions);
+        assert_eq!(
+            provider.completions_url(&provider.base_url),
+            format!(
+                "https://my-res.openai.azure.com/openai/deployments/gpt-4o-prod/chat/completions?api-version={AZURE_DEFAULT_API_VERSION}"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_azure_lists_its_deployment() {
+        let provider = OpenAiProvider::azure(
+            "https://my-res.openai.azure.com",
+            "gpt-4o-prod",
+            Some("2025-01-01-preview"),
+        );
+        let credentials = Credentials::ApiKey {
+            api_key: "k".into(),
+        };
+        let models = provider.list_models(&credentials).await.unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].id, "gpt-4o-prod");
+    }
+
     #[test]
     fn test_custom_base_url() {
         let provider = OpenAiProvider::openai(Some("https://my-proxy.example.com/"));
@@ -619,6 +1280,52 @@ mod tests {
         assert!(formatted[0].get("input_schema").is_none());
     }
 
+    #[test]
+    fn test_top_k_only_for_compatible_servers() {
+        assert!(!ApiStyle::OpenAi.accepts_top_k());
+        assert!(!ApiStyle::OpenAiResponses.accepts_top_k());
+        assert!(ApiStyle::Ollama.accepts_top_k());
+        assert!(ApiStyle::OpenRouter.accepts_top_k());
+    }
+
+    #[test]
+    fn test_openai_response_format() {
+        let mut request = CompletionRequest::default();
+        assert!(openai_response_format(&request, false).is_none());
+
+        request.response_format = Some(ResponseFormat::JsonSchema {
+            schema: json!({"type": "object"}),
+        });
+        let chat = openai_response_format(&request, false).unwrap();
+        assert_eq!(chat["type"], "json_schema");
+        assert_eq!(chat["json_schema"]["name"], STRUCTURED_OUTPUT_NAME);
+        assert_eq!(chat["json_schema"]["schema"]["type"], "object");
+        let responses = openai_response_format(&request, true).unwrap();
+        assert_eq!(responses["format"]["type"], "json_schema");
+        assert_eq!(responses["format"]["schema"]["type"], "object");
+    }
+
+    #[test]
+    fn test_openai_tool_choice() {
+        let mut request = CompletionRequest {
+            tool_choice: Some(ToolChoice::Tool {
+                name: "submit_answer".into(),
+            }),
+            ..Default::default()
+        };
+        // Dropped when no tools are offered
+        assert!(openai_tool_choice(&request, false).is_none());
+
+        request.tools = Some(vec![json!({"type": "function"})]);
+        let chat = openai_tool_choice(&request, false).unwrap();
+        assert_eq!(chat["function"]["name"], "submit_answer");
+        let responses = openai_tool_choice(&request, true).unwrap();
+        assert_eq!(responses["name"], "submit_answer");
+
+        request.tool_choice = Some(ToolChoice::Required);
+        assert_eq!(openai_tool_choice(&request, false).unwrap(), "required");
+    }
+
     #[test]
     fn test_is_tool_use_stop_openai() {
         let provider = OpenAiProvider::openai(None);
@@ -645,6 +1352,9 @@ mod tests {
                     input: json!({"command": "ls"}),
                 }],
                 usage: None,
+                thinking: None,
+                thinking_signature: None,
+                thinking_blocks: Vec::new(),
                 timestamp: Utc::now(),
             },
             TranscriptEntry::ToolCall {
@@ -688,6 +1398,13 @@ mod tests {
         assert_eq!(tc.function.as_ref().unwrap().name.as_deref(), Some("exec"));
     }
 
+    #[test]
+    fn test_chunk_deserialization_system_fingerprint() {
+        let json = r#"{"id":"chatcmpl-1","system_fingerprint":"fp_44709d6fcb","choices":[]}"#;
+        let chunk: ChatCompletionChunk = serde_json::from_str(json).unwrap();
+        assert_eq!(chunk.system_fingerprint.as_deref(), Some("fp_44709d6fcb"));
+    }
+
     #[test]
     fn test_chunk_deserialization_finish_reason() {
         let json =
@@ -746,4 +1463,177 @@ mod tests {
         );
         assert!(url.contains("aWtlcG5n"));
     }
+
+    #[test]
+    fn test_responses_provider_creation() {
+        let provider = OpenAiProvider::openai_responses(None);
+        assert_eq!(provider.id(), "openai");
+        assert_eq!(provider.api(), ModelApi::OpenAiResponses);
+        assert_eq!(provider.base_url, OPENAI_BASE_URL);
+
+        let tools = provider.format_tools(&[ToolDefinition {
+            name: "exec".into(),
+            description: "Run a shell command".into(),
+            parameters_schema: json!({"type": "object"}),
+        }]);
+        assert_eq!(tools[0]["name"], "exec");
+        assert!(tools[0].get("function").is_none());
+    }
+
+    #[test]
+    fn test_format_responses_input() {
+        use chrono::Utc;
+        let provider = OpenAiProvider::openai_responses(None);
+        let transcript = vec![
+            TranscriptEntry::User {
+                content: vec![ContentBlock::Text {
+                    text: "Run ls".into(),
+                }],
+                timestamp: Utc::now(),
+            },
+            TranscriptEntry::Assistant {
+                content: vec![
+                    ContentBlock::Text {
+                        text: "Sure.".into(),
+                    },
+                    ContentBlock::ToolUse {
+                        id: "call_1".into(),
+                        name: "exec".into(),
+                        input: json!({"command": "ls"}),
+                    },
+                ],
+                usage: None,
+                thinking: None,
+                thinking_signature: None,
+                thinking_blocks: Vec::new(),
+                timestamp: Utc::now(),
+            },
+            TranscriptEntry::ToolResult {
+                tool_use_id: "call_1".into(),
+                tool: "exec".into(),
+                content: "file1.txt".into(),
+                is_error: false,
+                timestamp: Utc::now(),
+            },
+        ];
+
+        let input = provider.format_messages(&transcript);
+        assert_eq!(input.len(), 4);
+        assert_eq!(input[0]["content"][0]["type"], "input_text");
+        assert_eq!(input[1]["content"][0]["type"], "output_text");
+        assert_eq!(input[2]["type"], "function_call");
+        assert_eq!(input[2]["call_id"], "call_1");
+        assert_eq!(input[2]["arguments"], r#"{"command":"ls"}"#);
+        assert_eq!(input[3]["type"], "function_call_output");
+        assert_eq!(input[3]["output"], "file1.txt");
+    }
+
+    #[test]
+    fn test_responses_encrypted_reasoning_round_trip() {
+        use chrono::Utc;
+        let mut decoder = ResponsesDecoder::default();
+        let summary = decoder
+            .process(responses_event(
+                r#"{"type":"response.reasoning_summary_text.delta","item_id":"rs_1","delta":"Plan"}"#,
+            ))
+            .unwrap()
+            .unwrap();
+        assert_eq!(summary.thinking.as_deref(), Some("Plan"));
+        let reasoning = decoder
+            .process(responses_event(
+                r#"{"type":"response.output_item.done","item":{"type":"reasoning","id":"rs_1","encrypted_content":"gAAA=="}}"#,
+            ))
+            .unwrap()
+            .unwrap();
+        assert_eq!(reasoning.thinking_signature.as_deref(), Some("gAAA=="));
+
+        let transcript = vec![TranscriptEntry::Assistant {
+            content: vec![ContentBlock::Text {
+                text: "Done.".into(),
+            }],
+            usage: None,
+            thinking: summary.thinking,
+            thinking_signature: reasoning.thinking_signature,
+            thinking_blocks: Vec::new(),
+            timestamp: Utc::now(),
+        }];
+        let input = format_responses_input(&transcript);
+        assert_eq!(input.len(), 2);
+        assert_eq!(input[0]["type"], "reasoning");
+        assert_eq!(input[0]["encrypted_content"], "gAAA==");
+        assert_eq!(input[0]["summary"][0]["text"], "Plan");
+        assert_eq!(input[1]["role"], "assistant");
+    }
+
+    fn responses_event(json: &str) -> ResponsesEvent {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_responses_decoder_text_and_function_call() {
+        let mut decoder = ResponsesDecoder::default();
+
+        let text = decoder
+            .process(responses_event(
+                r#"{"type":"response.output_text.delta","item_id":"msg_1","delta":"Hi"}"#,
+            ))
+            .unwrap()
+            .unwrap();
+        assert_eq!(text.delta.as_deref(), Some("Hi"));
+
+        assert!(decoder
+            .process(responses_event(
+                r#"{"type":"response.output_item.added","output_index":1,"item":{"type":"function_call","id":"fc_1","call_id":"call_1","name":"exec","arguments":""}}"#,
+            ))
+            .unwrap()
+            .is_none());
+        for delta in [r#"{\"command\""#, r#":\"ls\"}"#] {
+            let json = format!(
+                r#"{{"type":"response.function_call_arguments.delta","item_id":"fc_1","delta":"{delta}"}}"#
+            );
+            assert!(decoder.process(responses_event(&json)).unwrap().is_none());
+        }
+
+        // Done event without arguments falls back to the accumulated deltas
+        let call = decoder
+            .process(responses_event(
+                r#"{"type":"response.output_item.done","item":{"type":"function_call","id":"fc_1","call_id":"call_1","name":"exec"}}"#,
+            ))
+            .unwrap()
+            .unwrap();
+        let tool_use = call.tool_use.unwrap();
+        assert_eq!(tool_use.id, "call_1");
+        assert_eq!(tool_use.name, "exec");
+        assert_eq!(tool_use.input_json, r#"{"command":"ls"}"#);
+
+        let done = decoder
+            .process(responses_event(
+                r#"{"type":"response.completed","response":{"status":"completed","usage":{"input_tokens":12,"output_tokens":5,"input_tokens_details":{"cached_tokens":8}}}}"#,
+            ))
+            .unwrap()
+            .unwrap();
+        assert_eq!(done.stop_reason.as_deref(), Some("tool_calls"));
+        let usage = done.usage.unwrap();
+        assert_eq!(usage.input_tokens, Some(4));
+        assert_eq!(usage.output_tokens, Some(5));
+        assert_eq!(usage.cache_read_input_tokens, Some(8));
+        assert!(OpenAiProvider::openai_responses(None).is_tool_use_stop("tool_calls"));
+    }
+
+    #[test]
+    fn test_responses_decoder_errors() {
+        let mut decoder = ResponsesDecoder::default();
+        let err = decoder
+            .process(responses_event(
+                r#"{"type":"response.failed","response":{"error":{"code":"server_error","message":"boom"}}}"#,
+            ))
+            .unwrap_err();
+        assert!(err.to_string().contains("boom"));
+
+        let done = decoder
+            .process(responses_event(r#"{"type":"response.incomplete","response":{}}"#))
+            .unwrap()
+            .unwrap();
+        assert_eq!(done.stop_reason.as_deref(), Some("length"));
+    }
 }
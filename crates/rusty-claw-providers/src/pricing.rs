@@ -0,0 +1,106 @@
+//! Cost estimation from the configured pricing table.
+
+use std::collections::HashMap;
+
+use rusty_claw_core::config::ModelPricing;
+
+use crate::ChunkUsage;
+
+/// Look up pricing for a model: an exact match wins, otherwise the longest
+/// key that is a prefix of the model ID (so `claude-sonnet-4` covers dated
+/// snapshots).
+pub fn find_pricing<'a>(
+    pricing: &'a HashMap<String, ModelPricing>,
+    model: &str,
+) -> Option<&'a ModelPricing> {
+    pricing.get(model).or_else(|| {
+        pricing
+            .iter()
+            .filter(|(key, _)| model.starts_with(key.as_str()))
+            .max_by_key(|(key, _)| key.len())
+            .map(|(_, p)| p)
+    })
+}
+
+/// Estimate the USD cost of a single LLM call.
+///
+/// Returns `None` when the model has no pricing entry.
+pub fn estimate_cost(
+    pricing: &HashMap<String, ModelPricing>,
+    model: &str,
+    usage: &ChunkUsage,
+) -> Option<f64> {
+    let p = find_pricing(pricing, model)?;
+    let tokens = |n: Option<u64>| n.unwrap_or(0) as f64 / 1_000_000.0;
+
+    Some(
+        tokens(usage.input_tokens) * p.input_per_mtok
+            + tokens(usage.output_tokens) * p.output_per_mtok
+            + tokens(usage.cache_read_input_tokens)
+                * p.cache_read_per_mtok.unwrap_or(p.input_per_mtok)
+            + tokens(usage.cache_creation_input_tokens)
+                * p.cache_write_per_mtok.unwrap_or(p.input_per_mtok),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> HashMap<String, ModelPricing> {
+        HashMap::from([
+            (
+                "claude-sonnet-4".to_string(),
+                ModelPricing {
+                    input_per_mtok: 3.0,
+                    output_per_mtok: 15.0,
+                    cache_read_per_mtok: Some(0.3),
+                    cache_write_per_mtok: Some(3.75),
+                },
+            ),
+            (
+                "gpt-4o".to_string(),
+                ModelPricing {
+                    input_per_mtok: 2.5,
+                    output_per_mtok: 10.0,
+                    ..Default::default()
+                },
+            ),
+            (
+                "gpt-4o-mini".to_string(),
+                ModelPricing {
+                    input_per_mtok: 0.15,
+                    output_per_mtok: 0.6,
+                    ..Default::default()
+                },
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_estimate_cost_with_cache() {
+        let usage = ChunkUsage {
+            input_tokens: Some(1_000_000),
+            output_tokens: Some(100_000),
+            cache_read_input_tokens: Some(2_000_000),
+            cache_creation_input_tokens: Some(0),
+        };
+        let cost = estimate_cost(&table(), "claude-sonnet-4-20250514", &usage).unwrap();
+        assert!((cost - (3.0 + 1.5 + 0.6)).abs() < 1e-9, "{cost}");
+    }
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let usage = ChunkUsage {
+            input_tokens: Some(1_000_000),
+            ..Default::default()
+        };
+        let cost = estimate_cost(&table(), "gpt-4o-mini-2024-07-18", &usage).unwrap();
+        assert!((cost - 0.15).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_unknown_model_is_none() {
+        assert!(estimate_cost(&table(), "llama3", &ChunkUsage::default()).is_none());
+    }
+}
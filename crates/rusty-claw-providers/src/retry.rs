@@ -0,0 +1,269 @@
+//! Retrying provider — wraps a provider and retries transient failures.
+//!
+//! Rate limits (429), server errors (500/502/503/529) and timeouts are
+//! retried with exponential backoff and jitter, honoring `Retry-After` when
+//! the API sends it. Retries only wrap [`LlmProvider::stream`] setup, so
+//! they happen before any chunk is yielded and never duplicate partial
+//! output.
+
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::Stream;
+use rand::Rng;
+use tracing::warn;
+
+use crate::{
+    CompletionChunk, CompletionRequest, Credentials, LlmProvider, ModelApi, ModelInfo,
+    ProviderHttpError, ProviderTimeoutError, ToolDefinition,
+};
+use rusty_claw_core::session::TranscriptEntry;
+
+/// Default number of retries after the initial attempt.
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+/// Default base delay for exponential backoff.
+pub const DEFAULT_RETRY_BASE_MS: u64 = 500;
+/// Upper bound on any single wait, including `Retry-After`.
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Retry behaviour for [`RetryingProvider`].
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_MAX_RETRIES,
+            base_delay: Duration::from_millis(DEFAULT_RETRY_BASE_MS),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Backoff before retry number `attempt` (0-based): `base * 2^attempt`,
+    /// scaled by a random factor in `[0.5, 1.0)` and capped.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(MAX_DELAY);
+        let factor = rand::rng().random_range(0.5..1.0);
+        exp.mul_f64(factor)
+    }
+}
+
+/// Whether an error from `stream()` is worth retrying, and how long the
+/// server asked us to wait.
+fn classify(err: &anyhow::Error) -> Option<Option<Duration>> {
+    if let Some(http) = err.downcast_ref::<ProviderHttpError>() {
+        return matches!(http.status.as_u16(), 429 | 500 | 502 | 503 | 529)
+            .then_some(http.retry_after);
+    }
+    if err.downcast_ref::<ProviderTimeoutError>().is_some() {
+        return Some(None);
+    }
+    if let Some(req) = err.downcast_ref::<reqwest::Error>() {
+        return (req.is_connect() || req.is_timeout()).then_some(None);
+    }
+    None
+}
+
+/// A provider decorator that retries transient `stream()` failures.
+pub struct RetryingProvider {
+    inner: Arc<dyn LlmProvider>,
+    policy: RetryPolicy,
+}
+
+impl RetryingProvider {
+    pub fn new(inner: Arc<dyn LlmProvider>, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for RetryingProvider {
+    fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    fn api(&self) -> ModelApi {
+        self.inner.api()
+    }
+
+    fn format_tools(&self, tools: &[ToolDefinition]) -> Vec<serde_json::Value> {
+        self.inner.format_tools(tools)
+    }
+
+    fn format_messages(&self, transcript: &[TranscriptEntry]) -> Vec<serde_json::Value> {
+        self.inner.format_messages(transcript)
+    }
+
+    fn is_tool_use_stop(&self, stop_reason: &str) -> bool {
+        self.inner.is_tool_use_stop(stop_reason)
+    }
+
+    async fn stream(
+        &self,
+        request: &CompletionRequest,
+        credentials: &Credentials,
+    ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<CompletionChunk>> + Send>>> {
+        let mut attempt = 0;
+        loop {
+            match self.inner.stream(request, credentials).await {
+                Ok(stream) => return Ok(stream),
+                Err(e) => {
+                    let Some(retry_after) = classify(&e) else {
+                        return Err(e);
+                    };
+                    if attempt >= self.policy.max_retries {
+                        return Err(e);
+                    }
+                    let delay = retry_after
+                        .map(|d| d.min(MAX_DELAY))
+                        .unwrap_or_else(|| self.policy.backoff(attempt));
+                    warn!(
+                        provider = self.inner.id(),
+                        attempt = attempt + 1,
+                        delay_ms = delay.as_millis() as u64,
+                        %e,
+                        "Transient provider error, retrying"
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    async fn list_models(&self, credentials: &Credentials) -> anyhow::Result<Vec<ModelInfo>> {
+        self.inner.list_models(credentials).await
+    }
+
+    async fn count_tokens(&self, request: &CompletionRequest, credentials: &Credentials) -> anyhow::Result<usize> {
+        self.inner.count_tokens(request, credentials).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{ChunkStream, StubProvider};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Fails with the given status `failures` times, then succeeds.
+    fn flaky(status: u16, failures: u32) -> Arc<StubProvider> {
+        let attempts = AtomicU32::new(0);
+        Arc::new(StubProvider::new(move |_, _| {
+            let failed = attempts.fetch_add(1, Ordering::SeqCst) < failures;
+            async move {
+                if failed {
+                    return Err(ProviderHttpError {
+                        provider: "Flaky".into(),
+                        status: reqwest::StatusCode::from_u16(status).unwrap(),
+                        retry_after: None,
+                        body: String::new(),
+                    }
+                    .into());
+                }
+                Ok(Box::pin(futures::stream::empty()) as ChunkStream)
+            }
+        }))
+    }
+
+    fn request() -> CompletionRequest {
+        CompletionRequest {
+            model: "m".into(),
+            messages: vec![],
+            max_tokens: 16,
+            temperature: None,
+            tools: None,
+            system: None,
+            thinking_budget_tokens: None,
+            enable_prompt_cache: false,
+            stop_sequences: None,
+            tool_choice: None,
+            response_format: None,
+            top_p: None,
+            top_k: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            seed: None,
+        }
+    }
+
+    fn fast_policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_millis(1),
+        }
+    }
+
+    fn creds() -> Credentials {
+        Credentials::ApiKey {
+            api_key: "k".into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_then_succeeds() {
+        let inner = flaky(503, 2);
+        let provider = RetryingProvider::new(inner.clone(), fast_policy(3));
+        assert!(provider.stream(&request(), &creds()).await.is_ok());
+        assert_eq!(inner.calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let inner = flaky(429, 10);
+        let provider = RetryingProvider::new(inner.clone(), fast_policy(2));
+        let err = provider.stream(&request(), &creds()).await.err().unwrap();
+        assert!(err.to_string().contains("429"));
+        assert_eq!(inner.calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_client_errors() {
+        let inner = flaky(400, 1);
+        let provider = RetryingProvider::new(inner.clone(), fast_policy(3));
+        assert!(provider.stream(&request(), &creds()).await.is_err());
+        assert_eq!(inner.calls(), 1);
+    }
+
+    #[test]
+    fn test_classify_honors_retry_after() {
+        let err: anyhow::Error = ProviderHttpError {
+            provider: "X".into(),
+            status: reqwest::StatusCode::TOO_MANY_REQUESTS,
+            retry_after: Some(Duration::from_secs(7)),
+            body: String::new(),
+        }
+        .into();
+        assert_eq!(classify(&err), Some(Some(Duration::from_secs(7))));
+        assert_eq!(classify(&anyhow::anyhow!("parse error")), None);
+        let timeout: anyhow::Error = ProviderTimeoutError {
+            provider: "X".into(),
+            timeout: Duration::from_secs(1),
+        }
+        .into();
+        assert_eq!(classify(&timeout), Some(None));
+    }
+
+    #[test]
+    fn test_backoff_grows_with_jitter() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_millis(100),
+        };
+        for attempt in 0..4 {
+            let d = policy.backoff(attempt);
+            let full = Duration::from_millis(100 * 2u64.pow(attempt));
+            assert!(d >= full / 2 && d < full, "attempt {attempt}: {d:?}");
+        }
+        assert!(policy.backoff(30) <= MAX_DELAY);
+    }
+}
@@ -1,6 +1,11 @@
 //! Generic SSE (Server-Sent Events) line parser.
 //!
 //! Converts a `reqwest::Response` body into a `Stream<Item = SseEvent>`.
+//! Follows the event-stream format: lines end in `\n` or `\r\n`, lines
+//! starting with `:` are comments, consecutive `data:` lines are joined with
+//! `\n`, and an event is dispatched only at a blank line (or end of stream).
+
+use std::collections::VecDeque;
 
 use futures::Stream;
 use tokio_stream::StreamExt;
@@ -13,78 +18,130 @@ pub struct SseEvent {
     pub id: Option<String>,
 }
 
+/// Incremental event-stream parser. Bytes are buffered until a full line is
+/// available, so multi-byte UTF-8 characters split across chunks survive.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+    id: Option<String>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a chunk of the body, returning any events it completed.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        let mut start = 0;
+        while let Some(pos) = self.buffer[start..].iter().position(|b| *b == b'\n') {
+            let end = start + pos;
+            let raw = &self.buffer[start..end];
+            let raw = raw.strip_suffix(b"\r").unwrap_or(raw);
+            let line = String::from_utf8_lossy(raw).into_owned();
+            start = end + 1;
+            if let Some(event) = self.process_line(&line) {
+                events.push(event);
+            }
+        }
+        self.buffer.drain(..start);
+        events
+    }
+
+    /// Flush at end of stream: a trailing unterminated line and any pending
+    /// data are dispatched as a final event.
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        if !self.buffer.is_empty() {
+            let rest = std::mem::take(&mut self.buffer);
+            let rest = rest.strip_suffix(b"\r").unwrap_or(&rest);
+            let line = String::from_utf8_lossy(rest).into_owned();
+            if let Some(event) = self.process_line(&line) {
+                return Some(event);
+            }
+        }
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            // Comment / keep-alive
+            return None;
+        }
+
+        // "field: value" — a single space after the colon is not part of the
+        // value; a line without a colon is a field with an empty value.
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            "id" => self.id = Some(value.to_string()),
+            // `retry` and unknown fields are ignored
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        if self.data.is_empty() {
+            // Events without data are not dispatched; drop their fields too
+            self.event = None;
+            return None;
+        }
+        let event = SseEvent {
+            event: self.event.take(),
+            data: self.data.join("\n"),
+            id: self.id.take(),
+        };
+        self.data.clear();
+        Some(event)
+    }
+}
+
 /// Parse a reqwest response body as an SSE stream.
 pub fn parse_sse_stream(
     response: reqwest::Response,
 ) -> impl Stream<Item = anyhow::Result<SseEvent>> {
     let byte_stream = response.bytes_stream();
 
-    // We'll accumulate partial lines across chunks
     futures::stream::unfold(
         SseState {
             byte_stream: Box::pin(byte_stream),
-            buffer: String::new(),
-            current_event: None,
-            current_data: Vec::new(),
-            current_id: None,
+            parser: SseParser::new(),
+            pending: VecDeque::new(),
+            done: false,
         },
         |mut state| async move {
             loop {
-                // Try to extract a line from the buffer
-                if let Some(newline_pos) = state.buffer.find('\n') {
-                    let line = state.buffer[..newline_pos].trim_end_matches('\r').to_string();
-                    state.buffer = state.buffer[newline_pos + 1..].to_string();
-
-                    if line.is_empty() {
-                        // Empty line = dispatch event
-                        if !state.current_data.is_empty() {
-                            let event = SseEvent {
-                                event: state.current_event.take(),
-                                data: state.current_data.join("\n"),
-                                id: state.current_id.take(),
-                            };
-                            state.current_data.clear();
-                            return Some((Ok(event), state));
-                        }
-                        continue;
-                    }
-
-                    if line.starts_with(':') {
-                        // Comment, skip
-                        continue;
-                    }
-
-                    if let Some(value) = line.strip_prefix("event:") {
-                        state.current_event = Some(value.trim_start().to_string());
-                    } else if let Some(value) = line.strip_prefix("data:") {
-                        state.current_data.push(value.trim_start().to_string());
-                    } else if let Some(value) = line.strip_prefix("id:") {
-                        state.current_id = Some(value.trim_start().to_string());
-                    }
-                    // Ignore unknown fields
-                    continue;
+                if let Some(event) = state.pending.pop_front() {
+                    return Some((Ok(event), state));
+                }
+                if state.done {
+                    return None;
                 }
 
                 // Need more data from the stream
                 match state.byte_stream.next().await {
                     Some(Ok(chunk)) => {
-                        state.buffer.push_str(&String::from_utf8_lossy(&chunk));
+                        let events = state.parser.feed(&chunk);
+                        state.pending.extend(events);
                     }
                     Some(Err(e)) => {
                         return Some((Err(anyhow::anyhow!("SSE stream error: {e}")), state));
                     }
                     None => {
                         // Stream ended. Dispatch any remaining data.
-                        if !state.current_data.is_empty() {
-                            let event = SseEvent {
-                                event: state.current_event.take(),
-                                data: state.current_data.join("\n"),
-                                id: state.current_id.take(),
-                            };
-                            state.current_data.clear();
-                            return Some((Ok(event), state));
-                        }
-                        return None;
+                        state.done = true;
+                        state.pending.extend(state.parser.finish());
                     }
                 }
             }
@@ -94,16 +151,22 @@ pub fn parse_sse_stream(
 
 struct SseState {
     byte_stream: std::pin::Pin<Box<dyn Stream<Item = Result<bytes::Bytes, reqwest::Error>> + Send>>,
-    buffer: String,
-    current_event: Option<String>,
-    current_data: Vec<String>,
-    current_id: Option<String>,
+    parser: SseParser,
+    pending: VecDeque<SseEvent>,
+    done: bool,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn parse_all(chunks: &[&[u8]]) -> Vec<SseEvent> {
+        let mut parser = SseParser::new();
+        let mut events: Vec<SseEvent> = chunks.iter().flat_map(|c| parser.feed(c)).collect();
+        events.extend(parser.finish());
+        events
+    }
+
     #[test]
     fn test_sse_event_debug() {
         let event = SseEvent {
@@ -113,4 +176,56 @@ mod tests {
         };
         assert_eq!(event.event.as_deref(), Some("message_start"));
     }
+
+    #[test]
+    fn test_multi_line_data_joined() {
+        let events = parse_all(&[b"event: delta\ndata: {\"a\":\ndata: 1}\n\n"]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event.as_deref(), Some("delta"));
+        assert_eq!(events[0].data, "{\"a\":\n1}");
+    }
+
+    #[test]
+    fn test_comments_are_skipped() {
+        let events = parse_all(&[b": ping\n\ndata: one\n: keep-alive\n\n:\n\ndata: two\n\n"]);
+        let data: Vec<&str> = events.iter().map(|e| e.data.as_str()).collect();
+        assert_eq!(data, ["one", "two"]);
+    }
+
+    #[test]
+    fn test_crlf_line_endings() {
+        let events = parse_all(&[b"event: message_stop\r\ndata: {}\r\nid: 7\r\n\r\ndata: next\r\n\r\n"]);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event.as_deref(), Some("message_stop"));
+        assert_eq!(events[0].data, "{}");
+        assert_eq!(events[0].id.as_deref(), Some("7"));
+        assert_eq!(events[1].data, "next");
+    }
+
+    #[test]
+    fn test_dispatch_only_on_blank_line() {
+        let mut parser = SseParser::new();
+        assert!(parser.feed(b"data: par").is_empty());
+        assert!(parser.feed(b"tial\ndata: more\n").is_empty());
+        let events = parser.feed(b"\n");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "partial\nmore");
+    }
+
+    #[test]
+    fn test_utf8_split_across_chunks() {
+        let body = "data: héllo ✓\n\n".as_bytes();
+        let (a, b) = body.split_at(9); // inside the two-byte 'é'
+        let events = parse_all(&[a, b]);
+        assert_eq!(events[0].data, "héllo ✓");
+    }
+
+    #[test]
+    fn test_value_whitespace_and_trailing_event() {
+        // Only one leading space is stripped; an unterminated final event
+        // is still delivered at end of stream.
+        let events = parse_all(&[b"data:  indented\n\ndata:tight"]);
+        assert_eq!(events[0].data, " indented");
+        assert_eq!(events[1].data, "tight");
+    }
 }
@@ -0,0 +1,108 @@
+//! Test support: a stub [`LlmProvider`] whose `stream` is a closure.
+//!
+//! Built for this crate's tests and, with the `test-support` feature, for
+//! other crates' tests.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use futures::Stream;
+
+use rusty_claw_core::session::TranscriptEntry;
+
+use crate::{
+    CompletionChunk, CompletionRequest, Credentials, LlmProvider, ModelApi, ModelInfo,
+    ToolDefinition,
+};
+
+/// The stream a provider returns.
+pub type ChunkStream = Pin<Box<dyn Stream<Item = anyhow::Result<CompletionChunk>> + Send>>;
+
+type StreamFn = dyn Fn(&CompletionRequest, &Credentials) -> BoxFuture<'static, anyhow::Result<ChunkStream>>
+    + Send
+    + Sync;
+
+/// A provider that answers `stream` with a closure and counts the calls.
+pub struct StubProvider {
+    id: String,
+    api: ModelApi,
+    stream: Box<StreamFn>,
+    calls: AtomicU32,
+}
+
+impl StubProvider {
+    /// A provider with id "stub" whose `stream` calls `stream`.
+    pub fn new<F, Fut>(stream: F) -> Self
+    where
+        F: Fn(&CompletionRequest, &Credentials) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<ChunkStream>> + Send + 'static,
+    {
+        Self {
+            id: "stub".into(),
+            api: ModelApi::AnthropicMessages,
+            stream: Box::new(move |request, credentials| Box::pin(stream(request, credentials))),
+            calls: AtomicU32::new(0),
+        }
+    }
+
+    /// A provider that answers every request with `text`.
+    pub fn reply(text: &str) -> Self {
+        let chunk = CompletionChunk {
+            delta: Some(text.into()),
+            stop_reason: Some("end_turn".into()),
+            ..Default::default()
+        };
+        Self::new(move |_, _| {
+            let chunk = chunk.clone();
+            async move { Ok(Box::pin(futures::stream::iter([Ok(chunk)])) as ChunkStream) }
+        })
+    }
+
+    pub fn with_id(mut self, id: &str) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    pub fn with_api(mut self, api: ModelApi) -> Self {
+        self.api = api;
+        self
+    }
+
+    /// How many times `stream` was called.
+    pub fn calls(&self) -> u32 {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl LlmProvider for StubProvider {
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn api(&self) -> ModelApi {
+        self.api
+    }
+    fn format_tools(&self, _tools: &[ToolDefinition]) -> Vec<serde_json::Value> {
+        vec![]
+    }
+    fn format_messages(&self, _transcript: &[TranscriptEntry]) -> Vec<serde_json::Value> {
+        vec![]
+    }
+    fn is_tool_use_stop(&self, stop_reason: &str) -> bool {
+        stop_reason == "tool_use"
+    }
+    async fn stream(
+        &self,
+        request: &CompletionRequest,
+        credentials: &Credentials,
+    ) -> anyhow::Result<ChunkStream> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        (self.stream)(request, credentials).await
+    }
+    async fn list_models(&self, _credentials: &Credentials) -> anyhow::Result<Vec<ModelInfo>> {
+        Ok(vec![])
+    }
+}
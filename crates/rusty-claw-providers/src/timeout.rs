@@ -0,0 +1,171 @@
+//! Timeout provider — bounds how long a provider call may go silent.
+//!
+//! The same timeout applies to the initial request (until the response
+//! stream is available) and to every gap between streamed chunks, so a
+//! hung connection fails fast while a long but steady generation is never
+//! cut off. Expiry surfaces as [`ProviderTimeoutError`].
+
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::Stream;
+use tokio_stream::StreamExt;
+
+use crate::{
+    CompletionChunk, CompletionRequest, Credentials, LlmProvider, ModelApi, ModelInfo,
+    ProviderTimeoutError, ToolDefinition,
+};
+use rusty_claw_core::session::TranscriptEntry;
+
+/// Default request and idle timeout.
+pub const DEFAULT_REQUEST_TIMEOUT_MS: u64 = 120_000;
+
+/// A provider decorator that times out stalled requests and streams.
+pub struct TimeoutProvider {
+    inner: Arc<dyn LlmProvider>,
+    timeout: Duration,
+}
+
+impl TimeoutProvider {
+    pub fn new(inner: Arc<dyn LlmProvider>, timeout: Duration) -> Self {
+        Self { inner, timeout }
+    }
+
+    fn error(&self) -> ProviderTimeoutError {
+        ProviderTimeoutError {
+            provider: self.inner.id().to_string(),
+            timeout: self.timeout,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for TimeoutProvider {
+    fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    fn api(&self) -> ModelApi {
+        self.inner.api()
+    }
+
+    fn format_tools(&self, tools: &[ToolDefinition]) -> Vec<serde_json::Value> {
+        self.inner.format_tools(tools)
+    }
+
+    fn format_messages(&self, transcript: &[TranscriptEntry]) -> Vec<serde_json::Value> {
+        self.inner.format_messages(transcript)
+    }
+
+    fn is_tool_use_stop(&self, stop_reason: &str) -> bool {
+        self.inner.is_tool_use_stop(stop_reason)
+    }
+
+    async fn stream(
+        &self,
+        request: &CompletionRequest,
+        credentials: &Credentials,
+    ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<CompletionChunk>> + Send>>> {
+        let inner = tokio::time::timeout(self.timeout, self.inner.stream(request, credentials))
+            .await
+            .map_err(|_| self.error())??;
+
+        // Each chunk gets a fresh deadline; the stream ends after a timeout.
+        let idle = self.timeout;
+        let provider = self.inner.id().to_string();
+        let stream = futures::stream::unfold(Some(inner), move |state| {
+            let provider = provider.clone();
+            async move {
+                let mut inner = state?;
+                match tokio::time::timeout(idle, inner.next()).await {
+                    Ok(Some(item)) => Some((item, Some(inner))),
+                    Ok(None) => None,
+                    Err(_) => Some((
+                        Err(ProviderTimeoutError {
+                            provider,
+                            timeout: idle,
+                        }
+                        .into()),
+                        None,
+                    )),
+                }
+            }
+        });
+        Ok(Box::pin(stream))
+    }
+
+    async fn list_models(&self, credentials: &Credentials) -> anyhow::Result<Vec<ModelInfo>> {
+        tokio::time::timeout(self.timeout, self.inner.list_models(credentials))
+            .await
+            .map_err(|_| self.error())?
+    }
+
+    async fn count_tokens(&self, request: &CompletionRequest, credentials: &Credentials) -> anyhow::Result<usize> {
+        tokio::time::timeout(self.timeout, self.inner.count_tokens(request, credentials))
+            .await
+            .map_err(|_| self.error())?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{ChunkStream, StubProvider};
+
+    /// Waits `setup_ms` before returning a stream that yields one chunk per
+    /// entry in `gaps_ms`, sleeping that long before each.
+    fn wrap(setup_ms: u64, gaps_ms: &[u64]) -> TimeoutProvider {
+        let gaps: Vec<Duration> = gaps_ms.iter().map(|ms| Duration::from_millis(*ms)).collect();
+        let slow = StubProvider::new(move |_, _| {
+            let gaps = gaps.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(setup_ms)).await;
+                Ok(Box::pin(futures::stream::unfold(gaps.into_iter(), |mut gaps| async move {
+                    let gap = gaps.next()?;
+                    tokio::time::sleep(gap).await;
+                    let chunk = CompletionChunk {
+                        delta: Some("x".into()),
+                        ..Default::default()
+                    };
+                    Some((Ok(chunk), gaps))
+                })) as ChunkStream)
+            }
+        });
+        TimeoutProvider::new(Arc::new(slow), Duration::from_millis(200))
+    }
+
+    fn creds() -> Credentials {
+        Credentials::ApiKey {
+            api_key: "k".into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_setup_timeout() {
+        let err = wrap(600, &[]).stream(&CompletionRequest::default(), &creds()).await.err().unwrap();
+        assert!(err.downcast_ref::<ProviderTimeoutError>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_resets_per_chunk() {
+        // Total runtime exceeds the timeout, but no single gap does
+        let provider = wrap(10, &[70, 70, 70, 70]);
+        let stream = provider.stream(&CompletionRequest::default(), &creds()).await.unwrap();
+        let chunks: Vec<_> = stream.collect().await;
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks.iter().all(|c| c.is_ok()));
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_ends_stream_with_error() {
+        let provider = wrap(10, &[10, 600, 10]);
+        let stream = provider.stream(&CompletionRequest::default(), &creds()).await.unwrap();
+        let chunks: Vec<_> = stream.collect().await;
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[0].is_ok());
+        let err = chunks[1].as_ref().err().unwrap();
+        assert!(err.downcast_ref::<ProviderTimeoutError>().is_some());
+    }
+}
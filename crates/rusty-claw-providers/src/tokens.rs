@@ -0,0 +1,139 @@
+//! Prompt token counting fallbacks.
+//!
+//! Providers with a counting endpoint override [`LlmProvider::count_tokens`].
+//! The rest use [`approximate_bpe_tokens`], which splits text the way
+//! GPT-style byte-pair tokenizers pre-tokenize it and estimates the tokens in
+//! each piece; [`estimate_tokens`] is the plain 4-characters-per-token
+//! heuristic used when nothing better is known.
+//!
+//! [`LlmProvider::count_tokens`]: crate::LlmProvider::count_tokens
+
+use crate::CompletionRequest;
+
+/// Heuristic token count: one token per four bytes.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CharClass {
+    Letter,
+    Digit,
+    Space,
+    Other,
+}
+
+fn classify(c: char) -> CharClass {
+    if c.is_alphabetic() {
+        CharClass::Letter
+    } else if c.is_numeric() {
+        CharClass::Digit
+    } else if c.is_whitespace() {
+        CharClass::Space
+    } else {
+        CharClass::Other
+    }
+}
+
+/// Approximate BPE token count.
+///
+/// Text is pre-tokenized into words (with their leading space), runs of up to
+/// three digits, punctuation runs and whitespace runs, as cl100k-style
+/// tokenizers do. Short ASCII words are usually a single token; longer ones
+/// split into roughly eight-character subwords, and non-ASCII characters
+/// mostly cost a token each.
+pub fn approximate_bpe_tokens(text: &str) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let mut start = i;
+        // A single space attaches to the following word or punctuation
+        if chars[i] == ' '
+            && chars
+                .get(i + 1)
+                .is_some_and(|c| matches!(classify(*c), CharClass::Letter | CharClass::Other))
+        {
+            start += 1;
+        }
+        let class = classify(chars[start]);
+        let mut end = start + 1;
+        while end < chars.len() && classify(chars[end]) == class {
+            if class == CharClass::Digit && end - start == 3 {
+                break;
+            }
+            end += 1;
+        }
+        let piece = &chars[start..end];
+        tokens += match class {
+            CharClass::Letter => {
+                let non_ascii = piece.iter().filter(|c| !c.is_ascii()).count();
+                let ascii = piece.len() - non_ascii;
+                non_ascii + if ascii == 0 { 0 } else { 1 + (ascii - 1) / 8 }
+            }
+            CharClass::Digit | CharClass::Space => 1,
+            CharClass::Other => piece.len().div_ceil(2),
+        };
+        i = end;
+    }
+    tokens
+}
+
+/// Everything in a request that counts toward its prompt: system prompt,
+/// messages and tool definitions.
+pub fn request_text(request: &CompletionRequest) -> String {
+    let mut text = request.system.clone().unwrap_or_default();
+    for message in &request.messages {
+        text.push('\n');
+        text.push_str(&message.to_string());
+    }
+    for tool in request.tools.iter().flatten() {
+        text.push('\n');
+        text.push_str(&tool.to_string());
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approximate_bpe_tokens() {
+        // cl100k: "Hello" "," " world" "!"
+        assert_eq!(approximate_bpe_tokens("Hello, world!"), 4);
+        // Digits are grouped in threes: "123" "456" "7"
+        assert_eq!(approximate_bpe_tokens("1234567"), 3);
+        // Long words split into subwords
+        assert_eq!(approximate_bpe_tokens("internationalization"), 3);
+        // Non-ASCII text costs about a token per character
+        assert_eq!(approximate_bpe_tokens("日本語"), 3);
+        assert_eq!(approximate_bpe_tokens(""), 0);
+    }
+
+    #[test]
+    fn test_request_text_includes_system_and_tools() {
+        let request = CompletionRequest {
+            model: "m".into(),
+            messages: vec![serde_json::json!({"role": "user", "content": "hi"})],
+            max_tokens: 16,
+            temperature: None,
+            tools: Some(vec![serde_json::json!({"name": "read_file"})]),
+            system: Some("Be brief.".into()),
+            thinking_budget_tokens: None,
+            enable_prompt_cache: false,
+            stop_sequences: None,
+            tool_choice: None,
+            response_format: None,
+            top_p: None,
+            top_k: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            seed: None,
+        };
+        let text = request_text(&request);
+        assert!(text.starts_with("Be brief."));
+        assert!(text.contains("\"content\":\"hi\""));
+        assert!(text.contains("read_file"));
+    }
+}
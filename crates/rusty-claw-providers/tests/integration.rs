@@ -47,6 +47,15 @@ async fn verify_simple_completion(
         tools: None,
         system: Some("You are a helpful assistant. Follow instructions exactly.".into()),
         thinking_budget_tokens: None,
+        enable_prompt_cache: false,
+        stop_sequences: None,
+        tool_choice: None,
+        response_format: None,
+        top_p: None,
+        top_k: None,
+        frequency_penalty: None,
+        presence_penalty: None,
+        seed: None,
     };
 
     let stream = provider.stream(&request, credentials).await;
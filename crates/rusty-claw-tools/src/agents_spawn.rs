@@ -81,6 +81,7 @@ mod tests {
             restrict_to_workspace: false,
             sandbox_mode: rusty_claw_core::config::SandboxMode::Off,
             browser_pool: None,
+            progress: None,
         }
     }
 
@@ -132,6 +133,7 @@ mod tests {
                     sandbox: None,
                     thinking_budget_tokens: None,
                     max_spawn_depth: Some(5),
+                    ..Default::default()
                 }),
             }),
             ..Config::default()
@@ -150,6 +152,7 @@ mod tests {
                     sandbox: None,
                     thinking_budget_tokens: None,
                     max_spawn_depth: Some(1),
+                    ..Default::default()
                 }),
             }),
             ..Config::default()
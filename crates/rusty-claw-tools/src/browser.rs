@@ -1,12 +1,15 @@
 //! Browser automation tools for the agent.
 //!
-//! Provides navigate, screenshot, click, extract_text, evaluate_js, and wait_for.
+//! Provides navigate, screenshot, click, fill, select, extract_text, evaluate_js,
+//! wait_for, and clear_cookies.
 //! These tools delegate to the BrowserPool in ToolContext when available.
 
 use async_trait::async_trait;
 use base64::Engine;
 use serde_json::json;
 
+use rusty_claw_browser::ScreenshotOptions;
+
 use crate::{Tool, ToolContext, ToolMedia, ToolOutput};
 
 fn no_browser_error() -> ToolOutput {
@@ -91,13 +94,18 @@ impl Tool for BrowserScreenshotTool {
     }
 
     fn description(&self) -> &str {
-        "Take a screenshot of the current browser page and return it as an image."
+        "Take a screenshot of the current browser page and return it as an image. Captures the \
+         viewport by default, the whole page with full_page, or one element with selector."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
         json!({
             "type": "object",
             "properties": {
+                "full_page": {
+                    "type": "boolean",
+                    "description": "Capture the whole scrollable page (default: false)"
+                },
                 "selector": {
                     "type": "string",
                     "description": "Optional CSS selector to screenshot a specific element"
@@ -108,22 +116,39 @@ impl Tool for BrowserScreenshotTool {
 
     async fn execute(
         &self,
-        _params: serde_json::Value,
+        params: serde_json::Value,
         context: &ToolContext,
     ) -> anyhow::Result<ToolOutput> {
+        let options = ScreenshotOptions {
+            full_page: params
+                .get("full_page")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            selector: params
+                .get("selector")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty())
+                .map(String::from),
+        };
+
         let pool = match &context.browser_pool {
             Some(p) => p,
             None => return Ok(no_browser_error()),
         };
 
-        match pool.screenshot(&context.session_key).await {
-            Ok(bytes) => {
-                let b64 = base64::engine::general_purpose::STANDARD.encode(&bytes);
+        match pool.screenshot(&context.session_key, &options).await {
+            Ok(shot) => {
+                let b64 = base64::engine::general_purpose::STANDARD.encode(&shot.data);
                 Ok(ToolOutput {
-                    content: format!("Screenshot captured ({} bytes)", bytes.len()),
+                    content: format!(
+                        "Screenshot captured ({}x{}, {} bytes)",
+                        shot.width,
+                        shot.height,
+                        shot.data.len()
+                    ),
                     is_error: false,
                     media: Some(vec![ToolMedia {
-                        mime_type: "image/png".into(),
+                        mime_type: shot.mime_type.into(),
                         data: b64,
                     }]),
                 })
@@ -201,6 +226,167 @@ impl Tool for BrowserClickTool {
     }
 }
 
+/// Type a value into an input or textarea.
+pub struct BrowserFillTool;
+
+#[async_trait]
+impl Tool for BrowserFillTool {
+    fn name(&self) -> &str {
+        "browser_fill"
+    }
+
+    fn description(&self) -> &str {
+        "Type a value into an input or textarea identified by a CSS selector, replacing its \
+         content. Waits for the field to be editable. Optionally submits the form."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "selector": {
+                    "type": "string",
+                    "description": "CSS selector of the field to fill"
+                },
+                "value": {
+                    "type": "string",
+                    "description": "Text to type into the field"
+                },
+                "submit": {
+                    "type": "boolean",
+                    "description": "Submit the field's form afterwards (default: false)"
+                }
+            },
+            "required": ["selector", "value"]
+        })
+    }
+
+    async fn execute(
+        &self,
+        params: serde_json::Value,
+        context: &ToolContext,
+    ) -> anyhow::Result<ToolOutput> {
+        let selector = params
+            .get("selector")
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+
+        if selector.is_empty() {
+            return Ok(ToolOutput {
+                content: "Error: selector parameter is required".into(),
+                is_error: true,
+                media: None,
+            });
+        }
+
+        let Some(value) = params.get("value").and_then(|v| v.as_str()) else {
+            return Ok(ToolOutput {
+                content: "Error: value parameter is required".into(),
+                is_error: true,
+                media: None,
+            });
+        };
+        let submit = params
+            .get("submit")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let pool = match &context.browser_pool {
+            Some(p) => p,
+            None => return Ok(no_browser_error()),
+        };
+
+        match pool.fill(&context.session_key, selector, value, submit).await {
+            Ok(state) => Ok(ToolOutput {
+                content: format!(
+                    "Filled {selector}{}\nState: {state}",
+                    if submit { " and submitted" } else { "" }
+                ),
+                is_error: false,
+                media: None,
+            }),
+            Err(e) => Ok(ToolOutput {
+                content: format!("Fill failed: {e}"),
+                is_error: true,
+                media: None,
+            }),
+        }
+    }
+}
+
+/// Choose an option in a select dropdown.
+pub struct BrowserSelectTool;
+
+#[async_trait]
+impl Tool for BrowserSelectTool {
+    fn name(&self) -> &str {
+        "browser_select"
+    }
+
+    fn description(&self) -> &str {
+        "Choose an option in a <select> dropdown identified by a CSS selector, matching the \
+         option's value or visible label. Waits for the dropdown to be enabled."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "selector": {
+                    "type": "string",
+                    "description": "CSS selector of the <select> element"
+                },
+                "option": {
+                    "type": "string",
+                    "description": "Value or label of the option to select"
+                }
+            },
+            "required": ["selector", "option"]
+        })
+    }
+
+    async fn execute(
+        &self,
+        params: serde_json::Value,
+        context: &ToolContext,
+    ) -> anyhow::Result<ToolOutput> {
+        let selector = params
+            .get("selector")
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        let option = params
+            .get("option")
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+
+        if selector.is_empty() || option.is_empty() {
+            return Ok(ToolOutput {
+                content: "Error: selector and option parameters are required".into(),
+                is_error: true,
+                media: None,
+            });
+        }
+
+        let pool = match &context.browser_pool {
+            Some(p) => p,
+            None => return Ok(no_browser_error()),
+        };
+
+        match pool.select(&context.session_key, selector, option).await {
+            Ok(state) => Ok(ToolOutput {
+                content: format!("Selected in {selector}\nState: {state}"),
+                is_error: false,
+                media: None,
+            }),
+            Err(e) => Ok(ToolOutput {
+                content: format!("Select failed: {e}"),
+                is_error: true,
+                media: None,
+            }),
+        }
+    }
+}
+
 /// Extract text from the page or a specific element.
 pub struct BrowserExtractTextTool;
 
@@ -398,3 +584,165 @@ impl Tool for BrowserWaitForTool {
         }
     }
 }
+
+/// Reset the session's browser profile.
+pub struct BrowserClearCookiesTool;
+
+#[async_trait]
+impl Tool for BrowserClearCookiesTool {
+    fn name(&self) -> &str {
+        "browser_clear_cookies"
+    }
+
+    fn description(&self) -> &str {
+        "Clear the browser's cookies and site storage for this session, logging out of all sites. \
+         The current page is closed."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {}
+        })
+    }
+
+    async fn execute(
+        &self,
+        _params: serde_json::Value,
+        context: &ToolContext,
+    ) -> anyhow::Result<ToolOutput> {
+        let pool = match &context.browser_pool {
+            Some(p) => p,
+            None => return Ok(no_browser_error()),
+        };
+
+        match pool.clear_cookies(&context.session_key).await {
+            Ok(()) => Ok(ToolOutput {
+                content: "Cleared browser cookies and storage".into(),
+                is_error: false,
+                media: None,
+            }),
+            Err(e) => Ok(ToolOutput {
+                content: format!("Clearing cookies failed: {e}"),
+                is_error: true,
+                media: None,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn test_context() -> ToolContext {
+        ToolContext {
+            session_key: "test".into(),
+            workspace: std::env::temp_dir(),
+            config: Arc::new(rusty_claw_core::config::Config::default()),
+            restrict_to_workspace: false,
+            sandbox_mode: rusty_claw_core::config::SandboxMode::Off,
+            browser_pool: None,
+            progress: None,
+        }
+    }
+
+    async fn run(tool: &dyn Tool, params: serde_json::Value) -> ToolOutput {
+        tool.execute(params, &test_context()).await.unwrap()
+    }
+
+    #[test]
+    fn test_fill_schema() {
+        let schema = BrowserFillTool.parameters_schema();
+        assert_eq!(BrowserFillTool.name(), "browser_fill");
+        assert_eq!(schema["required"], json!(["selector", "value"]));
+        assert_eq!(schema["properties"]["selector"]["type"], "string");
+        assert_eq!(schema["properties"]["value"]["type"], "string");
+        assert_eq!(schema["properties"]["submit"]["type"], "boolean");
+    }
+
+    #[test]
+    fn test_select_schema() {
+        let schema = BrowserSelectTool.parameters_schema();
+        assert_eq!(BrowserSelectTool.name(), "browser_select");
+        assert_eq!(schema["required"], json!(["selector", "option"]));
+        assert_eq!(schema["properties"]["selector"]["type"], "string");
+        assert_eq!(schema["properties"]["option"]["type"], "string");
+    }
+
+    #[tokio::test]
+    async fn test_fill_requires_selector() {
+        let result = run(&BrowserFillTool, json!({"value": "alice"})).await;
+        assert!(result.is_error);
+        assert!(result.content.contains("selector parameter is required"));
+
+        let result = run(&BrowserFillTool, json!({"selector": "", "value": "alice"})).await;
+        assert!(result.content.contains("selector parameter is required"));
+    }
+
+    #[tokio::test]
+    async fn test_fill_requires_value() {
+        let result = run(&BrowserFillTool, json!({"selector": "#user"})).await;
+        assert!(result.is_error);
+        assert!(result.content.contains("value parameter is required"));
+    }
+
+    #[tokio::test]
+    async fn test_fill_rejects_wrong_types() {
+        let result = run(&BrowserFillTool, json!({"selector": 42, "value": "alice"})).await;
+        assert!(result.content.contains("selector parameter is required"));
+
+        let result = run(&BrowserFillTool, json!({"selector": "#age", "value": 42})).await;
+        assert!(result.is_error);
+        assert!(result.content.contains("value parameter is required"));
+    }
+
+    #[tokio::test]
+    async fn test_fill_valid_params_need_browser() {
+        // An empty value is valid: it clears the field
+        let result = run(&BrowserFillTool, json!({"selector": "#user", "value": ""})).await;
+        assert!(result.is_error);
+        assert!(result.content.contains("Browser not configured"));
+
+        let result = run(
+            &BrowserFillTool,
+            json!({"selector": "#user", "value": "alice", "submit": true}),
+        )
+        .await;
+        assert!(result.content.contains("Browser not configured"));
+    }
+
+    #[tokio::test]
+    async fn test_select_requires_selector_and_option() {
+        for params in [
+            json!({"option": "de"}),
+            json!({"selector": "#country"}),
+            json!({"selector": "", "option": "de"}),
+            json!({"selector": "#country", "option": ""}),
+        ] {
+            let result = run(&BrowserSelectTool, params).await;
+            assert!(result.is_error);
+            assert!(result.content.contains("selector and option parameters are required"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_select_rejects_wrong_types() {
+        for params in [
+            json!({"selector": ["#country"], "option": "de"}),
+            json!({"selector": "#country", "option": 3}),
+        ] {
+            let result = run(&BrowserSelectTool, params).await;
+            assert!(result.is_error);
+            assert!(result.content.contains("selector and option parameters are required"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_select_valid_params_need_browser() {
+        let result = run(&BrowserSelectTool, json!({"selector": "#country", "option": "Germany"})).await;
+        assert!(result.is_error);
+        assert!(result.content.contains("Browser not configured"));
+    }
+}
@@ -1,8 +1,15 @@
 //! Canvas tool — allows the agent to push HTML, reset, eval JS, and snapshot a canvas session.
+//!
+//! Pushes come in three modes: `replace` sends full HTML, while `patch` and
+//! `append` change a single element so the client keeps its DOM state.
+//! The `handlers` action declares which element ids the agent wants to hear
+//! about; clicks and submissions on them arrive as `[canvas action]` messages.
 
 use async_trait::async_trait;
 use serde_json::json;
 
+use rusty_claw_canvas::{CanvasHandler, CanvasOperation, CanvasPatch};
+
 use crate::{Tool, ToolContext, ToolOutput};
 
 /// Tool for interacting with the canvas/A2UI system.
@@ -15,7 +22,7 @@ impl Tool for CanvasTool {
     }
 
     fn description(&self) -> &str {
-        "Interact with the visual canvas workspace. Actions: push (add HTML), reset (clear), eval (run JS), snapshot (get current state)."
+        "Interact with the visual canvas workspace. Actions: push (add HTML; mode 'patch' updates one element by selector, 'append' adds a child to it), handlers (declare element ids whose clicks/submissions come back to you as '[canvas action] <id>: <value>' messages), reset (clear), eval (run JS), snapshot (get current state)."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
@@ -24,12 +31,47 @@ impl Tool for CanvasTool {
             "properties": {
                 "action": {
                     "type": "string",
-                    "enum": ["push", "reset", "eval", "snapshot"],
+                    "enum": ["push", "handlers", "reset", "eval", "snapshot"],
                     "description": "The canvas operation to perform"
                 },
+                "mode": {
+                    "type": "string",
+                    "enum": ["replace", "patch", "append"],
+                    "description": "For 'push': replace (full HTML, default), patch (change the element matching selector), append (add html as its last child)"
+                },
                 "html": {
                     "type": "string",
-                    "description": "HTML content to push (for 'push' action)"
+                    "description": "HTML content to push; for patch, the element's new inner HTML"
+                },
+                "selector": {
+                    "type": "string",
+                    "description": "CSS selector of the target element (for patch and append)"
+                },
+                "attribute": {
+                    "type": "string",
+                    "description": "For patch: set this attribute instead of replacing inner HTML"
+                },
+                "value": {
+                    "type": "string",
+                    "description": "For patch with attribute: the new value (omit to remove the attribute)"
+                },
+                "handlers": {
+                    "type": "array",
+                    "description": "For 'handlers': actions you can receive, replacing any earlier declaration",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "id": {
+                                "type": "string",
+                                "description": "Element id the canvas reports clicks or submissions for"
+                            },
+                            "description": {
+                                "type": "string",
+                                "description": "What the action means"
+                            }
+                        },
+                        "required": ["id"]
+                    }
                 },
                 "js": {
                     "type": "string",
@@ -52,20 +94,69 @@ impl Tool for CanvasTool {
 
         match action {
             "push" => {
-                let html = params
-                    .get("html")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("");
-                if html.is_empty() {
-                    return Ok(ToolOutput {
-                        content: "Error: html parameter is required for push action".into(),
-                        is_error: true,
-                        media: None,
-                    });
-                }
+                let op = match push_operation(&params) {
+                    Ok(op) => op,
+                    Err(e) => {
+                        return Ok(ToolOutput {
+                            content: format!("Error: {e}"),
+                            is_error: true,
+                            media: None,
+                        });
+                    }
+                };
                 // In a real implementation, this would push via CanvasManager
+                let content = match &op {
+                    CanvasOperation::Push { html } => {
+                        format!("Canvas push queued ({} bytes of HTML)", html.len())
+                    }
+                    CanvasOperation::Patch { selector, .. } => {
+                        format!("Canvas patch queued for '{selector}'")
+                    }
+                    CanvasOperation::AppendChild { selector, html } => {
+                        format!("Canvas append queued to '{selector}' ({} bytes of HTML)", html.len())
+                    }
+                    _ => unreachable!("push_operation only builds push, patch and append"),
+                };
                 Ok(ToolOutput {
-                    content: format!("Canvas push queued ({} bytes of HTML)", html.len()),
+                    content,
+                    is_error: false,
+                    media: None,
+                })
+            }
+            "handlers" => {
+                let handlers: Vec<CanvasHandler> = match params
+                    .get("handlers")
+                    .map(|v| serde_json::from_value(v.clone()))
+                {
+                    Some(Ok(handlers)) => handlers,
+                    Some(Err(e)) => {
+                        return Ok(ToolOutput {
+                            content: format!("Error: invalid handlers: {e}"),
+                            is_error: true,
+                            media: None,
+                        });
+                    }
+                    None => {
+                        return Ok(ToolOutput {
+                            content: "Error: handlers parameter is required for handlers action".into(),
+                            is_error: true,
+                            media: None,
+                        });
+                    }
+                };
+                // In a real implementation, this would send
+                // CanvasOperation::DeclareHandlers via CanvasManager
+                let ids: Vec<&str> = handlers.iter().map(|h| h.id.as_str()).collect();
+                let content = if ids.is_empty() {
+                    "Canvas handlers cleared; no user actions will be delivered".to_string()
+                } else {
+                    format!(
+                        "Canvas handlers declared: {}. Actions on these elements arrive as '[canvas action] <id>: <value>' messages.",
+                        ids.join(", ")
+                    )
+                };
+                Ok(ToolOutput {
+                    content,
                     is_error: false,
                     media: None,
                 })
@@ -104,10 +195,96 @@ impl Tool for CanvasTool {
                 })
             }
             _ => Ok(ToolOutput {
-                content: format!("Error: unknown canvas action '{action}'. Use push, reset, eval, or snapshot."),
+                content: format!("Error: unknown canvas action '{action}'. Use push, handlers, reset, eval, or snapshot."),
                 is_error: true,
                 media: None,
             }),
         }
     }
 }
+
+/// Build the operation for a `push` action from its `mode`.
+fn push_operation(params: &serde_json::Value) -> Result<CanvasOperation, String> {
+    let str_param = |name: &str| {
+        params
+            .get(name)
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+    };
+    let mode = str_param("mode").unwrap_or_else(|| "replace".into());
+    let html = str_param("html");
+
+    if mode == "replace" {
+        let html = html.ok_or("html parameter is required for push action")?;
+        return Ok(CanvasOperation::Push { html });
+    }
+
+    let selector = str_param("selector")
+        .ok_or_else(|| format!("selector parameter is required for {mode} mode"))?;
+    match mode.as_str() {
+        "patch" => {
+            let patch = match str_param("attribute") {
+                Some(name) => CanvasPatch::Attribute {
+                    name,
+                    value: params.get("value").and_then(|v| v.as_str()).map(String::from),
+                },
+                None => CanvasPatch::Html {
+                    // Empty html is allowed here: it clears the element
+                    html: params
+                        .get("html")
+                        .and_then(|v| v.as_str())
+                        .ok_or("html or attribute parameter is required for patch mode")?
+                        .to_string(),
+                },
+            };
+            Ok(CanvasOperation::Patch { selector, patch })
+        }
+        "append" => {
+            let html = html.ok_or("html parameter is required for append mode")?;
+            Ok(CanvasOperation::AppendChild { selector, html })
+        }
+        other => Err(format!("unknown push mode '{other}'. Use replace, patch, or append.")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_modes() {
+        assert!(matches!(
+            push_operation(&json!({"html": "<h1>Hi</h1>"})),
+            Ok(CanvasOperation::Push { .. })
+        ));
+
+        match push_operation(&json!({"mode": "patch", "selector": "#n", "attribute": "class", "value": "done"})) {
+            Ok(CanvasOperation::Patch { selector, patch }) => {
+                assert_eq!(selector, "#n");
+                assert_eq!(
+                    patch,
+                    CanvasPatch::Attribute {
+                        name: "class".into(),
+                        value: Some("done".into())
+                    }
+                );
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(matches!(
+            push_operation(&json!({"mode": "patch", "selector": "#n", "html": ""})),
+            Ok(CanvasOperation::Patch { patch: CanvasPatch::Html { .. }, .. })
+        ));
+        assert!(matches!(
+            push_operation(&json!({"mode": "append", "selector": "#log", "html": "<li>x</li>"})),
+            Ok(CanvasOperation::AppendChild { .. })
+        ));
+
+        assert_eq!(
+            push_operation(&json!({"mode": "append", "html": "<li>x</li>"})).unwrap_err(),
+            "selector parameter is required for append mode"
+        );
+        assert!(push_operation(&json!({"mode": "morph", "selector": "#n"})).is_err());
+    }
+}
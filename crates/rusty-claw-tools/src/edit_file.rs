@@ -1,6 +1,14 @@
 //! File editing tool — exact string replacement.
+//!
+//! Several edits can be sent in one call. They apply in order to the
+//! file's content and are written together, or not at all: if any edit
+//! fails to match, the file is left untouched and every edit's outcome is
+//! reported.
+
+use std::path::Path;
 
 use async_trait::async_trait;
+use serde::Deserialize;
 use serde_json::json;
 
 use crate::path_guard::validate_path;
@@ -8,6 +16,80 @@ use crate::{Tool, ToolContext, ToolOutput};
 
 pub struct EditFileTool;
 
+/// One replacement. `old_string`/`new_string` are accepted as aliases.
+#[derive(Debug, Deserialize)]
+struct Edit {
+    #[serde(alias = "old_string")]
+    old_text: String,
+    #[serde(alias = "new_string")]
+    new_text: String,
+    /// Number of occurrences that must match; all of them are replaced.
+    #[serde(default = "default_expected_count")]
+    expected_count: usize,
+}
+
+fn default_expected_count() -> usize {
+    1
+}
+
+/// Apply `edits` in order, returning the new content or, if any edit fails,
+/// `None`. The per-edit report lists every outcome either way.
+fn apply_edits(content: &str, edits: &[Edit]) -> (Option<String>, Vec<String>) {
+    let mut current = content.to_string();
+    let mut report = Vec::with_capacity(edits.len());
+    let mut failed = false;
+
+    for (i, edit) in edits.iter().enumerate() {
+        let n = i + 1;
+        if edit.old_text.is_empty() {
+            report.push(format!("edit {n}: failed — old_text is empty"));
+            failed = true;
+            continue;
+        }
+        let count = current.matches(edit.old_text.as_str()).count();
+        if count == 0 {
+            report.push(format!("edit {n}: failed — No match found for old_text"));
+            failed = true;
+        } else if count != edit.expected_count {
+            let hint = if edit.expected_count == 1 {
+                " Provide more surrounding context to make the match unique."
+            } else {
+                ""
+            };
+            report.push(format!(
+                "edit {n}: failed — old_text matches {count} times, expected {}.{hint}",
+                edit.expected_count
+            ));
+            failed = true;
+        } else {
+            current = current.replace(edit.old_text.as_str(), &edit.new_text);
+            let plural = if count == 1 { "" } else { "s" };
+            report.push(format!("edit {n}: replaced {count} occurrence{plural}"));
+        }
+    }
+
+    ((!failed).then_some(current), report)
+}
+
+/// Write via a temp file in the same directory and rename over the target,
+/// keeping the original permissions.
+async fn write_atomic(path: &Path, content: &str) -> std::io::Result<()> {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("file");
+    let tmp_path = path.with_file_name(format!(".{file_name}.{}.tmp", uuid::Uuid::new_v4().simple()));
+    let result = async {
+        tokio::fs::write(&tmp_path, content.as_bytes()).await?;
+        if let Ok(metadata) = tokio::fs::metadata(path).await {
+            tokio::fs::set_permissions(&tmp_path, metadata.permissions()).await?;
+        }
+        tokio::fs::rename(&tmp_path, path).await
+    }
+    .await;
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+    }
+    result
+}
+
 #[async_trait]
 impl Tool for EditFileTool {
     fn name(&self) -> &str {
@@ -15,7 +97,7 @@ impl Tool for EditFileTool {
     }
 
     fn description(&self) -> &str {
-        "Edit a file by replacing an exact text match with new text. The old_text must appear exactly once in the file."
+        "Edit a file by replacing exact text matches. Pass old_text/new_text for one edit (old_text must appear exactly once), or an `edits` array to apply several edits atomically: if any edit fails, nothing is written."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
@@ -33,9 +115,25 @@ impl Tool for EditFileTool {
                 "new_text": {
                     "type": "string",
                     "description": "Text to replace the old_text with"
+                },
+                "edits": {
+                    "type": "array",
+                    "description": "Edits applied in order, each to the result of the previous one",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "old_text": { "type": "string", "description": "Exact text to find" },
+                            "new_text": { "type": "string", "description": "Replacement text" },
+                            "expected_count": {
+                                "type": "integer",
+                                "description": "Number of occurrences that must match, all replaced (default: 1)"
+                            }
+                        },
+                        "required": ["old_text", "new_text"]
+                    }
                 }
             },
-            "required": ["path", "old_text", "new_text"]
+            "required": ["path"]
         })
     }
 
@@ -49,15 +147,32 @@ impl Tool for EditFileTool {
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow::anyhow!("missing 'path' parameter"))?;
 
-        let old_text = params
-            .get("old_text")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow::anyhow!("missing 'old_text' parameter"))?;
-
-        let new_text = params
-            .get("new_text")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow::anyhow!("missing 'new_text' parameter"))?;
+        let edits: Vec<Edit> = match params.get("edits") {
+            Some(edits) => serde_json::from_value(edits.clone())
+                .map_err(|e| anyhow::anyhow!("invalid 'edits' parameter: {e}"))?,
+            None => {
+                let old_text = params
+                    .get("old_text")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("missing 'old_text' parameter"))?;
+                let new_text = params
+                    .get("new_text")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("missing 'new_text' parameter"))?;
+                vec![Edit {
+                    old_text: old_text.to_string(),
+                    new_text: new_text.to_string(),
+                    expected_count: 1,
+                }]
+            }
+        };
+        if edits.is_empty() {
+            return Ok(ToolOutput {
+                content: "No edits given".into(),
+                is_error: true,
+                media: None,
+            });
+        }
 
         let path = match validate_path(raw_path, &context.workspace, context.restrict_to_workspace)
         {
@@ -90,36 +205,29 @@ impl Tool for EditFileTool {
             }
         };
 
-        let match_count = content.matches(old_text).count();
-
-        if match_count == 0 {
+        let (new_content, report) = apply_edits(&content, &edits);
+        let Some(new_content) = new_content else {
             return Ok(ToolOutput {
-                content: "No match found for old_text in the file".into(),
+                content: format!(
+                    "No changes written to {}:\n{}",
+                    path.display(),
+                    report.join("\n")
+                ),
                 is_error: true,
                 media: None,
             });
-        }
+        };
 
-        if match_count > 1 {
+        if let Err(e) = write_atomic(&path, &new_content).await {
             return Ok(ToolOutput {
-                content: format!(
-                    "old_text matches {match_count} times — must match exactly once. \
-                     Provide more surrounding context to make the match unique."
-                ),
+                content: format!("Write error: {e}"),
                 is_error: true,
                 media: None,
             });
         }
 
-        let new_content = content.replacen(old_text, new_text, 1);
-
-        // Atomic write
-        let tmp_path = path.with_extension("edit.tmp");
-        tokio::fs::write(&tmp_path, new_content.as_bytes()).await?;
-        tokio::fs::rename(&tmp_path, &path).await?;
-
         Ok(ToolOutput {
-            content: format!("Edited {}", path.display()),
+            content: format!("Edited {}:\n{}", path.display(), report.join("\n")),
             is_error: false,
             media: None,
         })
@@ -144,6 +252,7 @@ mod tests {
             restrict_to_workspace: true,
             sandbox_mode: rusty_claw_core::config::SandboxMode::default(),
             browser_pool: None,
+            progress: None,
         };
 
         let result = EditFileTool
@@ -177,6 +286,7 @@ mod tests {
             restrict_to_workspace: true,
             sandbox_mode: rusty_claw_core::config::SandboxMode::default(),
             browser_pool: None,
+            progress: None,
         };
 
         let result = EditFileTool
@@ -203,6 +313,7 @@ mod tests {
             restrict_to_workspace: true,
             sandbox_mode: rusty_claw_core::config::SandboxMode::default(),
             browser_pool: None,
+            progress: None,
         };
 
         let result = EditFileTool
@@ -215,4 +326,78 @@ mod tests {
         assert!(result.is_error);
         assert!(result.content.contains("3 times"));
     }
+
+    #[test]
+    fn test_apply_edits_in_order() {
+        let edits: Vec<Edit> = serde_json::from_value(json!([
+            {"old_text": "a", "new_text": "b", "expected_count": 2},
+            {"old_string": "bc", "new_string": "X"}
+        ]))
+        .unwrap();
+        let (content, report) = apply_edits("a ac", &edits);
+        assert_eq!(content.as_deref(), Some("b X"));
+        assert_eq!(report, ["edit 1: replaced 2 occurrences", "edit 2: replaced 1 occurrence"]);
+    }
+
+    #[tokio::test]
+    async fn test_multi_edit_is_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path();
+        std::fs::write(workspace.join("cfg.txt"), "port=1\nhost=x\nhost=y\n").unwrap();
+
+        let ctx = ToolContext {
+            session_key: "test".into(),
+            workspace: workspace.to_path_buf(),
+            config: Arc::new(rusty_claw_core::config::Config::default()),
+            restrict_to_workspace: true,
+            sandbox_mode: rusty_claw_core::config::SandboxMode::default(),
+            browser_pool: None,
+            progress: None,
+        };
+
+        let result = EditFileTool
+            .execute(
+                json!({
+                    "path": "cfg.txt",
+                    "edits": [
+                        {"old_text": "port=1", "new_text": "port=2"},
+                        {"old_text": "host=", "new_text": "h="},
+                        {"old_text": "missing", "new_text": "z"}
+                    ]
+                }),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        assert!(result.content.contains("edit 1: replaced 1 occurrence"));
+        assert!(result.content.contains("edit 2: failed — old_text matches 2 times, expected 1"));
+        assert!(result.content.contains("edit 3: failed — No match"));
+        // Nothing was written, and no temp files were left behind
+        assert_eq!(
+            std::fs::read_to_string(workspace.join("cfg.txt")).unwrap(),
+            "port=1\nhost=x\nhost=y\n"
+        );
+        assert_eq!(std::fs::read_dir(workspace).unwrap().count(), 1);
+
+        let result = EditFileTool
+            .execute(
+                json!({
+                    "path": "cfg.txt",
+                    "edits": [
+                        {"old_text": "port=1", "new_text": "port=2"},
+                        {"old_text": "host=", "new_text": "h=", "expected_count": 2}
+                    ]
+                }),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        assert!(!result.is_error, "{}", result.content);
+        assert_eq!(
+            std::fs::read_to_string(workspace.join("cfg.txt")).unwrap(),
+            "port=2\nh=x\nh=y\n"
+        );
+        assert_eq!(std::fs::read_dir(workspace).unwrap().count(), 1);
+    }
 }
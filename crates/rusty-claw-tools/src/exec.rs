@@ -1,9 +1,20 @@
 //! Shell command execution tool with security hardening.
+//!
+//! Output is streamed to the caller as it arrives (see
+//! [`ToolContext::report_progress`]) and commands run in their own process
+//! group, so a timeout kills everything the command started.
+
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use serde_json::json;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tracing::warn;
 
+use rusty_claw_core::config::{AllowedCommand, ExecConfig};
+
 use crate::{Tool, ToolContext, ToolOutput};
 
 /// Commands or patterns that are too dangerous to execute.
@@ -81,13 +92,147 @@ impl ExecTool {
         false
     }
 
-    /// Check if command is in the allowlist (prefix match).
-    fn is_allowed(command: &str, allowed: &[String]) -> bool {
-        let trimmed = command.trim();
-        allowed.iter().any(|prefix| trimmed.starts_with(prefix.as_str()))
+    /// Check a command against the allowlist. The command is split into
+    /// argv like a shell would, and its leading words must equal an entry's
+    /// words. Shell operators are only accepted after an entry that opted
+    /// into shell mode. Errors name the rejected token.
+    fn check_allowlist(command: &str, allowed: &[AllowedCommand]) -> Result<(), String> {
+        let parsed = split_command(command)?;
+        let Some(program) = parsed.argv.first() else {
+            return Err("empty command".into());
+        };
+
+        let entries: Vec<(&AllowedCommand, Vec<String>)> = allowed
+            .iter()
+            .filter_map(|e| split_command(e.command()).ok().map(|p| (e, p.argv)))
+            .filter(|(_, argv)| !argv.is_empty())
+            .collect();
+
+        // Prefer a shell-mode entry when several match
+        let matched = entries
+            .iter()
+            .filter(|(_, argv)| parsed.argv.starts_with(argv))
+            .max_by_key(|(e, _)| e.shell());
+        if let Some((entry, _)) = matched {
+            return match parsed.operator {
+                Some(op) if !entry.shell() => Err(format!(
+                    "shell operator '{op}' is not allowed (the allowlist entry '{}' does not enable shell mode)",
+                    entry.command()
+                )),
+                _ => Ok(()),
+            };
+        }
+
+        // Name the first word that fell outside every entry for this program
+        let matching_words = entries
+            .iter()
+            .filter(|(_, argv)| argv[0] == *program)
+            .map(|(_, argv)| argv.iter().zip(&parsed.argv).take_while(|(a, b)| a == b).count())
+            .max();
+        match matching_words {
+            None => Err(format!("program '{program}' is not in the allowlist")),
+            Some(n) => match parsed.argv.get(n) {
+                Some(word) => Err(format!("argument '{word}' is not allowed for '{program}'")),
+                None => Err(format!(
+                    "'{}' does not match any allowlisted '{program}' command",
+                    parsed.argv.join(" ")
+                )),
+            },
+        }
     }
 }
 
+/// A command split into words, up to the first shell operator.
+struct SplitCommand {
+    argv: Vec<String>,
+    operator: Option<String>,
+}
+
+/// Split a command into argv with POSIX shell quoting rules: single quotes
+/// are literal, double quotes allow `\` escapes, and an unquoted backslash
+/// escapes the next character. Stops at the first operator (`;`, `&`, `|`,
+/// `<`, `>`, `(`, `)`, newline) or substitution (`` ` ``, `$(`), which is
+/// reported in `operator`.
+fn split_command(command: &str) -> Result<SplitCommand, String> {
+    let mut argv = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = command.chars().peekable();
+
+    let operator = loop {
+        let Some(c) = chars.next() else {
+            break None;
+        };
+        match c {
+            c if c.is_whitespace() && c != '\n' => {
+                if in_word {
+                    argv.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated single quote".into()),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('$' | '`' | '"' | '\\')) => word.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated double quote".into()),
+                        },
+                        Some('`') => return Ok(SplitCommand { argv, operator: Some("`".into()) }),
+                        Some('$') if chars.peek() == Some(&'(') => {
+                            return Ok(SplitCommand { argv, operator: Some("$(".into()) });
+                        }
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated double quote".into()),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                if let Some(c) = chars.next() {
+                    word.push(c);
+                }
+            }
+            '$' if chars.peek() == Some(&'(') => break Some("$(".to_string()),
+            ';' | '&' | '|' | '<' | '>' => {
+                // Report doubled operators (`&&`, `||`, `>>`) as written
+                let mut op = c.to_string();
+                if chars.peek() == Some(&c) {
+                    op.push(c);
+                }
+                break Some(op);
+            }
+            '`' | '(' | ')' => break Some(c.to_string()),
+            '\n' => break Some("newline".to_string()),
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    };
+
+    if in_word {
+        argv.push(word);
+    }
+    Ok(SplitCommand { argv, operator })
+}
+
 #[async_trait]
 impl Tool for ExecTool {
     fn name(&self) -> &str {
@@ -95,7 +240,7 @@ impl Tool for ExecTool {
     }
 
     fn description(&self) -> &str {
-        "Execute a shell command in the workspace directory. Returns stdout and stderr."
+        "Execute a shell command in the workspace directory. Returns stdout and stderr; output is streamed while the command runs."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
@@ -108,7 +253,7 @@ impl Tool for ExecTool {
                 },
                 "timeout_ms": {
                     "type": "integer",
-                    "description": "Timeout in milliseconds (default: 30000)"
+                    "description": "Timeout in milliseconds (default and maximum: exec.timeout_ms from config, 30000 unless set). On expiry the command is killed and partial output returned."
                 }
             },
             "required": ["command"]
@@ -125,11 +270,6 @@ impl Tool for ExecTool {
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow::anyhow!("missing 'command' parameter"))?;
 
-        let timeout_ms = params
-            .get("timeout_ms")
-            .and_then(|v| v.as_u64())
-            .unwrap_or(30_000);
-
         // Read exec config
         let exec_config = context
             .config
@@ -137,6 +277,13 @@ impl Tool for ExecTool {
             .as_ref()
             .and_then(|t| t.exec.as_ref());
 
+        // A per-call timeout may shorten the configured one, never extend it
+        let configured_timeout_ms = exec_config.map_or(30_000, |c| c.timeout_ms);
+        let timeout_ms = params
+            .get("timeout_ms")
+            .and_then(|v| v.as_u64())
+            .map_or(configured_timeout_ms, |t| t.min(configured_timeout_ms));
+
         let mode = exec_config
             .map(|c| c.mode.as_str())
             .unwrap_or("blocklist");
@@ -152,10 +299,10 @@ impl Tool for ExecTool {
                 .cloned()
                 .unwrap_or_default();
 
-            if !Self::is_allowed(command, &allowed) {
-                warn!(command, "Command not in allowlist");
+            if let Err(reason) = Self::check_allowlist(command, &allowed) {
+                warn!(command, %reason, "Command rejected by allowlist");
                 return Ok(ToolOutput {
-                    content: format!("Command not allowed: '{command}' is not in the allowlist"),
+                    content: format!("Command not allowed: {reason}"),
                     is_error: true,
                     media: None,
                 });
@@ -172,79 +319,237 @@ impl Tool for ExecTool {
             }
         }
 
-        // Check for Docker sandbox mode
-        let docker_image = exec_config.and_then(|c| c.docker_image.as_ref());
-        let sandbox_mode = context.sandbox_mode;
-
-        let (shell, args) = if sandbox_mode != rusty_claw_core::config::SandboxMode::Off {
-            if let Some(image) = docker_image {
-                // Docker-sandboxed execution
-                let docker_cmd = format!(
-                    "docker run --rm --network=none -w /workspace -v {}:/workspace:ro {} sh -c {}",
-                    context.workspace.display(),
-                    image,
-                    shell_escape::escape(command.into())
-                );
-                ("sh".to_string(), vec!["-c".to_string(), docker_cmd])
-            } else {
-                ("sh".to_string(), vec!["-c".to_string(), command.to_string()])
+        // Docker sandbox: with an image configured, never fall back to the host
+        let docker = exec_config
+            .filter(|_| context.sandbox_mode != rusty_claw_core::config::SandboxMode::Off)
+            .and_then(|c| c.docker_image.as_deref().map(|image| (c, image)))
+            .map(|(c, image)| {
+                let name = format!("rusty-claw-exec-{}", uuid::Uuid::new_v4().simple());
+                let args = docker_args(command, image, &context.workspace, c, &name);
+                (name, args)
+            });
+
+        let (shell, args) = match &docker {
+            Some((_, args)) => ("docker".to_string(), args.clone()),
+            None => ("sh".to_string(), vec!["-c".to_string(), command.to_string()]),
+        };
+
+        let mut cmd = tokio::process::Command::new(&shell);
+        cmd.args(&args)
+            .current_dir(&context.workspace)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        // Own process group, so a timeout also reaches the command's children
+        #[cfg(unix)]
+        cmd.process_group(0);
+
+        let mut child = match cmd.spawn() {
+            Ok(child) => child,
+            Err(e) if docker.is_some() && e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(ToolOutput {
+                    content: "Docker sandbox unavailable: `docker` was not found in PATH. \
+                              exec.docker_image is set, so the command was not run on the host."
+                        .into(),
+                    is_error: true,
+                    media: None,
+                });
+            }
+            Err(e) => {
+                return Ok(ToolOutput {
+                    content: format!("Failed to execute command: {e}"),
+                    is_error: true,
+                    media: None,
+                });
+            }
+        };
+
+        let mut stdout = child.stdout.take();
+        let mut stderr = child.stderr.take();
+        let mut output = CapturedOutput::new(max_output);
+        let run = async {
+            pump_output(&mut stdout, &mut stderr, &mut output, context).await;
+            child.wait().await
+        };
+        let result = tokio::time::timeout(Duration::from_millis(timeout_ms), run).await;
+
+        let (header, is_error) = match result {
+            // `docker run` itself failed: daemon unreachable, image missing, ...
+            Ok(Ok(status)) if docker.is_some() && status.code() == Some(125) => (
+                "Docker sandbox unavailable (docker run exited with 125); \
+                 the command was not run on the host."
+                    .to_string(),
+                true,
+            ),
+            Ok(Ok(status)) => (
+                format!("Exit code: {}", status.code().unwrap_or(-1)),
+                !status.success(),
+            ),
+            Ok(Err(e)) => (format!("Failed to wait for command: {e}"), true),
+            Err(_) => {
+                warn!(command, timeout_ms, "Command timed out, killing process group");
+                kill_process_group(&mut child).await;
+                if let Some((name, _)) = &docker {
+                    // The CLI exiting does not stop the container
+                    let _ = tokio::process::Command::new("docker")
+                        .args(["kill", name.as_str()])
+                        .stdout(Stdio::null())
+                        .stderr(Stdio::null())
+                        .status()
+                        .await;
+                }
+                (
+                    format!("Command timed out after {timeout_ms}ms and was killed. Partial output:"),
+                    true,
+                )
             }
+        };
+
+        Ok(ToolOutput {
+            content: output.render(&header),
+            is_error,
+            media: None,
+        })
+    }
+}
+
+/// Arguments for `docker run` executing `command` in `image`. The workspace
+/// is mounted read-write at its host path and used as the working directory;
+/// networking is off unless the config allows it.
+fn docker_args(
+    command: &str,
+    image: &str,
+    workspace: &Path,
+    config: &ExecConfig,
+    name: &str,
+) -> Vec<String> {
+    let workspace = workspace.display().to_string();
+    let mut args: Vec<String> = vec!["run".into(), "--rm".into(), "--name".into(), name.into()];
+    if !config.docker_network {
+        args.extend(["--network".into(), "none".into()]);
+    }
+    args.extend(["-v".into(), format!("{workspace}:{workspace}:rw")]);
+    for mount in &config.docker_mounts {
+        args.extend(["-v".into(), mount.clone()]);
+    }
+    args.extend([
+        "-w".into(),
+        workspace,
+        image.into(),
+        "sh".into(),
+        "-c".into(),
+        command.into(),
+    ]);
+    args
+}
+
+/// stdout/stderr captured up to a combined byte limit.
+struct CapturedOutput {
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    limit: usize,
+    truncated: bool,
+}
+
+impl CapturedOutput {
+    fn new(limit: usize) -> Self {
+        Self {
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+            limit,
+            truncated: false,
+        }
+    }
+
+    fn push(&mut self, chunk: &[u8], is_stderr: bool) {
+        let room = self.limit.saturating_sub(self.stdout.len() + self.stderr.len());
+        if chunk.len() > room {
+            self.truncated = true;
+        }
+        let chunk = &chunk[..chunk.len().min(room)];
+        if is_stderr {
+            self.stderr.extend_from_slice(chunk);
+        } else {
+            self.stdout.extend_from_slice(chunk);
+        }
+    }
+
+    fn render(&self, header: &str) -> String {
+        let stdout = String::from_utf8_lossy(&self.stdout);
+        let stderr = String::from_utf8_lossy(&self.stderr);
+        let mut content = if stderr.is_empty() {
+            format!("{header}\n{stdout}")
         } else {
-            ("sh".to_string(), vec!["-c".to_string(), command.to_string()])
+            format!("{header}\nstdout:\n{stdout}\nstderr:\n{stderr}")
         };
+        if self.truncated {
+            content.push_str(&format!("...\n[output truncated at {}]", self.limit));
+        }
+        content
+    }
+}
 
-        let result = tokio::time::timeout(
-            std::time::Duration::from_millis(timeout_ms),
-            tokio::process::Command::new(&shell)
-                .args(&args)
-                .current_dir(&context.workspace)
-                .output(),
-        )
-        .await;
-
-        match result {
-            Ok(Ok(output)) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                let exit_code = output.status.code().unwrap_or(-1);
-
-                let content = if stderr.is_empty() {
-                    format!("Exit code: {exit_code}\n{stdout}")
+/// Read both pipes until EOF, capturing output and reporting each chunk as
+/// progress. Keeps draining past the capture limit so the child never
+/// blocks on a full pipe.
+async fn pump_output<O, E>(
+    stdout: &mut Option<O>,
+    stderr: &mut Option<E>,
+    output: &mut CapturedOutput,
+    context: &ToolContext,
+) where
+    O: AsyncRead + Unpin,
+    E: AsyncRead + Unpin,
+{
+    let mut out_buf = [0u8; 8192];
+    let mut err_buf = [0u8; 8192];
+    let mut out_open = stdout.is_some();
+    let mut err_open = stderr.is_some();
+
+    while out_open || err_open {
+        tokio::select! {
+            n = read_chunk(stdout, &mut out_buf), if out_open => {
+                if n == 0 {
+                    out_open = false;
                 } else {
-                    format!("Exit code: {exit_code}\nstdout:\n{stdout}\nstderr:\n{stderr}")
-                };
-
-                // Truncate very long output
-                let content = if content.len() > max_output {
-                    format!(
-                        "{}...\n[output truncated at {}]",
-                        &content[..max_output],
-                        max_output
-                    )
+                    output.push(&out_buf[..n], false);
+                    context.report_progress(String::from_utf8_lossy(&out_buf[..n]));
+                }
+            }
+            n = read_chunk(stderr, &mut err_buf), if err_open => {
+                if n == 0 {
+                    err_open = false;
                 } else {
-                    content
-                };
-
-                Ok(ToolOutput {
-                    content,
-                    is_error: !output.status.success(),
-                    media: None,
-                })
+                    output.push(&err_buf[..n], true);
+                    context.report_progress(String::from_utf8_lossy(&err_buf[..n]));
+                }
             }
-            Ok(Err(e)) => Ok(ToolOutput {
-                content: format!("Failed to execute command: {e}"),
-                is_error: true,
-                media: None,
-            }),
-            Err(_) => Ok(ToolOutput {
-                content: format!("Command timed out after {timeout_ms}ms"),
-                is_error: true,
-                media: None,
-            }),
         }
     }
 }
 
+/// Read one chunk from an optional pipe; 0 means EOF (or a read error).
+async fn read_chunk<R: AsyncRead + Unpin>(pipe: &mut Option<R>, buf: &mut [u8]) -> usize {
+    match pipe {
+        Some(pipe) => pipe.read(buf).await.unwrap_or(0),
+        None => 0,
+    }
+}
+
+/// Kill the child's whole process group, then reap the child.
+async fn kill_process_group(child: &mut tokio::process::Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // SAFETY: killpg only sends a signal; the group id is the child's pid
+        // because it was spawned with process_group(0).
+        unsafe {
+            libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+        }
+    }
+    let _ = child.kill().await;
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -258,6 +563,7 @@ mod tests {
             restrict_to_workspace: false,
             sandbox_mode: rusty_claw_core::config::SandboxMode::Off,
             browser_pool: None,
+            progress: None,
         }
     }
 
@@ -279,12 +585,75 @@ mod tests {
 
     #[test]
     fn test_allowlist_mode() {
-        let allowed = vec!["git ".to_string(), "cargo ".to_string(), "ls".to_string()];
-        assert!(ExecTool::is_allowed("git status", &allowed));
-        assert!(ExecTool::is_allowed("cargo test", &allowed));
-        assert!(ExecTool::is_allowed("ls -la", &allowed));
-        assert!(!ExecTool::is_allowed("rm -rf /", &allowed));
-        assert!(!ExecTool::is_allowed("echo hello", &allowed));
+        let allowed: Vec<AllowedCommand> = vec!["git ".into(), "cargo ".into(), "ls".into()];
+        assert!(ExecTool::check_allowlist("git status", &allowed).is_ok());
+        assert!(ExecTool::check_allowlist("cargo test", &allowed).is_ok());
+        assert!(ExecTool::check_allowlist("ls -la", &allowed).is_ok());
+        assert!(ExecTool::check_allowlist("rm -rf /", &allowed).is_err());
+        assert!(ExecTool::check_allowlist("echo hello", &allowed).is_err());
+    }
+
+    #[test]
+    fn test_allowlist_rejects_shell_operators() {
+        let allowed: Vec<AllowedCommand> = vec!["ls".into(), "git status".into()];
+        let cases = [
+            ("ls; rm -rf ~", "shell operator ';'"),
+            ("ls && rm -rf ~", "shell operator '&&'"),
+            ("ls | sh", "shell operator '|'"),
+            ("ls > out", "shell operator '>'"),
+            ("ls `rm -rf ~`", "shell operator '`'"),
+            ("ls \"$(rm -rf ~)\"", "shell operator '$('"),
+            ("lsx", "program 'lsx' is not in the allowlist"),
+            ("/tmp/ls", "program '/tmp/ls' is not in the allowlist"),
+            ("git push", "argument 'push' is not allowed for 'git'"),
+            ("git", "'git' does not match any allowlisted 'git' command"),
+            ("ls 'unterminated", "unterminated single quote"),
+        ];
+        for (command, expected) in cases {
+            let err = ExecTool::check_allowlist(command, &allowed).unwrap_err();
+            assert!(err.starts_with(expected), "{command}: {err}");
+        }
+
+        // Quoting is resolved before matching, and quoted operators are plain text
+        assert!(ExecTool::check_allowlist("'l's \"a;b\" c\\|d", &allowed).is_ok());
+        assert!(ExecTool::check_allowlist("git  status --short", &allowed).is_ok());
+    }
+
+    #[test]
+    fn test_allowlist_shell_mode_entry() {
+        let allowed = vec![
+            AllowedCommand::from("make"),
+            AllowedCommand::Detailed {
+                command: "make test".into(),
+                shell: true,
+            },
+        ];
+        assert!(ExecTool::check_allowlist("make test | tee log", &allowed).is_ok());
+        assert!(ExecTool::check_allowlist("make build | tee log", &allowed).is_err());
+        assert!(ExecTool::check_allowlist("make build", &allowed).is_ok());
+    }
+
+    #[test]
+    fn test_split_command() {
+        let parsed = split_command(r#"echo "a \"b\"" 'c d' e\ f"#).unwrap();
+        assert_eq!(parsed.argv, ["echo", "a \"b\"", "c d", "e f"]);
+        assert!(parsed.operator.is_none());
+
+        let parsed = split_command("grep x file || true").unwrap();
+        assert_eq!(parsed.argv, ["grep", "x", "file"]);
+        assert_eq!(parsed.operator.as_deref(), Some("||"));
+    }
+
+    #[test]
+    fn test_allowed_commands_config_forms() {
+        let config: rusty_claw_core::config::ExecConfig = serde_json::from_value(json!({
+            "mode": "allowlist",
+            "allowed_commands": ["git", { "command": "make", "shell": true }]
+        }))
+        .unwrap();
+        assert!(!config.allowed_commands[0].shell());
+        assert!(config.allowed_commands[1].shell());
+        assert_eq!(config.allowed_commands[1].command(), "make");
     }
 
     #[tokio::test]
@@ -333,4 +702,120 @@ mod tests {
         assert!(result.is_error);
         assert!(result.content.contains("timed out"));
     }
+
+    #[tokio::test]
+    async fn test_exec_timeout_kills_group_and_keeps_partial_output() {
+        let ctx = test_context();
+        let started = std::time::Instant::now();
+        let result = ExecTool
+            .execute(
+                // The background sleep holds the pipes open; only killing the
+                // whole group lets the tool return
+                json!({"command": "echo started; sleep 10 & sleep 10", "timeout_ms": 300}),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(result.is_error);
+        assert!(result.content.starts_with("Command timed out after 300ms"));
+        assert!(result.content.contains("started"));
+    }
+
+    #[tokio::test]
+    async fn test_exec_timeout_defaults_from_config() {
+        let mut config = rusty_claw_core::config::Config::default();
+        config.tools = Some(rusty_claw_core::config::ToolsConfig {
+            exec: Some(rusty_claw_core::config::ExecConfig {
+                mode: "blocklist".into(),
+                allowed_commands: vec![],
+                docker_image: None,
+                docker_mounts: vec![],
+                docker_network: false,
+                max_output_bytes: 100_000,
+                timeout_ms: 100,
+            }),
+            ..Default::default()
+        });
+        let ctx = ToolContext {
+            config: Arc::new(config),
+            ..test_context()
+        };
+        let result = ExecTool
+            .execute(json!({"command": "sleep 10"}), &ctx)
+            .await
+            .unwrap();
+        assert!(result.content.contains("timed out after 100ms"));
+    }
+
+    #[tokio::test]
+    async fn test_exec_timeout_clamped_to_config() {
+        let mut config = rusty_claw_core::config::Config::default();
+        config.tools = Some(rusty_claw_core::config::ToolsConfig {
+            exec: Some(rusty_claw_core::config::ExecConfig {
+                mode: "blocklist".into(),
+                allowed_commands: vec![],
+                docker_image: None,
+                docker_mounts: vec![],
+                docker_network: false,
+                max_output_bytes: 100_000,
+                timeout_ms: 100,
+            }),
+            ..Default::default()
+        });
+        let ctx = ToolContext {
+            config: Arc::new(config),
+            ..test_context()
+        };
+        let started = std::time::Instant::now();
+        let result = ExecTool
+            .execute(json!({"command": "sleep 10", "timeout_ms": 60_000}), &ctx)
+            .await
+            .unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(result.content.contains("timed out after 100ms"));
+    }
+
+    #[tokio::test]
+    async fn test_exec_streams_progress() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let ctx = ToolContext {
+            progress: Some(tx),
+            ..test_context()
+        };
+        let result = ExecTool
+            .execute(json!({"command": "echo one; sleep 0.1; echo two >&2"}), &ctx)
+            .await
+            .unwrap();
+        assert!(!result.is_error);
+        assert!(result.content.contains("stderr:\ntwo"));
+
+        let mut chunks = Vec::new();
+        while let Ok(crate::ToolProgress::Output(chunk)) = rx.try_recv() {
+            chunks.push(chunk);
+        }
+        assert_eq!(chunks, ["one\n", "two\n"]);
+    }
+
+    #[test]
+    fn test_docker_args() {
+        let mut config: ExecConfig = serde_json::from_value(json!({
+            "docker_image": "alpine:3",
+            "docker_mounts": ["/data:/data:ro"]
+        }))
+        .unwrap();
+        let args = docker_args("ls -la", "alpine:3", Path::new("/home/me/ws"), &config, "c1");
+        assert_eq!(
+            args,
+            [
+                "run", "--rm", "--name", "c1", "--network", "none", "-v",
+                "/home/me/ws:/home/me/ws:rw", "-v", "/data:/data:ro", "-w", "/home/me/ws",
+                "alpine:3", "sh", "-c", "ls -la",
+            ]
+        );
+
+        config.docker_network = true;
+        let args = docker_args("ls", "alpine:3", Path::new("/ws"), &config, "c2");
+        assert!(!args.iter().any(|a| a == "--network"));
+    }
 }
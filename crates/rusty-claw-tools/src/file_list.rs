@@ -250,6 +250,7 @@ mod tests {
             restrict_to_workspace: true,
             sandbox_mode: rusty_claw_core::config::SandboxMode::default(),
             browser_pool: None,
+            progress: None,
         }
     }
 
@@ -0,0 +1,737 @@
+//! HTML to Markdown conversion for fetched pages.
+//!
+//! A small, forgiving parser builds an element tree, the main content is
+//! picked out (`<main>`, a single `<article>`, or the body with navigation,
+//! headers, footers and sidebars dropped), and the result is rendered as
+//! Markdown: headings, paragraphs, links, emphasis, lists, code blocks,
+//! blockquotes, images and simple tables.
+
+/// Elements whose content is never rendered.
+const SKIPPED: &[&str] = &[
+    "script", "style", "noscript", "template", "svg", "iframe", "head", "form", "button",
+    "select", "input", "textarea", "canvas", "object", "embed", "dialog",
+];
+
+/// Page chrome that is dropped (a `<header>` is kept inside the main content).
+const BOILERPLATE: &[&str] = &["nav", "header", "footer", "aside"];
+
+/// ARIA roles marking page chrome.
+const BOILERPLATE_ROLES: &[&str] = &["navigation", "banner", "contentinfo", "complementary", "search"];
+
+/// class/id tokens that mark page chrome.
+const BOILERPLATE_TOKENS: &[&str] = &[
+    "nav", "navbar", "menu", "footer", "sidebar", "breadcrumb", "breadcrumbs", "cookie",
+    "cookies", "share", "social", "advert", "ads", "banner", "skip",
+];
+
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param",
+    "source", "track", "wbr",
+];
+
+/// Elements rendered as blocks; anything else is inline.
+const BLOCK: &[&str] = &[
+    "address", "article", "blockquote", "body", "center", "dd", "details", "div", "dl", "dt",
+    "figcaption", "figure", "footer", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr",
+    "html", "li", "main", "nav", "ol", "p", "pre", "section", "summary", "table", "ul",
+    "aside",
+];
+
+/// Elements that implicitly close an open `<p>`.
+const CLOSES_P: &[&str] = &[
+    "address", "article", "aside", "blockquote", "div", "dl", "figure", "footer", "h1", "h2",
+    "h3", "h4", "h5", "h6", "header", "hr", "main", "nav", "ol", "p", "pre", "section",
+    "table", "ul",
+];
+
+#[derive(Debug)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attrs: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn is_boilerplate(&self, in_main: bool) -> bool {
+        if BOILERPLATE.contains(&self.name.as_str()) && !(in_main && self.name == "header") {
+            return true;
+        }
+        if self.attr("hidden").is_some() || self.attr("aria-hidden") == Some("true") {
+            return true;
+        }
+        if self
+            .attr("role")
+            .is_some_and(|r| BOILERPLATE_ROLES.contains(&r))
+        {
+            return true;
+        }
+        if matches!(self.name.as_str(), "html" | "body" | "main" | "article") {
+            return false;
+        }
+        let names = [self.attr("class"), self.attr("id")];
+        names.iter().flatten().any(|v| {
+            v.split(|c: char| !c.is_ascii_alphanumeric())
+                .any(|t| BOILERPLATE_TOKENS.contains(&t.to_ascii_lowercase().as_str()))
+        })
+    }
+}
+
+/// Convert an HTML document to Markdown. Relative links and images are
+/// resolved against `base` when given.
+pub fn html_to_markdown(html: &str, base: Option<&url::Url>) -> String {
+    let root = parse(html);
+    let main = main_content(&root);
+    let renderer = Renderer {
+        base,
+        in_main: main.is_some(),
+    };
+    renderer.blocks(&main.unwrap_or(&root).children).trim().to_string()
+}
+
+/// Pick `<main>` (or `role="main"`), else a lone `<article>`.
+fn main_content(root: &Element) -> Option<&Element> {
+    let mut mains = Vec::new();
+    let mut articles = Vec::new();
+    collect_main(root, &mut mains, &mut articles);
+    if let Some(main) = mains.first() {
+        return Some(main);
+    }
+    match articles.as_slice() {
+        [article] => Some(article),
+        _ => None,
+    }
+}
+
+fn collect_main<'a>(el: &'a Element, mains: &mut Vec<&'a Element>, articles: &mut Vec<&'a Element>) {
+    for child in &el.children {
+        if let Node::Element(child) = child {
+            if SKIPPED.contains(&child.name.as_str()) {
+                continue;
+            }
+            if child.name == "main" || child.attr("role") == Some("main") {
+                mains.push(child);
+            } else if child.name == "article" {
+                articles.push(child);
+            }
+            collect_main(child, mains, articles);
+        }
+    }
+}
+
+// --- Parsing ---
+
+fn parse(html: &str) -> Element {
+    let mut stack: Vec<Element> = vec![Element::default()];
+    let bytes = html.as_bytes();
+    let mut pos = 0;
+
+    while pos < html.len() {
+        let Some(offset) = html[pos..].find('<') else {
+            push_text(&mut stack, &html[pos..]);
+            break;
+        };
+        if offset > 0 {
+            push_text(&mut stack, &html[pos..pos + offset]);
+        }
+        pos += offset;
+        let rest = &html[pos..];
+
+        if rest.starts_with("<!--") {
+            pos += rest.find("-->").map_or(rest.len(), |end| end + 3);
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            pos += rest.find('>').map_or(rest.len(), |end| end + 1);
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let name = tag_name(after);
+            pos += rest.find('>').map_or(rest.len(), |end| end + 1);
+            if !name.is_empty() {
+                close(&mut stack, &name);
+            }
+        } else if bytes.get(pos + 1).is_some_and(|b| b.is_ascii_alphabetic()) {
+            let (element, self_closing, len) = start_tag(rest);
+            pos += len;
+            let name = element.name.clone();
+            open_implied_closes(&mut stack, &name);
+
+            if name == "script" || name == "style" || name == "textarea" || name == "title" {
+                // Raw text: everything up to the matching end tag
+                let end = find_end_tag(&html[pos..], &name).unwrap_or(html.len() - pos);
+                let mut element = element;
+                if !SKIPPED.contains(&name.as_str()) {
+                    element.children.push(Node::Text(decode_entities(&html[pos..pos + end])));
+                }
+                pos += end;
+                pos += html[pos..].find('>').map_or(html.len() - pos, |e| e + 1);
+                append(&mut stack, Node::Element(element));
+            } else if self_closing || VOID.contains(&name.as_str()) {
+                append(&mut stack, Node::Element(element));
+            } else {
+                stack.push(element);
+            }
+        } else {
+            push_text(&mut stack, "<");
+            pos += 1;
+        }
+    }
+
+    while stack.len() > 1 {
+        pop(&mut stack);
+    }
+    stack.pop().unwrap_or_default()
+}
+
+fn tag_name(s: &str) -> String {
+    s.chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == ':')
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// Parse a start tag at the beginning of `s`, returning the element, whether
+/// it was self-closing, and the tag's length in bytes.
+fn start_tag(s: &str) -> (Element, bool, usize) {
+    // Tag syntax is ASCII, so byte offsets at delimiters are char boundaries
+    let bytes = s.as_bytes();
+    let at = |i: usize| bytes.get(i).copied();
+    let skip_space = |mut i: usize| {
+        while at(i).is_some_and(|b| b.is_ascii_whitespace()) {
+            i += 1;
+        }
+        i
+    };
+    let name = tag_name(&s[1..]);
+    let mut attrs = Vec::new();
+    let mut i = 1 + name.len();
+    let mut self_closing = false;
+
+    loop {
+        i = skip_space(i);
+        match at(i) {
+            None => return (Element { name, attrs, children: vec![] }, self_closing, s.len()),
+            Some(b'>') => {
+                return (Element { name, attrs, children: vec![] }, self_closing, i + 1);
+            }
+            Some(b'/') => {
+                self_closing = true;
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+        self_closing = false;
+
+        let key_start = i;
+        while at(i).is_some_and(|b| !b.is_ascii_whitespace() && !matches!(b, b'=' | b'>' | b'/')) {
+            i += 1;
+        }
+        let key = s[key_start..i].to_ascii_lowercase();
+        i = skip_space(i);
+        let mut value = String::new();
+        if at(i) == Some(b'=') {
+            i = skip_space(i + 1);
+            match at(i) {
+                Some(q @ (b'"' | b'\'')) => {
+                    let end = bytes[i + 1..]
+                        .iter()
+                        .position(|b| *b == q)
+                        .map_or(s.len(), |e| i + 1 + e);
+                    value = decode_entities(&s[i + 1..end]);
+                    i = (end + 1).min(s.len());
+                }
+                _ => {
+                    let start = i;
+                    while at(i).is_some_and(|b| !b.is_ascii_whitespace() && b != b'>') {
+                        i += 1;
+                    }
+                    value = decode_entities(&s[start..i]);
+                }
+            }
+        }
+        if key.is_empty() {
+            // Stray delimiter; skip it so parsing always advances
+            i += 1;
+        } else {
+            attrs.push((key, value));
+        }
+    }
+}
+
+/// Find the `</name` end tag in `s`, ignoring ASCII case.
+fn find_end_tag(s: &str, name: &str) -> Option<usize> {
+    let mut from = 0;
+    while let Some(offset) = s[from..].find("</") {
+        let at = from + offset;
+        let candidate = s.as_bytes().get(at + 2..at + 2 + name.len());
+        if candidate.is_some_and(|c| c.eq_ignore_ascii_case(name.as_bytes())) {
+            return Some(at);
+        }
+        from = at + 2;
+    }
+    None
+}
+
+/// Close elements that HTML closes implicitly when `name` opens.
+fn open_implied_closes(stack: &mut Vec<Element>, name: &str) {
+    let innermost = |stack: &Vec<Element>, names: &[&str], scope: &[&str]| {
+        stack
+            .iter()
+            .rev()
+            .take_while(|e| !scope.contains(&e.name.as_str()))
+            .any(|e| names.contains(&e.name.as_str()))
+    };
+    if CLOSES_P.contains(&name) && innermost(stack, &["p"], &["div", "td", "th", "li", "blockquote"]) {
+        close(stack, "p");
+    }
+    match name {
+        "li" if innermost(stack, &["li"], &["ul", "ol"]) => close(stack, "li"),
+        "dt" | "dd" if innermost(stack, &["dt", "dd"], &["dl"]) => {
+            let open = stack
+                .iter()
+                .rev()
+                .find(|e| e.name == "dt" || e.name == "dd")
+                .map(|e| e.name.clone())
+                .unwrap_or_default();
+            close(stack, &open);
+        }
+        "td" | "th" if innermost(stack, &["td", "th"], &["tr", "table"]) => {
+            let open = stack
+                .iter()
+                .rev()
+                .find(|e| e.name == "td" || e.name == "th")
+                .map(|e| e.name.clone())
+                .unwrap_or_default();
+            close(stack, &open);
+        }
+        "tr" if innermost(stack, &["tr"], &["table"]) => close(stack, "tr"),
+        _ => {}
+    }
+}
+
+/// Pop up to and including the innermost open `name`; stray end tags are ignored.
+fn close(stack: &mut Vec<Element>, name: &str) {
+    if let Some(index) = stack.iter().skip(1).rposition(|e| e.name == name) {
+        while stack.len() > index + 1 {
+            pop(stack);
+        }
+    }
+}
+
+fn pop(stack: &mut Vec<Element>) {
+    if let Some(element) = stack.pop() {
+        append(stack, Node::Element(element));
+    }
+}
+
+fn append(stack: &mut [Element], node: Node) {
+    if let Some(parent) = stack.last_mut() {
+        parent.children.push(node);
+    }
+}
+
+fn push_text(stack: &mut [Element], text: &str) {
+    append(stack, Node::Text(decode_entities(text)));
+}
+
+/// Decode character references: the common named ones and all numeric forms.
+fn decode_entities(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let semicolon = rest.as_bytes()[1..].iter().take(12).position(|b| *b == b';');
+        let decoded = semicolon.and_then(|end| {
+            let entity = &rest[1..end + 1];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                "ndash" => Some('–'),
+                "mdash" => Some('—'),
+                "hellip" => Some('…'),
+                "copy" => Some('©'),
+                "rsquo" => Some('’'),
+                "lsquo" => Some('‘'),
+                "rdquo" => Some('”'),
+                "ldquo" => Some('“'),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end + 2))
+        });
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// --- Rendering ---
+
+struct Renderer<'a> {
+    base: Option<&'a url::Url>,
+    /// Rendering the selected `<main>`/`<article>` rather than the whole page.
+    in_main: bool,
+}
+
+impl Renderer<'_> {
+    /// Render a sequence of nodes as Markdown blocks separated by blank lines.
+    fn blocks(&self, nodes: &[Node]) -> String {
+        let mut blocks: Vec<String> = Vec::new();
+        let mut inline = String::new();
+
+        for node in nodes {
+            match node {
+                Node::Text(text) => inline.push_str(&collapse(text)),
+                Node::Element(el) if self.skip(el) => {}
+                Node::Element(el) if BLOCK.contains(&el.name.as_str()) => {
+                    flush(&mut inline, &mut blocks);
+                    let block = self.block(el);
+                    if !block.trim().is_empty() {
+                        blocks.push(block);
+                    }
+                }
+                Node::Element(el) => self.inline(el, &mut inline),
+            }
+        }
+        flush(&mut inline, &mut blocks);
+        blocks.join("\n\n")
+    }
+
+    fn block(&self, el: &Element) -> String {
+        match el.name.as_str() {
+            h @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") => {
+                let level = h[1..].parse().unwrap_or(1);
+                let text = self.inline_children(el).replace('\n', " ");
+                format!("{} {}", "#".repeat(level), text.trim())
+            }
+            "ul" | "ol" => self.list(el),
+            "pre" => {
+                let code = text_content(el);
+                let lang = el
+                    .children
+                    .iter()
+                    .find_map(|c| match c {
+                        Node::Element(code) if code.name == "code" => code.attr("class"),
+                        _ => None,
+                    })
+                    .and_then(|class| {
+                        class.split_whitespace().find_map(|c| {
+                            c.strip_prefix("language-").or_else(|| c.strip_prefix("lang-"))
+                        })
+                    })
+                    .unwrap_or("");
+                let code = code.strip_prefix('\n').unwrap_or(&code).trim_end();
+                let fence = if code.contains("```") { "````" } else { "```" };
+                format!("{fence}{lang}\n{code}\n{fence}")
+            }
+            "blockquote" => self
+                .blocks(&el.children)
+                .lines()
+                .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {line}") })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            "hr" => "---".to_string(),
+            "table" => self.table(el),
+            "dt" => {
+                let text = self.inline_children(el);
+                format!("**{}**", text.trim())
+            }
+            _ => self.blocks(&el.children),
+        }
+    }
+
+    fn list(&self, el: &Element) -> String {
+        let ordered = el.name == "ol";
+        let start: usize = el.attr("start").and_then(|s| s.parse().ok()).unwrap_or(1);
+        let mut items = Vec::new();
+        for child in &el.children {
+            let Node::Element(item) = child else { continue };
+            if self.skip(item) {
+                continue;
+            }
+            let body = if item.name == "li" {
+                self.blocks(&item.children)
+            } else {
+                self.block(item)
+            };
+            if body.trim().is_empty() {
+                continue;
+            }
+            let marker = if ordered {
+                format!("{}. ", start + items.len())
+            } else {
+                "- ".to_string()
+            };
+            let indent = " ".repeat(marker.len());
+            let mut lines = body.trim().lines();
+            let mut rendered = format!("{marker}{}", lines.next().unwrap_or(""));
+            for line in lines {
+                rendered.push('\n');
+                if !line.is_empty() {
+                    rendered.push_str(&indent);
+                    rendered.push_str(line);
+                }
+            }
+            items.push(rendered);
+        }
+        items.join("\n")
+    }
+
+    fn table(&self, el: &Element) -> String {
+        let mut rows = Vec::new();
+        collect_rows(el, &mut rows);
+        let mut lines = Vec::new();
+        for (i, row) in rows.iter().enumerate() {
+            let cells: Vec<String> = row
+                .children
+                .iter()
+                .filter_map(|c| match c {
+                    Node::Element(cell) if cell.name == "td" || cell.name == "th" => Some(
+                        self.inline_children(cell)
+                            .replace('\n', " ")
+                            .replace('|', "\\|")
+                            .trim()
+                            .to_string(),
+                    ),
+                    _ => None,
+                })
+                .collect();
+            if cells.is_empty() {
+                continue;
+            }
+            lines.push(format!("| {} |", cells.join(" | ")));
+            if i == 0 {
+                lines.push(format!("|{}", " --- |".repeat(cells.len())));
+            }
+        }
+        lines.join("\n")
+    }
+
+    fn inline_children(&self, el: &Element) -> String {
+        let mut out = String::new();
+        for child in &el.children {
+            match child {
+                Node::Text(text) => out.push_str(&collapse(text)),
+                Node::Element(el) if SKIPPED.contains(&el.name.as_str()) => {}
+                Node::Element(el) => self.inline(el, &mut out),
+            }
+        }
+        tidy(&out)
+    }
+
+    fn inline(&self, el: &Element, out: &mut String) {
+        match el.name.as_str() {
+            "br" => out.push('\n'),
+            "a" => {
+                let text = self.inline_children(el);
+                let href = el
+                    .attr("href")
+                    .filter(|h| !h.starts_with('#') && !h.starts_with("javascript:"))
+                    .map(|h| self.resolve(h));
+                match href {
+                    Some(href) if !text.is_empty() => out.push_str(&format!("[{text}]({href})")),
+                    _ => out.push_str(&text),
+                }
+            }
+            "img" => {
+                if let Some(src) = el.attr("src").filter(|s| !s.starts_with("data:")) {
+                    let alt = el.attr("alt").unwrap_or("").trim();
+                    out.push_str(&format!("![{alt}]({})", self.resolve(src)));
+                }
+            }
+            "strong" | "b" => wrap(out, &self.inline_children(el), "**"),
+            "em" | "i" => wrap(out, &self.inline_children(el), "*"),
+            "del" | "s" | "strike" => wrap(out, &self.inline_children(el), "~~"),
+            "code" | "kbd" | "samp" => {
+                let code = collapse(&text_content(el));
+                let tick = if code.contains('`') { "``" } else { "`" };
+                if !code.trim().is_empty() {
+                    out.push_str(&format!("{tick}{}{tick}", code.trim()));
+                }
+            }
+            _ => {
+                // Blocks nested in inline context are flattened
+                let text = self.inline_children(el);
+                if BLOCK.contains(&el.name.as_str()) && !out.is_empty() {
+                    out.push(' ');
+                }
+                out.push_str(&text);
+            }
+        }
+    }
+
+    fn skip(&self, el: &Element) -> bool {
+        SKIPPED.contains(&el.name.as_str()) || el.is_boilerplate(self.in_main)
+    }
+
+    fn resolve(&self, href: &str) -> String {
+        self.base
+            .and_then(|base| base.join(href).ok())
+            .map(|u| u.to_string())
+            .unwrap_or_else(|| href.to_string())
+    }
+}
+
+fn collect_rows<'a>(el: &'a Element, rows: &mut Vec<&'a Element>) {
+    for child in &el.children {
+        if let Node::Element(child) = child {
+            if child.name == "tr" {
+                rows.push(child);
+            } else if child.name != "table" {
+                collect_rows(child, rows);
+            }
+        }
+    }
+}
+
+fn text_content(el: &Element) -> String {
+    let mut out = String::new();
+    for child in &el.children {
+        match child {
+            Node::Text(text) => out.push_str(text),
+            Node::Element(el) if el.name == "br" => out.push('\n'),
+            Node::Element(el) => out.push_str(&text_content(el)),
+        }
+    }
+    out
+}
+
+fn wrap(out: &mut String, text: &str, marker: &str) {
+    if !text.is_empty() {
+        out.push_str(&format!("{marker}{text}{marker}"));
+    }
+}
+
+/// Collapse runs of whitespace to single spaces.
+fn collapse(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !last_space {
+                out.push(' ');
+            }
+            last_space = true;
+        } else {
+            out.push(c);
+            last_space = false;
+        }
+    }
+    out
+}
+
+/// Merge doubled spaces left by adjacent nodes and trim around line breaks.
+fn tidy(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == ' ' && (out.ends_with(' ') || out.ends_with('\n') || out.is_empty()) {
+            continue;
+        }
+        if c == '\n' && out.ends_with(' ') {
+            out.pop();
+        }
+        out.push(c);
+    }
+    out.trim().to_string()
+}
+
+fn flush(inline: &mut String, blocks: &mut Vec<String>) {
+    let text = tidy(inline);
+    if !text.is_empty() {
+        blocks.push(text);
+    }
+    inline.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headings_links_and_emphasis() {
+        let base = url::Url::parse("https://example.com/docs/").unwrap();
+        let html = r#"<h1>Title</h1><p>Read <a href="guide">the <b>guide</b></a> or
+            <a href="https://other.org/x">this</a> &amp; <em>more</em>.</p>"#;
+        assert_eq!(
+            html_to_markdown(html, Some(&base)),
+            "# Title\n\nRead [the **guide**](https://example.com/docs/guide) or \
+             [this](https://other.org/x) & *more*."
+        );
+    }
+
+    #[test]
+    fn test_lists_and_code_blocks() {
+        let html = r#"<ul><li>One<li>Two<ol><li>Nested</li></ol></ul>
+            <pre><code class="language-rust">fn main() {
+    println!("&lt;hi&gt;");
+}</code></pre><p>Use <code>cargo  run</code></p>"#;
+        assert_eq!(
+            html_to_markdown(html, None),
+            "- One\n- Two\n\n  1. Nested\n\n\
+             ```rust\nfn main() {\n    println!(\"<hi>\");\n}\n```\n\nUse `cargo run`"
+        );
+    }
+
+    #[test]
+    fn test_boilerplate_is_dropped() {
+        let html = r#"<html><head><title>T</title><style>p{}</style></head><body>
+            <header><a href="/">Home</a></header>
+            <nav><ul><li>Menu</li></ul></nav>
+            <div class="cookie-banner">Accept cookies</div>
+            <div class="content"><h2>Story</h2><p>Body text.</p><script>track()</script></div>
+            <footer>Copyright</footer></body></html>"#;
+        assert_eq!(html_to_markdown(html, None), "## Story\n\nBody text.");
+    }
+
+    #[test]
+    fn test_main_content_preferred() {
+        let html = r#"<body><div>Sidebar links</div>
+            <article><header><h1>Post</h1></header><p>First<br>second</p>
+            <blockquote><p>Quoted</p></blockquote>
+            <table><tr><th>A</th><th>B</th></tr><tr><td>1</td><td>2</td></tr></table>
+            </article></body>"#;
+        assert_eq!(
+            html_to_markdown(html, None),
+            "# Post\n\nFirst\nsecond\n\n> Quoted\n\n| A | B |\n| --- | --- |\n| 1 | 2 |"
+        );
+    }
+
+    #[test]
+    fn test_malformed_html_does_not_panic() {
+        let html = "<div><p>Unclosed <b>bold <i>mixed</b> text</i></span>< 3 &bogus; &#x1F600;<a href='x";
+        let md = html_to_markdown(html, None);
+        assert!(md.contains("Unclosed"));
+        assert!(md.contains("< 3 &bogus; 😀"));
+    }
+}
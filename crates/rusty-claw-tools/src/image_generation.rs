@@ -1,15 +1,119 @@
 //! Image generation tool using OpenAI DALL-E or Stability AI.
+//!
+//! Besides text-to-image (`operation: generate`), an existing workspace
+//! image can be edited (optionally inpainted through a mask) or varied.
+//! Images are returned inline and saved to disk: under the data directory
+//! by default, or at `save_to` in the workspace so later tools can use them.
+
+use std::path::{Path, PathBuf};
 
 use async_trait::async_trait;
+use base64::Engine;
 use serde_json::json;
 use tracing::info;
 
-use crate::{Tool, ToolContext, ToolOutput};
+use crate::path_guard::{validate_path, validate_write_path};
+use crate::{Tool, ToolContext, ToolMedia, ToolOutput};
+
+/// Upper bound for `n` (both providers cap a request at 10 images).
+const MAX_IMAGES: usize = 10;
 
 pub struct ImageGenerationTool;
 
+/// What to do with the prompt and optional input image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operation {
+    Generate,
+    Edit,
+    Variation,
+}
+
+impl Operation {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "generate" => Some(Self::Generate),
+            "edit" => Some(Self::Edit),
+            "variation" => Some(Self::Variation),
+            _ => None,
+        }
+    }
+}
+
+/// An input image read from the workspace.
+struct SourceImage {
+    bytes: Vec<u8>,
+    file_name: String,
+    mime_type: &'static str,
+}
+
+impl SourceImage {
+    async fn read(raw: &str, context: &ToolContext) -> Result<Self, String> {
+        let path = validate_path(raw, &context.workspace, context.restrict_to_workspace)
+            .map_err(|e| format!("Path error: {e}"))?;
+        let bytes = tokio::fs::read(&path)
+            .await
+            .map_err(|e| format!("Cannot read image '{raw}': {e}"))?;
+        let mime_type = match path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("jpg" | "jpeg") => "image/jpeg",
+            Some("webp") => "image/webp",
+            _ => "image/png",
+        };
+        Ok(Self {
+            bytes,
+            file_name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "image.png".into()),
+            mime_type,
+        })
+    }
+
+    fn part(&self) -> anyhow::Result<reqwest::multipart::Part> {
+        Ok(reqwest::multipart::Part::bytes(self.bytes.clone())
+            .file_name(self.file_name.clone())
+            .mime_str(self.mime_type)?)
+    }
+}
+
+/// Images returned by a provider, with the model/engine line for the output.
+struct Generated {
+    images: Vec<Vec<u8>>,
+    label: String,
+}
+
+/// Workspace targets for `save_to`: the path itself for one image, else
+/// numbered siblings (`logo.png` → `logo-1.png`, `logo-2.png`, ...).
+fn save_paths(save_to: &str, n: usize) -> Vec<String> {
+    let path = if Path::new(save_to).extension().is_some() {
+        save_to.to_string()
+    } else {
+        format!("{save_to}.png")
+    };
+    if n == 1 {
+        return vec![path];
+    }
+    let p = Path::new(&path);
+    let stem = p.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = p.extension().unwrap_or_default().to_string_lossy();
+    (1..=n)
+        .map(|i| {
+            p.with_file_name(format!("{stem}-{i}.{ext}"))
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect()
+}
+
+fn error_output(content: impl Into<String>) -> ToolOutput {
+    ToolOutput {
+        content: content.into(),
+        is_error: true,
+        media: None,
+    }
+}
+
 /// Generate a unique output filename.
-fn output_filename() -> std::path::PathBuf {
+fn output_filename() -> PathBuf {
     let dir = rusty_claw_core::config::data_dir().join("images");
     let ts = chrono::Utc::now().format("%Y%m%d_%H%M%S");
     let id = uuid::Uuid::new_v4().simple().to_string();
@@ -35,7 +139,7 @@ impl Tool for ImageGenerationTool {
     }
 
     fn description(&self) -> &str {
-        "Generate an image from a text prompt using DALL-E or Stability AI. Returns the path to the saved image."
+        "Generate images from a text prompt, edit an existing image (optionally with a mask), or create variations, using DALL-E or Stability AI. Returns the images inline plus the paths they were saved to."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
@@ -44,7 +148,20 @@ impl Tool for ImageGenerationTool {
             "properties": {
                 "prompt": {
                     "type": "string",
-                    "description": "Text description of the image to generate"
+                    "description": "Text description of the image to generate, or of the change to make for 'edit' (ignored by OpenAI variations)"
+                },
+                "operation": {
+                    "type": "string",
+                    "enum": ["generate", "edit", "variation"],
+                    "description": "generate (default): text to image; edit: change 'image' as the prompt describes; variation: new versions of 'image'"
+                },
+                "image": {
+                    "type": "string",
+                    "description": "Workspace path of the input image (for edit and variation)"
+                },
+                "mask": {
+                    "type": "string",
+                    "description": "Workspace path of a mask image for edit; transparent (OpenAI) or white (Stability) areas are repainted"
                 },
                 "size": {
                     "type": "string",
@@ -56,11 +173,15 @@ impl Tool for ImageGenerationTool {
                 },
                 "n": {
                     "type": "integer",
-                    "description": "Number of images to generate (default: 1)"
+                    "description": "Number of variations to generate (default: 1, max: 10)"
                 },
                 "quality": {
                     "type": "string",
                     "description": "Image quality ('standard' or 'hd', OpenAI only)"
+                },
+                "save_to": {
+                    "type": "string",
+                    "description": "Workspace path to save the image to (e.g. 'assets/logo.png'); with n > 1 files are numbered logo-1.png, logo-2.png, ..."
                 }
             },
             "required": ["prompt"]
@@ -100,14 +221,10 @@ impl Tool for ImageGenerationTool {
         let api_key = match api_key {
             Some(key) => key,
             None => {
-                return Ok(ToolOutput {
-                    content: format!(
-                        "Image generation not configured. Set tools.image_generation.api_key in config or {} environment variable.",
-                        if provider == "stability" { "STABILITY_API_KEY" } else { "OPENAI_API_KEY" }
-                    ),
-                    is_error: true,
-                    media: None,
-                });
+                return Ok(error_output(format!(
+                    "Image generation not configured. Set tools.image_generation.api_key in config or {} environment variable.",
+                    if provider == "stability" { "STABILITY_API_KEY" } else { "OPENAI_API_KEY" }
+                )));
             }
         };
 
@@ -128,19 +245,114 @@ impl Tool for ImageGenerationTool {
             .or_else(|| img_config.and_then(|c| c.default_quality.as_deref()))
             .unwrap_or("standard");
 
-        match provider {
-            "stability" => {
-                self.generate_stability(&api_key, prompt, size, model)
+        let n = params
+            .get("n")
+            .and_then(|v| v.as_u64())
+            .map(|n| (n as usize).clamp(1, MAX_IMAGES))
+            .unwrap_or(1);
+
+        let operation = match params.get("operation").and_then(|v| v.as_str()) {
+            None => Operation::Generate,
+            Some(op) => match Operation::parse(op) {
+                Some(op) => op,
+                None => {
+                    return Ok(error_output(format!(
+                        "Unknown operation '{op}'. Use generate, edit, or variation."
+                    )));
+                }
+            },
+        };
+
+        let source = match (operation, params.get("image").and_then(|v| v.as_str())) {
+            (Operation::Generate, _) => None,
+            (_, None) => {
+                return Ok(error_output("The 'image' parameter is required for edit and variation"));
+            }
+            (_, Some(raw)) => match SourceImage::read(raw, context).await {
+                Ok(image) => Some(image),
+                Err(e) => return Ok(error_output(e)),
+            },
+        };
+        let mask = match params.get("mask").and_then(|v| v.as_str()) {
+            Some(_) if operation != Operation::Edit => {
+                return Ok(error_output("The 'mask' parameter only applies to edit"));
+            }
+            Some(raw) => match SourceImage::read(raw, context).await {
+                Ok(image) => Some(image),
+                Err(e) => return Ok(error_output(e)),
+            },
+            None => None,
+        };
+
+        // Validate the destination before paying for the generation
+        let targets = match params.get("save_to").and_then(|v| v.as_str()) {
+            Some(save_to) => {
+                let mut targets = Vec::with_capacity(n);
+                for raw in save_paths(save_to, n) {
+                    match validate_write_path(&raw, &context.workspace, context.restrict_to_workspace, true) {
+                        Ok(path) => targets.push(path),
+                        Err(e) => return Ok(error_output(format!("Path error: {e}"))),
+                    }
+                }
+                Some(targets)
+            }
+            None => None,
+        };
+
+        let generated = match (provider, source) {
+            ("stability", Some(image)) => {
+                self.image_to_image_stability(&api_key, operation, prompt, model, n, &image, mask.as_ref())
                     .await
             }
-            _ => {
-                self.generate_openai(&api_key, prompt, size, model, quality)
+            ("stability", None) => self.generate_stability(&api_key, prompt, size, model, n).await,
+            (_, Some(image)) => {
+                self.transform_openai(&api_key, operation, prompt, size, model, n, &image, mask.as_ref())
                     .await
             }
+            (_, None) => self.generate_openai(&api_key, prompt, size, model, quality, n).await,
+        };
+        let generated = match generated {
+            Ok(generated) => generated,
+            Err(e) => return Ok(error_output(e.to_string())),
+        };
+
+        let paths = targets.unwrap_or_else(|| generated.images.iter().map(|_| output_filename()).collect());
+        let mut lines = Vec::new();
+        let mut media = Vec::new();
+        for (bytes, path) in generated.images.iter().zip(&paths) {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(path, bytes).await?;
+            info!(path = %path.display(), size_kb = bytes.len() / 1024, provider, "Image generated");
+
+            lines.push(format!(
+                "Image saved to: {} ({}KB)",
+                display_path(path, &context.workspace),
+                bytes.len() / 1024
+            ));
+            media.push(ToolMedia {
+                mime_type: "image/png".into(),
+                data: base64::engine::general_purpose::STANDARD.encode(bytes),
+            });
         }
+
+        Ok(ToolOutput {
+            content: format!("{}\n{}\nPrompt: {}", lines.join("\n"), generated.label, prompt),
+            is_error: false,
+            media: Some(media),
+        })
     }
 }
 
+/// Workspace-relative form of `path` when it lies inside the workspace.
+fn display_path(path: &Path, workspace: &Path) -> String {
+    let workspace = workspace.canonicalize().unwrap_or_else(|_| workspace.to_path_buf());
+    path.strip_prefix(&workspace)
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|_| path.display().to_string())
+}
+
 impl ImageGenerationTool {
     async fn generate_openai(
         &self,
@@ -149,66 +361,48 @@ impl ImageGenerationTool {
         size: &str,
         model: Option<&str>,
         quality: &str,
-    ) -> anyhow::Result<ToolOutput> {
+        n: usize,
+    ) -> anyhow::Result<Generated> {
         let model = model.unwrap_or("dall-e-3");
         let client = reqwest::Client::new();
 
-        let resp = client
-            .post("https://api.openai.com/v1/images/generations")
-            .header("Authorization", format!("Bearer {api_key}"))
-            .json(&json!({
+        // dall-e-3 only accepts n=1, so variations take one request each
+        let (requests, per_request) = if model == "dall-e-3" { (n, 1) } else { (1, n) };
+
+        let mut images = Vec::with_capacity(n);
+        for _ in 0..requests {
+            let mut body = json!({
                 "model": model,
                 "prompt": prompt,
-                "n": 1,
+                "n": per_request,
                 "size": size,
                 "quality": quality,
-                "response_format": "b64_json",
-            }))
-            .send()
-            .await?;
-
-        if !resp.status().is_success() {
-            let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            return Ok(ToolOutput {
-                content: format!("OpenAI API error ({status}): {body}"),
-                is_error: true,
-                media: None,
             });
-        }
-
-        let body: serde_json::Value = resp.json().await?;
-        let b64 = body["data"][0]["b64_json"]
-            .as_str()
-            .ok_or_else(|| anyhow::anyhow!("No image data in response"))?;
+            // GPT image models always return base64 and reject this field
+            if model.starts_with("dall-e") {
+                body["response_format"] = json!("b64_json");
+            }
 
-        use base64::Engine;
-        let bytes = base64::engine::general_purpose::STANDARD.decode(b64)?;
-        let file_path = output_filename();
+            let resp = client
+                .post("https://api.openai.com/v1/images/generations")
+                .header("Authorization", format!("Bearer {api_key}"))
+                .json(&body)
+                .send()
+                .await?;
+
+            if !resp.status().is_success() {
+                let status = resp.status();
+                let body = resp.text().await.unwrap_or_default();
+                anyhow::bail!("OpenAI API error ({status}): {body}");
+            }
 
-        if let Some(parent) = file_path.parent() {
-            tokio::fs::create_dir_all(parent).await?;
+            let body: serde_json::Value = resp.json().await?;
+            images.extend(decode_images(&body["data"], "b64_json")?);
         }
-        tokio::fs::write(&file_path, &bytes).await?;
-
-        let size_kb = bytes.len() / 1024;
-        info!(
-            path = %file_path.display(),
-            size_kb,
-            model,
-            "Image generated (OpenAI)"
-        );
 
-        Ok(ToolOutput {
-            content: format!(
-                "Image saved to: {}\nSize: {}KB\nModel: {}\nPrompt: {}",
-                file_path.display(),
-                size_kb,
-                model,
-                prompt,
-            ),
-            is_error: false,
-            media: None,
+        Ok(Generated {
+            images,
+            label: format!("Model: {model}"),
         })
     }
 
@@ -218,7 +412,8 @@ impl ImageGenerationTool {
         prompt: &str,
         size: &str,
         model: Option<&str>,
-    ) -> anyhow::Result<ToolOutput> {
+        n: usize,
+    ) -> anyhow::Result<Generated> {
         let engine = model.unwrap_or("stable-diffusion-xl-1024-v1-0");
         let (width, height) = parse_size(size);
         let client = reqwest::Client::new();
@@ -233,7 +428,7 @@ impl ImageGenerationTool {
                 "cfg_scale": 7,
                 "width": width,
                 "height": height,
-                "samples": 1,
+                "samples": n,
                 "steps": 30,
             }))
             .send()
@@ -242,47 +437,151 @@ impl ImageGenerationTool {
         if !resp.status().is_success() {
             let status = resp.status();
             let body = resp.text().await.unwrap_or_default();
-            return Ok(ToolOutput {
-                content: format!("Stability AI API error ({status}): {body}"),
-                is_error: true,
-                media: None,
-            });
+            anyhow::bail!("Stability AI API error ({status}): {body}");
+        }
+
+        let body: serde_json::Value = resp.json().await?;
+        Ok(Generated {
+            images: decode_images(&body["artifacts"], "base64")?,
+            label: format!("Engine: {engine}"),
+        })
+    }
+}
+
+impl ImageGenerationTool {
+    /// OpenAI `/v1/images/edits` or `/v1/images/variations`.
+    #[allow(clippy::too_many_arguments)]
+    async fn transform_openai(
+        &self,
+        api_key: &str,
+        operation: Operation,
+        prompt: &str,
+        size: &str,
+        model: Option<&str>,
+        n: usize,
+        image: &SourceImage,
+        mask: Option<&SourceImage>,
+    ) -> anyhow::Result<Generated> {
+        // dall-e-3 supports neither endpoint
+        let model = model.filter(|m| *m != "dall-e-3").unwrap_or("dall-e-2");
+        let (endpoint, field) = match operation {
+            Operation::Variation => ("variations", "image"),
+            // GPT image models take the input as an array field
+            _ if model.starts_with("gpt-image") => ("edits", "image[]"),
+            _ => ("edits", "image"),
+        };
+
+        let mut form = reqwest::multipart::Form::new()
+            .part(field, image.part()?)
+            .text("model", model.to_string())
+            .text("n", n.to_string())
+            .text("size", size.to_string());
+        if operation == Operation::Edit {
+            form = form.text("prompt", prompt.to_string());
+        }
+        if let Some(mask) = mask {
+            form = form.part("mask", mask.part()?);
+        }
+        if model.starts_with("dall-e") {
+            form = form.text("response_format", "b64_json");
+        }
+
+        let resp = reqwest::Client::new()
+            .post(format!("https://api.openai.com/v1/images/{endpoint}"))
+            .header("Authorization", format!("Bearer {api_key}"))
+            .multipart(form)
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            anyhow::bail!("OpenAI API error ({status}): {body}");
         }
 
         let body: serde_json::Value = resp.json().await?;
-        let b64 = body["artifacts"][0]["base64"]
-            .as_str()
-            .ok_or_else(|| anyhow::anyhow!("No image data in response"))?;
+        Ok(Generated {
+            images: decode_images(&body["data"], "b64_json")?,
+            label: format!("Model: {model}"),
+        })
+    }
+
+    /// Stability image-to-image; with a mask, the masking (inpainting)
+    /// endpoint. Variations keep less of the input image than edits.
+    #[allow(clippy::too_many_arguments)]
+    async fn image_to_image_stability(
+        &self,
+        api_key: &str,
+        operation: Operation,
+        prompt: &str,
+        model: Option<&str>,
+        n: usize,
+        image: &SourceImage,
+        mask: Option<&SourceImage>,
+    ) -> anyhow::Result<Generated> {
+        let engine = model.unwrap_or("stable-diffusion-xl-1024-v1-0");
+        let mut form = reqwest::multipart::Form::new()
+            .part("init_image", image.part()?)
+            .text("text_prompts[0][text]", prompt.to_string())
+            .text("text_prompts[0][weight]", "1")
+            .text("cfg_scale", "7")
+            .text("samples", n.to_string())
+            .text("steps", "30");
+
+        let url = match mask {
+            Some(mask) => {
+                form = form
+                    .part("mask_image", mask.part()?)
+                    .text("mask_source", "MASK_IMAGE_WHITE");
+                format!("https://api.stability.ai/v1/generation/{engine}/image-to-image/masking")
+            }
+            None => {
+                form = form
+                    .text("init_image_mode", "IMAGE_STRENGTH")
+                    .text("image_strength", if operation == Operation::Variation { "0.5" } else { "0.35" });
+                format!("https://api.stability.ai/v1/generation/{engine}/image-to-image")
+            }
+        };
 
-        use base64::Engine;
-        let bytes = base64::engine::general_purpose::STANDARD.decode(b64)?;
-        let file_path = output_filename();
+        let resp = reqwest::Client::new()
+            .post(&url)
+            .header("Authorization", format!("Bearer {api_key}"))
+            .header("Accept", "application/json")
+            .multipart(form)
+            .send()
+            .await?;
 
-        if let Some(parent) = file_path.parent() {
-            tokio::fs::create_dir_all(parent).await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            anyhow::bail!("Stability AI API error ({status}): {body}");
         }
-        tokio::fs::write(&file_path, &bytes).await?;
-
-        let size_kb = bytes.len() / 1024;
-        info!(
-            path = %file_path.display(),
-            size_kb,
-            engine,
-            "Image generated (Stability AI)"
-        );
 
-        Ok(ToolOutput {
-            content: format!(
-                "Image saved to: {}\nSize: {}KB\nEngine: {}\nPrompt: {}",
-                file_path.display(),
-                size_kb,
-                engine,
-                prompt,
-            ),
-            is_error: false,
-            media: None,
+        let body: serde_json::Value = resp.json().await?;
+        Ok(Generated {
+            images: decode_images(&body["artifacts"], "base64")?,
+            label: format!("Engine: {engine}"),
+        })
+    }
+}
+
+/// Decode the base64 images in a provider's result array.
+fn decode_images(items: &serde_json::Value, field: &str) -> anyhow::Result<Vec<Vec<u8>>> {
+    let images = items
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item[field].as_str())
+                .map(|b64| base64::engine::general_purpose::STANDARD.decode(b64))
+                .collect::<Result<Vec<_>, _>>()
         })
+        .transpose()?
+        .unwrap_or_default();
+    if images.is_empty() {
+        anyhow::bail!("No image data in response");
     }
+    Ok(images)
 }
 
 #[cfg(test)]
@@ -305,6 +604,89 @@ mod tests {
         assert_eq!(parse_size(""), (1024, 1024));
     }
 
+    #[test]
+    fn test_save_paths() {
+        assert_eq!(save_paths("assets/logo.png", 1), ["assets/logo.png"]);
+        assert_eq!(save_paths("assets/logo", 1), ["assets/logo.png"]);
+        assert_eq!(
+            save_paths("assets/logo.png", 3),
+            ["assets/logo-1.png", "assets/logo-2.png", "assets/logo-3.png"]
+        );
+    }
+
+    #[test]
+    fn test_decode_images() {
+        let body = json!({"data": [{"b64_json": "aGk="}, {"b64_json": "eW8="}]});
+        assert_eq!(decode_images(&body["data"], "b64_json").unwrap(), [b"hi".to_vec(), b"yo".to_vec()]);
+        assert!(decode_images(&json!([]), "b64_json").is_err());
+    }
+
+    #[test]
+    fn test_operation_parse() {
+        assert_eq!(Operation::parse("edit"), Some(Operation::Edit));
+        assert_eq!(Operation::parse("variation"), Some(Operation::Variation));
+        assert_eq!(Operation::parse("upscale"), None);
+    }
+
+    #[tokio::test]
+    async fn test_save_to_outside_workspace_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = rusty_claw_core::config::Config {
+            tools: Some(rusty_claw_core::config::ToolsConfig {
+                image_generation: Some(rusty_claw_core::config::ImageGenerationConfig {
+                    provider: "openai".into(),
+                    api_key: Some("sk-test".into()),
+                    api_key_env: None,
+                    default_model: None,
+                    default_size: None,
+                    default_quality: None,
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let ctx = ToolContext {
+            session_key: "test".into(),
+            workspace: dir.path().to_path_buf(),
+            config: std::sync::Arc::new(config),
+            restrict_to_workspace: true,
+            sandbox_mode: rusty_claw_core::config::SandboxMode::default(),
+            browser_pool: None,
+            progress: None,
+        };
+
+        let result = ImageGenerationTool
+            .execute(json!({"prompt": "a cat", "save_to": "/etc/cat.png"}), &ctx)
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        assert!(result.content.contains("outside the workspace"), "{}", result.content);
+
+        // Input images go through the same guard
+        let result = ImageGenerationTool
+            .execute(json!({"prompt": "a hat", "operation": "edit", "image": "/etc/hostname"}), &ctx)
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        assert!(result.content.contains("outside the workspace"), "{}", result.content);
+
+        let result = ImageGenerationTool
+            .execute(json!({"prompt": "more", "operation": "variation"}), &ctx)
+            .await
+            .unwrap();
+        assert!(result.content.contains("'image' parameter is required"));
+
+        std::fs::write(dir.path().join("cat.png"), b"png").unwrap();
+        let result = ImageGenerationTool
+            .execute(
+                json!({"prompt": "more", "operation": "variation", "image": "cat.png", "mask": "cat.png"}),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        assert!(result.content.contains("only applies to edit"));
+    }
+
     #[tokio::test]
     async fn test_missing_config_returns_error() {
         let ctx = ToolContext {
@@ -314,6 +696,7 @@ mod tests {
             restrict_to_workspace: false,
             sandbox_mode: rusty_claw_core::config::SandboxMode::default(),
             browser_pool: None,
+            progress: None,
         };
 
         let saved = std::env::var("OPENAI_API_KEY").ok();
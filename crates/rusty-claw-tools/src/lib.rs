@@ -8,6 +8,7 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
 
 use rusty_claw_browser::BrowserPool;
 use rusty_claw_core::config::Config;
@@ -18,10 +19,12 @@ pub mod canvas;
 pub mod edit_file;
 pub mod exec;
 pub mod file_list;
+pub mod html_markdown;
 pub mod image_generation;
 pub mod memory;
 pub mod path_guard;
 pub mod read_file;
+pub mod search_files;
 pub mod sessions;
 pub mod transcription;
 pub mod tts;
@@ -37,6 +40,36 @@ pub struct ToolContext {
     pub restrict_to_workspace: bool,
     pub sandbox_mode: rusty_claw_core::config::SandboxMode,
     pub browser_pool: Option<Arc<BrowserPool>>,
+    /// Receives incremental output from long-running tools (e.g. `exec`
+    /// output, `tts` audio), forwarded to clients while the tool runs.
+    pub progress: Option<mpsc::UnboundedSender<ToolProgress>>,
+}
+
+/// Incremental output reported by a running tool.
+#[derive(Debug, Clone)]
+pub enum ToolProgress {
+    /// A chunk of text output, sent as a partial tool result.
+    Output(String),
+    /// A chunk of audio to play as soon as it arrives.
+    Audio { media: ToolMedia, is_final: bool },
+}
+
+impl ToolContext {
+    /// Report incremental output. A no-op when nobody is listening.
+    pub fn report_progress(&self, chunk: impl Into<String>) {
+        if let Some(tx) = &self.progress {
+            let _ = tx.send(ToolProgress::Output(chunk.into()));
+        }
+    }
+
+    /// Report a chunk of audio; returns false when nobody is listening, so
+    /// the tool can fall back to returning the audio in its output.
+    pub fn report_audio(&self, media: ToolMedia, is_final: bool) -> bool {
+        match &self.progress {
+            Some(tx) => tx.send(ToolProgress::Audio { media, is_final }).is_ok(),
+            None => false,
+        }
+    }
 }
 
 /// Output from a tool execution.
@@ -134,6 +167,7 @@ pub fn register_builtin_tools(registry: &mut ToolRegistry) {
     registry.register(Box::new(memory::MemorySetTool));
     registry.register(Box::new(memory::MemoryListTool));
     registry.register(Box::new(memory::MemorySearchTool));
+    registry.register(Box::new(memory::MemoryDeleteTool));
 
     // Session tools
     registry.register(Box::new(sessions::SessionsListTool));
@@ -148,15 +182,19 @@ pub fn register_builtin_tools(registry: &mut ToolRegistry) {
     registry.register(Box::new(browser::BrowserNavigateTool));
     registry.register(Box::new(browser::BrowserScreenshotTool));
     registry.register(Box::new(browser::BrowserClickTool));
+    registry.register(Box::new(browser::BrowserFillTool));
+    registry.register(Box::new(browser::BrowserSelectTool));
     registry.register(Box::new(browser::BrowserExtractTextTool));
     registry.register(Box::new(browser::BrowserEvaluateJsTool));
     registry.register(Box::new(browser::BrowserWaitForTool));
+    registry.register(Box::new(browser::BrowserClearCookiesTool));
 
     // Canvas tool
     registry.register(Box::new(canvas::CanvasTool));
 
-    // File listing tool
+    // File listing and search tools
     registry.register(Box::new(file_list::FileListTool));
+    registry.register(Box::new(search_files::SearchFilesTool));
 
     // Agent spawning tool
     registry.register(Box::new(agents_spawn::AgentsSpawnTool));
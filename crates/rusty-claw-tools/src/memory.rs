@@ -1,14 +1,23 @@
 //! Memory tools — file-based key-value memory for the agent.
 //!
 //! Storage: `~/.rusty_claw/memory/{namespace}.json`
-//! Each namespace is a JSON object mapping keys to string values.
+//! Each namespace is a JSON object mapping keys to string values, or to
+//! `{ "value", "expires_at", "embedding" }` objects for entries stored with
+//! a TTL or an embedding vector. Expired entries are skipped on read and
+//! purged from the file lazily.
+//!
+//! When `memory.embeddings` is configured, `memory_search` ranks entries by
+//! cosine similarity to the query; otherwise it does keyword matching.
 
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 use async_trait::async_trait;
-use serde::Deserialize;
-use tracing::debug;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use rusty_claw_providers::embeddings::{self, EmbeddingProvider};
 
 use crate::{Tool, ToolContext, ToolOutput};
 
@@ -31,7 +40,85 @@ fn namespace_path(base: &Path, namespace: &str) -> PathBuf {
     base.join(format!("{safe_name}.json"))
 }
 
-fn load_namespace(path: &Path) -> HashMap<String, String> {
+/// A stored memory value with optional expiry and embedding.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "StoredEntry", into = "StoredEntry")]
+struct MemoryEntry {
+    value: String,
+    expires_at: Option<DateTime<Utc>>,
+    embedding: Option<Embedding>,
+}
+
+/// Vector for an entry, tagged with the model that produced it so a model
+/// change triggers re-embedding rather than comparing incompatible vectors.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Embedding {
+    model: String,
+    vector: Vec<f32>,
+}
+
+impl MemoryEntry {
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
+}
+
+impl From<&str> for MemoryEntry {
+    fn from(value: &str) -> Self {
+        Self {
+            value: value.to_string(),
+            expires_at: None,
+            embedding: None,
+        }
+    }
+}
+
+/// On-disk form: entries without metadata stay plain strings, so older
+/// files load unchanged.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredEntry {
+    Plain(String),
+    Full {
+        value: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expires_at: Option<DateTime<Utc>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        embedding: Option<Embedding>,
+    },
+}
+
+impl From<StoredEntry> for MemoryEntry {
+    fn from(stored: StoredEntry) -> Self {
+        match stored {
+            StoredEntry::Plain(value) => Self::from(value.as_str()),
+            StoredEntry::Full {
+                value,
+                expires_at,
+                embedding,
+            } => Self {
+                value,
+                expires_at,
+                embedding,
+            },
+        }
+    }
+}
+
+impl From<MemoryEntry> for StoredEntry {
+    fn from(entry: MemoryEntry) -> Self {
+        if entry.expires_at.is_none() && entry.embedding.is_none() {
+            return Self::Plain(entry.value);
+        }
+        Self::Full {
+            value: entry.value,
+            expires_at: entry.expires_at,
+            embedding: entry.embedding,
+        }
+    }
+}
+
+fn load_namespace(path: &Path) -> HashMap<String, MemoryEntry> {
     if !path.exists() {
         return HashMap::new();
     }
@@ -41,7 +128,7 @@ fn load_namespace(path: &Path) -> HashMap<String, String> {
         .unwrap_or_default()
 }
 
-fn save_namespace(path: &Path, data: &HashMap<String, String>) -> anyhow::Result<()> {
+fn save_namespace(path: &Path, data: &HashMap<String, MemoryEntry>) -> anyhow::Result<()> {
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent)?;
     }
@@ -50,6 +137,91 @@ fn save_namespace(path: &Path, data: &HashMap<String, String>) -> anyhow::Result
     Ok(())
 }
 
+/// Load a namespace without its expired entries, rewriting the file if any
+/// were dropped.
+fn load_live_namespace(path: &Path) -> HashMap<String, MemoryEntry> {
+    let mut data = load_namespace(path);
+    let now = Utc::now();
+    let before = data.len();
+    data.retain(|_, entry| !entry.is_expired(now));
+    if data.len() != before {
+        debug!(path = %path.display(), purged = before - data.len(), "Purged expired memory entries");
+        if let Err(e) = save_namespace(path, &data) {
+            debug!(%e, "Failed to rewrite memory namespace after purge");
+        }
+    }
+    data
+}
+
+/// The configured embedding provider, if any.
+fn embedding_provider(context: &ToolContext) -> Option<Box<dyn EmbeddingProvider>> {
+    let config = context.config.memory.as_ref()?.embeddings.as_ref()?;
+    match embeddings::from_config(config) {
+        Ok(provider) => Some(provider),
+        Err(e) => {
+            warn!(%e, "Memory embeddings unavailable, using keyword search");
+            None
+        }
+    }
+}
+
+/// Text embedded for an entry: the key carries meaning too ("car" → "Tesla").
+fn embedding_text(key: &str, value: &str) -> String {
+    format!("{key}: {value}")
+}
+
+/// Rank entries by cosine similarity to `query`, best first. Entries without
+/// a vector from the current model are embedded first; returns whether any
+/// were, so the caller can persist them.
+async fn semantic_rank(
+    data: &mut HashMap<String, MemoryEntry>,
+    query: &str,
+    provider: &dyn EmbeddingProvider,
+) -> anyhow::Result<(Vec<(String, f32)>, bool)> {
+    let model = provider.model();
+    let stale: Vec<String> = data
+        .iter()
+        .filter(|(_, e)| e.embedding.as_ref().is_none_or(|emb| emb.model != model))
+        .map(|(k, _)| k.clone())
+        .collect();
+
+    let mut inputs: Vec<String> = stale
+        .iter()
+        .map(|k| embedding_text(k, &data[k].value))
+        .collect();
+    inputs.push(query.to_string());
+    let mut vectors = provider.embed(&inputs).await?;
+    let query_vector = vectors
+        .pop()
+        .ok_or_else(|| anyhow::anyhow!("embedding provider returned no vectors"))?;
+
+    for (key, vector) in stale.iter().zip(vectors) {
+        if let Some(entry) = data.get_mut(key) {
+            entry.embedding = Some(Embedding {
+                model: model.to_string(),
+                vector,
+            });
+        }
+    }
+
+    let mut ranked: Vec<(String, f32)> = data
+        .iter()
+        .filter_map(|(k, e)| {
+            let emb = e.embedding.as_ref()?;
+            Some((k.clone(), embeddings::cosine_similarity(&emb.vector, &query_vector)))
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    Ok((ranked, !stale.is_empty()))
+}
+
+fn preview(value: &str) -> String {
+    match value.char_indices().nth(100) {
+        Some((i, _)) => format!("{}...", &value[..i]),
+        None => value.to_string(),
+    }
+}
+
 // --- MemoryGetTool ---
 
 pub struct MemoryGetTool;
@@ -102,11 +274,11 @@ impl Tool for MemoryGetTool {
 
         let dir = memory_dir(context);
         let path = namespace_path(&dir, &p.namespace);
-        let data = load_namespace(&path);
+        let data = load_live_namespace(&path);
 
         match data.get(&p.key) {
-            Some(val) => Ok(ToolOutput {
-                content: val.clone(),
+            Some(entry) => Ok(ToolOutput {
+                content: entry.value.clone(),
                 is_error: false,
                 media: None,
             }),
@@ -129,6 +301,8 @@ struct SetParams {
     value: String,
     #[serde(default = "default_namespace")]
     namespace: String,
+    #[serde(default)]
+    ttl_seconds: Option<u64>,
 }
 
 #[async_trait]
@@ -138,7 +312,7 @@ impl Tool for MemorySetTool {
     }
 
     fn description(&self) -> &str {
-        "Store a key-value pair in persistent memory. This persists across sessions."
+        "Store a key-value pair in persistent memory. This persists across sessions unless ttl_seconds is given, after which the entry expires."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
@@ -153,6 +327,10 @@ impl Tool for MemorySetTool {
                     "type": "string",
                     "description": "The value to store"
                 },
+                "ttl_seconds": {
+                    "type": "integer",
+                    "description": "Forget the entry after this many seconds (for transient facts). Default: never"
+                },
                 "namespace": {
                     "type": "string",
                     "description": "Memory namespace (default: 'default')"
@@ -172,12 +350,43 @@ impl Tool for MemorySetTool {
 
         let dir = memory_dir(context);
         let path = namespace_path(&dir, &p.namespace);
-        let mut data = load_namespace(&path);
-        data.insert(p.key.clone(), p.value);
+        let mut data = load_live_namespace(&path);
+        let expires_at = p.ttl_seconds.and_then(|ttl| {
+            let ttl = chrono::Duration::try_seconds(i64::try_from(ttl).ok()?)?;
+            Utc::now().checked_add_signed(ttl)
+        });
+
+        let embedding = match embedding_provider(context) {
+            Some(provider) => match provider.embed(&[embedding_text(&p.key, &p.value)]).await {
+                Ok(mut vectors) => vectors.pop().map(|vector| Embedding {
+                    model: provider.model().to_string(),
+                    vector,
+                }),
+                Err(e) => {
+                    // Stored without a vector; memory_search embeds it later
+                    warn!(%e, key = %p.key, "Failed to embed memory entry");
+                    None
+                }
+            },
+            None => None,
+        };
+
+        data.insert(
+            p.key.clone(),
+            MemoryEntry {
+                value: p.value,
+                expires_at,
+                embedding,
+            },
+        );
         save_namespace(&path, &data)?;
 
+        let expiry = match expires_at {
+            Some(at) => format!(" (expires {})", at.to_rfc3339()),
+            None => String::new(),
+        };
         Ok(ToolOutput {
-            content: format!("Stored key '{}' in namespace '{}'", p.key, p.namespace),
+            content: format!("Stored key '{}' in namespace '{}'{expiry}", p.key, p.namespace),
             is_error: false,
             media: None,
         })
@@ -226,7 +435,7 @@ impl Tool for MemoryListTool {
 
         let dir = memory_dir(context);
         let path = namespace_path(&dir, &p.namespace);
-        let data = load_namespace(&path);
+        let data = load_live_namespace(&path);
 
         if data.is_empty() {
             return Ok(ToolOutput {
@@ -257,8 +466,13 @@ struct SearchParams {
     query: String,
     #[serde(default = "default_namespace")]
     namespace: String,
+    #[serde(default)]
+    limit: Option<usize>,
 }
 
+/// Results returned by a semantic search when no limit is given.
+const DEFAULT_SEMANTIC_LIMIT: usize = 5;
+
 #[async_trait]
 impl Tool for MemorySearchTool {
     fn name(&self) -> &str {
@@ -266,7 +480,7 @@ impl Tool for MemorySearchTool {
     }
 
     fn description(&self) -> &str {
-        "Search for keys and values containing the query string in a memory namespace."
+        "Search a memory namespace. Ranks entries by meaning when embeddings are configured, otherwise matches keys and values containing the query string."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
@@ -275,7 +489,11 @@ impl Tool for MemorySearchTool {
             "properties": {
                 "query": {
                     "type": "string",
-                    "description": "Substring to search for in keys and values"
+                    "description": "What to look for (a question or keywords)"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Maximum number of results (default: 5 for semantic search, all keyword matches otherwise)"
                 },
                 "namespace": {
                     "type": "string",
@@ -296,11 +514,24 @@ impl Tool for MemorySearchTool {
 
         let dir = memory_dir(context);
         let path = namespace_path(&dir, &p.namespace);
-        let data = load_namespace(&path);
+        let mut data = load_live_namespace(&path);
+
+        if let Some(provider) = embedding_provider(context) {
+            match semantic_rank(&mut data, &p.query, provider.as_ref()).await {
+                Ok((ranked, embedded)) => {
+                    if embedded && let Err(e) = save_namespace(&path, &data) {
+                        debug!(%e, "Failed to persist memory embeddings");
+                    }
+                    return Ok(render_ranked(&p, &data, ranked));
+                }
+                Err(e) => warn!(%e, "Semantic memory search failed, using keyword search"),
+            }
+        }
 
         let query_lower = p.query.to_lowercase();
-        let matches: Vec<(&String, &String)> = data
+        let mut matches: Vec<(&String, &String)> = data
             .iter()
+            .map(|(k, entry)| (k, &entry.value))
             .filter(|(k, v)| {
                 k.to_lowercase().contains(&query_lower) || v.to_lowercase().contains(&query_lower)
             })
@@ -314,14 +545,13 @@ impl Tool for MemorySearchTool {
             });
         }
 
+        matches.sort();
+        if let Some(limit) = p.limit {
+            matches.truncate(limit);
+        }
         let mut output = format!("Found {} matches:\n\n", matches.len());
         for (k, v) in &matches {
-            let preview = if v.len() > 100 {
-                format!("{}...", &v[..100])
-            } else {
-                (*v).clone()
-            };
-            output.push_str(&format!("- **{k}**: {preview}\n"));
+            output.push_str(&format!("- **{k}**: {}\n", preview(v)));
         }
 
         Ok(ToolOutput {
@@ -332,6 +562,96 @@ impl Tool for MemorySearchTool {
     }
 }
 
+fn render_ranked(
+    p: &SearchParams,
+    data: &HashMap<String, MemoryEntry>,
+    mut ranked: Vec<(String, f32)>,
+) -> ToolOutput {
+    ranked.truncate(p.limit.unwrap_or(DEFAULT_SEMANTIC_LIMIT));
+    if ranked.is_empty() {
+        return ToolOutput {
+            content: format!("No matches for '{}' in namespace '{}'", p.query, p.namespace),
+            is_error: false,
+            media: None,
+        };
+    }
+
+    let mut output = format!("Found {} matches (ranked by similarity):\n\n", ranked.len());
+    for (k, score) in &ranked {
+        output.push_str(&format!("- **{k}** ({score:.2}): {}\n", preview(&data[k].value)));
+    }
+    ToolOutput {
+        content: output,
+        is_error: false,
+        media: None,
+    }
+}
+
+// --- MemoryDeleteTool ---
+
+pub struct MemoryDeleteTool;
+
+#[derive(Deserialize)]
+struct DeleteParams {
+    key: String,
+    #[serde(default = "default_namespace")]
+    namespace: String,
+}
+
+#[async_trait]
+impl Tool for MemoryDeleteTool {
+    fn name(&self) -> &str {
+        "memory_delete"
+    }
+
+    fn description(&self) -> &str {
+        "Delete a key from persistent memory, forgetting its value."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "key": {
+                    "type": "string",
+                    "description": "The key to delete"
+                },
+                "namespace": {
+                    "type": "string",
+                    "description": "Memory namespace (default: 'default')"
+                }
+            },
+            "required": ["key"]
+        })
+    }
+
+    async fn execute(
+        &self,
+        params: serde_json::Value,
+        context: &ToolContext,
+    ) -> anyhow::Result<ToolOutput> {
+        let p: DeleteParams = serde_json::from_value(params)?;
+        debug!(key = %p.key, namespace = %p.namespace, "memory_delete");
+
+        let dir = memory_dir(context);
+        let path = namespace_path(&dir, &p.namespace);
+        let mut data = load_live_namespace(&path);
+
+        let content = if data.remove(&p.key).is_some() {
+            save_namespace(&path, &data)?;
+            format!("Deleted key '{}' from namespace '{}'", p.key, p.namespace)
+        } else {
+            format!("Key '{}' not found in namespace '{}'", p.key, p.namespace)
+        };
+
+        Ok(ToolOutput {
+            content,
+            is_error: false,
+            media: None,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -364,8 +684,8 @@ mod tests {
 
         save_namespace(&path, &data).unwrap();
         let loaded = load_namespace(&path);
-        assert_eq!(loaded.get("key1").unwrap(), "value1");
-        assert_eq!(loaded.get("key2").unwrap(), "value2");
+        assert_eq!(loaded.get("key1").unwrap().value, "value1");
+        assert_eq!(loaded.get("key2").unwrap().value, "value2");
     }
 
     #[test]
@@ -373,4 +693,135 @@ mod tests {
         let data = load_namespace(Path::new("/nonexistent/path.json"));
         assert!(data.is_empty());
     }
+
+    #[test]
+    fn test_plain_and_expiring_entries_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ns.json");
+        std::fs::write(
+            &path,
+            r#"{"old": "plain string", "fresh": {"value": "v", "expires_at": "2999-01-01T00:00:00Z"}}"#,
+        )
+        .unwrap();
+
+        let data = load_namespace(&path);
+        assert_eq!(data["old"].value, "plain string");
+        assert!(data["old"].expires_at.is_none());
+        assert!(data["fresh"].expires_at.is_some());
+
+        save_namespace(&path, &data).unwrap();
+        let raw: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(raw["old"], "plain string");
+        assert_eq!(raw["fresh"]["value"], "v");
+    }
+
+    #[test]
+    fn test_expired_entries_are_purged() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ns.json");
+        let mut data = HashMap::new();
+        data.insert("keep".to_string(), MemoryEntry::from("forever"));
+        data.insert(
+            "gone".to_string(),
+            MemoryEntry {
+                value: "in a meeting".into(),
+                expires_at: Some(Utc::now() - chrono::Duration::seconds(1)),
+                embedding: None,
+            },
+        );
+        save_namespace(&path, &data).unwrap();
+
+        let live = load_live_namespace(&path);
+        assert!(live.contains_key("keep"));
+        assert!(!live.contains_key("gone"));
+        // The file itself was rewritten without the expired entry
+        assert!(!load_namespace(&path).contains_key("gone"));
+    }
+
+    #[tokio::test]
+    async fn test_ttl_and_delete_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = rusty_claw_core::config::Config {
+            memory: Some(rusty_claw_core::config::MemoryConfig {
+                dir: Some(dir.path().display().to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let ctx = ToolContext {
+            session_key: "test".into(),
+            workspace: dir.path().to_path_buf(),
+            config: std::sync::Arc::new(config),
+            restrict_to_workspace: true,
+            sandbox_mode: Default::default(),
+            browser_pool: None,
+            progress: None,
+        };
+        let run = |tool: &'static dyn Tool, params: serde_json::Value| {
+            let ctx = &ctx;
+            async move { tool.execute(params, ctx).await.unwrap().content }
+        };
+
+        run(&MemorySetTool, serde_json::json!({"key": "status", "value": "busy", "ttl_seconds": 0})).await;
+        run(&MemorySetTool, serde_json::json!({"key": "car", "value": "Tesla"})).await;
+        assert!(run(&MemoryGetTool, serde_json::json!({"key": "status"})).await.contains("not found"));
+        assert_eq!(run(&MemoryListTool, serde_json::json!({})).await, "Keys in 'default' (1):\ncar");
+
+        let deleted = run(&MemoryDeleteTool, serde_json::json!({"key": "car"})).await;
+        assert_eq!(deleted, "Deleted key 'car' from namespace 'default'");
+        assert!(run(&MemoryGetTool, serde_json::json!({"key": "car"})).await.contains("not found"));
+        assert!(run(&MemoryDeleteTool, serde_json::json!({"key": "car"})).await.contains("not found"));
+    }
+
+    /// Maps text onto two concepts (vehicles, food) by keyword.
+    struct ConceptEmbeddings(&'static str);
+
+    #[async_trait]
+    impl EmbeddingProvider for ConceptEmbeddings {
+        fn id(&self) -> &str {
+            "test"
+        }
+
+        fn model(&self) -> &str {
+            self.0
+        }
+
+        async fn embed(&self, texts: &[String]) -> anyhow::Result<Vec<Vec<f32>>> {
+            let score = |t: &str, words: &[&str]| {
+                words.iter().filter(|w| t.to_lowercase().contains(*w)).count() as f32
+            };
+            Ok(texts
+                .iter()
+                .map(|t| {
+                    vec![
+                        score(t, &["car", "drive", "tesla"]) + 0.1,
+                        score(t, &["food", "eat", "pizza"]) + 0.1,
+                    ]
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_semantic_rank_backfills_embeddings() {
+        let mut data = HashMap::new();
+        data.insert("vehicle".to_string(), MemoryEntry::from("owns a Tesla Model 3"));
+        data.insert("favorite".to_string(), MemoryEntry::from("pepperoni pizza"));
+
+        let (ranked, embedded) = semantic_rank(&mut data, "what car do I drive", &ConceptEmbeddings("v1"))
+            .await
+            .unwrap();
+        assert!(embedded);
+        assert_eq!(ranked[0].0, "vehicle");
+        assert!(ranked[0].1 > ranked[1].1);
+        assert_eq!(data["vehicle"].embedding.as_ref().unwrap().model, "v1");
+
+        // Vectors are reused for the same model and redone for a new one
+        let (_, embedded) = semantic_rank(&mut data, "food", &ConceptEmbeddings("v1")).await.unwrap();
+        assert!(!embedded);
+        let (_, embedded) = semantic_rank(&mut data, "food", &ConceptEmbeddings("v2")).await.unwrap();
+        assert!(embedded);
+        assert_eq!(data["favorite"].embedding.as_ref().unwrap().model, "v2");
+    }
 }
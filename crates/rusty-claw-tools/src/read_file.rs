@@ -1,4 +1,7 @@
 //! File reading tool.
+//!
+//! Large files are paged: a line range selects what to read and output is
+//! capped at `max_bytes`, with a footer saying which lines were shown.
 
 use async_trait::async_trait;
 use serde_json::json;
@@ -6,6 +9,12 @@ use serde_json::json;
 use crate::path_guard::validate_path;
 use crate::{Tool, ToolContext, ToolOutput};
 
+/// Output cap when the caller does not pass `max_bytes`.
+const DEFAULT_MAX_BYTES: usize = 100_000;
+
+/// How much of the file is sniffed for NUL bytes when detecting binaries.
+const BINARY_SNIFF_BYTES: usize = 8192;
+
 pub struct ReadFileTool;
 
 #[async_trait]
@@ -15,7 +24,7 @@ impl Tool for ReadFileTool {
     }
 
     fn description(&self) -> &str {
-        "Read the contents of a file, optionally with line offset and limit. Returns content with line numbers."
+        "Read the contents of a file with line numbers. Use start_line/end_line to page through large files; output is capped at max_bytes and a footer shows which lines were returned."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
@@ -26,13 +35,25 @@ impl Tool for ReadFileTool {
                     "type": "string",
                     "description": "Path to the file (relative to workspace or absolute)"
                 },
+                "start_line": {
+                    "type": "integer",
+                    "description": "First line to return (1-indexed, inclusive)"
+                },
+                "end_line": {
+                    "type": "integer",
+                    "description": "Last line to return (1-indexed, inclusive)"
+                },
+                "max_bytes": {
+                    "type": "integer",
+                    "description": "Maximum bytes of output (default: 100000)"
+                },
                 "offset": {
                     "type": "integer",
-                    "description": "Line number to start from (0-indexed)"
+                    "description": "Line number to start from (0-indexed); prefer start_line"
                 },
                 "limit": {
                     "type": "integer",
-                    "description": "Maximum number of lines to return"
+                    "description": "Maximum number of lines to return; prefer end_line"
                 }
             },
             "required": ["path"]
@@ -49,8 +70,19 @@ impl Tool for ReadFileTool {
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow::anyhow!("missing 'path' parameter"))?;
 
-        let offset = params.get("offset").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
-        let limit = params.get("limit").and_then(|v| v.as_u64()).map(|v| v as usize);
+        let get = |key: &str| params.get(key).and_then(|v| v.as_u64()).map(|v| v as usize);
+        // 0-indexed first line and exclusive end; start_line/end_line win
+        // over the older offset/limit pair
+        let first = match get("start_line") {
+            Some(line) => line.saturating_sub(1),
+            None => get("offset").unwrap_or(0),
+        };
+        let end = match (get("end_line"), get("limit")) {
+            (Some(line), _) => Some(line),
+            (None, Some(limit)) => Some(first + limit),
+            (None, None) => None,
+        };
+        let max_bytes = get("max_bytes").unwrap_or(DEFAULT_MAX_BYTES);
 
         let path = match validate_path(raw_path, &context.workspace, context.restrict_to_workspace)
         {
@@ -80,8 +112,8 @@ impl Tool for ReadFileTool {
             });
         }
 
-        let content = match tokio::fs::read_to_string(&path).await {
-            Ok(c) => c,
+        let bytes = match tokio::fs::read(&path).await {
+            Ok(b) => b,
             Err(e) => {
                 return Ok(ToolOutput {
                     content: format!("Read error: {e}"),
@@ -91,34 +123,88 @@ impl Tool for ReadFileTool {
             }
         };
 
-        let lines: Vec<&str> = content.lines().collect();
-        let total = lines.len();
-
-        let end = match limit {
-            Some(lim) => (offset + lim).min(total),
-            None => total,
+        let content = match decode_text(&bytes) {
+            Ok(c) => c,
+            Err(reason) => {
+                return Ok(ToolOutput {
+                    content: format!(
+                        "Cannot read {}: {reason}. Use exec with a tool like `file` or `xxd` to inspect binary files.",
+                        path.display()
+                    ),
+                    is_error: true,
+                    media: None,
+                });
+            }
         };
 
-        let selected = &lines[offset.min(total)..end];
-
-        let mut result = String::new();
-        for (i, line) in selected.iter().enumerate() {
-            let line_num = offset + i + 1;
-            result.push_str(&format!("{line_num:>6}\t{line}\n"));
-        }
-
-        if result.is_empty() {
-            result = "(empty file or offset beyond end)".into();
-        }
-
         Ok(ToolOutput {
-            content: result,
+            content: render_lines(content, first, end, max_bytes),
             is_error: false,
             media: None,
         })
     }
 }
 
+/// Decode file contents as UTF-8 text, explaining why binary data is not.
+fn decode_text(bytes: &[u8]) -> Result<&str, String> {
+    let sniff = &bytes[..bytes.len().min(BINARY_SNIFF_BYTES)];
+    if sniff.contains(&0) {
+        return Err(format!("file appears to be binary ({} bytes)", bytes.len()));
+    }
+    std::str::from_utf8(bytes)
+        .map_err(|e| format!("file is not valid UTF-8 text (invalid byte at offset {})", e.valid_up_to()))
+}
+
+/// Number the lines in `first..end` (0-indexed, exclusive end), stopping
+/// once `max_bytes` of output is reached. A footer is added whenever the
+/// output does not cover the whole file.
+fn render_lines(content: &str, first: usize, end: Option<usize>, max_bytes: usize) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let total = lines.len();
+    let first = first.min(total);
+    let end = end.unwrap_or(total).clamp(first, total);
+
+    let mut result = String::new();
+    let mut shown = 0;
+    let mut cut_line = false;
+    for (i, line) in lines[first..end].iter().enumerate() {
+        let numbered = format!("{:>6}\t{line}\n", first + i + 1);
+        if result.len() + numbered.len() > max_bytes {
+            if shown == 0 {
+                // Always make progress: show the start of an oversized line
+                let mut cut = max_bytes.min(numbered.len());
+                while !numbered.is_char_boundary(cut) {
+                    cut -= 1;
+                }
+                result.push_str(&numbered[..cut]);
+                result.push_str("…\n");
+                shown = 1;
+                cut_line = true;
+            }
+            break;
+        }
+        result.push_str(&numbered);
+        shown += 1;
+    }
+
+    if result.is_empty() {
+        return "(empty file or offset beyond end)".into();
+    }
+    if first > 0 || first + shown < total || cut_line {
+        let cut = if cut_line {
+            format!(", line {} cut at {max_bytes} bytes", first + 1)
+        } else {
+            String::new()
+        };
+        result.push_str(&format!(
+            "[truncated: showing lines {}–{} of {total}{cut}]\n",
+            first + 1,
+            first + shown
+        ));
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -137,6 +223,7 @@ mod tests {
             restrict_to_workspace: true,
             sandbox_mode: rusty_claw_core::config::SandboxMode::default(),
             browser_pool: None,
+            progress: None,
         };
 
         let result = ReadFileTool
@@ -161,6 +248,7 @@ mod tests {
             restrict_to_workspace: true,
             sandbox_mode: rusty_claw_core::config::SandboxMode::default(),
             browser_pool: None,
+            progress: None,
         };
 
         let result = ReadFileTool
@@ -183,6 +271,7 @@ mod tests {
             restrict_to_workspace: true,
             sandbox_mode: rusty_claw_core::config::SandboxMode::default(),
             browser_pool: None,
+            progress: None,
         };
 
         let result = ReadFileTool
@@ -192,4 +281,72 @@ mod tests {
         assert!(result.is_error);
         assert!(result.content.contains("not found"));
     }
+
+    #[test]
+    fn test_render_line_range_footer() {
+        let content: String = (1..=5000).map(|i| format!("line {i}\n")).collect();
+        let out = render_lines(&content, 0, Some(200), DEFAULT_MAX_BYTES);
+        assert!(out.starts_with("     1\tline 1\n"));
+        assert!(out.contains("   200\tline 200\n"));
+        assert!(!out.contains("line 201"));
+        assert!(out.ends_with("[truncated: showing lines 1–200 of 5000]\n"));
+
+        // The whole file needs no footer
+        assert_eq!(render_lines("a\nb", 0, None, DEFAULT_MAX_BYTES), "     1\ta\n     2\tb\n");
+    }
+
+    #[test]
+    fn test_render_stops_at_max_bytes() {
+        let content: String = (1..=100).map(|i| format!("line {i}\n")).collect();
+        // Each numbered line is 14 bytes for lines 1-9
+        let out = render_lines(&content, 0, None, 30);
+        assert!(out.ends_with("[truncated: showing lines 1–2 of 100]\n"), "{out}");
+
+        // An oversized single line is cut rather than skipped
+        let out = render_lines(&"é".repeat(100), 0, None, 20);
+        assert!(out.contains('…'));
+        assert!(out.ends_with("[truncated: showing lines 1–1 of 1, line 1 cut at 20 bytes]\n"));
+    }
+
+    #[tokio::test]
+    async fn test_read_file_start_end_lines_and_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path();
+        std::fs::write(workspace.join("log.txt"), "a\nb\nc\nd\ne").unwrap();
+        std::fs::write(workspace.join("blob.bin"), [0x7f, b'E', b'L', b'F', 0, 1, 2]).unwrap();
+        std::fs::write(workspace.join("latin1.txt"), [b'c', b'a', b'f', 0xe9]).unwrap();
+
+        let ctx = ToolContext {
+            session_key: "test".into(),
+            workspace: workspace.to_path_buf(),
+            config: Arc::new(rusty_claw_core::config::Config::default()),
+            restrict_to_workspace: true,
+            sandbox_mode: rusty_claw_core::config::SandboxMode::default(),
+            browser_pool: None,
+            progress: None,
+        };
+
+        let result = ReadFileTool
+            .execute(json!({"path": "log.txt", "start_line": 2, "end_line": 3}), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(
+            result.content,
+            "     2\tb\n     3\tc\n[truncated: showing lines 2–3 of 5]\n"
+        );
+
+        let result = ReadFileTool
+            .execute(json!({"path": "blob.bin"}), &ctx)
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        assert!(result.content.contains("appears to be binary"));
+
+        let result = ReadFileTool
+            .execute(json!({"path": "latin1.txt"}), &ctx)
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        assert!(result.content.contains("invalid byte at offset 3"));
+    }
 }
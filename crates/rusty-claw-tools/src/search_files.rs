@@ -0,0 +1,414 @@
+//! Workspace search tool — find files by glob and grep their contents.
+//!
+//! The walk honors `.gitignore`/`.ignore` files and skips hidden entries.
+//! Output is capped at `max_results` matches (or files, without a regex).
+
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::path_guard::validate_path;
+use crate::{Tool, ToolContext, ToolOutput};
+
+/// Hard ceiling on `max_results`.
+const MAX_RESULTS_LIMIT: usize = 1000;
+
+/// Files larger than this are skipped when searching contents.
+const MAX_SEARCH_FILE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Matched and context lines are shortened past this many characters.
+const MAX_LINE_CHARS: usize = 300;
+
+pub struct SearchFilesTool;
+
+#[derive(Deserialize)]
+struct Params {
+    #[serde(default)]
+    pattern: Option<String>,
+    #[serde(default)]
+    regex: Option<String>,
+    #[serde(default)]
+    path: Option<String>,
+    #[serde(default = "default_context_lines")]
+    context_lines: usize,
+    #[serde(default)]
+    case_insensitive: bool,
+    #[serde(default = "default_max_results")]
+    max_results: usize,
+}
+
+fn default_context_lines() -> usize {
+    2
+}
+
+fn default_max_results() -> usize {
+    100
+}
+
+struct SearchRequest {
+    root: PathBuf,
+    workspace: PathBuf,
+    restrict: bool,
+    glob: Option<glob::Pattern>,
+    regex: Option<regex::Regex>,
+    context_lines: usize,
+    max_results: usize,
+}
+
+/// Walk `root` and render the results.
+fn search(req: &SearchRequest) -> String {
+    let workspace_canon = req
+        .workspace
+        .canonicalize()
+        .unwrap_or_else(|_| req.workspace.clone());
+    let walker = ignore::WalkBuilder::new(&req.root)
+        .require_git(false)
+        .sort_by_file_path(|a, b| a.cmp(b))
+        .build();
+
+    let mut out = String::new();
+    let mut results = 0;
+    let mut files_matched = 0;
+    let mut capped = false;
+
+    for entry in walker.flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file() || t.is_symlink()) {
+            continue;
+        }
+        let path = entry.path();
+        let relative = path.strip_prefix(&req.root).unwrap_or(path);
+        if let Some(glob) = &req.glob
+            && !glob_matches(glob, relative)
+        {
+            continue;
+        }
+        // Symlinks may point outside the workspace; resolve before reading
+        let Ok(resolved) = path.canonicalize() else {
+            continue;
+        };
+        if !resolved.is_file() || (req.restrict && !resolved.starts_with(&workspace_canon)) {
+            continue;
+        }
+        let display = path
+            .strip_prefix(&req.workspace)
+            .or_else(|_| path.strip_prefix(&workspace_canon))
+            .unwrap_or(path)
+            .display()
+            .to_string();
+
+        let Some(regex) = &req.regex else {
+            if results == req.max_results {
+                capped = true;
+                break;
+            }
+            out.push_str(&display);
+            out.push('\n');
+            results += 1;
+            continue;
+        };
+
+        if std::fs::metadata(&resolved).is_ok_and(|m| m.len() > MAX_SEARCH_FILE_BYTES) {
+            continue;
+        }
+        let Ok(bytes) = std::fs::read(&resolved) else {
+            continue;
+        };
+        if bytes[..bytes.len().min(8192)].contains(&0) {
+            continue; // binary
+        }
+        let Ok(text) = std::str::from_utf8(&bytes) else {
+            continue;
+        };
+        let lines: Vec<&str> = text.lines().collect();
+        let matches: Vec<usize> = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| regex.is_match(line))
+            .map(|(i, _)| i)
+            .collect();
+        if matches.is_empty() {
+            continue;
+        }
+
+        if files_matched > 0 {
+            out.push('\n');
+        }
+        files_matched += 1;
+        let mut last_printed: Option<usize> = None;
+        for &m in &matches {
+            if results == req.max_results {
+                capped = true;
+                break;
+            }
+            results += 1;
+            let start = m.saturating_sub(req.context_lines);
+            let end = (m + req.context_lines).min(lines.len() - 1);
+            let from = match last_printed {
+                Some(last) if last + 1 >= start => last + 1,
+                Some(_) => {
+                    out.push_str("--\n");
+                    start
+                }
+                None => start,
+            };
+            for (i, line) in lines.iter().enumerate().take(end + 1).skip(from) {
+                // ripgrep style: `path:line:` for matches, `path-line-` for context
+                let sep = if regex.is_match(line) { ':' } else { '-' };
+                out.push_str(&format!("{display}{sep}{}{sep}{}\n", i + 1, shorten(line)));
+            }
+            last_printed = Some(end.max(last_printed.unwrap_or(0)));
+        }
+        if capped {
+            break;
+        }
+    }
+
+    if out.is_empty() {
+        return "No matches found".into();
+    }
+    if capped {
+        out.push_str(&format!(
+            "[results capped at {}; narrow the pattern or path]\n",
+            req.max_results
+        ));
+    }
+    out
+}
+
+/// Patterns with a `/` match the path below the search root; others match
+/// the file name, so `*.rs` finds Rust files at any depth.
+fn glob_matches(glob: &glob::Pattern, relative: &Path) -> bool {
+    if glob.as_str().contains('/') {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        glob.matches_path_with(relative, options)
+    } else {
+        relative
+            .file_name()
+            .is_some_and(|name| glob.matches(&name.to_string_lossy()))
+    }
+}
+
+fn shorten(line: &str) -> String {
+    if line.chars().count() <= MAX_LINE_CHARS {
+        return line.to_string();
+    }
+    let cut: String = line.chars().take(MAX_LINE_CHARS).collect();
+    format!("{cut}…")
+}
+
+fn error_output(content: String) -> ToolOutput {
+    ToolOutput {
+        content,
+        is_error: true,
+        media: None,
+    }
+}
+
+#[async_trait]
+impl Tool for SearchFilesTool {
+    fn name(&self) -> &str {
+        "search_files"
+    }
+
+    fn description(&self) -> &str {
+        "Search the workspace: find files matching a glob pattern and, with a regex, the matching lines with line numbers and surrounding context. Respects .gitignore."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "pattern": {
+                    "type": "string",
+                    "description": "Glob for files to include (e.g. \"*.rs\", \"src/**/*.ts\"). Without a '/', matches file names at any depth. Default: all files"
+                },
+                "regex": {
+                    "type": "string",
+                    "description": "Regular expression to search file contents for. Without it, matching file paths are listed"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Directory to search (relative to workspace or absolute). Default: \".\""
+                },
+                "context_lines": {
+                    "type": "integer",
+                    "description": "Lines of context around each match. Default: 2"
+                },
+                "case_insensitive": {
+                    "type": "boolean",
+                    "description": "Match the regex case-insensitively. Default: false"
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Maximum matches (or files, without a regex) to return. Default: 100"
+                }
+            }
+        })
+    }
+
+    async fn execute(
+        &self,
+        params: serde_json::Value,
+        context: &ToolContext,
+    ) -> anyhow::Result<ToolOutput> {
+        let p: Params = serde_json::from_value(params)?;
+
+        let root = match validate_path(
+            p.path.as_deref().unwrap_or("."),
+            &context.workspace,
+            context.restrict_to_workspace,
+        ) {
+            Ok(root) => root,
+            Err(e) => return Ok(error_output(format!("Path error: {e}"))),
+        };
+        if !root.is_dir() {
+            return Ok(error_output(format!("Not a directory: {}", root.display())));
+        }
+
+        let glob = match p.pattern.as_deref().map(glob::Pattern::new).transpose() {
+            Ok(glob) => glob,
+            Err(e) => return Ok(error_output(format!("Invalid glob pattern: {e}"))),
+        };
+        let regex = match p
+            .regex
+            .as_deref()
+            .map(|r| {
+                regex::RegexBuilder::new(r)
+                    .case_insensitive(p.case_insensitive)
+                    .build()
+            })
+            .transpose()
+        {
+            Ok(regex) => regex,
+            Err(e) => return Ok(error_output(format!("Invalid regex: {e}"))),
+        };
+
+        let request = SearchRequest {
+            root,
+            workspace: context.workspace.clone(),
+            restrict: context.restrict_to_workspace,
+            glob,
+            regex,
+            context_lines: p.context_lines,
+            max_results: p.max_results.clamp(1, MAX_RESULTS_LIMIT),
+        };
+        let content = tokio::task::spawn_blocking(move || search(&request)).await?;
+
+        Ok(ToolOutput {
+            content,
+            is_error: false,
+            media: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn workspace() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/nested")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {\n    helper();\n}\n\nfn helper() {}\n").unwrap();
+        std::fs::write(root.join("src/nested/lib.rs"), "// TODO: helper docs\n").unwrap();
+        std::fs::write(root.join("target/out.rs"), "fn helper() {}\n").unwrap();
+        std::fs::write(root.join("notes.md"), "helper notes\n").unwrap();
+        dir
+    }
+
+    fn context(workspace: &Path) -> ToolContext {
+        ToolContext {
+            session_key: "test".into(),
+            workspace: workspace.to_path_buf(),
+            config: Arc::new(rusty_claw_core::config::Config::default()),
+            restrict_to_workspace: true,
+            sandbox_mode: rusty_claw_core::config::SandboxMode::default(),
+            browser_pool: None,
+            progress: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_glob_lists_files_respecting_gitignore() {
+        let dir = workspace();
+        let result = SearchFilesTool
+            .execute(json!({"pattern": "*.rs"}), &context(dir.path()))
+            .await
+            .unwrap();
+        assert_eq!(result.content, "src/main.rs\nsrc/nested/lib.rs\n");
+
+        let result = SearchFilesTool
+            .execute(json!({"pattern": "src/*.rs"}), &context(dir.path()))
+            .await
+            .unwrap();
+        assert_eq!(result.content, "src/main.rs\n");
+
+        let result = SearchFilesTool
+            .execute(json!({"pattern": "src/**/*.rs"}), &context(dir.path()))
+            .await
+            .unwrap();
+        assert_eq!(result.content, "src/main.rs\nsrc/nested/lib.rs\n");
+    }
+
+    #[tokio::test]
+    async fn test_regex_with_context() {
+        let dir = workspace();
+        let result = SearchFilesTool
+            .execute(
+                json!({"pattern": "*.rs", "regex": "fn helper", "context_lines": 1}),
+                &context(dir.path()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.content, "src/main.rs-4-\nsrc/main.rs:5:fn helper() {}\n");
+
+        let result = SearchFilesTool
+            .execute(json!({"regex": "HELPER", "case_insensitive": true, "context_lines": 0}), &context(dir.path()))
+            .await
+            .unwrap();
+        assert_eq!(
+            result.content,
+            "notes.md:1:helper notes\n\n\
+             src/main.rs:2:    helper();\n--\nsrc/main.rs:5:fn helper() {}\n\n\
+             src/nested/lib.rs:1:// TODO: helper docs\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_results_are_capped() {
+        let dir = workspace();
+        let result = SearchFilesTool
+            .execute(json!({"regex": "helper", "max_results": 2, "context_lines": 0}), &context(dir.path()))
+            .await
+            .unwrap();
+        assert_eq!(result.content.lines().filter(|l| l.contains(":")).count(), 2);
+        assert!(result.content.ends_with("[results capped at 2; narrow the pattern or path]\n"));
+    }
+
+    #[tokio::test]
+    async fn test_cannot_escape_workspace() {
+        let dir = workspace();
+        let result = SearchFilesTool
+            .execute(json!({"path": "..", "regex": "x"}), &context(dir.path()))
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        assert!(result.content.contains("outside the workspace"));
+
+        let result = SearchFilesTool
+            .execute(json!({"regex": "("}), &context(dir.path()))
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        assert!(result.content.starts_with("Invalid regex"));
+    }
+}
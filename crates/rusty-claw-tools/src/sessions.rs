@@ -30,15 +30,11 @@ impl Tool for SessionsListTool {
     async fn execute(
         &self,
         _params: serde_json::Value,
-        _context: &ToolContext,
+        context: &ToolContext,
     ) -> anyhow::Result<ToolOutput> {
         debug!("sessions_list");
 
-        let store = rusty_claw_core::session_store::JsonlSessionStore::new(
-            rusty_claw_core::session_store::JsonlSessionStore::default_path(),
-        );
-
-        use rusty_claw_core::session::SessionStore;
+        let store = rusty_claw_core::session_store::open_session_store(&context.config).await?;
         let sessions = store.list().await?;
 
         if sessions.is_empty() {
@@ -108,16 +104,12 @@ impl Tool for SessionsSendTool {
     async fn execute(
         &self,
         params: serde_json::Value,
-        _context: &ToolContext,
+        context: &ToolContext,
     ) -> anyhow::Result<ToolOutput> {
         let p: SendParams = serde_json::from_value(params)?;
         debug!(session = %p.session_hash, "sessions_send");
 
-        let store = rusty_claw_core::session_store::JsonlSessionStore::new(
-            rusty_claw_core::session_store::JsonlSessionStore::default_path(),
-        );
-
-        use rusty_claw_core::session::SessionStore;
+        let store = rusty_claw_core::session_store::open_session_store(&context.config).await?;
         let sessions = store.list().await?;
 
         let session_meta = sessions
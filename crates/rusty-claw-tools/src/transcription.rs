@@ -1,6 +1,10 @@
 //! Voice transcription tool using Whisper via Groq or OpenAI.
+//!
+//! Returns plain text by default. With `timestamps: true` the provider's
+//! `verbose_json` response is condensed into segment and word timings.
 
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tracing::info;
 
@@ -24,6 +28,94 @@ fn default_model(provider: &str) -> &str {
     }
 }
 
+/// Normalize a language hint to the ISO 639-1 code Whisper expects:
+/// `en-US` and `English` both become `en`. Returns `None` for hints that
+/// can't be mapped, which leaves detection to the provider.
+fn normalize_language(hint: &str) -> Option<String> {
+    let hint = hint.trim().to_ascii_lowercase();
+    let primary = hint.split(['-', '_']).next().unwrap_or_default();
+    if primary.len() == 2 && primary.chars().all(|c| c.is_ascii_lowercase()) {
+        return Some(primary.to_string());
+    }
+    let code = match hint.as_str() {
+        "english" => "en",
+        "german" | "deutsch" => "de",
+        "french" | "français" | "francais" => "fr",
+        "spanish" | "español" | "espanol" => "es",
+        "italian" | "italiano" => "it",
+        "portuguese" | "português" | "portugues" => "pt",
+        "dutch" | "nederlands" => "nl",
+        "polish" | "polski" => "pl",
+        "russian" => "ru",
+        "ukrainian" => "uk",
+        "turkish" => "tr",
+        "arabic" => "ar",
+        "hindi" => "hi",
+        "japanese" => "ja",
+        "korean" => "ko",
+        "chinese" | "mandarin" => "zh",
+        "swedish" => "sv",
+        "norwegian" => "no",
+        "danish" => "da",
+        "finnish" => "fi",
+        "greek" => "el",
+        "czech" => "cs",
+        _ => return None,
+    };
+    Some(code.to_string())
+}
+
+/// Whisper `verbose_json` response (fields we use).
+#[derive(Debug, Deserialize)]
+struct VerboseTranscript {
+    text: String,
+    #[serde(default)]
+    language: Option<String>,
+    #[serde(default)]
+    duration: Option<f64>,
+    #[serde(default)]
+    segments: Vec<Segment>,
+    #[serde(default)]
+    words: Vec<Word>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Segment {
+    start: f64,
+    end: f64,
+    text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Word {
+    word: String,
+    start: f64,
+    end: f64,
+}
+
+/// Condense a `verbose_json` body into the structured tool output.
+fn timestamped_output(body: &str) -> anyhow::Result<serde_json::Value> {
+    let verbose: VerboseTranscript = serde_json::from_str(body)?;
+    let segments: Vec<Segment> = verbose
+        .segments
+        .into_iter()
+        .map(|s| Segment {
+            text: s.text.trim().to_string(),
+            ..s
+        })
+        .collect();
+    let mut output = json!({
+        "text": verbose.text.trim(),
+        "language": verbose.language,
+        "duration": verbose.duration,
+        "segments": segments,
+    });
+    if !verbose.words.is_empty() {
+        output["words"] = json!(verbose.words);
+    }
+    Ok(output)
+}
+
 #[async_trait]
 impl Tool for TranscriptionTool {
     fn name(&self) -> &str {
@@ -44,7 +136,11 @@ impl Tool for TranscriptionTool {
                 },
                 "language": {
                     "type": "string",
-                    "description": "ISO 639-1 language code (e.g. 'en', 'de'). Optional."
+                    "description": "Spoken language as an ISO 639-1 code or name (e.g. 'en', 'de-DE', 'German'). Avoids auto-detect errors on short clips. Optional."
+                },
+                "timestamps": {
+                    "type": "boolean",
+                    "description": "Return JSON with segment and word timestamps (for captions) instead of plain text. Default: false"
                 },
                 "prompt": {
                     "type": "string",
@@ -65,7 +161,10 @@ impl Tool for TranscriptionTool {
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow::anyhow!("missing 'file_path' parameter"))?;
 
-        let language = params.get("language").and_then(|v| v.as_str());
+        let timestamps = params
+            .get("timestamps")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
         let prompt = params.get("prompt").and_then(|v| v.as_str());
 
         // Check file exists
@@ -112,6 +211,26 @@ impl Tool for TranscriptionTool {
             }
         };
 
+        let language_hint = params
+            .get("language")
+            .and_then(|v| v.as_str())
+            .or_else(|| transcription_config.and_then(|c| c.language.as_deref()));
+        let language = match language_hint {
+            Some(hint) => match normalize_language(hint) {
+                Some(code) => Some(code),
+                None => {
+                    return Ok(ToolOutput {
+                        content: format!(
+                            "Unrecognized language '{hint}'. Use an ISO 639-1 code such as 'en' or 'de'."
+                        ),
+                        is_error: true,
+                        media: None,
+                    });
+                }
+            },
+            None => None,
+        };
+
         let model = transcription_config
             .and_then(|c| c.model.as_deref())
             .unwrap_or_else(|| default_model(provider));
@@ -132,11 +251,18 @@ impl Tool for TranscriptionTool {
 
         let mut form = reqwest::multipart::Form::new()
             .part("file", file_part)
-            .text("model", model.to_string())
-            .text("response_format", "text");
+            .text("model", model.to_string());
 
-        if let Some(lang) = language {
-            form = form.text("language", lang.to_string());
+        if timestamps {
+            form = form
+                .text("response_format", "verbose_json")
+                .text("timestamp_granularities[]", "segment")
+                .text("timestamp_granularities[]", "word");
+        } else {
+            form = form.text("response_format", "text");
+        }
+        if let Some(lang) = &language {
+            form = form.text("language", lang.clone());
         }
         if let Some(p) = prompt {
             form = form.text("prompt", p.to_string());
@@ -160,13 +286,28 @@ impl Tool for TranscriptionTool {
             });
         }
 
-        let transcript = resp.text().await?;
-        let transcript = transcript.trim().to_string();
+        let body = resp.text().await?;
+        let transcript = if timestamps {
+            match timestamped_output(&body) {
+                Ok(value) => serde_json::to_string_pretty(&value)?,
+                Err(e) => {
+                    return Ok(ToolOutput {
+                        content: format!("Unexpected transcription response: {e}"),
+                        is_error: true,
+                        media: None,
+                    });
+                }
+            }
+        } else {
+            body.trim().to_string()
+        };
 
         info!(
             provider,
             model,
             file = file_name,
+            language = language.as_deref().unwrap_or("auto"),
+            timestamps,
             chars = transcript.len(),
             "Audio transcribed"
         );
@@ -208,6 +349,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_normalize_language() {
+        assert_eq!(normalize_language("en").as_deref(), Some("en"));
+        assert_eq!(normalize_language("de-DE").as_deref(), Some("de"));
+        assert_eq!(normalize_language("pt_BR").as_deref(), Some("pt"));
+        assert_eq!(normalize_language("German").as_deref(), Some("de"));
+        assert_eq!(normalize_language("klingon"), None);
+    }
+
+    #[test]
+    fn test_timestamped_output() {
+        let body = r#"{
+            "task": "transcribe", "language": "english", "duration": 2.5,
+            "text": " Hello world. ",
+            "segments": [{"id": 0, "seek": 0, "start": 0.0, "end": 2.5, "text": " Hello world.", "tokens": [1, 2]}],
+            "words": [{"word": "Hello", "start": 0.0, "end": 0.6}, {"word": "world", "start": 0.7, "end": 1.2}]
+        }"#;
+        let output = timestamped_output(body).unwrap();
+        assert_eq!(output["text"], "Hello world.");
+        assert_eq!(output["language"], "english");
+        assert_eq!(output["segments"][0], json!({"start": 0.0, "end": 2.5, "text": "Hello world."}));
+        assert_eq!(output["words"][1]["word"], "world");
+
+        // Providers that only return segments omit words entirely
+        let output = timestamped_output(r#"{"text": "Hi", "segments": []}"#).unwrap();
+        assert!(output.get("words").is_none());
+    }
+
     #[tokio::test]
     async fn test_missing_file_returns_error() {
         let ctx = ToolContext {
@@ -217,6 +386,7 @@ mod tests {
             restrict_to_workspace: false,
             sandbox_mode: rusty_claw_core::config::SandboxMode::default(),
             browser_pool: None,
+            progress: None,
         };
 
         let result = TranscriptionTool
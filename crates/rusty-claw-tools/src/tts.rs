@@ -1,31 +1,227 @@
-//! Text-to-speech tool using ElevenLabs API.
+//! Text-to-speech tool with pluggable backends (ElevenLabs, OpenAI, Azure Speech).
+//!
+//! Audio is streamed to the client in chunks as it is synthesized so
+//! playback can start early. For backends without streaming synthesis,
+//! without a listener, or with `stream: false`, the whole file is returned
+//! as a single media attachment instead.
 
 use async_trait::async_trait;
+use base64::Engine;
 use serde_json::json;
-use tracing::info;
+use tracing::{debug, info};
 
-use crate::{Tool, ToolContext, ToolOutput};
+use rusty_claw_core::config::TtsConfig;
 
-const DEFAULT_VOICE_ID: &str = "21m00Tcm4TlvDq8ikWAM"; // ElevenLabs "Rachel"
-const DEFAULT_MODEL: &str = "eleven_monolingual_v1";
-const DEFAULT_OUTPUT_FORMAT: &str = "mp3_44100_128";
+use crate::{Tool, ToolContext, ToolMedia, ToolOutput};
+
+/// Audio is forwarded to the client in chunks of at least this size.
+const STREAM_CHUNK_BYTES: usize = 16 * 1024;
 
 pub struct TtsTool;
 
+/// A speech synthesis backend selected by `tools.tts.provider`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TtsBackend {
+    ElevenLabs,
+    OpenAi,
+    Azure,
+}
+
+/// Voice, model and output format after applying backend defaults.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Synthesis {
+    voice: String,
+    model: String,
+    format: String,
+}
+
+impl TtsBackend {
+    fn from_provider(provider: &str) -> Option<Self> {
+        match provider {
+            "elevenlabs" => Some(Self::ElevenLabs),
+            "openai" => Some(Self::OpenAi),
+            "azure" => Some(Self::Azure),
+            _ => None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::ElevenLabs => "ElevenLabs",
+            Self::OpenAi => "OpenAI",
+            Self::Azure => "Azure Speech",
+        }
+    }
+
+    fn api_key_env(self) -> &'static str {
+        match self {
+            Self::ElevenLabs => "ELEVENLABS_API_KEY",
+            Self::OpenAi => "OPENAI_API_KEY",
+            Self::Azure => "AZURE_SPEECH_KEY",
+        }
+    }
+
+    /// Azure's REST endpoint returns the finished file, so it is not streamed.
+    fn supports_streaming(self) -> bool {
+        !matches!(self, Self::Azure)
+    }
+
+    fn synthesis(self, voice: Option<&str>, model: Option<&str>, format: Option<&str>) -> Synthesis {
+        let (default_voice, default_model, default_format) = match self {
+            // ElevenLabs "Rachel"
+            Self::ElevenLabs => ("21m00Tcm4TlvDq8ikWAM", "eleven_monolingual_v1", "mp3_44100_128"),
+            Self::OpenAi => ("alloy", "tts-1", "mp3"),
+            // Azure selects the engine through the voice; there is no model
+            Self::Azure => ("en-US-JennyNeural", "", "audio-24khz-48kbitrate-mono-mp3"),
+        };
+        Synthesis {
+            voice: voice.unwrap_or(default_voice).to_string(),
+            model: model.unwrap_or(default_model).to_string(),
+            format: format.unwrap_or(default_format).to_string(),
+        }
+    }
+
+    fn request(
+        self,
+        client: &reqwest::Client,
+        config: Option<&TtsConfig>,
+        api_key: &str,
+        text: &str,
+        synthesis: &Synthesis,
+    ) -> anyhow::Result<reqwest::RequestBuilder> {
+        let Synthesis { voice, model, format } = synthesis;
+        Ok(match self {
+            Self::ElevenLabs => client
+                .post(format!(
+                    "https://api.elevenlabs.io/v1/text-to-speech/{voice}/stream?output_format={format}"
+                ))
+                .header("xi-api-key", api_key)
+                .json(&json!({
+                    "text": text,
+                    "model_id": model,
+                    "voice_settings": {
+                        "stability": 0.5,
+                        "similarity_boost": 0.75
+                    }
+                })),
+            Self::OpenAi => client
+                .post("https://api.openai.com/v1/audio/speech")
+                .bearer_auth(api_key)
+                .json(&openai_body(text, synthesis)),
+            Self::Azure => {
+                let region = config
+                    .and_then(|c| c.region.clone())
+                    .or_else(|| std::env::var("AZURE_SPEECH_REGION").ok().filter(|v| !v.is_empty()))
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Azure Speech needs tools.tts.region or AZURE_SPEECH_REGION")
+                    })?;
+                client
+                    .post(format!(
+                        "https://{region}.tts.speech.microsoft.com/cognitiveservices/v1"
+                    ))
+                    .header("Ocp-Apim-Subscription-Key", api_key)
+                    .header("Content-Type", "application/ssml+xml")
+                    .header("X-Microsoft-OutputFormat", format.as_str())
+                    .header("User-Agent", "RustyClaw")
+                    .body(azure_ssml(text, voice))
+            }
+        })
+    }
+}
+
+/// Request body for OpenAI `/v1/audio/speech`.
+fn openai_body(text: &str, synthesis: &Synthesis) -> serde_json::Value {
+    json!({
+        "model": synthesis.model,
+        "input": text,
+        "voice": synthesis.voice,
+        "response_format": synthesis.format,
+    })
+}
+
+/// SSML document for Azure Speech. The locale is taken from the voice name
+/// (e.g. `de-DE-KatjaNeural`).
+fn azure_ssml(text: &str, voice: &str) -> String {
+    let lang = voice.splitn(3, '-').take(2).collect::<Vec<_>>().join("-");
+    let escaped = text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;");
+    format!(
+        "<speak version='1.0' xml:lang='{lang}'><voice name='{voice}'>{escaped}</voice></speak>"
+    )
+}
+
+/// File extension and MIME type for a backend output format, e.g.
+/// `mp3_44100_128`, `opus`, or `riff-24khz-16bit-mono-pcm`.
+fn audio_kind(format: &str) -> (&'static str, &'static str) {
+    let f = format.to_ascii_lowercase();
+    if f.contains("mp3") {
+        ("mp3", "audio/mpeg")
+    } else if f.contains("opus") || f.contains("ogg") {
+        ("ogg", "audio/ogg")
+    } else if f.contains("webm") {
+        ("webm", "audio/webm")
+    } else if f.contains("aac") {
+        ("aac", "audio/aac")
+    } else if f.contains("flac") {
+        ("flac", "audio/flac")
+    } else if f.contains("wav") || f.contains("riff") {
+        ("wav", "audio/wav")
+    } else if f.contains("ulaw") || f.contains("mulaw") {
+        ("ulaw", "audio/basic")
+    } else if f.contains("pcm") || f.contains("raw") {
+        ("pcm", "audio/pcm")
+    } else {
+        ("mp3", "audio/mpeg")
+    }
+}
+
 /// Generate a unique output filename.
 fn output_filename(format: &str) -> std::path::PathBuf {
     let dir = rusty_claw_core::config::data_dir().join("audio");
     let ts = chrono::Utc::now().format("%Y%m%d_%H%M%S");
     let id = uuid::Uuid::new_v4().simple().to_string();
-    let ext = match format {
-        f if f.starts_with("mp3") => "mp3",
-        f if f.starts_with("pcm") => "pcm",
-        f if f.starts_with("ulaw") => "ulaw",
-        _ => "mp3",
-    };
+    let (ext, _) = audio_kind(format);
     dir.join(format!("tts_{ts}_{}.{ext}", &id[..8]))
 }
 
+fn audio_media(mime_type: &str, bytes: &[u8]) -> ToolMedia {
+    ToolMedia {
+        mime_type: mime_type.to_string(),
+        data: base64::engine::general_purpose::STANDARD.encode(bytes),
+    }
+}
+
+/// Read the response body, forwarding audio chunks to the client as they
+/// arrive. Returns the full audio and whether it was streamed; if nobody
+/// is listening, streaming stops and the caller returns the audio whole.
+async fn stream_audio(
+    mut resp: reqwest::Response,
+    context: &ToolContext,
+    mime_type: &str,
+) -> anyhow::Result<(Vec<u8>, bool)> {
+    let mut audio = Vec::new();
+    let mut sent = 0;
+    let mut streaming = context.progress.is_some();
+    while let Some(chunk) = resp.chunk().await? {
+        audio.extend_from_slice(&chunk);
+        if streaming && audio.len() - sent >= STREAM_CHUNK_BYTES {
+            streaming = context.report_audio(audio_media(mime_type, &audio[sent..]), false);
+            sent = audio.len();
+        }
+    }
+    if streaming {
+        streaming = context.report_audio(audio_media(mime_type, &audio[sent..]), true);
+    }
+    if !streaming {
+        debug!("No audio listener, returning TTS audio as a single blob");
+    }
+    Ok((audio, streaming))
+}
+
 #[async_trait]
 impl Tool for TtsTool {
     fn name(&self) -> &str {
@@ -33,7 +229,7 @@ impl Tool for TtsTool {
     }
 
     fn description(&self) -> &str {
-        "Convert text to speech using ElevenLabs. Returns the path to the generated audio file."
+        "Convert text to speech using the configured provider (ElevenLabs, OpenAI, or Azure Speech). Returns the path to the generated audio file."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
@@ -46,15 +242,19 @@ impl Tool for TtsTool {
                 },
                 "voice_id": {
                     "type": "string",
-                    "description": "ElevenLabs voice ID (optional, uses default voice)"
+                    "description": "Voice for the configured provider, e.g. an ElevenLabs voice ID, 'alloy' (OpenAI), or 'en-US-JennyNeural' (Azure). Optional"
                 },
                 "model_id": {
                     "type": "string",
-                    "description": "ElevenLabs model ID (optional)"
+                    "description": "Model for the configured provider (optional; ignored by Azure)"
                 },
                 "output_format": {
                     "type": "string",
-                    "description": "Output format (e.g. mp3_44100_128)"
+                    "description": "Output format in the provider's naming (e.g. mp3_44100_128, mp3, audio-24khz-48kbitrate-mono-mp3)"
+                },
+                "stream": {
+                    "type": "boolean",
+                    "description": "Stream audio to the client while it is generated (default: true)"
                 }
             },
             "required": ["text"]
@@ -78,71 +278,82 @@ impl Tool for TtsTool {
             .as_ref()
             .and_then(|t| t.tts.as_ref());
 
+        let provider = tts_config.map(|c| c.provider.as_str()).unwrap_or("elevenlabs");
+        let Some(backend) = TtsBackend::from_provider(provider) else {
+            return Ok(ToolOutput {
+                content: format!(
+                    "Unknown TTS provider '{provider}'. Set tools.tts.provider to one of: elevenlabs, openai, azure."
+                ),
+                is_error: true,
+                media: None,
+            });
+        };
+
         let api_key = tts_config
             .and_then(|c| c.resolve_api_key())
-            .or_else(|| std::env::var("ELEVENLABS_API_KEY").ok().filter(|v| !v.is_empty()));
+            .or_else(|| std::env::var(backend.api_key_env()).ok().filter(|v| !v.is_empty()));
 
         let api_key = match api_key {
             Some(key) => key,
             None => {
                 return Ok(ToolOutput {
-                    content: "TTS not configured. Set tools.tts.api_key in config or ELEVENLABS_API_KEY environment variable.".into(),
+                    content: format!(
+                        "TTS not configured. Set tools.tts.api_key in config or {} environment variable.",
+                        backend.api_key_env()
+                    ),
                     is_error: true,
                     media: None,
                 });
             }
         };
 
-        let voice_id = params
-            .get("voice_id")
-            .and_then(|v| v.as_str())
-            .or_else(|| tts_config.and_then(|c| c.default_voice.as_deref()))
-            .unwrap_or(DEFAULT_VOICE_ID);
-
-        let model_id = params
-            .get("model_id")
-            .and_then(|v| v.as_str())
-            .or_else(|| tts_config.and_then(|c| c.default_model.as_deref()))
-            .unwrap_or(DEFAULT_MODEL);
-
-        let output_format = params
-            .get("output_format")
-            .and_then(|v| v.as_str())
-            .or_else(|| tts_config.and_then(|c| c.output_format.as_deref()))
-            .unwrap_or(DEFAULT_OUTPUT_FORMAT);
-
-        let url = format!(
-            "https://api.elevenlabs.io/v1/text-to-speech/{voice_id}/stream?output_format={output_format}"
+        let synthesis = backend.synthesis(
+            params
+                .get("voice_id")
+                .and_then(|v| v.as_str())
+                .or_else(|| tts_config.and_then(|c| c.default_voice.as_deref())),
+            params
+                .get("model_id")
+                .and_then(|v| v.as_str())
+                .or_else(|| tts_config.and_then(|c| c.default_model.as_deref())),
+            params
+                .get("output_format")
+                .and_then(|v| v.as_str())
+                .or_else(|| tts_config.and_then(|c| c.output_format.as_deref())),
         );
 
         let client = reqwest::Client::new();
-        let resp = client
-            .post(&url)
-            .header("xi-api-key", &api_key)
-            .header("Content-Type", "application/json")
-            .json(&json!({
-                "text": text,
-                "model_id": model_id,
-                "voice_settings": {
-                    "stability": 0.5,
-                    "similarity_boost": 0.75
-                }
-            }))
-            .send()
-            .await?;
+        let request = match backend.request(&client, tts_config, &api_key, text, &synthesis) {
+            Ok(request) => request,
+            Err(e) => {
+                return Ok(ToolOutput {
+                    content: format!("TTS not configured: {e}"),
+                    is_error: true,
+                    media: None,
+                });
+            }
+        };
+        let resp = request.send().await?;
 
         if !resp.status().is_success() {
             let status = resp.status();
             let body = resp.text().await.unwrap_or_default();
             return Ok(ToolOutput {
-                content: format!("ElevenLabs API error ({status}): {body}"),
+                content: format!("{} API error ({status}): {body}", backend.label()),
                 is_error: true,
                 media: None,
             });
         }
 
-        let bytes = resp.bytes().await?;
-        let file_path = output_filename(output_format);
+        let (_, mime_type) = audio_kind(&synthesis.format);
+        let stream = backend.supports_streaming()
+            && params.get("stream").and_then(|v| v.as_bool()).unwrap_or(true);
+        let (bytes, streamed) = if stream {
+            stream_audio(resp, context, mime_type).await?
+        } else {
+            (resp.bytes().await?.to_vec(), false)
+        };
+        let file_path = output_filename(&synthesis.format);
 
         // Ensure directory exists
         if let Some(parent) = file_path.parent() {
@@ -154,21 +365,29 @@ impl Tool for TtsTool {
         info!(
             path = %file_path.display(),
             size_kb,
-            voice = voice_id,
-            model = model_id,
+            provider,
+            voice = %synthesis.voice,
+            model = %synthesis.model,
+            streamed,
             "TTS audio generated"
         );
 
+        let model_line = if synthesis.model.is_empty() {
+            String::new()
+        } else {
+            format!("\nModel: {}", synthesis.model)
+        };
         Ok(ToolOutput {
             content: format!(
-                "Audio saved to: {}\nSize: {}KB\nVoice: {}\nModel: {}",
+                "Audio saved to: {}\nSize: {}KB\nVoice: {}{}{}",
                 file_path.display(),
                 size_kb,
-                voice_id,
-                model_id,
+                synthesis.voice,
+                model_line,
+                if streamed { "\nStreamed to client" } else { "" },
             ),
             is_error: false,
-            media: None,
+            media: (!streamed).then(|| vec![audio_media(mime_type, &bytes)]),
         })
     }
 }
@@ -193,6 +412,61 @@ mod tests {
         assert!(f1.extension().unwrap() == "mp3");
     }
 
+    #[test]
+    fn test_openai_request_body() {
+        let synthesis = TtsBackend::OpenAi.synthesis(Some("nova"), Some("gpt-4o-mini-tts"), None);
+        assert_eq!(
+            openai_body("Hello there", &synthesis),
+            json!({
+                "model": "gpt-4o-mini-tts",
+                "input": "Hello there",
+                "voice": "nova",
+                "response_format": "mp3"
+            })
+        );
+        assert_eq!(audio_kind(&synthesis.format), ("mp3", "audio/mpeg"));
+
+        let defaults = TtsBackend::OpenAi.synthesis(None, None, Some("opus"));
+        assert_eq!(defaults.voice, "alloy");
+        assert_eq!(defaults.model, "tts-1");
+        assert_eq!(audio_kind(&defaults.format), ("ogg", "audio/ogg"));
+    }
+
+    #[test]
+    fn test_azure_ssml_escapes_text() {
+        let ssml = azure_ssml("Fish & <chips>", "de-DE-KatjaNeural");
+        assert_eq!(
+            ssml,
+            "<speak version='1.0' xml:lang='de-DE'><voice name='de-DE-KatjaNeural'>Fish &amp; &lt;chips&gt;</voice></speak>"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_provider_returns_error() {
+        let config = rusty_claw_core::config::Config {
+            tools: Some(rusty_claw_core::config::ToolsConfig {
+                tts: Some(TtsConfig {
+                    provider: "polly".into(),
+                    api_key: Some("key".into()),
+                    api_key_env: None,
+                    default_voice: None,
+                    default_model: None,
+                    output_format: None,
+                    region: None,
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let ctx = ToolContext {
+            config: std::sync::Arc::new(config),
+            ..context(None)
+        };
+        let result = TtsTool.execute(json!({"text": "hello"}), &ctx).await.unwrap();
+        assert!(result.is_error);
+        assert!(result.content.contains("Unknown TTS provider 'polly'"));
+    }
+
     #[tokio::test]
     async fn test_missing_config_returns_error() {
         let ctx = ToolContext {
@@ -202,6 +476,7 @@ mod tests {
             restrict_to_workspace: false,
             sandbox_mode: rusty_claw_core::config::SandboxMode::default(),
             browser_pool: None,
+            progress: None,
         };
 
         // Ensure env var is not set for this test
@@ -220,4 +495,74 @@ mod tests {
             unsafe { std::env::set_var("ELEVENLABS_API_KEY", val) };
         }
     }
+
+    /// Serve `body` once over HTTP and return a response for it.
+    async fn audio_response(body: Vec<u8>) -> reqwest::Response {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = socket.read(&mut buf).await;
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: audio/mpeg\r\nContent-Length: {}\r\n\r\n",
+                body.len()
+            );
+            socket.write_all(head.as_bytes()).await.unwrap();
+            for part in body.chunks(8 * 1024) {
+                socket.write_all(part).await.unwrap();
+            }
+        });
+        reqwest::get(format!("http://{addr}/")).await.unwrap()
+    }
+
+    fn context(progress: Option<tokio::sync::mpsc::UnboundedSender<crate::ToolProgress>>) -> ToolContext {
+        ToolContext {
+            session_key: "test".into(),
+            workspace: std::env::temp_dir(),
+            config: std::sync::Arc::new(rusty_claw_core::config::Config::default()),
+            restrict_to_workspace: false,
+            sandbox_mode: rusty_claw_core::config::SandboxMode::default(),
+            browser_pool: None,
+            progress,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_audio_emits_chunks() {
+        let body: Vec<u8> = (0..50_000u32).map(|i| i as u8).collect();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let ctx = context(Some(tx));
+
+        let (audio, streamed) = stream_audio(audio_response(body.clone()).await, &ctx, "audio/mpeg")
+            .await
+            .unwrap();
+        assert!(streamed);
+        assert_eq!(audio, body);
+        drop(ctx);
+
+        let mut received = Vec::new();
+        let mut finals = Vec::new();
+        while let Some(crate::ToolProgress::Audio { media, is_final }) = rx.recv().await {
+            assert_eq!(media.mime_type, "audio/mpeg");
+            received.extend(base64::engine::general_purpose::STANDARD.decode(media.data).unwrap());
+            finals.push(is_final);
+        }
+        assert!(finals.len() >= 2, "expected several chunks, got {}", finals.len());
+        assert_eq!(finals.iter().filter(|f| **f).count(), 1);
+        assert_eq!(finals.last(), Some(&true));
+        assert_eq!(received, body);
+    }
+
+    #[tokio::test]
+    async fn test_stream_audio_without_listener_falls_back() {
+        let body = vec![7u8; 40_000];
+        let (audio, streamed) = stream_audio(audio_response(body.clone()).await, &context(None), "audio/mpeg")
+            .await
+            .unwrap();
+        assert!(!streamed);
+        assert_eq!(audio, body);
+    }
 }
@@ -1,37 +1,69 @@
 //! web_fetch tool — HTTP GET with content extraction and SSRF protection.
-
-use std::net::IpAddr;
+//!
+//! HTML pages are returned as Markdown of their main content by default
+//! (see [`crate::html_markdown`]); `format` selects plain text or the raw
+//! HTML instead.
+//!
+//! Limits come from `tools.web_fetch` in the config: responses are read up
+//! to `max_bytes` and truncated past it, requests time out after
+//! `timeout_ms`, and with `respect_robots` the site's robots.txt is checked
+//! first. Every URL, including each redirect hop, goes through the SSRF
+//! guard.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use rusty_claw_core::config::WebFetchConfig;
 use serde::Deserialize;
 use tracing::{debug, warn};
 
+use crate::html_markdown::html_to_markdown;
 use crate::{Tool, ToolContext, ToolOutput};
 
+/// Product token sent as User-Agent and matched against robots.txt groups.
+const USER_AGENT: &str = "RustyClaw";
+
+/// Redirect hops followed before giving up.
+const MAX_REDIRECTS: usize = 5;
+
+/// robots.txt files are read up to this size (RFC 9309 asks for 500 KiB).
+const MAX_ROBOTS_BYTES: usize = 512 * 1024;
+
 pub struct WebFetchTool;
 
 #[derive(Deserialize)]
 struct Params {
     url: String,
-    #[serde(default = "default_timeout")]
-    timeout_ms: u64,
-    #[serde(default = "default_max_size")]
-    max_size: usize,
+    #[serde(default)]
+    timeout_ms: Option<u64>,
+    #[serde(default)]
+    max_size: Option<usize>,
+    #[serde(default)]
+    format: Option<Format>,
+    /// Legacy alias for `format: "html"`.
     #[serde(default)]
     raw: bool,
+    #[serde(default)]
+    respect_robots: bool,
 }
 
-fn default_timeout() -> u64 {
-    30_000
-}
-
-fn default_max_size() -> usize {
-    1_048_576 // 1 MB
+/// How an HTML response is returned. Non-HTML bodies are always returned as-is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Format {
+    Html,
+    Text,
+    #[default]
+    Markdown,
 }
 
 /// Validate a URL against SSRF attacks.
-/// Blocks private IPs, localhost, cloud metadata endpoints, and non-HTTP schemes.
-async fn validate_url(url: &str) -> Result<(), String> {
+/// Blocks non-HTTP schemes and cloud metadata endpoints, and — unless
+/// `allow_private` is set — localhost and hosts resolving to private IPs.
+/// A host that fails to resolve is blocked too.
+async fn validate_url(url: &str, allow_private: bool) -> Result<(), String> {
     // Parse the URL
     let parsed = url::Url::parse(url).map_err(|e| format!("Invalid URL: {e}"))?;
 
@@ -46,65 +78,124 @@ async fn validate_url(url: &str) -> Result<(), String> {
         .host_str()
         .ok_or("URL has no host")?;
 
-    // Block localhost explicitly
-    if host == "localhost" || host == "127.0.0.1" || host == "::1" || host == "[::1]" || host == "0.0.0.0" {
-        return Err(format!("Blocked: requests to {host} are not allowed"));
-    }
-
-    // Block cloud metadata endpoints
+    // Block cloud metadata endpoints, even when private networks are allowed
     if host == "169.254.169.254" || host == "metadata.google.internal" {
         return Err(format!("Blocked: cloud metadata endpoint {host}"));
     }
 
+    // Block localhost explicitly
+    let is_localhost = matches!(host, "localhost" | "127.0.0.1" | "::1" | "[::1]" | "0.0.0.0");
+    if !allow_private && is_localhost {
+        return Err(format!("Blocked: requests to {host} are not allowed"));
+    }
+
     // Resolve hostname and check for private IPs
     let port = parsed.port().unwrap_or(if parsed.scheme() == "https" { 443 } else { 80 });
     let addr_str = format!("{host}:{port}");
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host(&addr_str)
+        .await
+        .map_err(|e| format!("Could not resolve {host}: {e}"))?
+        .collect();
+    check_resolved(host, &addrs, allow_private)
+}
 
-    match tokio::net::lookup_host(&addr_str).await {
-        Ok(addrs) => {
-            for addr in addrs {
-                if is_private_ip(addr.ip()) {
-                    return Err(format!(
-                        "Blocked: {host} resolves to private IP {}",
-                        addr.ip()
-                    ));
-                }
-            }
+/// Check the addresses `host` resolved to. Every address must be allowed,
+/// since the connection may use any of them.
+fn check_resolved(host: &str, addrs: &[SocketAddr], allow_private: bool) -> Result<(), String> {
+    if addrs.is_empty() {
+        return Err(format!("Could not resolve {host}: no addresses"));
+    }
+    for addr in addrs {
+        let ip = addr.ip();
+        if is_metadata_ip(ip) {
+            return Err(format!("Blocked: {host} resolves to cloud metadata endpoint {ip}"));
         }
-        Err(e) => {
-            // DNS resolution failure — allow the request to proceed,
-            // reqwest will handle the error
-            debug!(host, %e, "DNS lookup failed during SSRF check, allowing request");
+        if !allow_private && is_private_ip(ip) {
+            return Err(format!("Blocked: {host} resolves to private IP {ip}"));
         }
     }
-
     Ok(())
 }
 
-/// Check if an IP address is private/reserved.
+/// DNS resolver for the fetch client that applies the SSRF guard to the
+/// addresses it returns. The connection goes to exactly the addresses that
+/// were checked, so a host can't pass [`validate_url`] and then rebind to
+/// a private IP. Used for every redirect hop as well.
+struct GuardedResolver {
+    allow_private: bool,
+}
+
+impl reqwest::dns::Resolve for GuardedResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let allow_private = self.allow_private;
+        Box::pin(async move {
+            let host = name.as_str();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0)).await?.collect();
+            check_resolved(host, &addrs, allow_private)?;
+            let addrs: reqwest::dns::Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Check if an IP address is private/reserved. IPv6 addresses that embed an
+/// IPv4 address (mapped, compatible, NAT64, 6to4) are checked as IPv4.
 fn is_private_ip(ip: IpAddr) -> bool {
     match ip {
         IpAddr::V4(v4) => {
+            let octets = v4.octets();
             v4.is_loopback()              // 127.0.0.0/8
                 || v4.is_private()         // 10.0.0.0/8, 172.16.0.0/12, 192.168.0.0/16
                 || v4.is_link_local()      // 169.254.0.0/16
-                || v4.is_unspecified()     // 0.0.0.0
-                || v4.octets()[0] == 100 && v4.octets()[1] >= 64 && v4.octets()[1] <= 127 // 100.64.0.0/10 (CGNAT)
+                || octets[0] == 0          // 0.0.0.0/8
+                || v4.is_broadcast()       // 255.255.255.255
+                || v4.is_multicast()       // 224.0.0.0/4
+                || octets[0] >= 240        // 240.0.0.0/4 (reserved)
+                || octets[0] == 100 && (octets[1] & 0xc0) == 64 // 100.64.0.0/10 (CGNAT)
+                || octets[0] == 198 && (octets[1] & 0xfe) == 18 // 198.18.0.0/15 (benchmarking)
         }
         IpAddr::V6(v6) => {
+            if let Some(v4) = embedded_ipv4(v6) {
+                return is_private_ip(IpAddr::V4(v4));
+            }
+            let segments = v6.segments();
             v6.is_loopback()               // ::1
                 || v6.is_unspecified()      // ::
-                || {
-                    let segments = v6.segments();
-                    // fc00::/7 (unique local)
-                    (segments[0] & 0xfe00) == 0xfc00
-                    // fe80::/10 (link-local)
-                    || (segments[0] & 0xffc0) == 0xfe80
-                }
+                || v6.is_multicast()        // ff00::/8
+                || (segments[0] & 0xfe00) == 0xfc00 // fc00::/7 (unique local)
+                || (segments[0] & 0xffc0) == 0xfe80 // fe80::/10 (link-local)
         }
     }
 }
 
+/// Extract the IPv4 address embedded in an IPv6 address, if any.
+fn embedded_ipv4(v6: Ipv6Addr) -> Option<Ipv4Addr> {
+    let segments = v6.segments();
+    let v4 = |hi: u16, lo: u16| {
+        let [a, b] = hi.to_be_bytes();
+        let [c, d] = lo.to_be_bytes();
+        Ipv4Addr::new(a, b, c, d)
+    };
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        // 64:ff9b::a.b.c.d (NAT64)
+        return Some(v4(segments[6], segments[7]));
+    }
+    if segments[0] == 0x2002 {
+        // 2002:aabb:ccdd::/48 (6to4)
+        return Some(v4(segments[1], segments[2]));
+    }
+    // ::ffff:a.b.c.d and ::a.b.c.d (also covers :: and ::1)
+    v6.to_ipv4()
+}
+
+/// Check if an IP address is a cloud metadata endpoint.
+fn is_metadata_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => v4.octets() == [169, 254, 169, 254],
+        IpAddr::V6(v6) => embedded_ipv4(v6).is_some_and(|v4| v4.octets() == [169, 254, 169, 254]),
+    }
+}
+
 /// Strip HTML tags for readability. Simple approach — not a full parser.
 fn strip_html_tags(input: &str) -> String {
     let mut result = String::with_capacity(input.len());
@@ -184,6 +275,202 @@ fn strip_html_tags(input: &str) -> String {
     collapsed.trim().to_string()
 }
 
+/// GET `url`, following redirects manually so every hop passes the SSRF guard.
+async fn get_guarded(
+    client: &reqwest::Client,
+    url: &str,
+    allow_private: bool,
+) -> Result<reqwest::Response, String> {
+    let mut current = url::Url::parse(url).map_err(|e| format!("Invalid URL: {e}"))?;
+    for _ in 0..=MAX_REDIRECTS {
+        validate_url(current.as_str(), allow_private)
+            .await
+            .map_err(|reason| format!("Request blocked: {reason}"))?;
+        let resp = client
+            .get(current.clone())
+            .send()
+            .await
+            .map_err(|e| format!("Fetch error: {e}"))?;
+        if !resp.status().is_redirection() {
+            return Ok(resp);
+        }
+        let location = resp
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| format!("HTTP {} without Location header", resp.status()))?;
+        current = current
+            .join(location)
+            .map_err(|e| format!("Invalid redirect target: {e}"))?;
+        debug!(url = %current, "web_fetch following redirect");
+    }
+    Err(format!("Too many redirects (max: {MAX_REDIRECTS})"))
+}
+
+/// Read a response body up to `max_bytes`. Returns the bytes and whether
+/// the body was cut short.
+async fn read_capped(
+    mut resp: reqwest::Response,
+    max_bytes: usize,
+) -> Result<(Vec<u8>, bool), reqwest::Error> {
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await? {
+        let room = max_bytes - body.len();
+        if chunk.len() > room {
+            body.extend_from_slice(&chunk[..room]);
+            return Ok((body, true));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok((body, false))
+}
+
+/// Decode a body as UTF-8, dropping a multi-byte character cut off by truncation.
+fn decode_body(bytes: &[u8], truncated: bool) -> String {
+    let bytes = match std::str::from_utf8(bytes) {
+        Err(e) if truncated && e.error_len().is_none() => &bytes[..e.valid_up_to()],
+        _ => bytes,
+    };
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Check the site's robots.txt for `url`. A missing file (4xx) allows
+/// everything; an unreachable one is treated as a full disallow, per RFC 9309.
+async fn robots_allows(
+    client: &reqwest::Client,
+    url: &str,
+    allow_private: bool,
+) -> Result<bool, String> {
+    let parsed = url::Url::parse(url).map_err(|e| format!("Invalid URL: {e}"))?;
+    let robots_url = parsed
+        .join("/robots.txt")
+        .map_err(|e| format!("Invalid URL: {e}"))?;
+
+    let resp = get_guarded(client, robots_url.as_str(), allow_private)
+        .await
+        .map_err(|e| format!("robots.txt unavailable: {e}"))?;
+    let status = resp.status();
+    if status.is_client_error() {
+        return Ok(true);
+    }
+    if !status.is_success() {
+        return Err(format!("robots.txt unavailable: HTTP {status}"));
+    }
+    let (bytes, truncated) = read_capped(resp, MAX_ROBOTS_BYTES)
+        .await
+        .map_err(|e| format!("robots.txt unavailable: {e}"))?;
+
+    let mut path = parsed.path().to_string();
+    if let Some(query) = parsed.query() {
+        path.push('?');
+        path.push_str(query);
+    }
+    Ok(is_allowed_by_robots(&decode_body(&bytes, truncated), USER_AGENT, &path))
+}
+
+/// Evaluate robots.txt rules for `agent` and `path`.
+///
+/// Groups naming the agent take precedence over `*`. Among matching rules
+/// the longest pattern wins, with `Allow` winning ties; `*` and a trailing
+/// `$` are supported in patterns.
+fn is_allowed_by_robots(robots: &str, agent: &str, path: &str) -> bool {
+    #[derive(Default)]
+    struct Group {
+        agents: Vec<String>,
+        rules: Vec<(bool, String)>,
+    }
+
+    let mut groups: Vec<Group> = Vec::new();
+    let mut in_agents = false;
+    for line in robots.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let Some((field, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match field.trim().to_ascii_lowercase().as_str() {
+            "user-agent" => {
+                // Consecutive user-agent lines share one group
+                if !in_agents {
+                    groups.push(Group::default());
+                    in_agents = true;
+                }
+                if let Some(group) = groups.last_mut() {
+                    group.agents.push(value.to_ascii_lowercase());
+                }
+            }
+            rule @ ("allow" | "disallow") => {
+                in_agents = false;
+                if let Some(group) = groups.last_mut()
+                    && !value.is_empty()
+                {
+                    group.rules.push((rule == "allow", value.to_string()));
+                }
+            }
+            // Sitemap, crawl-delay and unknown records are ignored
+            _ => {}
+        }
+    }
+
+    let agent = agent.to_ascii_lowercase();
+    let mut matching: Vec<&Group> = groups
+        .iter()
+        .filter(|g| g.agents.contains(&agent))
+        .collect();
+    if matching.is_empty() {
+        matching = groups
+            .iter()
+            .filter(|g| g.agents.iter().any(|a| a == "*"))
+            .collect();
+    }
+
+    let mut best: Option<(usize, bool)> = None;
+    for (allow, pattern) in matching.iter().flat_map(|g| &g.rules) {
+        if !robots_pattern_matches(pattern, path) {
+            continue;
+        }
+        let candidate = (pattern.len(), *allow);
+        if best.is_none_or(|b| candidate > b) {
+            best = Some(candidate);
+        }
+    }
+    best.is_none_or(|(_, allow)| allow)
+}
+
+/// Match a robots.txt path pattern (`*` wildcard, optional `$` anchor).
+fn robots_pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = path.strip_prefix(parts.next().unwrap_or("")) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    if parts.is_empty() {
+        return !anchored || rest.is_empty();
+    }
+    for (i, part) in parts.iter().enumerate() {
+        if anchored && i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+fn error_output(content: String) -> ToolOutput {
+    ToolOutput {
+        content,
+        is_error: true,
+        media: None,
+    }
+}
+
 #[async_trait]
 impl Tool for WebFetchTool {
     fn name(&self) -> &str {
@@ -191,7 +478,7 @@ impl Tool for WebFetchTool {
     }
 
     fn description(&self) -> &str {
-        "Fetch the contents of a URL via HTTP GET. Returns the page text content (HTML tags stripped by default). Use `raw: true` for the raw response body."
+        "Fetch the contents of a URL via HTTP GET. HTML pages are returned as Markdown of the main content by default; use `format: \"text\"` for plain text or `format: \"html\"` for the raw source. Large responses are truncated."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
@@ -204,15 +491,20 @@ impl Tool for WebFetchTool {
                 },
                 "timeout_ms": {
                     "type": "integer",
-                    "description": "Request timeout in milliseconds (default: 30000)"
+                    "description": "Request timeout in milliseconds (default and maximum: configured timeout, 30000 unless set)"
                 },
                 "max_size": {
                     "type": "integer",
-                    "description": "Maximum response size in bytes (default: 1048576)"
+                    "description": "Maximum response size in bytes before truncation (default and maximum: configured limit, 2MB unless set)"
+                },
+                "format": {
+                    "type": "string",
+                    "enum": ["html", "text", "markdown"],
+                    "description": "Output format for HTML pages (default: markdown)"
                 },
-                "raw": {
+                "respect_robots": {
                     "type": "boolean",
-                    "description": "If true, return raw response body without HTML stripping"
+                    "description": "If true, check the site's robots.txt first and refuse disallowed pages"
                 }
             },
             "required": ["url"]
@@ -222,73 +514,95 @@ impl Tool for WebFetchTool {
     async fn execute(
         &self,
         params: serde_json::Value,
-        _context: &ToolContext,
+        context: &ToolContext,
     ) -> anyhow::Result<ToolOutput> {
         let p: Params = serde_json::from_value(params)?;
 
         debug!(url = %p.url, "web_fetch");
 
+        let defaults = WebFetchConfig::default();
+        let fetch_config = context
+            .config
+            .tools
+            .as_ref()
+            .and_then(|t| t.web_fetch.as_ref())
+            .unwrap_or(&defaults);
+
+        // Per-call limits may tighten the configured ones, never loosen them
+        let max_bytes = p
+            .max_size
+            .map_or(fetch_config.max_bytes, |m| m.min(fetch_config.max_bytes));
+        let timeout_ms = p
+            .timeout_ms
+            .map_or(fetch_config.timeout_ms, |t| t.min(fetch_config.timeout_ms));
+        let allow_private = fetch_config.allow_private_networks;
+
         // SSRF protection: validate URL before making request
-        if let Err(reason) = validate_url(&p.url).await {
+        if let Err(reason) = validate_url(&p.url, allow_private).await {
             warn!(url = %p.url, %reason, "SSRF protection blocked request");
-            return Ok(ToolOutput {
-                content: format!("Request blocked: {reason}"),
-                is_error: true,
-                media: None,
-            });
+            return Ok(error_output(format!("Request blocked: {reason}")));
         }
 
         let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_millis(p.timeout_ms))
-            .redirect(reqwest::redirect::Policy::limited(5))
+            .timeout(Duration::from_millis(timeout_ms))
+            .redirect(reqwest::redirect::Policy::none())
+            .dns_resolver(Arc::new(GuardedResolver { allow_private }))
+            .user_agent(format!("{USER_AGENT}/{}", env!("CARGO_PKG_VERSION")))
             .build()?;
 
-        let resp = match client.get(&p.url).send().await {
+        if fetch_config.respect_robots || p.respect_robots {
+            match robots_allows(&client, &p.url, allow_private).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    return Ok(error_output(format!("Blocked by robots.txt: {}", p.url)));
+                }
+                Err(e) => return Ok(error_output(e)),
+            }
+        }
+
+        let resp = match get_guarded(&client, &p.url, allow_private).await {
             Ok(r) => r,
             Err(e) => {
-                return Ok(ToolOutput {
-                    content: format!("Fetch error: {e}"),
-                    is_error: true,
-                    media: None,
-                });
+                warn!(url = %p.url, error = %e, "web_fetch failed");
+                return Ok(error_output(e));
             }
         };
 
         let status = resp.status();
         if !status.is_success() {
-            return Ok(ToolOutput {
-                content: format!("HTTP {status} for {}", p.url),
-                is_error: true,
-                media: None,
-            });
+            return Ok(error_output(format!("HTTP {status} for {}", p.url)));
         }
 
-        let bytes = resp.bytes().await?;
-        if bytes.len() > p.max_size {
-            return Ok(ToolOutput {
-                content: format!(
-                    "Response too large: {} bytes (max: {})",
-                    bytes.len(),
-                    p.max_size
-                ),
-                is_error: true,
-                media: None,
-            });
-        }
+        let final_url = resp.url().clone();
+        let content_type = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_ascii_lowercase());
 
-        let body = String::from_utf8_lossy(&bytes).to_string();
-        let content = if p.raw {
-            body
-        } else {
-            strip_html_tags(&body)
+        let (bytes, truncated) = match read_capped(resp, max_bytes).await {
+            Ok(body) => body,
+            Err(e) => return Ok(error_output(format!("Fetch error: {e}"))),
         };
 
-        // Truncate if still too long
-        let content = if content.len() > p.max_size {
-            format!("{}...\n[truncated at {} bytes]", &content[..p.max_size], p.max_size)
+        let body = decode_body(&bytes, truncated);
+        let is_html = match &content_type {
+            Some(ct) => ct.contains("html"),
+            None => body.trim_start().starts_with('<'),
+        };
+        let format = if p.raw {
+            Format::Html
         } else {
-            content
+            p.format.unwrap_or_default()
         };
+        let mut content = match format {
+            Format::Markdown if is_html => html_to_markdown(&body, Some(&final_url)),
+            Format::Text if is_html => strip_html_tags(&body),
+            _ => body,
+        };
+        if truncated {
+            content.push_str(&format!("...\n[truncated at {max_bytes} bytes]"));
+        }
 
         Ok(ToolOutput {
             content,
@@ -332,7 +646,6 @@ mod tests {
 
     #[test]
     fn test_is_private_ipv4() {
-        use std::net::Ipv4Addr;
         assert!(is_private_ip(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))));
         assert!(is_private_ip(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))));
         assert!(is_private_ip(IpAddr::V4(Ipv4Addr::new(172, 16, 0, 1))));
@@ -344,34 +657,274 @@ mod tests {
 
     #[test]
     fn test_is_private_ipv6() {
-        use std::net::Ipv6Addr;
         assert!(is_private_ip(IpAddr::V6(Ipv6Addr::LOCALHOST)));
         assert!(is_private_ip(IpAddr::V6(Ipv6Addr::UNSPECIFIED)));
+        let private = |s: &str| is_private_ip(s.parse().unwrap());
+        assert!(private("fc00::1"));
+        assert!(private("fd12:3456::1"));
+        assert!(private("fe80::1"));
+        assert!(private("::ffff:127.0.0.1"));
+        assert!(private("::ffff:10.0.0.1"));
+        assert!(private("::127.0.0.1"));
+        assert!(private("0.1.2.3"));
+        assert!(private("100.64.0.1"));
+        assert!(private("100.127.255.255"));
+        assert!(!private("100.128.0.1"));
+        assert!(!private("::ffff:8.8.8.8"));
+        assert!(!private("2606:4700::1111"));
+        assert!(is_metadata_ip("::ffff:169.254.169.254".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_is_private_reserved_ranges() {
+        let private = |s: &str| is_private_ip(s.parse().unwrap());
+        assert!(private("224.0.0.1"));
+        assert!(private("239.255.255.250"));
+        assert!(private("ff02::1"));
+        assert!(private("198.18.0.1"));
+        assert!(private("198.19.255.255"));
+        assert!(!private("198.20.0.1"));
+        assert!(private("240.0.0.1"));
+        assert!(private("250.1.2.3"));
+        assert!(!private("223.255.255.255"));
+    }
+
+    #[test]
+    fn test_is_private_embedded_ipv4() {
+        let private = |s: &str| is_private_ip(s.parse().unwrap());
+        // NAT64
+        assert!(private("64:ff9b::127.0.0.1"));
+        assert!(private("64:ff9b::a00:1"));
+        assert!(!private("64:ff9b::8.8.8.8"));
+        // 6to4
+        assert!(private("2002:7f00:1::"));
+        assert!(private("2002:c0a8:101::1"));
+        assert!(!private("2002:808:808::1"));
+        assert!(is_metadata_ip("64:ff9b::169.254.169.254".parse().unwrap()));
+        assert!(is_metadata_ip("2002:a9fe:a9fe::".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_guarded_resolver() {
+        use reqwest::dns::Resolve;
+        let name = |host: &str| host.parse::<reqwest::dns::Name>().unwrap();
+        let guarded = GuardedResolver { allow_private: false };
+        assert!(guarded.resolve(name("localhost")).await.is_err());
+        let open = GuardedResolver { allow_private: true };
+        assert!(open.resolve(name("localhost")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_ssrf_block_unresolvable() {
+        // A failed lookup blocks the request instead of letting it through
+        let err = validate_url("http://nonexistent.invalid/", false).await.unwrap_err();
+        assert!(err.contains("Could not resolve"), "{err}");
     }
 
     #[tokio::test]
     async fn test_ssrf_block_localhost() {
-        assert!(validate_url("http://localhost/secret").await.is_err());
-        assert!(validate_url("http://127.0.0.1/secret").await.is_err());
-        assert!(validate_url("http://[::1]/secret").await.is_err());
+        assert!(validate_url("http://localhost/secret", false).await.is_err());
+        assert!(validate_url("http://127.0.0.1/secret", false).await.is_err());
+        assert!(validate_url("http://[::1]/secret", false).await.is_err());
     }
 
     #[tokio::test]
     async fn test_ssrf_block_scheme() {
-        assert!(validate_url("file:///etc/passwd").await.is_err());
-        assert!(validate_url("ftp://example.com").await.is_err());
-        assert!(validate_url("gopher://example.com").await.is_err());
+        assert!(validate_url("file:///etc/passwd", false).await.is_err());
+        assert!(validate_url("ftp://example.com", false).await.is_err());
+        assert!(validate_url("gopher://example.com", false).await.is_err());
     }
 
     #[tokio::test]
     async fn test_ssrf_block_metadata() {
-        assert!(validate_url("http://169.254.169.254/latest/meta-data/").await.is_err());
+        assert!(validate_url("http://169.254.169.254/latest/meta-data/", false).await.is_err());
     }
 
     #[tokio::test]
     async fn test_ssrf_allow_public() {
         // Public URLs should pass validation
-        assert!(validate_url("https://example.com").await.is_ok());
-        assert!(validate_url("http://example.com/page").await.is_ok());
+        assert!(validate_url("https://1.1.1.1", false).await.is_ok());
+        assert!(validate_url("http://93.184.215.14/page", false).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_private_networks_flag() {
+        assert!(validate_url("http://127.0.0.1:8080/", true).await.is_ok());
+        assert!(validate_url("http://localhost/", true).await.is_ok());
+        // Metadata endpoints stay blocked regardless
+        assert!(validate_url("http://169.254.169.254/", true).await.is_err());
+    }
+
+    #[test]
+    fn test_robots_longest_match() {
+        let robots = "User-agent: *\nDisallow: /private\nAllow: /private/public\n\nSitemap: /s.xml\n";
+        assert!(is_allowed_by_robots(robots, USER_AGENT, "/"));
+        assert!(!is_allowed_by_robots(robots, USER_AGENT, "/private/page"));
+        assert!(is_allowed_by_robots(robots, USER_AGENT, "/private/public/page"));
+        assert!(is_allowed_by_robots("", USER_AGENT, "/anything"));
+        assert!(is_allowed_by_robots("User-agent: *\nDisallow:\n", USER_AGENT, "/x"));
+    }
+
+    #[test]
+    fn test_robots_agent_groups_and_wildcards() {
+        let robots = "\
+User-agent: *
+Disallow: /
+
+User-agent: other
+User-agent: RustyClaw # us
+Disallow: /*.pdf$
+Disallow: /tmp*/cache
+";
+        // Our own group replaces the `*` group entirely
+        assert!(is_allowed_by_robots(robots, USER_AGENT, "/docs/page"));
+        assert!(!is_allowed_by_robots(robots, USER_AGENT, "/docs/file.pdf"));
+        assert!(is_allowed_by_robots(robots, USER_AGENT, "/docs/file.pdf?x=1"));
+        assert!(!is_allowed_by_robots(robots, USER_AGENT, "/tmp1/a/cache/b"));
+        assert!(!is_allowed_by_robots(robots, "SomeBot", "/docs/page"));
+    }
+
+    /// Serve canned responses by path on a local port, one per connection.
+    async fn serve(routes: Vec<(&'static str, &'static str, String)>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                let (status, body) = routes
+                    .iter()
+                    .find(|(p, _, _)| *p == path)
+                    .map(|(_, s, b)| (*s, b.clone()))
+                    .unwrap_or(("404 Not Found", String::new()));
+                let response = format!(
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        format!("http://{addr}")
+    }
+
+    fn context(web_fetch: WebFetchConfig) -> ToolContext {
+        let config = rusty_claw_core::config::Config {
+            tools: Some(rusty_claw_core::config::ToolsConfig {
+                web_fetch: Some(web_fetch),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        ToolContext {
+            session_key: "test".into(),
+            workspace: std::env::temp_dir(),
+            config: std::sync::Arc::new(config),
+            restrict_to_workspace: true,
+            sandbox_mode: Default::default(),
+            browser_pool: None,
+            progress: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_truncates_at_max_bytes() {
+        let base = serve(vec![("/big", "200 OK", "a".repeat(10_000))]).await;
+        let ctx = context(WebFetchConfig {
+            max_bytes: 1000,
+            allow_private_networks: true,
+            ..Default::default()
+        });
+
+        let output = WebFetchTool
+            .execute(serde_json::json!({"url": format!("{base}/big"), "raw": true}), &ctx)
+            .await
+            .unwrap();
+        assert!(!output.is_error, "{}", output.content);
+        assert!(output.content.starts_with(&"a".repeat(1000)));
+        assert!(output.content.ends_with("[truncated at 1000 bytes]"));
+
+        // A per-call max_size cannot raise the configured cap
+        let output = WebFetchTool
+            .execute(
+                serde_json::json!({"url": format!("{base}/big"), "raw": true, "max_size": 50_000}),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        assert!(output.content.ends_with("[truncated at 1000 bytes]"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_blocks_private_network_by_default() {
+        let base = serve(vec![("/", "200 OK", "secret".into())]).await;
+        let output = WebFetchTool
+            .execute(serde_json::json!({"url": base}), &context(WebFetchConfig::default()))
+            .await
+            .unwrap();
+        assert!(output.is_error);
+        assert!(output.content.starts_with("Request blocked"), "{}", output.content);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_honors_robots() {
+        let base = serve(vec![
+            ("/robots.txt", "200 OK", "User-agent: *\nDisallow: /private\n".into()),
+            ("/private", "200 OK", "hidden".into()),
+            ("/public", "200 OK", "visible".into()),
+        ])
+        .await;
+        let ctx = context(WebFetchConfig {
+            respect_robots: true,
+            allow_private_networks: true,
+            ..Default::default()
+        });
+
+        let output = WebFetchTool
+            .execute(serde_json::json!({"url": format!("{base}/private")}), &ctx)
+            .await
+            .unwrap();
+        assert!(output.is_error);
+        assert!(output.content.starts_with("Blocked by robots.txt"));
+
+        let output = WebFetchTool
+            .execute(serde_json::json!({"url": format!("{base}/public")}), &ctx)
+            .await
+            .unwrap();
+        assert!(!output.is_error, "{}", output.content);
+        assert_eq!(output.content, "visible");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_formats() {
+        let page = "<html><body><nav>Menu</nav><main><h1>Doc</h1>\
+                    <p>See <a href=\"/next\">next</a>.</p></main></body></html>";
+        let base = serve(vec![("/page", "200 OK", page.into())]).await;
+        let ctx = context(WebFetchConfig {
+            allow_private_networks: true,
+            ..Default::default()
+        });
+        let url = format!("{base}/page");
+
+        let markdown = WebFetchTool
+            .execute(serde_json::json!({"url": url}), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(markdown.content, format!("# Doc\n\nSee [next]({base}/next)."));
+
+        let text = WebFetchTool
+            .execute(serde_json::json!({"url": url, "format": "text"}), &ctx)
+            .await
+            .unwrap();
+        assert!(text.content.contains("Menu") && !text.content.contains('<'));
+
+        let html = WebFetchTool
+            .execute(serde_json::json!({"url": url, "format": "html"}), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(html.content, page);
     }
 }
@@ -1,6 +1,7 @@
 //! web_search tool — external search API wrapper.
 //!
-//! Supports configurable backend (SearXNG, Brave, Google Custom Search).
+//! Dispatches to a configurable backend (Brave, SearXNG or Tavily) and
+//! normalizes results into `{title, url, snippet}`.
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -62,6 +63,111 @@ fn parse_brave_results(body: &serde_json::Value, max: usize) -> Vec<SearchResult
         .collect()
 }
 
+/// Parse Tavily search results.
+fn parse_tavily_results(body: &serde_json::Value, max: usize) -> Vec<SearchResult> {
+    let empty = vec![];
+    let results = body["results"].as_array().unwrap_or(&empty);
+    results
+        .iter()
+        .take(max)
+        .filter_map(|r| {
+            Some(SearchResult {
+                title: r["title"].as_str()?.to_string(),
+                url: r["url"].as_str()?.to_string(),
+                snippet: r["content"].as_str().unwrap_or("").to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Supported search backends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SearchProvider {
+    Brave,
+    Searxng,
+    Tavily,
+}
+
+impl SearchProvider {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "brave" => Some(Self::Brave),
+            "searxng" | "searx" => Some(Self::Searxng),
+            "tavily" => Some(Self::Tavily),
+            _ => None,
+        }
+    }
+
+    /// Guess the backend from its URL when `search_provider` is unset.
+    fn detect(url: &str) -> Self {
+        if url.contains("brave.com") {
+            Self::Brave
+        } else if url.contains("tavily.com") {
+            Self::Tavily
+        } else {
+            Self::Searxng
+        }
+    }
+
+    /// Public endpoint for hosted backends; SearXNG is always self-hosted.
+    fn default_url(self) -> Option<&'static str> {
+        match self {
+            Self::Brave => Some("https://api.search.brave.com"),
+            Self::Tavily => Some("https://api.tavily.com"),
+            Self::Searxng => None,
+        }
+    }
+
+    fn requires_key(self) -> bool {
+        matches!(self, Self::Brave | Self::Tavily)
+    }
+
+    fn build_request(
+        self,
+        client: &reqwest::Client,
+        base_url: &str,
+        api_key: &str,
+        query: &str,
+        num_results: usize,
+    ) -> reqwest::RequestBuilder {
+        let base_url = base_url.trim_end_matches('/');
+        match self {
+            Self::Brave => client
+                .get(format!("{base_url}/res/v1/web/search"))
+                .header("X-Subscription-Token", api_key)
+                .query(&[("q", query), ("count", &num_results.to_string())]),
+            Self::Searxng => client.get(format!("{base_url}/search")).query(&[
+                ("q", query),
+                ("format", "json"),
+                ("engines", "google,duckduckgo"),
+            ]),
+            Self::Tavily => client
+                .post(format!("{base_url}/search"))
+                .bearer_auth(api_key)
+                .json(&serde_json::json!({
+                    "query": query,
+                    "max_results": num_results,
+                })),
+        }
+    }
+
+    fn parse_results(self, body: &serde_json::Value, max: usize) -> Vec<SearchResult> {
+        match self {
+            Self::Brave => parse_brave_results(body, max),
+            Self::Searxng => parse_searxng_results(body, max),
+            Self::Tavily => parse_tavily_results(body, max),
+        }
+    }
+}
+
+fn tool_error(content: String) -> ToolOutput {
+    ToolOutput {
+        content,
+        is_error: true,
+        media: None,
+    }
+}
+
 #[async_trait]
 impl Tool for WebSearchTool {
     fn name(&self) -> &str {
@@ -69,7 +175,7 @@ impl Tool for WebSearchTool {
     }
 
     fn description(&self) -> &str {
-        "Search the web using a configured search API (SearXNG, Brave, etc.). Returns a list of results with title, URL, and snippet."
+        "Search the web using a configured search API (SearXNG, Brave or Tavily). Returns a list of results with title, URL, and snippet."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
@@ -97,86 +203,94 @@ impl Tool for WebSearchTool {
         let p: Params = serde_json::from_value(params)?;
         debug!(query = %p.query, "web_search");
 
-        // Get search API URL from config or environment
-        let search_url = context
-            .config
-            .tools
-            .as_ref()
+        let tools_config = context.config.tools.as_ref();
+
+        // Get search API URL, key and backend from config or environment
+        let search_url = tools_config
             .and_then(|t| t.search_api_url.clone())
             .or_else(|| std::env::var("SEARCH_API_URL").ok())
             .filter(|s| !s.is_empty());
 
-        let search_api_key = context
-            .config
-            .tools
-            .as_ref()
+        let search_api_key = tools_config
             .and_then(|t| t.search_api_key.clone())
             .or_else(|| std::env::var("SEARCH_API_KEY").ok())
             .filter(|s| !s.is_empty());
 
-        let Some(base_url) = search_url else {
-            return Ok(ToolOutput {
-                content: "No search API configured. Set tools.search_api_url in config or SEARCH_API_URL environment variable. Supported: SearXNG (e.g. http://localhost:8888), Brave Search API (https://api.search.brave.com).".to_string(),
-                is_error: true,
-                media: None,
-            });
+        let provider_name = tools_config
+            .and_then(|t| t.search_provider.clone())
+            .or_else(|| std::env::var("SEARCH_PROVIDER").ok())
+            .filter(|s| !s.is_empty());
+
+        let provider = match provider_name.as_deref() {
+            Some(name) => match SearchProvider::parse(name) {
+                Some(provider) => provider,
+                None => {
+                    return Ok(tool_error(format!(
+                        "Unknown search provider '{name}'. Set tools.search_provider to one of: brave, searxng, tavily."
+                    )));
+                }
+            },
+            None => match search_url.as_deref() {
+                Some(url) => SearchProvider::detect(url),
+                None => {
+                    return Ok(tool_error("No search API configured. Set tools.search_api_url in config or SEARCH_API_URL environment variable. Supported: SearXNG (e.g. http://localhost:8888), Brave Search API (https://api.search.brave.com), Tavily (https://api.tavily.com).".to_string()));
+                }
+            },
         };
 
+        let Some(base_url) = search_url.or_else(|| provider.default_url().map(String::from))
+        else {
+            return Ok(tool_error(
+                "SearXNG needs tools.search_api_url pointing at your instance (e.g. http://localhost:8888).".to_string(),
+            ));
+        };
+
+        if provider.requires_key() && search_api_key.is_none() {
+            return Ok(tool_error(format!(
+                "The {provider:?} search provider needs an API key. Set tools.search_api_key or SEARCH_API_KEY."
+            )));
+        }
+
         let client = reqwest::Client::builder()
             .timeout(std::time::Duration::from_secs(15))
             .build()?;
 
-        // Detect API type from URL
-        let is_brave = base_url.contains("brave.com");
-
-        let resp = if is_brave {
-            let key = search_api_key.unwrap_or_default();
-            client
-                .get(format!("{base_url}/res/v1/web/search"))
-                .header("X-Subscription-Token", key)
-                .query(&[("q", &p.query), ("count", &p.num_results.to_string())])
-                .send()
-                .await
-        } else {
-            // SearXNG-compatible
-            client
-                .get(format!("{base_url}/search"))
-                .query(&[
-                    ("q", p.query.as_str()),
-                    ("format", "json"),
-                    ("engines", "google,duckduckgo"),
-                ])
-                .send()
-                .await
-        };
+        let resp = provider
+            .build_request(
+                &client,
+                &base_url,
+                search_api_key.as_deref().unwrap_or_default(),
+                &p.query,
+                p.num_results,
+            )
+            .send()
+            .await;
 
         let resp = match resp {
             Ok(r) => r,
             Err(e) => {
-                return Ok(ToolOutput {
-                    content: format!("Search API error: {e}"),
-                    is_error: true,
-                    media: None,
-                });
+                return Ok(tool_error(format!("Search API error: {e}")));
             }
         };
 
         if !resp.status().is_success() {
-            return Ok(ToolOutput {
-                content: format!("Search API returned HTTP {}", resp.status()),
-                is_error: true,
-                media: None,
-            });
+            return Ok(tool_error(format!(
+                "Search API returned HTTP {}",
+                resp.status()
+            )));
         }
 
-        let body: serde_json::Value = resp.json().await?;
-
-        let results = if is_brave {
-            parse_brave_results(&body, p.num_results)
-        } else {
-            parse_searxng_results(&body, p.num_results)
+        let body: serde_json::Value = match resp.json().await {
+            Ok(body) => body,
+            Err(e) => {
+                return Ok(tool_error(format!(
+                    "Search API returned a non-JSON response ({provider:?} backend): {e}"
+                )));
+            }
         };
 
+        let results = provider.parse_results(&body, p.num_results);
+
         if results.is_empty() {
             return Ok(ToolOutput {
                 content: "No search results found.".to_string(),
@@ -235,6 +349,58 @@ mod tests {
         assert_eq!(results[0].snippet, "A test result");
     }
 
+    #[test]
+    fn test_parse_tavily_results() {
+        let body = serde_json::json!({
+            "query": "rust",
+            "results": [
+                {"title": "Rust", "url": "https://rust-lang.org", "content": "Fast and safe", "score": 0.9}
+            ]
+        });
+        let results = SearchProvider::Tavily.parse_results(&body, 5);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].snippet, "Fast and safe");
+    }
+
+    #[test]
+    fn test_search_provider_selection() {
+        assert_eq!(SearchProvider::parse("Brave"), Some(SearchProvider::Brave));
+        assert_eq!(SearchProvider::parse("searxng"), Some(SearchProvider::Searxng));
+        assert_eq!(SearchProvider::parse("bing"), None);
+        assert_eq!(
+            SearchProvider::detect("https://api.search.brave.com"),
+            SearchProvider::Brave
+        );
+        assert_eq!(SearchProvider::detect("http://localhost:8888"), SearchProvider::Searxng);
+        assert!(SearchProvider::Searxng.default_url().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_provider_is_tool_error() {
+        let config = rusty_claw_core::config::Config {
+            tools: Some(rusty_claw_core::config::ToolsConfig {
+                search_provider: Some("bing".into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let context = ToolContext {
+            session_key: "test".into(),
+            workspace: std::env::temp_dir(),
+            config: std::sync::Arc::new(config),
+            restrict_to_workspace: true,
+            sandbox_mode: Default::default(),
+            browser_pool: None,
+            progress: None,
+        };
+        let output = WebSearchTool
+            .execute(serde_json::json!({"query": "rust"}), &context)
+            .await
+            .unwrap();
+        assert!(output.is_error);
+        assert!(output.content.contains("Unknown search provider 'bing'"));
+    }
+
     #[test]
     fn test_parse_empty_results() {
         let body = serde_json::json!({"results": []});
@@ -121,6 +121,7 @@ mod tests {
             restrict_to_workspace: true,
             sandbox_mode: rusty_claw_core::config::SandboxMode::default(),
             browser_pool: None,
+            progress: None,
         };
 
         let result = WriteFileTool
@@ -148,6 +149,7 @@ mod tests {
             restrict_to_workspace: true,
             sandbox_mode: rusty_claw_core::config::SandboxMode::default(),
             browser_pool: None,
+            progress: None,
         };
 
         let result = WriteFileTool
@@ -5,6 +5,7 @@ import { escapeHtml } from '../utils.js';
 
 let isRunning = false;
 let streamingText = '';
+let lastTtftMs = null;
 let unsubs = [];
 
 export function mount(app) {
@@ -149,10 +150,19 @@ function handleAgentEvent(payload) {
       break;
     }
 
+    case 'timing': {
+      lastTtftMs = payload.time_to_first_token_ms;
+      break;
+    }
+
     case 'usage': {
       const el = document.createElement('div');
       el.style.cssText = 'font-size:11px;color:var(--text-muted);text-align:right;margin-bottom:12px';
       el.textContent = `Tokens: ${payload.input_tokens || 0} in / ${payload.output_tokens || 0} out`;
+      if (lastTtftMs !== null) {
+        el.textContent += ` · TTFT ${lastTtftMs} ms`;
+        lastTtftMs = null;
+      }
       document.getElementById('chat-messages')?.appendChild(el);
       scrollToBottom();
       break;